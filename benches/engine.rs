@@ -3,8 +3,11 @@
 //! Run: `cargo bench` or `cargo bench --bench engine`.
 
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
-use dire_matching_engine::market_data_gen::{Generator, GeneratorConfig};
-use dire_matching_engine::{Engine, InstrumentId, OrderId};
+use dire_matching_engine::market_data_gen::{replay_into_engine, Generator, GeneratorConfig, PriceProcess};
+use dire_matching_engine::{
+    match_order, match_order_into, Engine, InstrumentId, MatchOutput, Order, OrderBook, OrderId, OrderType, Side, TimeInForce,
+    TraderId,
+};
 use rust_decimal::Decimal;
 
 fn bench_submit_order_throughput(c: &mut Criterion) {
@@ -37,6 +40,88 @@ fn bench_submit_order_throughput(c: &mut Criterion) {
     group.finish();
 }
 
+/// Same order flow as `submit_order_1000`, with one [`MatchOutput`] reused across orders instead of new result
+/// vectors per order (compare the two to see the allocation savings).
+fn bench_submit_order_into_reused_buffer(c: &mut Criterion) {
+    const N: usize = 1000;
+    let mut group = c.benchmark_group("engine");
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function("submit_order_into_1000_reused_buffer", |b| {
+        b.iter_batched(
+            || {
+                let config = GeneratorConfig {
+                    seed: 42,
+                    instrument_id: InstrumentId(1),
+                    num_orders: N,
+                    tif_gtc_ratio: 1.0,
+                    tif_ioc_ratio: 0.0,
+                    ..Default::default()
+                };
+                let engine = Engine::new(InstrumentId(1));
+                let orders = Generator::new(config).all_orders();
+                (engine, orders, MatchOutput::new())
+            },
+            |(mut engine, orders, mut out)| {
+                for order in orders {
+                    engine.submit_order_into(order, &mut out).unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+/// Matching alone: 1000 IOC buys, each taking one lot from each of 5 resting levels, with fresh result vectors
+/// per order (`match_order`) versus one reused [`MatchOutput`] (`match_order_into`).
+fn bench_match_order_into_vs_match_order(c: &mut Criterion) {
+    const N: u64 = 1000;
+    const LEVELS: u64 = 5;
+    let setup = || {
+        let mut book = OrderBook::new(InstrumentId(1));
+        for id in 1..=N * LEVELS {
+            let price = 100 + ((id - 1) % LEVELS) as i64;
+            book.add_order(&limit_order(id, Side::Sell, price, 1)).unwrap();
+        }
+        let buys: Vec<Order> = (0..N)
+            .map(|i| {
+                let mut buy = limit_order(N * LEVELS + 1 + i, Side::Buy, 100 + LEVELS as i64, 2);
+                buy.quantity = Decimal::from(LEVELS);
+                buy.time_in_force = TimeInForce::IOC;
+                buy
+            })
+            .collect();
+        (book, buys)
+    };
+    let mut group = c.benchmark_group("matching");
+    group.throughput(Throughput::Elements(N));
+    group.bench_function("match_order_1000x5_fills", |b| {
+        b.iter_batched(
+            setup,
+            |(mut book, buys)| {
+                for (i, buy) in buys.iter().enumerate() {
+                    let (trades, _) = match_order(&mut book, buy, i as u64 * LEVELS + 1, i as u64 * (LEVELS + 1) + 1);
+                    assert_eq!(trades.len(), LEVELS as usize);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("match_order_into_1000x5_fills", |b| {
+        b.iter_batched(
+            || (setup(), MatchOutput::new()),
+            |((mut book, buys), mut out)| {
+                for (i, buy) in buys.iter().enumerate() {
+                    match_order_into(&mut book, buy, i as u64 * LEVELS + 1, i as u64 * (LEVELS + 1) + 1, &mut out);
+                    assert_eq!(out.trades.len(), LEVELS as usize);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
 fn bench_cancel_order(c: &mut Criterion) {
     const RESTING: usize = 500;
     const CANCELS_PER_ITER: usize = 100;
@@ -75,6 +160,82 @@ fn bench_cancel_order(c: &mut Criterion) {
     group.finish();
 }
 
+/// GTC limit order for building deep single-price levels.
+fn limit_order(id: u64, side: Side, price: i64, trader: u64) -> Order {
+    Order {
+        order_id: OrderId(id),
+        client_order_id: format!("deep-{}", id),
+        instrument_id: InstrumentId(1),
+        side,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(1),
+        price: Some(Decimal::from(price)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: id,
+        trader_id: TraderId(trader),
+    }
+}
+
+fn bench_cancel_order_deep_level(c: &mut Criterion) {
+    const DEPTH: usize = 10_000;
+    const CANCELS_PER_ITER: usize = 1000;
+    let mut group = c.benchmark_group("engine");
+    group.throughput(Throughput::Elements(CANCELS_PER_ITER as u64));
+    group.bench_function("cancel_order_1000_from_10000_deep_level", |b| {
+        b.iter_batched(
+            || {
+                let mut engine = Engine::new(InstrumentId(1));
+                for id in 1..=DEPTH as u64 {
+                    engine.submit_order(limit_order(id, Side::Sell, 100, 1)).unwrap();
+                }
+                // Cancel from the back of the queue, where a linear scan is most expensive.
+                let cancel_ids: Vec<OrderId> = (0..CANCELS_PER_ITER)
+                    .map(|i| OrderId((DEPTH - i) as u64))
+                    .collect();
+                (engine, cancel_ids)
+            },
+            |(mut engine, cancel_ids)| {
+                for id in cancel_ids {
+                    engine.cancel_order(id);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_market_order_deep_sweep(c: &mut Criterion) {
+    const LEVELS: usize = 10;
+    const DEPTH_PER_LEVEL: usize = 1000;
+    const RESTING: usize = LEVELS * DEPTH_PER_LEVEL;
+    let mut group = c.benchmark_group("engine");
+    group.throughput(Throughput::Elements(RESTING as u64));
+    group.bench_function("market_order_sweep_10_levels_x_1000_deep", |b| {
+        b.iter_batched(
+            || {
+                let mut engine = Engine::new(InstrumentId(1));
+                for id in 1..=RESTING as u64 {
+                    let price = 100 + ((id - 1) as usize / DEPTH_PER_LEVEL) as i64;
+                    engine.submit_order(limit_order(id, Side::Sell, price, 1)).unwrap();
+                }
+                let mut sweep = limit_order(RESTING as u64 + 1, Side::Buy, 0, 2);
+                sweep.order_type = OrderType::Market;
+                sweep.price = None;
+                sweep.time_in_force = TimeInForce::IOC;
+                sweep.quantity = Decimal::from(RESTING as u64);
+                (engine, sweep)
+            },
+            |(mut engine, sweep)| {
+                let (trades, _) = engine.submit_order(sweep).unwrap();
+                assert_eq!(trades.len(), RESTING);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
 fn bench_modify_order(c: &mut Criterion) {
     const RESTING: usize = 200;
     const MODIFIES: usize = 50;
@@ -120,10 +281,42 @@ fn bench_modify_order(c: &mut Criterion) {
     group.finish();
 }
 
+/// Order entry as clients send it: 1000 events of new orders, cancels (20%), and modifies (15%) of live orders
+/// around a mean-reverting mid, replayed with `replay_into_engine`.
+fn bench_mixed_events(c: &mut Criterion) {
+    const N: usize = 1000;
+    let mut group = c.benchmark_group("engine");
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function("mixed_events_1000", |b| {
+        b.iter_batched(
+            || {
+                let config = GeneratorConfig {
+                    seed: 42,
+                    instrument_id: InstrumentId(1),
+                    num_orders: N,
+                    price_process: PriceProcess::MeanReverting { start: 100.0, reversion: 0.05, volatility: 0.3, drift: 0.0 },
+                    cancel_ratio: 0.2,
+                    modify_ratio: 0.15,
+                    ..Default::default()
+                };
+                (Engine::new(InstrumentId(1)), Generator::new(config).all_events())
+            },
+            |(mut engine, events)| replay_into_engine(&mut engine, events).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_submit_order_throughput,
+    bench_submit_order_into_reused_buffer,
+    bench_match_order_into_vs_match_order,
     bench_cancel_order,
-    bench_modify_order
+    bench_cancel_order_deep_level,
+    bench_market_order_deep_sweep,
+    bench_modify_order,
+    bench_mixed_events
 );
 criterion_main!(benches);
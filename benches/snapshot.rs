@@ -0,0 +1,69 @@
+//! State file benchmarks: save and load a snapshot of a 1M-order book in each [`SnapshotFormat`].
+//!
+//! Run: `cargo bench --bench snapshot`. File sizes are printed before the first benchmark.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use dire_matching_engine::persistence::{FilePersistence, PersistedState, SnapshotFormat, STATE_VERSION};
+use dire_matching_engine::{
+    InstrumentId, MatchingEngine, MultiEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId,
+};
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+const ORDERS: u64 = 1_000_000;
+
+/// 1M resting orders on one instrument: bids at 1–500 and asks at 501–1000 (2,000 orders per level), from 100
+/// traders.
+fn deep_book_state() -> PersistedState {
+    let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), Some("DEEP".into()))]);
+    for id in 1..=ORDERS {
+        let level = (id % 1000) as i64 + 1;
+        engine
+            .submit_order(Order {
+                order_id: OrderId(id),
+                client_order_id: format!("c{}", id),
+                instrument_id: InstrumentId(1),
+                side: if level <= 500 { Side::Buy } else { Side::Sell },
+                order_type: OrderType::Limit,
+                quantity: Decimal::from(10 + id % 90),
+                price: Some(Decimal::new(level * 25, 2)),
+                time_in_force: TimeInForce::GTC,
+                timestamp: id,
+                trader_id: TraderId(id % 100),
+            })
+            .unwrap();
+    }
+    PersistedState {
+        version: STATE_VERSION,
+        engine: engine.snapshot(),
+        market_state: "Open".into(),
+        venue_config: None,
+    }
+}
+
+fn bench_snapshot_save_load(c: &mut Criterion) {
+    let state = deep_book_state();
+    let dir = std::env::temp_dir().join(format!("dire_snapshot_bench_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut group = c.benchmark_group("snapshot");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(30));
+    group.throughput(Throughput::Elements(ORDERS));
+    for (name, format) in [("json", SnapshotFormat::Json), ("binary", SnapshotFormat::Binary)] {
+        let persistence = FilePersistence::new(dir.join(format!("state.{}", name))).with_format(format);
+        persistence.save(&state).unwrap();
+        let size = std::fs::metadata(persistence.path()).unwrap().len();
+        eprintln!("snapshot/{}: {:.1} MB for {} resting orders", name, size as f64 / 1e6, ORDERS);
+        group.bench_function(format!("save_1m_orders_{}", name), |b| {
+            b.iter(|| persistence.save(&state).unwrap())
+        });
+        group.bench_function(format!("load_1m_orders_{}", name), |b| {
+            b.iter(|| persistence.load().unwrap().unwrap())
+        });
+    }
+    group.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, bench_snapshot_save_load);
+criterion_main!(benches);
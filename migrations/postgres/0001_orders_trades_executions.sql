@@ -0,0 +1,52 @@
+-- Settlement store (see src/settlement.rs): orders as accepted, trades, and execution reports.
+-- Ids and engine sequence numbers are the engine's u64 values; prices and quantities are exact decimals.
+
+-- The latest order accepted under each order id (a replacement is its own order), for the trader, instrument,
+-- and side behind trades and reports.
+CREATE TABLE orders (
+    order_id        BIGINT PRIMARY KEY,
+    client_order_id TEXT NOT NULL,
+    instrument_id   BIGINT NOT NULL,
+    trader_id       BIGINT NOT NULL,
+    side            TEXT NOT NULL,
+    order_type      TEXT NOT NULL,
+    time_in_force   TEXT NOT NULL,
+    quantity        NUMERIC NOT NULL,
+    price           NUMERIC,
+    timestamp       BIGINT NOT NULL
+);
+
+CREATE INDEX orders_trader ON orders (trader_id);
+
+CREATE TABLE trades (
+    trade_id       BIGINT PRIMARY KEY,
+    seq            BIGINT NOT NULL,
+    instrument_id  BIGINT NOT NULL,
+    buy_order_id   BIGINT NOT NULL,
+    sell_order_id  BIGINT NOT NULL,
+    price          NUMERIC NOT NULL,
+    quantity       NUMERIC NOT NULL,
+    aggressor_side TEXT NOT NULL,
+    timestamp      BIGINT NOT NULL
+);
+
+CREATE INDEX trades_instrument_seq ON trades (instrument_id, seq);
+CREATE INDEX trades_seq ON trades (seq);
+
+CREATE TABLE executions (
+    exec_id            BIGINT PRIMARY KEY,
+    seq                BIGINT NOT NULL,
+    order_id           BIGINT NOT NULL,
+    orig_order_id      BIGINT,
+    exec_type          TEXT NOT NULL,
+    order_status       TEXT NOT NULL,
+    filled_quantity    NUMERIC NOT NULL,
+    remaining_quantity NUMERIC NOT NULL,
+    avg_price          NUMERIC,
+    last_qty           NUMERIC,
+    last_px            NUMERIC,
+    timestamp          BIGINT NOT NULL
+);
+
+CREATE INDEX executions_order ON executions (order_id);
+CREATE INDEX executions_seq ON executions (seq);
@@ -0,0 +1,15 @@
+-- Engine state for recovery (see src/postgres.rs): the write-ahead command log, and the state snapshots and
+-- command-log segments a node copies to remote storage (see src/remote.rs).
+
+-- One row per engine command, numbered from 1 by the engine; `entry` is the journal entry as JSON.
+CREATE TABLE command_log (
+    seq   BIGINT PRIMARY KEY,
+    entry TEXT NOT NULL
+);
+
+-- Objects by key, as in an object store bucket.
+CREATE TABLE state_objects (
+    key        TEXT PRIMARY KEY,
+    data       BYTEA NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -7,56 +7,53 @@
 use axum::{
     body::Body,
     extract::{
-        Path,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo,
         Extension,
         Request,
     },
-    http::StatusCode,
+    http::{HeaderValue, StatusCode},
     middleware::{self, Next},
     response::{IntoResponse, Response},
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
-use std::collections::HashMap;
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
-use tokio::sync::broadcast;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
 
-use crate::audit::{AuditEvent, AuditSink, StdoutAuditSink};
-use crate::auth::{self, AuthConfig, AuthUser};
-use crate::persistence::{FilePersistence, PersistedState};
-use crate::{InstrumentId, MatchingEngine, MultiEngine, Order, OrderId};
+use crate::api_error::{ApiError, ApiJson, ApiPath, ApiQuery, ErrorCode};
+use crate::audit::{
+    AuditAction, AuditBufferPolicy, AuditContext, AuditEvent, AuditQuery, AuditSource, AuditSink, BufferedAuditSink, FileAuditStore, MultiAuditSink, StdoutAuditSink,
+};
+use crate::events::{BookObserver, EngineEvent, EngineEventSink};
+use crate::order_book::{BookDelta, BookLevels, BookStats, DEFAULT_TICK_SIZE};
+use crate::auth::{self, AuthConfig, AuthUser, Cidr, Credential, KeyGrant, KeyId, Permission, Permissions, Role};
+use crate::persistence::{
+    CommandLog, FileCommandLog, FilePersistence, PersistedState, PersistenceNotifier, RecoveryReport, RejectedSnapshot,
+    SnapshotFormat, SnapshotPolicy,
+    STATE_VERSION,
+};
+use crate::history::{HistoryCursor, HistoryQuery};
+use crate::remote::{ObjectStore, RemoteUploader, Upload};
+use crate::replication::Standby;
+use crate::stats::InstrumentStats;
+use crate::{
+    ExecutionReport, InstrumentId, InstrumentState, InstrumentUpdate, MatchingEngine, MultiEngine, Order, OrderId,
+    OrderStatus, OrderStatusView, PriceBand, Trade, TraderId,
+};
+use crate::venue::{OrderRateLimiter, VenueConfig};
 use std::sync::Arc;
 
 // ---------------------------------------------------------------------------
 // Phase 3 §4: Admin API — market state, instruments, config
 // ---------------------------------------------------------------------------
 
-/// Market state (US-011, US-012). When not Open, order submission is rejected (503 / FIX reject).
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum MarketState {
-    Open,
-    Halted,
-    Closed,
-}
-
-impl MarketState {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            MarketState::Open => "Open",
-            MarketState::Halted => "Halted",
-            MarketState::Closed => "Closed",
-        }
-    }
-    pub fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "Open" => Some(MarketState::Open),
-            "Halted" => Some(MarketState::Halted),
-            "Closed" => Some(MarketState::Closed),
-            _ => None,
-        }
-    }
-}
+/// Venue-wide market state; each instrument also has its own (see [`MultiEngine::set_instrument_market_state`]).
+pub use crate::types::MarketState;
 
 /// Payload broadcast to all WebSocket market-data clients when the book changes.
 #[derive(Clone, Debug)]
@@ -64,20 +61,480 @@ pub struct BookUpdate {
     pub instrument_id: u64,
     pub best_bid: Option<rust_decimal::Decimal>,
     pub best_ask: Option<rust_decimal::Decimal>,
+    /// Book checksum after the change (see [`crate::order_book::OrderBook::checksum`]).
+    pub checksum: u32,
+    /// L2 levels added, changed, or removed by the change.
+    pub delta: BookDelta,
+    /// Best N levels per side after the change, when snapshot levels are configured (see [`crate::BookSnapshot::levels`]).
+    pub levels: Option<BookLevels>,
+    /// Engine sequence number of the change (see [`crate::ExecutionReport::seq`]).
+    pub seq: u64,
+    /// The instrument's session statistics after the change (see [`MatchingEngine::stats_for`]).
+    pub ticker: Option<InstrumentStats>,
+}
+
+/// Turns every book change, whichever adapter or admin action made it, into a [`BookUpdate`] on the app's
+/// broadcast channel. Runs as a [`BookObserver`] under the engine lock, so updates go out in `seq` order.
+struct BookPublisher {
+    tx: broadcast::Sender<BookUpdate>,
+    /// Levels of each book as of its last update, the base of the next delta.
+    published: Mutex<HashMap<InstrumentId, BookLevels>>,
+}
+
+impl BookPublisher {
+    /// Publisher whose first deltas start from the books as they are in `engine` now.
+    fn new(tx: broadcast::Sender<BookUpdate>, engine: &MultiEngine) -> Self {
+        let published = engine
+            .instruments()
+            .into_iter()
+            .filter_map(|id| Some((id, engine.book_levels_for(id)?)))
+            .collect();
+        Self {
+            tx,
+            published: Mutex::new(published),
+        }
+    }
+}
+
+impl BookObserver for BookPublisher {
+    fn on_book_changed(&self, engine: &MultiEngine, instrument_id: InstrumentId) {
+        let (Some(snapshot), Some(after)) = (engine.book_snapshot_for(instrument_id), engine.book_levels_for(instrument_id))
+        else {
+            return;
+        };
+        let delta = {
+            let mut published = self.published.lock().expect("lock");
+            let delta = BookDelta::between(published.get(&instrument_id).unwrap_or(&BookLevels::default()), &after);
+            published.insert(instrument_id, after);
+            delta
+        };
+        let _ = self.tx.send(BookUpdate {
+            instrument_id: instrument_id.0,
+            best_bid: snapshot.best_bid,
+            best_ask: snapshot.best_ask,
+            checksum: snapshot.checksum,
+            delta,
+            levels: snapshot.levels,
+            seq: snapshot.seq,
+            ticker: engine.stats_for(instrument_id),
+        });
+    }
 }
 
-/// Shared app state: multi-instrument engine; broadcast; audit sink; market state and admin config (Phase 3 §4).
+/// Shared app state: multi-instrument engine; broadcast; audit sink; market state and venue config (Phase 3 §4).
 #[derive(Clone)]
 pub struct AppState {
     pub engine: std::sync::Arc<Mutex<MultiEngine>>,
+    /// Book updates from every book change (see [`BookPublisher`]).
     pub(crate) broadcast_tx: broadcast::Sender<BookUpdate>,
     pub(crate) audit_sink: Arc<dyn AuditSink + Send + Sync>,
     /// Market state: when not Open, REST and FIX reject new orders (503 / FIX reject).
     pub market_state: Arc<Mutex<MarketState>>,
-    /// Admin config key-value store (US-009). Keys are strings; values are JSON.
-    pub admin_config: Arc<Mutex<HashMap<String, serde_json::Value>>>,
-    /// When set, state is saved to file after each change and loaded on startup.
+    /// Venue config, changed through `PATCH /admin/config`; set it with [`AppState::set_venue_config`].
+    pub venue_config: Arc<Mutex<VenueConfig>>,
+    /// Order-entry request counts for [`crate::venue::RateLimits`].
+    pub(crate) order_rate: Arc<OrderRateLimiter>,
+    /// When set, state is loaded from file on startup and saved by the snapshotter ([`start_snapshotter`]) after
+    /// changes, and at shutdown.
     pub(crate) persistence: Option<Arc<FilePersistence>>,
+    /// Marked by every engine event (with persistence) and by the order-entry handlers; admin handlers save at
+    /// once instead.
+    pub(crate) notifier: Arc<PersistenceNotifier>,
+    /// Engine commands written ahead next to the state file (see [`FilePersistence::command_log`]); compacted
+    /// after each save.
+    pub(crate) command_log: Option<Arc<FileCommandLog>>,
+    /// What startup recovery did, with persistence (`GET /admin/recovery`).
+    pub(crate) recovery: Option<Arc<RecoveryReport>>,
+    /// Every [`EngineEvent`] from the engine, for adapters to consume (see [`AppState::subscribe_events`]).
+    pub(crate) events_tx: broadcast::Sender<EngineEvent>,
+    /// When to disconnect market-data clients that cannot keep up.
+    pub slow_consumer: SlowConsumerPolicy,
+    /// Pings, heartbeats, and idle timeout of every WebSocket.
+    pub heartbeat: HeartbeatPolicy,
+    /// Thread running the FIX acceptor, when there is one; `/health/ready` fails once it exits.
+    pub fix_acceptor: Option<Arc<std::thread::JoinHandle<()>>>,
+    /// Set while this node follows a primary (see [`crate::replication::start_standby`]): REST writes are
+    /// refused and `/health/ready` fails until it is promoted with `POST /admin/failover`.
+    pub standby: Option<Arc<Standby>>,
+    pub(crate) started_at: std::time::Instant,
+    /// Graceful shutdown signal and open WebSockets (see [`AppState::begin_shutdown`]).
+    pub shutdown: Shutdown,
+}
+
+impl AppState {
+    /// Receive the engine's event stream from now on. Slow receivers lag and skip events (see
+    /// [`broadcast::Receiver::recv`]).
+    pub fn subscribe_events(&self) -> broadcast::Receiver<EngineEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Where the REST API audits to; give it to other adapters (e.g. the FIX acceptor) so one trail covers
+    /// every entry point.
+    pub fn audit_sink(&self) -> Arc<dyn AuditSink + Send + Sync> {
+        self.audit_sink.clone()
+    }
+
+    /// What startup recovery did, with persistence (as `GET /admin/recovery` reports it).
+    pub fn recovery(&self) -> Option<&RecoveryReport> {
+        self.recovery.as_deref()
+    }
+
+    /// Start a graceful shutdown: close the market so REST and FIX reject new orders, save state, and tell
+    /// every WebSocket to send what it has queued and close (code 1001). Audited as `shutdown` by `actor`, from `context`.
+    /// Returns false, doing nothing, if a shutdown is already under way.
+    ///
+    /// State is saved with the market state from before the shutdown, so a restart resumes it.
+    pub fn begin_shutdown(&self, actor: &str, context: &AuditContext) -> bool {
+        {
+            let mut market_state = self.market_state.lock().expect("lock");
+            let started = self.shutdown.tx.send_if_modified(|resume| {
+                resume.is_none() && resume.replace(*market_state).is_none()
+            });
+            if !started {
+                return false;
+            }
+            *market_state = MarketState::Closed;
+        }
+        publish_market_state(self, MarketState::Closed);
+        self.audit_sink.emit(&AuditEvent::now(
+            actor.to_string(),
+            AuditAction::Shutdown,
+            Some(serde_json::json!({ "state": "Closed" })),
+            "success",
+        ).with_context(context));
+        persist_state(self);
+        true
+    }
+
+    /// Replace the venue config and apply it: risk limits, band, and trading session to the engine, rate
+    /// limits to the next order-entry request. Does not save state.
+    pub fn set_venue_config(&self, config: VenueConfig) {
+        let mut current = self.venue_config.lock().expect("lock");
+        {
+            let mut engine = self.engine.lock().expect("lock");
+            engine.set_risk_limits(config.risk);
+            engine.set_band_config(config.bands);
+            engine.set_trading_session(config.session);
+        }
+        *current = config;
+    }
+
+    /// The market state as saved: during a shutdown the one to resume on restart, otherwise the current one.
+    pub(crate) fn resume_market_state(&self) -> MarketState {
+        let market_state = self.market_state.lock().expect("lock");
+        let resume = *self.shutdown.tx.borrow();
+        resume.unwrap_or(*market_state)
+    }
+
+    /// Whether this node is a standby that has not been promoted.
+    fn is_standby(&self) -> bool {
+        self.standby.as_ref().is_some_and(|standby| !standby.is_promoted())
+    }
+
+    /// Save state once in-flight engine commands (e.g. from FIX sessions) are done, and write out queued audit
+    /// events. Call last, after the server stopped and WebSockets drained.
+    pub fn flush(&self) {
+        drop(self.engine.lock().expect("lock"));
+        persist_state(self);
+        self.audit_sink.flush();
+    }
+}
+
+/// Graceful shutdown of the server: set once by [`AppState::begin_shutdown`], with a count of open WebSockets
+/// so the process can wait for them to drain before exiting.
+#[derive(Clone)]
+pub struct Shutdown {
+    /// The market state to resume on restart, once a shutdown has begun.
+    tx: Arc<watch::Sender<Option<MarketState>>>,
+    open_sockets: Arc<AtomicUsize>,
+}
+
+impl Shutdown {
+    fn new() -> Self {
+        Self {
+            tx: Arc::new(watch::channel(None).0),
+            open_sockets: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.tx.borrow().is_some()
+    }
+
+    /// Completes once a shutdown has begun (immediately if it already has).
+    pub async fn requested(&self) {
+        let _ = self.tx.subscribe().wait_for(Option::is_some).await;
+    }
+
+    pub fn open_sockets(&self) -> usize {
+        self.open_sockets.load(Ordering::SeqCst)
+    }
+
+    /// Wait up to `grace` for every WebSocket to close. Returns false if some are still open.
+    pub async fn drained(&self, grace: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + grace;
+        while self.open_sockets() > 0 {
+            if tokio::time::Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        true
+    }
+
+    /// Count a WebSocket as open until the guard is dropped.
+    fn track(&self) -> OpenSocket {
+        self.open_sockets.fetch_add(1, Ordering::SeqCst);
+        OpenSocket(self.open_sockets.clone())
+    }
+}
+
+struct OpenSocket(Arc<AtomicUsize>);
+
+impl Drop for OpenSocket {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Close frame sent to every WebSocket once its queued messages are out during a shutdown.
+fn shutdown_close() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::AWAY,
+        reason: "server shutting down".into(),
+    }))
+}
+
+/// How `/ws/market-data` treats a client that reads slower than books change. Book updates queued for a client
+/// are always conflated to the latest state per instrument; these limits decide when to give up on it instead.
+/// The default never disconnects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SlowConsumerPolicy {
+    /// Close the socket (code 1008, "slow consumer") once more than this many book updates were conflated or
+    /// dropped for it without it catching up in between.
+    pub max_conflated: Option<u64>,
+    /// Drop the socket when one batch of messages takes longer than this to send.
+    pub send_timeout: Option<Duration>,
+}
+
+impl SlowConsumerPolicy {
+    /// Read the policy from `WS_MAX_CONFLATED` and `WS_SEND_TIMEOUT_MS`. Unset or unparsable variables leave
+    /// that limit off.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse().ok());
+        Self {
+            max_conflated: var("WS_MAX_CONFLATED"),
+            send_timeout: var("WS_SEND_TIMEOUT_MS").map(Duration::from_millis),
+        }
+    }
+}
+
+/// Liveness checks on every WebSocket (`/ws/market-data` and `/ws/executions`). The default sends nothing and
+/// never times out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    /// Send a ping frame and a `heartbeat` message this often.
+    pub interval: Option<Duration>,
+    /// Close the socket (code 1001, "idle timeout") when nothing, not even a pong, was received from the client
+    /// for this long. Checked on every heartbeat (or this often without one), so it can fire up to one period late.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl HeartbeatPolicy {
+    /// Read the policy from `WS_HEARTBEAT_INTERVAL_MS` and `WS_IDLE_TIMEOUT_MS`. Unset or unparsable variables
+    /// leave that check off.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse().ok());
+        Self {
+            interval: var("WS_HEARTBEAT_INTERVAL_MS").map(Duration::from_millis),
+            idle_timeout: var("WS_IDLE_TIMEOUT_MS").map(Duration::from_millis),
+        }
+    }
+}
+
+/// Heartbeat state of one WebSocket.
+struct Heartbeat {
+    policy: HeartbeatPolicy,
+    timer: Option<tokio::time::Interval>,
+    last_seen: tokio::time::Instant,
+}
+
+impl Heartbeat {
+    fn new(policy: HeartbeatPolicy) -> Self {
+        let timer = policy.interval.or(policy.idle_timeout).map(|period| {
+            let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            timer
+        });
+        Self {
+            policy,
+            timer,
+            last_seen: tokio::time::Instant::now(),
+        }
+    }
+
+    /// Note a frame from the client.
+    fn seen(&mut self) {
+        self.last_seen = tokio::time::Instant::now();
+    }
+
+    /// Wait for the next check; never completes when the policy is off.
+    async fn tick(&mut self) {
+        match self.timer.as_mut() {
+            Some(timer) => {
+                timer.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Close the socket if the client went quiet, otherwise ping it. Returns false if the socket is closed.
+    async fn check(&self, socket: &mut WebSocket) -> bool {
+        if self.policy.idle_timeout.is_some_and(|timeout| self.last_seen.elapsed() > timeout) {
+            let close = Message::Close(Some(CloseFrame {
+                code: close_code::AWAY,
+                reason: "idle timeout".into(),
+            }));
+            let _ = socket.send(close).await;
+            return false;
+        }
+        if self.policy.interval.is_none() {
+            return true;
+        }
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let heartbeat = serde_json::json!({ "type": "heartbeat", "timestamp": timestamp });
+        socket.send(Message::Ping(Vec::new())).await.is_ok()
+            && socket.send(Message::Text(heartbeat.to_string())).await.is_ok()
+    }
+}
+
+/// How often an open WebSocket checks that its API key still holds the grant it connected with.
+const KEY_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Identity a WebSocket was opened with, kept for the life of the connection. A key removed or changed through
+/// `/admin/keys` loses its open sockets (code 1008, "API key revoked") as well as its next request; the client
+/// reconnects to pick up a changed grant.
+struct SocketAuth {
+    config: AuthConfig,
+    user: AuthUser,
+    timer: Option<tokio::time::Interval>,
+}
+
+impl SocketAuth {
+    fn new(config: AuthConfig, user: AuthUser) -> Self {
+        let timer = user.key_id.is_some().then(|| {
+            let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + KEY_RECHECK_INTERVAL, KEY_RECHECK_INTERVAL);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            timer
+        });
+        Self { config, user, timer }
+    }
+
+    /// Wait for the next check; never completes for anonymous users.
+    async fn tick(&mut self) {
+        match self.timer.as_mut() {
+            Some(timer) => {
+                timer.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Close the socket if the key no longer holds its grant. Returns false if the socket is closed.
+    async fn check(&self, socket: &mut WebSocket) -> bool {
+        if self.config.is_current(&self.user) {
+            return true;
+        }
+        log::info!("closing WebSocket of API key {}: revoked or changed", self.user.actor());
+        let close = Message::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: "API key revoked".into(),
+        }));
+        let _ = socket.send(close).await;
+        false
+    }
+}
+
+/// Forwards engine events into the app's broadcast channel.
+struct BroadcastEventSink(broadcast::Sender<EngineEvent>);
+
+impl EngineEventSink for BroadcastEventSink {
+    fn on_event(&self, event: &EngineEvent) {
+        let _ = self.0.send(event.clone());
+    }
+}
+
+/// Audits orders the engine expired (IOC, FOK, and market remainders; good-till-date orders at their expiry)
+/// as `order_expired` by `engine`.
+struct ExpiryAuditSink(Arc<dyn AuditSink + Send + Sync>);
+
+impl EngineEventSink for ExpiryAuditSink {
+    fn on_event(&self, event: &EngineEvent) {
+        if let EngineEvent::Expired { order_id, instrument_id, quantity } = event {
+            self.0.emit(&AuditEvent::now(
+                "engine",
+                AuditAction::OrderExpired,
+                Some(serde_json::json!({
+                    "order_id": order_id.0,
+                    "instrument_id": instrument_id.0,
+                    "quantity": quantity,
+                })),
+                "success",
+            ));
+        }
+    }
+}
+
+/// `order_modify` audit resource: the order as it stood before the request (`before`, `null` once the engine no
+/// longer knows it) and the replacement as requested (`after`), so the change can be reconstructed. REST and FIX
+/// record the same shape.
+pub(crate) fn order_modify_resource(order_id: OrderId, before: Option<&OrderStatusView>, replacement: &Order) -> serde_json::Value {
+    let before = before.map(|order| {
+        serde_json::json!({
+            "instrument_id": order.instrument_id.0,
+            "side": order.side,
+            "price": order.price,
+            "quantity": order.quantity,
+            "filled_quantity": order.filled_quantity,
+            "remaining_quantity": order.remaining_quantity,
+            "trader_id": order.trader_id.0,
+            "status": order.status,
+        })
+    });
+    serde_json::json!({
+        "order_id": order_id.0,
+        "replacement_order_id": replacement.order_id.0,
+        "before": before,
+        "after": {
+            "client_order_id": replacement.client_order_id,
+            "instrument_id": replacement.instrument_id.0,
+            "side": replacement.side,
+            "order_type": replacement.order_type,
+            "price": replacement.price,
+            "quantity": replacement.quantity,
+            "time_in_force": replacement.time_in_force,
+            "trader_id": replacement.trader_id.0,
+        },
+    })
+}
+
+/// Capacity of the engine event channel behind [`AppState::subscribe_events`].
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Stdout and, when `AUDIT_STORE_PATH` is set, a [`FileAuditStore`] there for `GET /admin/audit`; written by a
+/// background thread with the buffer of `AUDIT_BUFFER_CAPACITY` and `AUDIT_OVERFLOW`.
+pub(crate) fn default_audit_sink() -> Arc<dyn AuditSink + Send + Sync> {
+    let mut sink: Arc<dyn AuditSink + Send + Sync> = Arc::new(StdoutAuditSink);
+    if let Ok(path) = std::env::var("AUDIT_STORE_PATH") {
+        match FileAuditStore::open(&path) {
+            Ok(store) => sink = Arc::new(MultiAuditSink::new(vec![sink, Arc::new(store)])),
+            Err(e) => log::error!("audit store not opened: {}; auditing to stdout only", e),
+        }
+    }
+    Arc::new(BufferedAuditSink::new(sink, AuditBufferPolicy::from_env()))
 }
 
 /// Builds shared app state (multi-instrument engine + broadcast + stdout audit + Open market state). Use this when you need to share the engine with FIX or other adapters.
@@ -87,7 +544,7 @@ pub fn create_app_state(instrument_id: InstrumentId) -> AppState {
 
 /// Builds shared app state with multiple initial instruments. Each entry is (instrument_id, optional symbol).
 pub fn create_app_state_with_instruments(initial: Vec<(InstrumentId, Option<String>)>) -> AppState {
-    create_app_state_with_sink_and_instruments(initial, Arc::new(StdoutAuditSink), None)
+    create_app_state_with_sink_and_instruments(initial, default_audit_sink(), None)
 }
 
 /// Like [`create_app_state`] but with a single instrument and an explicit audit sink (e.g. [`crate::audit::InMemoryAuditSink`] for tests).
@@ -95,70 +552,391 @@ pub fn create_app_state_with_sink(instrument_id: InstrumentId, audit_sink: Arc<d
     create_app_state_with_sink_and_instruments(vec![(instrument_id, None)], audit_sink, None)
 }
 
-/// Like [`create_app_state_with_instruments`] but with an explicit audit sink. When `persistence` is `Some`, state is loaded from file if present and saved after each change.
+/// A snapshot loaded at startup, and the file it came from.
+struct LoadedSnapshot {
+    engine: MultiEngine,
+    market_state: MarketState,
+    venue_config: VenueConfig,
+    path: std::path::PathBuf,
+}
+
+/// Load the state file or, if it does not load, the newest retained copy ([`FilePersistence::archive`]) that
+/// does. Every attempt is audited as `state_load` by `persistence`, and files that fail are added to `rejected`.
+/// `Ok(None)` when there is no state file; `Err` with the last failure when it exists but nothing loads.
+fn load_latest_snapshot(
+    persistence: &FilePersistence,
+    audit_sink: &dyn AuditSink,
+    rejected: &mut Vec<RejectedSnapshot>,
+) -> Result<Option<LoadedSnapshot>, String> {
+    let load = |path: &std::path::Path| -> Result<Option<LoadedSnapshot>, String> {
+        let Some(loaded) = FilePersistence::new(path).load()? else { return Ok(None) };
+        let mut engine = MultiEngine::new_with_instruments(vec![]);
+        let resting_orders = loaded.engine.books.iter().map(|(_, orders)| orders.len()).sum::<usize>();
+        engine.load_from_snapshot(loaded.engine)?;
+        let resource = serde_json::json!({ "path": path.display().to_string(), "resting_orders": resting_orders });
+        audit_sink.emit(&AuditEvent::now("persistence", AuditAction::StateLoad, Some(resource), "success"));
+        Ok(Some(LoadedSnapshot {
+            engine,
+            market_state: MarketState::from_str(loaded.market_state.trim()).unwrap_or(MarketState::Open),
+            venue_config: loaded.venue_config.unwrap_or_default(),
+            path: path.to_path_buf(),
+        }))
+    };
+    let mut failed = |path: &std::path::Path, e: &str| {
+        log::warn!("Failed to load persistence file {}: {}", path.display(), e);
+        let resource = serde_json::json!({ "path": path.display().to_string(), "error": e });
+        audit_sink.emit(&AuditEvent::now("persistence", AuditAction::StateLoad, Some(resource), "error"));
+        rejected.push(RejectedSnapshot {
+            path: path.display().to_string(),
+            error: e.to_string(),
+        });
+    };
+    let mut error = match load(persistence.path()) {
+        Ok(loaded) => return Ok(loaded),
+        Err(e) => e,
+    };
+    failed(persistence.path(), &error);
+    for (_, path) in persistence.snapshots()?.into_iter().rev() {
+        match load(&path) {
+            Ok(Some(loaded)) => return Ok(Some(loaded)),
+            Ok(None) => {}
+            Err(e) => {
+                failed(&path, &e);
+                error = e;
+            }
+        }
+    }
+    Err(error)
+}
+
+/// Like [`create_app_state_with_instruments`] but with an explicit audit sink. When `persistence` is `Some`, state is loaded from file if present, and every change marks
+/// the state's [`PersistenceNotifier`] for [`start_snapshotter`] to save. Engine commands are also written ahead to its command log; the commands the log holds beyond the state file are
+/// replayed on load, and the state saved again. Trades and execution reports are appended to its history log,
+/// which reloads the retained history. What recovery did is kept for `GET /admin/recovery`.
 pub fn create_app_state_with_sink_and_instruments(
     initial: Vec<(InstrumentId, Option<String>)>,
     audit_sink: Arc<dyn AuditSink + Send + Sync>,
     persistence: Option<Arc<FilePersistence>>,
 ) -> AppState {
     let (broadcast_tx, _) = broadcast::channel(32);
-    let (engine, market_state) = if let Some(ref p) = persistence {
-        match p.load() {
-            Ok(Some(loaded)) => {
-                let mut eng = MultiEngine::new_with_instruments(vec![]);
-                if let Err(e) = eng.load_from_snapshot(loaded.engine) {
-                    log::warn!("Failed to load persistence snapshot: {}; starting fresh", e);
-                }
-                let ms = MarketState::from_str(loaded.market_state.trim()).unwrap_or(MarketState::Open);
-                (Arc::new(Mutex::new(eng)), Arc::new(Mutex::new(ms)))
+    let mut venue_config = VenueConfig::default();
+    let mut recovery = RecoveryReport::default();
+    let load_started = std::time::Instant::now();
+    let loaded = persistence
+        .as_ref()
+        .map(|p| load_latest_snapshot(p, audit_sink.as_ref(), &mut recovery.rejected_snapshots));
+    // Whether a state file exists but no snapshot loaded, so the command log has nothing to apply to.
+    let mut discard_log = false;
+    let (engine, market_state) = match loaded {
+        Some(Ok(Some(loaded))) => {
+            recovery.snapshot_path = Some(loaded.path.display().to_string());
+            recovery.snapshot_seq = loaded.engine.last_seq();
+            recovery.snapshot_command_seq = loaded.engine.command_seq();
+            venue_config = loaded.venue_config;
+            (loaded.engine, loaded.market_state)
+        }
+        loaded => {
+            if let Some(Err(e)) = loaded {
+                log::warn!("No persisted state could be loaded ({}); starting fresh", e);
+                discard_log = true;
             }
-            Ok(None) | Err(_) => (
-                Arc::new(Mutex::new(MultiEngine::new_with_instruments(initial))),
-                Arc::new(Mutex::new(MarketState::Open)),
-            ),
+            (MultiEngine::new_with_instruments(initial), MarketState::Open)
         }
-    } else {
-        (
-            Arc::new(Mutex::new(MultiEngine::new_with_instruments(initial))),
-            Arc::new(Mutex::new(MarketState::Open)),
-        )
     };
-    AppState {
+    recovery.load_ms = load_started.elapsed().as_millis() as u64;
+    let (engine, market_state) = (Arc::new(Mutex::new(engine)), Arc::new(Mutex::new(market_state)));
+    let mut command_log = None;
+    if let Some(ref p) = persistence {
+        let mut guard = engine.lock().expect("lock");
+        let replay_started = std::time::Instant::now();
+        // Log records that failed their checksum or were cut short, which opening the logs dropped.
+        let audit_dropped = |path: &std::path::Path, dropped: usize| {
+            if dropped > 0 {
+                let resource = serde_json::json!({ "path": path.display().to_string(), "dropped": dropped });
+                audit_sink.emit(&AuditEvent::now("persistence", AuditAction::StateLoad, Some(resource), "error"));
+            }
+        };
+        let history = p.history_log().and_then(|log| {
+            recovery.history_dropped = log.dropped();
+            audit_dropped(log.path(), log.dropped());
+            guard.set_history_log(Arc::new(log))
+        });
+        match history {
+            Ok(records) => recovery.history_records = records,
+            Err(e) => log::warn!("Failed to open history log: {}; trades and reports are kept in memory only", e),
+        }
+        // Replayed before the id store and venue config apply, so the commands give the ids they gave before.
+        let opened = p.command_log().and_then(|log| {
+            recovery.command_log_dropped = log.dropped();
+            audit_dropped(log.path(), log.dropped());
+            let replayed = if discard_log {
+                Err("no snapshot loaded".to_string())
+            } else {
+                guard.replay_command_log(&log)
+            };
+            match replayed {
+                Ok(replay) => (recovery.replayed, recovery.skipped) = (replay.applied, replay.skipped),
+                Err(e) => {
+                    recovery.discarded = log.entries_after(guard.command_seq())?.len();
+                    if recovery.discarded > 0 {
+                        log::warn!("Discarding {} command log entries: {}", recovery.discarded, e);
+                        let resource = serde_json::json!({
+                            "path": log.path().display().to_string(),
+                            "discarded": recovery.discarded,
+                            "error": e,
+                        });
+                        audit_sink.emit(&AuditEvent::now("persistence", AuditAction::StateLoad, Some(resource), "error"));
+                    }
+                    log.clear()?;
+                }
+            }
+            Ok(log)
+        });
+        match opened {
+            Ok(log) => {
+                let log = Arc::new(log);
+                guard.set_command_log(log.clone());
+                command_log = Some(log);
+            }
+            Err(e) => log::warn!("Failed to open command log: {}; orders are only persisted with snapshots", e),
+        }
+        recovery.replay_ms = replay_started.elapsed().as_millis() as u64;
+        recovery.seq = guard.last_seq();
+        recovery.command_seq = guard.command_seq();
+        log::info!("Recovery: {:?}", recovery);
+        if let Err(e) = guard.set_id_store(Arc::new(p.id_store())) {
+            log::warn!("Failed to open id reservation file: {}; ids are only persisted with snapshots", e);
+        }
+    }
+    let (events_tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+    let notifier = Arc::new(PersistenceNotifier::new());
+    {
+        let mut guard = engine.lock().expect("lock");
+        if persistence.is_some() {
+            guard.add_event_sink(notifier.clone());
+        }
+        guard.add_event_sink(Arc::new(BroadcastEventSink(events_tx.clone())));
+        guard.add_event_sink(Arc::new(ExpiryAuditSink(audit_sink.clone())));
+        let publisher = BookPublisher::new(broadcast_tx.clone(), &guard);
+        guard.add_book_observer(Arc::new(publisher));
+    }
+    let recovery_report = persistence.is_some().then(|| Arc::new(recovery.clone()));
+    let state = AppState {
         engine,
         broadcast_tx,
         audit_sink,
         market_state,
-        admin_config: Arc::new(Mutex::new(HashMap::new())),
+        venue_config: Arc::new(Mutex::new(VenueConfig::default())),
+        order_rate: Arc::new(OrderRateLimiter::new()),
         persistence,
+        notifier,
+        command_log,
+        recovery: recovery_report,
+        events_tx,
+        slow_consumer: SlowConsumerPolicy::default(),
+        heartbeat: HeartbeatPolicy::default(),
+        fix_acceptor: None,
+        standby: None,
+        started_at: std::time::Instant::now(),
+        shutdown: Shutdown::new(),
+    };
+    state.set_venue_config(venue_config);
+    if recovery.replayed > 0 {
+        log::info!("Replayed {} commands from the command log", recovery.replayed);
+        persist_state(&state);
     }
+    state
+}
+
+/// Put a market state change on the engine event stream.
+pub(crate) fn publish_market_state(state: &AppState, market_state: MarketState) {
+    state.engine.lock().expect("lock").publish(EngineEvent::StateChange {
+        instrument_id: None,
+        state: market_state.as_str().to_string(),
+    });
 }
 
-fn persist_state(state: &AppState) {
+pub(crate) fn persist_state(state: &AppState) {
     let Some(ref p) = state.persistence else { return };
+    if let Err(e) = save_state(state, p) {
+        report_save_failure(state, p, e);
+    }
+}
+
+/// Log and audit a failed save.
+fn report_save_failure(state: &AppState, persistence: &FilePersistence, error: String) {
+    log::warn!("Persistence save failed: {}", error);
+    let resource = serde_json::json!({ "path": persistence.path().display().to_string(), "error": error });
+    state.audit_sink.emit(&AuditEvent::now("persistence", AuditAction::StateSave, Some(resource), "error"));
+}
+
+/// How often the snapshotter checks its triggers.
+const SNAPSHOTTER_POLL: Duration = Duration::from_millis(100);
+
+/// How long the snapshotter waits after a failed save before it tries again.
+const SNAPSHOTTER_RETRY: Duration = Duration::from_secs(1);
+
+/// Start a thread that saves state per `policy` until a shutdown begins (the shutdown saves it too), copying
+/// each save to `<path>.snapshot-<seq>` when `policy.retain` is set. With `policy.debounce`, this is what saves
+/// order entry: REST and FIX orders and engine timers alike mark the [`PersistenceNotifier`], and the state is
+/// saved that long after the first change (admin handlers save theirs at once). It also keeps the command log
+/// short. Returns
+/// `None` without persistence or when `policy` has no trigger. A failed save is audited as `state_save` and
+/// tried again a second later (or at the next trigger, when it was not a debounced save).
+pub fn start_snapshotter(state: &AppState, policy: SnapshotPolicy) -> Option<std::thread::JoinHandle<()>> {
+    let persistence = state.persistence.clone().filter(|_| policy.is_enabled())?;
+    let state = state.clone();
+    let mut saved_seq = state.engine.lock().expect("lock").last_seq();
+    Some(std::thread::spawn(move || {
+        let mut saved_at = std::time::Instant::now();
+        while !state.shutdown.is_requested() {
+            // Woken by the first change, then held off until the debounce period after it has passed.
+            let dirty_since = match policy.debounce {
+                Some(debounce) => state.notifier.wait(SNAPSHOTTER_POLL).inspect(|since| {
+                    std::thread::sleep(debounce.saturating_sub(since.elapsed()));
+                }),
+                None => {
+                    std::thread::sleep(SNAPSHOTTER_POLL);
+                    None
+                }
+            };
+            let seq = state.engine.lock().expect("lock").last_seq();
+            // A restore can move the sequence number back; that counts as movement too.
+            let moved = seq.abs_diff(saved_seq);
+            let due = dirty_since.is_some()
+                || policy.interval.is_some_and(|interval| moved > 0 && saved_at.elapsed() >= interval)
+                || policy.every_events.is_some_and(|n| moved >= n);
+            if !due {
+                continue;
+            }
+            let saved = save_state(&state, &persistence).and_then(|(persisted, _)| {
+                let seq = persisted.engine.next_seq - 1;
+                if policy.retain > 0 {
+                    persistence.archive(seq, policy.retain)?;
+                }
+                Ok(seq)
+            });
+            saved_seq = match saved {
+                Ok(seq) => seq,
+                Err(e) => {
+                    report_save_failure(&state, &persistence, e);
+                    std::thread::sleep(SNAPSHOTTER_RETRY);
+                    seq
+                }
+            };
+            saved_at = std::time::Instant::now();
+        }
+    }))
+}
+
+/// The thread of [`start_remote_uploader`].
+#[derive(Debug)]
+pub struct RemoteUploaderThread {
+    stop: Arc<AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl RemoteUploaderThread {
+    /// Upload once more and stop. Call after [`AppState::flush`], so the store has the final state. Blocks until
+    /// the upload is done.
+    pub fn finish(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self.thread.join();
+    }
+}
+
+/// Start a thread that copies the state file and command log to `store` every `interval` (see
+/// [`crate::remote::RemoteUploader`]) until [`RemoteUploaderThread::finish`]. Returns `None` without
+/// persistence. A failed upload is audited as `state_save` and tried again at the next interval.
+pub fn start_remote_uploader(
+    state: &AppState,
+    store: Arc<dyn ObjectStore>,
+    interval: Duration,
+) -> Option<RemoteUploaderThread> {
+    let persistence = state.persistence.clone()?;
+    let state = state.clone();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let thread = std::thread::spawn(move || {
+        let mut uploader = RemoteUploader::new(store.clone());
+        let mut uploaded_at: Option<std::time::Instant> = None;
+        loop {
+            let stopping = stopped.load(Ordering::SeqCst);
+            if stopping || uploaded_at.is_none_or(|at| at.elapsed() >= interval) {
+                let command_log = state.command_log.as_deref().map(|log| log as &dyn CommandLog);
+                match uploader.upload(&persistence, command_log) {
+                    Ok(upload) if upload != Upload::default() => log::debug!("Uploaded to {:?}: {:?}", store, upload),
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::warn!("Remote upload failed: {}", e);
+                        let resource = serde_json::json!({ "store": format!("{:?}", store), "error": e });
+                        state.audit_sink.emit(&AuditEvent::now("persistence", AuditAction::StateSave, Some(resource), "error"));
+                    }
+                }
+                uploaded_at = Some(std::time::Instant::now());
+            }
+            if stopping {
+                break;
+            }
+            std::thread::sleep(SNAPSHOTTER_POLL);
+        }
+    });
+    Some(RemoteUploaderThread { stop, thread })
+}
+
+/// Save the engine snapshot, market state, and venue config to `persistence`. Returns what was saved and the
+/// file's checksum.
+fn save_state(state: &AppState, persistence: &FilePersistence) -> Result<(PersistedState, u32), String> {
+    state.notifier.clear();
     let engine_snapshot = {
         let guard = state.engine.lock().expect("lock");
         guard.snapshot()
     };
-    let market_state_str = {
-        let guard = state.market_state.lock().expect("lock");
-        guard.as_str().to_string()
-    };
+    let venue_config = state.venue_config.lock().expect("lock").clone();
     let persisted = PersistedState {
+        version: STATE_VERSION,
         engine: engine_snapshot,
-        market_state: market_state_str,
+        market_state: state.resume_market_state().as_str().to_string(),
+        venue_config: Some(venue_config),
     };
-    if let Err(e) = p.save(&persisted) {
-        log::warn!("Persistence save failed: {}", e);
+    // Still unsaved, for the snapshotter to try again.
+    let checksum = persistence.save(&persisted).inspect_err(|_| state.notifier.mark_dirty())?;
+    if let Some(command_log) = &state.command_log {
+        if let Err(e) = command_log.compact(persisted.engine.command_seq) {
+            log::warn!("Command log compaction failed: {}", e);
+        }
     }
+    Ok((persisted, checksum))
+}
+
+/// What `POST /admin/snapshot` and `POST /admin/restore` report about the state file.
+fn snapshot_metadata(persistence: &FilePersistence, persisted: &PersistedState, checksum: u32) -> serde_json::Value {
+    let engine = &persisted.engine;
+    serde_json::json!({
+        "path": persistence.path().display().to_string(),
+        "checksum": checksum,
+        "seq": engine.next_seq.saturating_sub(1),
+        "next_trade_id": engine.next_trade_id,
+        "next_exec_id": engine.next_exec_id,
+        "instruments": engine.instruments.len(),
+        "resting_orders": engine.books.iter().map(|(_, orders)| orders.len()).sum::<usize>(),
+        "market_state": persisted.market_state,
+        "config_version": persisted.venue_config.as_ref().map_or(0, |config| config.version),
+    })
 }
 
-/// Builds app state with file persistence. When `path` is set, state is loaded from the file on startup (if it exists) and saved after each state change.
+/// Builds app state with file persistence. When `path` is set, state is loaded from the file on startup (if it exists) and saved by the snapshotter ([`start_snapshotter`]) after each state change.
+/// `WAL_FSYNC=true` syncs the command log to disk after every order (see [`FilePersistence::with_fsync`]);
+/// `STATE_SNAPSHOT_FORMAT=json` saves readable JSON instead of compressed binary ([`SnapshotFormat::from_env`]).
 pub fn create_app_state_with_persistence(
     initial: Vec<(InstrumentId, Option<String>)>,
     path: impl AsRef<std::path::Path>,
 ) -> AppState {
-    let persistence = Arc::new(FilePersistence::new(path));
-    create_app_state_with_sink_and_instruments(initial, Arc::new(StdoutAuditSink), Some(persistence))
+    let fsync = std::env::var("WAL_FSYNC")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let persistence = Arc::new(FilePersistence::new(path).with_fsync(fsync).with_format(SnapshotFormat::from_env()));
+    create_app_state_with_sink_and_instruments(initial, default_audit_sink(), Some(persistence))
 }
 
 /// Builds the REST/WebSocket router with the given state. Use with [`create_app_state`] when sharing engine with FIX.
@@ -173,355 +951,2140 @@ pub fn create_router_with_state(state: AppState) -> Router<()> {
 pub fn create_router_with_state_and_auth(state: AppState, auth_config_override: Option<AuthConfig>) -> Router<()> {
     let auth_config = auth_config_override.unwrap_or_else(AuthConfig::from_env);
 
-    let protected = Router::new()
-        .route("/orders", post(submit_order))
+    let rate_state = state.clone();
+    let standby_state = state.clone();
+    let audit_sink = state.audit_sink.clone();
+    let order_entry = Router::new()
+        .route("/orders", get(list_open_orders).post(submit_order))
         .route("/orders/cancel", post(cancel_order))
         .route("/orders/modify", post(modify_order))
-        .route("/ws/market-data", get(ws_market_data))
+        .route_layer(middleware::from_fn(move |req: Request<Body>, next: Next| {
+            let state = rate_state.clone();
+            async move { limit_order_rate(req, next, state).await }
+        }))
+        .route("/orders/:id", get(get_order))
+        .route("/executions", get(list_executions))
+        .route("/positions", get(list_positions))
+        .route("/ws/executions", get(ws_executions));
+    let market_data = Router::new()
+        .route("/trades", get(list_trades))
+        .route("/book/:id", get(get_book_depth))
+        .route("/ticker", get(list_tickers))
+        .route("/ticker/:id", get(get_ticker))
+        .route("/ws/market-data", get(ws_market_data));
+    let journal = Router::new().route("/events", get(list_events));
+
+    let protected = Router::new()
+        .merge(requiring(order_entry, Permission::SubmitOrders))
+        .merge(requiring(market_data, Permission::ViewMarketData))
+        .merge(requiring(journal, Permission::ViewAudit))
+        .route("/auth/login", post(auth_login))
+        .route("/auth/refresh", post(auth_refresh))
+        .route("/auth/logout", post(auth_logout))
+        .route("/admin/keys", get(admin_keys_list))
+        .route("/admin/keys/:key", put(admin_keys_put).delete(admin_keys_delete))
         .route("/admin/status", get(admin_status))
         .route("/admin/instruments", get(admin_instruments_list).post(admin_instruments_post))
-        .route("/admin/instruments/:id", delete(admin_instruments_delete))
+        .route("/admin/instruments/:id", delete(admin_instruments_delete).patch(admin_instruments_patch))
+        .route(
+            "/admin/instruments/:id/state",
+            get(admin_instrument_state_get).post(admin_instrument_state_post),
+        )
+        .route("/admin/book/:id", get(admin_book_orders))
+        .route("/admin/book/:id/uncross", post(admin_book_uncross))
         .route("/admin/config", get(admin_config_get).patch(admin_config_patch))
         .route("/admin/market-state", get(admin_market_state_get).post(admin_market_state_post))
         .route("/admin/emergency-halt", post(admin_emergency_halt))
+        .route("/admin/shutdown", post(admin_shutdown))
+        .route("/admin/snapshot", post(admin_snapshot))
+        .route("/admin/restore", post(admin_restore))
+        .route("/admin/recovery", get(admin_recovery))
+        .route("/admin/failover", post(admin_failover))
+        .route("/admin/audit", get(admin_audit))
         .layer(Extension(state.clone()))
+        .layer(Extension(auth_config.clone()))
+        .route_layer(middleware::from_fn(move |req: Request<Body>, next: Next| {
+            let state = standby_state.clone();
+            async move { refuse_writes_on_standby(req, next, state).await }
+        }))
         .route_layer(middleware::from_fn(move |req: Request<Body>, next: Next| {
             let config = auth_config.clone();
-            async move { auth::require_api_key_or_anonymous(req, next, config).await }
+            let audit = audit_sink.clone();
+            async move { auth::require_api_key_or_anonymous(req, next, config, audit).await }
         }));
 
-    Router::new()
-        .route("/health", get(health))
-        .layer(Extension(state))
-        .merge(protected)
+    Router::new()
+        .route("/health", get(health))
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
+        .layer(Extension(state))
+        .merge(protected)
+        .layer(middleware::from_fn(with_audit_context))
+}
+
+/// Request header carrying the id that correlates a request's audit events; echoed on every response.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-chosen request id kept; a longer one is replaced like a missing one.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Put the request's [`AuditContext`] in its extensions: `ws` for `/ws/*` routes and `rest` otherwise, the
+/// client's `X-Request-Id` (a new random id when it sends none or one that is not printable ASCII), and the peer
+/// address when the server records it. The request id is returned in the response's `X-Request-Id`.
+async fn with_audit_context(mut req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map_or_else(|| format!("{:016x}", rand::random::<u64>()), str::to_string);
+    let source = if req.uri().path().starts_with("/ws/") { AuditSource::Ws } else { AuditSource::Rest };
+    let client_ip = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_canonical());
+    req.extensions_mut().insert(AuditContext { source, request_id: Some(request_id.clone()), client_ip });
+    let mut response = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// `router` with each route requiring `permission` of the authenticated user (403 otherwise).
+fn requiring(router: Router<()>, permission: Permission) -> Router<()> {
+    router.route_layer(middleware::from_fn(move |req: Request<Body>, next: Next| {
+        auth::require_permission_for_route(req, next, permission)
+    }))
+}
+
+/// Reject order-entry requests (`POST`) over the venue's [`crate::venue::RateLimits`] for their API key with
+/// 429 `rate_limited`. Anonymous requests share one budget.
+async fn limit_order_rate(req: Request<Body>, next: Next, state: AppState) -> Response {
+    if req.method() != axum::http::Method::POST {
+        return next.run(req).await;
+    }
+    let limit = state.venue_config.lock().expect("lock").rate_limits.orders_per_second;
+    let client = req
+        .extensions()
+        .get::<AuthUser>()
+        .map_or_else(|| "anonymous".to_string(), AuthUser::actor);
+    if state.order_rate.allow(&client, limit, std::time::Instant::now()) {
+        return next.run(req).await;
+    }
+    let message = format!("order rate limit of {} per second exceeded", limit.unwrap_or_default());
+    let mut response = ApiError::new(StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimited, message).into_response();
+    response
+        .headers_mut()
+        .insert(axum::http::header::RETRY_AFTER, axum::http::HeaderValue::from_static("1"));
+    response
+}
+
+/// Reject requests that change state with 503 `standby` while this node is an unpromoted standby: its state
+/// comes from the primary. Reads, logins, and `POST /admin/failover` pass.
+async fn refuse_writes_on_standby(req: Request<Body>, next: Next, state: AppState) -> Response {
+    let path = req.uri().path();
+    let passes = req.method() == axum::http::Method::GET || path.starts_with("/auth/") || path == "/admin/failover";
+    if passes || !state.is_standby() {
+        return next.run(req).await;
+    }
+    let primary = state.standby.as_ref().map(|standby| standby.primary().to_string()).unwrap_or_default();
+    ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::Standby,
+        format!("this node is a standby of {}; send requests to the primary, or promote it with POST /admin/failover", primary),
+    )
+    .into_response()
+}
+
+/// Builds the REST/WebSocket router with a new state (convenience for tests). Returns `Router<()>` for `axum::serve`.
+pub fn create_router(instrument_id: InstrumentId) -> Router<()> {
+    create_router_with_state(create_app_state(instrument_id))
+}
+
+async fn health() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+/// How long the health probes wait for the engine lock before reporting the engine unresponsive.
+const HEALTH_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Take and release the engine lock without blocking the runtime. Returns how long it took, or why it failed.
+async fn probe_engine_lock(state: &AppState) -> Result<Duration, String> {
+    let start = tokio::time::Instant::now();
+    loop {
+        let keep_waiting = match state.engine.try_lock() {
+            Ok(_) => return Ok(start.elapsed()),
+            Err(std::sync::TryLockError::Poisoned(_)) => return Err("engine lock poisoned".to_string()),
+            Err(std::sync::TryLockError::WouldBlock) => start.elapsed() < HEALTH_LOCK_TIMEOUT,
+        };
+        if !keep_waiting {
+            return Err(format!("engine lock not acquired within {} ms", HEALTH_LOCK_TIMEOUT.as_millis()));
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+/// Engine check of a health response.
+fn engine_check(probe: &Result<Duration, String>) -> serde_json::Value {
+    match probe {
+        Ok(wait) => serde_json::json!({ "status": "ok", "lock_wait_ms": wait.as_millis() as u64 }),
+        Err(e) => serde_json::json!({ "status": "error", "message": e }),
+    }
+}
+
+/// Version, commit (`GIT_COMMIT` at build time, if set), and uptime.
+fn build_info(state: &AppState) -> serde_json::Value {
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "commit": option_env!("GIT_COMMIT"),
+        "uptime_secs": state.started_at.elapsed().as_secs(),
+    })
+}
+
+/// Liveness: 200 while the engine lock can be taken, 503 if it is stuck or poisoned (restart the process).
+async fn health_live(Extension(state): Extension<AppState>) -> Response {
+    let probe = probe_engine_lock(&state).await;
+    let status = if probe.is_ok() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let mut body = build_info(&state);
+    body["status"] = serde_json::json!(if probe.is_ok() { "ok" } else { "error" });
+    body["engine"] = engine_check(&probe);
+    (status, Json(body)).into_response()
+}
+
+/// Readiness: 200 when the engine lock is responsive, the persistence file (if configured) is writable, and the
+/// FIX acceptor (if started) is running; 503 otherwise. The body has every check, build info, the venue market
+/// state, and each instrument's state.
+async fn health_ready(Extension(state): Extension<AppState>) -> Response {
+    let probe = probe_engine_lock(&state).await;
+    let mut ready = probe.is_ok();
+    let persistence = match &state.persistence {
+        None => serde_json::json!({ "status": "disabled" }),
+        Some(p) => match p.check_writable() {
+            Ok(()) => serde_json::json!({ "status": "ok" }),
+            Err(e) => {
+                ready = false;
+                serde_json::json!({ "status": "error", "message": e })
+            }
+        },
+    };
+    let fix_acceptor = match &state.fix_acceptor {
+        None => serde_json::json!({ "status": "disabled" }),
+        Some(handle) if handle.is_finished() => {
+            ready = false;
+            serde_json::json!({ "status": "error", "message": "FIX acceptor stopped" })
+        }
+        Some(_) => serde_json::json!({ "status": "ok" }),
+    };
+    let replication = match &state.standby {
+        None => serde_json::json!({ "status": "disabled" }),
+        Some(standby) => {
+            let status = standby.status();
+            ready &= status.promoted;
+            let mut check = serde_json::json!(status);
+            check["status"] = serde_json::json!(if status.promoted { "ok" } else { "standby" });
+            check
+        }
+    };
+    let instruments: Vec<serde_json::Value> = if probe.is_ok() {
+        let guard = state.engine.lock().expect("lock");
+        guard
+            .list_instruments()
+            .into_iter()
+            .map(|(id, symbol)| {
+                serde_json::json!({
+                    "instrument_id": id.0,
+                    "symbol": symbol,
+                    "state": guard.instrument_state(id),
+                    "market_state": guard.instrument_market_state(id).map(|m| m.as_str()),
+                    "seq": guard.book_snapshot_for(id).map(|b| b.seq),
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let mut body = build_info(&state);
+    body["status"] = serde_json::json!(if ready { "ready" } else { "not_ready" });
+    body["market_state"] = serde_json::json!(state.market_state.lock().expect("lock").as_str());
+    body["checks"] = serde_json::json!({
+        "engine": engine_check(&probe),
+        "persistence": persistence,
+        "fix_acceptor": fix_acceptor,
+        "replication": replication,
+    });
+    body["instruments"] = serde_json::json!(instruments);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(body)).into_response()
+}
+
+// --- Login sessions for browser clients ---
+
+/// `POST /auth/login`: exchange the API key the request authenticated with for a session token and a refresh
+/// token. 403 when it authenticated with a token instead, 409 with auth disabled.
+async fn auth_login(
+    Extension(auth): Extension<AuthUser>,
+    Extension(credential): Extension<Credential>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    Extension(config): Extension<AuthConfig>,
+) -> Response {
+    let key = match (credential, auth.key_id) {
+        (Credential::ApiKey, Some(key)) => key,
+        (Credential::Anonymous, _) => {
+            return ApiError::new(StatusCode::CONFLICT, ErrorCode::Conflict, "authentication is disabled").into_response()
+        }
+        _ => {
+            return ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, "log in with an API key, not a token")
+                .into_response()
+        }
+    };
+    let tokens = config.sessions().issue(key, std::time::Instant::now());
+    state.audit_sink.emit(&AuditEvent::now(
+        key.to_string(),
+        AuditAction::Login,
+        Some(serde_json::json!({ "expires_in": tokens.expires_in, "refresh_expires_in": tokens.refresh_expires_in })),
+        "success",
+    ).with_context(&context));
+    (StatusCode::OK, Json(tokens)).into_response()
+}
+
+/// `POST /auth/refresh` with the refresh token as the credential: a new token pair; the old one stops working.
+async fn auth_refresh(Extension(credential): Extension<Credential>, Extension(config): Extension<AuthConfig>) -> Response {
+    let tokens = match credential {
+        Credential::RefreshToken(refresh_token) => config.sessions().refresh(&refresh_token, std::time::Instant::now()),
+        Credential::Anonymous => {
+            return ApiError::new(StatusCode::CONFLICT, ErrorCode::Conflict, "authentication is disabled").into_response()
+        }
+        _ => None,
+    };
+    match tokens {
+        Some(tokens) => (StatusCode::OK, Json(tokens)).into_response(),
+        None => ApiError::new(StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, "invalid or expired refresh token")
+            .into_response(),
+    }
+}
+
+/// `POST /auth/logout` with a session token: ends its session, refresh token included.
+async fn auth_logout(
+    Extension(auth): Extension<AuthUser>,
+    Extension(credential): Extension<Credential>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    Extension(config): Extension<AuthConfig>,
+) -> Response {
+    let Credential::SessionToken(token) = credential else {
+        return ApiError::new(StatusCode::CONFLICT, ErrorCode::Conflict, "not authenticated with a session token")
+            .into_response();
+    };
+    config.sessions().end(&token);
+    state.audit_sink.emit(&AuditEvent::now(auth.actor(), AuditAction::Logout, None, "success").with_context(&context));
+    (StatusCode::OK, Json(serde_json::json!({ "logged_out": true }))).into_response()
+}
+
+/// Admin-only: returns 200 with status. Requires the `halt_market` permission (403 otherwise).
+async fn admin_status(Extension(auth): Extension<AuthUser>) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    (StatusCode::OK, Json(serde_json::json!({ "status": "ok" }))).into_response()
+}
+
+// --- Admin API (US-008, US-009, US-011, US-012) ---
+
+/// One key of `GET /admin/keys`: its [`KeyId`] (never the key) and its [`KeyGrant`].
+fn admin_key_json(key_id: &KeyId, grant: &KeyGrant) -> serde_json::Value {
+    let mut obj = serde_json::json!(grant);
+    obj["key_id"] = serde_json::json!(key_id);
+    obj
+}
+
+async fn admin_keys_list(Extension(auth): Extension<AuthUser>, Extension(config): Extension<AuthConfig>) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageKeys) {
+        return r;
+    }
+    let keys: Vec<_> = config.keys().iter().map(|(key, grant)| admin_key_json(key, grant)).collect();
+    (StatusCode::OK, Json(keys)).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct AdminKeyBody {
+    role: String,
+    trader_id: Option<u64>,
+    /// Omitted: the role's defaults.
+    permissions: Option<Permissions>,
+    /// Omitted: every instrument.
+    instruments: Option<Vec<u64>>,
+    /// Omitted: any client address.
+    allowed_cidrs: Option<Vec<Cidr>>,
+}
+
+/// Add a key or replace its role, trader binding, permissions, instrument entitlement, and address allowlist. 201 when the key is new. Keys changed here
+/// last until restart; `API_KEYS` is read again then.
+async fn admin_keys_put(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    Extension(config): Extension<AuthConfig>,
+    ApiPath(key): ApiPath<String>,
+    ApiJson(body): ApiJson<AdminKeyBody>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageKeys) {
+        return r;
+    }
+    let Some(role) = Role::from_str(&body.role) else {
+        return ApiError::invalid_field("role", format!("unknown role {}; expected trader, admin, or operator", body.role))
+            .into_response();
+    };
+    if key.is_empty() || key.contains([',', ':']) {
+        return ApiError::invalid_field("key", "key must be non-empty without ',' or ':'").into_response();
+    }
+    let mut grant = KeyGrant::new(role, body.trader_id.map(TraderId));
+    if let Some(permissions) = body.permissions {
+        grant.permissions = permissions;
+    }
+    grant.instruments = body.instruments.map(|ids| ids.into_iter().map(InstrumentId).collect());
+    grant.allowed_cidrs = body.allowed_cidrs;
+    let key_id = KeyId::of(&key);
+    let after = admin_key_json(&key_id, &grant);
+    let before = config.set_key(&key, grant);
+    state.audit_sink.emit(&AuditEvent::now(
+        auth.actor(),
+        AuditAction::KeySet,
+        Some(serde_json::json!({ "key_id": key_id, "before": before, "after": after })),
+        "success",
+    ).with_context(&context));
+    let status = if before.is_some() { StatusCode::OK } else { StatusCode::CREATED };
+    (status, Json(after)).into_response()
+}
+
+/// Remove a key, named by the key itself or by its [`KeyId`] as listed.
+async fn admin_keys_delete(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    Extension(config): Extension<AuthConfig>,
+    ApiPath(key): ApiPath<String>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageKeys) {
+        return r;
+    }
+    let key_id = Some(KeyId::of(&key)).filter(|key_id| config.grant_of(key_id).is_some()).or_else(|| config.find_key_id(&key));
+    let Some((key_id, before)) = key_id.and_then(|key_id| Some((key_id, config.remove_key(&key_id)?))) else {
+        return ApiError::not_found("API key not found").into_response();
+    };
+    state.audit_sink.emit(&AuditEvent::now(
+        auth.actor(),
+        AuditAction::KeyDelete,
+        Some(serde_json::json!({ "key_id": key_id, "before": before })),
+        "success",
+    ).with_context(&context));
+    (StatusCode::OK, Json(serde_json::json!({ "deleted": true }))).into_response()
+}
+
+async fn admin_instruments_list(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageInstruments) {
+        return r;
+    }
+    let guard = state.engine.lock().expect("lock");
+    let list: Vec<serde_json::Value> = guard
+        .list_instruments()
+        .into_iter()
+        .map(|(id, symbol)| admin_instrument_json(&guard, id, symbol))
+        .collect();
+    (StatusCode::OK, Json(list)).into_response()
+}
+
+/// Metadata and states of one instrument, as listed by `GET /admin/instruments`.
+fn admin_instrument_json(engine: &MultiEngine, id: InstrumentId, symbol: Option<String>) -> serde_json::Value {
+    let mut obj = serde_json::json!({
+        "instrument_id": id.0,
+        "tick_size": engine.tick_size(id),
+        "lot_size": engine.lot_size(id),
+        "price_band": engine.price_band(id),
+        "state": engine.instrument_state(id).map(|s| s.as_str()),
+        "market_state": engine.instrument_market_state(id).map(|s| s.as_str()),
+    });
+    if let Some(s) = symbol {
+        obj["symbol"] = serde_json::Value::String(s);
+    }
+    obj
+}
+
+/// Current [`admin_instrument_json`] of `id`, if it exists.
+fn find_admin_instrument(engine: &MultiEngine, id: InstrumentId) -> Option<serde_json::Value> {
+    let (_, symbol) = engine.list_instruments().into_iter().find(|(i, _)| *i == id)?;
+    Some(admin_instrument_json(engine, id, symbol))
+}
+
+/// Deserialize a field that may be absent (`None`), `null` (`Some(None)`), or set.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+}
+
+/// Fields of `PATCH /admin/instruments/:id`; absent fields are left as they are, `null` clears an optional one.
+#[derive(serde::Deserialize)]
+struct AdminInstrumentPatchBody {
+    #[serde(default, deserialize_with = "nullable")]
+    symbol: Option<Option<String>>,
+    tick_size: Option<rust_decimal::Decimal>,
+    #[serde(default, deserialize_with = "nullable")]
+    lot_size: Option<Option<rust_decimal::Decimal>>,
+    #[serde(default, deserialize_with = "nullable")]
+    price_band: Option<Option<PriceBand>>,
+    state: Option<InstrumentState>,
+    market_state: Option<MarketState>,
+}
+
+/// Change an instrument's symbol, tick size, lot size, price band, and/or states in one step. Returns the
+/// instrument as listed by `GET /admin/instruments`.
+async fn admin_instruments_patch(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    ApiPath(id): ApiPath<u64>,
+    ApiJson(body): ApiJson<AdminInstrumentPatchBody>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageInstruments) {
+        return r;
+    }
+    let update = InstrumentUpdate {
+        symbol: body.symbol,
+        tick_size: body.tick_size,
+        lot_size: body.lot_size,
+        price_band: body.price_band,
+        state: body.state,
+        market_state: body.market_state,
+    };
+    if update.is_empty() {
+        return ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::InvalidField, "no instrument field to change")
+            .into_response();
+    }
+    let instrument_id = InstrumentId(id);
+    let result = {
+        let mut guard = state.engine.lock().expect("lock");
+        let before = find_admin_instrument(&guard, instrument_id);
+        guard
+            .update_instrument(instrument_id, update)
+            .map(|()| (before, find_admin_instrument(&guard, instrument_id)))
+    };
+    match result {
+        Ok((before, after)) => {
+            state.audit_sink.emit(&AuditEvent::now(
+                actor,
+                AuditAction::InstrumentUpdate,
+                Some(serde_json::json!({ "instrument_id": id, "before": before, "after": after })),
+                "success",
+            ).with_context(&context));
+            persist_state(&state);
+            (StatusCode::OK, Json(after)).into_response()
+        }
+        Err(e) if e.contains("not found") => ApiError::not_found(e).into_response(),
+        Err(e) if e.starts_with("Cannot change tick size") => ApiError::conflict(e).with_field("tick_size").into_response(),
+        Err(e) if e.ends_with("is delisted") => ApiError::conflict(e).with_field("state").into_response(),
+        Err(e) if e.starts_with("Lot size") => ApiError::invalid_field("lot_size", e).into_response(),
+        Err(e) if e.starts_with("Price band") => ApiError::invalid_field("price_band", e).into_response(),
+        Err(e) => ApiError::invalid_field("tick_size", e).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[allow(dead_code)]
+struct AdminInstrumentsPostBody {
+    instrument_id: u64,
+    symbol: Option<String>,
+    tick_size: Option<rust_decimal::Decimal>,
+}
+
+async fn admin_instruments_post(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    ApiJson(body): ApiJson<AdminInstrumentsPostBody>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageInstruments) {
+        return r;
+    }
+    let mut guard = state.engine.lock().expect("lock");
+    let tick_size = body.tick_size.unwrap_or(DEFAULT_TICK_SIZE);
+    match guard.add_instrument_with_tick_size(InstrumentId(body.instrument_id), body.symbol, tick_size) {
+        Ok(()) => {
+            drop(guard);
+            persist_state(&state);
+            (StatusCode::CREATED, Json(serde_json::json!({ "instrument_id": body.instrument_id }))).into_response()
+        }
+        Err(e) if e.contains("already exists") => ApiError::conflict(e).with_field("instrument_id").into_response(),
+        Err(e) => ApiError::invalid_field("tick_size", e).into_response(),
+    }
+}
+
+async fn admin_instruments_delete(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    ApiPath(id): ApiPath<u64>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageInstruments) {
+        return r;
+    }
+    let mut guard = state.engine.lock().expect("lock");
+    match guard.remove_instrument(InstrumentId(id)) {
+        Ok(()) => {
+            drop(guard);
+            persist_state(&state);
+            (StatusCode::NO_CONTENT, ()).into_response()
+        }
+        Err(e) if e.contains("not found") => ApiError::not_found(e).into_response(),
+        Err(e) => ApiError::conflict(e).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AdminInstrumentStateBody {
+    /// Lifecycle state: Active, Suspended, or Delisted.
+    state: Option<String>,
+    /// The instrument's own market state: Open, Halted, or Closed.
+    market_state: Option<String>,
+}
+
+/// Lifecycle and market state of one instrument.
+async fn admin_instrument_state_get(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    ApiPath(id): ApiPath<u64>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    let guard = state.engine.lock().expect("lock");
+    let instrument_id = InstrumentId(id);
+    match (guard.instrument_state(instrument_id), guard.instrument_market_state(instrument_id)) {
+        (Some(lifecycle), Some(market_state)) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "instrument_id": id,
+                "state": lifecycle.as_str(),
+                "market_state": market_state.as_str(),
+            })),
+        )
+            .into_response(),
+        _ => ApiError::not_found(format!("Instrument {} not found", id)).into_response(),
+    }
+}
+
+/// Suspend, reactivate, or delist one instrument, and/or open, halt, or close it; other instruments keep trading.
+async fn admin_instrument_state_post(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    ApiPath(id): ApiPath<u64>,
+    ApiJson(body): ApiJson<AdminInstrumentStateBody>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    let lifecycle = match body.state.as_deref().map(|s| InstrumentState::from_str(s.trim())) {
+        Some(None) => {
+            return ApiError::invalid_field("state", "state must be Active, Suspended, or Delisted").into_response()
+        }
+        Some(parsed) => parsed,
+        None => None,
+    };
+    let market_state = match body.market_state.as_deref().map(|s| MarketState::from_str(s.trim())) {
+        Some(None) => {
+            return ApiError::invalid_field("market_state", "market_state must be Open, Halted, or Closed").into_response()
+        }
+        Some(parsed) => parsed,
+        None => None,
+    };
+    if lifecycle.is_none() && market_state.is_none() {
+        return ApiError::invalid_field("state", "state or market_state is required").into_response();
+    }
+    let instrument_id = InstrumentId(id);
+    let result = {
+        let mut guard = state.engine.lock().expect("lock");
+        let mut result = Ok(());
+        if let Some(lifecycle) = lifecycle {
+            result = guard.set_instrument_state(instrument_id, lifecycle);
+        }
+        if let (Ok(()), Some(market_state)) = (&result, market_state) {
+            result = guard.set_instrument_market_state(instrument_id, market_state);
+        }
+        result.map(|()| (guard.instrument_state(instrument_id), guard.instrument_market_state(instrument_id)))
+    };
+    match result {
+        Ok((lifecycle, market_state)) => {
+            let body = serde_json::json!({
+                "instrument_id": id,
+                "state": lifecycle.map(|s| s.as_str()),
+                "market_state": market_state.map(|s| s.as_str()),
+            });
+            let event = AuditEvent::now(actor, AuditAction::InstrumentStateChange, Some(body.clone()), "success");
+            state.audit_sink.emit(&event.with_context(&context));
+            persist_state(&state);
+            (StatusCode::OK, Json(body)).into_response()
+        }
+        Err(e) if e.contains("not found") => ApiError::not_found(e).into_response(),
+        Err(e) => ApiError::conflict(e).into_response(),
+    }
+}
+
+/// Full L3 book for one instrument: every resting order with price, queue position, and trader.
+async fn admin_book_orders(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    ApiPath(id): ApiPath<u64>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageInstruments) {
+        return r;
+    }
+    let guard = state.engine.lock().expect("lock");
+    match guard.book_orders(InstrumentId(id)) {
+        Some(orders) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "instrument_id": id, "orders": orders })),
+        )
+            .into_response(),
+        None => ApiError::not_found(format!("Instrument {} not found", id)).into_response(),
+    }
+}
+
+/// Admin-only: uncross an instrument's book by matching overlapping levels. Returns whether it is still
+/// crossed (self-trade-prevented overlaps remain) plus the trades and reports produced.
+async fn admin_book_uncross(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    ApiPath(id): ApiPath<u64>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageInstruments) {
+        return r;
+    }
+    let instrument_id = InstrumentId(id);
+    let mut guard = state.engine.lock().expect("lock");
+    let (trades, reports) = match guard.repair_crossed(instrument_id) {
+        Ok(result) => result,
+        Err(e) => return ApiError::not_found(e).into_response(),
+    };
+    let crossed = guard.book_is_crossed(instrument_id);
+    drop(guard);
+    if !trades.is_empty() {
+        persist_state(&state);
+    }
+    state.audit_sink.emit(&AuditEvent::now(
+        actor,
+        AuditAction::BookUncross,
+        Some(serde_json::json!({ "instrument_id": id, "trades": trades.len() })),
+        "success",
+    ).with_context(&context));
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "instrument_id": id, "crossed": crossed, "trades": trades, "reports": reports })),
+    )
+        .into_response()
+}
+
+async fn admin_config_get(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageConfig) {
+        return r;
+    }
+    let config = state.venue_config.lock().expect("lock").clone();
+    (StatusCode::OK, Json(config)).into_response()
+}
+
+/// Merge-patch the venue config (see [`VenueConfig::patched`]) and apply it live. 409 when the patch carries a
+/// stale `version`.
+async fn admin_config_patch(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    ApiJson(patch): ApiJson<serde_json::Value>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageConfig) {
+        return r;
+    }
+    let actor = auth.actor();
+    let current = state.venue_config.lock().expect("lock").clone();
+    let config = match current.patched(&patch) {
+        Ok(config) => config,
+        Err((field, message)) if field == "version" => {
+            return ApiError::conflict(message).with_field(field).into_response()
+        }
+        Err((field, message)) if field.is_empty() => {
+            return ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::InvalidField, message)
+                .into_response()
+        }
+        Err((field, message)) => return ApiError::invalid_field(field, message).into_response(),
+    };
+    state.set_venue_config(config.clone());
+    state.audit_sink.emit(&AuditEvent::now(
+        actor,
+        AuditAction::ConfigChange,
+        Some(serde_json::json!({ "version": config.version, "patch": patch, "before": current, "after": config })),
+        "success",
+    ).with_context(&context));
+    persist_state(&state);
+    (StatusCode::OK, Json(config)).into_response()
+}
+
+async fn admin_market_state_get(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    let guard = state.market_state.lock().expect("lock");
+    let s = guard.as_str();
+    (StatusCode::OK, Json(serde_json::json!({ "state": s }))).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct AdminMarketStatePostBody {
+    state: String,
+}
+
+async fn admin_market_state_post(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+    ApiJson(body): ApiJson<AdminMarketStatePostBody>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    let Some(new_state) = MarketState::from_str(body.state.trim()) else {
+        return ApiError::invalid_field("state", "state must be Open, Halted, or Closed").into_response();
+    };
+    *state.market_state.lock().expect("lock") = new_state;
+    publish_market_state(&state, new_state);
+    state.audit_sink.emit(&AuditEvent::now(
+        actor,
+        AuditAction::MarketStateChange,
+        Some(serde_json::json!({ "state": new_state.as_str() })),
+        "success",
+    ).with_context(&context));
+    persist_state(&state);
+    (StatusCode::OK, Json(serde_json::json!({ "state": new_state.as_str() }))).into_response()
+}
+
+async fn admin_emergency_halt(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    *state.market_state.lock().expect("lock") = MarketState::Halted;
+    publish_market_state(&state, MarketState::Halted);
+    state.audit_sink.emit(&AuditEvent::now(
+        actor,
+        AuditAction::EmergencyHalt,
+        Some(serde_json::json!({ "state": "Halted" })),
+        "success",
+    ).with_context(&context));
+    persist_state(&state);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "state": "Halted", "message": "emergency halt applied" })),
+    )
+        .into_response()
+}
+
+/// Graceful shutdown: new orders are rejected at once, WebSockets drain and close, and the process exits once
+/// state is saved. 202 while the shutdown is under way, also when it already was.
+async fn admin_shutdown(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    state.begin_shutdown(&actor, &context);
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "state": "Closed", "message": "shutting down" })),
+    )
+        .into_response()
+}
+
+/// The persistence backend, or 409 when the server runs without one.
+fn require_persistence(state: &AppState) -> Result<Arc<FilePersistence>, ApiError> {
+    state
+        .persistence
+        .clone()
+        .ok_or_else(|| ApiError::conflict("persistence is not configured (set PERSISTENCE_PATH)"))
+}
+
+/// Save state to the persistence file now, e.g. before maintenance. Returns the snapshot's metadata.
+async fn admin_snapshot(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageConfig) {
+        return r;
+    }
+    let persistence = match require_persistence(&state) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+    let metadata = match save_state(&state, &persistence) {
+        Ok((persisted, checksum)) => snapshot_metadata(&persistence, &persisted, checksum),
+        Err(e) => {
+            return ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal, format!("snapshot failed: {}", e))
+                .into_response()
+        }
+    };
+    state.audit_sink.emit(&AuditEvent::now(actor, AuditAction::Snapshot, Some(metadata.clone()), "success").with_context(&context));
+    (StatusCode::OK, Json(metadata)).into_response()
+}
+
+/// How startup recovery went: the snapshot loaded, the command-log entries replayed on top, and how long each
+/// took. 409 without persistence.
+async fn admin_recovery(Extension(auth): Extension<AuthUser>, Extension(state): Extension<AppState>) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageConfig) {
+        return r;
+    }
+    match &state.recovery {
+        Some(recovery) => (StatusCode::OK, Json(recovery.as_ref())).into_response(),
+        None => ApiError::conflict("persistence is not configured (set PERSISTENCE_PATH)").into_response(),
+    }
+}
+
+/// Promote this standby: stop following the primary and take over with the state replicated so far, saved
+/// at once. Returns the standby's status as of the promotion. 409 when the node is not a standby or was already
+/// promoted. Fence the old primary first: both accept orders from here on.
+async fn admin_failover(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::HaltMarket) {
+        return r;
+    }
+    let Some(standby) = state.standby.clone() else {
+        return ApiError::conflict("this node is not a standby (set REPLICATION_PRIMARY)").into_response();
+    };
+    // Waits for the replication thread to stop, which may be connecting.
+    let promoted = tokio::task::spawn_blocking(move || standby.promote()).await.expect("promote");
+    let status = match promoted {
+        Ok(status) => status,
+        Err(e) => return ApiError::conflict(e).into_response(),
+    };
+    persist_state(&state);
+    let resource = serde_json::json!(status);
+    state.audit_sink.emit(&AuditEvent::now(actor, AuditAction::Failover, Some(resource.clone()), "success").with_context(&context));
+    (StatusCode::OK, Json(resource)).into_response()
+}
+
+/// Default and maximum number of events returned by `GET /admin/audit`.
+const AUDIT_PAGE_LIMIT: usize = 1000;
+
+#[derive(serde::Deserialize)]
+struct AuditParams {
+    actor: Option<String>,
+    action: Option<AuditAction>,
+    /// Only events at or after this Unix second.
+    from: Option<u64>,
+    /// Only events at or before this Unix second.
+    to: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// The newest audit events (at most `limit`) by `actor`, of `action`, between `from` and `to`, newest first.
+/// 409 unless the audit trail is kept in a store (`AUDIT_STORE_PATH`).
+async fn admin_audit(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    ApiQuery(params): ApiQuery<AuditParams>,
+) -> Response {
+    if let Err(r) = auth::require_permission(&auth, Permission::ViewAudit) {
+        return r;
+    }
+    let query = AuditQuery {
+        actor: params.actor,
+        action: params.action,
+        from: params.from,
+        to: params.to,
+        limit: params.limit.unwrap_or(AUDIT_PAGE_LIMIT).clamp(1, AUDIT_PAGE_LIMIT),
+    };
+    match state.audit_sink.query(&query) {
+        Some(Ok(events)) => (StatusCode::OK, Json(serde_json::json!({ "events": events }))).into_response(),
+        Some(Err(e)) => {
+            ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal, format!("audit query failed: {}", e))
+                .into_response()
+        }
+        None => ApiError::conflict("audit store is not configured (set AUDIT_STORE_PATH)").into_response(),
+    }
+}
+
+/// Replace the engine state, market state, and venue config with the persistence file's. The file is checked
+/// in full first, so a bad file leaves the running engine as it was.
+async fn admin_restore(
+    Extension(auth): Extension<AuthUser>,
+    Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
+) -> Response {
+    let actor = auth.actor();
+    if let Err(r) = auth::require_permission(&auth, Permission::ManageConfig) {
+        return r;
+    }
+    let persistence = match require_persistence(&state) {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+    if state.shutdown.is_requested() {
+        return ApiError::conflict("server is shutting down").into_response();
+    }
+    let internal = |e: String| {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::Internal, format!("restore failed: {}", e))
+            .into_response()
+    };
+    let (persisted, checksum) = match persistence.load_with_checksum() {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => {
+            return ApiError::not_found(format!("no saved state at {}", persistence.path().display())).into_response()
+        }
+        Err(e) => return internal(e),
+    };
+    if let Err(e) = MultiEngine::new_with_instruments(vec![]).load_from_snapshot(persisted.engine.clone()) {
+        return internal(e);
+    }
+    let market_state = MarketState::from_str(persisted.market_state.trim()).unwrap_or(MarketState::Open);
+    let metadata = snapshot_metadata(&persistence, &persisted, checksum);
+    if let Err(e) = state.engine.lock().expect("lock").load_from_snapshot(persisted.engine) {
+        return internal(e);
+    }
+    *state.market_state.lock().expect("lock") = market_state;
+    publish_market_state(&state, market_state);
+    if let Some(config) = persisted.venue_config {
+        state.set_venue_config(config);
+    }
+    state.audit_sink.emit(&AuditEvent::now(actor, AuditAction::Restore, Some(metadata.clone()), "success").with_context(&context));
+    (StatusCode::OK, Json(metadata)).into_response()
+}
+
+#[derive(serde::Deserialize)]
+struct MarketDataParams {
+    /// When set, each snapshot carries [`BookStats`] over this many levels per side.
+    stats_levels: Option<usize>,
+    /// When true, snapshots carry every L2 level and book changes are sent as `delta` messages.
+    #[serde(default)]
+    deltas: bool,
+    /// When true, the socket also gets a `ticker` message (session statistics) per instrument on connect and
+    /// after every trade.
+    #[serde(default)]
+    ticker: bool,
+    /// When true, every trade is also sent as a `trade` message (time and sales).
+    #[serde(default)]
+    trades: bool,
+    /// Comma-separated instrument ids to subscribe to; omitted: every instrument the key is entitled to.
+    instrument_ids: Option<String>,
+    /// Instruments the socket carries, from `instrument_ids` and the key's entitlement; `None` for every one.
+    #[serde(skip)]
+    instruments: Option<Vec<InstrumentId>>,
+}
+
+impl MarketDataParams {
+    fn carries(&self, instrument_id: u64) -> bool {
+        auth::entitled(&self.instruments, InstrumentId(instrument_id))
+    }
+}
+
+/// WebSocket market-data: on connect send one snapshot (best bid/ask), then keep connection open. 403 if
+/// `instrument_ids` names an instrument the key is not entitled to.
+async fn ws_market_data(
+    Extension(state): Extension<AppState>,
+    Extension(auth): Extension<AuthUser>,
+    Extension(config): Extension<AuthConfig>,
+    ApiQuery(mut params): ApiQuery<MarketDataParams>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    params.instruments = match params.instrument_ids.as_deref() {
+        Some(ids) => {
+            let mut instruments = Vec::new();
+            for id in ids.split(',') {
+                let Ok(id) = id.trim().parse() else {
+                    return ApiError::invalid_field("instrument_ids", format!("invalid instrument id {:?}", id)).into_response();
+                };
+                if let Err(r) = auth::require_instrument(&auth, InstrumentId(id), "instrument_ids") {
+                    return r;
+                }
+                instruments.push(InstrumentId(id));
+            }
+            Some(instruments)
+        }
+        None => auth.instruments.clone(),
+    };
+    let identity = SocketAuth::new(config, auth);
+    upgrade.on_upgrade(move |socket| handle_market_data_socket(state, socket, params, identity))
+}
+
+#[derive(serde::Serialize)]
+struct MarketDataSnapshot {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    instrument_id: u64,
+    best_bid: Option<rust_decimal::Decimal>,
+    best_ask: Option<rust_decimal::Decimal>,
+    checksum: u32,
+    /// Engine sequence number of the book's last change.
+    seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<BookStats>,
+    /// Aggregated levels, best first: every level in delta mode (the base the deltas apply to), otherwise
+    /// the configured top N levels (omitted when not configured).
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    levels: Option<BookLevels>,
+}
+
+/// Incremental L2 update sent in delta mode; `checksum` is the book's checksum after applying it.
+#[derive(serde::Serialize)]
+struct MarketDataDelta {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    instrument_id: u64,
+    #[serde(flatten)]
+    delta: BookDelta,
+    checksum: u32,
+    seq: u64,
+    /// `seq` of the previous snapshot or delta sent for this instrument on this connection; a client whose last
+    /// seen `seq` differs has missed an update and should request a snapshot.
+    prev_seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<BookStats>,
+}
+
+/// Message a market-data client can send.
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MarketDataRequest {
+    /// Resend a snapshot (every level in delta mode) of one instrument, or of all when `instrument_id` is
+    /// omitted; deltas then continue from it.
+    Snapshot { instrument_id: Option<u64> },
+}
+
+/// Session statistics of one instrument, sent in ticker mode.
+#[derive(serde::Serialize)]
+struct MarketDataTicker {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    #[serde(flatten)]
+    stats: InstrumentStats,
+}
+
+/// One print on the trade tape, sent in trades mode. Order ids are left out.
+#[derive(serde::Serialize)]
+struct MarketDataTrade {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    trade_id: u64,
+    instrument_id: u64,
+    price: rust_decimal::Decimal,
+    quantity: rust_decimal::Decimal,
+    aggressor_side: crate::Side,
+    timestamp: u64,
+    seq: u64,
+}
+
+impl From<&Trade> for MarketDataTrade {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            msg_type: "trade",
+            trade_id: trade.trade_id.0,
+            instrument_id: trade.instrument_id.0,
+            price: trade.price,
+            quantity: trade.quantity,
+            aggressor_side: trade.aggressor_side,
+            timestamp: trade.timestamp,
+            seq: trade.seq,
+        }
+    }
+}
+
+/// Trades after engine sequence number `seq` from the event journal, for a trades-mode client that fell behind.
+/// Empty if the journal no longer covers the gap (the client can page back with `GET /trades`).
+fn missed_trades(state: &AppState, seq: u64) -> Vec<Trade> {
+    let events = state.engine.lock().expect("lock").events_since(seq).unwrap_or_default();
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            EngineEvent::Trade(trade) => Some(trade),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Send trade messages newer than `last_seq` for the instruments the socket carries. Returns false if the
+/// socket closed.
+async fn send_trades(socket: &mut WebSocket, params: &MarketDataParams, last_seq: &mut u64, trades: Vec<Trade>) -> bool {
+    for trade in &trades {
+        if trade.seq <= *last_seq {
+            continue;
+        }
+        *last_seq = trade.seq;
+        if !params.carries(trade.instrument_id.0) {
+            continue;
+        }
+        if let Ok(json) = serde_json::to_string(&MarketDataTrade::from(trade)) {
+            if socket.send(Message::Text(json)).await.is_err() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Current session statistics of every instrument the socket carries.
+fn market_data_tickers(state: &AppState, params: &MarketDataParams) -> Vec<InstrumentStats> {
+    let guard = state.engine.lock().expect("lock");
+    guard
+        .instruments()
+        .into_iter()
+        .filter(|id| params.carries(id.0))
+        .filter_map(|id| guard.stats_for(id))
+        .collect()
+}
+
+/// One snapshot message per instrument the socket carries.
+fn market_data_snapshots(state: &AppState, params: &MarketDataParams) -> Vec<MarketDataSnapshot> {
+    let guard = state.engine.lock().expect("lock");
+    guard
+        .instruments()
+        .into_iter()
+        .filter(|id| params.carries(id.0))
+        .filter_map(|id| {
+            let book = guard.book_snapshot_for(id)?;
+            Some(MarketDataSnapshot {
+                msg_type: "snapshot",
+                instrument_id: book.instrument_id.0,
+                best_bid: book.best_bid,
+                best_ask: book.best_ask,
+                checksum: book.checksum,
+                seq: book.seq,
+                stats: params.stats_levels.and_then(|levels| guard.book_stats_for(id, levels)),
+                levels: if params.deltas { guard.book_levels_for(id) } else { book.levels },
+            })
+        })
+        .collect()
+}
+
+/// Sequence numbers already sent on one market-data socket, so replayed and broadcast updates are not sent twice
+/// or out of order.
+#[derive(Default)]
+struct SentSeqs {
+    by_instrument: HashMap<u64, u64>,
+}
+
+impl SentSeqs {
+    /// Sequence number of the last update sent for `instrument_id` (0 if none).
+    fn last_for(&self, instrument_id: u64) -> u64 {
+        self.by_instrument.get(&instrument_id).copied().unwrap_or(0)
+    }
+
+    /// Record a snapshot sent on request, even if it is not newer than the last update.
+    fn reset(&mut self, instrument_id: u64, seq: u64) {
+        self.by_instrument.insert(instrument_id, seq);
+    }
+
+    /// Whether an update for `instrument_id` at `seq` is newer than what was sent; records it if so.
+    fn advance(&mut self, instrument_id: u64, seq: u64) -> bool {
+        let sent = self.by_instrument.entry(instrument_id).or_insert(0);
+        if seq != 0 && seq <= *sent {
+            return false;
+        }
+        *sent = seq;
+        true
+    }
+}
+
+/// Send ticker messages for statistics that changed since the last ones sent (by the sequence number of their
+/// last trade). Returns false if the socket closed.
+async fn send_tickers(socket: &mut WebSocket, sent: &mut HashMap<u64, u64>, tickers: Vec<InstrumentStats>) -> bool {
+    for stats in tickers {
+        if sent.get(&stats.instrument_id.0).is_some_and(|&last| stats.seq <= last) {
+            continue;
+        }
+        sent.insert(stats.instrument_id.0, stats.seq);
+        let ticker = MarketDataTicker { msg_type: "ticker", stats };
+        if let Ok(json) = serde_json::to_string(&ticker) {
+            if socket.send(Message::Text(json)).await.is_err() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Send `snapshots` that are newer than what the socket already has. Returns false if the socket closed.
+async fn send_snapshots(socket: &mut WebSocket, sent: &mut SentSeqs, snapshots: Vec<MarketDataSnapshot>) -> bool {
+    for snapshot in snapshots {
+        if !sent.advance(snapshot.instrument_id, snapshot.seq) {
+            continue;
+        }
+        if let Ok(json) = serde_json::to_string(&snapshot) {
+            if socket.send(Message::Text(json)).await.is_err() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Book updates received for one market-data socket but not yet sent, conflated to the latest per instrument.
+#[derive(Default)]
+struct PendingBooks {
+    updates: BTreeMap<u64, BookUpdate>,
+    /// The broadcast channel dropped updates, so every book is resent from a fresh snapshot instead.
+    resync: bool,
+    /// Updates folded into a later one or dropped.
+    conflated: u64,
+}
+
+impl PendingBooks {
+    /// Queue `update`, replacing a pending one for the same instrument (their deltas are merged).
+    fn push(&mut self, update: BookUpdate) {
+        match self.updates.entry(update.instrument_id) {
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(update);
+            }
+            btree_map::Entry::Occupied(mut entry) => {
+                let mut delta = std::mem::take(&mut entry.get_mut().delta);
+                delta.merge(update.delta);
+                entry.insert(BookUpdate { delta, ..update });
+                self.conflated += 1;
+            }
+        }
+    }
+
+    fn lagged(&mut self, missed: u64) {
+        self.resync = true;
+        self.conflated += missed;
+    }
+
+    /// Queue every update already waiting on `rx`.
+    fn drain(&mut self, rx: &mut broadcast::Receiver<BookUpdate>) {
+        loop {
+            match rx.try_recv() {
+                Ok(update) => self.push(update),
+                Err(broadcast::error::TryRecvError::Lagged(missed)) => self.lagged(missed),
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+/// Await `send`, failing it if it takes longer than `limit`.
+async fn within(limit: Option<Duration>, send: impl std::future::Future<Output = bool>) -> bool {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, send).await.unwrap_or(false),
+        None => send.await,
+    }
+}
+
+/// Send the pending book updates, with their tickers in ticker mode; after a resync, current snapshots of every
+/// book instead. Returns false if the socket closed.
+async fn flush_books(
+    state: &AppState,
+    socket: &mut WebSocket,
+    sent: &mut SentSeqs,
+    sent_tickers: &mut HashMap<u64, u64>,
+    params: &MarketDataParams,
+    pending: PendingBooks,
+) -> bool {
+    if pending.resync {
+        return send_snapshots(socket, sent, market_data_snapshots(state, params)).await
+            && (!params.ticker || send_tickers(socket, sent_tickers, market_data_tickers(state, params)).await);
+    }
+    for update in pending.updates.into_values() {
+        if !params.carries(update.instrument_id) || params.deltas && update.delta.is_empty() {
+            continue;
+        }
+        let prev_seq = sent.last_for(update.instrument_id);
+        if !sent.advance(update.instrument_id, update.seq) {
+            continue;
+        }
+        let stats = params.stats_levels.and_then(|levels| {
+            let guard = state.engine.lock().expect("lock");
+            guard.book_stats_for(InstrumentId(update.instrument_id), levels)
+        });
+        let json = if params.deltas {
+            serde_json::to_string(&MarketDataDelta {
+                msg_type: "delta",
+                instrument_id: update.instrument_id,
+                delta: update.delta,
+                checksum: update.checksum,
+                seq: update.seq,
+                prev_seq,
+                stats,
+            })
+        } else {
+            serde_json::to_string(&MarketDataSnapshot {
+                msg_type: "snapshot",
+                instrument_id: update.instrument_id,
+                best_bid: update.best_bid,
+                best_ask: update.best_ask,
+                checksum: update.checksum,
+                seq: update.seq,
+                stats,
+                levels: update.levels,
+            })
+        };
+        if let Ok(json) = json {
+            if socket.send(Message::Text(json)).await.is_err() {
+                return false;
+            }
+        }
+        if params.ticker && !send_tickers(socket, sent_tickers, update.ticker.into_iter().collect()).await {
+            return false;
+        }
+    }
+    true
+}
+
+async fn handle_market_data_socket(state: AppState, mut socket: WebSocket, params: MarketDataParams, mut identity: SocketAuth) {
+    let _open = state.shutdown.track();
+    let mut sent = SentSeqs::default();
+    let mut sent_tickers = HashMap::new();
+    let mut events = state.subscribe_events();
+    if !send_snapshots(&mut socket, &mut sent, market_data_snapshots(&state, &params)).await {
+        return;
+    }
+    if params.ticker && !send_tickers(&mut socket, &mut sent_tickers, market_data_tickers(&state, &params)).await {
+        return;
+    }
+
+    let mut rx = state.broadcast_tx.subscribe();
+    let mut last_trade_seq = 0;
+    let policy = state.slow_consumer;
+    // Book updates conflated or dropped since the client last kept up.
+    let mut backlog = 0;
+    let mut heartbeat = Heartbeat::new(state.heartbeat);
+    loop {
+        tokio::select! {
+            _ = state.shutdown.requested() => {
+                if params.trades {
+                    let trades = missed_trades(&state, last_trade_seq);
+                    if !within(policy.send_timeout, send_trades(&mut socket, &params, &mut last_trade_seq, trades)).await {
+                        break;
+                    }
+                }
+                let mut pending = PendingBooks::default();
+                pending.drain(&mut rx);
+                let flushed = flush_books(&state, &mut socket, &mut sent, &mut sent_tickers, &params, pending);
+                if within(policy.send_timeout, flushed).await {
+                    let _ = within(policy.send_timeout, async { socket.send(shutdown_close()).await.is_ok() }).await;
+                }
+                break;
+            }
+            _ = heartbeat.tick() => {
+                if !within(policy.send_timeout, heartbeat.check(&mut socket)).await {
+                    break;
+                }
+            }
+            _ = identity.tick() => {
+                if !within(policy.send_timeout, identity.check(&mut socket)).await {
+                    break;
+                }
+            }
+            res = events.recv(), if params.trades => {
+                let trades = match res {
+                    Ok(EngineEvent::Trade(trade)) => vec![trade],
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(_)) => missed_trades(&state, last_trade_seq),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !within(policy.send_timeout, send_trades(&mut socket, &params, &mut last_trade_seq, trades)).await {
+                    break;
+                }
+            }
+            res = rx.recv() => {
+                let mut pending = PendingBooks::default();
+                match res {
+                    Ok(update) => pending.push(update),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => pending.lagged(missed),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+                pending.drain(&mut rx);
+                backlog = if pending.conflated == 0 { 0 } else { backlog + pending.conflated };
+                if policy.max_conflated.is_some_and(|max| backlog > max) {
+                    log::warn!("closing market-data socket: {} book updates conflated", backlog);
+                    let close = Message::Close(Some(CloseFrame {
+                        code: close_code::POLICY,
+                        reason: "slow consumer".into(),
+                    }));
+                    let _ = within(policy.send_timeout, async { socket.send(close).await.is_ok() }).await;
+                    break;
+                }
+                let flushed = flush_books(&state, &mut socket, &mut sent, &mut sent_tickers, &params, pending);
+                if !within(policy.send_timeout, flushed).await {
+                    break;
+                }
+            }
+            msg = socket.recv() => {
+                heartbeat.seen();
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if !answer_market_data_request(&state, &mut socket, &mut sent, &params, &text).await {
+                            break;
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    _ => break,
+                }
+            }
+        }
+    }
+}
+
+/// Handle one client message: a snapshot request is answered with fresh snapshots, anything else (or a request
+/// for an instrument the socket does not carry) with an `error` message. Returns false if the socket closed.
+async fn answer_market_data_request(
+    state: &AppState,
+    socket: &mut WebSocket,
+    sent: &mut SentSeqs,
+    params: &MarketDataParams,
+    text: &str,
+) -> bool {
+    let snapshots = match serde_json::from_str::<MarketDataRequest>(text) {
+        Ok(MarketDataRequest::Snapshot { instrument_id: Some(id) }) if !params.carries(id) => {
+            let message = format!("instrument {} is not subscribed or not entitled", id);
+            Err(ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, message).with_field("instrument_id"))
+        }
+        Ok(MarketDataRequest::Snapshot { instrument_id }) => {
+            let snapshots: Vec<MarketDataSnapshot> = market_data_snapshots(state, params)
+                .into_iter()
+                .filter(|s| instrument_id.is_none_or(|id| s.instrument_id == id))
+                .collect();
+            match instrument_id {
+                Some(id) if snapshots.is_empty() => Err(ApiError::not_found(format!("Instrument {} not found", id))),
+                _ => Ok(snapshots),
+            }
+        }
+        Err(e) => Err(ApiError::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidJson, e.to_string())),
+    };
+    let messages = match snapshots {
+        Ok(snapshots) => snapshots
+            .iter()
+            .filter_map(|snapshot| {
+                sent.reset(snapshot.instrument_id, snapshot.seq);
+                serde_json::to_string(snapshot).ok()
+            })
+            .collect(),
+        Err(error) => {
+            let mut json = serde_json::to_value(&error).unwrap_or_default();
+            json["type"] = "error".into();
+            vec![json.to_string()]
+        }
+    };
+    for json in messages {
+        if socket.send(Message::Text(json)).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+#[derive(serde::Deserialize)]
+struct ExecutionStreamParams {
+    /// Trader to stream when auth is disabled; keys bound to a trader always get their own.
+    trader_id: Option<u64>,
+}
+
+/// Private WebSocket stream of one trader's execution reports and fills. The trader comes from the API key's
+/// binding (`key:trader:7` in `API_KEYS`), or from `trader_id` when auth is disabled. 403 if the key is not
+/// bound to a trader.
+async fn ws_executions(
+    Extension(state): Extension<AppState>,
+    Extension(auth): Extension<AuthUser>,
+    Extension(config): Extension<AuthConfig>,
+    ApiQuery(params): ApiQuery<ExecutionStreamParams>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let trader_id = match (auth.trader_id, &auth.key_id, params.trader_id) {
+        (Some(trader_id), _, _) => trader_id,
+        (None, None, Some(id)) => TraderId(id),
+        (None, None, None) => {
+            return ApiError::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidField, "trader_id is required")
+                .with_field("trader_id")
+                .into_response()
+        }
+        (None, Some(_), _) => {
+            return ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, "API key is not bound to a trader")
+                .into_response()
+        }
+    };
+    let identity = SocketAuth::new(config, auth);
+    upgrade.on_upgrade(move |socket| handle_execution_socket(state, socket, trader_id, identity))
+}
+
+/// One of the trader's execution reports, sent on `/ws/executions`.
+#[derive(serde::Serialize)]
+struct PrivateReport<'a> {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    #[serde(flatten)]
+    report: &'a ExecutionReport,
 }
 
-/// Builds the REST/WebSocket router with a new state (convenience for tests). Returns `Router<()>` for `axum::serve`.
-pub fn create_router(instrument_id: InstrumentId) -> Router<()> {
-    create_router_with_state(create_app_state(instrument_id))
+/// One of the trader's fills: a trade seen from the side of their order.
+#[derive(serde::Serialize)]
+struct PrivateFill {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    trade_id: u64,
+    instrument_id: u64,
+    order_id: u64,
+    side: crate::Side,
+    price: rust_decimal::Decimal,
+    quantity: rust_decimal::Decimal,
+    /// Whether the trader's order was the aggressor (took liquidity).
+    aggressor: bool,
+    timestamp: u64,
+    seq: u64,
 }
 
-async fn health() -> impl IntoResponse {
-    (StatusCode::OK, "ok")
+/// One of the trader's resting orders canceled by request (cancels produce no execution report).
+#[derive(serde::Serialize)]
+struct PrivateCanceled {
+    #[serde(rename = "type")]
+    msg_type: &'static str,
+    order_id: u64,
+    instrument_id: u64,
 }
 
-/// Admin-only: returns 200 with status. Requires Admin or Operator role (403 for Trader).
-async fn admin_status(Extension(auth): Extension<AuthUser>) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .map(|()| (StatusCode::OK, Json(serde_json::json!({ "status": "ok" }))).into_response())
-        .unwrap_or_else(|r| r)
+/// Per-connection state of `/ws/executions`: the trader's orders that can still trade or be canceled, and the
+/// last trade, report, and sequence number sent.
+struct TraderStream {
+    trader_id: TraderId,
+    orders: HashSet<OrderId>,
+    last_seq: u64,
+    last_trade_id: u64,
+    last_exec_id: u64,
 }
 
-// --- Admin API (US-008, US-009, US-011, US-012) ---
+impl TraderStream {
+    fn new(trader_id: TraderId) -> Self {
+        Self {
+            trader_id,
+            orders: HashSet::new(),
+            last_seq: 0,
+            last_trade_id: 0,
+            last_exec_id: 0,
+        }
+    }
 
-async fn admin_instruments_list(
-    Extension(auth): Extension<AuthUser>,
-    Extension(state): Extension<AppState>,
-) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let guard = state.engine.lock().expect("lock");
-            let list: Vec<serde_json::Value> = guard
-                .list_instruments()
-                .into_iter()
-                .map(|(id, symbol)| {
-                    let mut obj = serde_json::json!({ "instrument_id": id.0 });
-                    if let Some(s) = symbol {
-                        obj["symbol"] = serde_json::Value::String(s);
-                    }
-                    obj
-                })
-                .collect();
-            Ok((StatusCode::OK, Json(list)).into_response())
-        })
-        .unwrap_or_else(|r| r)
+    /// Start tracking the trader's live orders.
+    fn track_open_orders(&mut self, engine: &MultiEngine) {
+        self.orders.extend(engine.open_orders(self.trader_id).into_iter().map(|o| o.order_id));
+    }
+
+    /// Fill messages for the trader's side(s) of `trade`; `owned` says whether an order is the trader's.
+    fn fills(&mut self, trade: &Trade, owned: impl Fn(OrderId) -> bool) -> Vec<(u64, String)> {
+        let mut out = Vec::new();
+        for (order_id, side) in [(trade.buy_order_id, crate::Side::Buy), (trade.sell_order_id, crate::Side::Sell)] {
+            if !owned(order_id) {
+                continue;
+            }
+            let fill = PrivateFill {
+                msg_type: "fill",
+                trade_id: trade.trade_id.0,
+                instrument_id: trade.instrument_id.0,
+                order_id: order_id.0,
+                side,
+                price: trade.price,
+                quantity: trade.quantity,
+                aggressor: trade.aggressor_side == side,
+                timestamp: trade.timestamp,
+                seq: trade.seq,
+            };
+            out.extend(serde_json::to_string(&fill).ok().map(|json| (trade.seq, json)));
+        }
+        if !out.is_empty() {
+            self.last_trade_id = self.last_trade_id.max(trade.trade_id.0);
+        }
+        out
+    }
+
+    /// The report message; stops tracking orders it finishes.
+    fn report(&mut self, report: &ExecutionReport) -> Option<(u64, String)> {
+        if let Some(orig) = report.orig_order_id.filter(|&orig| orig != report.order_id) {
+            self.orders.remove(&orig);
+        }
+        if matches!(report.order_status, OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected) {
+            self.orders.remove(&report.order_id);
+        }
+        self.last_exec_id = self.last_exec_id.max(report.exec_id.0);
+        let message = PrivateReport {
+            msg_type: "execution_report",
+            report,
+        };
+        serde_json::to_string(&message).ok().map(|json| (report.seq, json))
+    }
+
+    /// Messages for `event` if it concerns the trader's orders and was not sent yet.
+    fn on_event(&mut self, event: &EngineEvent) -> Vec<(u64, String)> {
+        if event.seq().is_some_and(|seq| seq <= self.last_seq) {
+            return Vec::new();
+        }
+        let out = self.messages(event);
+        self.last_seq = out.iter().map(|(seq, _)| *seq).fold(self.last_seq, u64::max);
+        out
+    }
+
+    fn messages(&mut self, event: &EngineEvent) -> Vec<(u64, String)> {
+        match event {
+            EngineEvent::OrderAccepted { order } if order.trader_id == self.trader_id => {
+                self.orders.insert(order.order_id);
+                Vec::new()
+            }
+            EngineEvent::Trade(trade) => {
+                let orders = &self.orders;
+                let owned: Vec<OrderId> =
+                    [trade.buy_order_id, trade.sell_order_id].into_iter().filter(|id| orders.contains(id)).collect();
+                self.fills(trade, |id| owned.contains(&id))
+            }
+            EngineEvent::Report(report) if self.orders.contains(&report.order_id) => {
+                self.report(report).into_iter().collect()
+            }
+            EngineEvent::Canceled { order_id, instrument_id } if self.orders.remove(order_id) => {
+                let canceled = PrivateCanceled {
+                    msg_type: "canceled",
+                    order_id: order_id.0,
+                    instrument_id: instrument_id.0,
+                };
+                serde_json::to_string(&canceled).ok().map(|json| (0, json)).into_iter().collect()
+            }
+            EngineEvent::Expired { order_id, .. } => {
+                self.orders.remove(order_id);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Fills and reports a lagged connection missed, from the engine's retained trade and execution history, in
+    /// sequence order; then re-read the trader's live orders. Cancels missed while lagging are not replayed.
+    fn catch_up(&mut self, state: &AppState) -> Vec<(u64, String)> {
+        let guard = state.engine.lock().expect("lock");
+        let query = |cursor| HistoryQuery {
+            trader_id: Some(self.trader_id),
+            cursor,
+            ..HistoryQuery::latest(HISTORY_PAGE_LIMIT)
+        };
+        let mut trades = Vec::new();
+        let mut cursor = HistoryCursor::After(self.last_trade_id);
+        loop {
+            let page = guard.trade_history(&query(cursor));
+            trades.extend(page.items);
+            match page.next_cursor {
+                Some(next) => cursor = HistoryCursor::After(next),
+                None => break,
+            }
+        }
+        let mut reports = Vec::new();
+        let mut cursor = HistoryCursor::After(self.last_exec_id);
+        loop {
+            let page = guard.execution_history(&query(cursor));
+            reports.extend(page.items);
+            match page.next_cursor {
+                Some(next) => cursor = HistoryCursor::After(next),
+                None => break,
+            }
+        }
+        let trader_id = self.trader_id;
+        let owned = |id: OrderId| guard.order_status(id).is_some_and(|o| o.trader_id == trader_id);
+        let since = self.last_seq;
+        let mut out = Vec::new();
+        for trade in trades.iter().filter(|t| t.seq > since) {
+            out.extend(self.fills(trade, owned));
+        }
+        for report in reports.iter().filter(|r| r.seq > since) {
+            out.extend(self.report(report));
+        }
+        out.sort_by_key(|(seq, _)| *seq);
+        self.last_seq = out.last().map_or(self.last_seq, |(seq, _)| *seq);
+        self.orders.clear();
+        self.track_open_orders(&guard);
+        out
+    }
 }
 
-#[derive(serde::Deserialize)]
-#[allow(dead_code)]
-struct AdminInstrumentsPostBody {
-    instrument_id: u64,
-    symbol: Option<String>,
+async fn send_private(socket: &mut WebSocket, messages: Vec<(u64, String)>) -> bool {
+    for (_, json) in messages {
+        if socket.send(Message::Text(json)).await.is_err() {
+            return false;
+        }
+    }
+    true
 }
 
-async fn admin_instruments_post(
-    Extension(auth): Extension<AuthUser>,
-    Extension(state): Extension<AppState>,
-    Json(body): Json<AdminInstrumentsPostBody>,
-) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let mut guard = state.engine.lock().expect("lock");
-            match guard.add_instrument(InstrumentId(body.instrument_id), body.symbol) {
-                Ok(()) => {
-                    drop(guard);
-                    persist_state(&state);
-                    Ok((StatusCode::CREATED, Json(serde_json::json!({ "instrument_id": body.instrument_id }))).into_response())
+async fn handle_execution_socket(state: AppState, mut socket: WebSocket, trader_id: TraderId, mut identity: SocketAuth) {
+    let _open = state.shutdown.track();
+    let mut events = state.subscribe_events();
+    let mut stream = TraderStream::new(trader_id);
+    stream.track_open_orders(&state.engine.lock().expect("lock"));
+    let mut heartbeat = Heartbeat::new(state.heartbeat);
+    loop {
+        tokio::select! {
+            _ = state.shutdown.requested() => {
+                let mut messages = Vec::new();
+                loop {
+                    match events.try_recv() {
+                        Ok(event) => messages.extend(stream.on_event(&event)),
+                        Err(broadcast::error::TryRecvError::Lagged(_)) => messages.extend(stream.catch_up(&state)),
+                        Err(_) => break,
+                    }
                 }
-                Err(e) => {
-                    let status = if e.contains("already exists") {
-                        StatusCode::CONFLICT
-                    } else {
-                        StatusCode::BAD_REQUEST
-                    };
-                    Err((status, Json(serde_json::json!({ "error": e }))).into_response())
+                if send_private(&mut socket, messages).await {
+                    let _ = socket.send(shutdown_close()).await;
                 }
+                break;
             }
-        })
-        .unwrap_or_else(|r| r)
-}
-
-async fn admin_instruments_delete(
-    Extension(auth): Extension<AuthUser>,
-    Extension(state): Extension<AppState>,
-    Path(id): Path<u64>,
-) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let mut guard = state.engine.lock().expect("lock");
-            match guard.remove_instrument(InstrumentId(id)) {
-                Ok(()) => {
-                    drop(guard);
-                    persist_state(&state);
-                    Ok((StatusCode::NO_CONTENT, ()).into_response())
+            _ = heartbeat.tick() => {
+                if !heartbeat.check(&mut socket).await {
+                    break;
                 }
-                Err(e) => {
-                    let status = if e.contains("not found") {
-                        StatusCode::NOT_FOUND
-                    } else if e.contains("resting orders") {
-                        StatusCode::CONFLICT
-                    } else {
-                        StatusCode::BAD_REQUEST
-                    };
-                    Err((status, Json(serde_json::json!({ "error": e }))).into_response())
+            }
+            _ = identity.tick() => {
+                if !identity.check(&mut socket).await {
+                    break;
                 }
             }
-        })
-        .unwrap_or_else(|r| r)
+            res = events.recv() => {
+                let messages = match res {
+                    Ok(event) => stream.on_event(&event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => stream.catch_up(&state),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !send_private(&mut socket, messages).await {
+                    break;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(_)) => heartbeat.seen(),
+                _ => break,
+            },
+        }
+    }
 }
 
-async fn admin_config_get(
-    Extension(auth): Extension<AuthUser>,
+#[derive(serde::Deserialize)]
+struct CancelRequest {
+    order_id: u64,
+}
+
+/// Order-status query: status, fills, and (while resting) queue position of an accepted order, or 404 if unknown.
+async fn get_order(
     Extension(state): Extension<AppState>,
+    ApiPath(id): ApiPath<u64>,
 ) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let guard = state.admin_config.lock().expect("lock");
-            let config: serde_json::Map<String, serde_json::Value> = guard.clone().into_iter().collect();
-            Ok((StatusCode::OK, Json(serde_json::Value::Object(config))).into_response())
-        })
-        .unwrap_or_else(|r| r)
+    let guard = state.engine.lock().expect("lock");
+    match guard.order_status(OrderId(id)) {
+        Some(view) => (StatusCode::OK, Json(view)).into_response(),
+        None => ApiError::not_found(format!("Order {} not found", id)).into_response(),
+    }
 }
 
-async fn admin_config_patch(
-    Extension(auth): Extension<AuthUser>,
+#[derive(serde::Deserialize)]
+struct OpenOrdersParams {
+    trader_id: u64,
+}
+
+/// Open orders of one trader across all instruments, for reconciling working orders after a reconnect.
+async fn list_open_orders(
     Extension(state): Extension<AppState>,
-    Json(patch): Json<serde_json::Value>,
-) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let obj = patch.as_object().ok_or_else(|| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({ "error": "config must be a JSON object" })),
-                )
-                    .into_response()
-            })?;
-            let mut guard = state.admin_config.lock().expect("lock");
-            for (k, v) in obj {
-                guard.insert(k.clone(), v.clone());
-            }
-            Ok((StatusCode::OK, Json(serde_json::json!({ "ok": true }))).into_response())
-        })
-        .unwrap_or_else(|r| r)
+    ApiQuery(params): ApiQuery<OpenOrdersParams>,
+) -> Response {
+    let orders = state.engine.lock().expect("lock").open_orders(TraderId(params.trader_id));
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "trader_id": params.trader_id, "orders": orders })),
+    )
+        .into_response()
 }
 
-async fn admin_market_state_get(
-    Extension(auth): Extension<AuthUser>,
+/// Levels per side returned by `GET /book/:id` when `levels` is not given, and the most it returns.
+const BOOK_DEPTH_DEFAULT_LEVELS: usize = 10;
+const BOOK_DEPTH_MAX_LEVELS: usize = 1000;
+
+#[derive(serde::Deserialize)]
+struct BookDepthParams {
+    levels: Option<usize>,
+}
+
+/// L2 depth for one instrument without a WebSocket: the best `levels` aggregated levels per side, top of book,
+/// spread, checksum, and the last retained trade. 404 if the instrument is unknown, 403 if the key is not
+/// entitled to it.
+async fn get_book_depth(
     Extension(state): Extension<AppState>,
+    Extension(auth): Extension<AuthUser>,
+    ApiPath(id): ApiPath<u64>,
+    ApiQuery(params): ApiQuery<BookDepthParams>,
 ) -> Response {
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let guard = state.market_state.lock().expect("lock");
-            let s = guard.as_str();
-            Ok((StatusCode::OK, Json(serde_json::json!({ "state": s }))).into_response())
+    if let Err(r) = auth::require_instrument(&auth, InstrumentId(id), "id") {
+        return r;
+    }
+    let levels = params.levels.unwrap_or(BOOK_DEPTH_DEFAULT_LEVELS).min(BOOK_DEPTH_MAX_LEVELS);
+    let instrument_id = InstrumentId(id);
+    let guard = state.engine.lock().expect("lock");
+    let (Some(snapshot), Some(mut depth)) = (guard.book_snapshot_for(instrument_id), guard.book_levels_for(instrument_id))
+    else {
+        return ApiError::not_found(format!("Instrument {} not found", id)).into_response();
+    };
+    let last_trade = guard.trades_for_instrument(instrument_id, 1).pop();
+    drop(guard);
+    depth.bids.truncate(levels);
+    depth.asks.truncate(levels);
+    let spread = snapshot.best_bid.zip(snapshot.best_ask).map(|(bid, ask)| ask - bid);
+    let last_trade = last_trade.map(|trade| {
+        serde_json::json!({
+            "trade_id": trade.trade_id.0,
+            "price": trade.price,
+            "quantity": trade.quantity,
+            "aggressor_side": trade.aggressor_side,
+            "timestamp": trade.timestamp,
         })
-        .unwrap_or_else(|r| r)
+    });
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "instrument_id": id,
+            "best_bid": snapshot.best_bid,
+            "best_ask": snapshot.best_ask,
+            "spread": spread,
+            "bids": depth.bids,
+            "asks": depth.asks,
+            "checksum": snapshot.checksum,
+            "seq": snapshot.seq,
+            "last_trade": last_trade,
+        })),
+    )
+        .into_response()
 }
 
+/// Default and maximum page size of `GET /trades` and `GET /executions`.
+const HISTORY_PAGE_LIMIT: usize = 1000;
+
 #[derive(serde::Deserialize)]
-struct AdminMarketStatePostBody {
-    state: String,
+struct HistoryParams {
+    /// Page forward: only entries with a greater id (a previous page's `next_cursor`).
+    since: Option<u64>,
+    /// Page backward: only entries with a smaller id (a previous page's `next_cursor`).
+    before: Option<u64>,
+    instrument_id: Option<u64>,
+    trader_id: Option<u64>,
+    /// Only entries with a timestamp at or after this.
+    from: Option<u64>,
+    /// Only entries with a timestamp at or before this.
+    to: Option<u64>,
+    limit: Option<usize>,
 }
 
-async fn admin_market_state_post(
-    Extension(auth): Extension<AuthUser>,
-    Extension(state): Extension<AppState>,
-    Json(body): Json<AdminMarketStatePostBody>,
-) -> Response {
-    let actor = auth.key_id.as_deref().unwrap_or("anonymous").to_string();
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            let new_state = MarketState::from_str(body.state.trim())
-                .ok_or_else(|| {
-                    (
-                        StatusCode::BAD_REQUEST,
-                        Json(serde_json::json!({ "error": "state must be Open, Halted, or Closed" })),
-                    )
-                        .into_response()
-                })?;
-            *state.market_state.lock().expect("lock") = new_state;
-            state.audit_sink.emit(&AuditEvent::now(
-                actor,
-                "market_state_change",
-                Some(serde_json::json!({ "state": new_state.as_str() })),
-                "success",
-            ));
-            persist_state(&state);
-            Ok((StatusCode::OK, Json(serde_json::json!({ "state": new_state.as_str() }))).into_response())
+impl HistoryParams {
+    /// Forward from `since` if given, otherwise backward from `before` (default: the newest entry).
+    fn query(&self) -> Result<HistoryQuery, ApiError> {
+        let cursor = match (self.since, self.before) {
+            (Some(_), Some(_)) => return Err(ApiError::invalid_field("before", "since and before cannot be combined")),
+            (Some(since), None) => HistoryCursor::After(since),
+            (None, before) => HistoryCursor::Before(before.unwrap_or(u64::MAX)),
+        };
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(ApiError::invalid_field("to", format!("to {} is before from {}", to, from)));
+            }
+        }
+        Ok(HistoryQuery {
+            instrument_id: self.instrument_id.map(InstrumentId),
+            trader_id: self.trader_id.map(TraderId),
+            from: self.from,
+            to: self.to,
+            cursor,
+            limit: self.limit.unwrap_or(HISTORY_PAGE_LIMIT).clamp(1, HISTORY_PAGE_LIMIT),
         })
-        .unwrap_or_else(|r| r)
+    }
 }
 
-async fn admin_emergency_halt(
-    Extension(auth): Extension<AuthUser>,
+/// One page of the engine's retained trades, oldest first, filtered by instrument, trader (buyer or seller),
+/// and timestamp. Pages forward from `since` or backward from `before` (default: the most recent trades); pass
+/// `next_cursor` back in the same parameter for the next page. A key entitled to some instruments only must name
+/// one of them in `instrument_id`.
+async fn list_trades(
     Extension(state): Extension<AppState>,
+    Extension(auth): Extension<AuthUser>,
+    ApiQuery(params): ApiQuery<HistoryParams>,
 ) -> Response {
-    let actor = auth.key_id.as_deref().unwrap_or("anonymous").to_string();
-    auth::require_admin_or_operator(&auth)
-        .map_err(|r| r)
-        .and_then(|()| {
-            *state.market_state.lock().expect("lock") = MarketState::Halted;
-            state.audit_sink.emit(&AuditEvent::now(
-                actor,
-                "emergency_halt",
-                Some(serde_json::json!({ "state": "Halted" })),
-                "success",
-            ));
-            persist_state(&state);
-            Ok((
-                StatusCode::OK,
-                Json(serde_json::json!({ "state": "Halted", "message": "emergency halt applied" })),
-            )
-                .into_response())
-        })
-        .unwrap_or_else(|r| r)
+    let query = match params.query() {
+        Ok(q) => q,
+        Err(e) => return e.into_response(),
+    };
+    match query.instrument_id {
+        Some(id) => {
+            if let Err(r) = auth::require_instrument(&auth, id, "instrument_id") {
+                return r;
+            }
+        }
+        None if auth.instruments.is_some() => {
+            let message = "instrument_id is required: API key is entitled to some instruments only";
+            return ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, message).with_field("instrument_id").into_response();
+        }
+        None => {}
+    }
+    let page = state.engine.lock().expect("lock").trade_history(&query);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "trades": page.items, "next_cursor": page.next_cursor })),
+    )
+        .into_response()
 }
 
-/// WebSocket market-data: on connect send one snapshot (best bid/ask), then keep connection open.
-async fn ws_market_data(
+/// One page of the engine's retained execution reports, paged and filtered like `GET /trades` (`trader_id`
+/// matches the trader of the report's order).
+async fn list_executions(
     Extension(state): Extension<AppState>,
-    upgrade: WebSocketUpgrade,
+    ApiQuery(params): ApiQuery<HistoryParams>,
 ) -> Response {
-    upgrade.on_upgrade(move |socket| handle_market_data_socket(state, socket))
+    let query = match params.query() {
+        Ok(q) => q,
+        Err(e) => return e.into_response(),
+    };
+    let page = state.engine.lock().expect("lock").execution_history(&query);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "executions": page.items, "next_cursor": page.next_cursor })),
+    )
+        .into_response()
 }
 
-#[derive(serde::Serialize)]
-struct MarketDataSnapshot {
-    #[serde(rename = "type")]
-    msg_type: &'static str,
-    instrument_id: u64,
-    best_bid: Option<rust_decimal::Decimal>,
-    best_ask: Option<rust_decimal::Decimal>,
+/// Session statistics of every instrument the key is entitled to, by instrument id.
+async fn list_tickers(Extension(state): Extension<AppState>, Extension(auth): Extension<AuthUser>) -> Response {
+    let guard = state.engine.lock().expect("lock");
+    let mut ids = guard.instruments();
+    ids.retain(|id| auth.entitled(*id));
+    ids.sort_by_key(|id| id.0);
+    let tickers: Vec<InstrumentStats> = ids.into_iter().filter_map(|id| guard.stats_for(id)).collect();
+    drop(guard);
+    (StatusCode::OK, Json(serde_json::json!({ "tickers": tickers }))).into_response()
 }
 
-async fn handle_market_data_socket(state: AppState, mut socket: WebSocket) {
-    let snapshots: Vec<MarketDataSnapshot> = {
-        let guard = state.engine.lock().expect("lock");
-        guard
-            .instruments()
-            .into_iter()
-            .filter_map(|id| {
-                guard.book_snapshot_for(id).map(|book| MarketDataSnapshot {
-                    msg_type: "snapshot",
-                    instrument_id: book.instrument_id.0,
-                    best_bid: book.best_bid,
-                    best_ask: book.best_ask,
-                })
-            })
-            .collect()
-    };
-    for snapshot in snapshots {
-        let json = match serde_json::to_string(&snapshot) {
-            Ok(s) => s,
-            Err(_) => continue,
-        };
-        if socket.send(Message::Text(json.into())).await.is_err() {
-            return;
-        }
+/// Session statistics of one instrument: open, high, low, last, volume, VWAP. 404 if the instrument is unknown,
+/// 403 if the key is not entitled to it.
+async fn get_ticker(
+    Extension(state): Extension<AppState>,
+    Extension(auth): Extension<AuthUser>,
+    ApiPath(id): ApiPath<u64>,
+) -> Response {
+    if let Err(r) = auth::require_instrument(&auth, InstrumentId(id), "id") {
+        return r;
+    }
+    match state.engine.lock().expect("lock").stats_for(InstrumentId(id)) {
+        Some(stats) => (StatusCode::OK, Json(stats)).into_response(),
+        None => ApiError::not_found(format!("Instrument {} not found", id)).into_response(),
     }
+}
 
-    let mut rx = state.broadcast_tx.subscribe();
-    loop {
-        tokio::select! {
-            res = rx.recv() => {
-                match res {
-                    Ok(update) => {
-                        let msg = MarketDataSnapshot {
-                            msg_type: "snapshot",
-                            instrument_id: update.instrument_id,
-                            best_bid: update.best_bid,
-                            best_ask: update.best_ask,
-                        };
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if socket.send(Message::Text(json.into())).await.is_err() {
-                                break;
-                            }
-                        }
-                    }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {}
-                    Err(broadcast::error::RecvError::Closed) => break,
-                }
-            }
-            msg = socket.recv() => match msg {
-                Some(Ok(_)) => {}
-                _ => break,
-            },
+/// Default and maximum number of events returned by `GET /events`.
+const EVENTS_PAGE_LIMIT: usize = 1000;
+
+#[derive(serde::Deserialize)]
+struct EventsParams {
+    /// Only events with a greater engine sequence number.
+    #[serde(default)]
+    since: u64,
+    limit: Option<usize>,
+}
+
+/// Gap fill: journaled trades, reports, and book changes after `since`, oldest first (at most `limit`).
+/// 410 if the journal no longer holds all of them; the client should resynchronize from snapshots.
+async fn list_events(
+    Extension(state): Extension<AppState>,
+    ApiQuery(params): ApiQuery<EventsParams>,
+) -> Response {
+    let limit = params.limit.unwrap_or(EVENTS_PAGE_LIMIT).min(EVENTS_PAGE_LIMIT);
+    let result = state.engine.lock().expect("lock").events_since(params.since);
+    match result {
+        Ok(mut events) => {
+            events.truncate(limit);
+            (StatusCode::OK, Json(serde_json::json!({ "events": events }))).into_response()
         }
+        Err(e) => ApiError::new(StatusCode::GONE, ErrorCode::Gone, e).into_response(),
     }
 }
 
 #[derive(serde::Deserialize)]
-struct CancelRequest {
-    order_id: u64,
+struct PositionsParams {
+    trader_id: u64,
+    instrument_id: Option<u64>,
+}
+
+/// Positions of one trader: every instrument they have traded or quoted, or just `instrument_id`.
+async fn list_positions(
+    Extension(state): Extension<AppState>,
+    ApiQuery(params): ApiQuery<PositionsParams>,
+) -> Response {
+    let trader_id = TraderId(params.trader_id);
+    let guard = state.engine.lock().expect("lock");
+    let positions = match params.instrument_id {
+        Some(id) => vec![guard.position(trader_id, InstrumentId(id))],
+        None => guard.positions_for_trader(trader_id),
+    };
+    drop(guard);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "trader_id": params.trader_id, "positions": positions })),
+    )
+        .into_response()
 }
 
 async fn cancel_order(
     Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
     Extension(auth): Extension<AuthUser>,
-    Json(body): Json<CancelRequest>,
+    ApiJson(body): ApiJson<CancelRequest>,
 ) -> Response {
-    let actor = auth.key_id.as_deref().unwrap_or("anonymous").to_string();
+    let actor = auth.actor();
     let order_id = body.order_id;
     let mut guard = state.engine.lock().expect("lock");
+    if let Some(order) = guard.get_order(OrderId(order_id)) {
+        let allowed = auth::require_order_owner(&auth, order.trader_id, "order_id")
+            .and(auth::require_instrument(&auth, order.instrument_id, "order_id"));
+        if let Err(r) = allowed {
+            drop(guard);
+            state.audit_sink.emit(&AuditEvent::now(
+                actor,
+                AuditAction::OrderCancel,
+                Some(serde_json::json!({ "order_id": order_id })),
+                "forbidden",
+            ).with_context(&context));
+            return r;
+        }
+    }
     let removed = guard.cancel_order(OrderId(order_id));
-    let update = removed.and_then(|instrument_id| {
-        guard.book_snapshot_for(instrument_id).map(|s| BookUpdate {
-            instrument_id: s.instrument_id.0,
-            best_bid: s.best_bid,
-            best_ask: s.best_ask,
-        })
-    });
     drop(guard);
-    if let Some(u) = update {
-        let _ = state.broadcast_tx.send(u);
-    }
     state.audit_sink.emit(&AuditEvent::now(
         actor,
-        "order_cancel",
+        AuditAction::OrderCancel,
         Some(serde_json::json!({ "order_id": order_id })),
         if removed.is_some() { "success" } else { "not_found" },
-    ));
+    ).with_context(&context));
     if removed.is_some() {
-        persist_state(&state);
+        state.notifier.mark_dirty();
     }
     #[derive(serde::Serialize)]
     struct Out {
@@ -530,6 +3093,22 @@ async fn cancel_order(
     (StatusCode::OK, Json(Out { canceled: removed.is_some() })).into_response()
 }
 
+/// 422 for an order whose quantity is not positive, before it reaches the engine. `prefix` is where the order
+/// sits in the request body (e.g. `replacement.`).
+fn validate_order_fields(order: &Order, prefix: &str) -> Result<(), ApiError> {
+    if order.quantity <= rust_decimal::Decimal::ZERO {
+        return Err(ApiError::invalid_field(
+            format!("{}quantity", prefix),
+            format!("Order quantity {} must be positive", order.quantity),
+        ));
+    }
+    Ok(())
+}
+
+fn market_closed() -> Response {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::MarketClosed, "market not open").into_response()
+}
+
 #[derive(serde::Deserialize)]
 struct ModifyRequest {
     order_id: u64,
@@ -538,40 +3117,41 @@ struct ModifyRequest {
 
 async fn modify_order(
     Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
     Extension(auth): Extension<AuthUser>,
-    Json(body): Json<ModifyRequest>,
+    ApiJson(body): ApiJson<ModifyRequest>,
 ) -> Response {
+    if let Err(e) = validate_order_fields(&body.replacement, "replacement.") {
+        return e.into_response();
+    }
     if *state.market_state.lock().expect("lock") != MarketState::Open {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "market not open" })),
-        )
-            .into_response();
+        return market_closed();
     }
-    let actor = auth.key_id.as_deref().unwrap_or("anonymous").to_string();
+    let actor = auth.actor();
     let order_id = body.order_id;
     let mut guard = state.engine.lock().expect("lock");
-    let out = match guard.modify_order(OrderId(order_id), &body.replacement) {
+    let resource = order_modify_resource(OrderId(order_id), guard.order_status(OrderId(order_id)).as_ref(), &body.replacement);
+    // The replacement may stay with the order's trader, or go to the key's own.
+    let replacement_trader = body.replacement.trader_id;
+    let ownership = match guard.get_order(OrderId(order_id)) {
+        Some(order) => match auth::require_order_owner(&auth, order.trader_id, "order_id") {
+            Ok(()) if order.trader_id == replacement_trader => Ok(()),
+            Ok(()) => auth::require_trader(&auth, replacement_trader, "replacement.trader_id"),
+            err => err,
+        },
+        None => auth::require_trader(&auth, replacement_trader, "replacement.trader_id"),
+    };
+    let instrument = auth::require_instrument(&auth, body.replacement.instrument_id, "replacement.instrument_id");
+    if let Err(r) = ownership.and(instrument) {
+        drop(guard);
+        state.audit_sink.emit(&AuditEvent::now(actor, AuditAction::OrderModify, Some(resource), "forbidden").with_context(&context));
+        return r;
+    }
+    match guard.modify_order(OrderId(order_id), &body.replacement) {
         Ok((trades, reports)) => {
-            let instrument_id = body.replacement.instrument_id;
-            let update = guard
-                .book_snapshot_for(instrument_id)
-                .map(|s| BookUpdate {
-                    instrument_id: s.instrument_id.0,
-                    best_bid: s.best_bid,
-                    best_ask: s.best_ask,
-                });
             drop(guard);
-            if let Some(u) = update {
-                let _ = state.broadcast_tx.send(u);
-            }
-            state.audit_sink.emit(&AuditEvent::now(
-                actor.clone(),
-                "order_modify",
-                Some(serde_json::json!({ "order_id": order_id, "replacement_order_id": body.replacement.order_id.0 })),
-                "success",
-            ));
-            persist_state(&state);
+            state.audit_sink.emit(&AuditEvent::now(actor, AuditAction::OrderModify, Some(resource), "success").with_context(&context));
+            state.notifier.mark_dirty();
             #[derive(serde::Serialize)]
             struct Out {
                 trades: Vec<crate::Trade>,
@@ -580,58 +3160,49 @@ async fn modify_order(
             (StatusCode::OK, Json(Out { trades, reports })).into_response()
         }
         Err(e) => {
-            state.audit_sink.emit(&AuditEvent::now(
-                actor,
-                "order_modify",
-                Some(serde_json::json!({ "order_id": order_id })),
-                "rejected",
-            ));
-            (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
+            state.audit_sink.emit(&AuditEvent::now(actor, AuditAction::OrderModify, Some(resource), "rejected").with_context(&context));
+            ApiError::rejected(e).into_response()
         }
-    };
-    out
+    }
 }
 
 async fn submit_order(
     Extension(state): Extension<AppState>,
+    Extension(context): Extension<AuditContext>,
     Extension(auth): Extension<AuthUser>,
-    Json(order): Json<Order>,
+    ApiJson(order): ApiJson<Order>,
 ) -> Response {
+    if let Err(e) = validate_order_fields(&order, "") {
+        return e.into_response();
+    }
     if *state.market_state.lock().expect("lock") != MarketState::Open {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "market not open" })),
-        )
-            .into_response();
+        return market_closed();
     }
-    let actor = auth.key_id.as_deref().unwrap_or("anonymous").to_string();
+    let actor = auth.actor();
     let order_id = order.order_id.0;
     let instrument_id = order.instrument_id;
+    let allowed = auth::require_trader(&auth, order.trader_id, "trader_id")
+        .and(auth::require_instrument(&auth, instrument_id, "instrument_id"));
+    if let Err(r) = allowed {
+        state.audit_sink.emit(&AuditEvent::now(
+            actor,
+            AuditAction::OrderSubmit,
+            Some(serde_json::json!({ "order_id": order_id, "instrument_id": instrument_id.0 })),
+            "forbidden",
+        ).with_context(&context));
+        return r;
+    }
     let mut guard = state.engine.lock().expect("lock");
     match guard.submit_order(order) {
         Ok((trades, reports)) => {
-            let update = guard
-                .book_snapshot_for(instrument_id)
-                .map(|s| BookUpdate {
-                    instrument_id: s.instrument_id.0,
-                    best_bid: s.best_bid,
-                    best_ask: s.best_ask,
-                });
             drop(guard);
-            if let Some(u) = update {
-                let _ = state.broadcast_tx.send(u);
-            }
             state.audit_sink.emit(&AuditEvent::now(
                 actor,
-                "order_submit",
+                AuditAction::OrderSubmit,
                 Some(serde_json::json!({ "order_id": order_id, "instrument_id": instrument_id.0 })),
                 "success",
-            ));
-            persist_state(&state);
+            ).with_context(&context));
+            state.notifier.mark_dirty();
             #[derive(serde::Serialize)]
             struct Out {
                 trades: Vec<crate::Trade>,
@@ -642,15 +3213,11 @@ async fn submit_order(
         Err(e) => {
             state.audit_sink.emit(&AuditEvent::now(
                 actor,
-                "order_submit",
+                AuditAction::OrderSubmit,
                 Some(serde_json::json!({ "order_id": order_id, "instrument_id": instrument_id.0 })),
                 "rejected",
-            ));
-            (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": e })),
-            )
-                .into_response()
+            ).with_context(&context));
+            ApiError::rejected(e).into_response()
         }
     }
 }
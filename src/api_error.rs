@@ -0,0 +1,272 @@
+//! Error envelope for every REST error response: `{ "code", "message", "field" }`.
+//!
+//! `code` is a stable, machine-readable [`ErrorCode`] that client SDKs can branch on; `message` is for humans
+//! and may change; `field` names the request field at fault (dotted path, e.g. `replacement.side`), or is
+//! `null`. The [`ApiJson`], [`ApiQuery`], and [`ApiPath`] extractors turn malformed requests into the same
+//! envelope: 400 `invalid_json` for bodies that are not JSON, 422 `invalid_field` for body values of the wrong
+//! type, unknown enum values, and missing fields, and 400 `invalid_field` for bad query strings.
+
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Path, Query, Request},
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+/// Stable error codes. Serialized in snake_case (e.g. `market_closed`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The body is not valid JSON.
+    InvalidJson,
+    /// A field has the wrong type, an unknown enum value, is missing, or is out of range (e.g. a negative quantity).
+    InvalidField,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    /// The request conflicts with current state (e.g. the instrument already exists or still has orders).
+    Conflict,
+    /// The requested history is no longer retained.
+    Gone,
+    /// The venue or the instrument is not open for trading.
+    MarketClosed,
+    UnknownInstrument,
+    /// The instrument is suspended or delisted.
+    InstrumentUnavailable,
+    DuplicateOrderId,
+    /// The price is missing on a limit order, off the tick grid, or out of range.
+    InvalidPrice,
+    /// The quantity is not a multiple of the instrument's lot size.
+    InvalidQuantity,
+    /// A pre-trade risk limit would be exceeded.
+    RiskLimit,
+    /// A book, price-level, or trader resting-order cap is reached.
+    BookLimit,
+    /// Too many order-entry requests; retry after the `Retry-After` seconds.
+    RateLimited,
+    /// Any other engine rejection.
+    OrderRejected,
+    /// The node is a standby following a primary and does not take changes until it is promoted.
+    Standby,
+    /// The server could not complete the request (e.g. the state file could not be written).
+    Internal,
+}
+
+/// An error response. Build one with [`ApiError::new`] or a shortcut, or from an engine rejection with
+/// [`ApiError::rejected`].
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message: String,
+    pub field: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            field: None,
+        }
+    }
+
+    /// Name the request field at fault.
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    /// 422 `invalid_field` for `field`.
+    pub fn invalid_field(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::InvalidField, message).with_field(field)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, ErrorCode::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, ErrorCode::Conflict, message)
+    }
+
+    /// An engine rejection of an order, modify, or quote, classified by its message. 503 when the market or
+    /// instrument is not open (like a venue-wide halt); 400 otherwise.
+    pub fn rejected(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let (status, code, field) = classify_rejection(&message);
+        let error = Self::new(status, code, message);
+        match field {
+            Some(field) => error.with_field(field),
+            None => error,
+        }
+    }
+}
+
+fn classify_rejection(message: &str) -> (StatusCode, ErrorCode, Option<&'static str>) {
+    let bad = StatusCode::BAD_REQUEST;
+    if message.starts_with("market not open") {
+        (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::MarketClosed, None)
+    } else if message.starts_with("Duplicate order id") || (message.starts_with("Order ") && message.ends_with("already exists")) {
+        (bad, ErrorCode::DuplicateOrderId, Some("order_id"))
+    } else if message.starts_with("Unknown instrument") || (message.starts_with("Instrument ") && message.ends_with("not found")) {
+        (bad, ErrorCode::UnknownInstrument, Some("instrument_id"))
+    } else if message.ends_with("is suspended") || message.ends_with("is delisted") {
+        (bad, ErrorCode::InstrumentUnavailable, Some("instrument_id"))
+    } else if message.starts_with("Order ") && message.ends_with("not found") {
+        (bad, ErrorCode::NotFound, Some("order_id"))
+    } else if message.contains("tick size") || message.contains("out of range") || message.contains("must have price") {
+        (bad, ErrorCode::InvalidPrice, Some("price"))
+    } else if message.contains("lot size") {
+        (bad, ErrorCode::InvalidQuantity, Some("quantity"))
+    } else if message.starts_with("Order quantity") && message.contains("exceeds") {
+        (bad, ErrorCode::RiskLimit, Some("quantity"))
+    } else if message.contains("exceed") {
+        (bad, ErrorCode::RiskLimit, None)
+    } else if message.contains("limit reached") {
+        (bad, ErrorCode::BookLimit, None)
+    } else {
+        (bad, ErrorCode::OrderRejected, None)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(&self)).into_response()
+    }
+}
+
+/// The field named by a serde error: `path` to the value that failed, or, for a missing field, the field itself.
+fn serde_field(path: &str, message: &str) -> Option<String> {
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next());
+    let path = (path != ".").then_some(path);
+    match (path, missing) {
+        (Some(path), Some(name)) => Some(format!("{}.{}", path, name)),
+        (None, Some(name)) => Some(name.to_string()),
+        (path, None) => path.map(str::to_string),
+    }
+}
+
+/// JSON body extractor whose rejections are [`ApiError`]s naming the field at fault.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, ApiError> {
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidJson, e.body_text()))?;
+        let de = &mut serde_json::Deserializer::from_slice(&bytes);
+        serde_path_to_error::deserialize(de).map(ApiJson).map_err(|e| {
+            let inner = e.inner();
+            if inner.is_syntax() || inner.is_eof() {
+                return ApiError::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidJson, inner.to_string());
+            }
+            let message = inner.to_string();
+            let field = serde_field(&e.path().to_string(), &message);
+            let error = ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::InvalidField, message);
+            match field {
+                Some(field) => error.with_field(field),
+                None => error,
+            }
+        })
+    }
+}
+
+/// Query-string extractor whose rejections are 400 `invalid_field` [`ApiError`]s.
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, ApiError> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(ApiQuery(value)),
+            Err(e) => {
+                let message = e.body_text();
+                let detail = message.rsplit(": ").next().unwrap_or_default();
+                let error = ApiError::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidField, message.clone());
+                Err(match serde_field(".", detail) {
+                    Some(field) => error.with_field(field),
+                    None => error,
+                })
+            }
+        }
+    }
+}
+
+/// Path-parameter extractor whose rejections are 422 `invalid_field` [`ApiError`]s.
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, ApiError> {
+        Path::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Path(value)| ApiPath(value))
+            .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::InvalidField, e.body_text()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_rejections_map_to_codes_and_fields() {
+        let cases = [
+            ("market not open for instrument 2", StatusCode::SERVICE_UNAVAILABLE, ErrorCode::MarketClosed, None),
+            ("Duplicate order id 7", StatusCode::BAD_REQUEST, ErrorCode::DuplicateOrderId, Some("order_id")),
+            ("Unknown instrument 9", StatusCode::BAD_REQUEST, ErrorCode::UnknownInstrument, Some("instrument_id")),
+            ("Instrument 2 is suspended", StatusCode::BAD_REQUEST, ErrorCode::InstrumentUnavailable, Some("instrument_id")),
+            ("Price 100.5 is not a multiple of tick size 1", StatusCode::BAD_REQUEST, ErrorCode::InvalidPrice, Some("price")),
+            ("Price 111 is out of range 90..110 for instrument 1", StatusCode::BAD_REQUEST, ErrorCode::InvalidPrice, Some("price")),
+            ("Quantity 1.5 is not a multiple of lot size 1", StatusCode::BAD_REQUEST, ErrorCode::InvalidQuantity, Some("quantity")),
+            ("Order quantity 600 exceeds max order quantity 500", StatusCode::BAD_REQUEST, ErrorCode::RiskLimit, Some("quantity")),
+            ("Position 9 for trader 1 would exceed max position 5", StatusCode::BAD_REQUEST, ErrorCode::RiskLimit, None),
+            ("Book order limit reached (10 resting orders)", StatusCode::BAD_REQUEST, ErrorCode::BookLimit, None),
+            ("Order 4 not found", StatusCode::BAD_REQUEST, ErrorCode::NotFound, Some("order_id")),
+            ("Quote bid 102 must be below ask 102", StatusCode::BAD_REQUEST, ErrorCode::OrderRejected, None),
+        ];
+        for (message, status, code, field) in cases {
+            let error = ApiError::rejected(message);
+            assert_eq!((error.status, error.code, error.field.as_deref()), (status, code, field), "{}", message);
+            assert_eq!(error.message, message);
+        }
+    }
+
+    #[test]
+    fn serde_field_names_path_or_missing_field() {
+        assert_eq!(serde_field("side", "unknown variant `Up`"), Some("side".to_string()));
+        assert_eq!(serde_field(".", "missing field `price`"), Some("price".to_string()));
+        assert_eq!(
+            serde_field("replacement", "missing field `side`"),
+            Some("replacement.side".to_string())
+        );
+        assert_eq!(serde_field(".", "invalid type"), None);
+    }
+}
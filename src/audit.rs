@@ -1,46 +1,251 @@
 //! Phase 3 §3: Structured audit trail for material actions.
 //!
-//! Events: order submit/cancel/modify, config changes, market state changes, emergency halt.
-//! Format: JSON with timestamp, actor, action, resource, outcome. Sink: stdout or pluggable (e.g. test mock).
+//! Events: order submit/cancel/modify, config changes, market state changes, emergency halt (see [`AuditAction`]).
+//! Format: JSON with schema version, timestamp, actor, action, resource, outcome, and where the action came from
+//! ([`AuditContext`]: protocol, request id, client IP). Sink: stdout or pluggable (e.g. test mock).
+//! [`BufferedAuditSink`] moves the writing off the request path: events are queued and written by a background
+//! thread, so a slow sink does not slow order entry. [`FileAuditStore`] keeps the trail in an append-only file
+//! that can be queried ([`AuditSink::query`], `GET /admin/audit`).
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Version of the [`AuditEvent`] record layout, written in every event as `schema_version`. Records without one
+/// are version 1 (no `source`, `request_id`, or `client_ip`).
+pub const AUDIT_SCHEMA_VERSION: u32 = 2;
+
+/// What was done. Serialized in snake_case (`order_submit`); new actions are added, existing names never change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    OrderSubmit,
+    OrderCancel,
+    OrderModify,
+    /// The engine dropped an order's unfilled quantity or a good-till-date order reached its expiry.
+    OrderExpired,
+    ConfigChange,
+    MarketStateChange,
+    EmergencyHalt,
+    InstrumentUpdate,
+    InstrumentStateChange,
+    BookUncross,
+    Snapshot,
+    Restore,
+    StateLoad,
+    StateSave,
+    Shutdown,
+    /// A standby was promoted to take over from its primary.
+    Failover,
+    AuthFailure,
+    Login,
+    Logout,
+    KeySet,
+    KeyDelete,
+    FixLogon,
+    FixLogout,
+    AuditOverflow,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 24] = [
+        AuditAction::OrderSubmit,
+        AuditAction::OrderCancel,
+        AuditAction::OrderModify,
+        AuditAction::OrderExpired,
+        AuditAction::ConfigChange,
+        AuditAction::MarketStateChange,
+        AuditAction::EmergencyHalt,
+        AuditAction::InstrumentUpdate,
+        AuditAction::InstrumentStateChange,
+        AuditAction::BookUncross,
+        AuditAction::Snapshot,
+        AuditAction::Restore,
+        AuditAction::StateLoad,
+        AuditAction::StateSave,
+        AuditAction::Shutdown,
+        AuditAction::Failover,
+        AuditAction::AuthFailure,
+        AuditAction::Login,
+        AuditAction::Logout,
+        AuditAction::KeySet,
+        AuditAction::KeyDelete,
+        AuditAction::FixLogon,
+        AuditAction::FixLogout,
+        AuditAction::AuditOverflow,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::OrderSubmit => "order_submit",
+            AuditAction::OrderCancel => "order_cancel",
+            AuditAction::OrderModify => "order_modify",
+            AuditAction::OrderExpired => "order_expired",
+            AuditAction::ConfigChange => "config_change",
+            AuditAction::MarketStateChange => "market_state_change",
+            AuditAction::EmergencyHalt => "emergency_halt",
+            AuditAction::InstrumentUpdate => "instrument_update",
+            AuditAction::InstrumentStateChange => "instrument_state_change",
+            AuditAction::BookUncross => "book_uncross",
+            AuditAction::Snapshot => "snapshot",
+            AuditAction::Restore => "restore",
+            AuditAction::StateLoad => "state_load",
+            AuditAction::StateSave => "state_save",
+            AuditAction::Shutdown => "shutdown",
+            AuditAction::Failover => "failover",
+            AuditAction::AuthFailure => "auth_failure",
+            AuditAction::Login => "login",
+            AuditAction::Logout => "logout",
+            AuditAction::KeySet => "key_set",
+            AuditAction::KeyDelete => "key_delete",
+            AuditAction::FixLogon => "fix_logon",
+            AuditAction::FixLogout => "fix_logout",
+            AuditAction::AuditOverflow => "audit_overflow",
+        }
+    }
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Protocol an audited action came in on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    Rest,
+    Fix,
+    /// A WebSocket route (`/ws/...`).
+    Ws,
+    /// The venue itself: engine expiries, persistence, shutdown signals, the audit writer.
+    #[default]
+    Internal,
+    /// Version 1 records, which did not say.
+    Unknown,
+}
+
+/// Where an action came from, attached to its events with [`AuditEvent::with_context`]. The REST router puts one
+/// in every request's extensions; FIX sessions build their own.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    pub source: AuditSource,
+    /// Correlates the events of one request: the REST request id (`X-Request-Id`), or the FIX session and
+    /// MsgSeqNum (`SENDER:TARGET:34`).
+    pub request_id: Option<String>,
+    pub client_ip: Option<IpAddr>,
+}
+
+impl AuditContext {
+    /// For actions the venue takes itself.
+    pub fn internal() -> Self {
+        Self::default()
+    }
+}
+
+fn unknown_source() -> AuditSource {
+    AuditSource::Unknown
+}
+
+fn schema_version_1() -> u32 {
+    1
+}
+
 /// Single audit record: one line of JSON per event.
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AuditEvent {
+    /// [`AUDIT_SCHEMA_VERSION`] of the record.
+    #[serde(default = "schema_version_1")]
+    pub schema_version: u32,
     /// Unix timestamp (seconds since epoch). Log aggregators can convert to ISO8601.
     pub timestamp_secs: u64,
-    /// Who performed the action (e.g. API key id, "fix", "anonymous").
+    /// Who performed the action (e.g. API key id, FIX SenderCompID, "engine", "anonymous").
     pub actor: String,
-    /// Action type: order_submit, order_cancel, order_modify, config_change, market_state_change, emergency_halt.
-    pub action: String,
+    pub action: AuditAction,
     /// Resource identifiers (e.g. order_id, instrument_id). Flexible for different action types.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub resource: Option<serde_json::Value>,
-    /// Outcome: success, rejected, error.
+    /// Outcome: success, rejected, forbidden, not_found, error.
     pub outcome: String,
+    #[serde(default = "unknown_source")]
+    pub source: AuditSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<IpAddr>,
 }
 
 impl AuditEvent {
-    pub fn now(actor: impl Into<String>, action: impl Into<String>, resource: Option<serde_json::Value>, outcome: impl Into<String>) -> Self {
+    /// An event of the current schema, from [`AuditSource::Internal`] until [`AuditEvent::with_context`] says
+    /// otherwise.
+    pub fn now(actor: impl Into<String>, action: AuditAction, resource: Option<serde_json::Value>, outcome: impl Into<String>) -> Self {
         let timestamp_secs = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_secs())
             .unwrap_or(0);
         Self {
+            schema_version: AUDIT_SCHEMA_VERSION,
             timestamp_secs,
             actor: actor.into(),
-            action: action.into(),
+            action,
             resource,
             outcome: outcome.into(),
+            source: AuditSource::Internal,
+            request_id: None,
+            client_ip: None,
         }
     }
+
+    /// Record the protocol, request id, and client address of `context`.
+    pub fn with_context(mut self, context: &AuditContext) -> Self {
+        self.source = context.source;
+        self.request_id = context.request_id.clone();
+        self.client_ip = context.client_ip;
+        self
+    }
+}
+
+/// Which events [`AuditSink::query`] returns: every given field must match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<AuditAction>,
+    /// Only events at or after this Unix second.
+    pub from: Option<u64>,
+    /// Only events at or before this Unix second.
+    pub to: Option<u64>,
+    /// At most this many events: the newest ones.
+    pub limit: usize,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.actor.as_ref().is_none_or(|actor| *actor == event.actor)
+            && self.action.is_none_or(|action| action == event.action)
+            && self.from.is_none_or(|from| event.timestamp_secs >= from)
+            && self.to.is_none_or(|to| event.timestamp_secs <= to)
+    }
 }
 
 /// Sink for audit events. Implementations write to stdout, file, or in-memory (tests).
 pub trait AuditSink: Send + Sync {
     fn emit(&self, event: &AuditEvent);
+
+    /// Write out every event emitted so far. Called on shutdown.
+    fn flush(&self) {}
+
+    /// Events matching `query`, newest first, once every event emitted so far is included. `None` when the sink
+    /// keeps no events to query.
+    fn query(&self, _query: &AuditQuery) -> Option<Result<Vec<AuditEvent>, String>> {
+        None
+    }
 }
 
 /// Writes one JSON line per event to stdout. Safe to use from multiple threads.
@@ -48,10 +253,332 @@ pub struct StdoutAuditSink;
 
 impl AuditSink for StdoutAuditSink {
     fn emit(&self, event: &AuditEvent) {
-        match serde_json::to_string(event) {
-            Ok(line) => println!("{}", line),
-            Err(_) => {}
+        if let Ok(line) = serde_json::to_string(event) {
+            println!("{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        use std::io::Write;
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Emits every event to each of its sinks, in order; queries go to the first sink that keeps events.
+pub struct MultiAuditSink(Vec<Arc<dyn AuditSink + Send + Sync>>);
+
+impl MultiAuditSink {
+    pub fn new(sinks: Vec<Arc<dyn AuditSink + Send + Sync>>) -> Self {
+        Self(sinks)
+    }
+}
+
+impl AuditSink for MultiAuditSink {
+    fn emit(&self, event: &AuditEvent) {
+        for sink in &self.0 {
+            sink.emit(event);
+        }
+    }
+
+    fn flush(&self) {
+        for sink in &self.0 {
+            sink.flush();
+        }
+    }
+
+    fn query(&self, query: &AuditQuery) -> Option<Result<Vec<AuditEvent>, String>> {
+        self.0.iter().find_map(|sink| sink.query(query))
+    }
+}
+
+/// What [`BufferedAuditSink`] does with an event when its buffer is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOverflow {
+    /// Wait for room: no event is lost, but a sink that stays slow slows the request path.
+    Block,
+    /// Drop the event and count it; the writer records the count as an `audit_overflow` event.
+    Drop,
+}
+
+/// Buffer size and overflow policy of a [`BufferedAuditSink`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuditBufferPolicy {
+    pub capacity: usize,
+    pub overflow: AuditOverflow,
+}
+
+impl Default for AuditBufferPolicy {
+    /// 10,000 events, blocking when full.
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            overflow: AuditOverflow::Block,
+        }
+    }
+}
+
+impl AuditBufferPolicy {
+    /// Read `AUDIT_BUFFER_CAPACITY` and `AUDIT_OVERFLOW` (`block` or `drop`); unset or invalid variables keep the
+    /// default.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let capacity = std::env::var("AUDIT_BUFFER_CAPACITY")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(default.capacity);
+        let overflow = match std::env::var("AUDIT_OVERFLOW").map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+            Ok("drop") => AuditOverflow::Drop,
+            Ok("block") => AuditOverflow::Block,
+            _ => default.overflow,
+        };
+        Self { capacity, overflow }
+    }
+}
+
+enum AuditMessage {
+    Event(AuditEvent),
+    /// Acknowledged once every earlier event is written.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Queues events in a bounded channel for a background thread that writes them to `inner`, in order. When the
+/// queue is full, [`AuditBufferPolicy::overflow`] decides whether `emit` waits or drops the event; dropped events
+/// are counted and reported by an `audit_overflow` event (actor `audit`) ahead of the next one written.
+/// [`AuditSink::flush`] waits until the queue is written; [`AuditSink::query`] flushes, then asks `inner`.
+pub struct BufferedAuditSink {
+    inner: Arc<dyn AuditSink + Send + Sync>,
+    tx: SyncSender<AuditMessage>,
+    overflow: AuditOverflow,
+    dropped: Arc<AtomicU64>,
+}
+
+impl BufferedAuditSink {
+    /// Start the writer thread; it stops once the sink is dropped and the queue written.
+    pub fn new(inner: Arc<dyn AuditSink + Send + Sync>, policy: AuditBufferPolicy) -> Self {
+        let (tx, rx) = mpsc::sync_channel(policy.capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer_dropped = dropped.clone();
+        let writer = inner.clone();
+        std::thread::Builder::new()
+            .name("audit-writer".to_string())
+            .spawn(move || {
+                for message in rx {
+                    let lost = writer_dropped.swap(0, Ordering::Relaxed);
+                    if lost > 0 {
+                        log::warn!("audit buffer full: {} events dropped", lost);
+                        let resource = serde_json::json!({ "dropped": lost });
+                        writer.emit(&AuditEvent::now("audit", AuditAction::AuditOverflow, Some(resource), "error"));
+                    }
+                    match message {
+                        AuditMessage::Event(event) => writer.emit(&event),
+                        AuditMessage::Flush(ack) => {
+                            writer.flush();
+                            let _ = ack.send(());
+                        }
+                    }
+                }
+            })
+            .expect("spawn audit writer");
+        Self {
+            inner,
+            tx,
+            overflow: policy.overflow,
+            dropped,
+        }
+    }
+
+    /// Events dropped since the writer last reported them.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl AuditSink for BufferedAuditSink {
+    fn emit(&self, event: &AuditEvent) {
+        let message = AuditMessage::Event(event.clone());
+        let sent = match self.overflow {
+            AuditOverflow::Block => self.tx.send(message).is_ok(),
+            AuditOverflow::Drop => match self.tx.try_send(message) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+            },
+        };
+        if !sent {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {
+        let (ack, done) = mpsc::channel();
+        if self.tx.send(AuditMessage::Flush(ack)).is_ok() {
+            let _ = done.recv();
+        }
+    }
+
+    fn query(&self, query: &AuditQuery) -> Option<Result<Vec<AuditEvent>, String>> {
+        self.flush();
+        self.inner.query(query)
+    }
+}
+
+/// Where one event is in a [`FileAuditStore`]'s file, with the fields queries filter on. Actors are numbered
+/// (see [`StoreIndex::names`]) so the index stays small.
+struct IndexEntry {
+    timestamp_secs: u64,
+    actor: u32,
+    action: AuditAction,
+    offset: u64,
+    len: u32,
+}
+
+#[derive(Default)]
+struct StoreIndex {
+    entries: Vec<IndexEntry>,
+    names: HashMap<String, u32>,
+    /// End of the file: where the next event is appended.
+    end: u64,
+}
+
+impl StoreIndex {
+    fn name(&mut self, name: &str) -> u32 {
+        let next = self.names.len() as u32;
+        *self.names.entry(name.to_string()).or_insert(next)
+    }
+
+    fn add(&mut self, event: &AuditEvent, len: u64) {
+        let entry = IndexEntry {
+            timestamp_secs: event.timestamp_secs,
+            actor: self.name(&event.actor),
+            action: event.action,
+            offset: self.end,
+            len: len as u32,
+        };
+        self.entries.push(entry);
+        self.end += len;
+    }
+}
+
+/// Append-only audit file: one JSON line per event, as [`StdoutAuditSink`] prints them, indexed in memory by
+/// timestamp, actor, and action. The index is rebuilt from the file on [`FileAuditStore::open`]; queries read
+/// only the matching lines. Lines that do not parse (e.g. one cut short by a crash) are skipped.
+pub struct FileAuditStore {
+    path: PathBuf,
+    index: Mutex<(std::fs::File, StoreIndex)>,
+}
+
+impl FileAuditStore {
+    /// Open `path` for appending, creating it if needed, and index the events already in it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut index = StoreIndex::default();
+        let mut reader = std::io::BufReader::new(&file);
+        let mut line = Vec::new();
+        let mut torn = false;
+        loop {
+            line.clear();
+            let len = reader.read_until(b'\n', &mut line).map_err(|e| e.to_string())? as u64;
+            if len == 0 {
+                break;
+            }
+            torn = !line.ends_with(b"\n");
+            match serde_json::from_slice::<AuditEvent>(&line) {
+                Ok(event) if !torn => index.add(&event, len),
+                _ => {
+                    log::warn!("audit store {}: skipping unreadable line at byte {}", path.display(), index.end);
+                    index.end += len;
+                }
+            }
+        }
+        if torn {
+            // A partial last line: start the next event on a line of its own.
+            file.write_all(b"\n").map_err(|e| e.to_string())?;
+            index.end += 1;
         }
+        Ok(Self {
+            path,
+            index: Mutex::new((file, index)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of events in the file.
+    pub fn len(&self) -> usize {
+        self.index.lock().expect("lock").1.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn read(&self, query: &AuditQuery) -> Result<Vec<AuditEvent>, String> {
+        let spans: Vec<(u64, u32)> = {
+            let guard = self.index.lock().expect("lock");
+            let index = &guard.1;
+            let actor = query.actor.as_ref().map(|name| index.names.get(name).copied());
+            if actor == Some(None) {
+                return Ok(Vec::new());
+            }
+            index
+                .entries
+                .iter()
+                .rev()
+                .filter(|entry| {
+                    actor.is_none_or(|actor| actor == Some(entry.actor))
+                        && query.action.is_none_or(|action| action == entry.action)
+                        && query.from.is_none_or(|from| entry.timestamp_secs >= from)
+                        && query.to.is_none_or(|to| entry.timestamp_secs <= to)
+                })
+                .take(query.limit)
+                .map(|entry| (entry.offset, entry.len))
+                .collect()
+        };
+        let mut file = std::fs::File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut line = Vec::new();
+        spans
+            .into_iter()
+            .map(|(offset, len)| {
+                line.resize(len as usize, 0);
+                file.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+                file.read_exact(&mut line).map_err(|e| e.to_string())?;
+                serde_json::from_slice(&line).map_err(|e| e.to_string())
+            })
+            .collect()
+    }
+}
+
+impl AuditSink for FileAuditStore {
+    fn emit(&self, event: &AuditEvent) {
+        let Ok(mut line) = serde_json::to_vec(event) else {
+            return;
+        };
+        line.push(b'\n');
+        let mut guard = self.index.lock().expect("lock");
+        let (file, index) = &mut *guard;
+        match file.write_all(&line) {
+            Ok(()) => index.add(event, line.len() as u64),
+            Err(e) => log::warn!("audit store {}: event not written: {}", self.path.display(), e),
+        }
+    }
+
+    fn flush(&self) {
+        let guard = self.index.lock().expect("lock");
+        if let Err(e) = guard.0.sync_data() {
+            log::warn!("audit store {}: sync failed: {}", self.path.display(), e);
+        }
+    }
+
+    fn query(&self, query: &AuditQuery) -> Option<Result<Vec<AuditEvent>, String>> {
+        Some(self.read(query))
     }
 }
 
@@ -87,4 +614,146 @@ impl AuditSink for InMemoryAuditSink {
     fn emit(&self, event: &AuditEvent) {
         self.events.lock().expect("lock").push(event.clone());
     }
+
+    fn query(&self, query: &AuditQuery) -> Option<Result<Vec<AuditEvent>, String>> {
+        let events = self.events.lock().expect("lock");
+        Some(Ok(events.iter().rev().filter(|e| query.matches(e)).take(query.limit).cloned().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Holds each event at `gate` (locked by the test) after telling `entered`.
+    struct GatedSink {
+        gate: Arc<Mutex<()>>,
+        entered: Mutex<mpsc::Sender<()>>,
+        events: InMemoryAuditSink,
+    }
+
+    impl AuditSink for GatedSink {
+        fn emit(&self, event: &AuditEvent) {
+            let _ = self.entered.lock().expect("lock").send(());
+            drop(self.gate.lock().expect("lock"));
+            self.events.emit(event);
+        }
+    }
+
+    fn event(n: u64) -> AuditEvent {
+        AuditEvent::now("k", AuditAction::OrderSubmit, Some(serde_json::json!({ "order_id": n })), "success")
+    }
+
+    #[test]
+    fn buffered_sink_writes_in_order_and_reports_dropped_events() {
+        let gate = Arc::new(Mutex::new(()));
+        let (entered, writer_entered) = mpsc::channel();
+        let events = InMemoryAuditSink::new();
+        let inner = Arc::new(GatedSink {
+            gate: gate.clone(),
+            entered: Mutex::new(entered),
+            events: events.clone(),
+        });
+        let sink = BufferedAuditSink::new(inner, AuditBufferPolicy { capacity: 2, overflow: AuditOverflow::Drop });
+
+        // The writer holds event 1 at the gate; 2 and 3 fill the buffer and 4 is dropped without waiting.
+        let closed = gate.lock().unwrap();
+        sink.emit(&event(1));
+        writer_entered.recv().unwrap();
+        (2..=4).for_each(|n| sink.emit(&event(n)));
+        assert_eq!((events.events().len(), sink.dropped()), (0, 1));
+        drop(closed);
+
+        sink.flush();
+        let written: Vec<_> = events.events().iter().map(|e| (e.action, e.resource.clone().unwrap())).collect();
+        assert_eq!(
+            written,
+            [
+                (AuditAction::OrderSubmit, serde_json::json!({ "order_id": 1 })),
+                (AuditAction::AuditOverflow, serde_json::json!({ "dropped": 1 })),
+                (AuditAction::OrderSubmit, serde_json::json!({ "order_id": 2 })),
+                (AuditAction::OrderSubmit, serde_json::json!({ "order_id": 3 })),
+            ]
+        );
+    }
+
+    #[test]
+    fn blocking_buffered_sink_keeps_every_event() {
+        let events = InMemoryAuditSink::new();
+        let sink = BufferedAuditSink::new(Arc::new(events.clone()), AuditBufferPolicy { capacity: 4, overflow: AuditOverflow::Block });
+        (0..100).for_each(|n| sink.emit(&event(n)));
+        sink.flush();
+        assert_eq!(events.events().len(), 100);
+        assert_eq!(sink.dropped(), 0);
+    }
+
+    #[test]
+    fn file_store_appends_reindexes_on_open_and_answers_queries() {
+        let path = std::env::temp_dir().join(format!("audit_store_{}.ndjson", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let at = |secs: u64, actor: &str, action: AuditAction| AuditEvent {
+            timestamp_secs: secs,
+            ..AuditEvent::now(actor, action, None, "success")
+        };
+        let query = |actor: Option<&str>, action: Option<AuditAction>, from: Option<u64>, to: Option<u64>, limit: usize| AuditQuery {
+            actor: actor.map(str::to_string),
+            action,
+            from,
+            to,
+            limit,
+        };
+        let times = |events: Vec<AuditEvent>| events.iter().map(|e| e.timestamp_secs).collect::<Vec<_>>();
+
+        let store = FileAuditStore::open(&path).unwrap();
+        store.emit(&at(100, "key-1", AuditAction::OrderSubmit));
+        store.emit(&at(200, "key-2", AuditAction::MarketStateChange));
+        store.emit(&at(300, "key-1", AuditAction::MarketStateChange));
+        drop(store);
+        // A line cut short by a crash is skipped, and the next event starts a line of its own.
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"timestamp_secs":400,"actor":"#).unwrap();
+        drop(file);
+
+        let store = FileAuditStore::open(&path).unwrap();
+        assert_eq!(store.len(), 3);
+        store.emit(&at(500, "key-2", AuditAction::OrderSubmit));
+        let found = |q: AuditQuery| times(store.query(&q).unwrap().unwrap());
+        assert_eq!(found(query(None, None, None, None, 10)), [500, 300, 200, 100]);
+        assert_eq!(found(query(None, Some(AuditAction::MarketStateChange), None, None, 10)), [300, 200]);
+        assert_eq!(found(query(Some("key-1"), Some(AuditAction::MarketStateChange), None, None, 10)), [300]);
+        assert!(found(query(None, Some(AuditAction::Restore), None, None, 10)).is_empty());
+        assert_eq!(found(query(None, None, Some(200), Some(300), 10)), [300, 200]);
+        assert_eq!(found(query(None, None, None, None, 2)), [500, 300]);
+        assert!(found(query(Some("key-9"), None, None, None, 10)).is_empty());
+        assert_eq!(FileAuditStore::open(&path).unwrap().len(), 4);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn events_carry_the_schema_version_and_context_and_version_1_lines_still_parse() {
+        for action in AuditAction::ALL {
+            let json = serde_json::to_value(action).unwrap();
+            assert_eq!(json, action.as_str());
+            assert_eq!(serde_json::from_value::<AuditAction>(json).unwrap(), action);
+        }
+
+        let context = AuditContext {
+            source: AuditSource::Fix,
+            request_id: Some("DESK:VENUE:7".to_string()),
+            client_ip: Some("10.0.0.9".parse().unwrap()),
+        };
+        let event = AuditEvent::now("DESK", AuditAction::FixLogon, None, "success").with_context(&context);
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["schema_version"], AUDIT_SCHEMA_VERSION);
+        assert_eq!((&json["action"], &json["source"]), (&"fix_logon".into(), &"fix".into()));
+        assert_eq!((&json["request_id"], &json["client_ip"]), (&"DESK:VENUE:7".into(), &"10.0.0.9".into()));
+        assert!(serde_json::to_value(AuditEvent::now("engine", AuditAction::OrderExpired, None, "success"))
+            .unwrap()
+            .get("request_id")
+            .is_none());
+
+        let v1: AuditEvent =
+            serde_json::from_str(r#"{"timestamp_secs":1,"actor":"k","action":"order_cancel","outcome":"success"}"#).unwrap();
+        assert_eq!((v1.schema_version, v1.source, v1.action), (1, AuditSource::Unknown, AuditAction::OrderCancel));
+    }
 }
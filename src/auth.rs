@@ -2,17 +2,49 @@
 //!
 //! When `DISABLE_AUTH=true` or `API_KEYS` is unset, all requests are accepted with a default
 //! trader role. Otherwise, validate `Authorization: Bearer <key>` or `X-API-Key: <key>` and
-//! look up the key in `API_KEYS` (format: `key1:role1,key2:role2`; roles: trader, admin, operator).
+//! look up the key in `API_KEYS` (format: `key1:role1,key2:role2`; roles: trader, admin, operator). A key can
+//! also be bound to a trader id (`key:trader:7`): it then enters, cancels, and modifies only that trader's orders,
+//! and gets the streams that only carry that trader's data.
+//!
+//! What a key may do is its [`Permissions`]: its role's defaults ([`Permissions::for_role`]), or the set listed
+//! after the trader id (`key:operator::halt_market+view_audit`). Routes and FIX messages each require one
+//! [`Permission`]; keys can be added, changed, and removed at runtime through `/admin/keys`. A key can further be
+//! entitled to some instruments only (`key:trader:7::1+2`): orders and market data for others are refused.
+//!
+//! WebSocket upgrades may also pass the key as an `api_key` query parameter, for clients (browsers) that cannot
+//! set headers; the socket keeps the identity it was opened with while the key stays unchanged.
+//!
+//! Keys and FIX SenderCompIDs can be limited to client addresses ([`Cidr`] allowlists in `API_KEY_ALLOWED_CIDRS`
+//! and `FIX_ALLOWED_CIDRS`); a credential used from elsewhere is refused.
+//!
+//! Browser clients can exchange a key for a short-lived session token at `POST /auth/login` ([`Sessions`]), renew
+//! it with its refresh token at `/auth/refresh`, and use it wherever the key is accepted.
+//!
+//! Keys are held only as SHA-256 digests and compared in constant time; logs and the audit trail name a key by its
+//! [`KeyId`], never by the key itself.
+//!
+//! Failed authentications are throttled per source address ([`AuthThrottle`]), over REST and FIX alike: after a
+//! few the source is locked out, for longer with every further failure, and each failure is audited.
+//!
+//! FIX sessions authenticate at Logon with Username/Password (553/554), the password being an API key bound to a
+//! trader, or with a SenderCompID listed in `FIX_SENDER_COMP_IDS` (`COMPID:trader_id,...`); see
+//! [`AuthConfig::fix_logon`].
 
 use axum::{
     body::Body,
-    extract::Request,
+    extract::{ConnectInfo, Query, Request},
     http::{header, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::api_error::{ApiError, ErrorCode};
+use crate::audit::{AuditAction, AuditContext, AuditEvent, AuditSink};
+use crate::types::{InstrumentId, TraderId};
 
 /// Role for RBAC (Phase 3 §2). Used by auth and later by permission checks.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -23,6 +55,7 @@ pub enum Role {
 }
 
 impl Role {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         if s.eq_ignore_ascii_case("trader") {
             Some(Role::Trader)
@@ -34,38 +67,634 @@ impl Role {
             None
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Trader => "trader",
+            Role::Admin => "admin",
+            Role::Operator => "operator",
+        }
+    }
+}
+
+/// One thing a key may do. Each route and FIX message requires one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Enter, cancel, and modify orders (for the key's trader when it is bound to one), and read them back:
+    /// `/orders`, `/executions`, `/positions`, `/ws/executions`, and FIX order entry.
+    SubmitOrders,
+    /// Cancel and modify any trader's orders, whatever the key's trader binding.
+    CancelAny,
+    /// Public market data: `/book`, `/ticker`, `/trades`, `/ws/market-data`, and FIX MarketDataRequest.
+    ViewMarketData,
+    /// Add, change, and delist instruments; read and uncross their books (`/admin/instruments`, `/admin/book`).
+    ManageInstruments,
+    /// Market and instrument trading states, emergency halt, and shutdown (`/admin/status`, `/admin/market-state`,
+    /// `/admin/instruments/{id}/state`, `/admin/emergency-halt`, `/admin/shutdown`).
+    HaltMarket,
+    /// The engine event journal (`/events`) and the audit trail (`/admin/audit`).
+    ViewAudit,
+    /// Venue configuration, snapshots, restores, and the startup recovery report (`/admin/config`,
+    /// `/admin/snapshot`, `/admin/restore`, `/admin/recovery`).
+    ManageConfig,
+    /// API keys and their permissions (`/admin/keys`).
+    ManageKeys,
 }
 
-/// Authenticated user (key id + role). Injected by auth middleware when auth succeeds or is disabled.
-#[derive(Clone, Debug)]
+impl Permission {
+    pub const ALL: [Permission; 8] = [
+        Permission::SubmitOrders,
+        Permission::CancelAny,
+        Permission::ViewMarketData,
+        Permission::ManageInstruments,
+        Permission::HaltMarket,
+        Permission::ViewAudit,
+        Permission::ManageConfig,
+        Permission::ManageKeys,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::SubmitOrders => "submit_orders",
+            Permission::CancelAny => "cancel_any",
+            Permission::ViewMarketData => "view_market_data",
+            Permission::ManageInstruments => "manage_instruments",
+            Permission::HaltMarket => "halt_market",
+            Permission::ViewAudit => "view_audit",
+            Permission::ManageConfig => "manage_config",
+            Permission::ManageKeys => "manage_keys",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str().eq_ignore_ascii_case(s))
+    }
+}
+
+/// A set of [`Permission`]s. Serialized as a list of their names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Permissions(u16);
+
+impl Permissions {
+    pub fn of(permissions: &[Permission]) -> Self {
+        Self(permissions.iter().fold(0, |bits, p| bits | 1 << *p as u16))
+    }
+
+    /// Defaults of `role`: traders enter orders and see market data; operators may also cancel any order, manage
+    /// instruments and configuration, halt the market, and read the journal; admins may do all that and manage
+    /// keys.
+    pub fn for_role(role: Role) -> Self {
+        match role {
+            Role::Trader => Self::of(&[Permission::SubmitOrders, Permission::ViewMarketData]),
+            Role::Operator => Self::of(&Permission::ALL[..7]),
+            Role::Admin => Self::of(&Permission::ALL),
+        }
+    }
+
+    pub fn contains(&self, permission: Permission) -> bool {
+        self.0 & 1 << permission as u16 != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Permission> + '_ {
+        Permission::ALL.into_iter().filter(|p| self.contains(*p))
+    }
+}
+
+impl serde::Serialize for Permissions {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Permissions {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::of(&Vec::<Permission>::deserialize(deserializer)?))
+    }
+}
+
+/// Names an API key without revealing it: the key's SHA-256 digest. Keys are held, compared, and logged only by it;
+/// it displays as `key-` and the digest's first 8 hex digits, which is how keys appear in logs and the audit trail.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyId([u8; 32]);
+
+impl KeyId {
+    pub fn of(key: &str) -> Self {
+        let digest = ring::digest::digest(&ring::digest::SHA256, key.as_bytes());
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(digest.as_ref());
+        Self(bytes)
+    }
+
+    /// Equality in time independent of where the digests differ.
+    fn constant_time_eq(&self, other: &Self) -> bool {
+        self.0.iter().zip(other.0.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+impl std::fmt::Display for KeyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "key-")?;
+        self.0[..4].iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl std::fmt::Debug for KeyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl serde::Serialize for KeyId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Authenticated user (key id, role, and permissions). Injected by auth middleware when auth succeeds or is
+/// disabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct AuthUser {
-    pub key_id: Option<String>,
+    pub key_id: Option<KeyId>,
     pub role: Role,
+    /// Trader the key is bound to in `API_KEYS`, if any.
+    pub trader_id: Option<TraderId>,
+    pub permissions: Permissions,
+    /// Instruments the key is entitled to; `None` for every instrument.
+    pub instruments: Option<Vec<InstrumentId>>,
+}
+
+impl AuthUser {
+    /// The user authenticated by the key `key_id` with `grant`.
+    pub fn from_grant(key_id: KeyId, grant: KeyGrant) -> Self {
+        Self {
+            key_id: Some(key_id),
+            role: grant.role,
+            trader_id: grant.trader_id,
+            permissions: grant.permissions,
+            instruments: grant.instruments,
+        }
+    }
+
+    /// Who the audit trail names for this user: its [`KeyId`], or `anonymous`.
+    pub fn actor(&self) -> String {
+        self.key_id.map_or_else(|| "anonymous".to_string(), |id| id.to_string())
+    }
+
+    /// Whether the key may trade and see market data of `instrument_id`.
+    pub fn entitled(&self, instrument_id: InstrumentId) -> bool {
+        entitled(&self.instruments, instrument_id)
+    }
+}
+
+/// Whether an entitlement (`None` for every instrument) includes `instrument_id`.
+pub fn entitled(instruments: &Option<Vec<InstrumentId>>, instrument_id: InstrumentId) -> bool {
+    instruments.as_ref().is_none_or(|ids| ids.contains(&instrument_id))
 }
 
+/// With auth disabled: a trader that may enter orders and see market data. The journal and the audit trail
+/// (`view_audit`) name traders, addresses, and order contents, so they stay closed, like the rest of `/admin/*`.
 impl Default for AuthUser {
     fn default() -> Self {
         Self {
             key_id: None,
             role: Role::Trader,
+            trader_id: None,
+            permissions: Permissions::of(&[Permission::SubmitOrders, Permission::ViewMarketData]),
+            instruments: None,
         }
     }
 }
 
-/// Returns `Ok(())` if `user.role` is Admin or Operator; otherwise returns a 403 Response.
-/// Use in admin-only handlers: `require_admin_or_operator(&auth)?`.
-pub fn require_admin_or_operator(user: &AuthUser) -> Result<(), Response> {
-    match user.role {
-        Role::Admin | Role::Operator => Ok(()),
-        Role::Trader => Err((StatusCode::FORBIDDEN, "admin or operator role required").into_response()),
+/// Returns `Ok(())` if `user` holds `permission`; otherwise returns a 403 Response.
+/// Use in handlers: `require_permission(&auth, Permission::HaltMarket)?`.
+#[allow(clippy::result_large_err)]
+pub fn require_permission(user: &AuthUser, permission: Permission) -> Result<(), Response> {
+    if user.permissions.contains(permission) {
+        return Ok(());
+    }
+    let message = format!("permission {} required", permission.as_str());
+    Err(ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, message).into_response())
+}
+
+/// Route middleware: 403 unless the authenticated user holds `permission`. Runs inside the auth middleware.
+pub async fn require_permission_for_route(req: Request<Body>, next: Next, permission: Permission) -> Response {
+    let user = req.extensions().get::<AuthUser>().cloned().unwrap_or_default();
+    match require_permission(&user, permission) {
+        Ok(()) => next.run(req).await,
+        Err(r) => r,
+    }
+}
+
+/// Returns `Ok(())` if `user` may enter or manage orders of `trader_id`: a key bound to a trader acts for that
+/// trader only; unbound keys (and anonymous users with auth disabled) for any. Otherwise returns a 403 Response
+/// naming `field`.
+#[allow(clippy::result_large_err)]
+pub fn require_trader(user: &AuthUser, trader_id: TraderId, field: &str) -> Result<(), Response> {
+    match user.trader_id {
+        Some(bound) if bound != trader_id => {
+            let message = format!("API key is bound to trader {}, not trader {}", bound.0, trader_id.0);
+            Err(ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, message).with_field(field).into_response())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns `Ok(())` if `user` is entitled to `instrument_id`; otherwise returns a 403 Response naming `field`.
+#[allow(clippy::result_large_err)]
+pub fn require_instrument(user: &AuthUser, instrument_id: InstrumentId, field: &str) -> Result<(), Response> {
+    if user.entitled(instrument_id) {
+        return Ok(());
+    }
+    let message = format!("API key is not entitled to instrument {}", instrument_id.0);
+    Err(ApiError::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, message).with_field(field).into_response())
+}
+
+/// Like [`require_trader`] for an order that already exists: a key with [`Permission::CancelAny`] may cancel or
+/// modify any trader's orders.
+#[allow(clippy::result_large_err)]
+pub fn require_order_owner(user: &AuthUser, trader_id: TraderId, field: &str) -> Result<(), Response> {
+    if user.permissions.contains(Permission::CancelAny) {
+        return Ok(());
     }
+    require_trader(user, trader_id, field)
+}
+
+/// An IP network in CIDR notation (`10.0.0.0/8`, `2001:db8::/32`); a bare address is a single host. Serialized as
+/// that string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Whether `addr` is in the network. IPv4-mapped IPv6 addresses match as IPv4.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let (network, addr, bits) = match (self.network, addr.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => (u32::from(network) as u128, u32::from(addr) as u128, 32),
+            (IpAddr::V6(network), IpAddr::V6(addr)) => (u128::from(network), u128::from(addr), 128),
+            _ => return false,
+        };
+        let shift = bits - u32::from(self.prefix_len);
+        network.checked_shr(shift).unwrap_or(0) == addr.checked_shr(shift).unwrap_or(0)
+    }
+}
+
+impl std::str::FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let (addr, prefix_len) = s.split_once('/').map_or((s, None), |(a, p)| (a, Some(p)));
+        let network: IpAddr = addr.parse().map_err(|_| format!("invalid address in CIDR {}", s))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).ok_or_else(|| format!("invalid prefix length in CIDR {}", s))?,
+            None => max,
+        };
+        Ok(Self { network, prefix_len })
+    }
+}
+
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+impl serde::Serialize for Cidr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Cidr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Whether an address allowlist (`None` for any address) admits `peer`. An unknown peer only passes `None`.
+pub fn address_allowed(cidrs: &Option<Vec<Cidr>>, peer: Option<IpAddr>) -> bool {
+    match cidrs {
+        None => true,
+        Some(cidrs) => peer.is_some_and(|ip| cidrs.iter().any(|cidr| cidr.contains(ip))),
+    }
+}
+
+fn describe_peer(peer: Option<IpAddr>) -> String {
+    peer.map_or_else(|| "an unknown address".to_string(), |ip| ip.to_canonical().to_string())
+}
+
+/// What `API_KEYS` (or `/admin/keys`) grants one key.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct KeyGrant {
+    #[serde(serialize_with = "serialize_role")]
+    pub role: Role,
+    pub trader_id: Option<TraderId>,
+    pub permissions: Permissions,
+    /// Instruments the key is entitled to; `None` for every instrument.
+    pub instruments: Option<Vec<InstrumentId>>,
+    /// Client addresses the key may be used from; `None` for any.
+    pub allowed_cidrs: Option<Vec<Cidr>>,
+}
+
+impl KeyGrant {
+    /// `role` with its default permissions.
+    pub fn new(role: Role, trader_id: Option<TraderId>) -> Self {
+        Self {
+            role,
+            trader_id,
+            permissions: Permissions::for_role(role),
+            instruments: None,
+            allowed_cidrs: None,
+        }
+    }
+}
+
+fn serialize_role<S: serde::Serializer>(role: &Role, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(role.as_str())
+}
+
+/// Authenticated FIX session: the trader it is bound to (`None` when unbound), what it may do, and the
+/// instruments it may do it for (`None` for every instrument).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixLogon {
+    pub trader_id: Option<TraderId>,
+    pub permissions: Permissions,
+    pub instruments: Option<Vec<InstrumentId>>,
+}
+
+/// When failed authentications from one source lock it out: after `max_failures` in a row, for `lockout`, doubled
+/// with every further failure up to `max_lockout`. A success, or `max_lockout` without a failure, clears the count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuthThrottlePolicy {
+    pub max_failures: u32,
+    pub lockout: Duration,
+    pub max_lockout: Duration,
+}
+
+impl Default for AuthThrottlePolicy {
+    /// 5 failures, then 1 s, doubling up to 15 min.
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            lockout: Duration::from_secs(1),
+            max_lockout: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+impl AuthThrottlePolicy {
+    /// Read `AUTH_MAX_FAILURES`, `AUTH_LOCKOUT_MS`, and `AUTH_MAX_LOCKOUT_MS`; unset or unparsable variables keep
+    /// the default.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse::<u64>().ok());
+        let default = Self::default();
+        Self {
+            max_failures: var("AUTH_MAX_FAILURES").map_or(default.max_failures, |n| n as u32),
+            lockout: var("AUTH_LOCKOUT_MS").map_or(default.lockout, Duration::from_millis),
+            max_lockout: var("AUTH_MAX_LOCKOUT_MS").map_or(default.max_lockout, Duration::from_millis),
+        }
+    }
+}
+
+/// Sources tracked before quiet ones are dropped.
+const THROTTLE_MAX_SOURCES: usize = 10_000;
+
+/// The one source all clients without a known address share, so varying the key tried gets no fresh attempts.
+const UNKNOWN_PEER: &str = "unknown-peer";
+
+/// Failed authentications of one source, none more than the policy's `max_lockout` after the one before.
+struct Failures {
+    in_a_row: u32,
+    last: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Failed authentications in a row by source: the client IP, or one shared source for clients whose IP is unknown.
+pub struct AuthThrottle {
+    policy: AuthThrottlePolicy,
+    sources: Mutex<HashMap<String, Failures>>,
+}
+
+impl AuthThrottle {
+    pub fn new(policy: AuthThrottlePolicy) -> Self {
+        Self {
+            policy,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `Err` with the time left while `source` is locked out.
+    pub fn check(&self, source: &str, now: Instant) -> Result<(), Duration> {
+        let sources = self.sources.lock().expect("lock");
+        match sources.get(source).and_then(|f| f.locked_until) {
+            Some(until) if until > now => Err(until - now),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a failure from `source`; returns the failures in a row and the lockout it starts, if any.
+    pub fn failure(&self, source: &str, now: Instant) -> (u32, Option<Duration>) {
+        let policy = self.policy;
+        let mut sources = self.sources.lock().expect("lock");
+        if sources.len() >= THROTTLE_MAX_SOURCES {
+            sources.retain(|_, f| now.saturating_duration_since(f.last) < policy.max_lockout);
+        }
+        let failures = sources.entry(source.to_string()).or_insert(Failures {
+            in_a_row: 0,
+            last: now,
+            locked_until: None,
+        });
+        if now.saturating_duration_since(failures.last) >= policy.max_lockout {
+            failures.in_a_row = 0;
+        }
+        failures.in_a_row += 1;
+        failures.last = now;
+        if failures.in_a_row < policy.max_failures {
+            return (failures.in_a_row, None);
+        }
+        let doublings = (failures.in_a_row - policy.max_failures).min(31);
+        let lockout = policy.lockout.saturating_mul(1 << doublings).min(policy.max_lockout);
+        failures.locked_until = Some(now + lockout);
+        (failures.in_a_row, Some(lockout))
+    }
+
+    /// Clear the failures of `source` after it authenticated, so clients sharing an address (behind one NAT) are
+    /// not locked out by failures before it.
+    pub fn success(&self, source: &str) {
+        self.sources.lock().expect("lock").remove(source);
+    }
+}
+
+/// How long the tokens of `/auth/login` last: the session token `ttl`, the refresh token `refresh_ttl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionPolicy {
+    pub ttl: Duration,
+    pub refresh_ttl: Duration,
 }
 
-/// Auth configuration: disable flag and key → role map. Built from env.
+impl Default for SessionPolicy {
+    /// 15 min session tokens, refreshable for 12 h.
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(15 * 60),
+            refresh_ttl: Duration::from_secs(12 * 60 * 60),
+        }
+    }
+}
+
+impl SessionPolicy {
+    /// Read `AUTH_SESSION_TTL_MS` and `AUTH_REFRESH_TTL_MS`; unset or unparsable variables keep the default.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse::<u64>().ok());
+        let default = Self::default();
+        Self {
+            ttl: var("AUTH_SESSION_TTL_MS").map_or(default.ttl, Duration::from_millis),
+            refresh_ttl: var("AUTH_REFRESH_TTL_MS").map_or(default.refresh_ttl, Duration::from_millis),
+        }
+    }
+}
+
+/// Tokens issued by `/auth/login` and `/auth/refresh`; lifetimes in seconds.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct SessionTokens {
+    pub token: String,
+    pub expires_in: u64,
+    pub refresh_token: String,
+    pub refresh_expires_in: u64,
+}
+
+/// One login: the key it stands for and its current token pair.
+struct Session {
+    key_id: KeyId,
+    token: String,
+    expires: Instant,
+    refresh_expires: Instant,
+}
+
+/// Sessions by refresh token, and session tokens to their refresh token.
+#[derive(Default)]
+struct SessionTable {
+    by_refresh: HashMap<String, Session>,
+    refresh_of: HashMap<String, String>,
+}
+
+/// Short-lived session tokens standing for API keys, so browsers need not hold the keys. A token authenticates as
+/// its key, with the key's current grant: removing or changing the key affects its sessions too.
+pub struct Sessions {
+    policy: SessionPolicy,
+    table: Mutex<SessionTable>,
+}
+
+impl Sessions {
+    pub fn new(policy: SessionPolicy) -> Self {
+        Self {
+            policy,
+            table: Mutex::new(SessionTable::default()),
+        }
+    }
+
+    /// Start a session for the key `key_id`.
+    pub fn issue(&self, key_id: KeyId, now: Instant) -> SessionTokens {
+        let mut table = self.table.lock().expect("lock");
+        let SessionTable { by_refresh, refresh_of } = &mut *table;
+        by_refresh.retain(|_, session| session.refresh_expires > now);
+        refresh_of.retain(|_, refresh| by_refresh.contains_key(refresh));
+        self.insert(&mut table, key_id, now)
+    }
+
+    fn insert(&self, table: &mut SessionTable, key_id: KeyId, now: Instant) -> SessionTokens {
+        let (token, refresh_token) = (new_token(), new_token());
+        table.refresh_of.insert(token.clone(), refresh_token.clone());
+        table.by_refresh.insert(
+            refresh_token.clone(),
+            Session {
+                key_id,
+                token: token.clone(),
+                expires: now + self.policy.ttl,
+                refresh_expires: now + self.policy.refresh_ttl,
+            },
+        );
+        SessionTokens {
+            token,
+            expires_in: self.policy.ttl.as_secs(),
+            refresh_token,
+            refresh_expires_in: self.policy.refresh_ttl.as_secs(),
+        }
+    }
+
+    /// The key an unexpired session token stands for.
+    pub fn key_of_token(&self, token: &str, now: Instant) -> Option<KeyId> {
+        let table = self.table.lock().expect("lock");
+        let session = table.by_refresh.get(table.refresh_of.get(token)?)?;
+        (session.token == token && session.expires > now).then_some(session.key_id)
+    }
+
+    /// The key an unexpired refresh token stands for.
+    pub fn key_of_refresh_token(&self, refresh_token: &str, now: Instant) -> Option<KeyId> {
+        let table = self.table.lock().expect("lock");
+        let session = table.by_refresh.get(refresh_token)?;
+        (session.refresh_expires > now).then_some(session.key_id)
+    }
+
+    /// Replace the session of `refresh_token` with a new token pair; both old tokens stop working.
+    pub fn refresh(&self, refresh_token: &str, now: Instant) -> Option<SessionTokens> {
+        let mut table = self.table.lock().expect("lock");
+        let session = table.by_refresh.remove(refresh_token)?;
+        table.refresh_of.remove(&session.token);
+        (session.refresh_expires > now).then(|| self.insert(&mut table, session.key_id, now))
+    }
+
+    /// End the session of a session or refresh token; returns whether there was one.
+    pub fn end(&self, token: &str) -> bool {
+        let mut table = self.table.lock().expect("lock");
+        let refresh_token = table.refresh_of.get(token).cloned().unwrap_or_else(|| token.to_string());
+        match table.by_refresh.remove(&refresh_token) {
+            Some(session) => {
+                table.refresh_of.remove(&session.token);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 256 random bits, hex-encoded.
+fn new_token() -> String {
+    use rand::Rng;
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// What a request authenticated with; injected by the auth middleware next to its [`AuthUser`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Credential {
+    /// Nothing: auth is disabled.
+    Anonymous,
+    ApiKey,
+    /// A session token from `/auth/login`.
+    SessionToken(String),
+    /// A refresh token; accepted by `/auth/refresh` only.
+    RefreshToken(String),
+}
+
+/// Auth configuration: disable flag, key → grant map, FIX SenderCompID allowlist and address allowlists, the
+/// failed-authentication throttle, and login sessions. Built from env; the keys, throttle, and sessions are shared
+/// by clones, so changes through `/admin/keys` reach every protocol.
 #[derive(Clone)]
 pub struct AuthConfig {
     pub disable: bool,
-    keys: Arc<HashMap<String, Role>>,
+    /// Grants by key digest; the keys themselves are not kept.
+    keys: Arc<RwLock<HashMap<KeyId, KeyGrant>>>,
+    fix_sender_comp_ids: Arc<HashMap<String, TraderId>>,
+    /// SenderCompID → client addresses its FIX sessions may log on from.
+    fix_allowed_cidrs: Arc<HashMap<String, Vec<Cidr>>>,
+    throttle: Arc<AuthThrottle>,
+    sessions: Arc<Sessions>,
 }
 
 impl AuthConfig {
@@ -73,70 +702,335 @@ impl AuthConfig {
     pub fn disabled() -> Self {
         Self {
             disable: true,
-            keys: Arc::new(HashMap::new()),
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            fix_sender_comp_ids: Arc::new(HashMap::new()),
+            fix_allowed_cidrs: Arc::new(HashMap::new()),
+            throttle: Arc::new(AuthThrottle::new(AuthThrottlePolicy::default())),
+            sessions: Arc::new(Sessions::new(SessionPolicy::default())),
         }
     }
 
-    /// Build from key:role string (e.g. "key1:trader,key2:admin"). For tests.
+    /// Build from key:role string (e.g. "key1:trader:7,key2:admin"). For tests.
     pub fn from_keys(keys: &str) -> Self {
-        let map: HashMap<String, Role> = keys
-            .split(',')
-            .filter_map(|part| {
-                let part = part.trim();
-                let mut split = part.splitn(2, ':');
-                let key = split.next()?.trim().to_string();
-                let role_str = split.next()?.trim();
-                let role = Role::from_str(role_str)?;
-                if key.is_empty() {
-                    return None;
-                }
-                Some((key, role))
-            })
-            .collect();
+        let map = parse_keys(keys);
         Self {
             disable: map.is_empty(),
-            keys: Arc::new(map),
+            keys: Arc::new(RwLock::new(map)),
+            fix_sender_comp_ids: Arc::new(HashMap::new()),
+            fix_allowed_cidrs: Arc::new(HashMap::new()),
+            throttle: Arc::new(AuthThrottle::new(AuthThrottlePolicy::default())),
+            sessions: Arc::new(Sessions::new(SessionPolicy::default())),
+        }
+    }
+
+    /// Expire login sessions by `policy` instead of the default. For tests.
+    pub fn with_session_policy(mut self, policy: SessionPolicy) -> Self {
+        self.sessions = Arc::new(Sessions::new(policy));
+        self
+    }
+
+    /// Throttle failed authentications with `policy` instead of the default. For tests.
+    pub fn with_throttle_policy(mut self, policy: AuthThrottlePolicy) -> Self {
+        self.throttle = Arc::new(AuthThrottle::new(policy));
+        self
+    }
+
+    /// Allow FIX logons from `COMPID:trader_id` entries (e.g. "DESK1:7,DESK2:8") without credentials. For tests.
+    pub fn with_fix_sender_comp_ids(mut self, ids: &str) -> Self {
+        self.fix_sender_comp_ids = Arc::new(parse_sender_comp_ids(ids));
+        self
+    }
+
+    /// Limit keys to client addresses with `key=cidr+cidr` entries (e.g. "k1=10.0.0.0/8+192.168.1.5"). For tests.
+    pub fn with_key_allowed_cidrs(self, cidrs: &str) -> Self {
+        self.apply_key_allowed_cidrs(parse_allowed_cidrs(cidrs));
+        self
+    }
+
+    /// Limit FIX SenderCompIDs to client addresses with `COMPID=cidr+cidr` entries. For tests.
+    pub fn with_fix_allowed_cidrs(mut self, cidrs: &str) -> Self {
+        self.fix_allowed_cidrs = Arc::new(parse_allowed_cidrs(cidrs));
+        self
+    }
+
+    fn apply_key_allowed_cidrs(&self, cidrs: HashMap<String, Vec<Cidr>>) {
+        let mut keys = self.keys.write().expect("lock");
+        for (key, cidrs) in cidrs {
+            let key_id = KeyId::of(&key);
+            match keys.get_mut(&key_id) {
+                Some(grant) => grant.allowed_cidrs = Some(cidrs),
+                None => log::warn!("allowed CIDRs for unknown API key {} ignored", key_id),
+            }
         }
     }
 
     /// Load from env: `DISABLE_AUTH=true` or unset `API_KEYS` => auth disabled.
-    /// `API_KEYS=secret1:trader,secret2:admin` => comma-separated key:role pairs.
+    /// `API_KEYS=secret1:trader:7,secret2:admin` => comma-separated key:role pairs, each optionally bound to a
+    /// trader id, given its own permissions, and entitled to some instruments only
+    /// (`key:role:[trader_id]:[perm+perm]:[instrument+instrument]`). `API_KEY_ALLOWED_CIDRS` and
+    /// `FIX_ALLOWED_CIDRS` (`name=cidr+cidr,...`) limit keys and FIX SenderCompIDs to client addresses.
     pub fn from_env() -> Self {
         let disable = std::env::var("DISABLE_AUTH")
             .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
             .unwrap_or(false);
 
-        let keys = std::env::var("API_KEYS").ok().map(|s| {
-            let map: HashMap<String, Role> = s
-                .split(',')
-                .filter_map(|part| {
-                    let part = part.trim();
-                    let mut split = part.splitn(2, ':');
-                    let key = split.next()?.trim().to_string();
-                    let role_str = split.next()?.trim();
-                    let role = Role::from_str(role_str)?;
-                    if key.is_empty() {
-                        return None;
-                    }
-                    Some((key, role))
-                })
-                .collect();
-            Arc::new(map)
-        });
-
-        let keys = keys.unwrap_or_else(|| Arc::new(HashMap::new()));
+        let keys = std::env::var("API_KEYS").ok().map(|s| parse_keys(&s)).unwrap_or_default();
 
         let disable = disable || keys.is_empty();
 
-        Self { disable, keys }
+        let fix_sender_comp_ids = std::env::var("FIX_SENDER_COMP_IDS")
+            .map(|s| parse_sender_comp_ids(&s))
+            .unwrap_or_default();
+
+        let allowed_cidrs = |name: &str| std::env::var(name).map(|s| parse_allowed_cidrs(&s)).unwrap_or_default();
+
+        let config = Self {
+            disable,
+            keys: Arc::new(RwLock::new(keys)),
+            fix_sender_comp_ids: Arc::new(fix_sender_comp_ids),
+            fix_allowed_cidrs: Arc::new(allowed_cidrs("FIX_ALLOWED_CIDRS")),
+            throttle: Arc::new(AuthThrottle::new(AuthThrottlePolicy::from_env())),
+            sessions: Arc::new(Sessions::new(SessionPolicy::from_env())),
+        };
+        config.apply_key_allowed_cidrs(allowed_cidrs("API_KEY_ALLOWED_CIDRS"));
+        config
     }
 
     pub fn lookup(&self, key: &str) -> Option<Role> {
-        self.keys.get(key).copied()
+        self.grant(key).map(|g| g.role)
+    }
+
+    /// Role, trader binding, permissions, and instrument entitlement of `key`. The key's digest is compared with
+    /// every stored one in constant time, so neither the time taken nor an early exit tells how close a guess was.
+    pub fn grant(&self, key: &str) -> Option<KeyGrant> {
+        self.grant_with_id(key).map(|(_, grant)| grant)
+    }
+
+    fn grant_with_id(&self, key: &str) -> Option<(KeyId, KeyGrant)> {
+        let key_id = KeyId::of(key);
+        let keys = self.keys.read().expect("lock");
+        let mut found = None;
+        for (stored, grant) in keys.iter() {
+            if stored.constant_time_eq(&key_id) {
+                found = Some((*stored, grant.clone()));
+            }
+        }
+        found
+    }
+
+    /// Grant of the key named `key_id`.
+    pub fn grant_of(&self, key_id: &KeyId) -> Option<KeyGrant> {
+        self.keys.read().expect("lock").get(key_id).cloned()
+    }
+
+    /// Every key id and its grant, by key id.
+    pub fn keys(&self) -> Vec<(KeyId, KeyGrant)> {
+        let mut keys: Vec<_> = self.keys.read().expect("lock").iter().map(|(k, g)| (*k, g.clone())).collect();
+        keys.sort_by_key(|(key_id, _)| *key_id);
+        keys
+    }
+
+    /// The stored key displayed as `id` (e.g. `key-1a2b3c4d`), if exactly one is.
+    pub fn find_key_id(&self, id: &str) -> Option<KeyId> {
+        let keys = self.keys.read().expect("lock");
+        let mut matching = keys.keys().filter(|key_id| key_id.to_string() == id);
+        match (matching.next(), matching.next()) {
+            (Some(key_id), None) => Some(*key_id),
+            _ => None,
+        }
+    }
+
+    /// Add `key`, or replace its grant; returns the previous one.
+    pub fn set_key(&self, key: &str, grant: KeyGrant) -> Option<KeyGrant> {
+        self.keys.write().expect("lock").insert(KeyId::of(key), grant)
+    }
+
+    /// Remove the key named `key_id`; returns its grant if it existed.
+    pub fn remove_key(&self, key_id: &KeyId) -> Option<KeyGrant> {
+        self.keys.write().expect("lock").remove(key_id)
+    }
+
+    /// Login sessions of `/auth/login`.
+    pub fn sessions(&self) -> &Sessions {
+        &self.sessions
+    }
+
+    /// The key and grant `credential` authenticates as: an API key, or an unexpired session token of a key that
+    /// still exists. With `refresh`, only a refresh token is accepted instead.
+    pub fn authenticate(&self, credential: &str, refresh: bool, now: Instant) -> Option<(KeyId, KeyGrant, Credential)> {
+        if refresh {
+            let key_id = self.sessions.key_of_refresh_token(credential, now)?;
+            let grant = self.grant_of(&key_id)?;
+            return Some((key_id, grant, Credential::RefreshToken(credential.to_string())));
+        }
+        if let Some((key_id, grant)) = self.grant_with_id(credential) {
+            return Some((key_id, grant, Credential::ApiKey));
+        }
+        let key_id = self.sessions.key_of_token(credential, now)?;
+        let grant = self.grant_of(&key_id)?;
+        Some((key_id, grant, Credential::SessionToken(credential.to_string())))
+    }
+
+    /// Whether `user` still holds what it authenticated with: false once its key was removed or its grant changed.
+    /// Always true for anonymous users (auth disabled).
+    pub fn is_current(&self, user: &AuthUser) -> bool {
+        match user.key_id {
+            Some(key_id) => self.grant_of(&key_id).is_some_and(|grant| AuthUser::from_grant(key_id, grant) == *user),
+            None => true,
+        }
+    }
+
+    /// Authenticate a FIX Logon and return the trader the session is bound to and its permissions. Username (553)
+    /// and Password (554, an API key bound to a trader) take precedence, and the session gets the key's
+    /// permissions and instrument entitlement; otherwise SenderCompID (49) must be in `FIX_SENDER_COMP_IDS`, and the session gets a trader's.
+    /// With auth disabled and no allowlist every logon is accepted unbound (`trader_id` `None`), and orders keep
+    /// their own Account (1). Failed logons are throttled per `peer` address, sharing the count of REST requests from
+    /// it, since the CompIDs are the client's to choose (in one shared bucket when the address is unknown). The
+    /// session's `peer` address must be allowed for the key and for the SenderCompID, where they have allowlists.
+    pub fn fix_logon(
+        &self,
+        sender_comp_id: Option<&str>,
+        username: Option<&str>,
+        password: Option<&str>,
+        peer: Option<IpAddr>,
+    ) -> Result<FixLogon, String> {
+        if self.disable && self.fix_sender_comp_ids.is_empty() {
+            return self.check_fix_logon(sender_comp_id, username, password, peer);
+        }
+        let source = peer.map_or_else(|| UNKNOWN_PEER.to_string(), |ip| ip.to_canonical().to_string());
+        let now = Instant::now();
+        if let Err(left) = self.throttle.check(&source, now) {
+            return Err(format!("too many failed logons; retry in {} s", left.as_secs().max(1)));
+        }
+        let result = self.check_fix_logon(sender_comp_id, username, password, peer);
+        match &result {
+            Ok(_) => self.throttle.success(&source),
+            Err(e) => {
+                let (failures, lockout) = self.throttle.failure(&source, now);
+                log::warn!("failed FIX logon {} from {} as {}: {}", failures, source, sender_comp_id.unwrap_or("(missing)"), e);
+                if let Some(lockout) = lockout {
+                    log::warn!("{} locked out for {} ms", source, lockout.as_millis());
+                }
+            }
+        }
+        result
+    }
+
+    fn check_fix_logon(
+        &self,
+        sender_comp_id: Option<&str>,
+        username: Option<&str>,
+        password: Option<&str>,
+        peer: Option<IpAddr>,
+    ) -> Result<FixLogon, String> {
+        if let Some(id) = sender_comp_id {
+            let cidrs = self.fix_allowed_cidrs.get(id).cloned();
+            if !address_allowed(&cidrs, peer) {
+                return Err(format!("SenderCompID {} is not allowed from {}", id, describe_peer(peer)));
+            }
+        }
+        let trader = |trader_id| FixLogon {
+            trader_id,
+            permissions: Permissions::for_role(Role::Trader),
+            instruments: None,
+        };
+        if self.disable && self.fix_sender_comp_ids.is_empty() {
+            return Ok(trader(None));
+        }
+        if username.is_some() || password.is_some() {
+            let (Some(username), Some(password)) = (username.filter(|u| !u.is_empty()), password) else {
+                return Err("Username (553) and Password (554) are both required".to_string());
+            };
+            let grant = self
+                .grant(password)
+                .ok_or_else(|| format!("invalid Password (554) for Username {}", username))?;
+            if !address_allowed(&grant.allowed_cidrs, peer) {
+                return Err(format!("API key is not allowed from {}", describe_peer(peer)));
+            }
+            return match grant.trader_id {
+                Some(trader_id) => Ok(FixLogon {
+                    trader_id: Some(trader_id),
+                    permissions: grant.permissions,
+                    instruments: grant.instruments,
+                }),
+                None => Err("API key is not bound to a trader".to_string()),
+            };
+        }
+        match sender_comp_id.and_then(|id| self.fix_sender_comp_ids.get(id)) {
+            Some(trader_id) => Ok(trader(Some(*trader_id))),
+            None => Err(format!(
+                "SenderCompID {} is not allowed without Username (553) and Password (554)",
+                sender_comp_id.unwrap_or("(missing)")
+            )),
+        }
     }
 }
 
-/// Returns the API key from `Authorization: Bearer <key>` or `X-API-Key: <key>`.
+/// Parse `name=cidr+cidr` entries separated by commas. A name whose list has a malformed CIDR gets an empty list,
+/// which admits no address, rather than none.
+fn parse_allowed_cidrs(s: &str) -> HashMap<String, Vec<Cidr>> {
+    s.split(',')
+        .filter_map(|part| {
+            let (name, cidrs) = part.trim().split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let cidrs = cidrs.split('+').map(str::parse).collect::<Result<Vec<Cidr>, _>>().unwrap_or_else(|e| {
+                log::warn!("allowed CIDRs of {}: {}; no address is allowed", name, e);
+                Vec::new()
+            });
+            Some((name.to_string(), cidrs))
+        })
+        .collect()
+}
+
+/// Parse `COMPID:trader_id` entries separated by commas, skipping malformed ones.
+fn parse_sender_comp_ids(s: &str) -> HashMap<String, TraderId> {
+    s.split(',')
+        .filter_map(|part| {
+            let (id, trader_id) = part.trim().split_once(':')?;
+            let id = id.trim();
+            if id.is_empty() {
+                return None;
+            }
+            Some((id.to_string(), TraderId(trader_id.trim().parse().ok()?)))
+        })
+        .collect()
+}
+
+/// Parse `key:role[:trader_id[:permission+permission...[:instrument_id+instrument_id...]]]` entries separated by
+/// commas, skipping malformed ones. The trader id and permissions may be empty when more follows; without
+/// permissions the key gets its role's defaults, without instruments it is entitled to all.
+fn parse_keys(s: &str) -> HashMap<KeyId, KeyGrant> {
+    s.split(',')
+        .filter_map(|part| {
+            let mut split = part.trim().splitn(5, ':');
+            let key = split.next()?.trim().to_string();
+            let role = Role::from_str(split.next()?.trim())?;
+            let trader_id = match split.next().map(str::trim) {
+                Some("") | None => None,
+                Some(id) => Some(TraderId(id.parse().ok()?)),
+            };
+            let mut grant = KeyGrant::new(role, trader_id);
+            if let Some(permissions) = split.next().filter(|p| !p.trim().is_empty()) {
+                let permissions: Option<Vec<_>> = permissions.split('+').map(|p| Permission::from_str(p.trim())).collect();
+                grant.permissions = Permissions::of(&permissions?);
+            }
+            if let Some(instruments) = split.next() {
+                let instruments: Option<Vec<_>> = instruments.split('+').map(|id| id.trim().parse().ok().map(InstrumentId)).collect();
+                grant.instruments = Some(instruments?);
+            }
+            if key.is_empty() {
+                return None;
+            }
+            Some((KeyId::of(&key), grant))
+        })
+        .collect()
+}
+
+/// Returns the API key from `Authorization: Bearer <key>` or `X-API-Key: <key>`, or on a WebSocket upgrade from
+/// the `api_key` query parameter.
 fn get_api_key_from_request(req: &Request) -> Option<String> {
     if let Some(v) = req.headers().get(header::AUTHORIZATION) {
         if let Ok(s) = v.to_str() {
@@ -151,37 +1045,298 @@ fn get_api_key_from_request(req: &Request) -> Option<String> {
             return Some(s.trim().to_string());
         }
     }
+    let upgrade = req.headers().get(header::UPGRADE).and_then(|v| v.to_str().ok());
+    if upgrade.is_some_and(|v| v.eq_ignore_ascii_case("websocket")) {
+        let Query(mut params) = Query::<HashMap<String, String>>::try_from_uri(req.uri()).ok()?;
+        return params.remove("api_key").map(|key| key.trim().to_string());
+    }
     None
 }
 
 /// Auth middleware: when auth is disabled, injects `AuthUser { role: Trader }` and continues.
-/// Otherwise, requires a valid API key and injects `AuthUser { key_id, role }`; returns 401 if missing/invalid.
+/// Otherwise, requires a valid API key and injects its [`AuthUser`]; returns 401 if missing/invalid. Failures are
+/// counted per client IP (in one shared bucket when the server does not record peers), cleared by a success, and
+/// audited as `auth_failure`;
+/// a source over the [`AuthThrottlePolicy`] gets 429 `rate_limited` with `Retry-After` until its lockout ends. A
+/// key used from an address outside its [`KeyGrant::allowed_cidrs`] gets 403 and counts as a failure. A session
+/// token authenticates as its key (see [`AuthConfig::authenticate`]); the [`Credential`] used is injected as well.
 pub async fn require_api_key_or_anonymous(
     mut req: Request<Body>,
     next: Next,
     config: AuthConfig,
+    audit: Arc<dyn AuditSink + Send + Sync>,
 ) -> Response {
     if config.disable {
         req.extensions_mut().insert(AuthUser::default());
+        req.extensions_mut().insert(Credential::Anonymous);
         return next.run(req).await;
     }
 
-    let key = match get_api_key_from_request(&req) {
-        Some(k) if !k.is_empty() => k,
-        _ => {
-            return (StatusCode::UNAUTHORIZED, "missing or invalid Authorization or X-API-Key")
-                .into_response();
+    let key = get_api_key_from_request(&req).filter(|k| !k.is_empty());
+    let key_id = key.as_deref().map(KeyId::of);
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_canonical());
+    let source = peer.map_or_else(|| UNKNOWN_PEER.to_string(), |ip| ip.to_string());
+    let now = Instant::now();
+    if let Err(left) = config.throttle.check(&source, now) {
+        let secs = left.as_secs().max(1);
+        let message = format!("too many failed authentications; retry in {} s", secs);
+        let mut response = ApiError::new(StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimited, message).into_response();
+        if let Ok(value) = header::HeaderValue::from_str(&secs.to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        return response;
+    }
+
+    let refresh = req.uri().path() == "/auth/refresh";
+    let (status, code, message, reason) = match key.map(|key| config.authenticate(&key, refresh, now)) {
+        Some(Some((_, grant, _))) if !address_allowed(&grant.allowed_cidrs, peer) => {
+            let message = format!("API key is not allowed from {}", describe_peer(peer));
+            (StatusCode::FORBIDDEN, ErrorCode::Forbidden, message, "address_not_allowed")
+        }
+        Some(Some((key, grant, credential))) => {
+            config.throttle.success(&source);
+            req.extensions_mut().insert(AuthUser::from_grant(key, grant));
+            req.extensions_mut().insert(credential);
+            return next.run(req).await;
         }
+        Some(None) if refresh => {
+            (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, "invalid or expired refresh token".to_string(), "invalid")
+        }
+        Some(None) => (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, "invalid API key or session token".to_string(), "invalid"),
+        None => (
+            StatusCode::UNAUTHORIZED,
+            ErrorCode::Unauthorized,
+            "missing or invalid Authorization or X-API-Key".to_string(),
+            "missing",
+        ),
     };
+    let (failures, lockout) = config.throttle.failure(&source, now);
+    let context = req.extensions().get::<AuditContext>().cloned().unwrap_or_default();
+    audit.emit(&AuditEvent::now(
+        "anonymous",
+        AuditAction::AuthFailure,
+        Some(serde_json::json!({
+            "source": source,
+            "key_id": key_id,
+            "reason": reason,
+            "path": req.uri().path(),
+            "failures": failures,
+            "lockout_ms": lockout.map(|l| l.as_millis() as u64),
+        })),
+        match (lockout, status) {
+            (Some(_), _) => "locked_out",
+            (None, StatusCode::FORBIDDEN) => "forbidden",
+            (None, _) => "unauthorized",
+        },
+    ).with_context(&context));
+    ApiError::new(status, code, message).into_response()
+}
 
-    match config.lookup(&key) {
-        Some(role) => {
-            req.extensions_mut().insert(AuthUser {
-                key_id: Some(key),
-                role,
-            });
-            next.run(req).await
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_keys_bind_optional_trader_ids() {
+        let config = AuthConfig::from_keys("t7:trader:7, a:admin,bad:trader:x,:admin");
+        assert_eq!(config.grant("t7"), Some(KeyGrant::new(Role::Trader, Some(TraderId(7)))));
+        assert_eq!(config.grant("a").map(|g| (g.role, g.trader_id)), Some((Role::Admin, None)));
+        assert_eq!(config.lookup("bad"), None);
+        assert_eq!(config.lookup(""), None);
+    }
+
+    #[test]
+    fn api_keys_are_held_and_shown_only_by_their_id() {
+        let config = AuthConfig::from_keys("secret-one:trader:7,secret-two:admin");
+        let id = KeyId::of("secret-one");
+        // The first 4 bytes of SHA-256("abc").
+        assert_eq!(KeyId::of("abc").to_string(), "key-ba7816bf");
+        assert_eq!(format!("{:?}", id), id.to_string());
+        assert_ne!(id, KeyId::of("secret-two"));
+        assert!(id.constant_time_eq(&KeyId::of("secret-one")) && !id.constant_time_eq(&KeyId::of("secret-onf")));
+        assert_eq!(config.find_key_id(&id.to_string()), Some(id));
+        assert_eq!(config.find_key_id("secret-one"), None);
+        let user = AuthUser::from_grant(id, config.grant("secret-one").unwrap());
+        assert_eq!(user.actor(), id.to_string());
+        assert_eq!(AuthUser::default().actor(), "anonymous");
+        let listed = serde_json::to_string(&config.keys()).unwrap();
+        assert!(!listed.contains("secret"), "{}", listed);
+    }
+
+    #[test]
+    fn api_keys_take_role_defaults_or_listed_permissions() {
+        let config = AuthConfig::from_keys("t:trader,o:operator::halt_market+view_audit,r:trader:7:cancel_any,x:admin::fly");
+        let permissions = |key| config.grant(key).map(|g| g.permissions.iter().map(|p| p.as_str()).collect::<Vec<_>>());
+        assert_eq!(permissions("t"), Some(vec!["submit_orders", "view_market_data"]));
+        assert_eq!(permissions("o"), Some(vec!["halt_market", "view_audit"]));
+        assert_eq!(config.grant("r").map(|g| (g.trader_id, g.permissions)), Some((Some(TraderId(7)), Permissions::of(&[Permission::CancelAny]))));
+        assert_eq!(config.lookup("x"), None);
+        assert!(Permissions::for_role(Role::Admin).contains(Permission::ManageKeys));
+        assert!(!Permissions::for_role(Role::Operator).contains(Permission::ManageKeys));
+
+        // Keys changed at runtime are seen by every clone.
+        let shared = config.clone();
+        shared.set_key("new", KeyGrant::new(Role::Admin, None));
+        assert_eq!(config.lookup("new"), Some(Role::Admin));
+        let user = AuthUser::from_grant(KeyId::of("o"), config.grant("o").unwrap());
+        assert!(config.is_current(&user) && config.is_current(&AuthUser::default()));
+        shared.set_key("o", KeyGrant::new(Role::Operator, None));
+        assert!(!config.is_current(&user));
+        assert!(shared.remove_key(&KeyId::of("t")).is_some());
+        let mut ids = ["new", "o", "r"].map(KeyId::of);
+        ids.sort();
+        assert_eq!(config.keys().iter().map(|(k, _)| *k).collect::<Vec<_>>(), ids);
+    }
+
+    #[test]
+    fn api_keys_may_be_entitled_to_some_instruments() {
+        let config = AuthConfig::from_keys("t:trader:7::1+2,o:operator::halt_market:3,all:trader:7,bad:trader:7::x");
+        let instruments = |key| config.grant(key).map(|g| g.instruments);
+        assert_eq!(instruments("t"), Some(Some(vec![InstrumentId(1), InstrumentId(2)])));
+        assert_eq!(config.grant("t").map(|g| g.permissions), Some(Permissions::for_role(Role::Trader)));
+        assert_eq!(instruments("o"), Some(Some(vec![InstrumentId(3)])));
+        assert_eq!(instruments("all"), Some(None));
+        assert_eq!(config.lookup("bad"), None);
+        assert!(entitled(&None, InstrumentId(9)));
+        assert!(!entitled(&Some(vec![InstrumentId(1)]), InstrumentId(9)));
+
+        let logon = config.with_fix_sender_comp_ids("").fix_logon(Some("CLIENT"), Some("u"), Some("t"), None).unwrap();
+        assert_eq!(logon.instruments, Some(vec![InstrumentId(1), InstrumentId(2)]));
+    }
+
+    #[test]
+    fn cidrs_match_networks_and_limit_fix_logons() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let net: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains(ip("10.1.200.3")) && !net.contains(ip("10.2.0.1")));
+        assert!(net.contains(ip("::ffff:10.1.0.9")));
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(ip("8.8.8.8")));
+        let host: Cidr = "2001:db8::1".parse().unwrap();
+        assert_eq!(host.to_string(), "2001:db8::1/128");
+        assert!(host.contains(ip("2001:db8::1")) && !host.contains(ip("2001:db8::2")));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err() && "x/8".parse::<Cidr>().is_err());
+        assert!(address_allowed(&None, None));
+        assert!(!address_allowed(&Some(vec![net]), None));
+
+        let config = AuthConfig::from_keys("t7:trader:7,t8:trader:8")
+            .with_key_allowed_cidrs("t7=10.1.0.0/16+192.168.0.5,t8=10.0.0.0/99")
+            .with_fix_allowed_cidrs("DESK=192.168.0.0/24");
+        assert_eq!(config.grant("t7").unwrap().allowed_cidrs.map(|c| c.len()), Some(2));
+        // A malformed list admits nothing.
+        assert_eq!(config.grant("t8").unwrap().allowed_cidrs, Some(Vec::new()));
+        let logon = |sender, key, peer: &str| config.fix_logon(Some(sender), Some("u"), Some(key), Some(ip(peer)));
+        assert!(logon("CLIENT", "t7", "10.1.0.1").is_ok());
+        assert_eq!(logon("CLIENT", "t7", "10.9.0.1"), Err("API key is not allowed from 10.9.0.1".to_string()));
+        assert!(logon("DESK", "t7", "192.168.0.5").is_ok());
+        assert_eq!(logon("DESK", "t7", "10.1.0.1"), Err("SenderCompID DESK is not allowed from 10.1.0.1".to_string()));
+        assert!(config.fix_logon(Some("CLIENT"), Some("u"), Some("t7"), None).unwrap_err().contains("unknown address"));
+    }
+
+    #[test]
+    fn failed_authentications_lock_the_source_out_with_growing_backoff() {
+        let policy = AuthThrottlePolicy {
+            max_failures: 3,
+            lockout: Duration::from_secs(1),
+            max_lockout: Duration::from_secs(5),
+        };
+        let throttle = AuthThrottle::new(policy);
+        let t0 = Instant::now();
+        assert_eq!(throttle.failure("ip", t0), (1, None));
+        assert_eq!(throttle.failure("ip", t0), (2, None));
+        assert_eq!(throttle.failure("ip", t0), (3, Some(Duration::from_secs(1))));
+        assert_eq!(throttle.check("ip", t0), Err(Duration::from_secs(1)));
+        assert_eq!(throttle.check("other", t0), Ok(()));
+        let t1 = t0 + Duration::from_secs(1);
+        assert_eq!(throttle.check("ip", t1), Ok(()));
+        assert_eq!(throttle.failure("ip", t1), (4, Some(Duration::from_secs(2))));
+        assert_eq!(throttle.failure("ip", t1).1, Some(Duration::from_secs(4)));
+        assert_eq!(throttle.failure("ip", t1).1, Some(Duration::from_secs(5)));
+        // Quiet for max_lockout: the count starts over.
+        assert_eq!(throttle.failure("ip", t1 + Duration::from_secs(5)), (1, None));
+        throttle.success("ip");
+        assert_eq!(throttle.failure("ip", t1 + Duration::from_secs(5)), (1, None));
+
+        // FIX logons are throttled per peer address, whatever SenderCompID they give; a good logon clears the count.
+        let config = AuthConfig::from_keys("t7:trader:7").with_throttle_policy(policy);
+        let ip = |s: &str| s.parse::<IpAddr>().ok();
+        for sender in ["X", "Y"] {
+            assert!(config.fix_logon(Some(sender), Some("u"), Some("bad"), ip("10.0.0.1")).is_err());
+        }
+        assert!(config.fix_logon(Some("X"), Some("u"), Some("t7"), ip("10.0.0.1")).is_ok());
+        for sender in ["X", "Y", "Z"] {
+            let err = config.fix_logon(Some(sender), Some("u"), Some("bad"), ip("10.0.0.1")).unwrap_err();
+            assert!(err.contains("invalid Password"), "{}", err);
+        }
+        let err = config.fix_logon(Some("W"), Some("u"), Some("t7"), ip("10.0.0.1")).unwrap_err();
+        assert!(err.starts_with("too many failed logons"), "{}", err);
+        assert!(config.fix_logon(Some("X"), Some("u"), Some("t7"), ip("10.0.0.2")).is_ok());
+
+        // Without an address, every password tried counts against the one shared bucket.
+        for password in ["bad1", "bad2", "bad3"] {
+            assert!(config.fix_logon(Some("X"), Some("u"), Some(password), None).unwrap_err().contains("invalid Password"));
         }
-        None => (StatusCode::UNAUTHORIZED, "invalid API key").into_response(),
+        let err = config.fix_logon(Some("X"), Some("u"), Some("bad4"), None).unwrap_err();
+        assert!(err.starts_with("too many failed logons"), "{}", err);
+    }
+
+    #[test]
+    fn session_tokens_stand_for_their_key_until_they_expire() {
+        let config = AuthConfig::from_keys("k:trader:7").with_session_policy(SessionPolicy {
+            ttl: Duration::from_secs(60),
+            refresh_ttl: Duration::from_secs(600),
+        });
+        let t0 = Instant::now();
+        let tokens = config.sessions().issue(KeyId::of("k"), t0);
+        assert_eq!((tokens.expires_in, tokens.refresh_expires_in, tokens.token.len()), (60, 600, 64));
+        let (key, grant, credential) = config.authenticate(&tokens.token, false, t0).unwrap();
+        assert_eq!((key, grant.trader_id), (KeyId::of("k"), Some(TraderId(7))));
+        assert_eq!(credential, Credential::SessionToken(tokens.token.clone()));
+        assert_eq!(config.authenticate("k", false, t0).map(|a| a.2), Some(Credential::ApiKey));
+        // Refresh tokens only refresh; API keys don't.
+        assert!(config.authenticate(&tokens.refresh_token, false, t0).is_none());
+        assert!(config.authenticate("k", true, t0).is_none());
+        assert!(config.authenticate(&tokens.token, false, t0 + Duration::from_secs(60)).is_none());
+
+        // Refreshing replaces both tokens.
+        let later = t0 + Duration::from_secs(120);
+        let renewed = config.sessions().refresh(&tokens.refresh_token, later).unwrap();
+        assert!(config.sessions().refresh(&tokens.refresh_token, later).is_none());
+        assert!(config.authenticate(&tokens.token, false, t0).is_none());
+        assert!(config.authenticate(&renewed.token, false, later).is_some());
+        assert!(config.sessions().refresh(&renewed.refresh_token, later + Duration::from_secs(600)).is_none());
+
+        // Ending a session, or removing its key, ends its tokens.
+        let session = config.sessions().issue(KeyId::of("k"), later);
+        assert!(config.sessions().end(&session.token));
+        assert!(config.authenticate(&session.refresh_token, true, later).is_none());
+        assert!(!config.sessions().end(&session.token));
+        let session = config.sessions().issue(KeyId::of("k"), later);
+        config.remove_key(&KeyId::of("k"));
+        assert!(config.authenticate(&session.token, false, later).is_none());
+    }
+
+    #[test]
+    fn fix_logon_checks_credentials_then_sender_comp_id() {
+        let trader = Permissions::for_role(Role::Trader);
+        let logon = |trader_id, permissions| {
+            Ok(FixLogon {
+                trader_id,
+                permissions,
+                instruments: None,
+            })
+        };
+        assert_eq!(AuthConfig::disabled().fix_logon(Some("ANY"), None, None, None), logon(None, trader));
+
+        let config = AuthConfig::from_keys("t7:trader:7,a:admin,md:trader:8:view_market_data").with_fix_sender_comp_ids("DESK9:9, bad:x");
+        assert_eq!(config.fix_logon(Some("CLIENT"), Some("u"), Some("t7"), None), logon(Some(TraderId(7)), trader));
+        assert_eq!(config.fix_logon(Some("DESK9"), None, None, None), logon(Some(TraderId(9)), trader));
+        let market_data = Permissions::of(&[Permission::ViewMarketData]);
+        assert_eq!(config.fix_logon(Some("CLIENT"), Some("u"), Some("md"), None), logon(Some(TraderId(8)), market_data));
+        // Credentials, when sent, decide even for an allowed SenderCompID.
+        assert!(config.fix_logon(Some("DESK9"), Some("u"), Some("wrong"), None).unwrap_err().contains("invalid Password"));
+        assert!(config.fix_logon(Some("CLIENT"), None, Some("t7"), None).unwrap_err().contains("both required"));
+        assert_eq!(config.fix_logon(Some("CLIENT"), Some("u"), Some("a"), None), Err("API key is not bound to a trader".to_string()));
+        assert!(config.fix_logon(Some("bad"), None, None, None).unwrap_err().contains("not allowed"));
+        assert!(config.fix_logon(None, None, None, None).unwrap_err().contains("(missing)"));
     }
 }
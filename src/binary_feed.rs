@@ -0,0 +1,565 @@
+//! Binary market data feed in the style of Simple Binary Encoding (SBE), for subscribers that can't afford JSON
+//! over WebSocket.
+//!
+//! Every message is framed by a Simple Open Framing Header (message length `u32` big-endian, including the
+//! header, and encoding type `0xEB50`, SBE little-endian), then an SBE message header (block length, template
+//! id, schema id, version; `u16` little-endian each), then the fixed block and any repeating group. Integers are
+//! little-endian; prices and quantities are `i64` mantissas with exponent [`PRICE_EXPONENT`]. Templates:
+//!
+//! - **0 Heartbeat:** `feed_seq` of the last message sent.
+//! - **1 BookUpdate:** `feed_seq`, `engine_seq`, `instrument_id`, then a group of levels (`price`, `quantity`,
+//!   `side` 1 bid / 2 ask, `action` 0 added / 1 changed / 2 removed); `quantity` is the level's new total.
+//! - **2 Trade:** `feed_seq`, `engine_seq`, `instrument_id`, `trade_id`, `price`, `quantity`, `timestamp`,
+//!   `aggressor_side`.
+//! - **3 BookSnapshot:** as BookUpdate, every level of the book (`action` added), sent to a TCP subscriber on
+//!   connect; updates with a higher `feed_seq` apply on top of it.
+//!
+//! `feed_seq` numbers BookUpdate and Trade messages 1, 2, 3, ... so a subscriber detects a gap; `engine_seq` is
+//! the engine's sequence number (see [`crate::MultiEngine::events_since`]). [`BinaryFeed`] publishes the engine
+//! event stream; [`run_binary_feed_tcp`] and [`run_binary_feed_udp`] send it out.
+
+use crate::engine::{MatchingEngine, MultiEngine};
+use crate::events::{BookObserver, EngineEvent, EngineEventSink};
+use crate::execution::Trade;
+use crate::order_book::{BookDelta, BookLevels, LevelAction};
+use crate::types::{InstrumentId, Side, TradeId};
+use log::warn;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// SBE schema id and version of every message.
+pub const SCHEMA_ID: u16 = 1;
+pub const SCHEMA_VERSION: u16 = 1;
+/// Prices and quantities are sent as `mantissa * 10^PRICE_EXPONENT`; values with more decimal places are not
+/// published.
+pub const PRICE_EXPONENT: u32 = 8;
+/// Simple Open Framing Header encoding type of SBE 1.0 little-endian.
+const SOFH_ENCODING_TYPE: u16 = 0xEB50;
+const SOFH_LEN: usize = 6;
+const MESSAGE_HEADER_LEN: usize = 8;
+/// Group size encoding: block length and entry count, `u16` each.
+const GROUP_HEADER_LEN: usize = 4;
+const LEVEL_LEN: usize = 18;
+/// A subscriber this many messages behind is disconnected.
+const SUBSCRIBER_CAPACITY: usize = 65_536;
+/// How often an idle subscriber gets a Heartbeat.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+const HEARTBEAT: u16 = 0;
+const BOOK_UPDATE: u16 = 1;
+const TRADE: u16 = 2;
+const BOOK_SNAPSHOT: u16 = 3;
+
+/// One price level in a [`BookMessage`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedLevel {
+    pub side: Side,
+    pub action: LevelAction,
+    pub price: Decimal,
+    /// The level's total after the change; zero when removed.
+    pub quantity: Decimal,
+}
+
+/// BookUpdate or BookSnapshot: levels of one instrument.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookMessage {
+    pub feed_seq: u64,
+    pub engine_seq: u64,
+    pub instrument_id: InstrumentId,
+    pub levels: Vec<FeedLevel>,
+}
+
+/// A trade as the feed sends it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TradeMessage {
+    pub feed_seq: u64,
+    pub engine_seq: u64,
+    pub instrument_id: InstrumentId,
+    pub trade_id: TradeId,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub timestamp: u64,
+    pub aggressor_side: Side,
+}
+
+/// One feed message (see the module docs for the layout).
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeedMessage {
+    Heartbeat { feed_seq: u64 },
+    BookUpdate(BookMessage),
+    Trade(TradeMessage),
+    BookSnapshot(BookMessage),
+}
+
+impl FeedMessage {
+    /// `feed_seq` of the message.
+    pub fn feed_seq(&self) -> u64 {
+        match self {
+            FeedMessage::Heartbeat { feed_seq } => *feed_seq,
+            FeedMessage::BookUpdate(book) | FeedMessage::BookSnapshot(book) => book.feed_seq,
+            FeedMessage::Trade(trade) => trade.feed_seq,
+        }
+    }
+
+    /// The framed message. `Err` when a price or quantity does not fit [`PRICE_EXPONENT`] and an `i64`.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let (template, block) = match self {
+            FeedMessage::Heartbeat { feed_seq } => (HEARTBEAT, feed_seq.to_le_bytes().to_vec()),
+            FeedMessage::BookUpdate(book) => (BOOK_UPDATE, encode_book(book)?),
+            FeedMessage::BookSnapshot(book) => (BOOK_SNAPSHOT, encode_book(book)?),
+            FeedMessage::Trade(trade) => {
+                let mut block = Vec::with_capacity(57);
+                for value in [trade.feed_seq, trade.engine_seq, trade.instrument_id.0, trade.trade_id.0] {
+                    block.extend(value.to_le_bytes());
+                }
+                block.extend(mantissa(trade.price)?.to_le_bytes());
+                block.extend(mantissa(trade.quantity)?.to_le_bytes());
+                block.extend(trade.timestamp.to_le_bytes());
+                block.push(side_code(trade.aggressor_side));
+                (TRADE, block)
+            }
+        };
+        let block_length = match self {
+            FeedMessage::BookUpdate(_) | FeedMessage::BookSnapshot(_) => 24,
+            _ => block.len(),
+        };
+        let len = SOFH_LEN + MESSAGE_HEADER_LEN + block.len();
+        let mut out = Vec::with_capacity(len);
+        out.extend((len as u32).to_be_bytes());
+        out.extend(SOFH_ENCODING_TYPE.to_be_bytes());
+        for value in [block_length as u16, template, SCHEMA_ID, SCHEMA_VERSION] {
+            out.extend(value.to_le_bytes());
+        }
+        out.extend(block);
+        Ok(out)
+    }
+
+    /// The message at the start of `buf` and the bytes it takes. `Ok(None)` while it is incomplete; `Err` when
+    /// `buf` does not start with a message of this schema.
+    pub fn decode(buf: &[u8]) -> Result<Option<(FeedMessage, usize)>, String> {
+        if buf.len() < SOFH_LEN + MESSAGE_HEADER_LEN {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes(buf[0..4].try_into().expect("4 bytes")) as usize;
+        if u16::from_be_bytes([buf[4], buf[5]]) != SOFH_ENCODING_TYPE {
+            return Err("not an SBE little-endian frame".into());
+        }
+        if len < SOFH_LEN + MESSAGE_HEADER_LEN {
+            return Err(format!("frame length {} is shorter than its headers", len));
+        }
+        if buf.len() < len {
+            return Ok(None);
+        }
+        let mut r = Reader { buf: &buf[SOFH_LEN..len] };
+        let (block_length, template, schema, version) = (r.u16()?, r.u16()?, r.u16()?, r.u16()?);
+        if (schema, version) != (SCHEMA_ID, SCHEMA_VERSION) {
+            return Err(format!("unsupported schema {} version {}", schema, version));
+        }
+        let message = match template {
+            HEARTBEAT => FeedMessage::Heartbeat { feed_seq: r.u64()? },
+            BOOK_UPDATE | BOOK_SNAPSHOT => {
+                let mut block = Reader { buf: r.take(block_length as usize)? };
+                let (feed_seq, engine_seq, instrument_id) = (block.u64()?, block.u64()?, InstrumentId(block.u64()?));
+                let (entry_length, count) = (r.u16()? as usize, r.u16()?);
+                let mut levels = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let mut entry = Reader { buf: r.take(entry_length)? };
+                    let (price, quantity) = (price(entry.i64()?), price(entry.i64()?));
+                    let side = side_of(entry.u8()?)?;
+                    let action = match entry.u8()? {
+                        0 => LevelAction::Added,
+                        1 => LevelAction::Changed,
+                        2 => LevelAction::Removed,
+                        other => return Err(format!("invalid level action {}", other)),
+                    };
+                    levels.push(FeedLevel { side, action, price, quantity });
+                }
+                let book = BookMessage { feed_seq, engine_seq, instrument_id, levels };
+                match template {
+                    BOOK_UPDATE => FeedMessage::BookUpdate(book),
+                    _ => FeedMessage::BookSnapshot(book),
+                }
+            }
+            TRADE => FeedMessage::Trade(TradeMessage {
+                feed_seq: r.u64()?,
+                engine_seq: r.u64()?,
+                instrument_id: InstrumentId(r.u64()?),
+                trade_id: TradeId(r.u64()?),
+                price: price(r.i64()?),
+                quantity: price(r.i64()?),
+                timestamp: r.u64()?,
+                aggressor_side: side_of(r.u8()?)?,
+            }),
+            other => return Err(format!("unknown template {}", other)),
+        };
+        Ok(Some((message, len)))
+    }
+}
+
+fn encode_book(book: &BookMessage) -> Result<Vec<u8>, String> {
+    let mut block = Vec::with_capacity(24 + GROUP_HEADER_LEN + book.levels.len() * LEVEL_LEN);
+    for value in [book.feed_seq, book.engine_seq, book.instrument_id.0] {
+        block.extend(value.to_le_bytes());
+    }
+    let count = u16::try_from(book.levels.len()).map_err(|_| format!("{} levels in one message", book.levels.len()))?;
+    block.extend((LEVEL_LEN as u16).to_le_bytes());
+    block.extend(count.to_le_bytes());
+    for level in &book.levels {
+        block.extend(mantissa(level.price)?.to_le_bytes());
+        block.extend(mantissa(level.quantity)?.to_le_bytes());
+        block.push(side_code(level.side));
+        block.push(match level.action {
+            LevelAction::Added => 0,
+            LevelAction::Changed => 1,
+            LevelAction::Removed => 2,
+        });
+    }
+    Ok(block)
+}
+
+/// `value * 10^PRICE_EXPONENT` as an `i64`.
+pub(crate) fn mantissa(value: Decimal) -> Result<i64, String> {
+    value
+        .checked_mul(Decimal::from(10i64.pow(PRICE_EXPONENT)))
+        .filter(|scaled| scaled.fract().is_zero())
+        .and_then(|scaled| scaled.to_i64())
+        .ok_or_else(|| format!("{} does not fit the feed's price encoding", value))
+}
+
+/// The value of a [`mantissa`].
+pub(crate) fn price(mantissa: i64) -> Decimal {
+    Decimal::new(mantissa, PRICE_EXPONENT).normalize()
+}
+
+fn side_code(side: Side) -> u8 {
+    match side {
+        Side::Buy => 1,
+        Side::Sell => 2,
+    }
+}
+
+fn side_of(code: u8) -> Result<Side, String> {
+    match code {
+        1 => Ok(Side::Buy),
+        2 => Ok(Side::Sell),
+        other => Err(format!("invalid side {}", other)),
+    }
+}
+
+/// Little-endian reads off the front of a message.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.buf.len() < n {
+            return Err("message shorter than its block".into());
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")))
+    }
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+}
+
+/// Every level of `levels` as added.
+fn snapshot_levels(levels: &BookLevels) -> Vec<FeedLevel> {
+    let side = |side: Side, levels: &[(Decimal, Decimal)]| {
+        levels
+            .iter()
+            .map(move |&(price, quantity)| FeedLevel { side, action: LevelAction::Added, price, quantity })
+            .collect::<Vec<_>>()
+    };
+    let mut out = side(Side::Buy, &levels.bids);
+    out.extend(side(Side::Sell, &levels.asks));
+    out
+}
+
+#[derive(Default)]
+struct FeedState {
+    /// `feed_seq` of the last message published.
+    last_seq: u64,
+    /// Levels of each book as of its last update, the base of the next one.
+    published: HashMap<InstrumentId, BookLevels>,
+    subscribers: Vec<SyncSender<Arc<[u8]>>>,
+}
+
+/// Publishes the engine's book changes and trades as feed messages. Register it with [`BinaryFeed::attach`];
+/// it runs under the engine lock, so messages are numbered in engine order.
+#[derive(Default)]
+pub struct BinaryFeed {
+    state: Mutex<FeedState>,
+}
+
+impl BinaryFeed {
+    /// A feed registered as an event sink and book observer of `engine`, its first updates starting from the
+    /// books as they are now.
+    pub fn attach(engine: &Mutex<MultiEngine>) -> Arc<Self> {
+        let mut engine = engine.lock().expect("lock");
+        let published = engine
+            .instruments()
+            .into_iter()
+            .filter_map(|id| Some((id, engine.book_levels_for(id)?)))
+            .collect();
+        let feed = Arc::new(Self {
+            state: Mutex::new(FeedState { published, ..FeedState::default() }),
+        });
+        engine.add_event_sink(feed.clone());
+        engine.add_book_observer(feed.clone());
+        feed
+    }
+
+    /// A snapshot of every book, then a channel of the messages that follow it. Holds the engine lock so no
+    /// change falls between the two.
+    pub fn subscribe(&self, engine: &Mutex<MultiEngine>) -> (Vec<FeedMessage>, Receiver<Arc<[u8]>>) {
+        let engine = engine.lock().expect("lock");
+        let mut state = self.state.lock().expect("lock");
+        let mut instruments: Vec<_> = state.published.keys().copied().collect();
+        instruments.sort_by_key(|id| id.0);
+        let snapshots = instruments
+            .into_iter()
+            .map(|id| {
+                FeedMessage::BookSnapshot(BookMessage {
+                    feed_seq: state.last_seq,
+                    engine_seq: engine.book_snapshot_for(id).map_or(0, |snapshot| snapshot.seq),
+                    instrument_id: id,
+                    levels: snapshot_levels(&state.published[&id]),
+                })
+            })
+            .collect();
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_CAPACITY);
+        state.subscribers.push(tx);
+        (snapshots, rx)
+    }
+
+    /// `feed_seq` of the last message published.
+    pub fn last_seq(&self) -> u64 {
+        self.state.lock().expect("lock").last_seq
+    }
+
+    /// Number and send the message `build` makes for the next `feed_seq`.
+    fn publish(&self, build: impl FnOnce(u64) -> FeedMessage) {
+        let mut state = self.state.lock().expect("lock");
+        let message = build(state.last_seq + 1);
+        let bytes: Arc<[u8]> = match message.encode() {
+            Ok(bytes) => bytes.into(),
+            Err(e) => {
+                warn!("binary feed message not published: {}", e);
+                return;
+            }
+        };
+        state.last_seq += 1;
+        state.subscribers.retain(|tx| match tx.try_send(bytes.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!("binary feed subscriber dropped: {} messages behind", SUBSCRIBER_CAPACITY);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+impl EngineEventSink for BinaryFeed {
+    fn on_event(&self, event: &EngineEvent) {
+        if let EngineEvent::Trade(trade) = event {
+            let trade: &Trade = trade;
+            self.publish(|feed_seq| {
+                FeedMessage::Trade(TradeMessage {
+                    feed_seq,
+                    engine_seq: trade.seq,
+                    instrument_id: trade.instrument_id,
+                    trade_id: trade.trade_id,
+                    price: trade.price,
+                    quantity: trade.quantity,
+                    timestamp: trade.timestamp,
+                    aggressor_side: trade.aggressor_side,
+                })
+            });
+        }
+    }
+}
+
+impl BookObserver for BinaryFeed {
+    fn on_book_changed(&self, engine: &MultiEngine, instrument_id: InstrumentId) {
+        let Some(after) = engine.book_levels_for(instrument_id) else {
+            return;
+        };
+        let delta = {
+            let mut state = self.state.lock().expect("lock");
+            let delta = BookDelta::between(state.published.get(&instrument_id).unwrap_or(&BookLevels::default()), &after);
+            state.published.insert(instrument_id, after);
+            delta
+        };
+        if delta.is_empty() {
+            return;
+        }
+        let engine_seq = engine.book_snapshot_for(instrument_id).map_or(0, |snapshot| snapshot.seq);
+        let levels = delta
+            .bids
+            .into_iter()
+            .map(|change| (Side::Buy, change))
+            .chain(delta.asks.into_iter().map(|change| (Side::Sell, change)))
+            .map(|(side, change)| FeedLevel {
+                side,
+                action: change.action,
+                price: change.price,
+                quantity: change.quantity,
+            })
+            .collect();
+        self.publish(|feed_seq| {
+            FeedMessage::BookUpdate(BookMessage {
+                feed_seq,
+                engine_seq,
+                instrument_id,
+                levels,
+            })
+        });
+    }
+}
+
+/// Serve `feed` over TCP on `listener`: each connection gets a BookSnapshot per instrument, then every message
+/// as it is published, and a Heartbeat when idle. Subscribers only read; one that falls too far behind is
+/// disconnected.
+pub fn run_binary_feed_tcp(listener: TcpListener, engine: Arc<Mutex<MultiEngine>>, feed: Arc<BinaryFeed>) {
+    for stream in listener.incoming().flatten() {
+        let (snapshots, rx) = feed.subscribe(&engine);
+        let last_seq = snapshots.first().map_or_else(|| feed.last_seq(), FeedMessage::feed_seq);
+        std::thread::spawn(move || {
+            let mut stream = stream;
+            let _ = stream.set_nodelay(true);
+            let result = snapshots
+                .iter()
+                .try_for_each(|snapshot| {
+                    let bytes = snapshot.encode()?;
+                    stream.write_all(&bytes).map_err(|e| e.to_string())
+                })
+                .and_then(|()| forward(&rx, last_seq, |bytes| stream.write_all(bytes).map_err(|e| e.to_string())));
+            if let Err(e) = result {
+                warn!("binary feed subscriber disconnected: {}", e);
+            }
+        });
+    }
+}
+
+/// Send `feed` as UDP datagrams, one message each, from `socket` to `destination` (e.g. a multicast group).
+/// Datagrams can be lost: subscribers detect gaps by `feed_seq`. Returns when a send fails.
+pub fn run_binary_feed_udp(socket: UdpSocket, destination: SocketAddr, engine: Arc<Mutex<MultiEngine>>, feed: Arc<BinaryFeed>) {
+    let (_, rx) = feed.subscribe(&engine);
+    let result = forward(&rx, feed.last_seq(), |bytes| {
+        socket.send_to(bytes, destination).map(|_| ()).map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        warn!("binary feed to {} stopped: {}", destination, e);
+    }
+}
+
+/// Send what arrives on `rx`, with a Heartbeat after every [`HEARTBEAT_INTERVAL`] without a message.
+fn forward(rx: &Receiver<Arc<[u8]>>, mut last_seq: u64, mut send: impl FnMut(&[u8]) -> Result<(), String>) -> Result<(), String> {
+    loop {
+        match rx.recv_timeout(HEARTBEAT_INTERVAL) {
+            Ok(bytes) => {
+                // Every template starts with `feed_seq`.
+                let start = SOFH_LEN + MESSAGE_HEADER_LEN;
+                last_seq = u64::from_le_bytes(bytes[start..start + 8].try_into().expect("8 bytes"));
+                send(&bytes)?;
+            }
+            Err(RecvTimeoutError::Timeout) => send(&FeedMessage::Heartbeat { feed_seq: last_seq }.encode()?)?,
+            Err(RecvTimeoutError::Disconnected) => return Err("feed dropped the subscriber".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip_and_decode_incrementally() {
+        let book = BookMessage {
+            feed_seq: 7,
+            engine_seq: 42,
+            instrument_id: InstrumentId(3),
+            levels: vec![
+                FeedLevel {
+                    side: Side::Buy,
+                    action: LevelAction::Changed,
+                    price: Decimal::new(10025, 2),
+                    quantity: Decimal::new(15, 1),
+                },
+                FeedLevel {
+                    side: Side::Sell,
+                    action: LevelAction::Removed,
+                    price: Decimal::from(101),
+                    quantity: Decimal::ZERO,
+                },
+            ],
+        };
+        let messages = [
+            FeedMessage::Heartbeat { feed_seq: 6 },
+            FeedMessage::BookUpdate(book.clone()),
+            FeedMessage::BookSnapshot(BookMessage { levels: Vec::new(), ..book }),
+            FeedMessage::Trade(TradeMessage {
+                feed_seq: 8,
+                engine_seq: 43,
+                instrument_id: InstrumentId(3),
+                trade_id: TradeId(9),
+                price: Decimal::new(10025, 2),
+                quantity: Decimal::new(5, 1),
+                timestamp: 1_700_000_000,
+                aggressor_side: Side::Sell,
+            }),
+        ];
+        let stream: Vec<u8> = messages.iter().flat_map(|m| m.encode().unwrap()).collect();
+        let mut offset = 0;
+        for expected in &messages {
+            assert_eq!(FeedMessage::decode(&stream[offset..offset + 10]).unwrap(), None);
+            let (message, len) = FeedMessage::decode(&stream[offset..]).unwrap().unwrap();
+            assert_eq!(&message, expected);
+            offset += len;
+        }
+        assert_eq!(offset, stream.len());
+        // Header: length, SBE little-endian, block length 24, template 1.
+        let update = messages[1].encode().unwrap();
+        assert_eq!(&update[..10], &[0, 0, 0, 78, 0xEB, 0x50, 24, 0, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_unrepresentable_prices_and_foreign_frames() {
+        let trade = |price| {
+            FeedMessage::Trade(TradeMessage {
+                feed_seq: 1,
+                engine_seq: 1,
+                instrument_id: InstrumentId(1),
+                trade_id: TradeId(1),
+                price,
+                quantity: Decimal::ONE,
+                timestamp: 0,
+                aggressor_side: Side::Buy,
+            })
+        };
+        assert!(trade(Decimal::new(1, 9)).encode().unwrap_err().contains("does not fit"));
+        assert!(trade(Decimal::MAX).encode().is_err());
+        let mut bytes = trade(Decimal::ONE).encode().unwrap();
+        bytes[5] = 0;
+        assert!(FeedMessage::decode(&bytes).is_err());
+    }
+}
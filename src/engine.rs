@@ -4,13 +4,26 @@
 //! without managing `OrderBook` and `match_order` directly. All protocol adapters (REST,
 //! WebSocket, FIX) use the same entry point: [`Engine`] or [`MultiEngine`] behind shared state ([`crate::api::AppState`]).
 
+use crate::events::{BookObserver, EngineEvent, EngineEventSink, EventJournal, EventSinks, EVENT_JOURNAL_CAPACITY};
 use crate::execution::{ExecutionReport, Trade};
-use crate::matching::match_order;
-use crate::order_book::OrderBook;
-use crate::types::{InstrumentId, Order, OrderId, RestingOrder};
-use log::info;
+use crate::history::{ExecutionStore, HistoryPage, HistoryQuery, HistoryRecord, TradeStore};
+use crate::ids::{IdAllocator, IdStore, IdWatermark};
+use crate::journal::{Command, CommandReplay, InputJournal, InputSink, JournalEntry, Replay};
+use crate::persistence::{CommandLog, HistoryLog};
+use crate::matching::{match_order, match_order_into, replace_order, uncross_book, MatchOutput};
+use crate::order_book::{BookLevels, BookLimits, BookStats, OrderBook, DEFAULT_TICK_SIZE};
+use crate::positions::{Position, PositionBook};
+use crate::risk::RiskLimits;
+use crate::venue::{BandConfig, TradingSession};
+use crate::stats::{InstrumentStats, StatsBook};
+use crate::scheduler::{FiredTimer, Scheduler, SchedulerSnapshot, TimedAction, Timer, TimerId};
+use crate::types::{
+    BookOrder, InstrumentId, MarketState, Order, OrderId, OrderStatus, OrderStatusView, Quote, RestingOrder,
+    RestingOrderView, Side, TradeId, TraderId,
+};
+use log::{info, warn};
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 // ---------------------------------------------------------------------------
 // Protocol abstraction (Phase 2): trait used by REST, WebSocket, FIX adapters
@@ -22,6 +35,28 @@ pub struct BookSnapshot {
     pub instrument_id: InstrumentId,
     pub best_bid: Option<Decimal>,
     pub best_ask: Option<Decimal>,
+    /// Book checksum (see [`OrderBook::checksum`]).
+    pub checksum: u32,
+    /// Best N aggregated levels per side when the engine is configured with snapshot levels
+    /// (see [`MultiEngine::set_snapshot_levels`]); `None` otherwise.
+    pub levels: Option<BookLevels>,
+    /// Engine sequence number of the last change to this book (see [`ExecutionReport::seq`]); 0 if none yet.
+    pub seq: u64,
+}
+
+/// Top-of-book snapshot of `book`, with its best `snapshot_levels` levels per side when set.
+fn book_snapshot(book: &OrderBook, snapshot_levels: Option<usize>, seq: u64) -> BookSnapshot {
+    BookSnapshot {
+        instrument_id: book.instrument_id(),
+        best_bid: book.best_bid(),
+        best_ask: book.best_ask(),
+        checksum: book.checksum(),
+        levels: snapshot_levels.map(|n| BookLevels {
+            bids: book.depth(Side::Buy, n),
+            asks: book.depth(Side::Sell, n),
+        }),
+        seq,
+    }
 }
 
 /// Service interface for the matching engine. All protocol adapters (REST, WebSocket, FIX)
@@ -33,7 +68,8 @@ pub trait MatchingEngine {
     /// Cancel a resting order by id. Returns `Some(instrument_id)` if found and removed (for broadcasting that instrument's update), `None` if not found.
     fn cancel_order(&mut self, order_id: OrderId) -> Option<InstrumentId>;
 
-    /// Modify: cancel by `order_id`, then match the replacement. Returns trades and reports.
+    /// Modify: cancel by `order_id`, then match the replacement. Returns trades and reports; the first report is
+    /// `Replaced` and carries both the original and the replacement order id.
     fn modify_order(
         &mut self,
         order_id: OrderId,
@@ -46,6 +82,48 @@ pub trait MatchingEngine {
     /// Top-of-book snapshot for a given instrument. Returns `None` if instrument not found.
     fn book_snapshot_for(&self, id: InstrumentId) -> Option<BookSnapshot>;
 
+    /// Imbalance, spread, and microprice over the best `levels` levels per side. Returns `None` if instrument not found.
+    fn book_stats_for(&self, id: InstrumentId, levels: usize) -> Option<BookStats>;
+
+    /// All aggregated price levels (for L2 snapshots and [`crate::order_book::BookDelta`]). Returns `None` if instrument not found.
+    fn book_levels_for(&self, id: InstrumentId) -> Option<BookLevels>;
+
+    /// Live state of a resting order (side, price, remaining quantity, queue position).
+    /// Returns `None` if the order is not resting on any book.
+    fn get_order(&self, order_id: OrderId) -> Option<RestingOrderView>;
+
+    /// Status of an accepted order, including filled and canceled ones: status, filled and remaining
+    /// quantity, price, and queue details while resting. Returns `None` for unknown orders and for
+    /// finished orders older than the last [`RECENT_ORDER_IDS_CAPACITY`].
+    fn order_status(&self, order_id: OrderId) -> Option<OrderStatusView>;
+
+    /// Retained trades with an id greater than `trade_id`, oldest first (pass `TradeId(0)` for all).
+    /// Only the last [`TRADE_HISTORY_CAPACITY`] trades are kept.
+    fn trades_since(&self, trade_id: TradeId) -> Vec<Trade>;
+
+    /// The most recent `limit` retained trades for one instrument, oldest first.
+    fn trades_for_instrument(&self, id: InstrumentId, limit: usize) -> Vec<Trade>;
+
+    /// The most recent `limit` retained trades with an id less than `trade_id` (optionally only for
+    /// `instrument_id`), oldest first: pages backwards through the tape from the first trade of the last page.
+    fn trades_before(&self, trade_id: TradeId, instrument_id: Option<InstrumentId>, limit: usize) -> Vec<Trade>;
+
+    /// One page of retained trades matching `query`'s instrument, trader, and time filters (see
+    /// [`crate::history`]).
+    fn trade_history(&self, query: &HistoryQuery) -> HistoryPage<Trade>;
+
+    /// One page of retained execution reports matching `query`. Only the last [`EXECUTION_HISTORY_CAPACITY`]
+    /// reports are kept; cancels that produce no report are not included.
+    fn execution_history(&self, query: &HistoryQuery) -> HistoryPage<ExecutionReport>;
+
+    /// Net filled position of `trader_id` in `instrument_id` (see [`crate::positions`]), with the quantity
+    /// they have resting on each side. Flat if they have never traded or quoted the instrument.
+    fn position(&self, trader_id: TraderId, instrument_id: InstrumentId) -> Position;
+
+    /// Session statistics (open, high, low, last, volume, VWAP) of one instrument, updated on every trade (see
+    /// [`crate::stats`]). Returns `None` if instrument not found.
+    fn stats_for(&self, id: InstrumentId) -> Option<InstrumentStats>;
+
     /// First instrument (for backward compat). Default: first of `instruments()`.
     fn instrument_id(&self) -> InstrumentId {
         self.instruments().into_iter().next().unwrap_or(InstrumentId(0))
@@ -67,6 +145,9 @@ pub trait MatchingEngine {
             instrument_id: self.instrument_id(),
             best_bid: None,
             best_ask: None,
+            checksum: 0,
+            levels: None,
+            seq: 0,
         })
     }
 }
@@ -98,16 +179,57 @@ impl MatchingEngine for Engine {
 
     fn book_snapshot_for(&self, id: InstrumentId) -> Option<BookSnapshot> {
         if id == self.instrument_id {
-            Some(BookSnapshot {
-                instrument_id: self.instrument_id,
-                best_bid: self.book.best_bid(),
-                best_ask: self.book.best_ask(),
-            })
+            Some(book_snapshot(&self.book, self.snapshot_levels, self.seq.book_seq(self.instrument_id)))
         } else {
             None
         }
     }
 
+    fn book_stats_for(&self, id: InstrumentId, levels: usize) -> Option<BookStats> {
+        (id == self.instrument_id).then(|| self.book.stats(levels))
+    }
+
+    fn book_levels_for(&self, id: InstrumentId) -> Option<BookLevels> {
+        (id == self.instrument_id).then(|| self.book.levels())
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<RestingOrderView> {
+        self.book.get_order(order_id)
+    }
+
+    fn order_status(&self, order_id: OrderId) -> Option<OrderStatusView> {
+        self.orders.view(order_id, Some(&self.book))
+    }
+
+    fn trades_since(&self, trade_id: TradeId) -> Vec<Trade> {
+        self.trades.since(trade_id)
+    }
+
+    fn trades_for_instrument(&self, id: InstrumentId, limit: usize) -> Vec<Trade> {
+        self.trades.for_instrument(id, limit)
+    }
+
+    fn trades_before(&self, trade_id: TradeId, instrument_id: Option<InstrumentId>, limit: usize) -> Vec<Trade> {
+        self.trades.before(trade_id, instrument_id, limit)
+    }
+
+    fn trade_history(&self, query: &HistoryQuery) -> HistoryPage<Trade> {
+        self.trades.page(query)
+    }
+
+    fn execution_history(&self, query: &HistoryQuery) -> HistoryPage<ExecutionReport> {
+        self.executions.page(query)
+    }
+
+    fn position(&self, trader_id: TraderId, instrument_id: InstrumentId) -> Position {
+        let book = (instrument_id == self.instrument_id).then_some(&self.book);
+        position_with_exposure(&self.positions, book, trader_id, instrument_id)
+    }
+
+    fn stats_for(&self, id: InstrumentId) -> Option<InstrumentStats> {
+        (id == self.instrument_id).then(|| self.stats.get(id))
+    }
+
     fn instrument_id(&self) -> InstrumentId {
         self.instrument_id
     }
@@ -123,6 +245,363 @@ impl MatchingEngine for Engine {
 
 // ---------------------------------------------------------------------------
 
+/// How many accepted order ids each engine remembers for duplicate detection after the order leaves the book.
+pub const RECENT_ORDER_IDS_CAPACITY: usize = 100_000;
+
+/// Bounded record of recently accepted order ids (FIFO eviction). Live orders are checked against the book.
+#[derive(Debug)]
+struct RecentOrderIds {
+    ids: HashSet<OrderId>,
+    order: VecDeque<OrderId>,
+    capacity: usize,
+}
+
+impl RecentOrderIds {
+    fn new(capacity: usize) -> Self {
+        Self {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn contains(&self, order_id: OrderId) -> bool {
+        self.ids.contains(&order_id)
+    }
+
+    fn insert(&mut self, order_id: OrderId) {
+        if !self.ids.insert(order_id) {
+            return;
+        }
+        self.order.push_back(order_id);
+        while self.order.len() > self.capacity {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+    }
+}
+
+/// Engine-wide output sequence: every trade, execution report, and book change takes the next number, so
+/// consumers can detect gaps.
+#[derive(Debug)]
+struct Sequencer {
+    next: u64,
+    /// Sequence number of each book's last change.
+    books: HashMap<InstrumentId, u64>,
+}
+
+impl Sequencer {
+    fn new() -> Self {
+        Self::resume(1)
+    }
+
+    fn resume(next: u64) -> Self {
+        Self {
+            next: next.max(1),
+            books: HashMap::new(),
+        }
+    }
+
+    fn take(&mut self) -> u64 {
+        let seq = self.next;
+        self.next += 1;
+        seq
+    }
+
+    /// Number `trades` then `reports` in order.
+    fn stamp(&mut self, trades: &mut [Trade], reports: &mut [ExecutionReport]) {
+        for trade in trades {
+            trade.seq = self.take();
+        }
+        for report in reports {
+            report.seq = self.take();
+        }
+    }
+
+    fn book_changed(&mut self, instrument_id: InstrumentId) {
+        let seq = self.take();
+        self.books.insert(instrument_id, seq);
+    }
+
+    fn book_seq(&self, instrument_id: InstrumentId) -> u64 {
+        self.books.get(&instrument_id).copied().unwrap_or(0)
+    }
+}
+
+/// How many trades each engine retains for [`MatchingEngine::trades_since`],
+/// [`MatchingEngine::trades_for_instrument`], and [`MatchingEngine::trade_history`].
+pub const TRADE_HISTORY_CAPACITY: usize = 100_000;
+
+/// How many execution reports each engine retains for [`MatchingEngine::execution_history`].
+pub const EXECUTION_HISTORY_CAPACITY: usize = 100_000;
+
+/// Engine-side state of one accepted order, kept after it leaves the book.
+#[derive(Clone, Debug)]
+struct OrderRecord {
+    instrument_id: InstrumentId,
+    side: Side,
+    price: Option<Decimal>,
+    trader_id: TraderId,
+    quantity: Decimal,
+    filled: Decimal,
+    /// Sum of price * quantity over fills, for the average price.
+    notional: Decimal,
+    status: OrderStatus,
+}
+
+/// Status of accepted orders for [`MatchingEngine::order_status`]: every live order, plus the last
+/// `capacity` filled or canceled ones (FIFO eviction, like [`RecentOrderIds`]).
+#[derive(Debug)]
+struct OrderTracker {
+    records: HashMap<OrderId, OrderRecord>,
+    done: VecDeque<OrderId>,
+    capacity: usize,
+}
+
+impl OrderTracker {
+    fn new(capacity: usize) -> Self {
+        Self {
+            records: HashMap::new(),
+            done: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Track a newly accepted order and apply its fills. `rests` is whether it is on the book after
+    /// matching; an order that does not rest (IOC, FOK, market) ends as its execution reports say (see
+    /// [`unrested_status`]).
+    fn accept(&mut self, order: &Order, trades: &[Trade], reports: &[ExecutionReport], rests: bool) {
+        self.open(order, Decimal::ZERO, Decimal::ZERO, trades, reports, rests);
+    }
+
+    /// Track the replacement of a cancel/replace. It inherits the original's fills, so its filled quantity
+    /// stays cumulative across the chain; a replaced original with a different id is canceled.
+    fn replace(
+        &mut self,
+        order_id: OrderId,
+        replacement: &Order,
+        trades: &[Trade],
+        reports: &[ExecutionReport],
+        rests: bool,
+    ) {
+        let (filled, notional) = self
+            .records
+            .get(&order_id)
+            .map_or((Decimal::ZERO, Decimal::ZERO), |r| (r.filled, r.notional));
+        if replacement.order_id != order_id {
+            self.finish(order_id, OrderStatus::Canceled);
+        }
+        self.open(replacement, filled, notional, trades, reports, rests);
+    }
+
+    fn open(
+        &mut self,
+        order: &Order,
+        filled: Decimal,
+        notional: Decimal,
+        trades: &[Trade],
+        reports: &[ExecutionReport],
+        rests: bool,
+    ) {
+        self.records.insert(
+            order.order_id,
+            OrderRecord {
+                instrument_id: order.instrument_id,
+                side: order.side,
+                price: order.price,
+                trader_id: order.trader_id,
+                quantity: filled + order.quantity,
+                filled,
+                notional,
+                status: if filled.is_zero() { OrderStatus::New } else { OrderStatus::PartiallyFilled },
+            },
+        );
+        self.apply_trades(trades);
+        if !rests {
+            self.finish(order.order_id, unrested_status(order.order_id, reports));
+        }
+    }
+
+    /// Track an order restored onto the book, with its remaining quantity as its quantity.
+    fn restore(&mut self, resting: &RestingOrder) {
+        self.accept(&resting.to_order(), &[], &[], true);
+    }
+
+    /// Cumulative fills of a partially filled order, for snapshots. `None` if untracked or unfilled.
+    fn fill_state(&self, order_id: OrderId) -> Option<OrderFillState> {
+        let record = self.records.get(&order_id).filter(|r| !r.filled.is_zero())?;
+        Some(OrderFillState {
+            order_id,
+            quantity: record.quantity,
+            filled_quantity: record.filled,
+            filled_notional: record.notional,
+        })
+    }
+
+    /// Reapply snapshot fill state to a restored order.
+    fn restore_fills(&mut self, fills: &OrderFillState) {
+        if let Some(record) = self.records.get_mut(&fills.order_id) {
+            record.quantity = fills.quantity;
+            record.filled = fills.filled_quantity;
+            record.notional = fills.filled_notional;
+            if !record.filled.is_zero() && record.status == OrderStatus::New {
+                record.status = OrderStatus::PartiallyFilled;
+            }
+        }
+    }
+
+    /// Add fills to both sides of each trade.
+    fn apply_trades(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            for order_id in [trade.buy_order_id, trade.sell_order_id] {
+                let Some(record) = self.records.get_mut(&order_id) else {
+                    continue;
+                };
+                record.filled += trade.quantity;
+                record.notional += trade.price * trade.quantity;
+                if record.filled >= record.quantity {
+                    self.finish(order_id, OrderStatus::Filled);
+                } else {
+                    record.status = OrderStatus::PartiallyFilled;
+                }
+            }
+        }
+    }
+
+    /// Move a live order to a terminal status; no-op if unknown or already terminal.
+    fn finish(&mut self, order_id: OrderId, status: OrderStatus) {
+        let Some(record) = self.records.get_mut(&order_id) else {
+            return;
+        };
+        if is_terminal(record.status) {
+            return;
+        }
+        record.status = status;
+        self.done.push_back(order_id);
+        while self.done.len() > self.capacity {
+            if let Some(old) = self.done.pop_front() {
+                // Skip ids that were reused by a live order since finishing.
+                if self.records.get(&old).is_some_and(|r| is_terminal(r.status)) {
+                    self.records.remove(&old);
+                }
+            }
+        }
+    }
+
+    /// Instrument and trader of a tracked order.
+    fn owner(&self, order_id: OrderId) -> Option<(InstrumentId, TraderId)> {
+        self.records.get(&order_id).map(|r| (r.instrument_id, r.trader_id))
+    }
+
+    /// Status view of `order_id`, with queue details from `book` while it rests there.
+    fn view(&self, order_id: OrderId, book: Option<&OrderBook>) -> Option<OrderStatusView> {
+        let record = self.records.get(&order_id)?;
+        let resting = book.and_then(|b| b.get_order(order_id));
+        let remaining = match (&resting, record.status) {
+            (Some(r), _) => r.remaining_quantity,
+            (None, OrderStatus::New | OrderStatus::PartiallyFilled) => record.quantity - record.filled,
+            (None, _) => Decimal::ZERO,
+        };
+        Some(OrderStatusView {
+            order_id,
+            instrument_id: record.instrument_id,
+            side: record.side,
+            price: record.price,
+            status: record.status,
+            quantity: record.quantity,
+            filled_quantity: record.filled,
+            remaining_quantity: remaining,
+            avg_price: (!record.filled.is_zero()).then(|| record.notional / record.filled),
+            trader_id: record.trader_id,
+            queue_position: resting.as_ref().map(|r| r.queue_position),
+            level: resting.as_ref().map(|r| r.level),
+            quantity_ahead: resting.as_ref().map(|r| r.quantity_ahead),
+        })
+    }
+}
+
+/// Retain `trades` and `reports` in the history stores, tagged with the instrument and traders of their orders
+/// (looked up from the order tracker).
+fn record_history(
+    trade_store: &mut TradeStore,
+    execution_store: &mut ExecutionStore,
+    orders: &OrderTracker,
+    trades: &[Trade],
+    reports: &[ExecutionReport],
+) {
+    trade_store.record(trades, |order_id| orders.owner(order_id).map(|(_, trader_id)| trader_id));
+    execution_store.record(reports, |order_id| orders.owner(order_id));
+}
+
+/// Net `trades` into the positions of the traders on each side (looked up from the order tracker).
+fn apply_positions(positions: &mut PositionBook, orders: &OrderTracker, trades: &[Trade]) {
+    let trader = |order_id: &OrderId| orders.records.get(order_id).map(|r| r.trader_id);
+    for trade in trades {
+        if let (Some(buyer), Some(seller)) = (trader(&trade.buy_order_id), trader(&trade.sell_order_id)) {
+            positions.apply_trade(buyer, seller, trade);
+        }
+    }
+}
+
+/// `positions`' entry for (trader, instrument) with the trader's resting quantity on `book` filled in.
+fn position_with_exposure(
+    positions: &PositionBook,
+    book: Option<&OrderBook>,
+    trader_id: TraderId,
+    instrument_id: InstrumentId,
+) -> Position {
+    let mut position = positions.get(trader_id, instrument_id);
+    if let Some(book) = book {
+        position.open_buy_quantity = book.resting_qty_by_trader(trader_id, Side::Buy);
+        position.open_sell_quantity = book.resting_qty_by_trader(trader_id, Side::Sell);
+    }
+    position
+}
+
+/// Run `limits` against `order` (or a replacement for `replacing`, whose resting quantity no longer counts
+/// toward the trader's exposure).
+fn check_risk(
+    limits: &RiskLimits,
+    positions: &PositionBook,
+    book: &OrderBook,
+    order: &Order,
+    replacing: Option<OrderId>,
+) -> Result<(), String> {
+    if *limits == RiskLimits::default() {
+        return Ok(());
+    }
+    let mut position = position_with_exposure(positions, Some(book), order.trader_id, order.instrument_id);
+    if let Some(old) = replacing.and_then(|id| book.get_order(id)).filter(|o| o.trader_id == order.trader_id) {
+        match old.side {
+            Side::Buy => position.open_buy_quantity -= old.remaining_quantity,
+            Side::Sell => position.open_sell_quantity -= old.remaining_quantity,
+        }
+    }
+    limits.check(order, &position)
+}
+
+fn is_terminal(status: OrderStatus) -> bool {
+    matches!(status, OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected)
+}
+
+/// Final status of an order that did not rest after matching: the status of its last execution report when
+/// that is terminal (a FOK kill, a fill), so order status and events agree with the report; otherwise its
+/// unfilled remainder was dropped (IOC partial fill, market order out of liquidity) and it is canceled.
+fn unrested_status(order_id: OrderId, reports: &[ExecutionReport]) -> OrderStatus {
+    reports
+        .iter()
+        .rev()
+        .find(|report| report.order_id == order_id)
+        .map(|report| report.order_status)
+        .filter(|status| is_terminal(*status))
+        .unwrap_or(OrderStatus::Canceled)
+}
+
+fn duplicate_order_id(order_id: OrderId) -> String {
+    format!("Duplicate order id {}", order_id.0)
+}
+
 /// Single-instrument matching engine.
 ///
 /// Use [`Engine::submit_order`] to send orders; the engine runs matching and returns
@@ -132,8 +611,18 @@ impl MatchingEngine for Engine {
 pub struct Engine {
     instrument_id: InstrumentId,
     book: OrderBook,
-    next_trade_id: u64,
-    next_exec_id: u64,
+    ids: IdAllocator,
+    seq: Sequencer,
+    recent_order_ids: RecentOrderIds,
+    orders: OrderTracker,
+    trades: TradeStore,
+    executions: ExecutionStore,
+    positions: PositionBook,
+    stats: StatsBook,
+    risk_limits: RiskLimits,
+    snapshot_levels: Option<usize>,
+    /// Reused by [`Engine::submit_order`] so matching does not allocate a fills buffer per order.
+    scratch: MatchOutput,
 }
 
 impl Engine {
@@ -142,15 +631,50 @@ impl Engine {
         Self {
             instrument_id,
             book: OrderBook::new(instrument_id),
-            next_trade_id: 1,
-            next_exec_id: 1,
+            ids: IdAllocator::new(),
+            seq: Sequencer::new(),
+            recent_order_ids: RecentOrderIds::new(RECENT_ORDER_IDS_CAPACITY),
+            orders: OrderTracker::new(RECENT_ORDER_IDS_CAPACITY),
+            trades: TradeStore::new(TRADE_HISTORY_CAPACITY),
+            executions: ExecutionStore::new(EXECUTION_HISTORY_CAPACITY),
+            positions: PositionBook::new(),
+            stats: StatsBook::new(),
+            risk_limits: RiskLimits::default(),
+            snapshot_levels: None,
+            scratch: MatchOutput::new(),
         }
     }
 
+    /// Include the best `levels` aggregated levels per side in [`BookSnapshot`]s (`None` = top of book only).
+    pub fn set_snapshot_levels(&mut self, levels: Option<usize>) {
+        self.snapshot_levels = levels;
+    }
+
+    /// Creates an engine whose limit prices must be multiples of `tick_size`.
+    /// Returns `Err` if `tick_size` is not positive.
+    pub fn with_tick_size(instrument_id: InstrumentId, tick_size: Decimal) -> Result<Self, String> {
+        let mut engine = Self::new(instrument_id);
+        engine.book = OrderBook::with_tick_size(instrument_id, tick_size)?;
+        Ok(engine)
+    }
+
     /// Submits an order: runs matching and returns trades and execution reports.
     ///
-    /// Returns `Err` if the order is for a different instrument.
+    /// Returns `Err` if the order is for a different instrument, if its price is not a multiple of the
+    /// tick size, if its order id is resting or was recently used (see [`RECENT_ORDER_IDS_CAPACITY`]),
+    /// if it fails a [`RiskLimits`] check, or if it would rest past a [`BookLimits`] cap.
     pub fn submit_order(&mut self, order: Order) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        let mut out = std::mem::take(&mut self.scratch);
+        let result = self.submit_order_into(order, &mut out);
+        let fills = out.take();
+        self.scratch = out;
+        result.map(|()| fills)
+    }
+
+    /// Like [`Engine::submit_order`], writing the trades and reports into `out` (cleared first). Reusing one
+    /// [`MatchOutput`] across orders avoids allocating result vectors per order at high order rates.
+    pub fn submit_order_into(&mut self, order: Order, out: &mut MatchOutput) -> Result<(), String> {
+        out.clear();
         info!(
             "order submitted order_id={} side={:?} quantity={} price={:?}",
             order.order_id.0,
@@ -159,20 +683,26 @@ impl Engine {
             order.price
         );
         if order.instrument_id != self.instrument_id {
-            return Err(format!(
-                "Order instrument does not match engine instrument"
-            ));
+            return Err("Order instrument does not match engine instrument".into());
         }
         if order.is_limit() && order.price.is_none() {
             return Err("Limit order must have price".into());
         }
-        let (trades, reports) = match_order(
-            &mut self.book,
-            &order,
-            self.next_trade_id,
-            self.next_exec_id,
-        );
-        for report in &reports {
+        if self.is_duplicate_order_id(order.order_id) {
+            return Err(duplicate_order_id(order.order_id));
+        }
+        self.book.validate_order(&order, None)?;
+        check_risk(&self.risk_limits, &self.positions, &self.book, &order, None)?;
+        match_order_into(&mut self.book, &order, self.ids.next_trade_id(), self.ids.next_exec_id(), out);
+        let MatchOutput { trades, reports, .. } = out;
+        self.recent_order_ids.insert(order.order_id);
+        self.seq.stamp(trades, reports);
+        self.seq.book_changed(self.instrument_id);
+        self.orders.accept(&order, trades, reports, self.book.contains_order(order.order_id));
+        record_history(&mut self.trades, &mut self.executions, &self.orders, trades, reports);
+        self.stats.record(trades);
+        apply_positions(&mut self.positions, &self.orders, trades);
+        for report in reports.iter() {
             info!(
                 "execution_report order_id={} exec_type={:?} order_status={:?} filled={} remaining={}",
                 report.order_id.0,
@@ -182,7 +712,7 @@ impl Engine {
                 report.remaining_quantity
             );
         }
-        for trade in &trades {
+        for trade in trades.iter() {
             info!(
                 "trade trade_id={} buy_order={} sell_order={} price={} quantity={}",
                 trade.trade_id.0,
@@ -192,15 +722,34 @@ impl Engine {
                 trade.quantity
             );
         }
-        self.next_trade_id += trades.len() as u64;
-        self.next_exec_id += reports.len() as u64;
-        Ok((trades, reports))
+        self.ids.advance(trades.len(), reports.len());
+        Ok(())
+    }
+
+    /// Uncross the book if its best bid reaches its best ask (see [`uncross_book`]), e.g. after restoring a
+    /// snapshot. Returns the resulting trades and reports; both are empty if the book was not crossed.
+    pub fn repair_crossed(&mut self) -> (Vec<Trade>, Vec<ExecutionReport>) {
+        if !self.book.is_crossed() {
+            return (Vec::new(), Vec::new());
+        }
+        let (mut trades, mut reports) = uncross_book(&mut self.book, self.ids.next_trade_id(), self.ids.next_exec_id());
+        self.seq.stamp(&mut trades, &mut reports);
+        self.seq.book_changed(self.instrument_id);
+        self.orders.apply_trades(&trades);
+        record_history(&mut self.trades, &mut self.executions, &self.orders, &trades, &reports);
+        self.stats.record(&trades);
+        apply_positions(&mut self.positions, &self.orders, &trades);
+        self.ids.advance(trades.len(), reports.len());
+        info!("book uncrossed instrument_id={} trades={}", self.instrument_id.0, trades.len());
+        (trades, reports)
     }
 
     /// Cancels a resting order by id. Returns `true` if the order was found and removed.
     pub fn cancel_order(&mut self, order_id: crate::types::OrderId) -> bool {
         let removed = self.book.cancel_order(order_id);
         if removed {
+            self.orders.finish(order_id, OrderStatus::Canceled);
+            self.seq.book_changed(self.instrument_id);
             info!("order canceled order_id={}", order_id.0);
         }
         removed
@@ -208,8 +757,11 @@ impl Engine {
 
     /// Modifies an order: cancel by `order_id`, then run matching on the replacement.
     /// Replacement may use the same or a new order id. Price-time is preserved: any
-    /// resting quantity from the replacement goes to the back of its price level.
-    /// Returns trades and execution reports from matching the replacement.
+    /// resting quantity from the replacement goes to the back of its price level, except
+    /// when only the quantity goes down at the same price, which amends in place and keeps
+    /// the order's queue position.
+    /// Returns trades and execution reports: first an [`crate::types::ExecType::Replaced`] report
+    /// linking the original and replacement ids, then any fills from matching the replacement.
     pub fn modify_order(
         &mut self,
         order_id: crate::types::OrderId,
@@ -218,9 +770,25 @@ impl Engine {
         if replacement.instrument_id != self.instrument_id {
             return Err("Replacement order must be for the same instrument".into());
         }
-        if !self.book.cancel_order(order_id) {
-            return Err(format!("Order {} not found", order_id.0));
+        if replacement.order_id != order_id && self.is_duplicate_order_id(replacement.order_id) {
+            return Err(duplicate_order_id(replacement.order_id));
         }
+        check_risk(&self.risk_limits, &self.positions, &self.book, replacement, Some(order_id))?;
+        let (mut trades, mut reports) = replace_order(
+            &mut self.book,
+            order_id,
+            replacement,
+            self.ids.next_trade_id(),
+            self.ids.next_exec_id(),
+        )?;
+        self.recent_order_ids.insert(replacement.order_id);
+        self.seq.stamp(&mut trades, &mut reports);
+        self.seq.book_changed(self.instrument_id);
+        let rests = self.book.contains_order(replacement.order_id);
+        self.orders.replace(order_id, replacement, &trades, &reports, rests);
+        record_history(&mut self.trades, &mut self.executions, &self.orders, &trades, &reports);
+        self.stats.record(&trades);
+        apply_positions(&mut self.positions, &self.orders, &trades);
         info!(
             "order modified old_order_id={} replacement order_id={} side={:?} quantity={} price={:?}",
             order_id.0,
@@ -229,12 +797,6 @@ impl Engine {
             replacement.quantity,
             replacement.price
         );
-        let (trades, reports) = match_order(
-            &mut self.book,
-            replacement,
-            self.next_trade_id,
-            self.next_exec_id,
-        );
         for report in &reports {
             info!(
                 "execution_report order_id={} exec_type={:?} order_status={:?} filled={} remaining={}",
@@ -255,11 +817,41 @@ impl Engine {
                 trade.quantity
             );
         }
-        self.next_trade_id += trades.len() as u64;
-        self.next_exec_id += reports.len() as u64;
+        self.ids.advance(trades.len(), reports.len());
         Ok((trades, reports))
     }
 
+    fn is_duplicate_order_id(&self, order_id: OrderId) -> bool {
+        self.book.contains_order(order_id) || self.recent_order_ids.contains(order_id)
+    }
+
+    /// Set resting-order caps for the book (see [`BookLimits`]).
+    pub fn set_book_limits(&mut self, limits: BookLimits) {
+        self.book.set_limits(limits);
+    }
+
+    /// Set pre-trade risk limits checked on submit and modify (see [`RiskLimits`]).
+    pub fn set_risk_limits(&mut self, limits: RiskLimits) {
+        self.risk_limits = limits;
+    }
+
+    /// Start a new statistics session (e.g. at the start of the trading day): open, high, low, last, and volume
+    /// restart from the next trade.
+    pub fn reset_session_stats(&mut self) {
+        self.stats.clear();
+    }
+
+    /// Persist trade and execution ids through `store` so they keep increasing across restarts, crashes
+    /// included (see [`IdAllocator::set_store`]). Call before submitting orders.
+    pub fn set_id_store(&mut self, store: std::sync::Arc<dyn IdStore>) -> Result<(), String> {
+        self.ids.set_store(store)
+    }
+
+    /// Next trade and execution ids.
+    pub fn id_watermark(&self) -> IdWatermark {
+        self.ids.watermark()
+    }
+
     /// Returns the instrument this engine handles.
     pub fn instrument_id(&self) -> InstrumentId {
         self.instrument_id
@@ -280,21 +872,189 @@ impl Engine {
 // Multi-instrument engine: one book per instrument, admin can add/remove
 // ---------------------------------------------------------------------------
 
+/// Current [`EngineSnapshot`] schema version. Version 2 added [`EngineSnapshot::order_fills`]; snapshots
+/// without a version are version 1. State files are versioned separately
+/// ([`crate::persistence::STATE_VERSION`]); a change here needs a state file migration step.
+pub const ENGINE_SNAPSHOT_VERSION: u32 = 2;
+
+/// How often [`MultiEngine::replay_command_log`] logs its progress, in entries.
+pub const COMMAND_REPLAY_PROGRESS_EVERY: usize = 10_000;
+
+/// Cumulative fill state of a resting order, so a restored partially filled order keeps its original
+/// quantity, filled quantity, and average price (the book itself only holds the remaining quantity).
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OrderFillState {
+    pub order_id: OrderId,
+    /// Total order quantity, including quantity filled before any replace.
+    pub quantity: Decimal,
+    pub filled_quantity: Decimal,
+    /// Sum of price × quantity over the fills.
+    pub filled_notional: Decimal,
+}
+
+/// Order ids of a trader's live two-sided quote on one instrument (see [`MultiEngine::submit_quote`]). A side
+/// is `None` when it was not quoted; an id may refer to an order that has since filled.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QuoteState {
+    pub trader_id: TraderId,
+    pub instrument_id: InstrumentId,
+    pub bid_order_id: Option<OrderId>,
+    pub ask_order_id: Option<OrderId>,
+}
+
 /// Serializable snapshot of MultiEngine state for persistence.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct EngineSnapshot {
+    /// Schema version ([`ENGINE_SNAPSHOT_VERSION`] when written; missing = 1).
+    #[serde(default = "legacy_snapshot_version")]
+    pub version: u32,
     pub instruments: Vec<(InstrumentId, Option<String>)>,
     /// Per-instrument resting orders.
     pub books: Vec<(InstrumentId, Vec<RestingOrder>)>,
     pub order_to_instrument: Vec<(OrderId, InstrumentId)>,
     pub next_trade_id: u64,
     pub next_exec_id: u64,
+    /// Next engine sequence number (see [`ExecutionReport::seq`]). Older snapshots restart the sequence at 1.
+    #[serde(default)]
+    pub next_seq: u64,
+    /// Per-instrument tick size. Snapshots without it restore with [`DEFAULT_TICK_SIZE`].
+    #[serde(default)]
+    pub tick_sizes: Vec<(InstrumentId, Decimal)>,
+    /// Engine time and pending timers (see [`MultiEngine::advance_time`]). Older snapshots restore with none.
+    #[serde(default)]
+    pub scheduler: SchedulerSnapshot,
+    /// Fill state of resting orders that have traded (version 2). Orders not listed restore as unfilled.
+    #[serde(default)]
+    pub order_fills: Vec<OrderFillState>,
+    /// Instruments not [`InstrumentState::Active`]. Instruments not listed restore as active.
+    #[serde(default)]
+    pub instrument_states: Vec<(InstrumentId, InstrumentState)>,
+    /// Instruments whose own market state is not Open. Instruments not listed restore as Open.
+    #[serde(default)]
+    pub instrument_market_states: Vec<(InstrumentId, MarketState)>,
+    /// Live two-sided quotes, so the next quote from the same trader still replaces them.
+    #[serde(default)]
+    pub quotes: Vec<QuoteState>,
+    /// Session statistics of instruments that have traded. Older snapshots restore with none.
+    #[serde(default)]
+    pub session_stats: Vec<InstrumentStats>,
+    /// Instruments with a lot size. Instruments not listed restore without one.
+    #[serde(default)]
+    pub lot_sizes: Vec<(InstrumentId, Decimal)>,
+    /// Instruments with a price band. Instruments not listed restore without one.
+    #[serde(default)]
+    pub price_bands: Vec<(InstrumentId, PriceBand)>,
+    /// Last [`CommandLog`] entry the snapshot includes (see [`MultiEngine::command_seq`]); 0 in older snapshots.
+    #[serde(default)]
+    pub command_seq: u64,
+    /// Next venue-assigned order id (see [`MultiEngine::allocate_order_ids`]); 0 in older snapshots.
+    #[serde(default)]
+    pub next_order_id: u64,
+}
+
+fn legacy_snapshot_version() -> u32 {
+    1
 }
 
-/// Metadata for an instrument (optional symbol for display).
+/// Trading state of one instrument, independent of the global market state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InstrumentState {
+    /// Accepting orders.
+    #[default]
+    Active,
+    /// New orders and replaces are rejected; cancels are still accepted. Can be reactivated.
+    Suspended,
+    /// Like `Suspended`, but permanent.
+    Delisted,
+}
+
+impl InstrumentState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstrumentState::Active => "Active",
+            InstrumentState::Suspended => "Suspended",
+            InstrumentState::Delisted => "Delisted",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "Active" => Some(InstrumentState::Active),
+            "Suspended" => Some(InstrumentState::Suspended),
+            "Delisted" => Some(InstrumentState::Delisted),
+            _ => None,
+        }
+    }
+}
+
+/// Static limit-price range of an instrument: limit orders priced outside `[low, high]` are rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PriceBand {
+    pub low: Decimal,
+    pub high: Decimal,
+}
+
+/// Metadata for an instrument (optional symbol for display), its lifecycle state, its own market state, and
+/// the order rules beyond its book's tick size.
 #[derive(Clone, Debug)]
 pub struct InstrumentMeta {
     pub symbol: Option<String>,
+    pub state: InstrumentState,
+    /// Halting one instrument leaves the others trading; the venue-wide market state still applies on top.
+    pub market_state: MarketState,
+    /// Order quantities must be multiples of this; `None` = any quantity.
+    pub lot_size: Option<Decimal>,
+    /// Allowed limit prices; `None` = any price.
+    pub price_band: Option<PriceBand>,
+}
+
+impl InstrumentMeta {
+    fn new(symbol: Option<String>) -> Self {
+        Self {
+            symbol,
+            state: InstrumentState::Active,
+            market_state: MarketState::Open,
+            lot_size: None,
+            price_band: None,
+        }
+    }
+
+    /// `Err` with the reason if `order` breaks the lot size or price band.
+    fn check_order(&self, order: &Order) -> Result<(), String> {
+        if let Some(lot) = self.lot_size {
+            if !(order.quantity % lot).is_zero() {
+                return Err(format!("Quantity {} is not a multiple of lot size {}", order.quantity, lot));
+            }
+        }
+        if let (Some(band), true, Some(price)) = (self.price_band, order.is_limit(), order.price) {
+            if price < band.low || price > band.high {
+                return Err(format!(
+                    "Price {} is out of range {}..{} for instrument {}",
+                    price, band.low, band.high, order.instrument_id.0
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Changes to an instrument's metadata for [`MultiEngine::update_instrument`]. `None` leaves a field as it is;
+/// `Some(None)` clears an optional one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstrumentUpdate {
+    pub symbol: Option<Option<String>>,
+    pub tick_size: Option<Decimal>,
+    pub lot_size: Option<Option<Decimal>>,
+    pub price_band: Option<Option<PriceBand>>,
+    pub state: Option<InstrumentState>,
+    pub market_state: Option<MarketState>,
+}
+
+impl InstrumentUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 /// Multi-instrument matching engine. Holds one order book per instrument; admin can add/remove instruments.
@@ -304,8 +1064,41 @@ pub struct MultiEngine {
     books: HashMap<InstrumentId, OrderBook>,
     registry: HashMap<InstrumentId, InstrumentMeta>,
     order_to_instrument: HashMap<OrderId, InstrumentId>,
-    next_trade_id: u64,
-    next_exec_id: u64,
+    ids: IdAllocator,
+    seq: Sequencer,
+    recent_order_ids: RecentOrderIds,
+    orders: OrderTracker,
+    trades: TradeStore,
+    executions: ExecutionStore,
+    positions: PositionBook,
+    stats: StatsBook,
+    risk_limits: RiskLimits,
+    /// Dynamic band around each instrument's last trade.
+    band: BandConfig,
+    /// Daily trading hours; orders outside them are rejected.
+    session: TradingSession,
+    event_sinks: EventSinks,
+    journal: EventJournal,
+    /// Accepted commands, while recording (see [`MultiEngine::start_input_journal`]).
+    input_journal: Option<InputJournal>,
+    /// Consumers of accepted commands (see [`MultiEngine::add_input_sink`]).
+    input_sinks: Vec<std::sync::Arc<dyn InputSink>>,
+    /// Write-ahead log of order entry (see [`MultiEngine::set_command_log`]).
+    command_log: Option<std::sync::Arc<dyn CommandLog>>,
+    /// Number of the last command written to (or replayed from) the command log.
+    command_seq: u64,
+    /// Append-only trade and execution history (see [`MultiEngine::set_history_log`]).
+    history_log: Option<std::sync::Arc<dyn HistoryLog>>,
+    /// While the command log is replayed, the last sequence number the history log already holds: trades and
+    /// reports up to it are not recorded again. 0 otherwise.
+    history_replayed_through: u64,
+    scheduler: Scheduler,
+    /// Live two-sided quote per (trader, instrument).
+    quotes: HashMap<(TraderId, InstrumentId), QuoteState>,
+    /// Caps applied to every book, including instruments added later.
+    book_limits: BookLimits,
+    /// Levels per side carried in [`BookSnapshot`]s; `None` = top of book only.
+    snapshot_levels: Option<usize>,
 }
 
 impl MultiEngine {
@@ -315,484 +1108,3036 @@ impl MultiEngine {
         let mut registry = HashMap::new();
         for (id, symbol) in initial {
             books.insert(id, OrderBook::new(id));
-            registry.insert(id, InstrumentMeta { symbol });
+            registry.insert(id, InstrumentMeta::new(symbol));
         }
         Self {
             books,
             registry,
             order_to_instrument: HashMap::new(),
-            next_trade_id: 1,
-            next_exec_id: 1,
+            ids: IdAllocator::new(),
+            seq: Sequencer::new(),
+            recent_order_ids: RecentOrderIds::new(RECENT_ORDER_IDS_CAPACITY),
+            orders: OrderTracker::new(RECENT_ORDER_IDS_CAPACITY),
+            trades: TradeStore::new(TRADE_HISTORY_CAPACITY),
+            executions: ExecutionStore::new(EXECUTION_HISTORY_CAPACITY),
+            positions: PositionBook::new(),
+            stats: StatsBook::new(),
+            risk_limits: RiskLimits::default(),
+            band: BandConfig::default(),
+            session: TradingSession::default(),
+            event_sinks: EventSinks::default(),
+            journal: EventJournal::new(EVENT_JOURNAL_CAPACITY, 1),
+            input_journal: None,
+            input_sinks: Vec::new(),
+            command_log: None,
+            command_seq: 0,
+            history_log: None,
+            history_replayed_through: 0,
+            scheduler: Scheduler::new(),
+            quotes: HashMap::new(),
+            book_limits: BookLimits::default(),
+            snapshot_levels: None,
         }
     }
 
-    /// Add an instrument (new order book). Returns error if instrument already exists.
-    pub fn add_instrument(&mut self, instrument_id: InstrumentId, symbol: Option<String>) -> Result<(), String> {
-        if self.books.contains_key(&instrument_id) {
-            return Err(format!("Instrument {} already exists", instrument_id.0));
-        }
-        self.books.insert(instrument_id, OrderBook::new(instrument_id));
-        self.registry.insert(instrument_id, InstrumentMeta { symbol });
-        Ok(())
+    /// Include the best `levels` aggregated levels per side in every [`BookSnapshot`] (`None` = top of book only).
+    pub fn set_snapshot_levels(&mut self, levels: Option<usize>) {
+        self.snapshot_levels = levels;
     }
 
-    /// Remove an instrument. Returns error if the book has resting orders.
-    pub fn remove_instrument(&mut self, instrument_id: InstrumentId) -> Result<(), String> {
-        let book = self.books.get(&instrument_id).ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
-        if book.has_resting_orders() {
-            return Err("Instrument has resting orders; cancel them first".to_string());
+    /// Set resting-order caps for all books, current and future (see [`BookLimits`]).
+    pub fn set_book_limits(&mut self, limits: BookLimits) {
+        self.book_limits = limits;
+        for book in self.books.values_mut() {
+            book.set_limits(limits);
         }
-        self.books.remove(&instrument_id);
-        self.registry.remove(&instrument_id);
-        self.order_to_instrument.retain(|_, id| *id != instrument_id);
-        Ok(())
     }
 
-    /// Snapshot of engine state for persistence. Serialize to JSON and restore with [`load_from_snapshot`].
-    pub fn snapshot(&self) -> EngineSnapshot {
-        let instruments: Vec<(InstrumentId, Option<String>)> = self
-            .registry
-            .iter()
-            .map(|(&id, meta)| (id, meta.symbol.clone()))
-            .collect();
-        let books: Vec<(InstrumentId, Vec<RestingOrder>)> = self
-            .books
-            .iter()
-            .map(|(&id, book)| (id, book.resting_orders_snapshot()))
-            .collect();
-        let order_to_instrument: Vec<(OrderId, InstrumentId)> = self
-            .order_to_instrument
-            .iter()
-            .map(|(&oid, &iid)| (oid, iid))
-            .collect();
-        EngineSnapshot {
-            instruments,
-            books,
-            order_to_instrument,
-            next_trade_id: self.next_trade_id,
-            next_exec_id: self.next_exec_id,
-        }
+    /// Set pre-trade risk limits checked on submit and modify, for all instruments (see [`RiskLimits`]).
+    pub fn set_risk_limits(&mut self, limits: RiskLimits) {
+        self.risk_limits = limits;
     }
 
-    /// Restore engine from a snapshot (e.g. after loading from persistence). Replaces current state.
-    pub fn load_from_snapshot(&mut self, snap: EngineSnapshot) -> Result<(), String> {
-        use crate::types::{OrderType, TimeInForce};
-        self.books.clear();
-        self.registry.clear();
-        self.order_to_instrument.clear();
-        for (id, symbol) in &snap.instruments {
-            self.books.insert(*id, OrderBook::new(*id));
-            self.registry.insert(*id, InstrumentMeta { symbol: symbol.clone() });
+    /// Reject limit prices too far from each instrument's last trade on submit, modify, and quote (see
+    /// [`BandConfig`]).
+    pub fn set_band_config(&mut self, band: BandConfig) {
+        self.band = band;
+    }
+
+    /// Reject new orders, replaces, and quotes outside `session` as if the market were closed (see
+    /// [`TradingSession`]). Cancels are always accepted.
+    pub fn set_trading_session(&mut self, session: TradingSession) {
+        self.session = session;
+    }
+
+    /// Start a new statistics session for every instrument (e.g. at the start of the trading day): open, high,
+    /// low, last, and volume restart from the next trade. Returns `Err`, resetting nothing, if the command log
+    /// cannot be written.
+    pub fn reset_session_stats(&mut self) -> Result<(), String> {
+        self.write_ahead(|| Command::ResetSessionStats)?;
+        self.stats.clear();
+        self.record_input(|| Command::ResetSessionStats);
+        self.publish_state_change(None, "stats_reset");
+        Ok(())
+    }
+
+    /// Persist trade and execution ids through `store` so they keep increasing across restarts, crashes
+    /// included, even when the last snapshot is older than the last trade (see [`IdAllocator::set_store`]).
+    pub fn set_id_store(&mut self, store: std::sync::Arc<dyn IdStore>) -> Result<(), String> {
+        self.ids.set_store(store)
+    }
+
+    /// Stop persisting ids and return the id store, e.g. while a standby takes its ids from a primary's
+    /// snapshots; give it back with [`MultiEngine::set_id_store`].
+    pub fn take_id_store(&mut self) -> Option<std::sync::Arc<dyn IdStore>> {
+        self.ids.take_store()
+    }
+
+    /// Next trade, execution, and venue-assigned order ids.
+    pub fn id_watermark(&self) -> IdWatermark {
+        self.ids.watermark()
+    }
+
+    /// Ids for `count` orders the venue numbers itself (FIX replacement orders and quote sides), from the same
+    /// allocator as trade ids so no two sessions hand out the same one. Ids a client already used are skipped.
+    /// Returns the first; the rest follow consecutively.
+    pub fn allocate_order_ids(&mut self, count: u64) -> OrderId {
+        loop {
+            let first = self.ids.allocate_order_ids(count);
+            if !(first..first + count).any(|id| self.is_duplicate_order_id(OrderId(id))) {
+                return OrderId(first);
+            }
         }
-        for (instrument_id, resting) in &snap.books {
-            let book = self.books.get_mut(instrument_id).ok_or_else(|| format!("Instrument {} not in snapshot instruments", instrument_id.0))?;
-            book.load_resting_orders(resting, OrderType::Limit, TimeInForce::GTC)?;
-            for r in resting {
-                self.order_to_instrument.insert(r.order_id, *instrument_id);
+    }
+
+    /// Current pre-trade risk limits.
+    pub fn risk_limits(&self) -> RiskLimits {
+        self.risk_limits
+    }
+
+    /// Register a consumer of every [`EngineEvent`] this engine emits from now on.
+    pub fn add_event_sink(&mut self, sink: std::sync::Arc<dyn EngineEventSink>) {
+        self.event_sinks.add(sink);
+    }
+
+    /// Register an observer called after every book change from now on (see [`BookObserver`]).
+    pub fn add_book_observer(&mut self, observer: std::sync::Arc<dyn BookObserver>) {
+        self.event_sinks.add_observer(observer);
+    }
+
+    /// Send `event` to every registered sink, e.g. a market state change made outside the engine, and
+    /// journal it if it is sequenced.
+    pub fn publish(&mut self, event: EngineEvent) {
+        self.event_sinks.publish(&event);
+        self.journal.record(event);
+    }
+
+    /// Journaled trades, reports, and book changes with a sequence number greater than `seq`, oldest first.
+    /// Returns `Err` if some of them have been evicted (see [`EVENT_JOURNAL_CAPACITY`]) or predate a snapshot
+    /// restore; the caller should resynchronize from a snapshot instead.
+    pub fn events_since(&self, seq: u64) -> Result<Vec<EngineEvent>, String> {
+        self.journal.since(seq)
+    }
+
+    /// Start recording every accepted command into an [`InputJournal`] based on the current state, replacing
+    /// any journal already being recorded. Loading a snapshot restarts the journal from the loaded state.
+    pub fn start_input_journal(&mut self) {
+        self.input_journal = Some(InputJournal::new(self.snapshot()));
+    }
+
+    /// The input journal being recorded, if any.
+    pub fn input_journal(&self) -> Option<&InputJournal> {
+        self.input_journal.as_ref()
+    }
+
+    /// Stop recording and return the input journal, if one was being recorded.
+    pub fn take_input_journal(&mut self) -> Option<InputJournal> {
+        self.input_journal.take()
+    }
+
+    /// Give every command this engine accepts from now on to `sink`, in the order an input journal records
+    /// them, and tell it when a snapshot load replaces the state.
+    pub fn add_input_sink(&mut self, sink: std::sync::Arc<dyn InputSink>) {
+        self.input_sinks.push(sink);
+    }
+
+    /// Write every state-changing command (order entry, instrument changes, uncrosses, timers, and time; see
+    /// [`Command`]) to `log` once it passes validation and before it changes any state. A command that cannot
+    /// be written is refused: `Err` where the call returns a `Result`, `None` for cancels, `false` for timer
+    /// cancels, and no timers run for [`MultiEngine::advance_time`]. Entries are numbered on from
+    /// [`MultiEngine::command_seq`]; loading a snapshot clears the log.
+    pub fn set_command_log(&mut self, log: std::sync::Arc<dyn CommandLog>) {
+        self.command_log = Some(log);
+    }
+
+    /// Append every trade and execution report to `log` from now on, and reload the retained history from the
+    /// records it already holds (the last [`TRADE_HISTORY_CAPACITY`] trades and [`EXECUTION_HISTORY_CAPACITY`]
+    /// reports). Set it before [`MultiEngine::replay_command_log`], which then skips what the log already holds.
+    /// Returns how many records the log held.
+    pub fn set_history_log(&mut self, log: std::sync::Arc<dyn HistoryLog>) -> Result<usize, String> {
+        let records = log.records()?;
+        let count = records.len();
+        self.trades = TradeStore::new(TRADE_HISTORY_CAPACITY);
+        self.executions = ExecutionStore::new(EXECUTION_HISTORY_CAPACITY);
+        self.push_history(records);
+        self.history_log = Some(log);
+        Ok(count)
+    }
+
+    /// Retain `records` in the history stores.
+    fn push_history(&mut self, records: Vec<HistoryRecord>) {
+        for record in records {
+            match record {
+                HistoryRecord::Trade { trade, buyer, seller } => self.trades.push(trade, buyer, seller),
+                HistoryRecord::Report {
+                    report,
+                    instrument_id,
+                    trader_id,
+                } => self.executions.push(report, instrument_id, trader_id),
             }
         }
-        self.next_trade_id = snap.next_trade_id;
-        self.next_exec_id = snap.next_exec_id;
-        Ok(())
     }
 
-    /// List instruments with optional symbol (for admin GET).
-    pub fn list_instruments(&self) -> Vec<(InstrumentId, Option<String>)> {
-        self.registry
+    /// Retain `trades` and `reports` in the history stores and append them to the history log, tagged with the
+    /// instrument and traders of their orders (looked up from the order tracker).
+    fn record_history(&mut self, trades: &[Trade], reports: &[ExecutionReport]) {
+        let Some(log) = &self.history_log else {
+            record_history(&mut self.trades, &mut self.executions, &self.orders, trades, reports);
+            return;
+        };
+        let orders = &self.orders;
+        let trader = |order_id| orders.owner(order_id).map(|(_, trader_id)| trader_id);
+        let mut records: Vec<HistoryRecord> = trades
             .iter()
-            .map(|(&id, meta)| (id, meta.symbol.clone()))
-            .collect()
+            .map(|trade| HistoryRecord::Trade {
+                trade: trade.clone(),
+                buyer: trader(trade.buy_order_id),
+                seller: trader(trade.sell_order_id),
+            })
+            .chain(reports.iter().map(|report| {
+                let owner = orders.owner(report.order_id);
+                HistoryRecord::Report {
+                    report: report.clone(),
+                    instrument_id: owner.map(|(instrument_id, _)| instrument_id),
+                    trader_id: owner.map(|(_, trader_id)| trader_id),
+                }
+            }))
+            .filter(|record| record.seq() > self.history_replayed_through)
+            .collect();
+        records.sort_by_key(HistoryRecord::seq);
+        if let Err(e) = log.append(&records) {
+            warn!("History log write failed: {}; {} trades and reports kept in memory only", e, records.len());
+        }
+        self.push_history(records);
     }
 
-    fn update_order_to_instrument_after_submit(&mut self, order: &Order, reports: &[ExecutionReport]) {
-        let aggressor_report = reports.iter().find(|r| r.order_id == order.order_id);
-        if let Some(r) = aggressor_report {
-            if r.remaining_quantity > Decimal::ZERO {
-                self.order_to_instrument.insert(order.order_id, order.instrument_id);
+    /// Number of the last command written to (or replayed from) the command log; snapshots record it.
+    pub fn command_seq(&self) -> u64 {
+        self.command_seq
+    }
+
+    /// Sequence number of the last trade, report, or book change (0 before any).
+    pub fn last_seq(&self) -> u64 {
+        self.seq.next - 1
+    }
+
+    /// Apply the commands `log` holds after [`MultiEngine::command_seq`], e.g. at startup on top of the last
+    /// saved snapshot, with the same results (ids and sequence numbers included) as when they were first
+    /// accepted. Like [`MultiEngine::replay`], run it before risk limits, bands, book limits, and the trading
+    /// session are configured: the commands already passed those. A command that fails again (a replace whose
+    /// order was gone) is skipped, as it failed the first time. Returns `Err`, applying nothing, if the log
+    /// cannot be read or does not follow on from `command_seq` (it was emptied by a later save than the
+    /// snapshot's). Progress is logged every [`COMMAND_REPLAY_PROGRESS_EVERY`] entries.
+    pub fn replay_command_log(&mut self, log: &dyn CommandLog) -> Result<CommandReplay, String> {
+        let entries = log.entries_after(self.command_seq)?;
+        if let Some(first) = entries.first().filter(|first| first.seq != self.command_seq + 1) {
+            return Err(format!(
+                "Command log starts at entry {}, but the snapshot ends at entry {}",
+                first.seq, self.command_seq
+            ));
+        }
+        let writer = self.command_log.take();
+        self.history_replayed_through = self.history_log.as_ref().map_or(0, |log| log.last_seq());
+        let mut replay = CommandReplay::default();
+        let total = entries.len();
+        for (done, entry) in entries.into_iter().enumerate() {
+            if done > 0 && done % COMMAND_REPLAY_PROGRESS_EVERY == 0 {
+                info!("Command log replay: {}/{} entries", done, total);
             }
+            match self.apply_command(&entry.command) {
+                Ok(_) => replay.applied += 1,
+                Err(e) => {
+                    warn!("command log entry {} skipped on replay: {}", entry.seq, e);
+                    replay.skipped += 1;
+                }
+            }
+            self.command_seq = entry.seq;
         }
+        self.command_log = writer;
+        self.history_replayed_through = 0;
+        Ok(replay)
     }
 
-    fn update_order_to_instrument_after_modify(&mut self, replacement: &Order, reports: &[ExecutionReport]) {
-        let aggressor_report = reports.iter().find(|r| r.order_id == replacement.order_id);
-        if let Some(r) = aggressor_report {
-            if r.remaining_quantity > Decimal::ZERO {
-                self.order_to_instrument.insert(replacement.order_id, replacement.instrument_id);
+    /// Write `command` to the command log, if there is one, as the next entry.
+    fn write_ahead(&mut self, command: impl FnOnce() -> Command) -> Result<(), String> {
+        let Some(log) = &self.command_log else { return Ok(()) };
+        let entry = JournalEntry::new(self.command_seq + 1, command());
+        log.append(&entry).map_err(|e| format!("Command log write failed: {}", e))?;
+        self.command_seq = entry.seq;
+        Ok(())
+    }
+
+    /// Rebuild an engine from `journal`: restore its base snapshot, then apply every command in order. Returns
+    /// the engine and the trades and reports the commands produced, identical to the original run's (ids,
+    /// sequence numbers, and timestamps included). Returns `Err` if a command is no longer accepted, i.e. the
+    /// journal does not belong to its base.
+    pub fn replay(journal: &InputJournal) -> Result<Replay, String> {
+        let mut engine = MultiEngine::new_with_instruments(vec![]);
+        engine.load_from_snapshot(journal.base.clone())?;
+        let mut replay_trades = Vec::new();
+        let mut replay_reports = Vec::new();
+        for entry in &journal.entries {
+            let (trades, reports) = engine
+                .apply_input(&entry.command)
+                .map_err(|e| format!("Journal entry {} failed on replay: {}", entry.seq, e))?;
+            replay_trades.extend(trades);
+            replay_reports.extend(reports);
+        }
+        Ok(Replay {
+            engine,
+            trades: replay_trades,
+            reports: replay_reports,
+        })
+    }
+
+    /// Apply `command`, accepted by an engine in the same state as this one (e.g. a primary's, streamed to a
+    /// standby), with the same results. Risk limits, bands, book limits, and the trading session are lifted
+    /// while it applies: the command already passed the accepting engine's. Returns `Err` if it is not
+    /// accepted, i.e. this engine's state differs.
+    pub fn apply_input(&mut self, command: &Command) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        let risk_limits = std::mem::take(&mut self.risk_limits);
+        let band = std::mem::take(&mut self.band);
+        let session = std::mem::take(&mut self.session);
+        let book_limits = self.book_limits;
+        if book_limits != BookLimits::default() {
+            self.set_book_limits(BookLimits::default());
+        }
+        let result = self.apply_command(command);
+        self.risk_limits = risk_limits;
+        self.band = band;
+        self.session = session;
+        if book_limits != BookLimits::default() {
+            self.set_book_limits(book_limits);
+        }
+        result
+    }
+
+    fn apply_command(&mut self, command: &Command) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        Ok(match command {
+            Command::Submit { order } => self.submit_order(order.clone())?,
+            Command::Cancel { order_id } => {
+                self.cancel_order(*order_id).ok_or_else(|| format!("Order {} not found", order_id.0))?;
+                (Vec::new(), Vec::new())
+            }
+            Command::Modify { order_id, replacement } => self.modify_order(*order_id, replacement)?,
+            Command::AddInstrument { instrument_id, symbol, tick_size } => {
+                self.add_instrument_with_tick_size(*instrument_id, symbol.clone(), *tick_size)?;
+                (Vec::new(), Vec::new())
+            }
+            Command::RemoveInstrument { instrument_id } => {
+                self.remove_instrument(*instrument_id)?;
+                (Vec::new(), Vec::new())
+            }
+            Command::Uncross { instrument_id } => self.repair_crossed(*instrument_id)?,
+            Command::SetInstrumentState { instrument_id, state } => {
+                self.set_instrument_state(*instrument_id, *state)?;
+                (Vec::new(), Vec::new())
+            }
+            Command::SetInstrumentMarketState { instrument_id, market_state } => {
+                self.set_instrument_market_state(*instrument_id, *market_state)?;
+                (Vec::new(), Vec::new())
+            }
+            Command::UpdateInstrument {
+                instrument_id,
+                symbol,
+                tick_size,
+                lot_size,
+                price_band,
+            } => {
+                let update = InstrumentUpdate {
+                    symbol: Some(symbol.clone()),
+                    tick_size: Some(*tick_size),
+                    lot_size: Some(*lot_size),
+                    price_band: Some(*price_band),
+                    ..InstrumentUpdate::default()
+                };
+                self.update_instrument(*instrument_id, update)?;
+                (Vec::new(), Vec::new())
+            }
+            // A quote is journaled once its old sides are pulled, even if a new side was then rejected, so
+            // the same rejection on replay is expected.
+            Command::Quote { quote } => self.submit_quote(quote).unwrap_or_default(),
+            Command::Schedule { due, action } => {
+                self.schedule(*due, action.clone())?;
+                (Vec::new(), Vec::new())
+            }
+            Command::CancelTimer { timer_id } => {
+                if !self.cancel_timer(*timer_id) {
+                    return Err(format!("Timer {} not found", timer_id.0));
+                }
+                (Vec::new(), Vec::new())
+            }
+            Command::ResetSessionStats => {
+                self.reset_session_stats()?;
+                (Vec::new(), Vec::new())
+            }
+            Command::AdvanceTime { now } => {
+                let (mut trades, mut reports) = (Vec::new(), Vec::new());
+                for (timer_trades, timer_reports) in self.advance_time(*now).into_iter().filter_map(|f| f.result.ok()) {
+                    trades.extend(timer_trades);
+                    reports.extend(timer_reports);
+                }
+                (trades, reports)
+            }
+        })
+    }
+
+    fn record_input(&mut self, command: impl FnOnce() -> Command) {
+        if self.input_journal.is_none() && self.input_sinks.is_empty() {
+            return;
+        }
+        let command = command();
+        for sink in &self.input_sinks {
+            sink.on_command(&command);
+        }
+        if let Some(journal) = &mut self.input_journal {
+            journal.record(command);
+        }
+    }
+
+    fn publish_state_change(&mut self, instrument_id: Option<InstrumentId>, state: &str) {
+        if !self.event_sinks.is_empty() {
+            self.publish(EngineEvent::StateChange {
+                instrument_id,
+                state: state.to_string(),
+            });
+        }
+    }
+
+    fn publish_book_changed(&mut self, instrument_id: InstrumentId) {
+        let Some(book) = self.books.get(&instrument_id) else { return };
+        let event = EngineEvent::BookChanged {
+            instrument_id,
+            seq: self.seq.book_seq(instrument_id),
+            best_bid: book.best_bid(),
+            best_ask: book.best_ask(),
+            checksum: book.checksum(),
+        };
+        self.publish(event);
+        self.event_sinks.book_changed(self, instrument_id);
+    }
+
+    fn publish_fills(&mut self, trades: &[Trade], reports: &[ExecutionReport]) {
+        for trade in trades {
+            self.publish(EngineEvent::Trade(trade.clone()));
+        }
+        for report in reports {
+            self.publish(EngineEvent::Report(report.clone()));
+        }
+    }
+
+    /// Events for one accepted order (or replacement): accepted, trades, reports, `Expired` if its
+    /// unfilled quantity was dropped rather than rested, then the book change.
+    fn publish_order_events(&mut self, order: &Order, trades: &[Trade], reports: &[ExecutionReport]) {
+        if !self.event_sinks.is_empty() {
+            self.publish(EngineEvent::OrderAccepted { order: order.clone() });
+        }
+        self.publish_fills(trades, reports);
+        if let Some(record) = self.orders.records.get(&order.order_id) {
+            if record.status == OrderStatus::Canceled && !self.event_sinks.is_empty() {
+                let quantity = record.quantity - record.filled;
+                self.publish(EngineEvent::Expired {
+                    order_id: order.order_id,
+                    instrument_id: order.instrument_id,
+                    quantity,
+                });
             }
         }
+        self.publish_book_changed(order.instrument_id);
     }
-}
 
-impl MatchingEngine for MultiEngine {
-    fn submit_order(&mut self, order: Order) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
-        let book = self.books.get_mut(&order.instrument_id).ok_or_else(|| {
-            format!("Unknown instrument {}", order.instrument_id.0)
+    /// Add an instrument (new order book) with [`DEFAULT_TICK_SIZE`]. Returns error if instrument already exists.
+    pub fn add_instrument(&mut self, instrument_id: InstrumentId, symbol: Option<String>) -> Result<(), String> {
+        self.add_instrument_with_tick_size(instrument_id, symbol, DEFAULT_TICK_SIZE)
+    }
+
+    /// Add an instrument whose limit prices must be multiples of `tick_size`. Returns error if the
+    /// instrument already exists or `tick_size` is not positive.
+    pub fn add_instrument_with_tick_size(
+        &mut self,
+        instrument_id: InstrumentId,
+        symbol: Option<String>,
+        tick_size: Decimal,
+    ) -> Result<(), String> {
+        if self.books.contains_key(&instrument_id) {
+            return Err(format!("Instrument {} already exists", instrument_id.0));
+        }
+        let mut book = OrderBook::with_tick_size(instrument_id, tick_size)?;
+        book.set_limits(self.book_limits);
+        self.write_ahead(|| Command::AddInstrument {
+            instrument_id,
+            symbol: symbol.clone(),
+            tick_size,
         })?;
-        if order.is_limit() && order.price.is_none() {
-            return Err("Limit order must have price".into());
+        self.books.insert(instrument_id, book);
+        self.record_input(|| Command::AddInstrument {
+            instrument_id,
+            symbol: symbol.clone(),
+            tick_size,
+        });
+        self.registry.insert(instrument_id, InstrumentMeta::new(symbol));
+        self.publish_state_change(Some(instrument_id), "added");
+        self.event_sinks.book_changed(self, instrument_id);
+        Ok(())
+    }
+
+    /// Tick size of an instrument's book. `None` if the instrument is unknown.
+    pub fn tick_size(&self, instrument_id: InstrumentId) -> Option<Decimal> {
+        self.books.get(&instrument_id).map(|book| book.tick_size())
+    }
+
+    /// Lot size of an instrument (`None` if it has none or is unknown).
+    pub fn lot_size(&self, instrument_id: InstrumentId) -> Option<Decimal> {
+        self.registry.get(&instrument_id).and_then(|meta| meta.lot_size)
+    }
+
+    /// Price band of an instrument (`None` if it has none or is unknown).
+    pub fn price_band(&self, instrument_id: InstrumentId) -> Option<PriceBand> {
+        self.registry.get(&instrument_id).and_then(|meta| meta.price_band)
+    }
+
+    /// Change an instrument's symbol, tick size, lot size, price band, and/or states at runtime; nothing changes
+    /// if any part is invalid. Returns `Err` if the instrument is unknown, a size is not positive, the band's
+    /// low is above its high, a resting order is off the new tick grid, or the state change is refused (see
+    /// [`MultiEngine::set_instrument_state`]). Resting orders outside a new lot size or band stay on the book;
+    /// the rules apply to new orders and replaces.
+    pub fn update_instrument(&mut self, instrument_id: InstrumentId, update: InstrumentUpdate) -> Result<(), String> {
+        let meta = self
+            .registry
+            .get(&instrument_id)
+            .ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        if let Some(Some(lot)) = update.lot_size {
+            if lot <= Decimal::ZERO {
+                return Err(format!("Lot size {} must be positive", lot));
+            }
+        }
+        if let Some(Some(band)) = update.price_band {
+            if band.low > band.high {
+                return Err(format!("Price band low {} is above high {}", band.low, band.high));
+            }
+        }
+        if meta.state == InstrumentState::Delisted && update.state.is_some_and(|s| s != InstrumentState::Delisted) {
+            return Err(format!("Instrument {} is delisted", instrument_id.0));
+        }
+        let book = self.books.get(&instrument_id).ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        // Rebuilt on the new grid, and only swapped in once the change is written ahead.
+        let rebuilt = match update.tick_size {
+            Some(tick_size) if tick_size <= Decimal::ZERO => return Err(format!("Tick size {} must be positive", tick_size)),
+            Some(tick_size) => {
+                let mut snapshot = book.snapshot();
+                snapshot.tick_size = tick_size;
+                let rebuilt = OrderBook::restore(&snapshot)
+                    .map_err(|e| format!("Cannot change tick size of instrument {}: {}", instrument_id.0, e))?;
+                Some(rebuilt)
+            }
+            None => None,
+        };
+        let tick_size = rebuilt.as_ref().unwrap_or(book).tick_size();
+        let symbol = update.symbol.unwrap_or_else(|| meta.symbol.clone());
+        let lot_size = update.lot_size.unwrap_or(meta.lot_size);
+        let price_band = update.price_band.unwrap_or(meta.price_band);
+        self.write_ahead(|| Command::UpdateInstrument {
+            instrument_id,
+            symbol: symbol.clone(),
+            tick_size,
+            lot_size,
+            price_band,
+        })?;
+        if let Some(book) = rebuilt {
+            self.books.insert(instrument_id, book);
         }
+        let meta = self.registry.get_mut(&instrument_id).expect("checked above");
+        meta.symbol = symbol.clone();
+        meta.lot_size = lot_size;
+        meta.price_band = price_band;
         info!(
-            "order submitted order_id={} instrument_id={} side={:?} quantity={} price={:?}",
-            order.order_id.0,
-            order.instrument_id.0,
-            order.side,
-            order.quantity,
-            order.price
+            "instrument updated instrument_id={} symbol={:?} tick_size={} lot_size={:?} price_band={:?}",
+            instrument_id.0, symbol, tick_size, lot_size, price_band
         );
-        let (trades, reports) = match_order(
-            book,
-            &order,
-            self.next_trade_id,
-            self.next_exec_id,
-        );
-        self.next_trade_id += trades.len() as u64;
-        self.next_exec_id += reports.len() as u64;
-        self.update_order_to_instrument_after_submit(&order, &reports);
-        for report in &reports {
-            info!(
-                "execution_report order_id={} exec_type={:?} order_status={:?} filled={} remaining={}",
-                report.order_id.0,
-                report.exec_type,
-                report.order_status,
-                report.filled_quantity,
-                report.remaining_quantity
-            );
+        self.record_input(|| Command::UpdateInstrument {
+            instrument_id,
+            symbol,
+            tick_size,
+            lot_size,
+            price_band,
+        });
+        self.publish_state_change(Some(instrument_id), "updated");
+        if let Some(state) = update.state {
+            self.set_instrument_state(instrument_id, state)?;
         }
-        for trade in &trades {
-            info!(
-                "trade trade_id={} buy_order={} sell_order={} price={} quantity={}",
-                trade.trade_id.0,
-                trade.buy_order_id.0,
-                trade.sell_order_id.0,
-                trade.price,
-                trade.quantity
-            );
+        if let Some(market_state) = update.market_state {
+            self.set_instrument_market_state(instrument_id, market_state)?;
         }
-        Ok((trades, reports))
+        Ok(())
     }
 
-    fn cancel_order(&mut self, order_id: OrderId) -> Option<InstrumentId> {
-        let instrument_id = self.order_to_instrument.remove(&order_id)?;
-        let book = self.books.get_mut(&instrument_id)?;
-        let removed = book.cancel_order(order_id);
-        if removed {
-            info!("order canceled order_id={} instrument_id={}", order_id.0, instrument_id.0);
-            Some(instrument_id)
-        } else {
-            self.order_to_instrument.insert(order_id, instrument_id);
-            None
+    /// Trading state of an instrument. `None` if the instrument is unknown.
+    pub fn instrument_state(&self, instrument_id: InstrumentId) -> Option<InstrumentState> {
+        self.registry.get(&instrument_id).map(|meta| meta.state)
+    }
+
+    /// Suspend, reactivate, or delist one instrument while others keep trading. Resting orders stay on the book
+    /// and can still be canceled. Returns `Err` if the instrument is unknown or already delisted.
+    pub fn set_instrument_state(&mut self, instrument_id: InstrumentId, state: InstrumentState) -> Result<(), String> {
+        let meta = self
+            .registry
+            .get(&instrument_id)
+            .ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        if meta.state == InstrumentState::Delisted && state != InstrumentState::Delisted {
+            return Err(format!("Instrument {} is delisted", instrument_id.0));
         }
+        self.write_ahead(|| Command::SetInstrumentState { instrument_id, state })?;
+        self.registry.get_mut(&instrument_id).expect("checked above").state = state;
+        info!("instrument state instrument_id={} state={}", instrument_id.0, state.as_str());
+        self.record_input(|| Command::SetInstrumentState { instrument_id, state });
+        self.publish_state_change(Some(instrument_id), state.as_str());
+        Ok(())
     }
 
-    fn modify_order(
+    /// Market state of one instrument (separate from the venue-wide state). `None` if the instrument is unknown.
+    pub fn instrument_market_state(&self, instrument_id: InstrumentId) -> Option<MarketState> {
+        self.registry.get(&instrument_id).map(|meta| meta.market_state)
+    }
+
+    /// Open, halt, or close one instrument while others keep trading. Returns `Err` if the instrument is unknown.
+    pub fn set_instrument_market_state(
         &mut self,
-        order_id: OrderId,
-        replacement: &Order,
-    ) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
-        let instrument_id = self.order_to_instrument.remove(&order_id).ok_or_else(|| format!("Order {} not found", order_id.0))?;
-        if replacement.instrument_id != instrument_id {
-            self.order_to_instrument.insert(order_id, instrument_id);
-            return Err("Replacement order must be for the same instrument".into());
+        instrument_id: InstrumentId,
+        market_state: MarketState,
+    ) -> Result<(), String> {
+        if !self.registry.contains_key(&instrument_id) {
+            return Err(format!("Instrument {} not found", instrument_id.0));
         }
-        let book = self.books.get_mut(&instrument_id).ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
-        if !book.cancel_order(order_id) {
-            self.order_to_instrument.insert(order_id, instrument_id);
-            return Err(format!("Order {} not found", order_id.0));
+        self.write_ahead(|| Command::SetInstrumentMarketState {
+            instrument_id,
+            market_state,
+        })?;
+        self.registry.get_mut(&instrument_id).expect("checked above").market_state = market_state;
+        info!("instrument market state instrument_id={} state={}", instrument_id.0, market_state.as_str());
+        self.record_input(|| Command::SetInstrumentMarketState {
+            instrument_id,
+            market_state,
+        });
+        self.publish_state_change(Some(instrument_id), market_state.as_str());
+        Ok(())
+    }
+
+    /// `Err` with the reason if `instrument_id` is suspended, delisted, or not open.
+    fn check_instrument_active(&self, instrument_id: InstrumentId) -> Result<(), String> {
+        let Some(meta) = self.registry.get(&instrument_id) else { return Ok(()) };
+        match meta.state {
+            InstrumentState::Suspended => return Err(format!("Instrument {} is suspended", instrument_id.0)),
+            InstrumentState::Delisted => return Err(format!("Instrument {} is delisted", instrument_id.0)),
+            InstrumentState::Active => {}
         }
-        info!(
-            "order modified old_order_id={} replacement order_id={} instrument_id={} side={:?} quantity={} price={:?}",
-            order_id.0,
-            replacement.order_id.0,
-            instrument_id.0,
-            replacement.side,
-            replacement.quantity,
-            replacement.price
-        );
-        let (trades, reports) = match_order(
-            book,
-            replacement,
-            self.next_trade_id,
-            self.next_exec_id,
-        );
-        self.next_trade_id += trades.len() as u64;
-        self.next_exec_id += reports.len() as u64;
-        self.update_order_to_instrument_after_modify(replacement, &reports);
-        for report in &reports {
-            info!(
-                "execution_report order_id={} exec_type={:?} order_status={:?} filled={} remaining={}",
-                report.order_id.0,
-                report.exec_type,
-                report.order_status,
-                report.filled_quantity,
-                report.remaining_quantity
-            );
+        if meta.market_state != MarketState::Open {
+            return Err(format!("market not open for instrument {}", instrument_id.0));
         }
-        for trade in &trades {
-            info!(
-                "trade trade_id={} buy_order={} sell_order={} price={} quantity={}",
-                trade.trade_id.0,
-                trade.buy_order_id.0,
-                trade.sell_order_id.0,
-                trade.price,
-                trade.quantity
-            );
+        self.session.check_open()
+    }
+
+    /// `Err` with the reason if `order` breaks its instrument's lot size or price band, or is outside the
+    /// dynamic band around the last trade.
+    fn check_instrument_rules(&self, order: &Order) -> Result<(), String> {
+        if let Some(meta) = self.registry.get(&order.instrument_id) {
+            meta.check_order(order)?;
         }
-        Ok((trades, reports))
+        let (Some(price), true, Some(_)) = (order.price, order.is_limit(), self.band.max_deviation_pct) else {
+            return Ok(());
+        };
+        let Some((low, high)) = self.stats.get(order.instrument_id).last.and_then(|last| self.band.range(last)) else {
+            return Ok(());
+        };
+        if price < low || price > high {
+            return Err(format!("Price {} is out of range {}..{} for instrument {}", price, low, high, order.instrument_id.0));
+        }
+        Ok(())
     }
 
-    fn instruments(&self) -> Vec<InstrumentId> {
-        self.registry.keys().copied().collect()
+    /// Atomically replace `quote.trader_id`'s two-sided quote on `quote.instrument_id`: the previous quote's
+    /// resting sides are canceled and the new sides submitted as GTC limit orders (bid first), with no other
+    /// command in between. Quote both sides with zero quantity to pull the quote.
+    ///
+    /// Rejected without touching the book if the instrument is not open, the bid is not below the ask (the quote
+    /// would cross itself), an order id is in use, or a side fails tick, book-limit, or risk checks. A side
+    /// rejected only once the other is on the book (e.g. a per-trader order cap) returns `Err` after the old
+    /// quote was pulled and the sides before it were submitted.
+    pub fn submit_quote(&mut self, quote: &Quote) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        self.check_instrument_active(quote.instrument_id)?;
+        let book = self
+            .books
+            .get(&quote.instrument_id)
+            .ok_or_else(|| format!("Unknown instrument {}", quote.instrument_id.0))?;
+        if quote.bid_quantity.is_sign_negative() || quote.ask_quantity.is_sign_negative() {
+            return Err("Quote quantities must not be negative".into());
+        }
+        let sides = quote.orders();
+        if sides.iter().any(|order| order.price.is_some_and(|p| p <= Decimal::ZERO)) {
+            return Err("Quote prices must be positive".into());
+        }
+        if sides.len() == 2 {
+            if quote.bid_price >= quote.ask_price {
+                return Err(format!("Quote bid {} must be below ask {}", quote.bid_price, quote.ask_price));
+            }
+            if quote.bid_order_id == quote.ask_order_id {
+                return Err(duplicate_order_id(quote.ask_order_id));
+            }
+        }
+        let key = (quote.trader_id, quote.instrument_id);
+        let previous = self.quotes.get(&key).cloned();
+        for order in &sides {
+            if self.is_duplicate_order_id(order.order_id) {
+                return Err(duplicate_order_id(order.order_id));
+            }
+            let replacing = previous.as_ref().and_then(|q| match order.side {
+                Side::Buy => q.bid_order_id,
+                Side::Sell => q.ask_order_id,
+            });
+            self.check_instrument_rules(order)?;
+            book.validate_order(order, replacing)?;
+            check_risk(&self.risk_limits, &self.positions, book, order, replacing)?;
+        }
+        self.write_ahead(|| Command::Quote { quote: quote.clone() })?;
+
+        // The cancels and submits below are journaled and written ahead as this one quote.
+        let journal = self.input_journal.take();
+        let input_sinks = std::mem::take(&mut self.input_sinks);
+        let command_log = self.command_log.take();
+        for order_id in previous.iter().flat_map(|q| [q.bid_order_id, q.ask_order_id]).flatten() {
+            self.cancel_order(order_id);
+        }
+        let mut state = QuoteState {
+            trader_id: quote.trader_id,
+            instrument_id: quote.instrument_id,
+            bid_order_id: None,
+            ask_order_id: None,
+        };
+        let (mut trades, mut reports) = (Vec::new(), Vec::new());
+        let mut result = Ok(());
+        for order in sides {
+            let (side, order_id) = (order.side, order.order_id);
+            match self.submit_order(order) {
+                Ok((t, r)) => {
+                    trades.extend(t);
+                    reports.extend(r);
+                    match side {
+                        Side::Buy => state.bid_order_id = Some(order_id),
+                        Side::Sell => state.ask_order_id = Some(order_id),
+                    }
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.input_journal = journal;
+        self.input_sinks = input_sinks;
+        self.command_log = command_log;
+        self.record_input(|| Command::Quote { quote: quote.clone() });
+        if state.bid_order_id.is_some() || state.ask_order_id.is_some() {
+            self.quotes.insert(key, state);
+        } else {
+            self.quotes.remove(&key);
+        }
+        info!(
+            "quote quote_id={} trader_id={} instrument_id={} bid={}@{} ask={}@{}",
+            quote.quote_id,
+            quote.trader_id.0,
+            quote.instrument_id.0,
+            quote.bid_quantity,
+            quote.bid_price,
+            quote.ask_quantity,
+            quote.ask_price
+        );
+        result.map(|()| (trades, reports))
+    }
+
+    /// Order ids of `trader_id`'s last quote on `instrument_id`, if any side of it was quoted.
+    pub fn live_quote(&self, trader_id: TraderId, instrument_id: InstrumentId) -> Option<QuoteState> {
+        self.quotes.get(&(trader_id, instrument_id)).cloned()
+    }
+
+    /// Remove an instrument. Returns error if the book has resting orders.
+    pub fn remove_instrument(&mut self, instrument_id: InstrumentId) -> Result<(), String> {
+        let book = self.books.get(&instrument_id).ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        if book.has_resting_orders() {
+            return Err("Instrument has resting orders; cancel them first".to_string());
+        }
+        self.write_ahead(|| Command::RemoveInstrument { instrument_id })?;
+        self.books.remove(&instrument_id);
+        self.registry.remove(&instrument_id);
+        self.order_to_instrument.retain(|_, id| *id != instrument_id);
+        self.quotes.retain(|(_, id), _| *id != instrument_id);
+        self.stats.remove(instrument_id);
+        self.record_input(|| Command::RemoveInstrument { instrument_id });
+        self.publish_state_change(Some(instrument_id), "removed");
+        Ok(())
+    }
+
+    /// Snapshot of engine state for persistence. Serialize to JSON and restore with [`load_from_snapshot`].
+    pub fn snapshot(&self) -> EngineSnapshot {
+        let instruments: Vec<(InstrumentId, Option<String>)> = self
+            .registry
+            .iter()
+            .map(|(&id, meta)| (id, meta.symbol.clone()))
+            .collect();
+        let books: Vec<(InstrumentId, Vec<RestingOrder>)> = self
+            .books
+            .iter()
+            .map(|(&id, book)| (id, book.resting_orders_snapshot()))
+            .collect();
+        let order_to_instrument: Vec<(OrderId, InstrumentId)> = self
+            .order_to_instrument
+            .iter()
+            .map(|(&oid, &iid)| (oid, iid))
+            .collect();
+        let tick_sizes: Vec<(InstrumentId, Decimal)> = self
+            .books
+            .iter()
+            .map(|(&id, book)| (id, book.tick_size()))
+            .collect();
+        let order_fills: Vec<OrderFillState> = self
+            .order_to_instrument
+            .keys()
+            .filter_map(|order_id| self.orders.fill_state(*order_id))
+            .collect();
+        let instrument_states: Vec<(InstrumentId, InstrumentState)> = self
+            .registry
+            .iter()
+            .filter(|(_, meta)| meta.state != InstrumentState::Active)
+            .map(|(&id, meta)| (id, meta.state))
+            .collect();
+        let instrument_market_states: Vec<(InstrumentId, MarketState)> = self
+            .registry
+            .iter()
+            .filter(|(_, meta)| meta.market_state != MarketState::Open)
+            .map(|(&id, meta)| (id, meta.market_state))
+            .collect();
+        let lot_sizes: Vec<(InstrumentId, Decimal)> = self
+            .registry
+            .iter()
+            .filter_map(|(&id, meta)| meta.lot_size.map(|lot| (id, lot)))
+            .collect();
+        let price_bands: Vec<(InstrumentId, PriceBand)> = self
+            .registry
+            .iter()
+            .filter_map(|(&id, meta)| meta.price_band.map(|band| (id, band)))
+            .collect();
+        EngineSnapshot {
+            version: ENGINE_SNAPSHOT_VERSION,
+            instruments,
+            books,
+            order_to_instrument,
+            next_trade_id: self.ids.next_trade_id(),
+            next_exec_id: self.ids.next_exec_id(),
+            next_seq: self.seq.next,
+            tick_sizes,
+            scheduler: self.scheduler.snapshot(),
+            order_fills,
+            instrument_states,
+            instrument_market_states,
+            quotes: self.quotes.values().cloned().collect(),
+            session_stats: self.stats.all(),
+            lot_sizes,
+            price_bands,
+            command_seq: self.command_seq,
+            next_order_id: self.ids.watermark().next_order_id,
+        }
+    }
+
+    /// Restore engine from a snapshot (e.g. after loading from persistence). Replaces current state.
+    /// Returns `Err` for a snapshot written by a newer version, or one whose books do not match its instruments.
+    pub fn load_from_snapshot(&mut self, snap: EngineSnapshot) -> Result<(), String> {
+        if snap.version > ENGINE_SNAPSHOT_VERSION {
+            return Err(format!(
+                "Unsupported snapshot version {} (this engine reads up to {})",
+                snap.version, ENGINE_SNAPSHOT_VERSION
+            ));
+        }
+        self.books.clear();
+        self.registry.clear();
+        self.order_to_instrument.clear();
+        self.orders = OrderTracker::new(RECENT_ORDER_IDS_CAPACITY);
+        self.trades = TradeStore::new(TRADE_HISTORY_CAPACITY);
+        self.executions = ExecutionStore::new(EXECUTION_HISTORY_CAPACITY);
+        if let Some(log) = &self.history_log {
+            match log.records() {
+                Ok(records) => self.push_history(records),
+                Err(e) => warn!("History log could not be reloaded: {}; history restarts empty", e),
+            }
+        }
+        self.positions = PositionBook::new();
+        let tick_sizes: HashMap<InstrumentId, Decimal> = snap.tick_sizes.iter().copied().collect();
+        for (id, symbol) in &snap.instruments {
+            let tick_size = tick_sizes.get(id).copied().unwrap_or(DEFAULT_TICK_SIZE);
+            let mut book = OrderBook::with_tick_size(*id, tick_size)?;
+            book.set_limits(self.book_limits);
+            self.books.insert(*id, book);
+            self.registry.insert(*id, InstrumentMeta::new(symbol.clone()));
+        }
+        for (instrument_id, resting) in &snap.books {
+            let book = self.books.get_mut(instrument_id).ok_or_else(|| format!("Instrument {} not in snapshot instruments", instrument_id.0))?;
+            book.load_resting_orders(resting)?;
+            for r in resting {
+                self.order_to_instrument.insert(r.order_id, *instrument_id);
+                self.orders.restore(r);
+            }
+        }
+        for (id, state) in &snap.instrument_states {
+            if let Some(meta) = self.registry.get_mut(id) {
+                meta.state = *state;
+            }
+        }
+        for (id, market_state) in &snap.instrument_market_states {
+            if let Some(meta) = self.registry.get_mut(id) {
+                meta.market_state = *market_state;
+            }
+        }
+        for (id, lot_size) in &snap.lot_sizes {
+            if let Some(meta) = self.registry.get_mut(id) {
+                meta.lot_size = Some(*lot_size);
+            }
+        }
+        for (id, price_band) in &snap.price_bands {
+            if let Some(meta) = self.registry.get_mut(id) {
+                meta.price_band = Some(*price_band);
+            }
+        }
+        for fills in &snap.order_fills {
+            self.orders.restore_fills(fills);
+        }
+        self.quotes = snap
+            .quotes
+            .iter()
+            .map(|q| ((q.trader_id, q.instrument_id), q.clone()))
+            .collect();
+        self.stats.restore(snap.session_stats);
+        self.ids.restore(IdWatermark {
+            next_trade_id: snap.next_trade_id,
+            next_exec_id: snap.next_exec_id,
+            next_order_id: snap.next_order_id,
+        });
+        self.seq = Sequencer::resume(snap.next_seq);
+        self.scheduler = Scheduler::restore(snap.scheduler);
+        self.journal = EventJournal::new(EVENT_JOURNAL_CAPACITY, self.seq.next);
+        if self.input_journal.is_some() {
+            self.start_input_journal();
+        }
+        for sink in &self.input_sinks {
+            sink.on_reset();
+        }
+        self.command_seq = snap.command_seq;
+        if let Some(log) = &self.command_log {
+            // Commands logged after the snapshot no longer apply to the restored state.
+            if let Err(e) = log.clear() {
+                warn!("command log not cleared after snapshot load: {}", e);
+            }
+        }
+        self.publish_state_change(None, "restored");
+        for instrument_id in self.books.keys() {
+            self.event_sinks.book_changed(self, *instrument_id);
+        }
+        Ok(())
+    }
+
+    /// Uncross one instrument's book if its best bid reaches its best ask (see [`uncross_book`]). Returns the
+    /// resulting trades and reports (empty if it was not crossed), or `Err` if the instrument is unknown.
+    pub fn repair_crossed(&mut self, instrument_id: InstrumentId) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        let book = self.books.get(&instrument_id).ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        if !book.is_crossed() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        self.write_ahead(|| Command::Uncross { instrument_id })?;
+        let out = self.uncross(instrument_id)?;
+        self.record_input(|| Command::Uncross { instrument_id });
+        Ok(out)
+    }
+
+    fn uncross(&mut self, instrument_id: InstrumentId) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        let book = self
+            .books
+            .get_mut(&instrument_id)
+            .ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        if !book.is_crossed() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        let (mut trades, mut reports) = uncross_book(book, self.ids.next_trade_id(), self.ids.next_exec_id());
+        self.seq.stamp(&mut trades, &mut reports);
+        self.seq.book_changed(instrument_id);
+        self.orders.apply_trades(&trades);
+        self.record_history(&trades, &reports);
+        self.stats.record(&trades);
+        apply_positions(&mut self.positions, &self.orders, &trades);
+        self.ids.advance(trades.len(), reports.len());
+        self.publish_fills(&trades, &reports);
+        self.publish_book_changed(instrument_id);
+        self.publish_state_change(Some(instrument_id), "uncrossed");
+        info!("book uncrossed instrument_id={} trades={}", instrument_id.0, trades.len());
+        Ok((trades, reports))
+    }
+
+    /// Current engine time: the latest time passed to [`MultiEngine::advance_time`] (0 until then).
+    pub fn now(&self) -> u64 {
+        self.scheduler.now()
+    }
+
+    /// Run `action` once engine time reaches `due` (on the next [`MultiEngine::advance_time`] if `due` has
+    /// already passed). Timers due at the same time run in the order they were set. Returns `Err`, setting
+    /// nothing, if the command log cannot be written.
+    pub fn schedule(&mut self, due: u64, action: TimedAction) -> Result<TimerId, String> {
+        self.write_ahead(|| Command::Schedule {
+            due,
+            action: action.clone(),
+        })?;
+        self.record_input(|| Command::Schedule {
+            due,
+            action: action.clone(),
+        });
+        Ok(self.scheduler.schedule(due, action))
+    }
+
+    /// Expire a resting order at `due` (good-till-date). Returns `Err` if the order is not resting.
+    pub fn expire_order_at(&mut self, order_id: OrderId, due: u64) -> Result<TimerId, String> {
+        if !self.order_to_instrument.contains_key(&order_id) {
+            return Err(format!("Order {} not found", order_id.0));
+        }
+        self.schedule(due, TimedAction::ExpireOrder { order_id })
+    }
+
+    /// Cancel a pending timer. Returns `false` if it already ran, does not exist, or the command log cannot be
+    /// written.
+    pub fn cancel_timer(&mut self, timer_id: TimerId) -> bool {
+        if !self.scheduler.is_pending(timer_id) {
+            return false;
+        }
+        if let Err(e) = self.write_ahead(|| Command::CancelTimer { timer_id }) {
+            warn!("timer {} not canceled: {}", timer_id.0, e);
+            return false;
+        }
+        let canceled = self.scheduler.cancel(timer_id);
+        if canceled {
+            self.record_input(|| Command::CancelTimer { timer_id });
+        }
+        canceled
+    }
+
+    /// Pending timers in the order they will run.
+    pub fn pending_timers(&self) -> Vec<Timer> {
+        self.scheduler.pending()
+    }
+
+    /// Move engine time forward to `now` (earlier times are ignored) and run every timer now due, in order.
+    /// Expired orders are removed like cancels and published as [`EngineEvent::Expired`]; uncrosses return
+    /// their trades and reports in the [`FiredTimer`]. If the command log cannot be written, time stays where it
+    /// was and the due timers wait for the next call.
+    pub fn advance_time(&mut self, now: u64) -> Vec<FiredTimer> {
+        if self.scheduler.has_due(now) {
+            if let Err(e) = self.write_ahead(|| Command::AdvanceTime { now }) {
+                warn!("engine time not advanced to {}: {}", now, e);
+                return Vec::new();
+            }
+        }
+        let due = self.scheduler.advance(now);
+        if !due.is_empty() {
+            self.record_input(|| Command::AdvanceTime { now });
+        }
+        due.into_iter()
+            .map(|timer| {
+                let result = match &timer.action {
+                    TimedAction::ExpireOrder { order_id } => self.expire_order(*order_id).map(|()| (Vec::new(), Vec::new())),
+                    TimedAction::Uncross { instrument_id } => self.uncross(*instrument_id),
+                };
+                FiredTimer { timer, result }
+            })
+            .collect()
+    }
+
+    fn expire_order(&mut self, order_id: OrderId) -> Result<(), String> {
+        let (instrument_id, quantity) = self
+            .remove_resting(order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id.0))?;
+        info!("order expired order_id={} instrument_id={}", order_id.0, instrument_id.0);
+        if !self.event_sinks.is_empty() {
+            self.publish(EngineEvent::Expired {
+                order_id,
+                instrument_id,
+                quantity,
+            });
+        }
+        self.publish_book_changed(instrument_id);
+        Ok(())
+    }
+
+    /// Take a resting order off its book and mark it canceled. Returns its instrument and unfilled quantity.
+    fn remove_resting(&mut self, order_id: OrderId) -> Option<(InstrumentId, Decimal)> {
+        let instrument_id = *self.order_to_instrument.get(&order_id)?;
+        let book = self.books.get_mut(&instrument_id)?;
+        let quantity = book.get_order(order_id)?.remaining_quantity;
+        if !book.cancel_order(order_id) {
+            return None;
+        }
+        self.order_to_instrument.remove(&order_id);
+        self.orders.finish(order_id, OrderStatus::Canceled);
+        self.seq.book_changed(instrument_id);
+        Some((instrument_id, quantity))
+    }
+
+    /// True if the instrument's book is crossed (see [`OrderBook::is_crossed`]); false if unknown.
+    pub fn book_is_crossed(&self, instrument_id: InstrumentId) -> bool {
+        self.books.get(&instrument_id).is_some_and(|book| book.is_crossed())
+    }
+
+    /// Live (resting) orders of `trader_id` across every instrument, by instrument id, then as
+    /// [`OrderBook::resting_orders_for_trader`] orders them. For reconciling working orders after a reconnect.
+    pub fn open_orders(&self, trader_id: TraderId) -> Vec<OrderStatusView> {
+        let mut ids: Vec<InstrumentId> = self.books.keys().copied().collect();
+        ids.sort_by_key(|id| id.0);
+        ids.iter()
+            .flat_map(|id| {
+                let book = &self.books[id];
+                book.resting_orders_for_trader(trader_id)
+                    .filter_map(move |o| self.orders.view(o.order_id, Some(book)))
+            })
+            .collect()
+    }
+
+    /// Cancel every resting order of `trader_id`, only in `instrument_id` and/or on `side` when given. Each is
+    /// canceled (journaled and published) as by [`MatchingEngine::cancel_order`]. Returns the canceled order ids.
+    pub fn mass_cancel(
+        &mut self,
+        trader_id: TraderId,
+        instrument_id: Option<InstrumentId>,
+        side: Option<Side>,
+    ) -> Vec<OrderId> {
+        let order_ids: Vec<OrderId> = self
+            .open_orders(trader_id)
+            .into_iter()
+            .filter(|o| instrument_id.is_none_or(|id| o.instrument_id == id) && side.is_none_or(|s| o.side == s))
+            .map(|o| o.order_id)
+            .collect();
+        order_ids
+            .into_iter()
+            .filter(|&order_id| MatchingEngine::cancel_order(self, order_id).is_some())
+            .collect()
+    }
+
+    /// Positions of `trader_id` in every instrument they have traded or have resting orders in, by instrument id.
+    pub fn positions_for_trader(&self, trader_id: TraderId) -> Vec<Position> {
+        let mut ids = self.positions.instruments_for(trader_id);
+        ids.extend(
+            self.books
+                .iter()
+                .filter(|(_, book)| book.resting_count_for_trader(trader_id) > 0)
+                .map(|(id, _)| *id),
+        );
+        ids.sort_by_key(|id| id.0);
+        ids.dedup();
+        ids.into_iter().map(|id| self.position(trader_id, id)).collect()
+    }
+
+    /// Full order-by-order (L3) view of one instrument's book. `None` if the instrument is unknown.
+    pub fn book_orders(&self, instrument_id: InstrumentId) -> Option<Vec<BookOrder>> {
+        self.books.get(&instrument_id).map(|book| book.orders_iter().collect())
+    }
+
+    /// List instruments with optional symbol (for admin GET).
+    pub fn list_instruments(&self) -> Vec<(InstrumentId, Option<String>)> {
+        self.registry
+            .iter()
+            .map(|(&id, meta)| (id, meta.symbol.clone()))
+            .collect()
+    }
+
+    /// Instrument named by `symbol`: the one registered with that symbol, else the instrument whose id it is
+    /// (so unnamed instruments can still be addressed). `None` if neither exists.
+    pub fn instrument_by_symbol(&self, symbol: &str) -> Option<InstrumentId> {
+        self.registry
+            .iter()
+            .find(|(_, meta)| meta.symbol.as_deref() == Some(symbol))
+            .map(|(&id, _)| id)
+            .or_else(|| {
+                let id = InstrumentId(symbol.parse().ok()?);
+                self.registry.contains_key(&id).then_some(id)
+            })
+    }
+
+    fn is_duplicate_order_id(&self, order_id: OrderId) -> bool {
+        self.order_to_instrument.contains_key(&order_id) || self.recent_order_ids.contains(order_id)
+    }
+
+    fn update_order_to_instrument_after_submit(&mut self, order: &Order, reports: &[ExecutionReport]) {
+        let aggressor_report = reports.iter().find(|r| r.order_id == order.order_id);
+        if let Some(r) = aggressor_report {
+            if r.remaining_quantity > Decimal::ZERO {
+                self.order_to_instrument.insert(order.order_id, order.instrument_id);
+            }
+        }
+    }
+
+    fn update_order_to_instrument_after_modify(&mut self, replacement: &Order, reports: &[ExecutionReport]) {
+        // Last report for the replacement reflects its final state (the leading Replaced report does not).
+        let aggressor_report = reports.iter().rev().find(|r| r.order_id == replacement.order_id);
+        if let Some(r) = aggressor_report {
+            if r.remaining_quantity > Decimal::ZERO {
+                self.order_to_instrument.insert(replacement.order_id, replacement.instrument_id);
+            }
+        }
+    }
+}
+
+impl MatchingEngine for MultiEngine {
+    fn submit_order(&mut self, order: Order) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        if self.is_duplicate_order_id(order.order_id) {
+            return Err(duplicate_order_id(order.order_id));
+        }
+        self.check_instrument_active(order.instrument_id)?;
+        self.check_instrument_rules(&order)?;
+        let book = self.books.get_mut(&order.instrument_id).ok_or_else(|| {
+            format!("Unknown instrument {}", order.instrument_id.0)
+        })?;
+        if order.is_limit() && order.price.is_none() {
+            return Err("Limit order must have price".into());
+        }
+        book.validate_order(&order, None)?;
+        check_risk(&self.risk_limits, &self.positions, book, &order, None)?;
+        self.write_ahead(|| Command::Submit { order: order.clone() })?;
+        let book = self.books.get_mut(&order.instrument_id).expect("checked above");
+        info!(
+            "order submitted order_id={} instrument_id={} side={:?} quantity={} price={:?}",
+            order.order_id.0,
+            order.instrument_id.0,
+            order.side,
+            order.quantity,
+            order.price
+        );
+        let (mut trades, mut reports) = match_order(
+            book,
+            &order,
+            self.ids.next_trade_id(),
+            self.ids.next_exec_id(),
+        );
+        self.recent_order_ids.insert(order.order_id);
+        self.seq.stamp(&mut trades, &mut reports);
+        self.seq.book_changed(order.instrument_id);
+        self.orders.accept(&order, &trades, &reports, book.contains_order(order.order_id));
+        self.record_history(&trades, &reports);
+        self.stats.record(&trades);
+        apply_positions(&mut self.positions, &self.orders, &trades);
+        self.ids.advance(trades.len(), reports.len());
+        self.update_order_to_instrument_after_submit(&order, &reports);
+        self.record_input(|| Command::Submit { order: order.clone() });
+        self.publish_order_events(&order, &trades, &reports);
+        for report in &reports {
+            info!(
+                "execution_report order_id={} exec_type={:?} order_status={:?} filled={} remaining={}",
+                report.order_id.0,
+                report.exec_type,
+                report.order_status,
+                report.filled_quantity,
+                report.remaining_quantity
+            );
+        }
+        for trade in &trades {
+            info!(
+                "trade trade_id={} buy_order={} sell_order={} price={} quantity={}",
+                trade.trade_id.0,
+                trade.buy_order_id.0,
+                trade.sell_order_id.0,
+                trade.price,
+                trade.quantity
+            );
+        }
+        Ok((trades, reports))
+    }
+
+    fn cancel_order(&mut self, order_id: OrderId) -> Option<InstrumentId> {
+        if !self.order_to_instrument.contains_key(&order_id) {
+            return None;
+        }
+        if let Err(e) = self.write_ahead(|| Command::Cancel { order_id }) {
+            warn!("order cancel refused order_id={}: {}", order_id.0, e);
+            return None;
+        }
+        let (instrument_id, _) = self.remove_resting(order_id)?;
+        self.record_input(|| Command::Cancel { order_id });
+        info!("order canceled order_id={} instrument_id={}", order_id.0, instrument_id.0);
+        if !self.event_sinks.is_empty() {
+            self.publish(EngineEvent::Canceled { order_id, instrument_id });
+        }
+        self.publish_book_changed(instrument_id);
+        Some(instrument_id)
+    }
+
+    fn modify_order(
+        &mut self,
+        order_id: OrderId,
+        replacement: &Order,
+    ) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+        let instrument_id = self.order_to_instrument.remove(&order_id).ok_or_else(|| format!("Order {} not found", order_id.0))?;
+        if replacement.instrument_id != instrument_id {
+            self.order_to_instrument.insert(order_id, instrument_id);
+            return Err("Replacement order must be for the same instrument".into());
+        }
+        if let Err(e) = self
+            .check_instrument_active(instrument_id)
+            .and_then(|()| self.check_instrument_rules(replacement))
+        {
+            self.order_to_instrument.insert(order_id, instrument_id);
+            return Err(e);
+        }
+        if replacement.order_id != order_id && self.is_duplicate_order_id(replacement.order_id) {
+            self.order_to_instrument.insert(order_id, instrument_id);
+            return Err(duplicate_order_id(replacement.order_id));
+        }
+        let book = self.books.get_mut(&instrument_id).ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+        if let Err(e) = check_risk(&self.risk_limits, &self.positions, book, replacement, Some(order_id)) {
+            self.order_to_instrument.insert(order_id, instrument_id);
+            return Err(e);
+        }
+        if let Err(e) = self.write_ahead(|| Command::Modify {
+            order_id,
+            replacement: replacement.clone(),
+        }) {
+            self.order_to_instrument.insert(order_id, instrument_id);
+            return Err(e);
+        }
+        let book = self.books.get_mut(&instrument_id).expect("checked above");
+        let (mut trades, mut reports) = match replace_order(
+            book,
+            order_id,
+            replacement,
+            self.ids.next_trade_id(),
+            self.ids.next_exec_id(),
+        ) {
+            Ok(out) => out,
+            Err(e) => {
+                self.order_to_instrument.insert(order_id, instrument_id);
+                return Err(e);
+            }
+        };
+        let rests = book.contains_order(replacement.order_id);
+        self.recent_order_ids.insert(replacement.order_id);
+        self.seq.stamp(&mut trades, &mut reports);
+        self.seq.book_changed(instrument_id);
+        self.orders.replace(order_id, replacement, &trades, &reports, rests);
+        self.record_history(&trades, &reports);
+        self.stats.record(&trades);
+        apply_positions(&mut self.positions, &self.orders, &trades);
+        info!(
+            "order modified old_order_id={} replacement order_id={} instrument_id={} side={:?} quantity={} price={:?}",
+            order_id.0,
+            replacement.order_id.0,
+            instrument_id.0,
+            replacement.side,
+            replacement.quantity,
+            replacement.price
+        );
+        self.ids.advance(trades.len(), reports.len());
+        self.update_order_to_instrument_after_modify(replacement, &reports);
+        self.record_input(|| Command::Modify {
+            order_id,
+            replacement: replacement.clone(),
+        });
+        self.publish_order_events(replacement, &trades, &reports);
+        for report in &reports {
+            info!(
+                "execution_report order_id={} exec_type={:?} order_status={:?} filled={} remaining={}",
+                report.order_id.0,
+                report.exec_type,
+                report.order_status,
+                report.filled_quantity,
+                report.remaining_quantity
+            );
+        }
+        for trade in &trades {
+            info!(
+                "trade trade_id={} buy_order={} sell_order={} price={} quantity={}",
+                trade.trade_id.0,
+                trade.buy_order_id.0,
+                trade.sell_order_id.0,
+                trade.price,
+                trade.quantity
+            );
+        }
+        Ok((trades, reports))
+    }
+
+    fn instruments(&self) -> Vec<InstrumentId> {
+        self.registry.keys().copied().collect()
+    }
+
+    fn book_snapshot_for(&self, id: InstrumentId) -> Option<BookSnapshot> {
+        self.books.get(&id).map(|book| book_snapshot(book, self.snapshot_levels, self.seq.book_seq(id)))
+    }
+
+    fn book_stats_for(&self, id: InstrumentId, levels: usize) -> Option<BookStats> {
+        self.books.get(&id).map(|book| book.stats(levels))
+    }
+
+    fn book_levels_for(&self, id: InstrumentId) -> Option<BookLevels> {
+        self.books.get(&id).map(|book| book.levels())
+    }
+
+    fn get_order(&self, order_id: OrderId) -> Option<RestingOrderView> {
+        let instrument_id = self.order_to_instrument.get(&order_id)?;
+        self.books.get(instrument_id)?.get_order(order_id)
+    }
+
+    fn order_status(&self, order_id: OrderId) -> Option<OrderStatusView> {
+        let instrument_id = self.orders.records.get(&order_id)?.instrument_id;
+        self.orders.view(order_id, self.books.get(&instrument_id))
+    }
+
+    fn trades_since(&self, trade_id: TradeId) -> Vec<Trade> {
+        self.trades.since(trade_id)
+    }
+
+    fn trades_for_instrument(&self, id: InstrumentId, limit: usize) -> Vec<Trade> {
+        self.trades.for_instrument(id, limit)
+    }
+
+    fn trades_before(&self, trade_id: TradeId, instrument_id: Option<InstrumentId>, limit: usize) -> Vec<Trade> {
+        self.trades.before(trade_id, instrument_id, limit)
+    }
+
+    fn trade_history(&self, query: &HistoryQuery) -> HistoryPage<Trade> {
+        self.trades.page(query)
+    }
+
+    fn execution_history(&self, query: &HistoryQuery) -> HistoryPage<ExecutionReport> {
+        self.executions.page(query)
+    }
+
+    fn position(&self, trader_id: TraderId, instrument_id: InstrumentId) -> Position {
+        position_with_exposure(&self.positions, self.books.get(&instrument_id), trader_id, instrument_id)
+    }
+
+    fn stats_for(&self, id: InstrumentId) -> Option<InstrumentStats> {
+        self.books.contains_key(&id).then(|| self.stats.get(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryCursor;
+    use crate::types::{Order, OrderId, OrderType, Quote, Side, TimeInForce, TraderId};
+    use rust_decimal::Decimal;
+
+    fn init_log() {
+        let _ = env_logger::try_init();
+    }
+
+    #[test]
+    fn engine_submit_order_matches_and_returns_trades() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let sell = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        engine.submit_order(sell).unwrap();
+        let buy = Order {
+            order_id: OrderId(2),
+            client_order_id: "c2".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 2,
+            trader_id: TraderId(2),
+        };
+        let (trades, reports) = engine.submit_order(buy).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].quantity, Decimal::from(10));
+        assert!(!reports.is_empty());
+    }
+
+    #[test]
+    fn engine_submit_order_wrong_instrument_returns_err() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let order = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(2),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        assert!(engine.submit_order(order).is_err());
+    }
+
+    #[test]
+    fn engine_submit_order_limit_without_price_rejected() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let order = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: None,
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        let err = engine.submit_order(order).unwrap_err();
+        assert!(err.to_lowercase().contains("price"));
+    }
+
+    #[test]
+    fn engine_order_flow_submit_then_cancel() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let sell = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(5),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        engine.submit_order(sell).unwrap();
+        let canceled = engine.cancel_order(OrderId(1));
+        assert!(canceled);
+        assert!(engine.best_ask().is_none(), "cancel resting: book no longer has that order");
+    }
+
+    #[test]
+    fn engine_modify_then_incoming_matches() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let sell = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        engine.submit_order(sell).unwrap();
+        let replacement = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(5),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 2,
+            trader_id: TraderId(1),
+        };
+        engine.modify_order(OrderId(1), &replacement).unwrap();
+        let buy = Order {
+            order_id: OrderId(2),
+            client_order_id: "c2".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(5),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 3,
+            trader_id: TraderId(2),
+        };
+        let (trades, _) = engine.submit_order(buy).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].quantity, Decimal::from(5));
+        assert!(engine.best_ask().is_none());
+        assert!(engine.best_bid().is_none());
+    }
+
+    #[test]
+    fn engine_modify_order_replacement_rests_and_returns_reports() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let sell = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        engine.submit_order(sell).unwrap();
+        let replacement = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(5),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 2,
+            trader_id: TraderId(1),
+        };
+        let (trades, reports) = engine.modify_order(OrderId(1), &replacement).unwrap();
+        assert_eq!(trades.len(), 0);
+        assert!(!reports.is_empty());
+        assert_eq!(engine.best_ask(), Some(Decimal::from(100)));
+    }
+
+    #[test]
+    fn engine_modify_quantity_down_keeps_queue_position() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let sell = |id: u64, qty: i64, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        engine.submit_order(sell(1, 10, 1)).unwrap();
+        engine.submit_order(sell(2, 10, 2)).unwrap();
+        let (trades, reports) = engine.modify_order(OrderId(1), &sell(1, 3, 1)).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].exec_type, crate::types::ExecType::Replaced);
+        assert_eq!(reports[0].remaining_quantity, Decimal::from(3));
+        let buy = Order {
+            order_id: OrderId(3),
+            client_order_id: "c3".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(3),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 4,
+            trader_id: TraderId(3),
+        };
+        let (trades, _) = engine.submit_order(buy).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].sell_order_id, OrderId(1), "amended order kept time priority");
+    }
+
+    #[test]
+    fn engine_rejects_duplicate_live_and_recent_order_ids() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let order = |id: u64, side: Side, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        engine.submit_order(order(1, Side::Sell, 1)).unwrap();
+        let err = engine.submit_order(order(1, Side::Sell, 1)).unwrap_err();
+        assert!(err.contains("Duplicate order id 1"), "live id rejected: {}", err);
+        engine.submit_order(order(2, Side::Buy, 2)).unwrap();
+        let err = engine.submit_order(order(2, Side::Buy, 2)).unwrap_err();
+        assert!(err.contains("Duplicate"), "filled id rejected: {}", err);
+        engine.submit_order(order(3, Side::Sell, 1)).unwrap();
+        let err = engine.modify_order(OrderId(3), &order(2, Side::Sell, 1)).unwrap_err();
+        assert!(err.contains("Duplicate"), "replacement reusing an old id rejected: {}", err);
+        assert_eq!(engine.best_ask(), Some(Decimal::from(100)), "original order untouched");
+    }
+
+    #[test]
+    fn engine_rejects_orders_past_book_limits_and_keeps_original_on_replace() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        engine.set_book_limits(BookLimits {
+            max_orders_per_level: Some(1),
+            ..Default::default()
+        });
+        let order = |id: u64, side: Side, price: i64, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        engine.submit_order(order(1, Side::Sell, 100, 1)).unwrap();
+        engine.submit_order(order(2, Side::Sell, 101, 2)).unwrap();
+        let err = engine.submit_order(order(3, Side::Sell, 100, 3)).unwrap_err();
+        assert!(err.contains("Price level 100 order limit"), "{}", err);
+        let err = engine.modify_order(OrderId(2), &order(4, Side::Sell, 100, 2)).unwrap_err();
+        assert!(err.contains("Price level 100 order limit"), "{}", err);
+        assert_eq!(engine.book.get_order(OrderId(2)).map(|o| o.price), Some(Decimal::from(101)));
+        // Fully crossing orders never rest, so the cap does not apply.
+        engine.submit_order(order(5, Side::Buy, 100, 4)).unwrap();
+    }
+
+    #[test]
+    fn multi_engine_rejects_duplicate_order_id_across_instruments() {
+        init_log();
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        let order = |instrument: u64| Order {
+            order_id: OrderId(7),
+            client_order_id: "c7".into(),
+            instrument_id: InstrumentId(instrument),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        MatchingEngine::submit_order(&mut engine, order(1)).unwrap();
+        let err = MatchingEngine::submit_order(&mut engine, order(2)).unwrap_err();
+        assert!(err.contains("Duplicate order id 7"));
+    }
+
+    #[test]
+    fn instrument_by_symbol_prefers_registered_symbol_then_id() {
+        let engine = MultiEngine::new_with_instruments(vec![
+            (InstrumentId(1), Some("BTC-USD".into())),
+            (InstrumentId(2), Some("1".into())),
+            (InstrumentId(3), None),
+        ]);
+        assert_eq!(engine.instrument_by_symbol("BTC-USD"), Some(InstrumentId(1)));
+        assert_eq!(engine.instrument_by_symbol("1"), Some(InstrumentId(2)));
+        assert_eq!(engine.instrument_by_symbol("3"), Some(InstrumentId(3)));
+        assert_eq!(engine.instrument_by_symbol("4"), None);
+        assert_eq!(engine.instrument_by_symbol("ETH-USD"), None);
+    }
+
+    #[test]
+    fn mass_cancel_filters_by_trader_instrument_and_side() {
+        init_log();
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        let order = |id: u64, instrument: u64, side: Side, price: i64, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(trader),
+        };
+        for o in [
+            order(1, 1, Side::Buy, 99, 7),
+            order(2, 1, Side::Sell, 101, 7),
+            order(3, 2, Side::Buy, 99, 7),
+            order(4, 1, Side::Buy, 98, 8),
+        ] {
+            MatchingEngine::submit_order(&mut engine, o).unwrap();
+        }
+
+        assert_eq!(engine.mass_cancel(TraderId(7), Some(InstrumentId(1)), Some(Side::Buy)), vec![OrderId(1)]);
+        assert_eq!(engine.mass_cancel(TraderId(7), None, None), vec![OrderId(2), OrderId(3)]);
+        assert!(engine.mass_cancel(TraderId(7), None, None).is_empty());
+        assert_eq!(engine.open_orders(TraderId(8)).len(), 1);
+    }
+
+    #[test]
+    fn engine_modify_order_not_found_returns_err() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let replacement = Order {
+            order_id: OrderId(2),
+            client_order_id: "c2".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(5),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        let err = engine.modify_order(OrderId(999), &replacement).unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[test]
+    fn engine_modify_order_wrong_instrument_returns_err() {
+        init_log();
+        let mut engine = Engine::new(InstrumentId(1));
+        let sell = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(10),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 1,
+            trader_id: TraderId(1),
+        };
+        engine.submit_order(sell).unwrap();
+        let replacement = Order {
+            order_id: OrderId(1),
+            client_order_id: "c1".into(),
+            instrument_id: InstrumentId(2),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(5),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: 2,
+            trader_id: TraderId(1),
+        };
+        let err = engine.modify_order(OrderId(1), &replacement).unwrap_err();
+        assert!(err.contains("same instrument"));
+    }
+
+    #[test]
+    fn order_status_tracks_fills_cancels_and_replaces() {
+        init_log();
+        let order = |id: u64, side: Side, qty: i64, price: i64, tif: TimeInForce, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
+            time_in_force: tif,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.submit_order(order(1, Side::Sell, 10, 100, TimeInForce::GTC, 1)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 4, 100, TimeInForce::GTC, 2)).unwrap();
+        engine.submit_order(order(3, Side::Buy, 9, 100, TimeInForce::IOC, 3)).unwrap();
+
+        let filled = engine.order_status(OrderId(2)).unwrap();
+        assert_eq!(filled.status, OrderStatus::Filled);
+        assert_eq!((filled.filled_quantity, filled.remaining_quantity), (Decimal::from(4), Decimal::ZERO));
+        assert_eq!(filled.avg_price, Some(Decimal::from(100)));
+        // IOC took the remaining 6 and canceled the rest.
+        let ioc = engine.order_status(OrderId(3)).unwrap();
+        assert_eq!((ioc.status, ioc.filled_quantity, ioc.remaining_quantity), (OrderStatus::Canceled, Decimal::from(6), Decimal::ZERO));
+        let sell = engine.order_status(OrderId(1)).unwrap();
+        assert_eq!(sell.status, OrderStatus::Filled);
+
+        engine.submit_order(order(4, Side::Sell, 10, 101, TimeInForce::GTC, 1)).unwrap();
+        engine.submit_order(order(5, Side::Buy, 3, 101, TimeInForce::GTC, 2)).unwrap();
+        engine.modify_order(OrderId(4), &order(6, Side::Sell, 5, 101, TimeInForce::GTC, 1)).unwrap();
+        assert_eq!(engine.order_status(OrderId(4)).unwrap().status, OrderStatus::Canceled);
+        let replaced = engine.order_status(OrderId(6)).unwrap();
+        assert_eq!(replaced.status, OrderStatus::PartiallyFilled);
+        assert_eq!((replaced.quantity, replaced.filled_quantity, replaced.remaining_quantity), (Decimal::from(8), Decimal::from(3), Decimal::from(5)));
+        assert_eq!(replaced.queue_position, Some(0));
+
+        assert!(engine.cancel_order(OrderId(6)).is_some());
+        let canceled = engine.order_status(OrderId(6)).unwrap();
+        assert_eq!((canceled.status, canceled.remaining_quantity), (OrderStatus::Canceled, Decimal::ZERO));
+        assert!(canceled.queue_position.is_none());
+        assert!(engine.order_status(OrderId(99)).is_none());
+
+        // An order that does not rest ends with its last execution report's status, not always Canceled.
+        let (_, reports) = engine.submit_order(order(7, Side::Buy, 50, 101, TimeInForce::FOK, 3)).unwrap();
+        assert_eq!(engine.order_status(OrderId(7)).unwrap().status, reports.last().unwrap().order_status);
+        let rejected = ExecutionReport {
+            order_id: OrderId(8),
+            order_status: OrderStatus::Rejected,
+            ..reports.last().unwrap().clone()
+        };
+        let mut tracker = OrderTracker::new(10);
+        tracker.accept(&order(8, Side::Buy, 50, 101, TimeInForce::FOK, 3), &[], &[rejected], false);
+        assert_eq!(tracker.records[&OrderId(8)].status, OrderStatus::Rejected);
+    }
+
+    #[test]
+    fn open_orders_lists_live_orders_across_instruments() {
+        init_log();
+        let order = |id: u64, instrument: u64, side: Side, qty: i64, price: i64, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(2), None), (InstrumentId(1), None)]);
+        engine.submit_order(order(1, 2, Side::Buy, 5, 50, 7)).unwrap();
+        engine.submit_order(order(2, 1, Side::Sell, 10, 100, 7)).unwrap();
+        engine.submit_order(order(3, 1, Side::Buy, 5, 99, 8)).unwrap();
+        engine.submit_order(order(4, 1, Side::Buy, 4, 100, 8)).unwrap();
+        engine.submit_order(order(5, 1, Side::Buy, 1, 98, 7)).unwrap();
+        engine.cancel_order(OrderId(5));
+
+        let open = engine.open_orders(TraderId(7));
+        let ids: Vec<u64> = open.iter().map(|o| o.order_id.0).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(open[0].status, OrderStatus::PartiallyFilled);
+        assert_eq!(open[0].remaining_quantity, Decimal::from(6));
+        assert_eq!(open[1].instrument_id, InstrumentId(2));
+        assert_eq!(engine.open_orders(TraderId(8)).len(), 1);
+        assert!(engine.open_orders(TraderId(9)).is_empty());
+    }
+
+    #[test]
+    fn trade_history_queries_and_eviction() {
+        init_log();
+        let order = |id: u64, instrument: u64, side: Side, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        for (i, instrument) in [1, 2, 1, 1].into_iter().enumerate() {
+            let id = 10 * (i as u64 + 1);
+            engine.submit_order(order(id, instrument, Side::Sell, 100)).unwrap();
+            engine.submit_order(order(id + 1, instrument, Side::Buy, 100)).unwrap();
+        }
+        let ids = |trades: Vec<Trade>| trades.iter().map(|t| t.trade_id.0).collect::<Vec<_>>();
+        assert_eq!(ids(engine.trades_since(TradeId(0))), vec![1, 2, 3, 4]);
+        assert_eq!(ids(engine.trades_since(TradeId(2))), vec![3, 4]);
+        assert!(engine.trades_since(TradeId(4)).is_empty());
+        assert_eq!(ids(engine.trades_for_instrument(InstrumentId(1), 2)), vec![3, 4]);
+        assert_eq!(ids(engine.trades_for_instrument(InstrumentId(2), 10)), vec![2]);
+
+        let mut store = TradeStore::new(2);
+        store.record(&engine.trades_since(TradeId(0)), |_| None);
+        assert_eq!(ids(store.since(TradeId(0))), vec![3, 4]);
+    }
+
+    #[test]
+    fn positions_net_fills_and_report_resting_exposure() {
+        init_log();
+        let order = |id: u64, side: Side, qty: i64, price: i64, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        engine.submit_order(order(1, Side::Sell, 10, 100, 1)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 4, 100, 2)).unwrap();
+        engine.modify_order(OrderId(1), &order(3, Side::Sell, 6, 102, 1)).unwrap();
+        engine.submit_order(order(4, Side::Buy, 2, 102, 2)).unwrap();
+
+        let buyer = engine.position(TraderId(2), InstrumentId(1));
+        assert_eq!(buyer.net_quantity, Decimal::from(6));
+        assert_eq!(buyer.avg_entry_price, Some("100.66666666666666666666666667".parse().unwrap()));
+        let seller = engine.position(TraderId(1), InstrumentId(1));
+        assert_eq!(seller.net_quantity, Decimal::from(-6));
+        assert_eq!(seller.open_sell_quantity, Decimal::from(4));
+        assert!(seller.open_buy_quantity.is_zero());
+
+        engine.submit_order(order(5, Side::Buy, 1, 90, 3)).unwrap();
+        let quoting = engine.positions_for_trader(TraderId(3));
+        assert_eq!(quoting.len(), 1);
+        assert!(quoting[0].net_quantity.is_zero());
+        assert_eq!(quoting[0].open_buy_quantity, Decimal::from(1));
+        assert!(engine.positions_for_trader(TraderId(9)).is_empty());
+        assert_eq!(engine.position(TraderId(9), InstrumentId(2)), Position::flat(TraderId(9), InstrumentId(2)));
+    }
+
+    #[test]
+    fn risk_limits_reject_before_matching() {
+        init_log();
+        let order = |id: u64, side: Side, qty: i64, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(1),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.set_risk_limits(RiskLimits {
+            max_order_quantity: Some(Decimal::from(10)),
+            max_order_notional: Some(Decimal::from(900)),
+            max_position: Some(Decimal::from(15)),
+        });
+        let err = engine.submit_order(order(1, Side::Buy, 11, 1)).unwrap_err();
+        assert_eq!(err, "Order quantity 11 exceeds max order quantity 10");
+        let err = engine.submit_order(order(2, Side::Buy, 10, 100)).unwrap_err();
+        assert_eq!(err, "Order notional 1000 exceeds max order notional 900");
+
+        engine.submit_order(order(3, Side::Buy, 10, 50)).unwrap();
+        let err = engine.submit_order(order(4, Side::Buy, 6, 50)).unwrap_err();
+        assert_eq!(err, "Position 16 for trader 1 would exceed max position 15");
+        // Selling reduces exposure; a replacement no longer counts the order it replaces.
+        engine.submit_order(order(5, Side::Sell, 10, 60)).unwrap();
+        engine.modify_order(OrderId(3), &order(6, Side::Buy, 10, 50)).unwrap();
+        let err = engine.modify_order(OrderId(6), &order(7, Side::Buy, 10, 95)).unwrap_err();
+        assert_eq!(err, "Order notional 950 exceeds max order notional 900");
+        assert!(engine.get_order(OrderId(6)).is_some());
+        assert!(engine.order_status(OrderId(1)).is_none());
+    }
+
+    #[test]
+    fn band_config_and_trading_session_reject_orders() {
+        init_log();
+        let order = |id: u64, side: Side, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.set_band_config(BandConfig { max_deviation_pct: Some(Decimal::from(10)) });
+        // No last trade yet: anything goes.
+        engine.submit_order(order(1, Side::Sell, 100)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 100)).unwrap();
+        let err = engine.submit_order(order(3, Side::Sell, 111)).unwrap_err();
+        assert_eq!(err, "Price 111 is out of range 90..110 for instrument 1");
+        engine.submit_order(order(4, Side::Sell, 110)).unwrap();
+        let err = engine.modify_order(OrderId(4), &order(5, Side::Sell, 80)).unwrap_err();
+        assert!(err.contains("out of range"), "{}", err);
+
+        let now = crate::venue::SessionTime::now().minutes();
+        let at = |minutes: u16| crate::venue::SessionTime::new(minutes % 1440 / 60, minutes % 60);
+        engine.set_trading_session(TradingSession { open: at(now + 60), close: at(now + 120) });
+        let err = engine.submit_order(order(6, Side::Buy, 100)).unwrap_err();
+        assert!(err.starts_with("market not open: outside trading session"), "{}", err);
+        engine.cancel_order(OrderId(4)).unwrap();
+        engine.set_trading_session(TradingSession { open: at(now + 1380), close: at(now + 60) });
+        engine.submit_order(order(6, Side::Buy, 100)).unwrap();
+    }
+
+    #[test]
+    fn event_sinks_receive_lifecycle_events_in_order() {
+        init_log();
+        let order = |id: u64, side: Side, qty: i64, tif: TimeInForce| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(100)),
+            time_in_force: tif,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let sink = crate::events::InMemoryEventSink::new();
+        let mut engine = MultiEngine::new_with_instruments(vec![]);
+        engine.add_event_sink(std::sync::Arc::new(sink.clone()));
+        engine.add_instrument(InstrumentId(1), None).unwrap();
+        engine.submit_order(order(1, Side::Sell, 5, TimeInForce::GTC)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 8, TimeInForce::IOC)).unwrap();
+        engine.submit_order(order(3, Side::Sell, 1, TimeInForce::GTC)).unwrap();
+        engine.cancel_order(OrderId(3));
+
+        let kinds: Vec<String> = sink
+            .events()
+            .iter()
+            .map(|e| match e {
+                EngineEvent::OrderAccepted { order } => format!("accepted:{}", order.order_id.0),
+                EngineEvent::Trade(t) => format!("trade:{}", t.trade_id.0),
+                EngineEvent::Report(r) => format!("report:{}:{:?}", r.order_id.0, r.exec_type),
+                EngineEvent::Canceled { order_id, .. } => format!("canceled:{}", order_id.0),
+                EngineEvent::Expired { order_id, quantity, .. } => format!("expired:{}:{}", order_id.0, quantity),
+                EngineEvent::BookChanged { seq, .. } => format!("book:{}", seq),
+                EngineEvent::StateChange { state, .. } => format!("state:{}", state),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "state:added",
+                "accepted:1",
+                "report:1:New",
+                "book:2",
+                "accepted:2",
+                "trade:1",
+                "report:1:Fill",
+                "report:2:PartialFill",
+                "expired:2:3",
+                "book:6",
+                "accepted:3",
+                "report:3:New",
+                "book:8",
+                "canceled:3",
+                "book:9",
+            ]
+        );
+    }
+
+    #[test]
+    fn sequence_numbers_cover_trades_reports_and_book_changes() {
+        init_log();
+        let order = |id: u64, side: Side| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        let (_, reports) = engine.submit_order(order(1, Side::Sell)).unwrap();
+        assert_eq!(reports[0].seq, 1);
+        assert_eq!(engine.book_snapshot_for(InstrumentId(1)).unwrap().seq, 2);
+        let (trades, reports) = engine.submit_order(order(2, Side::Buy)).unwrap();
+        assert_eq!(trades[0].seq, 3);
+        assert_eq!(reports.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![4, 5]);
+        assert_eq!(engine.book_snapshot_for(InstrumentId(1)).unwrap().seq, 6);
+        assert_eq!(engine.book_snapshot_for(InstrumentId(2)).unwrap().seq, 0);
+        assert_eq!(engine.trades_since(TradeId(0))[0].seq, 3);
+
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        let (_, reports) = restored.submit_order(order(3, Side::Sell)).unwrap();
+        assert_eq!(reports[0].seq, 7);
+    }
+
+    #[test]
+    fn event_journal_replays_sequenced_events_and_reports_gaps() {
+        init_log();
+        let order = |id: u64, side: Side| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.submit_order(order(1, Side::Sell)).unwrap();
+        engine.submit_order(order(2, Side::Buy)).unwrap();
+        engine.submit_order(order(3, Side::Sell)).unwrap();
+        engine.cancel_order(OrderId(3));
+
+        let all = engine.events_since(0).unwrap();
+        let seqs: Vec<u64> = all.iter().filter_map(|e| e.seq()).collect();
+        assert_eq!(seqs, (1..=9).collect::<Vec<_>>());
+        assert!(matches!(all[1], EngineEvent::BookChanged { seq: 2, best_ask: Some(_), .. }));
+        assert!(matches!(all[8], EngineEvent::BookChanged { best_bid: None, best_ask: None, .. }));
+        let tail = engine.events_since(6).unwrap();
+        assert_eq!(tail.iter().filter_map(|e| e.seq()).collect::<Vec<_>>(), vec![7, 8, 9]);
+        assert!(engine.events_since(9).unwrap().is_empty());
+
+        // Once the journal overflows, requests reaching back past the retained window fail.
+        engine.journal = EventJournal::new(2, engine.seq.next);
+        engine.submit_order(order(4, Side::Sell)).unwrap();
+        engine.submit_order(order(5, Side::Sell)).unwrap();
+        assert!(engine.events_since(9).is_err());
+        assert!(engine.events_since(10).is_err());
+        assert_eq!(
+            engine.events_since(11).unwrap().iter().filter_map(|e| e.seq()).collect::<Vec<_>>(),
+            vec![12, 13]
+        );
+    }
+
+    #[test]
+    fn input_journal_replay_reproduces_trades_and_reports() {
+        init_log();
+        let order = |id: u64, instrument: u64, side: Side, qty: i64, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id % 3),
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        let mut trades = Vec::new();
+        let mut reports = Vec::new();
+        // State before the journal starts is carried by its base snapshot.
+        engine.submit_order(order(1, 1, Side::Sell, 5, 101)).unwrap();
+        engine.start_input_journal();
+
+        let mut collect = |out: (Vec<Trade>, Vec<ExecutionReport>)| {
+            trades.extend(out.0);
+            reports.extend(out.1);
+        };
+        collect(engine.submit_order(order(2, 1, Side::Sell, 3, 100)).unwrap());
+        collect(engine.submit_order(order(3, 1, Side::Buy, 4, 101)).unwrap());
+        engine
+            .add_instrument_with_tick_size(InstrumentId(2), Some("B".into()), Decimal::new(5, 1))
+            .unwrap();
+        collect(engine.submit_order(order(4, 2, Side::Buy, 2, 50)).unwrap());
+        collect(engine.modify_order(OrderId(4), &order(5, 2, Side::Buy, 6, 51)).unwrap());
+        collect(engine.submit_order(order(6, 2, Side::Sell, 4, 51)).unwrap());
+        assert!(engine.submit_order(order(6, 2, Side::Sell, 1, 51)).is_err());
+        assert_eq!(engine.cancel_order(OrderId(5)), Some(InstrumentId(2)));
+        assert_eq!(engine.cancel_order(OrderId(5)), None);
+        engine.remove_instrument(InstrumentId(2)).unwrap();
+        collect(engine.submit_order(order(7, 1, Side::Buy, 10, 102)).unwrap());
+
+        let journal = engine.input_journal().unwrap().clone();
+        assert_eq!(journal.entries.len(), 9);
+        assert_eq!(journal.entries.iter().map(|e| e.seq).collect::<Vec<_>>(), (1..=9).collect::<Vec<_>>());
+        assert!(matches!(journal.entries[6].command, Command::Cancel { order_id: OrderId(5) }));
+
+        // Journals survive serialization, and replaying one reproduces the run byte for byte.
+        let json = serde_json::to_string(&journal).unwrap();
+        let replay = MultiEngine::replay(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert!(!trades.is_empty());
+        assert_eq!(serde_json::to_string(&replay.trades).unwrap(), serde_json::to_string(&trades).unwrap());
+        assert_eq!(serde_json::to_string(&replay.reports).unwrap(), serde_json::to_string(&reports).unwrap());
+        assert_eq!(
+            replay.engine.book_snapshot_for(InstrumentId(1)).unwrap().checksum,
+            engine.book_snapshot_for(InstrumentId(1)).unwrap().checksum
+        );
+        assert_eq!(replay.engine.snapshot().next_seq, engine.snapshot().next_seq);
+        assert!(replay.engine.input_journal().is_none());
+
+        // A journal replayed onto the wrong base fails at the first command that no longer applies.
+        let mut wrong = journal.clone();
+        wrong.base = MultiEngine::new_with_instruments(vec![]).snapshot();
+        let err = MultiEngine::replay(&wrong).unwrap_err();
+        assert_eq!(err, "Journal entry 1 failed on replay: Unknown instrument 1");
+
+        assert!(engine.take_input_journal().is_some());
+        engine.submit_order(order(8, 1, Side::Sell, 1, 110)).unwrap();
+        assert!(engine.input_journal().is_none());
     }
 
-    fn book_snapshot_for(&self, id: InstrumentId) -> Option<BookSnapshot> {
-        self.books.get(&id).map(|book| BookSnapshot {
-            instrument_id: id,
-            best_bid: book.best_bid(),
-            best_ask: book.best_ask(),
-        })
+    /// Collects the commands and resets an [`InputSink`] is given.
+    #[derive(Debug, Default)]
+    struct CollectingInputSink {
+        commands: std::sync::Mutex<Vec<Command>>,
+        resets: std::sync::atomic::AtomicUsize,
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{Order, OrderId, OrderType, Side, TimeInForce, TraderId};
-    use rust_decimal::Decimal;
+    impl InputSink for CollectingInputSink {
+        fn on_command(&self, command: &Command) {
+            self.commands.lock().unwrap().push(command.clone());
+        }
 
-    fn init_log() {
-        let _ = env_logger::try_init();
+        fn on_reset(&self) {
+            self.resets.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
     }
 
     #[test]
-    fn engine_submit_order_matches_and_returns_trades() {
+    fn input_sinks_get_accepted_commands_that_apply_to_a_copy_despite_its_limits() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let sell = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
+        let order = |id: u64, side: Side, qty: i64, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
             instrument_id: InstrumentId(1),
-            side: Side::Sell,
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(id),
         };
-        engine.submit_order(sell).unwrap();
-        let buy = Order {
-            order_id: OrderId(2),
-            client_order_id: "c2".into(),
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        let sink = std::sync::Arc::new(CollectingInputSink::default());
+        engine.add_input_sink(sink.clone());
+        let mut copy = MultiEngine::new_with_instruments(vec![]);
+        copy.load_from_snapshot(engine.snapshot()).unwrap();
+        copy.set_risk_limits(RiskLimits {
+            max_order_quantity: Some(Decimal::ONE),
+            ..RiskLimits::default()
+        });
+        copy.set_book_limits(BookLimits {
+            max_orders_per_level: Some(1),
+            ..BookLimits::default()
+        });
+
+        engine.submit_order(order(1, Side::Buy, 5, 100)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 5, 100)).unwrap();
+        assert!(engine.submit_order(order(2, Side::Buy, 5, 100)).is_err(), "rejections are not given");
+        let quote = Quote {
+            quote_id: "q".into(),
+            trader_id: TraderId(9),
             instrument_id: InstrumentId(1),
-            side: Side::Buy,
+            bid_order_id: OrderId(3),
+            bid_price: Decimal::from(99),
+            bid_quantity: Decimal::from(2),
+            ask_order_id: OrderId(4),
+            ask_price: Decimal::from(101),
+            ask_quantity: Decimal::from(2),
+            timestamp: 3,
+        };
+        engine.submit_quote(&quote).unwrap();
+        let (trades, _) = engine.submit_order(order(5, Side::Sell, 7, 100)).unwrap();
+        let commands = sink.commands.lock().unwrap().clone();
+        assert_eq!(commands.len(), 4, "a quote is one command");
+        assert!(matches!(commands[2], Command::Quote { .. }));
+
+        let mut copied_trades = Vec::new();
+        for command in &commands {
+            copied_trades.extend(copy.apply_input(command).unwrap().0);
+        }
+        assert_eq!(serde_json::to_string(&copied_trades).unwrap(), serde_json::to_string(&trades).unwrap());
+        let sorted = |engine: &MultiEngine| {
+            let mut snapshot = engine.snapshot();
+            snapshot.order_to_instrument.sort_by_key(|(order_id, _)| order_id.0);
+            snapshot.order_fills.sort_by_key(|fill| fill.order_id.0);
+            serde_json::to_string(&snapshot).unwrap()
+        };
+        assert_eq!(sorted(&copy), sorted(&engine));
+        assert_eq!(copy.risk_limits().max_order_quantity, Some(Decimal::ONE), "limits apply again afterwards");
+        assert!(copy.submit_order(order(6, Side::Buy, 2, 90)).is_err());
+        assert_eq!(copy.apply_input(&Command::Cancel { order_id: OrderId(99) }).unwrap_err(), "Order 99 not found");
+
+        let snapshot = engine.snapshot();
+        engine.load_from_snapshot(snapshot).unwrap();
+        assert_eq!(sink.resets.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn command_log_replay_restores_orders_accepted_after_the_snapshot() {
+        init_log();
+        use crate::persistence::InMemoryCommandLog;
+        let order = |id: u64, side: Side, qty: i64, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 2,
-            trader_id: TraderId(2),
+            timestamp: id,
+            trader_id: TraderId(id % 3),
         };
-        let (trades, reports) = engine.submit_order(buy).unwrap();
-        assert_eq!(trades.len(), 1);
-        assert_eq!(trades[0].quantity, Decimal::from(10));
-        assert!(!reports.is_empty());
+        let log = std::sync::Arc::new(InMemoryCommandLog::new());
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.set_command_log(log.clone());
+        engine.submit_order(order(1, Side::Sell, 5, 101)).unwrap();
+        let saved = engine.snapshot();
+        assert_eq!(saved.command_seq, 1);
+
+        // Accepted after the save: a cross, a replace, a cancel, and a rejection, which is not logged.
+        engine.submit_order(order(2, Side::Buy, 2, 101)).unwrap();
+        engine.submit_order(order(3, Side::Buy, 1, 99)).unwrap();
+        engine.modify_order(OrderId(3), &order(4, Side::Buy, 3, 100)).unwrap();
+        assert!(engine.submit_order(order(4, Side::Sell, 1, 102)).is_err());
+        assert_eq!(engine.cancel_order(OrderId(1)), Some(InstrumentId(1)));
+        assert_eq!(engine.cancel_order(OrderId(1)), None);
+        assert_eq!(log.entries().iter().map(|e| e.seq).collect::<Vec<_>>(), (1..=5).collect::<Vec<_>>());
+        assert!(matches!(log.entries()[3].command, Command::Modify { order_id: OrderId(3), .. }));
+
+        // A crash before the next save: the saved snapshot plus the log rebuild the same engine.
+        let mut restarted = MultiEngine::new_with_instruments(vec![]);
+        restarted.load_from_snapshot(saved).unwrap();
+        let replay = restarted.replay_command_log(log.as_ref()).unwrap();
+        assert_eq!((replay.applied, replay.skipped), (4, 0));
+        assert_eq!(restarted.command_seq(), 5);
+        assert_eq!(
+            restarted.book_snapshot_for(InstrumentId(1)).unwrap().checksum,
+            engine.book_snapshot_for(InstrumentId(1)).unwrap().checksum
+        );
+        assert_eq!(
+            serde_json::to_string(&restarted.snapshot()).unwrap(),
+            serde_json::to_string(&engine.snapshot()).unwrap()
+        );
+        // Nothing is applied twice.
+        assert_eq!(restarted.replay_command_log(log.as_ref()).unwrap(), CommandReplay::default());
+
+        // The snapshot now covers the log; a later entry keeps it.
+        log.compact(4).unwrap();
+        assert_eq!(log.entries().len(), 5);
+        log.compact(restarted.snapshot().command_seq).unwrap();
+        assert!(log.entries().is_empty());
+
+        // A log that does not follow on from the snapshot (an older one) is refused whole.
+        restarted.set_command_log(log.clone());
+        restarted.submit_order(order(5, Side::Sell, 1, 105)).unwrap();
+        let mut older = MultiEngine::new_with_instruments(vec![]);
+        older.load_from_snapshot(engine.snapshot()).unwrap();
+        older.command_seq = 4;
+        let err = older.replay_command_log(log.as_ref()).unwrap_err();
+        assert_eq!(err, "Command log starts at entry 6, but the snapshot ends at entry 4");
+        assert!(older.get_order(OrderId(5)).is_none());
     }
 
     #[test]
-    fn engine_submit_order_wrong_instrument_returns_err() {
+    fn advance_time_runs_expiries_and_uncrosses_in_due_order() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let order = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
-            instrument_id: InstrumentId(2),
-            side: Side::Buy,
+        let order = |id: u64, side: Side, price: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(2),
+            price: Some(Decimal::from(price)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(id),
         };
-        assert!(engine.submit_order(order).is_err());
+        let sink = crate::events::InMemoryEventSink::new();
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.add_event_sink(std::sync::Arc::new(sink.clone()));
+        engine.start_input_journal();
+        engine.submit_order(order(1, Side::Buy, 99)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 98)).unwrap();
+        let gtd = engine.expire_order_at(OrderId(1), 1_000).unwrap();
+        let later = engine.expire_order_at(OrderId(2), 2_000).unwrap();
+        assert!(engine.expire_order_at(OrderId(9), 1_000).is_err());
+        assert_eq!(engine.pending_timers().iter().map(|t| t.timer_id).collect::<Vec<_>>(), vec![gtd, later]);
+
+        assert!(engine.advance_time(999).is_empty());
+        let fired = engine.advance_time(1_000);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].timer.timer_id, gtd);
+        assert!(fired[0].result.is_ok());
+        assert!(engine.get_order(OrderId(1)).is_none());
+        assert_eq!(engine.order_status(OrderId(1)).unwrap().status, OrderStatus::Canceled);
+        assert!(sink.events().iter().any(|e| matches!(e, EngineEvent::Expired { order_id: OrderId(1), .. })));
+
+        // Time never goes backwards; a canceled timer never fires.
+        assert!(engine.advance_time(10).is_empty());
+        assert_eq!(engine.now(), 1_000);
+        assert!(engine.cancel_timer(later));
+        assert!(!engine.cancel_timer(later));
+
+        // Timers due at the same time run in the order they were set; a failing one does not stop the rest.
+        engine.submit_order(order(3, Side::Sell, 97)).unwrap();
+        engine.schedule(3_000, TimedAction::ExpireOrder { order_id: OrderId(1) }).unwrap();
+        engine.schedule(3_000, TimedAction::Uncross { instrument_id: InstrumentId(1) }).unwrap();
+        let fired = engine.advance_time(5_000);
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].result.as_ref().unwrap_err(), "Order 1 not found");
+        let (trades, _) = fired[1].result.as_ref().unwrap();
+        assert!(trades.is_empty());
+        assert!(engine.pending_timers().is_empty());
+
+        // Timers survive snapshots and replay from the input journal.
+        engine.expire_order_at(OrderId(2), 6_000).unwrap();
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        assert_eq!(restored.pending_timers(), engine.pending_timers());
+        assert_eq!(restored.now(), 5_000);
+        restored.advance_time(6_000);
+        assert!(restored.get_order(OrderId(2)).is_none());
+
+        let replay = MultiEngine::replay(engine.input_journal().unwrap()).unwrap();
+        assert_eq!(replay.engine.pending_timers(), engine.pending_timers());
+        assert!(replay.engine.get_order(OrderId(1)).is_none());
     }
 
     #[test]
-    fn engine_submit_order_limit_without_price_rejected() {
+    fn snapshot_round_trip_keeps_order_metadata_and_fill_state() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let order = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
+        let order = |id: u64, side: Side, qty: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("client-{}", id),
             instrument_id: InstrumentId(1),
-            side: Side::Buy,
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
-            price: None,
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(100)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: 1_000 + id,
+            trader_id: TraderId(id),
         };
-        let err = engine.submit_order(order).unwrap_err();
-        assert!(err.to_lowercase().contains("price"));
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.submit_order(order(1, Side::Sell, 10)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 4)).unwrap();
+        let before = engine.order_status(OrderId(1)).unwrap();
+        assert_eq!(before.status, OrderStatus::PartiallyFilled);
+
+        let snap = engine.snapshot();
+        assert_eq!(snap.version, ENGINE_SNAPSHOT_VERSION);
+        assert_eq!(snap.order_fills.len(), 1);
+        let json = serde_json::to_string(&snap).unwrap();
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(serde_json::from_str(&json).unwrap()).unwrap();
+        let after = restored.order_status(OrderId(1)).unwrap();
+        assert_eq!(after.status, OrderStatus::PartiallyFilled);
+        assert_eq!(after.quantity, Decimal::from(10));
+        assert_eq!(after.filled_quantity, Decimal::from(4));
+        assert_eq!(after.remaining_quantity, Decimal::from(6));
+        assert_eq!(after.avg_price, Some(Decimal::from(100)));
+        let resting = &restored.snapshot().books[0].1[0];
+        assert_eq!(resting.client_order_id, "client-1");
+        assert_eq!(resting.timestamp, 1_001);
+        assert_eq!(resting.time_in_force, TimeInForce::GTC);
+
+        // Unversioned (version 1) snapshots still load, without fill state; newer versions are refused.
+        let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+        legacy.as_object_mut().unwrap().remove("version");
+        legacy.as_object_mut().unwrap().remove("order_fills");
+        let legacy: EngineSnapshot = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.version, 1);
+        restored.load_from_snapshot(legacy).unwrap();
+        assert_eq!(restored.order_status(OrderId(1)).unwrap().quantity, Decimal::from(6));
+        let mut newer = engine.snapshot();
+        newer.version = ENGINE_SNAPSHOT_VERSION + 1;
+        assert!(restored.load_from_snapshot(newer).unwrap_err().starts_with("Unsupported snapshot version 3"));
     }
 
     #[test]
-    fn engine_order_flow_submit_then_cancel() {
+    fn suspended_instrument_rejects_orders_while_others_trade() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let sell = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
-            instrument_id: InstrumentId(1),
-            side: Side::Sell,
+        let order = |id: u64, instrument: u64, side: Side| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(5),
+            quantity: Decimal::from(1),
             price: Some(Decimal::from(100)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(id),
         };
-        engine.submit_order(sell).unwrap();
-        let canceled = engine.cancel_order(OrderId(1));
-        assert!(canceled);
-        assert!(engine.best_ask().is_none(), "cancel resting: book no longer has that order");
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        engine.submit_order(order(1, 1, Side::Buy)).unwrap();
+        engine.set_instrument_state(InstrumentId(1), InstrumentState::Suspended).unwrap();
+        assert_eq!(engine.instrument_state(InstrumentId(1)), Some(InstrumentState::Suspended));
+
+        assert_eq!(engine.submit_order(order(2, 1, Side::Sell)).unwrap_err(), "Instrument 1 is suspended");
+        assert_eq!(
+            engine.modify_order(OrderId(1), &order(3, 1, Side::Buy)).unwrap_err(),
+            "Instrument 1 is suspended"
+        );
+        assert!(engine.submit_order(order(4, 2, Side::Buy)).is_ok());
+
+        // State survives a snapshot; reactivating resumes trading; delisting is final but allows cancels.
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        assert_eq!(restored.instrument_state(InstrumentId(1)), Some(InstrumentState::Suspended));
+        engine.set_instrument_state(InstrumentId(1), InstrumentState::Active).unwrap();
+        assert_eq!(engine.submit_order(order(5, 1, Side::Sell)).unwrap().0.len(), 1);
+        engine.set_instrument_state(InstrumentId(2), InstrumentState::Delisted).unwrap();
+        assert_eq!(engine.submit_order(order(6, 2, Side::Sell)).unwrap_err(), "Instrument 2 is delisted");
+        assert!(engine.set_instrument_state(InstrumentId(2), InstrumentState::Active).is_err());
+        assert_eq!(engine.cancel_order(OrderId(4)), Some(InstrumentId(2)));
+        assert!(engine.set_instrument_state(InstrumentId(9), InstrumentState::Suspended).is_err());
     }
 
     #[test]
-    fn engine_modify_then_incoming_matches() {
+    fn halted_instrument_rejects_orders_while_others_trade() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let sell = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
-            instrument_id: InstrumentId(1),
-            side: Side::Sell,
+        let order = |id: u64, instrument: u64, side: Side| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
+            quantity: Decimal::from(1),
             price: Some(Decimal::from(100)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(id),
         };
-        engine.submit_order(sell).unwrap();
-        let replacement = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        engine.start_input_journal();
+        engine.submit_order(order(1, 2, Side::Buy)).unwrap();
+        engine.set_instrument_market_state(InstrumentId(2), MarketState::Halted).unwrap();
+        assert_eq!(engine.instrument_market_state(InstrumentId(2)), Some(MarketState::Halted));
+        assert_eq!(engine.instrument_market_state(InstrumentId(1)), Some(MarketState::Open));
+
+        assert_eq!(
+            engine.submit_order(order(2, 2, Side::Sell)).unwrap_err(),
+            "market not open for instrument 2"
+        );
+        engine.submit_order(order(3, 1, Side::Buy)).unwrap();
+        assert_eq!(engine.submit_order(order(4, 1, Side::Sell)).unwrap().0.len(), 1);
+
+        // Market state survives a snapshot and a journal replay; reopening resumes trading.
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        assert_eq!(restored.instrument_market_state(InstrumentId(2)), Some(MarketState::Halted));
+        let replay = MultiEngine::replay(engine.input_journal().unwrap()).unwrap();
+        assert_eq!(replay.engine.instrument_market_state(InstrumentId(2)), Some(MarketState::Halted));
+        engine.set_instrument_market_state(InstrumentId(2), MarketState::Open).unwrap();
+        assert_eq!(engine.submit_order(order(5, 2, Side::Sell)).unwrap().0.len(), 1);
+        assert!(engine.set_instrument_market_state(InstrumentId(9), MarketState::Closed).is_err());
+    }
+
+    #[test]
+    fn quote_replaces_previous_sides_atomically() {
+        init_log();
+        let quote = |id: u64, bid: i64, ask: i64, quantity: i64| Quote {
+            quote_id: format!("q{}", id),
+            trader_id: TraderId(7),
+            instrument_id: InstrumentId(1),
+            bid_order_id: OrderId(id * 10 + 1),
+            bid_price: Decimal::from(bid),
+            bid_quantity: Decimal::from(quantity),
+            ask_order_id: OrderId(id * 10 + 2),
+            ask_price: Decimal::from(ask),
+            ask_quantity: Decimal::from(quantity),
+            timestamp: id,
+        };
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.start_input_journal();
+        engine.submit_quote(&quote(1, 99, 101, 5)).unwrap();
+        let snap = engine.book_snapshot_for(InstrumentId(1)).unwrap();
+        assert_eq!((snap.best_bid, snap.best_ask), (Some(Decimal::from(99)), Some(Decimal::from(101))));
+
+        // The new quote pulls both old sides; a self-crossing quote is rejected and leaves the book alone.
+        engine.submit_quote(&quote(2, 98, 102, 3)).unwrap();
+        assert_eq!(engine.book_orders(InstrumentId(1)).unwrap().len(), 2);
+        assert_eq!(engine.cancel_order(OrderId(11)), None);
+        assert_eq!(
+            engine.submit_quote(&quote(3, 102, 102, 3)).unwrap_err(),
+            "Quote bid 102 must be below ask 102"
+        );
+        assert!(engine.submit_quote(&quote(2, 97, 103, 3)).unwrap_err().starts_with("Duplicate order id"));
+        let live = engine.live_quote(TraderId(7), InstrumentId(1)).unwrap();
+        assert_eq!((live.bid_order_id, live.ask_order_id), (Some(OrderId(21)), Some(OrderId(22))));
+
+        // An aggressive order trades against the quote; zero quantities pull what is left.
+        let sell = Order {
+            order_id: OrderId(100),
+            client_order_id: "c100".into(),
             instrument_id: InstrumentId(1),
             side: Side::Sell,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(5),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(98)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 2,
-            trader_id: TraderId(1),
+            timestamp: 100,
+            trader_id: TraderId(8),
         };
-        engine.modify_order(OrderId(1), &replacement).unwrap();
-        let buy = Order {
-            order_id: OrderId(2),
-            client_order_id: "c2".into(),
+        assert_eq!(engine.submit_order(sell).unwrap().0.len(), 1);
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        assert_eq!(restored.live_quote(TraderId(7), InstrumentId(1)), Some(live));
+        engine.submit_quote(&quote(4, 0, 0, 0)).unwrap();
+        assert!(engine.book_orders(InstrumentId(1)).unwrap().is_empty());
+        assert_eq!(engine.live_quote(TraderId(7), InstrumentId(1)), None);
+
+        let replay = MultiEngine::replay(engine.input_journal().unwrap()).unwrap();
+        assert_eq!(replay.trades.len(), 1);
+        assert!(replay.engine.book_orders(InstrumentId(1)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn id_store_keeps_trade_ids_unique_across_crash_for_both_engines() {
+        init_log();
+        let order = |id: u64, side: Side| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
             instrument_id: InstrumentId(1),
-            side: Side::Buy,
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(5),
+            quantity: Decimal::from(1),
             price: Some(Decimal::from(100)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 3,
-            trader_id: TraderId(2),
+            timestamp: id,
+            trader_id: TraderId(id),
         };
-        let (trades, _) = engine.submit_order(buy).unwrap();
-        assert_eq!(trades.len(), 1);
-        assert_eq!(trades[0].quantity, Decimal::from(5));
-        assert!(engine.best_ask().is_none());
-        assert!(engine.best_bid().is_none());
+        let store = std::sync::Arc::new(crate::ids::InMemoryIdStore::new());
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.set_id_store(store.clone()).unwrap();
+        let stale = engine.snapshot();
+        engine.submit_order(order(1, Side::Buy)).unwrap();
+        let (trades, _) = engine.submit_order(order(2, Side::Sell)).unwrap();
+        let last_trade = trades[0].trade_id;
+
+        // Crash: only the snapshot from before the trade survives, but the store does not go back.
+        let mut restarted = MultiEngine::new_with_instruments(vec![]);
+        restarted.load_from_snapshot(stale).unwrap();
+        restarted.set_id_store(store.clone()).unwrap();
+        restarted.submit_order(order(3, Side::Buy)).unwrap();
+        let (trades, _) = restarted.submit_order(order(4, Side::Sell)).unwrap();
+        assert!(trades[0].trade_id.0 > last_trade.0);
+
+        // The single-instrument engine resumes the same way, with no snapshot at all.
+        let mut single = Engine::new(InstrumentId(1));
+        single.set_id_store(store.clone()).unwrap();
+        assert!(single.id_watermark().next_trade_id > trades[0].trade_id.0);
+        let mut fresh = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        fresh.submit_order(order(5, Side::Buy)).unwrap();
+        assert_eq!(fresh.submit_order(order(6, Side::Sell)).unwrap().0[0].trade_id, TradeId(1));
     }
 
     #[test]
-    fn engine_modify_order_replacement_rests_and_returns_reports() {
+    fn session_stats_track_trades_and_survive_snapshot_until_reset() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let sell = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
+        let order = |id: u64, side: Side, price: i64, qty: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
             instrument_id: InstrumentId(1),
-            side: Side::Sell,
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(qty),
+            price: Some(Decimal::from(price)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(id),
         };
-        engine.submit_order(sell).unwrap();
-        let replacement = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
-            instrument_id: InstrumentId(1),
-            side: Side::Sell,
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        engine.start_input_journal();
+        engine.submit_order(order(1, Side::Sell, 100, 3)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 100, 3)).unwrap();
+        engine.submit_order(order(3, Side::Sell, 97, 1)).unwrap();
+        engine.submit_order(order(4, Side::Buy, 97, 1)).unwrap();
+        let stats = engine.stats_for(InstrumentId(1)).unwrap();
+        assert_eq!((stats.open, stats.high, stats.low, stats.last), (
+            Some(Decimal::from(100)),
+            Some(Decimal::from(100)),
+            Some(Decimal::from(97)),
+            Some(Decimal::from(97)),
+        ));
+        assert_eq!((stats.volume, stats.trade_count), (Decimal::from(4), 2));
+        assert_eq!(stats.vwap, Some(Decimal::new(9925, 2)));
+        assert_eq!(engine.stats_for(InstrumentId(2)).unwrap().volume, Decimal::ZERO);
+        assert_eq!(engine.stats_for(InstrumentId(9)), None);
+
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        assert_eq!(restored.stats_for(InstrumentId(1)), Some(stats.clone()));
+        engine.reset_session_stats().unwrap();
+        assert_eq!(engine.stats_for(InstrumentId(1)).unwrap().last, None);
+        let replay = MultiEngine::replay(engine.input_journal().unwrap()).unwrap();
+        assert_eq!(replay.engine.stats_for(InstrumentId(1)).unwrap().trade_count, 0);
+
+        let mut single = Engine::new(InstrumentId(1));
+        single.submit_order(order(5, Side::Sell, 100, 2)).unwrap();
+        single.submit_order(order(6, Side::Buy, 100, 2)).unwrap();
+        assert_eq!(single.stats_for(InstrumentId(1)).unwrap().volume, Decimal::from(2));
+    }
+
+    #[test]
+    fn trade_and_execution_history_filter_and_page_by_cursor() {
+        init_log();
+        let order = |id: u64, instrument: u64, side: Side, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(5),
+            quantity: Decimal::from(1),
             price: Some(Decimal::from(100)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 2,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(trader),
         };
-        let (trades, reports) = engine.modify_order(OrderId(1), &replacement).unwrap();
-        assert_eq!(trades.len(), 0);
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None), (InstrumentId(2), None)]);
+        for (i, (instrument, buyer)) in [(1, 2), (2, 3), (1, 2), (1, 3)].into_iter().enumerate() {
+            let id = 10 * (i as u64 + 1);
+            engine.submit_order(order(id, instrument, Side::Sell, 1)).unwrap();
+            engine.submit_order(order(id + 1, instrument, Side::Buy, buyer)).unwrap();
+        }
+        let ids = |page: &HistoryPage<Trade>| page.items.iter().map(|t| t.trade_id.0).collect::<Vec<_>>();
+        let query = |cursor: HistoryCursor| HistoryQuery {
+            instrument_id: Some(InstrumentId(1)),
+            cursor,
+            ..HistoryQuery::latest(2)
+        };
+
+        let page = engine.trade_history(&query(HistoryCursor::After(0)));
+        assert_eq!((ids(&page), page.next_cursor), (vec![1, 3], Some(3)));
+        let page = engine.trade_history(&query(HistoryCursor::After(3)));
+        assert_eq!((ids(&page), page.next_cursor), (vec![4], None));
+        let page = engine.trade_history(&query(HistoryCursor::Before(u64::MAX)));
+        assert_eq!((ids(&page), page.next_cursor), (vec![3, 4], Some(3)));
+        let page = engine.trade_history(&query(HistoryCursor::Before(3)));
+        assert_eq!((ids(&page), page.next_cursor), (vec![1], None));
+
+        let by_trader = HistoryQuery {
+            trader_id: Some(TraderId(3)),
+            ..HistoryQuery::latest(10)
+        };
+        assert_eq!(ids(&engine.trade_history(&by_trader)), vec![2, 4]);
+        let by_time = HistoryQuery {
+            from: Some(21),
+            to: Some(31),
+            ..HistoryQuery::latest(10)
+        };
+        assert_eq!(ids(&engine.trade_history(&by_time)), vec![2, 3]);
+
+        let reports = engine.execution_history(&by_trader).items;
         assert!(!reports.is_empty());
-        assert_eq!(engine.best_ask(), Some(Decimal::from(100)));
+        assert!(reports.iter().all(|r| [21, 41].contains(&r.order_id.0)), "{:?}", reports);
+        let mut all = Vec::new();
+        let mut cursor = HistoryCursor::After(0);
+        loop {
+            let page = engine.execution_history(&HistoryQuery { cursor, ..HistoryQuery::latest(3) });
+            all.extend(page.items.iter().map(|r| r.exec_id.0));
+            match page.next_cursor {
+                Some(next) => cursor = HistoryCursor::After(next),
+                None => break,
+            }
+        }
+        assert_eq!(all, (1..=all.len() as u64).collect::<Vec<_>>());
     }
 
     #[test]
-    fn engine_modify_order_not_found_returns_err() {
+    fn history_log_keeps_trades_and_reports_across_a_restart_replay_and_restore() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let replacement = Order {
-            order_id: OrderId(2),
-            client_order_id: "c2".into(),
+        use crate::persistence::{HistoryLog, InMemoryCommandLog, InMemoryHistoryLog};
+        let order = |id: u64, side: Side, price: i64, trader: u64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
             instrument_id: InstrumentId(1),
-            side: Side::Sell,
+            side,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(5),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(price)),
             time_in_force: TimeInForce::GTC,
-            timestamp: 1,
-            trader_id: TraderId(1),
+            timestamp: id,
+            trader_id: TraderId(trader),
         };
-        let err = engine.modify_order(OrderId(999), &replacement).unwrap_err();
-        assert!(err.contains("not found"));
+        let commands = std::sync::Arc::new(InMemoryCommandLog::new());
+        let history = std::sync::Arc::new(InMemoryHistoryLog::new());
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        let saved = engine.snapshot();
+        engine.set_command_log(commands.clone());
+        assert_eq!(engine.set_history_log(history.clone()).unwrap(), 0);
+        engine.submit_order(order(1, Side::Sell, 100, 7)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 100, 8)).unwrap();
+        let through = engine.last_seq();
+        engine.submit_order(order(3, Side::Sell, 101, 7)).unwrap();
+        let records = history.records().unwrap();
+        assert!(records.windows(2).all(|w| w[0].seq() < w[1].seq()));
+        let trades: Vec<_> = records
+            .iter()
+            .filter_map(|r| match r {
+                HistoryRecord::Trade { trade, buyer, seller } => Some((trade.trade_id, *buyer, *seller)),
+                HistoryRecord::Report { .. } => None,
+            })
+            .collect();
+        assert_eq!(trades, [(TradeId(1), Some(TraderId(8)), Some(TraderId(7)))]);
+        let reports = engine.execution_history(&HistoryQuery::latest(100)).items;
+        assert_eq!(records.len(), trades.len() + reports.len());
+
+        // A crash after order 3 was applied but before its report reached the history log: the restart reloads
+        // the history, and the replay records only what the log lacks.
+        let partial = std::sync::Arc::new(InMemoryHistoryLog::new());
+        partial.append(&records.iter().filter(|r| r.seq() <= through).cloned().collect::<Vec<_>>()).unwrap();
+        let mut restarted = MultiEngine::new_with_instruments(vec![]);
+        restarted.load_from_snapshot(saved.clone()).unwrap();
+        assert_eq!(restarted.set_history_log(partial.clone()).unwrap(), records.len() - 1);
+        assert_eq!(restarted.replay_command_log(commands.as_ref()).unwrap().applied, 3);
+        let json = |records: Vec<HistoryRecord>| serde_json::to_value(records).unwrap();
+        assert_eq!(json(partial.records().unwrap()), json(records));
+        let by_buyer = HistoryQuery {
+            trader_id: Some(TraderId(8)),
+            ..HistoryQuery::latest(10)
+        };
+        assert_eq!(restarted.trade_history(&by_buyer).items.len(), 1);
+        let exec_ids = |engine: &MultiEngine| {
+            engine.execution_history(&HistoryQuery::latest(100)).items.iter().map(|r| r.exec_id).collect::<Vec<_>>()
+        };
+        assert_eq!(exec_ids(&restarted), exec_ids(&engine));
+
+        // Restoring a snapshot replaces the book but not the day's history.
+        restarted.load_from_snapshot(saved).unwrap();
+        assert_eq!(restarted.trade_history(&by_buyer).items.len(), 1);
+        assert_eq!(exec_ids(&restarted), exec_ids(&engine));
     }
 
     #[test]
-    fn engine_modify_order_wrong_instrument_returns_err() {
+    fn book_observers_see_each_change_with_the_book_updated() {
         init_log();
-        let mut engine = Engine::new(InstrumentId(1));
-        let sell = Order {
+        #[derive(Default)]
+        struct Seen(std::sync::Mutex<Vec<(u64, Option<Decimal>)>>);
+        impl BookObserver for Seen {
+            fn on_book_changed(&self, engine: &MultiEngine, instrument_id: InstrumentId) {
+                let best_bid = engine.book_snapshot_for(instrument_id).and_then(|b| b.best_bid);
+                self.0.lock().unwrap().push((instrument_id.0, best_bid));
+            }
+        }
+        let seen = std::sync::Arc::new(Seen::default());
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.add_book_observer(seen.clone());
+        engine.add_instrument(InstrumentId(2), None).unwrap();
+        let bid = Order {
             order_id: OrderId(1),
             client_order_id: "c1".into(),
             instrument_id: InstrumentId(1),
-            side: Side::Sell,
+            side: Side::Buy,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(10),
-            price: Some(Decimal::from(100)),
+            quantity: Decimal::from(3),
+            price: Some(Decimal::from(99)),
             time_in_force: TimeInForce::GTC,
             timestamp: 1,
             trader_id: TraderId(1),
         };
-        engine.submit_order(sell).unwrap();
-        let replacement = Order {
-            order_id: OrderId(1),
-            client_order_id: "c1".into(),
-            instrument_id: InstrumentId(2),
-            side: Side::Sell,
+        engine.submit_order(bid).unwrap();
+        let snapshot = engine.snapshot();
+        engine.cancel_order(OrderId(1));
+        engine.load_from_snapshot(snapshot).unwrap();
+
+        let mut seen = seen.0.lock().unwrap().clone();
+        // Restored books are reported in no particular order.
+        seen[3..].sort();
+        assert_eq!(
+            seen,
+            vec![(2, None), (1, Some(Decimal::from(99))), (1, None), (1, Some(Decimal::from(99))), (2, None)]
+        );
+    }
+
+    #[test]
+    fn update_instrument_applies_rules_atomically_and_replays() {
+        init_log();
+        let order = |id: u64, quantity: Decimal, price: Decimal| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
             order_type: OrderType::Limit,
-            quantity: Decimal::from(5),
-            price: Some(Decimal::from(100)),
+            quantity,
+            price: Some(price),
             time_in_force: TimeInForce::GTC,
-            timestamp: 2,
+            timestamp: id,
             trader_id: TraderId(1),
         };
-        let err = engine.modify_order(OrderId(1), &replacement).unwrap_err();
-        assert!(err.contains("same instrument"));
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.start_input_journal();
+        engine.submit_order(order(1, Decimal::from(3), Decimal::new(1005, 1))).unwrap();
+
+        // One bad part rejects the whole update.
+        let update = InstrumentUpdate {
+            tick_size: Some(Decimal::ONE),
+            lot_size: Some(Some(Decimal::ONE)),
+            ..InstrumentUpdate::default()
+        };
+        let err = engine.update_instrument(InstrumentId(1), update).unwrap_err();
+        assert!(err.starts_with("Cannot change tick size of instrument 1"), "{}", err);
+        assert_eq!(engine.lot_size(InstrumentId(1)), None);
+        assert_eq!(engine.tick_size(InstrumentId(1)), Some(DEFAULT_TICK_SIZE));
+        let zero_lot = InstrumentUpdate {
+            lot_size: Some(Some(Decimal::ZERO)),
+            ..InstrumentUpdate::default()
+        };
+        assert_eq!(engine.update_instrument(InstrumentId(1), zero_lot).unwrap_err(), "Lot size 0 must be positive");
+        let inverted = InstrumentUpdate {
+            price_band: Some(Some(PriceBand { low: Decimal::from(110), high: Decimal::from(90) })),
+            ..InstrumentUpdate::default()
+        };
+        assert!(engine.update_instrument(InstrumentId(1), inverted).is_err());
+        assert!(engine.update_instrument(InstrumentId(9), InstrumentUpdate::default()).is_err());
+
+        let band = PriceBand { low: Decimal::from(90), high: Decimal::from(110) };
+        let update = InstrumentUpdate {
+            symbol: Some(Some("ABC".into())),
+            tick_size: Some(Decimal::new(5, 1)),
+            lot_size: Some(Some(Decimal::ONE)),
+            price_band: Some(Some(band)),
+            state: None,
+            market_state: None,
+        };
+        engine.update_instrument(InstrumentId(1), update).unwrap();
+        assert_eq!(engine.list_instruments(), vec![(InstrumentId(1), Some("ABC".to_string()))]);
+        assert_eq!(engine.tick_size(InstrumentId(1)), Some(Decimal::new(5, 1)));
+        assert_eq!(engine.best_bid(), Some(Decimal::new(1005, 1)));
+
+        let err = engine.submit_order(order(2, Decimal::new(15, 1), Decimal::from(100))).unwrap_err();
+        assert_eq!(err, "Quantity 1.5 is not a multiple of lot size 1");
+        let err = engine.submit_order(order(3, Decimal::ONE, Decimal::from(111))).unwrap_err();
+        assert_eq!(err, "Price 111 is out of range 90..110 for instrument 1");
+        assert!(engine.submit_order(order(4, Decimal::ONE, Decimal::new(10025, 2))).is_err());
+        assert!(engine.modify_order(OrderId(1), &order(5, Decimal::ONE, Decimal::from(120))).is_err());
+        assert!(engine.get_order(OrderId(1)).is_some());
+        engine.submit_order(order(6, Decimal::from(2), Decimal::new(995, 1))).unwrap();
+
+        // Clearing the band and delisting in the same update.
+        let update = InstrumentUpdate {
+            price_band: Some(None),
+            state: Some(InstrumentState::Suspended),
+            ..InstrumentUpdate::default()
+        };
+        engine.update_instrument(InstrumentId(1), update).unwrap();
+        assert_eq!(engine.price_band(InstrumentId(1)), None);
+        assert_eq!(engine.instrument_state(InstrumentId(1)), Some(InstrumentState::Suspended));
+
+        let mut restored = MultiEngine::new_with_instruments(vec![]);
+        restored.load_from_snapshot(engine.snapshot()).unwrap();
+        assert_eq!(restored.lot_size(InstrumentId(1)), Some(Decimal::ONE));
+        assert_eq!(restored.tick_size(InstrumentId(1)), Some(Decimal::new(5, 1)));
+        assert_eq!(restored.price_band(InstrumentId(1)), None);
+
+        let replay = MultiEngine::replay(engine.input_journal().unwrap()).unwrap();
+        assert_eq!(replay.engine.lot_size(InstrumentId(1)), Some(Decimal::ONE));
+        assert_eq!(replay.engine.tick_size(InstrumentId(1)), Some(Decimal::new(5, 1)));
+        assert_eq!(replay.engine.instrument_state(InstrumentId(1)), Some(InstrumentState::Suspended));
+        assert_eq!(replay.engine.list_instruments(), engine.list_instruments());
     }
 }
@@ -0,0 +1,179 @@
+//! Canonical engine event stream: every order lifecycle change, trade, and state change, in the order the
+//! engine applied them.
+//!
+//! Register an [`EngineEventSink`] with [`crate::MultiEngine::add_event_sink`]; sinks are called synchronously
+//! while the engine lock is held, so they should only hand events off (e.g. to a channel), not block.
+//! Sequenced events (trades, reports, book changes) are also journaled so consumers that miss some can
+//! fetch them again with [`crate::MultiEngine::events_since`].
+//!
+//! A [`BookObserver`] (see [`crate::MultiEngine::add_book_observer`]) is called after every book change with
+//! read access to the engine, for output that needs more of the book than the event carries (e.g. L2 deltas).
+
+use crate::engine::MultiEngine;
+use crate::execution::{ExecutionReport, Trade};
+use crate::types::{InstrumentId, Order, OrderId};
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// One engine output event.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum EngineEvent {
+    /// An order (or a replacement) passed validation and risk checks and is about to match.
+    OrderAccepted { order: Order },
+    Trade(Trade),
+    Report(ExecutionReport),
+    /// A resting order was canceled by request.
+    Canceled { order_id: OrderId, instrument_id: InstrumentId },
+    /// An IOC, FOK, or market order's unfilled `quantity` was dropped instead of resting.
+    Expired {
+        order_id: OrderId,
+        instrument_id: InstrumentId,
+        quantity: Decimal,
+    },
+    /// A book changed; its top of book and checksum afterwards.
+    BookChanged {
+        instrument_id: InstrumentId,
+        seq: u64,
+        best_bid: Option<Decimal>,
+        best_ask: Option<Decimal>,
+        checksum: u32,
+    },
+    /// Instrument or market state changed: `added`, `removed`, `uncrossed`, an instrument state (`Active`,
+    /// `Suspended`, `Delisted`), or an instrument's market state (`Open`, `Halted`, `Closed`) per instrument;
+    /// `restored` (snapshot loaded); or the venue-wide market state.
+    StateChange {
+        instrument_id: Option<InstrumentId>,
+        state: String,
+    },
+}
+
+impl EngineEvent {
+    /// Engine sequence number of trades, reports, and book changes (the events kept in the journal).
+    pub fn seq(&self) -> Option<u64> {
+        match self {
+            EngineEvent::Trade(trade) => Some(trade.seq),
+            EngineEvent::Report(report) => Some(report.seq),
+            EngineEvent::BookChanged { seq, .. } => Some(*seq),
+            _ => None,
+        }
+    }
+}
+
+/// How many sequenced events each engine keeps for [`crate::MultiEngine::events_since`].
+pub const EVENT_JOURNAL_CAPACITY: usize = 100_000;
+
+/// Bounded journal of sequenced events (trades, reports, book changes) in sequence order, for gap fill.
+#[derive(Debug)]
+pub(crate) struct EventJournal {
+    events: VecDeque<EngineEvent>,
+    capacity: usize,
+    /// Highest sequence number no longer retained (evicted, or from before a snapshot restore).
+    evicted_through: u64,
+}
+
+impl EventJournal {
+    /// Journal whose first event will be `next_seq`.
+    pub(crate) fn new(capacity: usize, next_seq: u64) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+            evicted_through: next_seq.saturating_sub(1),
+        }
+    }
+
+    pub(crate) fn record(&mut self, event: EngineEvent) {
+        let Some(seq) = event.seq() else { return };
+        self.events.push_back(event);
+        if self.events.len() > self.capacity {
+            self.events.pop_front();
+            self.evicted_through = self.events.front().and_then(|e| e.seq()).map_or(seq, |s| s - 1);
+        }
+    }
+
+    /// Events with a sequence number greater than `seq`, or `Err` if some of them are no longer retained.
+    pub(crate) fn since(&self, seq: u64) -> Result<Vec<EngineEvent>, String> {
+        if seq < self.evicted_through {
+            return Err(format!(
+                "Events after {} are no longer retained (oldest available follows {})",
+                seq, self.evicted_through
+            ));
+        }
+        let start = self.events.partition_point(|e| e.seq().is_some_and(|s| s <= seq));
+        Ok(self.events.range(start..).cloned().collect())
+    }
+}
+
+/// Consumer of [`EngineEvent`]s.
+pub trait EngineEventSink: Send + Sync {
+    fn on_event(&self, event: &EngineEvent);
+}
+
+/// Called after each [`EngineEvent::BookChanged`] (and for every book after a snapshot load or when an
+/// instrument is added), while the engine lock is held.
+pub trait BookObserver: Send + Sync {
+    fn on_book_changed(&self, engine: &MultiEngine, instrument_id: InstrumentId);
+}
+
+/// Collects events in memory (for tests).
+#[derive(Clone, Default)]
+pub struct InMemoryEventSink {
+    events: Arc<Mutex<Vec<EngineEvent>>>,
+}
+
+impl InMemoryEventSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<EngineEvent> {
+        self.events.lock().expect("lock").clone()
+    }
+}
+
+impl EngineEventSink for InMemoryEventSink {
+    fn on_event(&self, event: &EngineEvent) {
+        self.events.lock().expect("lock").push(event.clone());
+    }
+}
+
+/// Registered sinks and book observers of one engine.
+#[derive(Clone, Default)]
+pub(crate) struct EventSinks {
+    sinks: Vec<Arc<dyn EngineEventSink>>,
+    observers: Vec<Arc<dyn BookObserver>>,
+}
+
+impl EventSinks {
+    pub(crate) fn add(&mut self, sink: Arc<dyn EngineEventSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub(crate) fn add_observer(&mut self, observer: Arc<dyn BookObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Whether no sink is registered (observers do not count).
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    pub(crate) fn publish(&self, event: &EngineEvent) {
+        for sink in &self.sinks {
+            sink.on_event(event);
+        }
+    }
+
+    pub(crate) fn book_changed(&self, engine: &MultiEngine, instrument_id: InstrumentId) {
+        for observer in &self.observers {
+            observer.on_book_changed(engine, instrument_id);
+        }
+    }
+}
+
+impl std::fmt::Debug for EventSinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EventSinks({} sinks, {} observers)", self.sinks.len(), self.observers.len())
+    }
+}
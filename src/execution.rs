@@ -1,6 +1,6 @@
 //! Execution reports and trades (charter data models).
 //!
-//! [`ExecutionReport`] is emitted for every order state change (New, PartialFill, Fill, Canceled).
+//! [`ExecutionReport`] is emitted for every order state change (New, PartialFill, Fill, Canceled, Replaced).
 //! [`Trade`] is emitted for each match between a buy and a sell.
 
 use crate::types::{ExecType, ExecutionId, OrderId, OrderStatus};
@@ -33,6 +33,12 @@ pub struct ExecutionReport {
     #[serde(default, serialize_with = "serialize_option_decimal")]
     pub last_px: Option<Decimal>,
     pub timestamp: u64,
+    /// Original order id on a [`ExecType::Replaced`] report (`order_id` is the replacement).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orig_order_id: Option<OrderId>,
+    /// Engine-wide sequence number, shared with trades and book updates; 0 until an engine assigns it.
+    #[serde(default)]
+    pub seq: u64,
 }
 
 /// Trade (charter).
@@ -46,4 +52,7 @@ pub struct Trade {
     pub quantity: Decimal,
     pub timestamp: u64,
     pub aggressor_side: crate::types::Side,
+    /// Engine-wide sequence number (see [`ExecutionReport::seq`]).
+    #[serde(default)]
+    pub seq: u64,
 }
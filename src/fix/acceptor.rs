@@ -1,162 +1,782 @@
 //! FIX 4.4 TCP acceptor: one listener, one engine; per-connection session with ClOrdID→OrderId mapping.
+//! Sessions must Logon first; the logon is authenticated with [`AuthConfig::fix_logon`]. Logons, logouts, and
+//! order entry are audited with the session's SenderCompID (49) as actor.
 
 use crate::api::MarketState;
+use crate::audit::{AuditAction, AuditContext, AuditEvent, AuditSink, AuditSource};
+use crate::auth::{self, AuthConfig, FixLogon, Permission, Permissions};
 use crate::engine::MatchingEngine;
+use crate::fix::decoder::{FixDecodeError, FixDecoder};
+use crate::fix::market_data::{MarketDataEvent, MarketDataHub, MarketDataSubscription};
+use crate::fix::session_store::{FixSessionState, FixSessionStore, SentMessages};
 use crate::fix::message::{
-    execution_report_to_fix_with_side, order_from_cancel_replace, order_from_new_order_single,
-    parse_fix_message, FixWriter,
+    execution_report_to_fix, execution_report_to_fix_with_orig, fix_symbol, in_version, market_data_incremental_to_fix,
+    market_data_request_reject_to_fix, market_data_snapshot_to_fix, mass_quote_ack_to_fix, order_cancel_reject_to_fix,
+    order_from_cancel_replace, order_from_new_order_single, order_mass_cancel_report_to_fix, parse_fix_message,
+    possible_duplicate, quote_from_mass_quote, transact_time, BusinessRejectReason, CxlRejReason, FixMessage, FixVersion,
+    FixWriter, MassCancelRejectReason, MdReqRejReason, OrderCancelReject, OrderContext, OrderMassCancelReport,
+    SessionRejectReason,
 };
-use crate::types::{OrderId, Side};
+use crate::types::{InstrumentId, OrderId, OrderStatus, Side, TraderId};
 use crate::MultiEngine;
 use log::warn;
-use std::collections::HashMap;
-use std::io::{Read, Write};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{ErrorKind, Read, Write};
 use std::sync::Mutex;
-use std::time::Duration;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+/// CompIDs of outbound messages until the client's first message names the session.
 const SENDER_COMP_ID: &str = "DIRED";
 const TARGET_COMP_ID: &str = "CLIENT";
 
 /// Run the FIX acceptor on `listener`. Each connection gets a session that shares `engine`.
 /// When `market_state` is not Open, NewOrderSingle and CancelReplaceRequest are rejected (FIX reject).
-/// Orders carry their own instrument_id; the engine may have multiple instruments.
+/// Orders carry their own instrument_id; the engine may have multiple instruments. Logons are authenticated
+/// with [`AuthConfig::from_env`] and audited to stdout.
 pub fn run_fix_acceptor(
     listener: std::net::TcpListener,
     engine: std::sync::Arc<Mutex<MultiEngine>>,
     market_state: std::sync::Arc<Mutex<MarketState>>,
 ) {
+    run_fix_acceptor_with_auth(listener, engine, market_state, None, AuthConfig::from_env());
+}
+
+/// Like [`run_fix_acceptor`], with every connection wrapped in TLS (see [`crate::tls`]). Clients that fail the
+/// handshake are dropped.
+pub fn run_fix_acceptor_tls(
+    listener: std::net::TcpListener,
+    engine: std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: std::sync::Arc<Mutex<MarketState>>,
+    tls: std::sync::Arc<rustls::ServerConfig>,
+) {
+    run_fix_acceptor_with_auth(listener, engine, market_state, Some(tls), AuthConfig::from_env());
+}
+
+/// Like [`run_fix_acceptor`] with optional TLS and explicit auth config (e.g. tests pass a fixed config to
+/// avoid env races). Sessions are kept in memory.
+pub fn run_fix_acceptor_with_auth(
+    listener: std::net::TcpListener,
+    engine: std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: std::sync::Arc<Mutex<MarketState>>,
+    tls: Option<std::sync::Arc<rustls::ServerConfig>>,
+    auth: AuthConfig,
+) {
+    let sessions = std::sync::Arc::new(FixSessionStore::in_memory());
+    let audit = crate::api::default_audit_sink();
+    run_fix_acceptor_with_sessions(listener, engine, market_state, tls, auth, sessions, audit);
+}
+
+/// Like [`run_fix_acceptor_with_auth`], resuming sessions from `sessions` (e.g. a [`FixSessionStore::open`]
+/// file, so they survive restarts) and auditing to `audit` (e.g. the REST API's, [`crate::api::AppState::audit_sink`]).
+pub fn run_fix_acceptor_with_sessions(
+    listener: std::net::TcpListener,
+    engine: std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: std::sync::Arc<Mutex<MarketState>>,
+    tls: Option<std::sync::Arc<rustls::ServerConfig>>,
+    auth: AuthConfig,
+    sessions: std::sync::Arc<FixSessionStore>,
+    audit: std::sync::Arc<dyn AuditSink + Send + Sync>,
+) {
+    let market_data = std::sync::Arc::new(MarketDataHub::default());
+    {
+        let mut guard = engine.lock().expect("lock");
+        guard.add_event_sink(market_data.clone());
+        guard.add_book_observer(market_data.clone());
+    }
     for stream in listener.incoming().flatten() {
         let engine = std::sync::Arc::clone(&engine);
+        let market_data = std::sync::Arc::clone(&market_data);
+        let sessions = std::sync::Arc::clone(&sessions);
         let market_state = std::sync::Arc::clone(&market_state);
+        let tls = tls.clone();
+        let auth = auth.clone();
+        let audit = std::sync::Arc::clone(&audit);
         std::thread::spawn(move || {
-            if let Err(e) = handle_fix_connection(stream, engine, market_state) {
+            let mut session = Session::new(market_data, sessions, audit);
+            session.peer = stream.peer_addr().ok().map(|addr| addr.ip());
+            // Several reports often answer one order; without this each after the first waits on the
+            // client's delayed ACK.
+            let _ = stream.set_nodelay(true);
+            let result = set_timeouts(&stream).and_then(|()| match tls {
+                Some(config) => {
+                    let conn = rustls::ServerConnection::new(config).map_err(|e| e.to_string())?;
+                    handle_fix_connection(rustls::StreamOwned::new(conn, stream), session, engine, market_state, &auth)
+                }
+                None => handle_fix_connection(stream, session, engine, market_state, &auth),
+            });
+            if let Err(e) = result {
                 warn!("FIX connection error: {}", e);
             }
         });
     }
 }
 
+/// How often an idle session wakes up to send market data.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// A session that sends nothing for this long is dropped.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn set_timeouts(stream: &std::net::TcpStream) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_write_timeout(Some(Duration::from_secs(10)))
+        .map_err(|e| e.to_string())
+}
+
+/// Sent messages kept for ResendRequest (2); older ones are answered with a gap fill.
+const RESEND_STORE_CAPACITY: usize = 10_000;
+/// Inbound messages held behind a sequence gap; a session that gets further ahead is logged out.
+const MAX_QUEUED_INBOUND: usize = 1_000;
+
+/// MsgSeqNum (34), SenderCompID (49), and TargetCompID (56) of an outbound message.
+type Header<'a> = (u32, &'a str, &'a str);
+
 struct Session {
     cl_ord_to_order_id: HashMap<String, OrderId>,
-    cl_ord_to_side: HashMap<String, Side>,
-    next_order_id: u64,
+    /// Order attributes by ClOrdID, echoed on execution reports.
+    cl_ord_to_order: HashMap<String, OrderContext>,
     out_seq: u32,
+    /// Set by an authenticated Logon; nothing else is accepted before it.
+    logged_on: bool,
+    /// Protocol version the Logon negotiated; outbound messages are framed for it.
+    version: FixVersion,
+    /// Trader the logon bound the session to: orders and quotes are entered for it, whatever their Account (1).
+    trader: Option<TraderId>,
+    /// Trader binding of the stored session [`Session::resume`] picked up, if there was one: the Logon must
+    /// have the same.
+    owner: Option<Option<TraderId>>,
+    /// What the logon's key allows: order entry and market data each need their [`Permission`].
+    permissions: Permissions,
+    /// Instruments the logon's key is entitled to; `None` for every instrument.
+    instruments: Option<Vec<InstrumentId>>,
+    /// Client address of the connection, checked against the key's and SenderCompID's allowlists at Logon.
+    peer: Option<std::net::IpAddr>,
+    /// MsgSeqNum (34) expected on the next inbound message.
+    in_seq: u32,
+    /// Inbound messages that arrived ahead of `in_seq`, by MsgSeqNum; `None` for ones handled on arrival
+    /// (Logon, ResendRequest, Logout) that only fill their number.
+    queued: BTreeMap<u32, Option<FixMessage>>,
+    /// A ResendRequest for the current gap is outstanding.
+    resend_requested: bool,
+    /// Sent messages by MsgSeqNum: MsgType (35) and the bytes as written.
+    sent: SentMessages,
+    market_data: std::sync::Arc<MarketDataHub>,
+    sessions: std::sync::Arc<FixSessionStore>,
+    /// Session key (`SenderCompID:TargetCompID`) claimed by the first Logon.
+    key: Option<String>,
+    /// SenderCompID (49) of the first Logon: the actor of the session's audit events.
+    comp_id: String,
+    /// SenderCompID (49) and TargetCompID (56) of outbound messages: the client's TargetCompID and
+    /// SenderCompID, swapped (see [`Session::reply_to`]).
+    sender_comp_id: String,
+    target_comp_id: String,
+    audit: std::sync::Arc<dyn AuditSink + Send + Sync>,
+    /// MsgSeqNum (34) of the message being handled; with the session key, the request id of its audit events.
+    msg_seq: Option<u32>,
+    /// [`Session::progress`] as of the last checkpoint.
+    checkpointed: (u32, u32, usize),
+    /// Market data subscriptions (MarketDataRequest 263=1) and, once there is one, the hub's feed.
+    md_subscriptions: Vec<MarketDataSubscription>,
+    md_events: Option<Receiver<MarketDataEvent>>,
 }
 
 impl Session {
-    fn new() -> Self {
+    fn new(
+        market_data: std::sync::Arc<MarketDataHub>,
+        sessions: std::sync::Arc<FixSessionStore>,
+        audit: std::sync::Arc<dyn AuditSink + Send + Sync>,
+    ) -> Self {
         Self {
             cl_ord_to_order_id: HashMap::new(),
-            cl_ord_to_side: HashMap::new(),
-            next_order_id: 1,
+            cl_ord_to_order: HashMap::new(),
             out_seq: 1,
+            logged_on: false,
+            version: FixVersion::Fix44,
+            trader: None,
+            owner: None,
+            permissions: Permissions::default(),
+            instruments: None,
+            peer: None,
+            in_seq: 1,
+            queued: BTreeMap::new(),
+            resend_requested: false,
+            sent: BTreeMap::new(),
+            market_data,
+            sessions,
+            key: None,
+            comp_id: String::new(),
+            sender_comp_id: SENDER_COMP_ID.to_string(),
+            target_comp_id: TARGET_COMP_ID.to_string(),
+            audit,
+            msg_seq: None,
+            checkpointed: (1, 1, 0),
+            md_subscriptions: Vec::new(),
+            md_events: None,
+        }
+    }
+    /// Claim the session the Logon names (SenderCompID 49, TargetCompID 56) and pick up its stored state. With
+    /// ResetSeqNumFlag (141=Y) only the orders carry over and MsgSeqNums start again at 1. `Err` while another
+    /// connection has the session. Earlier sent messages are taken over once the Logon is accepted, and only
+    /// when it is bound to the stored session's trader (see [`Session::check_owner`]).
+    fn resume(&mut self, logon: &FixMessage) -> Result<(), String> {
+        let comp_id = |tag: u32| logon.get(&tag).map_or("", String::as_str);
+        let key = format!("{}:{}", comp_id(49), comp_id(56));
+        if !self.sessions.claim(&key) {
+            return Err(format!("session {} is already logged on", key));
+        }
+        let stored = self.sessions.get(&key);
+        self.owner = stored.as_ref().map(|state| state.trader_id);
+        let state = stored.unwrap_or_default();
+        self.cl_ord_to_order_id = state.cl_ord_to_order_id;
+        self.cl_ord_to_order = state.cl_ord_to_order;
+        if logon.get(&141).map(String::as_str) != Some("Y") {
+            self.in_seq = state.in_seq;
+            self.out_seq = state.out_seq;
         }
+        self.key = Some(key);
+        self.comp_id = comp_id(49).to_string();
+        self.checkpointed = self.progress();
+        Ok(())
+    }
+    /// Address outbound messages back to the sender of `msg`: its TargetCompID (56) becomes our SenderCompID
+    /// (49) and its SenderCompID our TargetCompID. A CompID it leaves out keeps the current one.
+    fn reply_to(&mut self, msg: &FixMessage) {
+        if let Some(target) = msg.get(&56).filter(|id| !id.is_empty()) {
+            self.sender_comp_id = target.clone();
+        }
+        if let Some(sender) = msg.get(&49).filter(|id| !id.is_empty()) {
+            self.target_comp_id = sender.clone();
+        }
+    }
+    /// `Err` when the session was stored for another trader than `logon` binds it to: its orders are not
+    /// this logon's to cancel or replace.
+    fn check_owner(&self, logon: &FixLogon) -> Result<(), String> {
+        match self.owner {
+            Some(owner) if owner != logon.trader_id => {
+                Err(format!("session {} is bound to another trader", self.key.as_deref().unwrap_or_default()))
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Whether the session may cancel or replace an order of `trader_id`, as REST's
+    /// [`auth::require_order_owner`] decides: a bound session only its trader's, unless it has
+    /// [`Permission::CancelAny`].
+    fn may_manage(&self, trader_id: TraderId) -> bool {
+        self.trader.is_none_or(|trader| trader == trader_id) || self.permissions.contains(Permission::CancelAny)
+    }
+    /// Changes whenever the stored state would: sequence numbers, ClOrdIDs.
+    fn progress(&self) -> (u32, u32, usize) {
+        (self.in_seq, self.out_seq, self.cl_ord_to_order_id.len())
+    }
+    /// Store the session's state if it changed since the last checkpoint. Nothing is stored before the Logon
+    /// is accepted.
+    fn checkpoint(&mut self) {
+        let Some(key) = self.key.as_deref().filter(|_| self.logged_on) else {
+            return;
+        };
+        let progress = self.progress();
+        if progress == self.checkpointed {
+            return;
+        }
+        let state = FixSessionState {
+            in_seq: self.in_seq,
+            out_seq: self.out_seq,
+            cl_ord_to_order_id: self.cl_ord_to_order_id.clone(),
+            cl_ord_to_order: self.cl_ord_to_order.clone(),
+            trader_id: self.trader,
+        };
+        if let Err(e) = self.sessions.put(key, state) {
+            warn!("FIX session {} not saved: {}", key, e);
+        }
+        self.checkpointed = progress;
+    }
+    /// Audit `action` on `resource` by the session's SenderCompID, from the connection's address, with
+    /// `SENDER:TARGET:MsgSeqNum` of the message being handled as the request id.
+    fn audit(&self, action: AuditAction, resource: serde_json::Value, outcome: &str) {
+        let context = AuditContext {
+            source: AuditSource::Fix,
+            request_id: self.key.as_ref().zip(self.msg_seq).map(|(key, seq)| format!("{}:{}", key, seq)),
+            client_ip: self.peer,
+        };
+        let event = AuditEvent::now(self.comp_id.clone(), action, Some(resource), outcome);
+        self.audit.emit(&event.with_context(&context));
+    }
+    /// End of the connection: store the state and free the session for the next Logon. A logged-on session
+    /// is audited as `fix_logout`, whether or not the client sent a Logout.
+    fn close(mut self) {
+        self.checkpoint();
+        if self.logged_on {
+            self.audit(AuditAction::FixLogout, serde_json::json!({ "session": self.key }), "success");
+        }
+        if let Some(key) = &self.key {
+            let sent = self.logged_on.then(|| std::mem::take(&mut self.sent));
+            self.sessions.release(key, sent);
+        }
+    }
+    /// ClOrdID and attributes of the session's order `order_id`.
+    fn order(&self, order_id: OrderId) -> Option<(String, OrderContext)> {
+        let (cl_ord_id, _) = self.cl_ord_to_order_id.iter().find(|(_, &id)| id == order_id)?;
+        let context = self.cl_ord_to_order.get(cl_ord_id)?.clone();
+        Some((cl_ord_id.clone(), context))
     }
     fn next_seq(&mut self) -> u32 {
         let s = self.out_seq;
         self.out_seq += 1;
         s
     }
+    /// Header of the message numbered `seq`.
+    fn header(&self, seq: u32) -> Header<'_> {
+        (seq, &self.sender_comp_id, &self.target_comp_id)
+    }
+    /// Header of the next message, numbered by [`Session::next_seq`].
+    fn next_header(&mut self) -> Header<'_> {
+        let seq = self.next_seq();
+        self.header(seq)
+    }
+    /// Write `out` (numbered by [`Session::next_seq`]) and keep it for resends.
+    fn send(&mut self, stream: &mut impl Write, out: Vec<u8>) -> Result<(), String> {
+        let out = match self.version {
+            FixVersion::Fix44 => out,
+            version => in_version(&out, version),
+        };
+        stream.write_all(&out).map_err(|e| e.to_string())?;
+        if let Some((msg, _)) = parse_fix_message(&out) {
+            if let (Some(seq), Some(msg_type)) = (msg.get(&34).and_then(|s| s.parse().ok()), msg.get(&35)) {
+                self.sent.insert(seq, (msg_type.clone(), out));
+                while self.sent.len() > RESEND_STORE_CAPACITY {
+                    self.sent.pop_first();
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 fn handle_fix_connection(
-    mut stream: std::net::TcpStream,
+    mut stream: impl Read + Write,
+    mut session: Session,
     engine: std::sync::Arc<Mutex<MultiEngine>>,
     market_state: std::sync::Arc<Mutex<MarketState>>,
+    auth: &AuthConfig,
 ) -> Result<(), String> {
-    stream
-        .set_read_timeout(Some(Duration::from_secs(30)))
-        .map_err(|e| e.to_string())?;
-    stream
-        .set_write_timeout(Some(Duration::from_secs(10)))
-        .map_err(|e| e.to_string())?;
+    let result = run_session(&mut stream, &mut session, &engine, &market_state, auth);
+    session.close();
+    result
+}
 
-    let mut session = Session::new();
-    let mut buf = vec![0u8; 4096];
-    let mut read_pos = 0;
+/// Read and handle messages until the session logs out, fails, or the peer disconnects.
+fn run_session(
+    stream: &mut (impl Read + Write),
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: &std::sync::Mutex<MarketState>,
+    auth: &AuthConfig,
+) -> Result<(), String> {
+    let mut decoder = FixDecoder::new();
+    let mut chunk = [0u8; 4096];
+    let mut last_read = Instant::now();
 
     loop {
-        if read_pos >= buf.len() {
-            buf.resize(buf.len() * 2, 0);
+        publish_market_data(stream, session, engine)?;
+        session.checkpoint();
+        let msg = match decoder.next_message() {
+            Some(Ok(msg)) => msg,
+            Some(Err(FixDecodeError::Malformed(fields, error))) if session.logged_on => {
+                // Intact but unparsable: Rejected, and it takes its number.
+                let out = session_reject(&fields, error.tag, error.reason, &error.text, session.next_header());
+                session.send(stream, out)?;
+                if fields.get(&34).and_then(|s| s.parse::<u32>().ok()) == Some(session.in_seq) {
+                    session.in_seq += 1;
+                }
+                continue;
+            }
+            Some(Err(e)) => {
+                warn!("FIX garbled input dropped: {}", e);
+                continue;
+            }
+            None => {
+                match stream.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        decoder.extend(&chunk[..n]);
+                        last_read = Instant::now();
+                    }
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                        if last_read.elapsed() >= IDLE_TIMEOUT {
+                            return Err(format!("no message for {}s", IDLE_TIMEOUT.as_secs()));
+                        }
+                    }
+                    Err(e) => return Err(e.to_string()),
+                }
+                continue;
+            }
+        };
+        let msg_type = msg.get(&35).cloned().unwrap_or_default();
+        if session.key.is_none() {
+            // Until a Logon claims the session, answer whoever sent the message.
+            session.reply_to(&msg);
         }
-        let n = stream.read(&mut buf[read_pos..]).map_err(|e| e.to_string())?;
-        if n == 0 {
+        if !session.logged_on && msg_type != "A" {
+            let out = logout(session.next_header(), "first message must be Logon (A)");
+            session.send(stream, out)?;
             break;
         }
-        read_pos += n;
-
-        let (msg, consumed) = match parse_fix_message(&buf[..read_pos]) {
-            Some(m) => m,
-            None => continue,
-        };
-        read_pos -= consumed;
-        buf.copy_within(consumed.., 0);
-
-        let msg_type = msg.get(&35).ok_or_else(|| "missing MsgType 35".to_string())?.as_str();
-        match msg_type {
-            "A" => {
-                send_logon(&mut stream, session.next_seq())?;
-            }
-            "5" => {
-                send_logout(&mut stream, session.next_seq())?;
+        if !session.logged_on && session.key.is_none() {
+            let resumed = FixVersion::negotiate(&msg).and_then(|version| {
+                session.version = version;
+                session.resume(&msg)
+            });
+            if let Err(e) = resumed {
+                let out = logout(session.next_header(), &e);
+                session.send(stream, out)?;
                 break;
             }
-            "0" => {
-                send_heartbeat(&mut stream, session.next_seq())?;
+        }
+        let begin_string = msg.get(&8).map_or("", String::as_str);
+        if begin_string != session.version.begin_string() {
+            let text = format!("BeginString (8) {} does not match the session's {}", begin_string, session.version.begin_string());
+            let out = logout(session.next_header(), &text);
+            session.send(stream, out)?;
+            break;
+        }
+        let Some(seq) = msg.get(&34).and_then(|s| s.parse::<u32>().ok()) else {
+            let out = logout(session.next_header(), "MsgSeqNum (34) missing");
+            session.send(stream, out)?;
+            break;
+        };
+        if msg_type.is_empty() {
+            // Rejected, but it still takes its number.
+            let reason = SessionRejectReason::RequiredTagMissing;
+            let out = session_reject(&msg, Some(35), reason, "MsgType (35) missing", session.next_header());
+            session.send(stream, out)?;
+            if seq == session.in_seq {
+                session.in_seq += 1;
+            }
+            continue;
+        }
+        if msg_type == "4" && msg.get(&123).map(String::as_str) != Some("Y") {
+            // SequenceReset in reset mode: MsgSeqNum is ignored.
+            handle_sequence_reset(stream, &msg, session)?;
+        } else {
+            match seq.cmp(&session.in_seq) {
+                std::cmp::Ordering::Less => {
+                    if msg.get(&43).map(String::as_str) == Some("Y") {
+                        continue;
+                    }
+                    let text = format!("MsgSeqNum too low, expecting {} but received {}", session.in_seq, seq);
+                    let out = logout(session.next_header(), &text);
+                    session.send(stream, out)?;
+                    break;
+                }
+                std::cmp::Ordering::Greater => {
+                    if matches!(msg_type.as_str(), "A" | "2" | "5") {
+                        if !handle_message(stream, &msg, session, engine, market_state, auth)? {
+                            break;
+                        }
+                        session.queued.insert(seq, None);
+                    } else {
+                        session.queued.insert(seq, Some(msg));
+                    }
+                    if session.queued.len() > MAX_QUEUED_INBOUND {
+                        let out = logout(session.next_header(), "too many messages behind a MsgSeqNum gap");
+                        session.send(stream, out)?;
+                        break;
+                    }
+                    if !session.resend_requested {
+                        let from = session.in_seq.to_string();
+                        let out = session_message("2", session.next_header(), &[(7, from.as_str()), (16, "0")]);
+                        session.send(stream, out)?;
+                        session.resend_requested = true;
+                    }
+                    continue;
+                }
+                std::cmp::Ordering::Equal => {
+                    session.in_seq += 1;
+                    if !handle_message(stream, &msg, session, engine, market_state, auth)? {
+                        break;
+                    }
+                }
             }
-            "D" => {
-                handle_new_order_single(&mut stream, &msg, &mut session, &engine, &*market_state)?;
+        }
+        if !drain_queued(stream, session, engine, market_state, auth)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Handle queued messages that are now in sequence. `false` once the session logged out.
+fn drain_queued(
+    stream: &mut impl Write,
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: &std::sync::Mutex<MarketState>,
+    auth: &AuthConfig,
+) -> Result<bool, String> {
+    loop {
+        // Numbers below `in_seq` were resent or gap filled meanwhile.
+        while session.queued.first_key_value().is_some_and(|(&seq, _)| seq < session.in_seq) {
+            session.queued.pop_first();
+        }
+        let Some(next) = session.queued.remove(&session.in_seq) else {
+            break;
+        };
+        session.in_seq += 1;
+        if let Some(msg) = next {
+            if !handle_message(stream, &msg, session, engine, market_state, auth)? {
+                return Ok(false);
             }
-            "F" => {
-                handle_order_cancel_request(&mut stream, &msg, &mut session, &engine)?;
+        }
+    }
+    if session.queued.is_empty() {
+        session.resend_requested = false;
+    }
+    Ok(true)
+}
+
+/// Dispatch one in-sequence message by MsgType (35). `false` after a Logout.
+fn handle_message(
+    stream: &mut impl Write,
+    msg: &FixMessage,
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: &std::sync::Mutex<MarketState>,
+    auth: &AuthConfig,
+) -> Result<bool, String> {
+    let msg_type = msg.get(&35).map(String::as_str).unwrap_or_default();
+    session.msg_seq = msg.get(&34).and_then(|seq| seq.parse().ok());
+    if let (Some(appl_ver_id), Some(expected)) = (msg.get(&1128), session.version.appl_ver_id()) {
+        if appl_ver_id != expected {
+            let text = format!("unsupported ApplVerID (1128) {}", appl_ver_id);
+            let out = session_reject(msg, Some(1128), SessionRejectReason::UnsupportedApplVerId, &text, session.next_header());
+            session.send(stream, out)?;
+            return Ok(true);
+        }
+    }
+    match msg_type {
+        "A" => {
+            if session.logged_on {
+                warn!("FIX Logon on a logged-on session ignored");
+                return Ok(true);
             }
-            "G" => {
-                handle_order_cancel_replace_request(&mut stream, &msg, &mut session, &engine, &*market_state)?;
+            let field = |tag: u32| msg.get(&tag).map(String::as_str);
+            match auth.fix_logon(field(49), field(553), field(554), session.peer).and_then(|logon| {
+                session.check_owner(&logon)?;
+                Ok(logon)
+            }) {
+                Ok(logon) => {
+                    session.logged_on = true;
+                    session.trader = logon.trader_id;
+                    session.permissions = logon.permissions;
+                    session.instruments = logon.instruments;
+                    if let Some(key) = &session.key {
+                        let mut sent = session.sessions.take_sent(key);
+                        if field(141) != Some("Y") {
+                            sent.append(&mut session.sent);
+                            session.sent = sent;
+                        }
+                    }
+                    let mut fields = Vec::new();
+                    if field(141) == Some("Y") {
+                        fields.push((141, "Y"));
+                    }
+                    if let Some(appl_ver_id) = session.version.appl_ver_id() {
+                        fields.push((1137, appl_ver_id));
+                    }
+                    let out = session_message("A", session.next_header(), &fields);
+                    session.send(stream, out)?;
+                    let trader_id = session.trader.map(|trader| trader.0);
+                    session.audit(AuditAction::FixLogon, serde_json::json!({ "session": session.key, "trader_id": trader_id }), "success");
+                }
+                Err(e) => {
+                    warn!("FIX Logon from {} rejected: {}", field(49).unwrap_or("?"), e);
+                    session.audit(AuditAction::FixLogon, serde_json::json!({ "session": session.key, "reason": e }), "rejected");
+                    let out = logout(session.next_header(), &e);
+                    session.send(stream, out)?;
+                    return Ok(false);
+                }
             }
-            _ => {
-                warn!("FIX unknown MsgType: {}", msg_type);
+        }
+        "5" => {
+            let out = session_message("5", session.next_header(), &[]);
+            session.send(stream, out)?;
+            return Ok(false);
+        }
+        "0" => {
+            let out = session_message("0", session.next_header(), &[]);
+            session.send(stream, out)?;
+        }
+        "1" => {
+            let test_req_id = msg.get(&112).cloned().unwrap_or_default();
+            let out = session_message("0", session.next_header(), &[(112, test_req_id.as_str())]);
+            session.send(stream, out)?;
+        }
+        "2" => {
+            handle_resend_request(stream, msg, session)?;
+        }
+        "4" => {
+            // SequenceReset-GapFill: nothing below NewSeqNo (36) will be resent.
+            if let Some(new_seq) = msg.get(&36).and_then(|s| s.parse::<u32>().ok()) {
+                session.in_seq = session.in_seq.max(new_seq);
             }
         }
+        "D" | "V" | "F" | "G" | "i" | "q" if !session.permissions.contains(required_permission(msg_type)) => {
+            let text = format!("permission {} required", required_permission(msg_type).as_str());
+            let out = business_reject(msg, BusinessRejectReason::NotAuthorized, &text, session.next_header());
+            session.send(stream, out)?;
+        }
+        "D" => {
+            handle_new_order_single(stream, msg, session, engine, market_state)?;
+        }
+        "V" => {
+            handle_market_data_request(stream, msg, session, engine)?;
+        }
+        "F" => {
+            handle_order_cancel_request(stream, msg, session, engine)?;
+        }
+        "G" => {
+            handle_order_cancel_replace_request(stream, msg, session, engine, market_state)?;
+        }
+        "i" => {
+            handle_mass_quote(stream, msg, session, engine, market_state)?;
+        }
+        "q" => {
+            handle_order_mass_cancel_request(stream, msg, session, engine)?;
+        }
+        _ => {
+            warn!("FIX unsupported MsgType: {}", msg_type);
+            let text = format!("unsupported MsgType {}", msg_type);
+            let out = business_reject(msg, BusinessRejectReason::UnsupportedMessageType, &text, session.next_header());
+            session.send(stream, out)?;
+        }
     }
-    Ok(())
+    Ok(true)
 }
 
-fn send_logon(stream: &mut std::net::TcpStream, seq: u32) -> Result<(), String> {
-    let mut w = FixWriter::new();
-    w.set(35, "A");
-    w.set(34, seq.to_string());
-    w.set(49, SENDER_COMP_ID);
-    w.set(52, fix_timestamp_now());
-    w.set(56, TARGET_COMP_ID);
-    let mut out = Vec::new();
-    w.write(&mut out).map_err(|e| e.to_string())?;
-    stream.write_all(&out).map_err(|e| e.to_string())?;
-    Ok(())
+/// SequenceReset (4) in reset mode: the next inbound MsgSeqNum becomes NewSeqNo (36). Lowering it is refused
+/// with a session Reject (3).
+fn handle_sequence_reset(stream: &mut impl Write, fix: &FixMessage, session: &mut Session) -> Result<(), String> {
+    let text = match fix.get(&36).and_then(|s| s.parse::<u32>().ok()) {
+        Some(new_seq) if new_seq >= session.in_seq => {
+            session.in_seq = new_seq;
+            return Ok(());
+        }
+        Some(new_seq) => format!("NewSeqNo {} is below the expected MsgSeqNum {}", new_seq, session.in_seq),
+        None => "NewSeqNo (36) missing".to_string(),
+    };
+    let out = session_reject(fix, Some(36), SessionRejectReason::ValueIsIncorrect, &text, session.next_header());
+    session.send(stream, out)
 }
 
-fn send_logout(stream: &mut std::net::TcpStream, seq: u32) -> Result<(), String> {
-    let mut w = FixWriter::new();
-    w.set(35, "5");
-    w.set(34, seq.to_string());
-    w.set(49, SENDER_COMP_ID);
-    w.set(52, fix_timestamp_now());
-    w.set(56, TARGET_COMP_ID);
+/// ResendRequest (2) from BeginSeqNo (7) to EndSeqNo (16, 0 = latest): application messages are sent again
+/// with PossDupFlag (43=Y); session-level messages and ones no longer kept are covered by SequenceReset-GapFill.
+fn handle_resend_request(stream: &mut impl Write, fix: &FixMessage, session: &mut Session) -> Result<(), String> {
+    let last = session.out_seq - 1;
+    let begin = fix.get(&7).and_then(|s| s.parse::<u32>().ok()).unwrap_or(1).max(1);
+    let end = match fix.get(&16).and_then(|s| s.parse::<u32>().ok()) {
+        Some(0) | None => last,
+        Some(end) => end.min(last),
+    };
+    if begin > end {
+        return Ok(());
+    }
+    let now = fix_timestamp_now();
     let mut out = Vec::new();
-    w.write(&mut out).map_err(|e| e.to_string())?;
-    stream.write_all(&out).map_err(|e| e.to_string())?;
-    Ok(())
+    let mut next = begin;
+    for (&seq, (msg_type, raw)) in session.sent.range(begin..=end) {
+        if matches!(msg_type.as_str(), "A" | "0" | "1" | "2" | "4" | "5") {
+            continue;
+        }
+        if seq > next {
+            out.extend(in_version(&gap_fill(session.header(next), seq, &now), session.version));
+        }
+        out.extend(in_version(&possible_duplicate(raw, &now), session.version));
+        next = seq + 1;
+    }
+    if next <= end {
+        out.extend(in_version(&gap_fill(session.header(next), end + 1, &now), session.version));
+    }
+    stream.write_all(&out).map_err(|e| e.to_string())
+}
+
+/// SequenceReset-GapFill (4, 123=Y) with `header` that skips the peer ahead to `new_seq`.
+fn gap_fill(header: Header, new_seq: u32, now: &str) -> Vec<u8> {
+    let new_seq = new_seq.to_string();
+    session_message("4", header, &[(43, "Y"), (122, now), (123, "Y"), (36, new_seq.as_str())])
 }
 
-fn send_heartbeat(stream: &mut std::net::TcpStream, seq: u32) -> Result<(), String> {
+fn logout(header: Header, text: &str) -> Vec<u8> {
+    session_message("5", header, &[(58, text)])
+}
+
+/// Session-level message of `msg_type` with `fields` after the standard `header`.
+fn session_message(msg_type: &str, (seq, sender, target): Header, fields: &[(u32, &str)]) -> Vec<u8> {
     let mut w = FixWriter::new();
-    w.set(35, "0");
+    w.set(35, msg_type);
     w.set(34, seq.to_string());
-    w.set(49, SENDER_COMP_ID);
+    w.set(49, sender);
     w.set(52, fix_timestamp_now());
-    w.set(56, TARGET_COMP_ID);
+    w.set(56, target);
+    for (tag, value) in fields {
+        w.set(*tag, *value);
+    }
     let mut out = Vec::new();
-    w.write(&mut out).map_err(|e| e.to_string())?;
-    stream.write_all(&out).map_err(|e| e.to_string())?;
-    Ok(())
+    let _ = w.write(&mut out);
+    out
+}
+
+/// Session-level Reject (3) of `fix`: RefSeqNum (45), RefTagID (371) when one field is at fault, RefMsgType
+/// (372), SessionRejectReason (373), Text (58).
+fn session_reject(fix: &FixMessage, ref_tag: Option<u32>, reason: SessionRejectReason, text: &str, header: Header) -> Vec<u8> {
+    let ref_seq = fix.get(&34).map_or("0", String::as_str);
+    let ref_tag = ref_tag.map(|tag| tag.to_string());
+    let reason = (reason as u8).to_string();
+    let mut fields = vec![(45, ref_seq)];
+    if let Some(ref_tag) = &ref_tag {
+        fields.push((371, ref_tag.as_str()));
+    }
+    if let Some(msg_type) = fix.get(&35) {
+        fields.push((372, msg_type.as_str()));
+    }
+    fields.extend([(373, reason.as_str()), (58, text)]);
+    session_message("3", header, &fields)
+}
+
+/// Session Reject (3) for an error from the message conversions, which name the field as `Name (tag)`:
+/// RequiredTagMissing when it is missing, ValueIsIncorrect otherwise.
+fn field_reject(fix: &FixMessage, error: &str, header: Header) -> Vec<u8> {
+    let tag = error
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .and_then(|(tag, _)| tag.parse().ok());
+    let reason = match tag {
+        Some(_) if error.starts_with("missing") => SessionRejectReason::RequiredTagMissing,
+        Some(_) => SessionRejectReason::ValueIsIncorrect,
+        None => SessionRejectReason::Other,
+    };
+    session_reject(fix, tag, reason, error, header)
+}
+
+/// Permission an application message needs: market data for MarketDataRequest (V), order entry for the rest.
+fn required_permission(msg_type: &str) -> Permission {
+    match msg_type {
+        "V" => Permission::ViewMarketData,
+        _ => Permission::SubmitOrders,
+    }
+}
+
+/// BusinessMessageReject (j) of an application message `fix` that is well formed but not accepted: RefSeqNum
+/// (45), RefMsgType (372), BusinessRejectRefID (379, the ClOrdID if any), BusinessRejectReason (380), Text (58).
+fn business_reject(fix: &FixMessage, reason: BusinessRejectReason, text: &str, header: Header) -> Vec<u8> {
+    let reason = (reason as u8).to_string();
+    let mut fields = vec![(45, fix.get(&34).map_or("0", String::as_str)), (372, fix.get(&35).map_or("", String::as_str))];
+    if let Some(cl_ord_id) = fix.get(&11) {
+        fields.push((379, cl_ord_id.as_str()));
+    }
+    fields.extend([(380, reason.as_str()), (58, text)]);
+    session_message("j", header, &fields)
 }
 
 fn fix_timestamp_now() -> String {
@@ -186,157 +806,529 @@ mod message {
     }
 }
 
+/// Trader to enter an order or quote for: the session's bound trader (an Account (1) naming another is an
+/// error), or `None` to keep the message's own.
+fn bound_trader(fix: &FixMessage, session: &Session) -> Result<Option<TraderId>, String> {
+    let Some(trader) = session.trader else {
+        return Ok(None);
+    };
+    match fix.get(&1) {
+        Some(account) if account.parse::<u64>().ok() != Some(trader.0) => Err(format!(
+            "Account (1) {} does not match the session's trader {}",
+            account, trader.0
+        )),
+        _ => Ok(Some(trader)),
+    }
+}
+
+/// Instrument of the message's Symbol (55) or SecurityID (48): a registered symbol or an instrument id. `Err` too
+/// when the session is not entitled to it.
+fn instrument(fix: &FixMessage, session: &Session, engine: &Mutex<MultiEngine>) -> Result<InstrumentId, String> {
+    let symbol = fix_symbol(fix).ok_or("missing Symbol (55)")?;
+    let instrument_id = engine
+        .lock()
+        .expect("lock")
+        .instrument_by_symbol(symbol)
+        .ok_or_else(|| format!("unknown Symbol (55) {}", symbol))?;
+    if !auth::entitled(&session.instruments, instrument_id) {
+        return Err(format!("not entitled to Symbol (55) {}", symbol));
+    }
+    Ok(instrument_id)
+}
+
 fn handle_new_order_single(
-    stream: &mut std::net::TcpStream,
+    stream: &mut impl Write,
     fix: &crate::fix::message::FixMessage,
     session: &mut Session,
     engine: &std::sync::Arc<Mutex<MultiEngine>>,
     market_state: &std::sync::Mutex<MarketState>,
 ) -> Result<(), String> {
     if *market_state.lock().expect("lock") != MarketState::Open {
-        let cl_ord_id = fix.get(&11).cloned().unwrap_or_else(|| "?".to_string());
-        send_rejection(stream, &cl_ord_id, "market not open", session.next_seq())?;
+        let out = rejection(fix, None, "market not open", session.next_header());
+        session.send(stream, out)?;
         return Ok(());
     }
-    let order = order_from_new_order_single(fix)?;
+    let instrument_id = match instrument(fix, session, engine) {
+        Ok(instrument_id) => instrument_id,
+        Err(e) => {
+            let out = rejection(fix, None, &e, session.next_header());
+            return session.send(stream, out);
+        }
+    };
+    let mut order = match order_from_new_order_single(fix, instrument_id) {
+        Ok(order) => order,
+        Err(e) => {
+            let out = field_reject(fix, &e, session.next_header());
+            return session.send(stream, out);
+        }
+    };
     let cl_ord_id = order.client_order_id.clone();
-    let side = order.side;
+    if session.cl_ord_to_order_id.contains_key(&cl_ord_id) {
+        let context = OrderContext::of_order(&order, fix_symbol(fix).unwrap_or_default());
+        let out = rejection(fix, Some(&context), "duplicate ClOrdID", session.next_header());
+        session.send(stream, out)?;
+        return Ok(());
+    }
+    let resource = serde_json::json!({
+        "order_id": order.order_id.0,
+        "instrument_id": instrument_id.0,
+        "cl_ord_id": cl_ord_id,
+    });
+    match bound_trader(fix, session) {
+        Ok(trader) => order.trader_id = trader.unwrap_or(order.trader_id),
+        Err(e) => {
+            session.audit(AuditAction::OrderSubmit, resource, "forbidden");
+            let out = rejection(fix, None, &e, session.next_header());
+            session.send(stream, out)?;
+            return Ok(());
+        }
+    }
+    let context = OrderContext::of_order(&order, fix_symbol(fix).unwrap_or_default());
     session.cl_ord_to_order_id.insert(cl_ord_id.clone(), order.order_id);
-    session.cl_ord_to_side.insert(cl_ord_id.clone(), side);
+    session.cl_ord_to_order.insert(cl_ord_id.clone(), context.clone());
 
     let mut guard = engine.lock().expect("lock");
     match guard.submit_order(order) {
         Ok((_trades, reports)) => {
             drop(guard);
+            session.audit(AuditAction::OrderSubmit, resource, "success");
+            // Resting orders the match filled are reported under their own ClOrdID; orders of other sessions
+            // are not reported here.
             for report in &reports {
-                let out = execution_report_to_fix_with_side(
-                    report,
-                    side,
-                    &cl_ord_id,
-                    session.next_seq(),
-                    SENDER_COMP_ID,
-                    TARGET_COMP_ID,
-                );
-                stream.write_all(&out).map_err(|e| e.to_string())?;
+                let Some((cl_ord_id, context)) = session.order(report.order_id) else {
+                    continue;
+                };
+                let (seq, sender, target) = session.next_header();
+                let out = execution_report_to_fix(report, &context, &cl_ord_id, seq, sender, target);
+                session.send(stream, out)?;
             }
         }
         Err(e) => {
             drop(guard);
-            send_rejection(stream, &cl_ord_id, e.as_str(), session.next_seq())?;
+            session.audit(AuditAction::OrderSubmit, resource, "rejected");
+            let out = rejection(fix, Some(&context), e.as_str(), session.next_header());
+            session.send(stream, out)?;
         }
     }
     Ok(())
 }
 
-fn send_rejection(
-    stream: &mut std::net::TcpStream,
-    cl_ord_id: &str,
-    reason: &str,
-    seq: u32,
-) -> Result<(), String> {
+/// Rejected ExecutionReport for the NewOrderSingle `fix`. Echoes `order` when the order was parsed, otherwise
+/// the request's own order fields.
+fn rejection(fix: &FixMessage, order: Option<&OrderContext>, reason: &str, (seq, sender, target): Header) -> Vec<u8> {
     let mut w = FixWriter::new();
     w.set(35, "8");
     w.set(34, seq.to_string());
-    w.set(49, SENDER_COMP_ID);
+    w.set(49, sender);
     w.set(52, fix_timestamp_now());
-    w.set(56, TARGET_COMP_ID);
-    w.set(11, cl_ord_id);
+    w.set(56, target);
+    w.set(11, fix.get(&11).map_or("?", String::as_str));
     w.set(37, "0");
     w.set(17, "0");
-    w.set(38, "0");
+    w.set(38, fix.get(&38).map_or("0", String::as_str));
     w.set(39, "8");
-    w.set(40, "2");
-    w.set(54, "1");
+    match order {
+        Some(order) => order.write_to(&mut w),
+        None => {
+            for (tag, default) in [(1, None), (55, None), (54, Some("1")), (40, Some("2")), (44, None), (59, None)] {
+                if let Some(value) = fix.get(&tag).map(String::as_str).or(default) {
+                    w.set(tag, value);
+                }
+            }
+        }
+    }
     w.set(14, "0");
     w.set(151, "0");
     w.set(150, "8");
+    w.set(60, transact_time());
     w.set(58, reason);
     let mut out = Vec::new();
-    w.write(&mut out).map_err(|e| e.to_string())?;
-    stream.write_all(&out).map_err(|e| e.to_string())?;
-    Ok(())
+    let _ = w.write(&mut out);
+    out
+}
+
+/// OrderCancelReject (9) answering the cancel (F) or cancel/replace (G) request `fix`; `order` is the original
+/// order and its status when the session knows it.
+fn send_cancel_reject(
+    stream: &mut impl Write,
+    session: &mut Session,
+    fix: &FixMessage,
+    order: Option<(OrderId, OrderStatus)>,
+    reason: CxlRejReason,
+    text: &str,
+) -> Result<(), String> {
+    let orig_cl_ord_id = fix.get(&41).cloned().unwrap_or_default();
+    let reject = OrderCancelReject {
+        order_id: order.map(|(id, _)| id),
+        cl_ord_id: fix.get(&11).cloned().unwrap_or_else(|| orig_cl_ord_id.clone()),
+        orig_cl_ord_id,
+        ord_status: order.map_or(OrderStatus::Rejected, |(_, status)| status),
+        replace: fix.get(&35).map(String::as_str) == Some("G"),
+        reason,
+        text: text.to_string(),
+    };
+    let (seq, sender, target) = session.next_header();
+    let out = order_cancel_reject_to_fix(&reject, seq, sender, target);
+    session.send(stream, out)
+}
+
+/// The session's order for OrigClOrdID (41), or the OrderCancelReject reason and text when there is none.
+fn orig_order(fix: &FixMessage, session: &Session) -> Result<OrderId, &'static str> {
+    match fix.get(&41) {
+        None => Err("missing OrigClOrdID (41)"),
+        Some(orig) => session.cl_ord_to_order_id.get(orig).copied().ok_or("unknown OrigClOrdID"),
+    }
+}
+
+/// The original order and its engine status (Rejected once the engine no longer knows it).
+fn order_state(engine: &MultiEngine, order_id: OrderId) -> (OrderId, OrderStatus) {
+    (order_id, engine.order_status(order_id).map_or(OrderStatus::Rejected, |o| o.status))
 }
 
 fn handle_order_cancel_request(
-    stream: &mut std::net::TcpStream,
+    stream: &mut impl Write,
     fix: &crate::fix::message::FixMessage,
     session: &mut Session,
     engine: &std::sync::Arc<Mutex<MultiEngine>>,
 ) -> Result<(), String> {
-    let orig_cl_ord_id = fix.get(&41).ok_or_else(|| "missing OrigClOrdID (41)".to_string())?.clone();
-    let order_id = *session.cl_ord_to_order_id.get(&orig_cl_ord_id).ok_or_else(|| "OrigClOrdID not found".to_string())?;
-    let side = session.cl_ord_to_side.get(&orig_cl_ord_id).copied().unwrap_or(Side::Buy);
+    let order_id = match orig_order(fix, session) {
+        Ok(order_id) => order_id,
+        Err(text) => return send_cancel_reject(stream, session, fix, None, CxlRejReason::UnknownOrder, text),
+    };
+    let orig_cl_ord_id = fix.get(&41).cloned().unwrap_or_default();
+    let context = session.cl_ord_to_order.get(&orig_cl_ord_id).cloned();
+    let resource = serde_json::json!({ "order_id": order_id.0, "cl_ord_id": orig_cl_ord_id });
     let mut guard = engine.lock().expect("lock");
+    if guard.get_order(order_id).is_some_and(|order| !session.may_manage(order.trader_id)) {
+        let state = order_state(&guard, order_id);
+        drop(guard);
+        session.audit(AuditAction::OrderCancel, resource, "forbidden");
+        return send_cancel_reject(stream, session, fix, Some(state), CxlRejReason::Other, "order belongs to another trader");
+    }
     let removed = guard.cancel_order(order_id);
+    let state = order_state(&guard, order_id);
     drop(guard);
+    session.audit(AuditAction::OrderCancel, resource, if removed.is_some() { "success" } else { "not_found" });
     if removed.is_none() {
-        send_rejection(stream, &orig_cl_ord_id, "order not found", session.next_seq())?;
-        return Ok(());
+        return send_cancel_reject(stream, session, fix, Some(state), CxlRejReason::TooLateToCancel, "order is not open");
     }
+    let (seq, sender, target) = session.next_header();
     let mut w = FixWriter::new();
     w.set(35, "8");
-    w.set(34, session.next_seq().to_string());
-    w.set(49, SENDER_COMP_ID);
+    w.set(34, seq.to_string());
+    w.set(49, sender);
     w.set(52, fix_timestamp_now());
-    w.set(56, TARGET_COMP_ID);
+    w.set(56, target);
     w.set(11, &orig_cl_ord_id);
     w.set(17, "0");
     w.set(37, order_id.0.to_string());
     w.set(38, "0");
     w.set(39, "4");
-    w.set(40, "2");
-    w.set(54, match side { Side::Buy => "1", Side::Sell => "2" });
+    if let Some(context) = &context {
+        context.write_to(&mut w);
+    }
     w.set(14, "0");
     w.set(151, "0");
     w.set(150, "4");
+    w.set(60, transact_time());
     let mut out = Vec::new();
     w.write(&mut out).map_err(|e| e.to_string())?;
-    stream.write_all(&out).map_err(|e| e.to_string())?;
+    session.send(stream, out)?;
     Ok(())
 }
 
 fn handle_order_cancel_replace_request(
-    stream: &mut std::net::TcpStream,
+    stream: &mut impl Write,
     fix: &crate::fix::message::FixMessage,
     session: &mut Session,
     engine: &std::sync::Arc<Mutex<MultiEngine>>,
     market_state: &std::sync::Mutex<MarketState>,
 ) -> Result<(), String> {
+    let order_id = match orig_order(fix, session) {
+        Ok(order_id) => order_id,
+        Err(text) => return send_cancel_reject(stream, session, fix, None, CxlRejReason::UnknownOrder, text),
+    };
+    let orig_cl_ord_id = fix.get(&41).cloned().unwrap_or_default();
+    let guard = engine.lock().expect("lock");
+    let state = order_state(&guard, order_id);
+    let owner = guard.get_order(order_id).map(|order| order.trader_id);
+    drop(guard);
+    if owner.is_some_and(|trader_id| !session.may_manage(trader_id)) {
+        let resource = serde_json::json!({ "order_id": order_id.0, "orig_cl_ord_id": orig_cl_ord_id });
+        session.audit(AuditAction::OrderModify, resource, "forbidden");
+        return send_cancel_reject(stream, session, fix, Some(state), CxlRejReason::Other, "order belongs to another trader");
+    }
     if *market_state.lock().expect("lock") != MarketState::Open {
-        let cl_ord_id = fix.get(&11).cloned().unwrap_or_else(|| "?".to_string());
-        send_rejection(stream, &cl_ord_id, "market not open", session.next_seq())?;
-        return Ok(());
+        return send_cancel_reject(stream, session, fix, Some(state), CxlRejReason::BrokerOption, "market not open");
     }
-    let orig_cl_ord_id = fix.get(&41).ok_or_else(|| "missing OrigClOrdID (41)".to_string())?.clone();
-    let order_id = *session.cl_ord_to_order_id.get(&orig_cl_ord_id).ok_or_else(|| "OrigClOrdID not found".to_string())?;
-    let new_order_id = session.next_order_id;
-    session.next_order_id += 1;
-    let replacement = order_from_cancel_replace(fix, new_order_id)?;
+    if fix.get(&11).is_some_and(|cl_ord_id| session.cl_ord_to_order_id.contains_key(cl_ord_id)) {
+        return send_cancel_reject(stream, session, fix, Some(state), CxlRejReason::DuplicateClOrdId, "duplicate ClOrdID");
+    }
+    let new_order_id = engine.lock().expect("lock").allocate_order_ids(1).0;
+    let replacement = instrument(fix, session, engine).and_then(|instrument_id| {
+        let mut replacement = order_from_cancel_replace(fix, instrument_id, new_order_id)?;
+        replacement.trader_id = bound_trader(fix, session)?.unwrap_or(replacement.trader_id);
+        Ok(replacement)
+    });
+    let replacement = match replacement {
+        Ok(replacement) => replacement,
+        Err(e) => return send_cancel_reject(stream, session, fix, Some(state), CxlRejReason::Other, &e),
+    };
     let cl_ord_id = replacement.client_order_id.clone();
-    let side = replacement.side;
-    session.cl_ord_to_order_id.insert(cl_ord_id.clone(), replacement.order_id);
-    session.cl_ord_to_side.insert(cl_ord_id.clone(), side);
+    let context = OrderContext::of_order(&replacement, fix_symbol(fix).unwrap_or_default());
 
     let mut guard = engine.lock().expect("lock");
+    let mut resource = crate::api::order_modify_resource(order_id, guard.order_status(order_id).as_ref(), &replacement);
+    resource["cl_ord_id"] = serde_json::json!(cl_ord_id);
     match guard.modify_order(order_id, &replacement) {
         Ok((_trades, reports)) => {
             drop(guard);
+            session.audit(AuditAction::OrderModify, resource, "success");
+            session.cl_ord_to_order_id.insert(cl_ord_id.clone(), replacement.order_id);
+            session.cl_ord_to_order.insert(cl_ord_id.clone(), context.clone());
             for report in &reports {
-                let out = execution_report_to_fix_with_side(
-                    report,
-                    side,
-                    &cl_ord_id,
-                    session.next_seq(),
-                    SENDER_COMP_ID,
-                    TARGET_COMP_ID,
-                );
-                stream.write_all(&out).map_err(|e| e.to_string())?;
+                let Some((cl_ord_id, context)) = session.order(report.order_id) else {
+                    continue;
+                };
+                let orig = report.orig_order_id.map(|_| orig_cl_ord_id.as_str());
+                let (seq, sender, target) = session.next_header();
+                let out = execution_report_to_fix_with_orig(report, &context, &cl_ord_id, orig, seq, sender, target);
+                session.send(stream, out)?;
             }
         }
         Err(e) => {
+            let state = order_state(&guard, order_id);
             drop(guard);
-            send_rejection(stream, &cl_ord_id, e.as_str(), session.next_seq())?;
+            session.audit(AuditAction::OrderModify, resource, "rejected");
+            let reason = match state.1 {
+                OrderStatus::New | OrderStatus::PartiallyFilled => CxlRejReason::Other,
+                _ => CxlRejReason::TooLateToCancel,
+            };
+            send_cancel_reject(stream, session, fix, Some(state), reason, &e)?;
+        }
+    }
+    Ok(())
+}
+
+/// MarketDataRequest (35=V) for one Symbol (55, an instrument id): SubscriptionRequestType (263) 0 sends a
+/// MarketDataSnapshotFullRefresh, 1 also subscribes to MarketDataIncrementalRefresh, and 2 unsubscribes MDReqID
+/// (262). MarketDepth (264) is levels per side, 0 for the full book.
+fn handle_market_data_request(
+    stream: &mut impl Write,
+    fix: &FixMessage,
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+) -> Result<(), String> {
+    let Some(req_id) = fix.get(&262).cloned() else {
+        let out = session_reject(fix, Some(262), SessionRejectReason::RequiredTagMissing, "MDReqID (262) missing", session.next_header());
+        return session.send(stream, out);
+    };
+    let mut reject = |session: &mut Session, reason: MdReqRejReason, text: &str| {
+        let (seq, sender, target) = session.next_header();
+        let out = market_data_request_reject_to_fix(&req_id, reason, text, seq, sender, target);
+        session.send(stream, out)
+    };
+    let subscribe = match fix.get(&263).map(String::as_str) {
+        Some("0") => false,
+        Some("1") => true,
+        Some("2") => {
+            session.md_subscriptions.retain(|sub| sub.req_id != req_id);
+            return Ok(());
+        }
+        _ => {
+            return reject(session, MdReqRejReason::UnsupportedSubscriptionRequestType, "SubscriptionRequestType (263) must be 0, 1, or 2")
+        }
+    };
+    let Some(depth) = fix.get(&264).map_or(Some(0), |s| s.parse::<usize>().ok()) else {
+        return reject(session, MdReqRejReason::UnsupportedMarketDepth, "invalid MarketDepth (264)");
+    };
+    if subscribe && session.md_subscriptions.iter().any(|sub| sub.req_id == req_id) {
+        return reject(session, MdReqRejReason::DuplicateMdReqId, "MDReqID (262) already subscribed");
+    }
+    let symbol = fix_symbol(fix).unwrap_or_default().to_string();
+    let guard = engine.lock().expect("lock");
+    let Some((instrument_id, snapshot)) = guard
+        .instrument_by_symbol(&symbol)
+        .and_then(|id| Some((id, guard.book_snapshot_for(id)?)))
+    else {
+        drop(guard);
+        return reject(session, MdReqRejReason::UnknownSymbol, &format!("unknown Symbol (55) {}", symbol));
+    };
+    if !auth::entitled(&session.instruments, instrument_id) {
+        drop(guard);
+        return reject(session, MdReqRejReason::InsufficientPermissions, &format!("not entitled to Symbol (55) {}", symbol));
+    }
+    let levels = MarketDataSubscription::levels(&guard, instrument_id, depth);
+    drop(guard);
+    if subscribe {
+        if session.md_events.is_none() {
+            session.md_events = Some(session.market_data.subscribe());
+        }
+        session.md_subscriptions.push(MarketDataSubscription {
+            req_id: req_id.clone(),
+            symbol: symbol.clone(),
+            instrument_id,
+            depth,
+            sent: levels.clone(),
+            snapshot_seq: snapshot.seq,
+        });
+    }
+    let (seq, sender, target) = session.next_header();
+    let out = market_data_snapshot_to_fix(&req_id, &symbol, &levels, seq, sender, target);
+    session.send(stream, out)
+}
+
+/// Send a MarketDataIncrementalRefresh per subscription whose book changed or that traded since the last call.
+fn publish_market_data(
+    stream: &mut impl Write,
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+) -> Result<(), String> {
+    let Some(events) = &session.md_events else {
+        return Ok(());
+    };
+    let mut changed = Vec::new();
+    let mut trades = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        match event {
+            MarketDataEvent::Book(instrument_id) if !changed.contains(&instrument_id) => changed.push(instrument_id),
+            MarketDataEvent::Book(_) => {}
+            MarketDataEvent::Trade(trade) => trades.push(trade),
         }
     }
+    if changed.is_empty() && trades.is_empty() {
+        return Ok(());
+    }
+    let guard = engine.lock().expect("lock");
+    let mut refreshes = Vec::new();
+    for sub in &mut session.md_subscriptions {
+        let delta = if changed.contains(&sub.instrument_id) {
+            sub.refresh(&guard)
+        } else {
+            Default::default()
+        };
+        let sub_trades: Vec<_> = trades
+            .iter()
+            .filter(|t| t.instrument_id == sub.instrument_id && t.seq > sub.snapshot_seq)
+            .cloned()
+            .collect();
+        if !delta.is_empty() || !sub_trades.is_empty() {
+            refreshes.push((sub.req_id.clone(), sub.symbol.clone(), delta, sub_trades));
+        }
+    }
+    drop(guard);
+    for (req_id, symbol, delta, trades) in refreshes {
+        let (seq, sender, target) = session.next_header();
+        let out = market_data_incremental_to_fix(&req_id, &symbol, &delta, &trades, seq, sender, target);
+        session.send(stream, out)?;
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// OrderMassCancelRequest (35=q): cancel the session trader's resting orders in one Symbol (55) when
+/// MassCancelRequestType (530) is 1, or in every instrument when it is 7; only on Side (54) when given. Accepted
+/// whatever the market state. Replies with an OrderMassCancelReport listing the canceled orders.
+fn handle_order_mass_cancel_request(
+    stream: &mut impl Write,
+    fix: &FixMessage,
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+) -> Result<(), String> {
+    let request_type = fix.get(&530).cloned().unwrap_or_default();
+    let side = match fix.get(&54).map(String::as_str) {
+        None => Ok(None),
+        Some("1") => Ok(Some(Side::Buy)),
+        Some("2") => Ok(Some(Side::Sell)),
+        Some(_) => Err((MassCancelRejectReason::Other, "invalid Side (54)".to_string())),
+    };
+    let scope = match request_type.as_str() {
+        "1" => instrument(fix, session, engine).map(Some).map_err(|e| (MassCancelRejectReason::InvalidSecurity, e)),
+        "7" => Ok(None),
+        _ => Err((
+            MassCancelRejectReason::NotSupported,
+            "MassCancelRequestType (530) must be 1 (by symbol) or 7 (all orders)".to_string(),
+        )),
+    };
+    let result = scope.and_then(|instrument_id| {
+        let side = side?;
+        let trader = bound_trader(fix, session).map_err(|e| (MassCancelRejectReason::Other, e))?;
+        let trader_id = trader.unwrap_or_else(|| TraderId(fix.get(&1).and_then(|s| s.parse().ok()).unwrap_or(1)));
+        Ok((side, engine.lock().expect("lock").mass_cancel(trader_id, instrument_id, side)))
+    });
+    let (side, affected, reject) = match result {
+        Ok((side, order_ids)) => {
+            let cl_ord_ids: HashMap<OrderId, &String> =
+                session.cl_ord_to_order_id.iter().map(|(cl_ord_id, &order_id)| (order_id, cl_ord_id)).collect();
+            let affected = order_ids
+                .into_iter()
+                .map(|order_id| (order_id, cl_ord_ids.get(&order_id).map(|s| s.to_string())))
+                .collect();
+            (side, affected, None)
+        }
+        Err(reject) => (None, Vec::new(), Some(reject)),
+    };
+    let report = OrderMassCancelReport {
+        cl_ord_id: fix.get(&11).cloned().unwrap_or_default(),
+        request_type,
+        symbol: fix_symbol(fix).map(str::to_string),
+        side,
+        affected,
+        reject,
+    };
+    let (seq, sender, target) = session.next_header();
+    let out = order_mass_cancel_report_to_fix(&report, seq, sender, target);
+    session.send(stream, out)
+}
+
+/// MassQuote (35=i): replace the trader's quote on the instrument (see [`MultiEngine::submit_quote`]). Replies
+/// with a MassQuoteAcknowledgement, then an ExecutionReport per report on the quote's own orders (ClOrdID =
+/// QuoteID).
+fn handle_mass_quote(
+    stream: &mut impl Write,
+    fix: &crate::fix::message::FixMessage,
+    session: &mut Session,
+    engine: &std::sync::Arc<Mutex<MultiEngine>>,
+    market_state: &std::sync::Mutex<MarketState>,
+) -> Result<(), String> {
+    let quote_id = fix.get(&117).cloned().unwrap_or_else(|| "?".to_string());
+    if *market_state.lock().expect("lock") != MarketState::Open {
+        let (seq, sender, target) = session.next_header();
+        let out = mass_quote_ack_to_fix(&quote_id, Some("market not open"), seq, sender, target);
+        session.send(stream, out)?;
+        return Ok(());
+    }
+    let trader = bound_trader(fix, session);
+    let result = instrument(fix, session, engine).and_then(|instrument_id| {
+        let mut guard = engine.lock().expect("lock");
+        let bid_order_id = guard.allocate_order_ids(2).0;
+        let mut quote = quote_from_mass_quote(fix, instrument_id, bid_order_id, bid_order_id + 1)?;
+        quote.trader_id = trader?.unwrap_or(quote.trader_id);
+        guard.submit_quote(&quote).map(|out| (quote, out))
+    });
+    match result {
+        Ok((quote, (_trades, reports))) => {
+            let (seq, sender, target) = session.next_header();
+            let out = mass_quote_ack_to_fix(&quote_id, None, seq, sender, target);
+            session.send(stream, out)?;
+            for report in &reports {
+                let side = if report.order_id == quote.bid_order_id {
+                    Side::Buy
+                } else if report.order_id == quote.ask_order_id {
+                    Side::Sell
+                } else {
+                    continue;
+                };
+                let context = OrderContext::of_quote(&quote, side, fix_symbol(fix).unwrap_or_default());
+                let (seq, sender, target) = session.next_header();
+                let out = execution_report_to_fix(report, &context, &quote_id, seq, sender, target);
+                session.send(stream, out)?;
+            }
+        }
+        Err(e) => {
+            let (seq, sender, target) = session.next_header();
+            let out = mass_quote_ack_to_fix(&quote_id, Some(&e), seq, sender, target);
+            session.send(stream, out)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! Incremental FIX framing: bytes go in as they are read from the connection, whole messages come out. Frames
+//! are delimited by BodyLength (9) and checked against CheckSum (10) (see [`fix_frame_len`]); anything that is
+//! not a valid frame is skipped up to the next BeginString, so one corrupt message does not stall the stream.
+
+use crate::fix::message::{fix_frame_len, parse_fix_fields, FixFieldError, FixMessage, BEGIN_STRINGS};
+
+/// Why [`FixDecoder::next_message`] produced no message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FixDecodeError {
+    /// Bytes that are not a valid frame (wrong BodyLength or CheckSum, no BeginString) were skipped.
+    Garbled(String),
+    /// A whole frame, BodyLength and CheckSum correct, with a field that does not parse: the fields before it
+    /// and the error.
+    Malformed(FixMessage, FixFieldError),
+}
+
+impl std::fmt::Display for FixDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FixDecodeError::Garbled(text) => write!(f, "{}", text),
+            FixDecodeError::Malformed(_, error) => write!(f, "{}", error.text),
+        }
+    }
+}
+
+/// Streaming FIX frame decoder (FIX 4.4 and FIXT.1.1). Feed it with [`FixDecoder::extend`] and drain it with
+/// [`FixDecoder::next_message`] until that returns `None`; a partial message stays buffered for the next read,
+/// and several messages from one read come out one by one.
+#[derive(Debug, Default)]
+pub struct FixDecoder {
+    buf: Vec<u8>,
+}
+
+impl FixDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append bytes read from the connection.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Bytes buffered but not yet decoded.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// The next message, `None` while more bytes are needed. After `Some(Err)` the bad bytes are gone and
+    /// decoding goes on with the next call.
+    pub fn next_message(&mut self) -> Option<Result<FixMessage, FixDecodeError>> {
+        let start = match BEGIN_STRINGS.iter().filter_map(|begin| find(&self.buf, begin)).min() {
+            Some(start) => start,
+            None => {
+                // Keep a tail that may still grow into a BeginString.
+                let keep = BEGIN_STRINGS
+                    .iter()
+                    .flat_map(|begin| (1..begin.len()).filter(|&n| self.buf.ends_with(&begin[..n])))
+                    .max()
+                    .unwrap_or(0);
+                let skipped = self.buf.len() - keep;
+                if skipped == 0 {
+                    return None;
+                }
+                self.buf.drain(..skipped);
+                let text = format!("skipped {} bytes without BeginString (8)", skipped);
+                return Some(Err(FixDecodeError::Garbled(text)));
+            }
+        };
+        if start > 0 {
+            self.buf.drain(..start);
+            let text = format!("skipped {} bytes before BeginString (8)", start);
+            return Some(Err(FixDecodeError::Garbled(text)));
+        }
+        match fix_frame_len(&self.buf) {
+            Ok(None) => None,
+            Ok(Some(len)) => {
+                let frame: Vec<u8> = self.buf.drain(..len).collect();
+                Some(parse_fix_fields(&frame).map_err(|(fields, error)| FixDecodeError::Malformed(fields, error)))
+            }
+            Err(e) => {
+                // Resynchronize on the next BeginString after this one.
+                self.buf.drain(..1);
+                Some(Err(FixDecodeError::Garbled(e)))
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fix::message::{FixVersion, FixWriter};
+
+    fn message(seq: &str) -> Vec<u8> {
+        let mut w = FixWriter::new();
+        w.set(35, "0");
+        w.set(34, seq);
+        let mut out = Vec::new();
+        w.write(&mut out).unwrap();
+        out
+    }
+
+    fn seq(decoded: Option<Result<FixMessage, FixDecodeError>>) -> String {
+        decoded.expect("a message").expect("valid")[&34].clone()
+    }
+
+    #[test]
+    fn decodes_partial_and_pipelined_messages() {
+        let mut decoder = FixDecoder::new();
+        let stream = [message("1"), message("2"), message("3")].concat();
+        // One byte at a time: nothing comes out until a message is complete.
+        let first = message("1").len();
+        for &b in &stream[..first - 1] {
+            decoder.extend(&[b]);
+            assert!(decoder.next_message().is_none());
+        }
+        decoder.extend(&stream[first - 1..]);
+        assert_eq!(seq(decoder.next_message()), "1");
+        assert_eq!(seq(decoder.next_message()), "2");
+        assert_eq!(seq(decoder.next_message()), "3");
+        assert!(decoder.next_message().is_none());
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn decodes_fix44_and_fixt_frames_alike() {
+        let mut fixt = FixWriter::with_version(FixVersion::Fix50Sp2);
+        fixt.set(35, "0");
+        fixt.set(34, "2");
+        let mut decoder = FixDecoder::new();
+        fixt.write(&mut decoder.buf).unwrap();
+        decoder.extend(&message("3"));
+        let msg = decoder.next_message().unwrap().unwrap();
+        assert_eq!((msg[&8].as_str(), msg[&34].as_str()), ("FIXT.1.1", "2"));
+        assert_eq!(seq(decoder.next_message()), "3");
+    }
+
+    #[test]
+    fn skips_garbage_and_corrupt_frames_to_the_next_message() {
+        let mut decoder = FixDecoder::new();
+        let mut bad_checksum = message("2");
+        let n = bad_checksum.len();
+        bad_checksum[n - 2] = if bad_checksum[n - 2] == b'9' { b'0' } else { b'9' };
+        let mut bad_length = message("3");
+        bad_length[12] = b'9';
+        let bad_header = b"8=FIX.4.4\x019=x\x01";
+        decoder.extend(&[b"junk".as_slice(), &message("1"), &bad_checksum, &bad_length, bad_header, &message("4")].concat());
+
+        assert!(decoder.next_message().unwrap().unwrap_err().to_string().contains("skipped 4 bytes"));
+        assert_eq!(seq(decoder.next_message()), "1");
+        assert!(decoder.next_message().unwrap().unwrap_err().to_string().contains("CheckSum (10)"));
+        let mut errors = 0;
+        let seq4 = loop {
+            match decoder.next_message().expect("more") {
+                Ok(msg) => break msg[&34].clone(),
+                Err(_) => errors += 1,
+            }
+        };
+        assert_eq!(seq4, "4");
+        assert!(errors >= 2);
+        assert!(decoder.next_message().is_none());
+    }
+
+    #[test]
+    fn keeps_a_partial_begin_string_and_drops_oversized_frames() {
+        let mut decoder = FixDecoder::new();
+        decoder.extend(b"xx8=FIX");
+        assert!(decoder.next_message().unwrap().is_err());
+        assert!(decoder.next_message().is_none());
+        assert_eq!(decoder.buffered(), "8=FIX".len());
+        decoder.extend(&message("1")["8=FIX".len()..]);
+        assert_eq!(seq(decoder.next_message()), "1");
+
+        decoder.extend(format!("8=FIX.4.4\x019={}\x01", crate::fix::MAX_BODY_LENGTH + 1).as_bytes());
+        assert!(decoder.next_message().unwrap().unwrap_err().to_string().contains("BodyLength (9)"));
+        decoder.extend(&message("2"));
+        let mut next = decoder.next_message();
+        while let Some(Err(_)) = next {
+            next = decoder.next_message();
+        }
+        assert_eq!(seq(next), "2");
+    }
+}
@@ -0,0 +1,311 @@
+//! FIX initiator: the client side of a session. Connects out to a FIX acceptor (ours in end-to-end tests, or a
+//! downstream venue when bridging order flow), logs on, sends NewOrderSingles, and reads ExecutionReports back.
+//! Session-level traffic (Heartbeat, TestRequest, ResendRequest, SequenceReset) is answered while reading.
+
+use crate::fix::decoder::FixDecoder;
+use crate::fix::message::{
+    execution_report_from_fix, in_version, new_order_single_to_fix, transact_time, FixExecutionReport, FixMessage,
+    FixVersion, FixWriter,
+};
+use crate::types::Order;
+use log::warn;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Who the initiator logs on as and how. Its `Debug` output leaves out the Password.
+#[derive(Clone)]
+pub struct FixInitiatorConfig {
+    /// SenderCompID (49) of our messages.
+    pub sender_comp_id: String,
+    /// TargetCompID (56): the acceptor's CompID.
+    pub target_comp_id: String,
+    pub version: FixVersion,
+    /// Username (553) and Password (554) sent on the Logon.
+    pub credentials: Option<(String, String)>,
+    /// HeartBtInt (108): a Heartbeat is sent when nothing else was for this long.
+    pub heartbeat_interval: Duration,
+    /// How long a read waits for the acceptor before giving up.
+    pub read_timeout: Duration,
+}
+
+impl std::fmt::Debug for FixInitiatorConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FixInitiatorConfig")
+            .field("sender_comp_id", &self.sender_comp_id)
+            .field("target_comp_id", &self.target_comp_id)
+            .field("version", &self.version)
+            .field("credentials", &self.credentials.as_ref().map(|(username, _)| (username, "<redacted>")))
+            .field("heartbeat_interval", &self.heartbeat_interval)
+            .field("read_timeout", &self.read_timeout)
+            .finish()
+    }
+}
+
+impl FixInitiatorConfig {
+    /// FIX 4.4, no credentials, 30 s heartbeats, 5 s read timeout.
+    pub fn new(sender_comp_id: &str, target_comp_id: &str) -> Self {
+        Self {
+            sender_comp_id: sender_comp_id.to_string(),
+            target_comp_id: target_comp_id.to_string(),
+            version: FixVersion::Fix44,
+            credentials: None,
+            heartbeat_interval: Duration::from_secs(30),
+            read_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// One initiator session over `S` (a [`TcpStream`] from [`FixInitiator::connect`], or e.g. a TLS stream). The
+/// initiator keeps no state between connections, so its Logon resets the sequence numbers (141=Y), and no sent
+/// messages: a ResendRequest is answered with a gap fill.
+pub struct FixInitiator<S: Read + Write> {
+    stream: S,
+    config: FixInitiatorConfig,
+    decoder: FixDecoder,
+    /// MsgSeqNum of the next outbound message.
+    out_seq: u32,
+    /// MsgSeqNum (34) expected on the next inbound message.
+    in_seq: u32,
+    logged_on: bool,
+    last_sent: Instant,
+}
+
+impl FixInitiator<TcpStream> {
+    /// Connect to the acceptor at `addr`; call [`FixInitiator::logon`] next.
+    pub fn connect(addr: impl ToSocketAddrs, config: FixInitiatorConfig) -> Result<Self, String> {
+        let stream = TcpStream::connect(addr).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(config.read_timeout)).map_err(|e| e.to_string())?;
+        stream.set_nodelay(true).map_err(|e| e.to_string())?;
+        Ok(Self::new(stream, config))
+    }
+}
+
+impl<S: Read + Write> FixInitiator<S> {
+    /// An initiator on an open `stream`. Reads should time out (see [`FixInitiatorConfig::read_timeout`]) or
+    /// a silent acceptor blocks [`FixInitiator::next_message`].
+    pub fn new(stream: S, config: FixInitiatorConfig) -> Self {
+        Self {
+            stream,
+            config,
+            decoder: FixDecoder::new(),
+            out_seq: 1,
+            in_seq: 1,
+            logged_on: false,
+            last_sent: Instant::now(),
+        }
+    }
+
+    /// Whether the acceptor has accepted our Logon and not logged us out since.
+    pub fn logged_on(&self) -> bool {
+        self.logged_on
+    }
+
+    /// Send Logon (A) and wait for the acceptor's. `Err` with the acceptor's Text (58) when it logs us out.
+    pub fn logon(&mut self) -> Result<(), String> {
+        let fields = logon_fields(&self.config);
+        self.send_session_message("A", &fields)?;
+        let reply = self.read_message()?;
+        match reply.get(&35).map(String::as_str) {
+            Some("A") => {
+                self.in_seq = reply.get(&34).and_then(|s| s.parse::<u32>().ok()).map_or(1, |seq| seq + 1);
+                self.logged_on = true;
+                Ok(())
+            }
+            Some("5") => Err(format!("logon refused: {}", reply.get(&58).map_or("", String::as_str))),
+            other => Err(format!("expected Logon (A), got MsgType {}", other.unwrap_or("?"))),
+        }
+    }
+
+    /// Send `order` as a NewOrderSingle (D) for `symbol`; its `client_order_id` is the ClOrdID (11) the reports
+    /// come back under. Returns the message's MsgSeqNum.
+    pub fn send_order(&mut self, order: &Order, symbol: &str) -> Result<u32, String> {
+        if !self.logged_on {
+            return Err("not logged on".into());
+        }
+        let seq = self.next_seq();
+        let out = new_order_single_to_fix(order, symbol, seq, &self.config.sender_comp_id, &self.config.target_comp_id);
+        self.send(out)?;
+        Ok(seq)
+    }
+
+    /// The next ExecutionReport. Other application messages are skipped; a session Reject (3) or
+    /// BusinessMessageReject (j) of something we sent is an `Err` with its Text (58).
+    pub fn next_execution_report(&mut self) -> Result<FixExecutionReport, String> {
+        loop {
+            let msg = self.next_message()?;
+            let text = || msg.get(&58).map_or("", String::as_str);
+            match msg.get(&35).map(String::as_str) {
+                Some("8") => return execution_report_from_fix(&msg),
+                Some("3") => return Err(format!("Reject (3): {}", text())),
+                Some("j") => return Err(format!("BusinessMessageReject (j): {}", text())),
+                other => warn!("FIX initiator skipped MsgType {}", other.unwrap_or("?")),
+            }
+        }
+    }
+
+    /// The next application message (or session Reject). Heartbeats are consumed, TestRequests and
+    /// ResendRequests answered, SequenceResets applied; a Logout is answered and ends the session with `Err`.
+    pub fn next_message(&mut self) -> Result<FixMessage, String> {
+        loop {
+            let msg = self.read_message()?;
+            let Some(seq) = msg.get(&34).and_then(|s| s.parse::<u32>().ok()) else {
+                return Err("MsgSeqNum (34) missing".into());
+            };
+            let msg_type = msg.get(&35).cloned().unwrap_or_default();
+            if msg_type == "4" && msg.get(&123).map(String::as_str) != Some("Y") {
+                self.reset_in_seq(&msg);
+                continue;
+            }
+            if seq < self.in_seq && msg.get(&43).map(String::as_str) != Some("Y") {
+                let text = format!("MsgSeqNum too low, expecting {} but received {}", self.in_seq, seq);
+                self.send_session_message("5", &[(58, text.clone())])?;
+                self.logged_on = false;
+                return Err(text);
+            }
+            if seq > self.in_seq {
+                // Ask for the gap once; what was missed comes back with PossDupFlag (43=Y).
+                let fields = [(7, self.in_seq.to_string()), (16, "0".to_string())];
+                self.send_session_message("2", &fields)?;
+            }
+            self.in_seq = self.in_seq.max(seq + 1);
+            match msg_type.as_str() {
+                "0" => {}
+                "1" => {
+                    let test_req_id = msg.get(&112).cloned().unwrap_or_default();
+                    self.send_session_message("0", &[(112, test_req_id)])?;
+                }
+                "2" => {
+                    let next = self.out_seq.to_string();
+                    let begin = msg.get(&7).and_then(|s| s.parse::<u32>().ok()).unwrap_or(1);
+                    let out = self.session_message("4", begin, &[(43, "Y".into()), (123, "Y".into()), (36, next)]);
+                    self.send(out)?;
+                }
+                "4" => {
+                    // Gap fill: nothing below NewSeqNo (36) will be resent.
+                    if let Some(new_seq) = msg.get(&36).and_then(|s| s.parse::<u32>().ok()) {
+                        self.in_seq = self.in_seq.max(new_seq);
+                    }
+                }
+                "5" => {
+                    if self.logged_on {
+                        self.send_session_message("5", &[])?;
+                    }
+                    self.logged_on = false;
+                    return Err(format!("logged out: {}", msg.get(&58).map_or("", String::as_str)));
+                }
+                _ => return Ok(msg),
+            }
+        }
+    }
+
+    /// Send Logout (5) and wait for the acceptor's.
+    pub fn logout(&mut self) -> Result<(), String> {
+        self.send_session_message("5", &[])?;
+        self.logged_on = false;
+        loop {
+            let msg = self.read_message()?;
+            if msg.get(&35).map(String::as_str) == Some("5") {
+                return Ok(());
+            }
+        }
+    }
+
+    /// SequenceReset (4): the next inbound MsgSeqNum is NewSeqNo (36).
+    fn reset_in_seq(&mut self, msg: &FixMessage) {
+        if let Some(new_seq) = msg.get(&36).and_then(|s| s.parse::<u32>().ok()) {
+            self.in_seq = new_seq;
+        }
+    }
+
+    /// Next whole message off the wire, sending a Heartbeat whenever the interval passes without a send.
+    fn read_message(&mut self) -> Result<FixMessage, String> {
+        let mut chunk = [0u8; 4096];
+        let started = Instant::now();
+        loop {
+            match self.decoder.next_message() {
+                Some(Ok(msg)) => return Ok(msg),
+                Some(Err(e)) => {
+                    warn!("FIX initiator dropped garbled input: {}", e);
+                    continue;
+                }
+                None => {}
+            }
+            if self.logged_on && self.last_sent.elapsed() >= self.config.heartbeat_interval {
+                self.send_session_message("0", &[])?;
+            }
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err("connection closed".into()),
+                Ok(n) => self.decoder.extend(&chunk[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if started.elapsed() >= self.config.read_timeout {
+                        return Err(format!("no message for {}s", self.config.read_timeout.as_secs()));
+                    }
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+
+    fn next_seq(&mut self) -> u32 {
+        let s = self.out_seq;
+        self.out_seq += 1;
+        s
+    }
+
+    fn send_session_message(&mut self, msg_type: &str, fields: &[(u32, String)]) -> Result<(), String> {
+        let seq = self.next_seq();
+        let out = self.session_message(msg_type, seq, fields);
+        self.send(out)
+    }
+
+    fn session_message(&self, msg_type: &str, seq: u32, fields: &[(u32, String)]) -> Vec<u8> {
+        session_message(&self.config, msg_type, seq, fields)
+    }
+
+    /// Write `out`, framed for the session's version.
+    fn send(&mut self, out: Vec<u8>) -> Result<(), String> {
+        let out = framed(&self.config, out);
+        self.stream.write_all(&out).map_err(|e| e.to_string())?;
+        self.stream.flush().map_err(|e| e.to_string())?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+/// The fields of our Logon (A): reset sequence numbers, heartbeat interval, credentials, and ApplVerID for FIXT.
+pub(crate) fn logon_fields(config: &FixInitiatorConfig) -> Vec<(u32, String)> {
+    let heartbeat = config.heartbeat_interval.as_secs().to_string();
+    let mut fields = vec![(98, "0".to_string()), (108, heartbeat), (141, "Y".to_string())];
+    if let Some((username, password)) = &config.credentials {
+        fields.extend([(553, username.clone()), (554, password.clone())]);
+    }
+    if let Some(appl_ver_id) = config.version.appl_ver_id() {
+        fields.push((1137, appl_ver_id.to_string()));
+    }
+    fields
+}
+
+/// Session-level message of `msg_type` numbered `seq`, with `fields` after the standard header.
+pub(crate) fn session_message(config: &FixInitiatorConfig, msg_type: &str, seq: u32, fields: &[(u32, String)]) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, msg_type);
+    w.set(34, seq.to_string());
+    w.set(49, config.sender_comp_id.as_str());
+    w.set(52, transact_time());
+    w.set(56, config.target_comp_id.as_str());
+    for (tag, value) in fields {
+        w.set(*tag, value.as_str());
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// `out`, written as FIX 4.4, framed for the session's version.
+pub(crate) fn framed(config: &FixInitiatorConfig, out: Vec<u8>) -> Vec<u8> {
+    match config.version {
+        FixVersion::Fix44 => out,
+        version => in_version(&out, version),
+    }
+}
@@ -0,0 +1,88 @@
+//! FIX market data (MarketDataRequest 35=V): subscriptions of one session and the hub that feeds them book
+//! changes and trades from the engine.
+
+use crate::engine::MultiEngine;
+use crate::events::{BookObserver, EngineEvent, EngineEventSink};
+use crate::execution::Trade;
+use crate::order_book::{BookDelta, BookLevels};
+use crate::types::InstrumentId;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+/// What a subscribed session hears from the engine.
+#[derive(Clone, Debug)]
+pub(crate) enum MarketDataEvent {
+    /// The instrument's book changed; the session diffs it against what it last sent.
+    Book(InstrumentId),
+    Trade(Trade),
+}
+
+/// Fans engine output out to the sessions with market-data subscriptions. Registered once per acceptor as an
+/// event sink and book observer; sessions that went away are dropped on the next send.
+#[derive(Default)]
+pub(crate) struct MarketDataHub {
+    subscribers: Mutex<Vec<Sender<MarketDataEvent>>>,
+}
+
+impl MarketDataHub {
+    pub(crate) fn subscribe(&self) -> Receiver<MarketDataEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().expect("lock").push(tx);
+        rx
+    }
+
+    fn send(&self, event: MarketDataEvent) {
+        self.subscribers.lock().expect("lock").retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+impl EngineEventSink for MarketDataHub {
+    fn on_event(&self, event: &EngineEvent) {
+        if let EngineEvent::Trade(trade) = event {
+            self.send(MarketDataEvent::Trade(trade.clone()));
+        }
+    }
+}
+
+impl BookObserver for MarketDataHub {
+    fn on_book_changed(&self, _engine: &MultiEngine, instrument_id: InstrumentId) {
+        self.send(MarketDataEvent::Book(instrument_id));
+    }
+}
+
+/// One snapshot-plus-updates subscription (SubscriptionRequestType 263=1).
+#[derive(Debug)]
+pub(crate) struct MarketDataSubscription {
+    /// MDReqID (262).
+    pub(crate) req_id: String,
+    /// Symbol (55) as requested, echoed on every refresh.
+    pub(crate) symbol: String,
+    pub(crate) instrument_id: InstrumentId,
+    /// MarketDepth (264): levels per side, 0 for the full book.
+    pub(crate) depth: usize,
+    /// Levels as of the last refresh sent, the base of the next incremental.
+    pub(crate) sent: BookLevels,
+    /// Engine sequence number the snapshot reflects; trades up to it are already in the book.
+    pub(crate) snapshot_seq: u64,
+}
+
+impl MarketDataSubscription {
+    /// The instrument's levels now, cut to the subscription's depth.
+    pub(crate) fn levels(engine: &MultiEngine, instrument_id: InstrumentId, depth: usize) -> BookLevels {
+        use crate::engine::MatchingEngine;
+        let mut levels = engine.book_levels_for(instrument_id).unwrap_or_default();
+        if depth > 0 {
+            levels.bids.truncate(depth);
+            levels.asks.truncate(depth);
+        }
+        levels
+    }
+
+    /// Level changes since the last refresh; records the new levels as sent.
+    pub(crate) fn refresh(&mut self, engine: &MultiEngine) -> BookDelta {
+        let now = Self::levels(engine, self.instrument_id, self.depth);
+        let delta = BookDelta::between(&self.sent, &now);
+        self.sent = now;
+        delta
+    }
+}
@@ -1,7 +1,8 @@
 //! FIX 4.4 message parse/build and mapping to engine types.
 
-use crate::execution::ExecutionReport;
-use crate::types::{ExecType, InstrumentId, Order, OrderId, OrderStatus, OrderType, Side, TimeInForce, TraderId};
+use crate::execution::{ExecutionReport, Trade};
+use crate::order_book::{BookDelta, BookLevels, LevelAction};
+use crate::types::{ExecType, InstrumentId, Order, OrderId, OrderStatus, OrderType, Quote, Side, TimeInForce, TraderId};
 use rust_decimal::Decimal;
 use std::collections::HashMap;
 use std::io::{self, Write};
@@ -10,66 +11,168 @@ pub const FIX_SOH: u8 = 0x01;
 /// FIX message as tag → value. Tag 8, 9, 10 are treated specially for framing.
 pub type FixMessage = HashMap<u32, String>;
 
+/// FIX protocol version of a session: FIX 4.4, or FIX 5.0 SP2 application messages over the FIXT.1.1 session
+/// layer. The Logon picks it with BeginString (8) and DefaultApplVerID (1137); see [`FixVersion::negotiate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FixVersion {
+    #[default]
+    Fix44,
+    Fix50Sp2,
+}
+
+impl FixVersion {
+    /// BeginString (8) of every message in the session.
+    pub fn begin_string(self) -> &'static str {
+        match self {
+            FixVersion::Fix44 => "FIX.4.4",
+            FixVersion::Fix50Sp2 => "FIXT.1.1",
+        }
+    }
+
+    /// ApplVerID (1128) and DefaultApplVerID (1137) value; FIX 4.4 has none.
+    pub fn appl_ver_id(self) -> Option<&'static str> {
+        match self {
+            FixVersion::Fix44 => None,
+            FixVersion::Fix50Sp2 => Some("9"),
+        }
+    }
+
+    /// Version a Logon asks for: its BeginString (8), and for FIXT.1.1 its DefaultApplVerID (1137), which must
+    /// be 9 (FIX 5.0 SP2).
+    pub fn negotiate(logon: &FixMessage) -> Result<Self, String> {
+        match logon.get(&8).map(String::as_str) {
+            Some("FIX.4.4") | None => Ok(FixVersion::Fix44),
+            Some("FIXT.1.1") => match logon.get(&1137).map(String::as_str) {
+                Some("9") => Ok(FixVersion::Fix50Sp2),
+                Some(other) => Err(format!("unsupported DefaultApplVerID (1137) {}", other)),
+                None => Err("missing DefaultApplVerID (1137)".into()),
+            },
+            Some(other) => Err(format!("unsupported BeginString (8) {}", other)),
+        }
+    }
+}
+
+/// BeginString fields a frame may start with.
+pub(crate) const BEGIN_STRINGS: [&[u8]; 2] = [b"8=FIX.4.4\x01", b"8=FIXT.1.1\x01"];
+/// Frames claiming a longer body are treated as corrupt rather than buffered.
+pub const MAX_BODY_LENGTH: usize = 64 * 1024;
+/// Digits allowed in BodyLength (9); enough for [`MAX_BODY_LENGTH`].
+const MAX_BODY_LENGTH_DIGITS: usize = 6;
+/// `10=` + three digits + SOH.
+const TRAILER_LEN: usize = 7;
+
 /// Parse one FIX message from the start of `buf`. Returns the message and number of bytes consumed.
-/// Message must start with 8=FIX.4.4 and use 9=BodyLength, 10=CheckSum.
+/// Message must start with 8=FIX.4.4 (or 8=FIXT.1.1) and use 9=BodyLength, 10=CheckSum. `None` while the message is incomplete
+/// and when it is corrupt: BodyLength or CheckSum don't match the bytes, or a field doesn't parse.
 pub fn parse_fix_message(buf: &[u8]) -> Option<(FixMessage, usize)> {
-    if buf.len() < 14 || &buf[0..10] != b"8=FIX.4.4\x01" {
-        return None;
+    let len = fix_frame_len(buf).ok()??;
+    parse_fix_fields(&buf[..len]).ok().map(|msg| (msg, len))
+}
+
+/// Length of the frame at the start of `buf`, from BodyLength (9), once its CheckSum (10) checks out.
+/// `Ok(None)` while the frame is incomplete; `Err` when `buf` does not start with a valid frame.
+pub fn fix_frame_len(buf: &[u8]) -> Result<Option<usize>, String> {
+    let begin_len = match BEGIN_STRINGS.iter().find(|b| buf.starts_with(b)) {
+        Some(begin) => begin.len(),
+        None if BEGIN_STRINGS.iter().any(|b| b.starts_with(buf)) => return Ok(None),
+        None => return Err("message must start with BeginString (8) FIX.4.4 or FIXT.1.1".into()),
+    };
+    let header = &buf[begin_len..];
+    if !header.starts_with(b"9=") {
+        return match b"9=".starts_with(header) {
+            true => Ok(None),
+            false => Err("BodyLength (9) must follow BeginString (8)".into()),
+        };
     }
-    if buf[10..12] != b"9="[..] {
-        return None;
+    let digits = &header[2..];
+    let Some(soh) = digits.iter().take(MAX_BODY_LENGTH_DIGITS + 1).position(|&b| b == FIX_SOH) else {
+        return match digits.len() <= MAX_BODY_LENGTH_DIGITS && digits.iter().all(u8::is_ascii_digit) {
+            true => Ok(None),
+            false => Err("invalid BodyLength (9)".into()),
+        };
+    };
+    let body_len: usize = std::str::from_utf8(&digits[..soh])
+        .ok()
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|s| s.parse().ok())
+        .ok_or("invalid BodyLength (9)")?;
+    if body_len > MAX_BODY_LENGTH {
+        return Err(format!("BodyLength (9) {} is over {}", body_len, MAX_BODY_LENGTH));
     }
-    let mut i = 12;
-    while i < buf.len() && buf[i] != FIX_SOH {
-        i += 1;
+    let body_end = begin_len + 2 + soh + 1 + body_len;
+    let len = body_end + TRAILER_LEN;
+    // A BeginString inside the frame is the next message: BodyLength is wrong.
+    let frame = &buf[begin_len..buf.len().min(len)];
+    let next_message = |begin: &&[u8]| frame.windows(begin.len() + 1).any(|w| w[0] == FIX_SOH && w[1..] == **begin);
+    if BEGIN_STRINGS.iter().any(next_message) {
+        return Err("BodyLength (9) runs into the next message".into());
     }
-    let body_len_str = std::str::from_utf8(&buf[12..i]).ok()?;
-    let body_len: usize = body_len_str.parse().ok()?;
-    i += 1; // SOH after 9's value
-    let body_end = i + body_len;
-    if body_end + 7 > buf.len() || &buf[body_end..body_end + 3] != b"10=" {
-        return None;
+    if buf.len() < len {
+        return Ok(None);
     }
-    let msg_end = body_end + 3 + 3 + 1; // 10= + 3-digit checksum + SOH
-    if msg_end > buf.len() {
-        return None;
+    let trailer = &buf[body_end..len];
+    let checksum = std::str::from_utf8(&trailer[3..6]).ok().filter(|s| s.bytes().all(|b| b.is_ascii_digit()));
+    let (Some(checksum), true) = (checksum, trailer.starts_with(b"10=") && trailer[6] == FIX_SOH) else {
+        return Err("CheckSum (10) not where BodyLength (9) puts it".into());
+    };
+    let computed = buf[..body_end].iter().map(|&b| b as u32).sum::<u32>() % 256;
+    if checksum.parse::<u32>().ok() != Some(computed) {
+        return Err(format!("CheckSum (10) {} does not match {:03}", checksum, computed));
     }
+    Ok(Some(len))
+}
+
+/// A field of a well-framed message that does not parse: the tag when it is known, and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixFieldError {
+    pub tag: Option<u32>,
+    pub reason: SessionRejectReason,
+    pub text: String,
+}
+
+/// Fields of one whole frame (see [`fix_frame_len`]). On a field that does not parse, `Err` carries the
+/// fields before it, so a Reject can still refer to the message's MsgSeqNum (34) and MsgType (35).
+pub fn parse_fix_fields(frame: &[u8]) -> Result<FixMessage, (FixMessage, FixFieldError)> {
     let mut msg = FixMessage::new();
-    let mut pos = 0;
-    while pos < msg_end {
-        let eq = buf[pos..].iter().position(|&b| b == b'=').map(|p| p + pos);
-        let eq = match eq {
-            Some(e) if e < msg_end => e,
-            _ => break,
+    let body = frame.strip_suffix(&[FIX_SOH]).unwrap_or(frame);
+    for field in body.split(|&b| b == FIX_SOH) {
+        let error = |tag, reason, text: String| FixFieldError { tag, reason, text };
+        let Some(eq) = field.iter().position(|&b| b == b'=') else {
+            let text = format!("field without '=': {}", String::from_utf8_lossy(field));
+            return Err((msg, error(None, SessionRejectReason::IncorrectDataFormat, text)));
         };
-        let tag_str = std::str::from_utf8(&buf[pos..eq]).ok()?;
-        let tag: u32 = tag_str.parse().ok()?;
-        pos = eq + 1;
-        let soh = buf[pos..].iter().position(|&b| b == FIX_SOH).map(|p| p + pos);
-        let soh = soh.unwrap_or(msg_end);
-        let value = std::str::from_utf8(&buf[pos..soh]).ok()?.to_string();
-        msg.insert(tag, value);
-        pos = soh + 1;
-        if tag == 10 {
-            break;
-        }
+        let tag = std::str::from_utf8(&field[..eq]).ok().and_then(|t| t.parse::<u32>().ok().filter(|&t| t > 0));
+        let Some(tag) = tag else {
+            let text = format!("invalid tag number {}", String::from_utf8_lossy(&field[..eq]));
+            return Err((msg, error(None, SessionRejectReason::InvalidTagNumber, text)));
+        };
+        let Ok(value) = std::str::from_utf8(&field[eq + 1..]) else {
+            let text = format!("value of tag {} is not UTF-8", tag);
+            return Err((msg, error(Some(tag), SessionRejectReason::IncorrectDataFormat, text)));
+        };
+        msg.insert(tag, value.to_string());
     }
-    Some((msg, msg_end))
+    Ok(msg)
 }
 
 /// Build a FIX message and write to `w`. Sets 8, 9, 10 automatically.
 pub struct FixWriter {
     fields: Vec<(u32, String)>,
+    version: FixVersion,
 }
 
 impl FixWriter {
     pub fn new() -> Self {
-        Self { fields: Vec::new() }
+        Self::with_version(FixVersion::Fix44)
+    }
+    /// A writer whose BeginString (8) is `version`'s.
+    pub fn with_version(version: FixVersion) -> Self {
+        Self { fields: Vec::new(), version }
     }
     pub fn set(&mut self, tag: u32, value: impl Into<String>) {
         self.fields.push((tag, value.into()));
     }
-    /// Build message: 8=FIX.4.4, 9=body_len, body (all fields except 8,9,10), 10=checksum. Checksum = sum(bytes 8..10) % 256.
+    /// Build message: 8=BeginString, 9=body_len, body (all fields except 8,9,10), 10=checksum. Checksum = sum(bytes 8..10) % 256.
     pub fn write(&self, w: &mut impl io::Write) -> io::Result<()> {
         let mut body = Vec::new();
         for (tag, value) in &self.fields {
@@ -79,7 +182,7 @@ impl FixWriter {
             write!(body, "{}={}\x01", tag, value)?;
         }
         let body_len = body.len();
-        let header = format!("8=FIX.4.4\x019={}\x01", body_len);
+        let header = format!("8={}\x019={}\x01", self.version.begin_string(), body_len);
         let body_slice: &[u8] = &body;
         let sum: u32 = header.bytes().chain(body_slice.iter().copied()).map(|b| b as u32).sum();
         let checksum = sum % 256;
@@ -90,15 +193,62 @@ impl FixWriter {
     }
 }
 
-/// NewOrderSingle (35=D) → Order. Uses ClOrdID (11) as order_id if numeric; instrument from 55/48 (default 1); TraderId default 1.
-pub fn order_from_new_order_single(fix: &FixMessage) -> Result<Order, String> {
+impl Default for FixWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `raw` (a message built by [`FixWriter`]) marked for resending: PossDupFlag (43=Y), OrigSendingTime (122) from its
+/// SendingTime (52), and SendingTime set to `sending_time`.
+pub fn possible_duplicate(raw: &[u8], sending_time: &str) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    for field in raw.split(|&b| b == FIX_SOH) {
+        let field = String::from_utf8_lossy(field);
+        let Some((tag, value)) = field.split_once('=') else {
+            continue;
+        };
+        match tag.parse::<u32>() {
+            Ok(8) if value == "FIXT.1.1" => w.version = FixVersion::Fix50Sp2,
+            Ok(43) | Ok(122) | Err(_) => {}
+            Ok(52) => {
+                w.set(43, "Y");
+                w.set(52, sending_time);
+                w.set(122, value);
+            }
+            Ok(tag) => w.set(tag, value),
+        }
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// `raw` (a message built by [`FixWriter`]) framed for `version`: its BeginString (8), with BodyLength (9) and
+/// CheckSum (10) recomputed.
+pub fn in_version(raw: &[u8], version: FixVersion) -> Vec<u8> {
+    let mut w = FixWriter::with_version(version);
+    for field in raw.split(|&b| b == FIX_SOH) {
+        let field = String::from_utf8_lossy(field);
+        if let Some((Ok(tag), value)) = field.split_once('=').map(|(tag, value)| (tag.parse::<u32>(), value)) {
+            w.set(tag, value);
+        }
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// Symbol (55), or SecurityID (48) when there is no symbol: the instrument a message is for.
+pub fn fix_symbol(fix: &FixMessage) -> Option<&str> {
+    fix.get(&55).or_else(|| fix.get(&48)).map(String::as_str)
+}
+
+/// NewOrderSingle (35=D) → Order for `instrument_id` (resolved by the caller from [`fix_symbol`]). Uses ClOrdID
+/// (11) as order_id if numeric; TraderId default 1.
+pub fn order_from_new_order_single(fix: &FixMessage, instrument_id: InstrumentId) -> Result<Order, String> {
     let cl_ord_id = fix.get(&11).ok_or("missing ClOrdID (11)")?.clone();
-    let order_id = cl_ord_id.parse::<u64>().map_err(|_| "ClOrdID must be numeric")?;
-    let instrument_id = fix
-        .get(&55)
-        .or_else(|| fix.get(&48))
-        .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(1);
+    let order_id = cl_ord_id.parse::<u64>().map_err(|_| "invalid ClOrdID (11): must be numeric")?;
     let side = match fix.get(&54).map(|s| s.as_str()).unwrap_or("1") {
         "1" => Side::Buy,
         "2" => Side::Sell,
@@ -129,7 +279,7 @@ pub fn order_from_new_order_single(fix: &FixMessage) -> Result<Order, String> {
     Ok(Order {
         order_id: OrderId(order_id),
         client_order_id: cl_ord_id,
-        instrument_id: InstrumentId(instrument_id),
+        instrument_id,
         side,
         order_type: ord_type,
         quantity,
@@ -140,14 +290,9 @@ pub fn order_from_new_order_single(fix: &FixMessage) -> Result<Order, String> {
     })
 }
 
-/// OrderCancelReplaceRequest (35=G) → replacement Order. Uses ClOrdID (11) as new client order id; new_order_id is assigned by session.
-pub fn order_from_cancel_replace(fix: &FixMessage, new_order_id: u64) -> Result<Order, String> {
+/// OrderCancelReplaceRequest (35=G) → replacement Order for `instrument_id`. Uses ClOrdID (11) as new client order id; new_order_id is assigned by session.
+pub fn order_from_cancel_replace(fix: &FixMessage, instrument_id: InstrumentId, new_order_id: u64) -> Result<Order, String> {
     let cl_ord_id = fix.get(&11).ok_or("missing ClOrdID (11)")?.clone();
-    let instrument_id = fix
-        .get(&55)
-        .or_else(|| fix.get(&48))
-        .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(1);
     let side = match fix.get(&54).map(|s| s.as_str()).unwrap_or("1") {
         "1" => Side::Buy,
         "2" => Side::Sell,
@@ -161,7 +306,7 @@ pub fn order_from_cancel_replace(fix: &FixMessage, new_order_id: u64) -> Result<
         _ => return Err("invalid OrdType (40)".into()),
     };
     let price = if ord_type == OrderType::Limit {
-        fix.get(&44).map(|s| s.parse().ok()).flatten()
+        fix.get(&44).and_then(|s| s.parse().ok())
     } else {
         None
     };
@@ -177,7 +322,7 @@ pub fn order_from_cancel_replace(fix: &FixMessage, new_order_id: u64) -> Result<
     Ok(Order {
         order_id: OrderId(new_order_id),
         client_order_id: cl_ord_id,
-        instrument_id: InstrumentId(instrument_id),
+        instrument_id,
         side,
         order_type: ord_type,
         quantity,
@@ -188,16 +333,348 @@ pub fn order_from_cancel_replace(fix: &FixMessage, new_order_id: u64) -> Result<
     })
 }
 
+/// MassQuote (35=i) → Quote. Only one quote set (296) with one quote entry (295) is supported, since
+/// [`FixMessage`] keeps one value per tag. QuoteID (117) is the quote id; the instrument is resolved by the caller;
+/// BidPx/BidSize (132/134) and OfferPx/OfferSize (133/135), where a missing size or zero means that side is not
+/// quoted; trader from Account (1, default 1). Order ids for the two sides are assigned by the session.
+pub fn quote_from_mass_quote(
+    fix: &FixMessage,
+    instrument_id: InstrumentId,
+    bid_order_id: u64,
+    ask_order_id: u64,
+) -> Result<Quote, String> {
+    let quote_id = fix.get(&117).ok_or("missing QuoteID (117)")?.clone();
+    for tag in [296u32, 295] {
+        if fix.get(&tag).and_then(|s| s.parse::<u32>().ok()).unwrap_or(1) != 1 {
+            return Err(format!("only one quote entry per MassQuote is supported ({})", tag));
+        }
+    }
+    let decimal = |tag: u32, name: &str| -> Result<Decimal, String> {
+        match fix.get(&tag) {
+            Some(s) => s.parse().map_err(|_| format!("invalid {} ({})", name, tag)),
+            None => Ok(Decimal::ZERO),
+        }
+    };
+    let bid_quantity = decimal(134, "BidSize")?;
+    let ask_quantity = decimal(135, "OfferSize")?;
+    let bid_price = decimal(132, "BidPx")?;
+    let ask_price = decimal(133, "OfferPx")?;
+    if !bid_quantity.is_zero() && !fix.contains_key(&132) {
+        return Err("missing BidPx (132)".into());
+    }
+    if !ask_quantity.is_zero() && !fix.contains_key(&133) {
+        return Err("missing OfferPx (133)".into());
+    }
+    let timestamp = fix.get(&52).and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
+    let trader_id = fix.get(&1).and_then(|s| s.parse::<u64>().ok()).unwrap_or(1);
+
+    Ok(Quote {
+        quote_id,
+        trader_id: TraderId(trader_id),
+        instrument_id,
+        bid_order_id: OrderId(bid_order_id),
+        bid_price,
+        bid_quantity,
+        ask_order_id: OrderId(ask_order_id),
+        ask_price,
+        ask_quantity,
+        timestamp,
+    })
+}
+
+/// MassQuoteAcknowledgement (35=b) for `quote_id`: QuoteStatus (297) 0 = accepted, or 5 = rejected with
+/// QuoteRejectReason (300) 99 = other and the reason in Text (58).
+pub fn mass_quote_ack_to_fix(quote_id: &str, rejection: Option<&str>, seq: u32, sender: &str, target: &str) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "b");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, format_utc_timestamp(0));
+    w.set(56, target);
+    w.set(117, quote_id);
+    match rejection {
+        None => w.set(297, "0"),
+        Some(reason) => {
+            w.set(297, "5");
+            w.set(300, "99");
+            w.set(58, reason);
+        }
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// CxlRejReason (102) of an OrderCancelReject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CxlRejReason {
+    /// The order is no longer open (filled, canceled, or rejected).
+    TooLateToCancel = 0,
+    UnknownOrder = 1,
+    /// Refused by the venue, e.g. the market is not open.
+    BrokerOption = 2,
+    DuplicateClOrdId = 6,
+    Other = 99,
+}
+
+/// OrderCancelReject (35=9) for a cancel (`replace` false, CxlRejResponseTo (434) 1) or cancel/replace (434=2)
+/// request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderCancelReject {
+    /// The original order; OrderID (37) is `NONE` when it is unknown.
+    pub order_id: Option<OrderId>,
+    /// ClOrdID (11) of the request.
+    pub cl_ord_id: String,
+    pub orig_cl_ord_id: String,
+    /// OrdStatus (39) of the original order; Rejected when it is unknown.
+    pub ord_status: OrderStatus,
+    pub replace: bool,
+    pub reason: CxlRejReason,
+    /// Text (58).
+    pub text: String,
+}
+
+pub fn order_cancel_reject_to_fix(reject: &OrderCancelReject, seq: u32, sender: &str, target: &str) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "9");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, format_utc_timestamp(0));
+    w.set(56, target);
+    w.set(37, reject.order_id.map_or_else(|| "NONE".to_string(), |id| id.0.to_string()));
+    w.set(11, reject.cl_ord_id.as_str());
+    w.set(41, reject.orig_cl_ord_id.as_str());
+    w.set(39, ord_status_to_fix(reject.ord_status));
+    w.set(434, if reject.replace { "2" } else { "1" });
+    w.set(102, (reject.reason as u8).to_string());
+    w.set(58, reject.text.as_str());
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// SessionRejectReason (373) of a session-level Reject (35=3).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionRejectReason {
+    InvalidTagNumber = 0,
+    RequiredTagMissing = 1,
+    ValueIsIncorrect = 5,
+    IncorrectDataFormat = 6,
+    /// FIXT.1.1 sessions: ApplVerID (1128) other than the session's.
+    UnsupportedApplVerId = 18,
+    Other = 99,
+}
+
+/// BusinessRejectReason (380) of a BusinessMessageReject (35=j).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusinessRejectReason {
+    Other = 0,
+    UnsupportedMessageType = 3,
+    NotAuthorized = 6,
+}
+
+/// MassCancelRejectReason (532) of a rejected OrderMassCancelReport.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MassCancelRejectReason {
+    /// MassCancelRequestType (530) other than 1 (by security) or 7 (all orders).
+    NotSupported = 0,
+    /// Symbol (55) missing or unknown.
+    InvalidSecurity = 1,
+    Other = 99,
+}
+
+/// OrderMassCancelReport (35=r) answering an OrderMassCancelRequest (35=q).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderMassCancelReport {
+    /// ClOrdID (11) of the request.
+    pub cl_ord_id: String,
+    /// MassCancelRequestType (530) as requested; also MassCancelResponse (531) unless rejected.
+    pub request_type: String,
+    pub symbol: Option<String>,
+    pub side: Option<Side>,
+    /// Canceled orders: AffectedOrderID (535) and, when the session entered it, its ClOrdID as OrigClOrdID (41).
+    pub affected: Vec<(OrderId, Option<String>)>,
+    /// Why the request was rejected (MassCancelResponse 531=0) and Text (58).
+    pub reject: Option<(MassCancelRejectReason, String)>,
+}
+
+/// OrderMassCancelReport (35=r). OrderID (37) identifies the report as `MC<seq>`.
+pub fn order_mass_cancel_report_to_fix(report: &OrderMassCancelReport, seq: u32, sender: &str, target: &str) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "r");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, format_utc_timestamp(0));
+    w.set(56, target);
+    w.set(11, report.cl_ord_id.as_str());
+    w.set(37, format!("MC{}", seq));
+    w.set(530, report.request_type.as_str());
+    match &report.reject {
+        Some((reason, _)) => {
+            w.set(531, "0");
+            w.set(532, (*reason as u8).to_string());
+        }
+        None => w.set(531, report.request_type.as_str()),
+    }
+    w.set(533, report.affected.len().to_string());
+    if !report.affected.is_empty() {
+        w.set(534, report.affected.len().to_string());
+        for (order_id, cl_ord_id) in &report.affected {
+            if let Some(cl_ord_id) = cl_ord_id {
+                w.set(41, cl_ord_id.as_str());
+            }
+            w.set(535, order_id.0.to_string());
+        }
+    }
+    if let Some(symbol) = &report.symbol {
+        w.set(55, symbol.as_str());
+    }
+    if let Some(side) = report.side {
+        w.set(54, match side {
+            Side::Buy => "1",
+            Side::Sell => "2",
+        });
+    }
+    if let Some((_, text)) = &report.reject {
+        w.set(58, text.as_str());
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// MDReqRejReason (281) of a MarketDataRequestReject.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MdReqRejReason {
+    UnknownSymbol = 0,
+    DuplicateMdReqId = 1,
+    InsufficientPermissions = 3,
+    UnsupportedSubscriptionRequestType = 4,
+    UnsupportedMarketDepth = 5,
+}
+
+/// MarketDataRequestReject (35=Y) for MDReqID `req_id`.
+pub fn market_data_request_reject_to_fix(
+    req_id: &str,
+    reason: MdReqRejReason,
+    text: &str,
+    seq: u32,
+    sender: &str,
+    target: &str,
+) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "Y");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, format_utc_timestamp(0));
+    w.set(56, target);
+    w.set(262, req_id);
+    w.set(281, (reason as u8).to_string());
+    w.set(58, text);
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// MarketDataSnapshotFullRefresh (35=W) of `levels`: bids (MDEntryType 269=0) then offers (269=1), best first.
+pub fn market_data_snapshot_to_fix(
+    req_id: &str,
+    symbol: &str,
+    levels: &BookLevels,
+    seq: u32,
+    sender: &str,
+    target: &str,
+) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "W");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, format_utc_timestamp(0));
+    w.set(56, target);
+    w.set(262, req_id);
+    w.set(55, symbol);
+    w.set(268, (levels.bids.len() + levels.asks.len()).to_string());
+    for (entry_type, side) in [("0", &levels.bids), ("1", &levels.asks)] {
+        for (price, quantity) in side {
+            w.set(269, entry_type);
+            w.set(270, price.to_string());
+            w.set(271, quantity.to_string());
+        }
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// MarketDataIncrementalRefresh (35=X): the level changes in `delta` (MDUpdateAction 279 0=New, 1=Change,
+/// 2=Delete; a level's size is its new total), then `trades` (MDEntryType 269=2).
+pub fn market_data_incremental_to_fix(
+    req_id: &str,
+    symbol: &str,
+    delta: &BookDelta,
+    trades: &[Trade],
+    seq: u32,
+    sender: &str,
+    target: &str,
+) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "X");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, format_utc_timestamp(0));
+    w.set(56, target);
+    w.set(262, req_id);
+    w.set(268, (delta.bids.len() + delta.asks.len() + trades.len()).to_string());
+    for (entry_type, changes) in [("0", &delta.bids), ("1", &delta.asks)] {
+        for change in changes {
+            w.set(
+                279,
+                match change.action {
+                    LevelAction::Added => "0",
+                    LevelAction::Changed => "1",
+                    LevelAction::Removed => "2",
+                },
+            );
+            w.set(269, entry_type);
+            w.set(55, symbol);
+            w.set(270, change.price.to_string());
+            if change.action != LevelAction::Removed {
+                w.set(271, change.quantity.to_string());
+            }
+        }
+    }
+    for trade in trades {
+        w.set(279, "0");
+        w.set(269, "2");
+        w.set(55, symbol);
+        w.set(270, trade.price.to_string());
+        w.set(271, trade.quantity.to_string());
+    }
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// ExecType (150). FIX 4.4 reports partial and full fills alike as Trade (F); OrdStatus (39) tells them apart.
 fn exec_type_to_fix(e: ExecType) -> &'static str {
     match e {
         ExecType::New => "0",
-        ExecType::PartialFill => "F",
-        ExecType::Fill => "F",
+        ExecType::PartialFill | ExecType::Fill => "F",
         ExecType::Canceled => "4",
+        ExecType::Replaced => "5",
         ExecType::Rejected => "8",
     }
 }
 
+/// OrdStatus (39) of a report: for a fill, 1 (PartiallyFilled) while LeavesQty (151) is left, else 2 (Filled).
+fn report_ord_status_to_fix(report: &ExecutionReport) -> &'static str {
+    match report.exec_type {
+        ExecType::PartialFill | ExecType::Fill if report.remaining_quantity > Decimal::ZERO => "1",
+        ExecType::PartialFill | ExecType::Fill => "2",
+        _ => ord_status_to_fix(report.order_status),
+    }
+}
+
 fn ord_status_to_fix(s: OrderStatus) -> &'static str {
     match s {
         OrderStatus::New => "0",
@@ -208,14 +685,96 @@ fn ord_status_to_fix(s: OrderStatus) -> &'static str {
     }
 }
 
-/// ExecutionReport doesn't carry side; pass side so we can set tag 54 correctly.
-pub fn execution_report_to_fix_with_side(
+/// User-defined tag carrying the engine-wide sequence number (`ExecutionReport::seq`) on execution reports,
+/// independent of the session's MsgSeqNum (34).
+pub const ENGINE_SEQ_NUM_TAG: u32 = 5001;
+
+/// Order attributes an ExecutionReport echoes, which the engine's report doesn't carry: kept per ClOrdID from
+/// the NewOrderSingle, cancel/replace, or quote side that entered the order.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OrderContext {
+    /// Symbol (55) as the client sent it.
+    pub symbol: String,
+    pub side: Side,
+    pub ord_type: OrderType,
+    /// Price (44); `None` for market orders.
+    pub price: Option<Decimal>,
+    pub time_in_force: TimeInForce,
+    /// Account (1): the trader the order was entered for.
+    pub account: TraderId,
+}
+
+impl OrderContext {
+    pub fn of_order(order: &Order, symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            side: order.side,
+            ord_type: order.order_type,
+            price: order.price,
+            time_in_force: order.time_in_force,
+            account: order.trader_id,
+        }
+    }
+
+    /// One side of a quote: a GTC limit order at the bid or offer price.
+    pub fn of_quote(quote: &Quote, side: Side, symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            side,
+            ord_type: OrderType::Limit,
+            price: Some(match side {
+                Side::Buy => quote.bid_price,
+                Side::Sell => quote.ask_price,
+            }),
+            time_in_force: TimeInForce::GTC,
+            account: quote.trader_id,
+        }
+    }
+
+    /// Account (1), Symbol (55), Side (54), OrdType (40), Price (44), and TimeInForce (59).
+    pub fn write_to(&self, w: &mut FixWriter) {
+        w.set(1, self.account.0.to_string());
+        w.set(55, &self.symbol);
+        w.set(54, match self.side {
+            Side::Buy => "1",
+            Side::Sell => "2",
+        });
+        w.set(40, match self.ord_type {
+            OrderType::Market => "1",
+            OrderType::Limit => "2",
+        });
+        if let Some(price) = self.price {
+            w.set(44, price.to_string());
+        }
+        w.set(59, match self.time_in_force {
+            TimeInForce::GTC => "1",
+            TimeInForce::IOC => "3",
+            TimeInForce::FOK => "4",
+        });
+    }
+}
+
+/// ExecutionReport (8) for `order`, the attributes the client entered it with.
+pub fn execution_report_to_fix(
     report: &ExecutionReport,
-    side: Side,
+    order: &OrderContext,
     cl_ord_id: &str,
     seq: u32,
     sender: &str,
     target: &str,
+) -> Vec<u8> {
+    execution_report_to_fix_with_orig(report, order, cl_ord_id, None, seq, sender, target)
+}
+
+/// Like [`execution_report_to_fix`], plus OrigClOrdID (41) for cancel/replace reports (ExecType=5).
+pub fn execution_report_to_fix_with_orig(
+    report: &ExecutionReport,
+    order: &OrderContext,
+    cl_ord_id: &str,
+    orig_cl_ord_id: Option<&str>,
+    seq: u32,
+    sender: &str,
+    target: &str,
 ) -> Vec<u8> {
     let mut w = FixWriter::new();
     w.set(35, "8");
@@ -224,15 +783,14 @@ pub fn execution_report_to_fix_with_side(
     w.set(52, format_utc_timestamp(report.timestamp));
     w.set(56, target);
     w.set(11, cl_ord_id);
+    if let Some(orig) = orig_cl_ord_id {
+        w.set(41, orig);
+    }
     w.set(17, report.exec_id.0.to_string());
     w.set(37, report.order_id.0.to_string());
     w.set(38, (report.filled_quantity + report.remaining_quantity).to_string());
-    w.set(39, ord_status_to_fix(report.order_status));
-    w.set(40, "2");
-    w.set(54, match side {
-        Side::Buy => "1",
-        Side::Sell => "2",
-    });
+    w.set(39, report_ord_status_to_fix(report));
+    order.write_to(&mut w);
     w.set(14, report.filled_quantity.to_string());
     w.set(151, report.remaining_quantity.to_string());
     if let Some(avg) = report.avg_price {
@@ -245,19 +803,154 @@ pub fn execution_report_to_fix_with_side(
         w.set(31, lp.to_string());
     }
     w.set(150, exec_type_to_fix(report.exec_type));
+    w.set(60, transact_time());
+    if report.seq > 0 {
+        w.set(ENGINE_SEQ_NUM_TAG, report.seq.to_string());
+    }
     let mut out = Vec::new();
     let _ = w.write(&mut out);
     out
 }
 
-pub fn execution_report_to_fix(
-    report: &ExecutionReport,
+/// NewOrderSingle (35=D) for `order` on instrument `symbol`: the inverse of [`order_from_new_order_single`].
+/// ClOrdID (11) is the order's `client_order_id`.
+pub fn new_order_single_to_fix(order: &Order, symbol: &str, seq: u32, sender: &str, target: &str) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "D");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, transact_time());
+    w.set(56, target);
+    w.set(11, order.client_order_id.as_str());
+    OrderContext::of_order(order, symbol).write_to(&mut w);
+    w.set(38, order.quantity.to_string());
+    w.set(60, transact_time());
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// OrderCancelRequest (35=F) for the order entered as `orig_cl_ord_id` (OrigClOrdID 41) on `symbol`; `cl_ord_id`
+/// (11) names the request itself.
+pub fn order_cancel_request_to_fix(orig_cl_ord_id: &str, cl_ord_id: &str, symbol: &str, seq: u32, sender: &str, target: &str) -> Vec<u8> {
+    let mut w = FixWriter::new();
+    w.set(35, "F");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, transact_time());
+    w.set(56, target);
+    w.set(11, cl_ord_id);
+    w.set(41, orig_cl_ord_id);
+    w.set(55, symbol);
+    w.set(60, transact_time());
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// OrderCancelReplaceRequest (35=G) replacing the order entered as `orig_cl_ord_id` (41) with `replacement`
+/// under the new ClOrdID `cl_ord_id` (11): the inverse of [`order_from_cancel_replace`].
+pub fn order_cancel_replace_request_to_fix(
+    replacement: &Order,
+    orig_cl_ord_id: &str,
     cl_ord_id: &str,
+    symbol: &str,
     seq: u32,
     sender: &str,
     target: &str,
 ) -> Vec<u8> {
-    execution_report_to_fix_with_side(report, Side::Buy, cl_ord_id, seq, sender, target)
+    let mut w = FixWriter::new();
+    w.set(35, "G");
+    w.set(34, seq.to_string());
+    w.set(49, sender);
+    w.set(52, transact_time());
+    w.set(56, target);
+    w.set(11, cl_ord_id);
+    w.set(41, orig_cl_ord_id);
+    OrderContext::of_order(replacement, symbol).write_to(&mut w);
+    w.set(38, replacement.quantity.to_string());
+    w.set(60, transact_time());
+    let mut out = Vec::new();
+    let _ = w.write(&mut out);
+    out
+}
+
+/// An ExecutionReport (35=8) as a client reads it. Order ids are the sender's, kept as sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixExecutionReport {
+    /// ClOrdID (11).
+    pub cl_ord_id: String,
+    /// OrderID (37).
+    pub order_id: String,
+    /// ExecID (17).
+    pub exec_id: String,
+    /// ExecType (150); a Trade (F) is a Fill when OrdStatus is Filled, else a PartialFill.
+    pub exec_type: ExecType,
+    /// OrdStatus (39).
+    pub ord_status: OrderStatus,
+    /// Symbol (55).
+    pub symbol: Option<String>,
+    /// Side (54).
+    pub side: Option<Side>,
+    /// CumQty (14).
+    pub cum_qty: Decimal,
+    /// LeavesQty (151).
+    pub leaves_qty: Decimal,
+    /// LastQty (32) and LastPx (31) of a fill.
+    pub last_qty: Option<Decimal>,
+    pub last_px: Option<Decimal>,
+    /// Text (58), e.g. the reason of a reject.
+    pub text: Option<String>,
+}
+
+/// ExecutionReport (35=8) → [`FixExecutionReport`]: the inverse of [`execution_report_to_fix`].
+pub fn execution_report_from_fix(fix: &FixMessage) -> Result<FixExecutionReport, String> {
+    if fix.get(&35).map(String::as_str) != Some("8") {
+        return Err("not an ExecutionReport (35=8)".into());
+    }
+    let field = |tag: u32, name: &str| fix.get(&tag).cloned().ok_or_else(|| format!("missing {} ({})", name, tag));
+    let decimal = |tag: u32, name: &str| -> Result<Option<Decimal>, String> {
+        fix.get(&tag)
+            .map(|s| s.parse().map_err(|_| format!("invalid {} ({})", name, tag)))
+            .transpose()
+    };
+    let ord_status = match field(39, "OrdStatus")?.as_str() {
+        "0" => OrderStatus::New,
+        "1" => OrderStatus::PartiallyFilled,
+        "2" => OrderStatus::Filled,
+        "4" => OrderStatus::Canceled,
+        "8" => OrderStatus::Rejected,
+        other => return Err(format!("unsupported OrdStatus (39) {}", other)),
+    };
+    let exec_type = match field(150, "ExecType")?.as_str() {
+        "0" => ExecType::New,
+        "F" if ord_status == OrderStatus::Filled => ExecType::Fill,
+        "F" => ExecType::PartialFill,
+        "4" => ExecType::Canceled,
+        "5" => ExecType::Replaced,
+        "8" => ExecType::Rejected,
+        other => return Err(format!("unsupported ExecType (150) {}", other)),
+    };
+    let side = match fix.get(&54).map(String::as_str) {
+        None => None,
+        Some("1") => Some(Side::Buy),
+        Some("2") => Some(Side::Sell),
+        Some(_) => return Err("invalid Side (54)".into()),
+    };
+    Ok(FixExecutionReport {
+        cl_ord_id: field(11, "ClOrdID")?,
+        order_id: field(37, "OrderID")?,
+        exec_id: field(17, "ExecID")?,
+        exec_type,
+        ord_status,
+        symbol: fix.get(&55).cloned(),
+        side,
+        cum_qty: decimal(14, "CumQty")?.unwrap_or_default(),
+        leaves_qty: decimal(151, "LeavesQty")?.unwrap_or_default(),
+        last_qty: decimal(32, "LastQty")?,
+        last_px: decimal(31, "LastPx")?,
+        text: fix.get(&58).cloned(),
+    })
 }
 
 fn format_utc_timestamp(ts: u64) -> String {
@@ -279,6 +972,14 @@ fn format_utc_timestamp(ts: u64) -> String {
     format!("{:04}{:02}{:02}-{:02}:{:02}:{:02}", y, mth, d, h, m, s)
 }
 
+/// TransactTime (60): now, as a UTCTimestamp with milliseconds (`YYYYMMDD-HH:MM:SS.sss`).
+pub fn transact_time() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:03}", format_utc_timestamp(now.as_secs()), now.subsec_millis())
+}
+
 fn days_to_ymd(days: i64) -> (u32, u32, u32) {
     let z = days + 719468;
     let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
@@ -291,3 +992,102 @@ fn days_to_ymd(days: i64) -> (u32, u32, u32) {
     let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
     (y, m, d)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ExecutionId;
+
+    fn report(exec_type: ExecType, order_status: OrderStatus, filled: u32, remaining: u32) -> ExecutionReport {
+        ExecutionReport {
+            order_id: OrderId(1),
+            exec_id: ExecutionId(1),
+            exec_type,
+            order_status,
+            filled_quantity: Decimal::from(filled),
+            remaining_quantity: Decimal::from(remaining),
+            avg_price: None,
+            last_qty: None,
+            last_px: None,
+            timestamp: 1,
+            orig_order_id: None,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn fills_are_trades_with_ord_status_from_leaves_qty() {
+        let order = OrderContext {
+            symbol: "1".into(),
+            side: Side::Buy,
+            ord_type: OrderType::Limit,
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            account: TraderId(1),
+        };
+        let cases = [
+            (report(ExecType::New, OrderStatus::New, 0, 5), ("0", "0")),
+            (report(ExecType::PartialFill, OrderStatus::PartiallyFilled, 2, 3), ("F", "1")),
+            (report(ExecType::Fill, OrderStatus::Filled, 5, 0), ("F", "2")),
+            // LeavesQty decides, whatever the engine called the fill.
+            (report(ExecType::PartialFill, OrderStatus::PartiallyFilled, 2, 0), ("F", "2")),
+            (report(ExecType::Fill, OrderStatus::Filled, 2, 3), ("F", "1")),
+            (report(ExecType::Canceled, OrderStatus::Canceled, 2, 3), ("4", "4")),
+            (report(ExecType::Replaced, OrderStatus::New, 0, 5), ("5", "0")),
+            (report(ExecType::Rejected, OrderStatus::Rejected, 0, 0), ("8", "8")),
+        ];
+        for (report, (exec_type, ord_status)) in cases {
+            let out = execution_report_to_fix(&report, &order, "1", 1, "S", "T");
+            let (msg, _) = parse_fix_message(&out).unwrap();
+            let tags = (msg[&150].as_str(), msg[&39].as_str());
+            assert_eq!(tags, (exec_type, ord_status), "{:?} {:?}", report.exec_type, report.order_status);
+        }
+    }
+
+    #[test]
+    fn new_order_single_and_execution_report_round_trip() {
+        let order = Order {
+            order_id: OrderId(7),
+            client_order_id: "7".into(),
+            instrument_id: InstrumentId(1),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(3),
+            price: Some(Decimal::from(101)),
+            time_in_force: TimeInForce::IOC,
+            timestamp: 0,
+            trader_id: TraderId(2),
+        };
+        let (fix, _) = parse_fix_message(&new_order_single_to_fix(&order, "BTC-USD", 4, "S", "T")).unwrap();
+        assert_eq!((fix[&35].as_str(), fix[&34].as_str(), fix[&55].as_str()), ("D", "4", "BTC-USD"));
+        let parsed = order_from_new_order_single(&fix, InstrumentId(1)).unwrap();
+        assert_eq!(
+            (parsed.order_id, parsed.side, parsed.order_type, parsed.quantity, parsed.price, parsed.time_in_force, parsed.trader_id),
+            (order.order_id, order.side, order.order_type, order.quantity, order.price, order.time_in_force, order.trader_id)
+        );
+        let (fix, _) = parse_fix_message(&order_cancel_replace_request_to_fix(&order, "6", "7-9", "BTC-USD", 9, "S", "T")).unwrap();
+        assert_eq!((fix[&35].as_str(), fix[&11].as_str(), fix[&41].as_str()), ("G", "7-9", "6"));
+        let parsed = order_from_cancel_replace(&fix, InstrumentId(1), 12).unwrap();
+        assert_eq!((parsed.order_id, parsed.client_order_id.as_str()), (OrderId(12), "7-9"));
+        assert_eq!((parsed.quantity, parsed.price, parsed.trader_id), (order.quantity, order.price, order.trader_id));
+        let (fix, _) = parse_fix_message(&order_cancel_request_to_fix("7-9", "c1", "BTC-USD", 10, "S", "T")).unwrap();
+        assert_eq!((fix[&35].as_str(), fix[&11].as_str(), fix[&41].as_str(), fix[&55].as_str()), ("F", "c1", "7-9", "BTC-USD"));
+
+        let mut fill = report(ExecType::PartialFill, OrderStatus::PartiallyFilled, 1, 2);
+        fill.last_qty = Some(Decimal::from(1));
+        fill.last_px = Some(Decimal::from(101));
+        let context = OrderContext::of_order(&order, "BTC-USD");
+        let (fix, _) = parse_fix_message(&execution_report_to_fix(&fill, &context, "7", 5, "S", "T")).unwrap();
+        let parsed = execution_report_from_fix(&fix).unwrap();
+        assert_eq!(
+            (parsed.exec_type, parsed.ord_status, parsed.side, parsed.symbol.as_deref()),
+            (ExecType::PartialFill, OrderStatus::PartiallyFilled, Some(Side::Sell), Some("BTC-USD"))
+        );
+        assert_eq!((parsed.cum_qty, parsed.leaves_qty, parsed.last_px), (Decimal::from(1), Decimal::from(2), fill.last_px));
+
+        let filled = report(ExecType::Fill, OrderStatus::Filled, 3, 0);
+        let (fix, _) = parse_fix_message(&execution_report_to_fix(&filled, &context, "7", 6, "S", "T")).unwrap();
+        assert_eq!(execution_report_from_fix(&fix).unwrap().exec_type, ExecType::Fill);
+        assert!(execution_report_from_fix(&FixMessage::from([(35, "0".to_string())])).is_err());
+    }
+}
@@ -1,13 +1,27 @@
-//! FIX 4.4 adapter (Phase 2): tag-value parse/build and mapping to/from Engine.
+//! FIX 4.4 and FIX 5.0 SP2 (over FIXT.1.1) adapter (Phase 2): tag-value parse/build and mapping to/from Engine.
 //!
-//! In-process TCP acceptor runs in [`fix::acceptor`]; this module provides message parsing,
-//! building, and conversion between FIX and engine types.
+//! In-process TCP acceptor runs in [`fix::acceptor`]; [`fix::initiator`] is the client side, for end-to-end
+//! tests and for routing orders to other FIX venues. This module provides message parsing, building, and
+//! conversion between FIX and engine types.
 
 mod acceptor;
+mod decoder;
+pub mod initiator;
+mod market_data;
 pub mod message;
+mod session_store;
 
-pub use acceptor::run_fix_acceptor;
+pub use acceptor::{run_fix_acceptor, run_fix_acceptor_tls, run_fix_acceptor_with_auth, run_fix_acceptor_with_sessions};
+pub use decoder::{FixDecodeError, FixDecoder};
+pub use initiator::{FixInitiator, FixInitiatorConfig};
 pub use message::{
-    execution_report_to_fix, execution_report_to_fix_with_side, order_from_cancel_replace,
-    order_from_new_order_single, parse_fix_message, FixMessage, FixWriter,
+    execution_report_from_fix, execution_report_to_fix, execution_report_to_fix_with_orig, fix_frame_len, fix_symbol, in_version,
+    market_data_incremental_to_fix, market_data_request_reject_to_fix, market_data_snapshot_to_fix, mass_quote_ack_to_fix,
+    order_cancel_reject_to_fix, order_cancel_replace_request_to_fix, order_cancel_request_to_fix, order_from_cancel_replace,
+    new_order_single_to_fix, order_from_new_order_single,
+    order_mass_cancel_report_to_fix,
+    parse_fix_fields, parse_fix_message, possible_duplicate, quote_from_mass_quote, transact_time, BusinessRejectReason,
+    CxlRejReason, FixExecutionReport, FixFieldError, FixMessage, FixVersion, FixWriter, MassCancelRejectReason, MdReqRejReason,
+    OrderCancelReject, OrderContext, OrderMassCancelReport, SessionRejectReason, MAX_BODY_LENGTH,
 };
+pub use session_store::{FixSessionState, FixSessionStore};
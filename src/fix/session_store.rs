@@ -0,0 +1,163 @@
+//! FIX session state kept between connections. A client that logs on again with the same CompIDs resumes its
+//! MsgSeqNums and can still cancel or replace the orders it entered before; with a file, sessions also survive
+//! a restart.
+
+use crate::fix::message::OrderContext;
+use crate::types::{OrderId, TraderId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Sent messages by MsgSeqNum: MsgType (35) and the bytes as written.
+pub(crate) type SentMessages = BTreeMap<u32, (String, Vec<u8>)>;
+
+/// What a session carries over to its next connection.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FixSessionState {
+    /// MsgSeqNum (34) expected on the next inbound message.
+    pub in_seq: u32,
+    /// MsgSeqNum of the next outbound message.
+    pub out_seq: u32,
+    pub cl_ord_to_order_id: HashMap<String, OrderId>,
+    /// Order attributes by ClOrdID, echoed on execution reports.
+    #[serde(default)]
+    pub cl_ord_to_order: HashMap<String, OrderContext>,
+    /// Trader the Logon that created the session was bound to (`None` when unbound). The CompIDs are the
+    /// client's to choose, so a later Logon resumes the session only when bound to the same trader.
+    #[serde(default)]
+    pub trader_id: Option<TraderId>,
+}
+
+impl Default for FixSessionState {
+    fn default() -> Self {
+        Self {
+            in_seq: 1,
+            out_seq: 1,
+            cl_ord_to_order_id: HashMap::new(),
+            cl_ord_to_order: HashMap::new(),
+            trader_id: None,
+        }
+    }
+}
+
+/// Session state by session key (`SenderCompID:TargetCompID` as the client sends them). Kept in memory and,
+/// when opened on a file, saved there after every change. Sent messages for ResendRequests are kept in memory
+/// only, so after a restart a resend is answered with a gap fill.
+#[derive(Debug, Default)]
+pub struct FixSessionStore {
+    path: Option<PathBuf>,
+    sessions: Mutex<HashMap<String, FixSessionState>>,
+    sent: Mutex<HashMap<String, SentMessages>>,
+    /// Sessions a connection has logged on (or is logging on) with.
+    active: Mutex<HashSet<String>>,
+}
+
+impl FixSessionStore {
+    /// Sessions kept in memory: they survive reconnects but not a restart.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Sessions saved to `path` (JSON), loaded from it now. A missing file is an empty store.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let sessions = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| format!("{}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Ok(Self {
+            path: Some(path),
+            sessions: Mutex::new(sessions),
+            ..Self::default()
+        })
+    }
+
+    /// The file sessions are saved to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Stored state of session `key`.
+    pub fn get(&self, key: &str) -> Option<FixSessionState> {
+        self.sessions.lock().expect("lock").get(key).cloned()
+    }
+
+    /// Store the state of session `key`, and save the file.
+    pub fn put(&self, key: &str, state: FixSessionState) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().expect("lock");
+        sessions.insert(key.to_string(), state);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(&*sessions).map_err(|e| e.to_string())?;
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    /// Take session `key` for a connection; `false` while another connection has it.
+    pub(crate) fn claim(&self, key: &str) -> bool {
+        self.active.lock().expect("lock").insert(key.to_string())
+    }
+
+    /// Give session `key` back, keeping the messages it sent for the next connection's ResendRequests.
+    pub(crate) fn release(&self, key: &str, sent: Option<SentMessages>) {
+        if let Some(sent) = sent {
+            self.sent.lock().expect("lock").insert(key.to_string(), sent);
+        }
+        self.active.lock().expect("lock").remove(key);
+    }
+
+    /// Messages session `key` sent on its earlier connections.
+    pub(crate) fn take_sent(&self, key: &str) -> SentMessages {
+        self.sent.lock().expect("lock").remove(key).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderType, Side, TimeInForce, TraderId};
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn saves_sessions_to_the_file_and_claims_one_connection_at_a_time() {
+        let path = std::env::temp_dir().join(format!("fix_sessions_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = FixSessionStore::open(&path).unwrap();
+        assert_eq!(store.get("CLIENT:DIRED"), None);
+        let mut state = FixSessionState {
+            in_seq: 7,
+            out_seq: 9,
+            ..FixSessionState::default()
+        };
+        state.cl_ord_to_order_id.insert("100".into(), OrderId(100));
+        state.cl_ord_to_order.insert(
+            "100".into(),
+            OrderContext {
+                symbol: "BTC-USD".into(),
+                side: Side::Sell,
+                ord_type: OrderType::Limit,
+                price: Some(Decimal::from(100)),
+                time_in_force: TimeInForce::IOC,
+                account: TraderId(2),
+            },
+        );
+        store.put("CLIENT:DIRED", state.clone()).unwrap();
+
+        assert!(store.claim("CLIENT:DIRED"));
+        assert!(!store.claim("CLIENT:DIRED"));
+        store.release("CLIENT:DIRED", Some(BTreeMap::from([(8, ("8".to_string(), b"raw".to_vec()))])));
+        assert!(store.claim("CLIENT:DIRED"));
+        assert_eq!(store.take_sent("CLIENT:DIRED").len(), 1);
+        assert!(store.take_sent("CLIENT:DIRED").is_empty());
+
+        let reopened = FixSessionStore::open(&path).unwrap();
+        assert_eq!(reopened.get("CLIENT:DIRED"), Some(state));
+        std::fs::write(&path, "not json").unwrap();
+        assert!(FixSessionStore::open(&path).unwrap_err().contains("fix_sessions_"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
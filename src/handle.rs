@@ -0,0 +1,171 @@
+//! Command-channel (actor) interface to a [`MultiEngine`] running on its own thread.
+//!
+//! [`EngineHandle::spawn`] moves the engine onto a dedicated thread; every clone of the handle sends typed
+//! commands over one channel and awaits the result on a oneshot, so async adapters never block on an engine
+//! lock. Commands run one at a time in the order they arrive.
+
+use crate::engine::{MatchingEngine, MultiEngine};
+use crate::execution::{ExecutionReport, Trade};
+use crate::types::{InstrumentId, Order, OrderId};
+use std::sync::mpsc;
+use tokio::sync::oneshot;
+
+type Fills = Result<(Vec<Trade>, Vec<ExecutionReport>), String>;
+
+/// A command for the engine thread and the channel its result goes back on.
+enum EngineCommand {
+    Submit {
+        order: Order,
+        reply: oneshot::Sender<Fills>,
+    },
+    Cancel {
+        order_id: OrderId,
+        reply: oneshot::Sender<Option<InstrumentId>>,
+    },
+    Modify {
+        order_id: OrderId,
+        replacement: Order,
+        reply: oneshot::Sender<Fills>,
+    },
+    /// Read-only access; the closure sends its own result.
+    Query(Box<dyn FnOnce(&MultiEngine) + Send>),
+}
+
+/// Runs commands until every handle is dropped. A caller that stopped waiting (dropped its future) does not
+/// stop the engine: the result is discarded.
+fn run(mut engine: MultiEngine, rx: mpsc::Receiver<EngineCommand>) {
+    while let Ok(command) = rx.recv() {
+        match command {
+            EngineCommand::Submit { order, reply } => {
+                let _ = reply.send(engine.submit_order(order));
+            }
+            EngineCommand::Cancel { order_id, reply } => {
+                let _ = reply.send(engine.cancel_order(order_id));
+            }
+            EngineCommand::Modify {
+                order_id,
+                replacement,
+                reply,
+            } => {
+                let _ = reply.send(engine.modify_order(order_id, &replacement));
+            }
+            EngineCommand::Query(query) => query(&engine),
+        }
+    }
+}
+
+/// Cloneable handle to an engine thread. The thread exits once the last handle is dropped and the commands
+/// already sent have run.
+#[derive(Clone, Debug)]
+pub struct EngineHandle {
+    tx: mpsc::Sender<EngineCommand>,
+}
+
+impl EngineHandle {
+    /// Move `engine` onto a new thread named `engine` and return a handle to it.
+    pub fn spawn(engine: MultiEngine) -> Self {
+        Self::spawn_named(engine, "engine".to_string())
+    }
+
+    pub(crate) fn spawn_named(engine: MultiEngine, name: String) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name(name)
+            .spawn(move || run(engine, rx))
+            .expect("spawn engine thread");
+        Self { tx }
+    }
+
+    /// Submit an order; resolves to its trades and reports.
+    pub async fn submit_order(&self, order: Order) -> Fills {
+        let (reply, rx) = oneshot::channel();
+        self.send(EngineCommand::Submit { order, reply })?;
+        rx.await.map_err(|_| engine_stopped())?
+    }
+
+    /// Cancel a resting order. Resolves to its instrument, or `None` if not found (or the engine has stopped).
+    pub async fn cancel_order(&self, order_id: OrderId) -> Option<InstrumentId> {
+        let (reply, rx) = oneshot::channel();
+        self.send(EngineCommand::Cancel { order_id, reply }).ok()?;
+        rx.await.ok().flatten()
+    }
+
+    /// Modify a resting order; resolves to the replacement's trades and reports.
+    pub async fn modify_order(&self, order_id: OrderId, replacement: Order) -> Fills {
+        let (reply, rx) = oneshot::channel();
+        self.send(EngineCommand::Modify {
+            order_id,
+            replacement,
+            reply,
+        })?;
+        rx.await.map_err(|_| engine_stopped())?
+    }
+
+    /// Run `f` against the engine between commands and resolve to what it returns (e.g. a book snapshot or
+    /// open orders). Keep `f` short: the engine processes nothing else meanwhile.
+    pub async fn query<R, F>(&self, f: F) -> Result<R, String>
+    where
+        R: Send + 'static,
+        F: FnOnce(&MultiEngine) -> R + Send + 'static,
+    {
+        let (reply, rx) = oneshot::channel();
+        self.send(EngineCommand::Query(Box::new(move |engine| {
+            let _ = reply.send(f(engine));
+        })))?;
+        rx.await.map_err(|_| engine_stopped())
+    }
+
+    fn send(&self, command: EngineCommand) -> Result<(), String> {
+        self.tx.send(command).map_err(|_| engine_stopped())
+    }
+}
+
+fn engine_stopped() -> String {
+    "Engine stopped".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderType, Side, TimeInForce, TraderId};
+    use rust_decimal::Decimal;
+
+    fn order(id: u64, side: Side) -> Order {
+        Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(2),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        }
+    }
+
+    #[tokio::test]
+    async fn handle_runs_commands_on_engine_thread_in_order() {
+        let handle = EngineHandle::spawn(MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]));
+        let other = handle.clone();
+        handle.submit_order(order(1, Side::Buy)).await.unwrap();
+        let (trades, _) = other.submit_order(order(2, Side::Sell)).await.unwrap();
+        assert_eq!(trades.len(), 1);
+
+        handle.submit_order(order(3, Side::Buy)).await.unwrap();
+        let replacement = Order {
+            quantity: Decimal::from(5),
+            ..order(4, Side::Buy)
+        };
+        assert!(handle.modify_order(OrderId(3), replacement).await.is_ok());
+        let best_bid = handle
+            .query(|engine| engine.book_snapshot_for(InstrumentId(1)).and_then(|s| s.best_bid))
+            .await
+            .unwrap();
+        assert_eq!(best_bid, Some(Decimal::from(100)));
+        assert_eq!(handle.cancel_order(OrderId(4)).await, Some(InstrumentId(1)));
+        assert_eq!(handle.cancel_order(OrderId(4)).await, None);
+        assert!(handle.submit_order(order(1, Side::Buy)).await.is_err());
+    }
+}
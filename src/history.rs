@@ -0,0 +1,267 @@
+//! Retained trade and execution report history, read a page at a time with a [`HistoryQuery`].
+//!
+//! Each engine keeps the last [`crate::engine::TRADE_HISTORY_CAPACITY`] trades and
+//! [`crate::engine::EXECUTION_HISTORY_CAPACITY`] execution reports in id order, together with the instrument and
+//! traders of their orders so queries can filter on them. Without a history log, history starts empty at restart
+//! or snapshot load; with one ([`crate::MultiEngine::set_history_log`]) every [`HistoryRecord`] is also appended
+//! to it, and the retained history is reloaded from it.
+
+use crate::execution::{ExecutionReport, Trade};
+use crate::types::{InstrumentId, OrderId, TradeId, TraderId};
+use std::collections::VecDeque;
+
+/// A trade or execution report as history keeps it, with the traders or instrument of its orders (`None` when the
+/// order was no longer tracked).
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum HistoryRecord {
+    Trade {
+        trade: Trade,
+        buyer: Option<TraderId>,
+        seller: Option<TraderId>,
+    },
+    Report {
+        report: ExecutionReport,
+        instrument_id: Option<InstrumentId>,
+        trader_id: Option<TraderId>,
+    },
+}
+
+impl HistoryRecord {
+    /// Engine sequence number of the trade or report.
+    pub fn seq(&self) -> u64 {
+        match self {
+            HistoryRecord::Trade { trade, .. } => trade.seq,
+            HistoryRecord::Report { report, .. } => report.seq,
+        }
+    }
+}
+
+/// Where a page starts. Ids are exclusive; pass a page's [`HistoryPage::next_cursor`] back in the same variant
+/// to get the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryCursor {
+    /// The first `limit` matches with an id greater than this (0 for the oldest retained), paging forward.
+    After(u64),
+    /// The last `limit` matches with an id less than this (`u64::MAX` for the newest), paging backward.
+    Before(u64),
+}
+
+/// Filters and page bounds for [`crate::MatchingEngine::trade_history`] and
+/// [`crate::MatchingEngine::execution_history`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryQuery {
+    pub instrument_id: Option<InstrumentId>,
+    /// Trades the trader was buyer or seller in; reports on the trader's orders.
+    pub trader_id: Option<TraderId>,
+    /// Only entries with a timestamp at or after this.
+    pub from: Option<u64>,
+    /// Only entries with a timestamp at or before this.
+    pub to: Option<u64>,
+    pub cursor: HistoryCursor,
+    pub limit: usize,
+}
+
+impl HistoryQuery {
+    /// The newest `limit` entries, unfiltered.
+    pub fn latest(limit: usize) -> Self {
+        Self {
+            instrument_id: None,
+            trader_id: None,
+            from: None,
+            to: None,
+            cursor: HistoryCursor::Before(u64::MAX),
+            limit,
+        }
+    }
+
+    fn matches(&self, instrument_id: Option<InstrumentId>, traders: &[Option<TraderId>], timestamp: u64) -> bool {
+        self.instrument_id.is_none_or(|id| instrument_id == Some(id))
+            && self.trader_id.is_none_or(|id| traders.contains(&Some(id)))
+            && self.from.is_none_or(|from| timestamp >= from)
+            && self.to.is_none_or(|to| timestamp <= to)
+    }
+}
+
+/// One page of history, oldest first in either direction.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct HistoryPage<T> {
+    pub items: Vec<T>,
+    /// Cursor for the next page in the same direction: the last id of a forward page, the first id of a
+    /// backward one. `None` when no more retained entries match.
+    pub next_cursor: Option<u64>,
+}
+
+trait Entry {
+    fn id(&self) -> u64;
+    fn matches(&self, query: &HistoryQuery) -> bool;
+}
+
+/// Ring buffer of the most recent entries (FIFO eviction), in id order.
+#[derive(Debug)]
+struct Ring<E> {
+    entries: VecDeque<E>,
+    capacity: usize,
+}
+
+impl<E: Entry> Ring<E> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, entry: E) {
+        self.entries.push_back(entry);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Ids are assigned in increasing order, so the buffer is sorted by id.
+    fn page(&self, query: &HistoryQuery) -> (Vec<&E>, Option<u64>) {
+        match query.cursor {
+            HistoryCursor::After(id) => {
+                let start = self.entries.partition_point(|e| e.id() <= id);
+                let mut matches = self.entries.range(start..).filter(|e| e.matches(query));
+                let page: Vec<&E> = matches.by_ref().take(query.limit).collect();
+                let more = matches.next().is_some();
+                let next = page.last().filter(|_| more).map(|e| e.id());
+                (page, next)
+            }
+            HistoryCursor::Before(id) => {
+                let end = self.entries.partition_point(|e| e.id() < id);
+                let mut matches = self.entries.range(..end).rev().filter(|e| e.matches(query));
+                let mut page: Vec<&E> = matches.by_ref().take(query.limit).collect();
+                let more = matches.next().is_some();
+                page.reverse();
+                let next = page.first().filter(|_| more).map(|e| e.id());
+                (page, next)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TradeEntry {
+    trade: Trade,
+    buyer: Option<TraderId>,
+    seller: Option<TraderId>,
+}
+
+impl Entry for TradeEntry {
+    fn id(&self) -> u64 {
+        self.trade.trade_id.0
+    }
+
+    fn matches(&self, query: &HistoryQuery) -> bool {
+        query.matches(Some(self.trade.instrument_id), &[self.buyer, self.seller], self.trade.timestamp)
+    }
+}
+
+/// The most recent trades, with the traders on each side.
+#[derive(Debug)]
+pub(crate) struct TradeStore {
+    ring: Ring<TradeEntry>,
+}
+
+impl TradeStore {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { ring: Ring::new(capacity) }
+    }
+
+    /// Retain `trades`; `trader` looks up the trader of each side's order.
+    pub(crate) fn record(&mut self, trades: &[Trade], trader: impl Fn(OrderId) -> Option<TraderId>) {
+        for trade in trades {
+            self.push(trade.clone(), trader(trade.buy_order_id), trader(trade.sell_order_id));
+        }
+    }
+
+    pub(crate) fn push(&mut self, trade: Trade, buyer: Option<TraderId>, seller: Option<TraderId>) {
+        self.ring.push(TradeEntry { trade, buyer, seller });
+    }
+
+    pub(crate) fn since(&self, trade_id: TradeId) -> Vec<Trade> {
+        let start = self.ring.entries.partition_point(|e| e.id() <= trade_id.0);
+        self.ring.entries.range(start..).map(|e| e.trade.clone()).collect()
+    }
+
+    pub(crate) fn for_instrument(&self, instrument_id: InstrumentId, limit: usize) -> Vec<Trade> {
+        self.before(TradeId(u64::MAX), Some(instrument_id), limit)
+    }
+
+    pub(crate) fn before(&self, trade_id: TradeId, instrument_id: Option<InstrumentId>, limit: usize) -> Vec<Trade> {
+        let query = HistoryQuery {
+            instrument_id,
+            cursor: HistoryCursor::Before(trade_id.0),
+            ..HistoryQuery::latest(limit)
+        };
+        self.page(&query).items
+    }
+
+    pub(crate) fn page(&self, query: &HistoryQuery) -> HistoryPage<Trade> {
+        let (entries, next_cursor) = self.ring.page(query);
+        HistoryPage {
+            items: entries.into_iter().map(|e| e.trade.clone()).collect(),
+            next_cursor,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ExecutionEntry {
+    report: ExecutionReport,
+    instrument_id: Option<InstrumentId>,
+    trader_id: Option<TraderId>,
+}
+
+impl Entry for ExecutionEntry {
+    fn id(&self) -> u64 {
+        self.report.exec_id.0
+    }
+
+    fn matches(&self, query: &HistoryQuery) -> bool {
+        query.matches(self.instrument_id, &[self.trader_id], self.report.timestamp)
+    }
+}
+
+/// The most recent execution reports, with the instrument and trader of their orders.
+#[derive(Debug)]
+pub(crate) struct ExecutionStore {
+    ring: Ring<ExecutionEntry>,
+}
+
+impl ExecutionStore {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { ring: Ring::new(capacity) }
+    }
+
+    /// Retain `reports`; `owner` looks up the instrument and trader of each report's order.
+    pub(crate) fn record(
+        &mut self,
+        reports: &[ExecutionReport],
+        owner: impl Fn(OrderId) -> Option<(InstrumentId, TraderId)>,
+    ) {
+        for report in reports {
+            let owner = owner(report.order_id);
+            self.push(report.clone(), owner.map(|(instrument_id, _)| instrument_id), owner.map(|(_, trader_id)| trader_id));
+        }
+    }
+
+    pub(crate) fn push(&mut self, report: ExecutionReport, instrument_id: Option<InstrumentId>, trader_id: Option<TraderId>) {
+        self.ring.push(ExecutionEntry {
+            report,
+            instrument_id,
+            trader_id,
+        });
+    }
+
+    pub(crate) fn page(&self, query: &HistoryQuery) -> HistoryPage<ExecutionReport> {
+        let (entries, next_cursor) = self.ring.page(query);
+        HistoryPage {
+            items: entries.into_iter().map(|e| e.report.clone()).collect(),
+            next_cursor,
+        }
+    }
+}
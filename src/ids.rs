@@ -0,0 +1,312 @@
+//! Trade, execution, and venue-assigned order id allocation shared by [`crate::Engine`] and
+//! [`crate::MultiEngine`].
+//!
+//! Without a store, ids start at 1 and only survive a restart through an engine snapshot, which may be older
+//! than the last id handed out. With an [`IdStore`], the allocator reserves ids in blocks of
+//! [`ID_RESERVATION_BLOCK`] and saves the end of the block before any id in it leaves the engine; on restart it
+//! resumes from the saved reservation, so ids never repeat even after a crash (at the cost of a gap of up to
+//! one block).
+
+use log::warn;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Ids reserved per store write.
+pub const ID_RESERVATION_BLOCK: u64 = 10_000;
+
+/// First order id the venue assigns itself (see [`IdAllocator::allocate_order_ids`]): far above the ids clients
+/// choose, and still exact as a JavaScript number (below 2^53).
+pub const VENUE_ORDER_ID_BASE: u64 = 1 << 52;
+
+/// Next trade, execution, and venue-assigned order ids. As stored, every id below it may already have been used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IdWatermark {
+    pub next_trade_id: u64,
+    pub next_exec_id: u64,
+    /// 0 in files from before venue-assigned order ids, read as [`VENUE_ORDER_ID_BASE`].
+    #[serde(default)]
+    pub next_order_id: u64,
+}
+
+/// Durable home for the id reservation.
+pub trait IdStore: Send + Sync {
+    /// The last saved watermark, or `None` if nothing was saved yet.
+    fn load(&self) -> Result<Option<IdWatermark>, String>;
+    /// Replace the saved watermark. Must be durable when it returns `Ok`.
+    fn save(&self, watermark: IdWatermark) -> Result<(), String>;
+}
+
+/// Keeps the watermark in memory (for tests, or engines sharing a process-lifetime store).
+#[derive(Debug, Default)]
+pub struct InMemoryIdStore {
+    watermark: Mutex<Option<IdWatermark>>,
+}
+
+impl InMemoryIdStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last saved watermark.
+    pub fn watermark(&self) -> Option<IdWatermark> {
+        *self.watermark.lock().expect("lock")
+    }
+}
+
+impl IdStore for InMemoryIdStore {
+    fn load(&self) -> Result<Option<IdWatermark>, String> {
+        Ok(self.watermark())
+    }
+
+    fn save(&self, watermark: IdWatermark) -> Result<(), String> {
+        *self.watermark.lock().expect("lock") = Some(watermark);
+        Ok(())
+    }
+}
+
+/// One small JSON file, replaced atomically (write to `<path>.tmp`, then rename) so a crash mid-write leaves
+/// the previous watermark.
+#[derive(Clone, Debug)]
+pub struct FileIdStore {
+    path: PathBuf,
+}
+
+impl FileIdStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl IdStore for FileIdStore {
+    fn load(&self) -> Result<Option<IdWatermark>, String> {
+        let data = match std::fs::read_to_string(&self.path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        serde_json::from_str(&data).map(Some).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, watermark: IdWatermark) -> Result<(), String> {
+        let json = serde_json::to_string(&watermark).map_err(|e| e.to_string())?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let file = std::fs::File::create(&tmp).map_err(|e| e.to_string())?;
+        std::io::Write::write_all(&mut &file, json.as_bytes()).map_err(|e| e.to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// Hands out trade and execution ids. Matching reads the next ids with [`IdAllocator::next_trade_id`] and
+/// [`IdAllocator::next_exec_id`]; the engine then calls [`IdAllocator::advance`] with how many it used, before
+/// the results are returned or published. Order ids the venue assigns itself are handed out by
+/// [`IdAllocator::allocate_order_ids`].
+pub struct IdAllocator {
+    next: IdWatermark,
+    /// End of the block saved to the store; ids below it are safe to hand out.
+    reserved: IdWatermark,
+    store: Option<Arc<dyn IdStore>>,
+}
+
+impl IdAllocator {
+    /// Ids from 1, not persisted.
+    pub fn new() -> Self {
+        let start = IdWatermark {
+            next_trade_id: 1,
+            next_exec_id: 1,
+            next_order_id: VENUE_ORDER_ID_BASE,
+        };
+        Self {
+            next: start,
+            reserved: start,
+            store: None,
+        }
+    }
+
+    pub fn next_trade_id(&self) -> u64 {
+        self.next.next_trade_id
+    }
+
+    pub fn next_exec_id(&self) -> u64 {
+        self.next.next_exec_id
+    }
+
+    /// The next ids (what a snapshot records).
+    pub fn watermark(&self) -> IdWatermark {
+        self.next
+    }
+
+    /// Persist through `store` from now on. Resumes from the stored reservation if it is ahead of the current
+    /// ids (e.g. after a crash) and reserves the first block. Returns `Err` if the store cannot be read or
+    /// written; the allocator is left unchanged.
+    pub fn set_store(&mut self, store: Arc<dyn IdStore>) -> Result<(), String> {
+        let stored = store.load()?.unwrap_or_default();
+        let next = max_watermark(self.next, stored);
+        let reserved = reservation(next);
+        store.save(reserved)?;
+        self.next = next;
+        self.reserved = reserved;
+        self.store = Some(store);
+        Ok(())
+    }
+
+    /// Stop persisting and return the store, if there is one; [`IdAllocator::restore`] then continues from
+    /// exactly the ids it is given.
+    pub fn take_store(&mut self) -> Option<Arc<dyn IdStore>> {
+        self.reserved = self.next;
+        self.store.take()
+    }
+
+    /// Continue from `watermark` (e.g. a snapshot's next ids). With a store, never goes back below ids that may
+    /// already have been handed out.
+    pub fn restore(&mut self, watermark: IdWatermark) {
+        let watermark = IdWatermark {
+            next_order_id: watermark.next_order_id.max(VENUE_ORDER_ID_BASE),
+            ..watermark
+        };
+        self.next = if self.store.is_some() {
+            max_watermark(watermark, max_watermark(self.next, self.reserved))
+        } else {
+            watermark
+        };
+        self.reserve_if_needed();
+    }
+
+    /// Mark `trades` trade ids and `reports` execution ids, starting at the next ids, as used. Saves a new
+    /// reservation when they run past the current one; a failed save is logged and retried on the next call.
+    pub fn advance(&mut self, trades: usize, reports: usize) {
+        self.next.next_trade_id += trades as u64;
+        self.next.next_exec_id += reports as u64;
+        self.reserve_if_needed();
+    }
+
+    /// Hand out `count` consecutive order ids for orders the venue numbers itself (FIX replacements and quote
+    /// sides) and mark them used; returns the first. Like [`IdAllocator::advance`], saves a new reservation
+    /// before they run past the current one.
+    pub fn allocate_order_ids(&mut self, count: u64) -> u64 {
+        let first = self.next.next_order_id;
+        self.next.next_order_id += count;
+        self.reserve_if_needed();
+        first
+    }
+
+    fn reserve_if_needed(&mut self) {
+        let Some(store) = &self.store else {
+            self.reserved = self.next;
+            return;
+        };
+        if self.next.next_trade_id < self.reserved.next_trade_id
+            && self.next.next_exec_id < self.reserved.next_exec_id
+            && self.next.next_order_id < self.reserved.next_order_id
+        {
+            return;
+        }
+        let reserved = reservation(self.next);
+        match store.save(reserved) {
+            Ok(()) => self.reserved = reserved,
+            Err(e) => warn!(
+                "id reservation save failed next_trade_id={} next_exec_id={} next_order_id={}: {}",
+                self.next.next_trade_id, self.next.next_exec_id, self.next.next_order_id, e
+            ),
+        }
+    }
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for IdAllocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdAllocator")
+            .field("next", &self.next)
+            .field("reserved", &self.reserved)
+            .field("persistent", &self.store.is_some())
+            .finish()
+    }
+}
+
+fn reservation(next: IdWatermark) -> IdWatermark {
+    IdWatermark {
+        next_trade_id: next.next_trade_id + ID_RESERVATION_BLOCK,
+        next_exec_id: next.next_exec_id + ID_RESERVATION_BLOCK,
+        next_order_id: next.next_order_id + ID_RESERVATION_BLOCK,
+    }
+}
+
+fn max_watermark(a: IdWatermark, b: IdWatermark) -> IdWatermark {
+    IdWatermark {
+        next_trade_id: a.next_trade_id.max(b.next_trade_id),
+        next_exec_id: a.next_exec_id.max(b.next_exec_id),
+        next_order_id: a.next_order_id.max(b.next_order_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocator_resumes_past_everything_handed_out_after_crash() {
+        let store = Arc::new(InMemoryIdStore::new());
+        let mut ids = IdAllocator::new();
+        ids.set_store(store.clone()).unwrap();
+        assert_eq!(ids.next_trade_id(), 1);
+        ids.advance(3, 6);
+        assert_eq!(ids.allocate_order_ids(2), VENUE_ORDER_ID_BASE);
+        let before_crash = ids.watermark();
+        // Crash: the snapshot is older than the last ids handed out.
+        let mut recovered = IdAllocator::new();
+        recovered.set_store(store.clone()).unwrap();
+        recovered.restore(IdWatermark {
+            next_trade_id: 2,
+            next_exec_id: 2,
+            next_order_id: 0,
+        });
+        assert!(recovered.next_trade_id() >= before_crash.next_trade_id);
+        assert!(recovered.next_exec_id() >= before_crash.next_exec_id);
+        assert!(recovered.allocate_order_ids(1) >= before_crash.next_order_id);
+
+        // Running past the reservation saves a new one before the ids are used again.
+        let start = recovered.next_trade_id();
+        recovered.advance(ID_RESERVATION_BLOCK as usize + 1, 0);
+        assert!(store.watermark().unwrap().next_trade_id > start + ID_RESERVATION_BLOCK);
+    }
+
+    #[test]
+    fn allocator_without_store_restores_exactly() {
+        let mut ids = IdAllocator::new();
+        ids.advance(5, 5);
+        ids.restore(IdWatermark {
+            next_trade_id: 2,
+            next_exec_id: 3,
+            next_order_id: VENUE_ORDER_ID_BASE + 4,
+        });
+        assert_eq!((ids.next_trade_id(), ids.next_exec_id()), (2, 3));
+        assert_eq!(ids.allocate_order_ids(1), VENUE_ORDER_ID_BASE + 4);
+        // Older snapshots have no venue order ids: they start at the base.
+        ids.restore(IdWatermark { next_order_id: 0, ..ids.watermark() });
+        assert_eq!(ids.allocate_order_ids(1), VENUE_ORDER_ID_BASE);
+    }
+
+    #[test]
+    fn file_store_round_trips() {
+        let path = std::env::temp_dir().join(format!("dire_ids_{}.json", std::process::id()));
+        let store = FileIdStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+        let watermark = IdWatermark {
+            next_trade_id: 7,
+            next_exec_id: 9,
+            next_order_id: VENUE_ORDER_ID_BASE,
+        };
+        store.save(watermark).unwrap();
+        assert_eq!(store.load().unwrap(), Some(watermark));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,127 @@
+//! Deterministic input journal: every command the engine accepted, in order, on top of the snapshot it started
+//! from. Replaying it with [`crate::MultiEngine::replay`] reproduces the same trades and reports, for crash
+//! recovery and certification testing.
+//!
+//! Only accepted commands are recorded. Rejections (validation, duplicate ids, risk and book limits) change
+//! no state, so a replay without those limits configured takes the same path.
+
+use crate::engine::{EngineSnapshot, InstrumentState, MultiEngine, PriceBand};
+use crate::execution::{ExecutionReport, Trade};
+use crate::scheduler::{TimedAction, TimerId};
+use crate::types::{InstrumentId, MarketState, Order, OrderId, Quote};
+use rust_decimal::Decimal;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One state-changing engine command.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum Command {
+    Submit { order: Order },
+    Cancel { order_id: OrderId },
+    Modify { order_id: OrderId, replacement: Order },
+    AddInstrument {
+        instrument_id: InstrumentId,
+        symbol: Option<String>,
+        tick_size: Decimal,
+    },
+    RemoveInstrument { instrument_id: InstrumentId },
+    SetInstrumentState {
+        instrument_id: InstrumentId,
+        state: InstrumentState,
+    },
+    SetInstrumentMarketState {
+        instrument_id: InstrumentId,
+        market_state: MarketState,
+    },
+    /// Instrument metadata as it was after [`MultiEngine::update_instrument`] (state changes are journaled
+    /// separately).
+    UpdateInstrument {
+        instrument_id: InstrumentId,
+        symbol: Option<String>,
+        tick_size: Decimal,
+        lot_size: Option<Decimal>,
+        price_band: Option<PriceBand>,
+    },
+    /// Two-sided quote replacing the trader's previous one (see [`MultiEngine::submit_quote`]).
+    Quote { quote: Quote },
+    /// Uncross of a crossed book (see [`MultiEngine::repair_crossed`]).
+    Uncross { instrument_id: InstrumentId },
+    /// Timer set with [`MultiEngine::schedule`].
+    Schedule { due: u64, action: TimedAction },
+    CancelTimer { timer_id: TimerId },
+    /// Session statistics restarted (see [`MultiEngine::reset_session_stats`]).
+    ResetSessionStats,
+    /// Engine time moved forward (see [`MultiEngine::advance_time`]); replay runs the same timers.
+    AdvanceTime { now: u64 },
+}
+
+/// An accepted command with its position in the journal and when the engine accepted it.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct JournalEntry {
+    /// Input sequence number: 1 for the first command after the base snapshot, then contiguous.
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch when the engine accepted the command (informational; replay does
+    /// not use it, matching only uses order timestamps).
+    pub timestamp: u64,
+    pub command: Command,
+}
+
+impl JournalEntry {
+    /// Entry `seq` for `command`, accepted now.
+    pub fn new(seq: u64, command: Command) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Self { seq, timestamp, command }
+    }
+}
+
+/// Accepted commands since `base` was taken, oldest first.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct InputJournal {
+    /// Engine state the commands apply to.
+    pub base: EngineSnapshot,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl InputJournal {
+    /// Empty journal starting from `base`.
+    pub fn new(base: EngineSnapshot) -> Self {
+        Self {
+            base,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Append `command` with the next input sequence number and the current time.
+    pub fn record(&mut self, command: Command) {
+        self.entries.push(JournalEntry::new(self.entries.len() as u64 + 1, command));
+    }
+}
+
+/// Consumer of every command the engine accepts, as the input journal records it (see
+/// [`MultiEngine::add_input_sink`]), e.g. to stream it to a standby. Called while the engine lock is held.
+pub trait InputSink: Send + Sync + std::fmt::Debug {
+    fn on_command(&self, command: &Command);
+    /// The engine state was replaced by [`MultiEngine::load_from_snapshot`]: commands from now on apply to the
+    /// loaded state, not to what came before.
+    fn on_reset(&self);
+}
+
+/// Result of [`MultiEngine::replay`]: the rebuilt engine and every trade and report the journal's commands
+/// produced, in order.
+#[derive(Debug)]
+pub struct Replay {
+    pub engine: MultiEngine,
+    pub trades: Vec<Trade>,
+    pub reports: Vec<ExecutionReport>,
+}
+
+/// Result of [`MultiEngine::replay_command_log`]: how many entries were applied, and how many failed again and
+/// were skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommandReplay {
+    pub applied: usize,
+    pub skipped: usize,
+}
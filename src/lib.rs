@@ -38,21 +38,68 @@
 //! trade/execution IDs yourself.
 
 pub mod api;
+pub mod api_error;
 pub mod audit;
 pub mod auth;
+pub mod binary_feed;
 pub mod engine;
+pub mod events;
 pub mod market_data_gen;
 pub mod execution;
 pub mod fix;
+pub mod handle;
+pub mod history;
+pub mod ids;
+pub mod journal;
 pub mod matching;
 pub mod order_book;
+pub mod order_feed;
 pub mod persistence;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod positions;
+pub mod remote;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod replication;
+pub mod risk;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scenario;
+pub mod scheduler;
+pub mod settlement;
+pub mod shard;
+pub mod stats;
+pub mod tls;
 pub mod types;
+pub mod venue;
 
-pub use engine::{BookSnapshot, Engine, EngineSnapshot, InstrumentMeta, MatchingEngine, MultiEngine};
+pub use engine::{
+    BookSnapshot, Engine, EngineSnapshot, InstrumentMeta, InstrumentState, InstrumentUpdate, MatchingEngine, MultiEngine, OrderFillState, PriceBand, QuoteState, ENGINE_SNAPSHOT_VERSION,
+};
+pub use events::{BookObserver, EngineEvent, EngineEventSink, InMemoryEventSink};
 pub use execution::{ExecutionReport, Trade};
-pub use matching::match_order;
-pub use order_book::{Fill, OrderBook};
-pub use auth::{AuthConfig, AuthUser, Role};
-pub use types::{ExecType, InstrumentId, Order, OrderId, OrderStatus, OrderType, RestingOrder, Side, TimeInForce, TraderId};
-pub use market_data_gen::{replay_into_engine, replay_into_engine_with_delay, Generator, GeneratorConfig};
+pub use handle::EngineHandle;
+pub use history::{HistoryCursor, HistoryPage, HistoryQuery};
+pub use ids::{FileIdStore, IdAllocator, IdStore, IdWatermark, InMemoryIdStore, ID_RESERVATION_BLOCK, VENUE_ORDER_ID_BASE};
+pub use journal::{Command, CommandReplay, InputJournal, InputSink, JournalEntry, Replay};
+pub use matching::{match_order, match_order_into, MatchOutput};
+pub use order_book::{
+    BookDelta, BookLevels, BookLimits, BookStats, Fill, LevelAction, LevelChange, OrderBook, OrderBookBuilder, OrderBookSnapshot, RestingOrderRef, DEFAULT_TICK_SIZE,
+};
+pub use auth::{
+    AuthConfig, AuthThrottlePolicy, AuthUser, Cidr, Credential, FixLogon, KeyGrant, KeyId, Permission, Permissions,
+    Role, SessionPolicy, SessionTokens,
+};
+pub use positions::{Position, PositionBook};
+pub use risk::RiskLimits;
+pub use scenario::{Scenario, ScenarioReport, ScenarioRunner};
+pub use scheduler::{FiredTimer, SchedulerSnapshot, TimedAction, Timer, TimerId};
+pub use shard::ShardedEngine;
+pub use stats::{InstrumentStats, StatsBook};
+pub use types::{BookOrder, ExecType, InstrumentId, MarketState, Order, OrderId, OrderStatus, OrderStatusView, OrderType, QueuePosition, Quote, RestingOrder, RestingOrderView, Side, TimeInForce, TradeId, TraderId};
+pub use venue::{BandConfig, RateLimits, SessionTime, TradingSession, VenueConfig};
+pub use market_data_gen::{
+    replay_into_engine, replay_into_engine_at_arrival_times, replay_into_engine_with_delay, GenEvent, Generator,
+    GeneratorConfig, PriceProcess,
+};
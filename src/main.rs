@@ -7,12 +7,256 @@
 //! for multiple (e.g. "1,2,3" or "1:AAPL,2:GOOG" for id:symbol). When INSTRUMENT_IDS is set
 //! it takes precedence over INSTRUMENT_ID.
 //! Set PERSISTENCE_PATH to a file path to save/load state (instruments, resting orders, market state) across restarts.
+//! FIX sessions (sequence numbers, ClOrdIDs) are then saved next to it in `<path>.fix-sessions`.
+//! A background thread saves the state STATE_SAVE_DEBOUNCE_MS (default 100) after any change, from REST, FIX,
+//! or the engine's timers. STATE_SNAPSHOT_INTERVAL_SECS and STATE_SNAPSHOT_EVERY_EVENTS also save it, keeping
+//! the last STATE_SNAPSHOT_RETAIN copies as `<path>.snapshot-<seq>`. The file is compressed binary unless
+//! STATE_SNAPSHOT_FORMAT=json.
+//! MAX_ORDERS_PER_TRADER, MAX_ORDERS_PER_LEVEL, and MAX_BOOK_ORDERS cap resting orders per book (unset = unlimited).
+//! SNAPSHOT_LEVELS adds the best N aggregated levels per side to market-data snapshots (unset = top of book only).
+//!
+//! TLS: TLS_CERT_PATH and TLS_KEY_PATH (PEM) serve HTTP and WebSockets over TLS, and FIX too unless
+//! FIX_TLS_CERT_PATH and FIX_TLS_KEY_PATH give it its own certificate. Unset = plaintext.
+//!
+//! Binary feed: BINARY_FEED_PORT serves the SBE-style market data feed over TCP; BINARY_FEED_UDP (`host:port`,
+//! e.g. a multicast group `239.1.1.1:30001`) sends it as UDP datagrams. ORDER_FEED_PORT serves the ITCH-style
+//! order-by-order feed over TCP. Unset = off.
+//!
+//! Shutdown: SIGINT, SIGTERM, or POST /admin/shutdown closes the market, stops accepting connections, waits up to
+//! SHUTDOWN_GRACE_MS (default 10000) for WebSockets to drain, saves state, writes out queued audit events, and exits.
+//!
+//! Audit events go to stdout through a background writer; AUDIT_BUFFER_CAPACITY and AUDIT_OVERFLOW (`block` or
+//! `drop`) size its queue and say what happens when it is full. AUDIT_STORE_PATH also keeps them in an append-only
+//! file queried by GET /admin/audit.
+//!
+//! Remote state: STATE_S3_BUCKET (builds with the `s3` feature; STATE_S3_PREFIX, STATE_S3_REGION,
+//! STATE_S3_ENDPOINT, and AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY/AWS_SESSION_TOKEN) uploads the state file and
+//! command log to an S3-compatible bucket every STATE_UPLOAD_INTERVAL_SECS (default 10), and a node starting
+//! without a state file at PERSISTENCE_PATH restores from it. STATE_DATABASE_URL (`postgres://…`, builds with the
+//! `postgres` feature) does the same with a PostgreSQL database in place of the bucket.
+//!
+//! Replication: REPLICATION_PORT serves warm standbys the state and every command the engine accepts.
+//! REPLICATION_PRIMARY (`host:port` of a primary's REPLICATION_PORT) starts this node as a standby: it follows
+//! the primary, refuses REST writes, and opens its FIX port only once promoted with POST /admin/failover.
+//!
+//! Settlement: SETTLEMENT_DATABASE_URL (`postgres://…`, builds with the `postgres` feature) stores orders, trades,
+//! and execution reports in PostgreSQL for downstream settlement, migrating the schema on startup.
 
 use dire_matching_engine::api;
-use dire_matching_engine::fix;
-use dire_matching_engine::InstrumentId;
+use dire_matching_engine::audit::AuditContext;
+use dire_matching_engine::binary_feed::{self, BinaryFeed};
+use dire_matching_engine::fix::{self, FixSessionStore};
+use dire_matching_engine::order_feed::{self, OrderFeed};
+use dire_matching_engine::persistence::{FilePersistence, SnapshotPolicy};
+use dire_matching_engine::remote::{self, ObjectStore};
+use dire_matching_engine::replication;
+use dire_matching_engine::settlement::SettlementSink;
+use dire_matching_engine::tls::{self, TlsPaths};
+use dire_matching_engine::{AuthConfig, BookLimits, InstrumentId, RiskLimits, VenueConfig};
+use std::time::Duration;
 use tokio::net::TcpListener;
 
+/// Default time open WebSockets get to drain on shutdown.
+const DEFAULT_SHUTDOWN_GRACE_MS: u64 = 10_000;
+
+/// TLS config from `cert_var`/`key_var`, or `fallback`; exits on a bad config.
+fn load_tls(cert_var: &str, key_var: &str, fallback: Option<&TlsPaths>) -> Option<(TlsPaths, std::sync::Arc<rustls::ServerConfig>)> {
+    let paths = match TlsPaths::from_env(cert_var, key_var) {
+        Ok(paths) => paths.or_else(|| fallback.cloned())?,
+        Err(e) => {
+            eprintln!("TLS config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match paths.load() {
+        Ok(config) => Some((paths, config)),
+        Err(e) => {
+            eprintln!("TLS config: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Completes on SIGINT or (on Unix) SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Start the binary market data feed's TCP and UDP publishers configured in the environment; exits on a bad
+/// config.
+fn start_binary_feed(state: &api::AppState) {
+    let tcp_port = std::env::var("BINARY_FEED_PORT").ok().filter(|s| !s.trim().is_empty());
+    let udp_destination = std::env::var("BINARY_FEED_UDP").ok().filter(|s| !s.trim().is_empty());
+    if tcp_port.is_none() && udp_destination.is_none() {
+        return;
+    }
+    let feed = BinaryFeed::attach(&state.engine);
+    if let Some(port) = tcp_port {
+        let addr = format!("0.0.0.0:{}", port.trim());
+        let listener = std::net::TcpListener::bind(&addr).unwrap_or_else(|e| {
+            eprintln!("BINARY_FEED_PORT: {}: {}", addr, e);
+            std::process::exit(1);
+        });
+        let (engine, feed) = (state.engine.clone(), feed.clone());
+        std::thread::spawn(move || binary_feed::run_binary_feed_tcp(listener, engine, feed));
+        eprintln!("Binary market data feed on tcp://{}", addr);
+    }
+    if let Some(destination) = udp_destination {
+        let destination: std::net::SocketAddr = destination.trim().parse().unwrap_or_else(|e| {
+            eprintln!("BINARY_FEED_UDP: {}: {}", destination, e);
+            std::process::exit(1);
+        });
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap_or_else(|e| {
+            eprintln!("BINARY_FEED_UDP: {}", e);
+            std::process::exit(1);
+        });
+        let engine = state.engine.clone();
+        std::thread::spawn(move || binary_feed::run_binary_feed_udp(socket, destination, engine, feed));
+        eprintln!("Binary market data feed to udp://{}", destination);
+    }
+}
+
+fn start_order_feed(state: &api::AppState) {
+    let Some(port) = std::env::var("ORDER_FEED_PORT").ok().filter(|s| !s.trim().is_empty()) else {
+        return;
+    };
+    let addr = format!("0.0.0.0:{}", port.trim());
+    let listener = std::net::TcpListener::bind(&addr).unwrap_or_else(|e| {
+        eprintln!("ORDER_FEED_PORT: {}: {}", addr, e);
+        std::process::exit(1);
+    });
+    let (engine, feed) = (state.engine.clone(), OrderFeed::attach(&state.engine));
+    std::thread::spawn(move || order_feed::run_order_feed_tcp(listener, engine, feed));
+    eprintln!("Order-by-order feed on tcp://{}", addr);
+}
+
+/// Attach the PostgreSQL settlement store at SETTLEMENT_DATABASE_URL, if set; exits if it cannot connect.
+#[cfg(feature = "postgres")]
+async fn start_settlement(state: &api::AppState) -> Option<std::sync::Arc<SettlementSink>> {
+    let url = std::env::var("SETTLEMENT_DATABASE_URL").ok().filter(|s| !s.trim().is_empty())?;
+    let store = dire_matching_engine::postgres::PostgresStore::connect(url.trim()).await.unwrap_or_else(|e| {
+        eprintln!("SETTLEMENT_DATABASE_URL: {}", e);
+        std::process::exit(1);
+    });
+    let sink = std::sync::Arc::new(SettlementSink::new(std::sync::Arc::new(store)));
+    state.engine.lock().expect("lock").add_event_sink(sink.clone());
+    eprintln!("Settlement store: PostgreSQL");
+    Some(sink)
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn start_settlement(_state: &api::AppState) -> Option<std::sync::Arc<SettlementSink>> {
+    if std::env::var("SETTLEMENT_DATABASE_URL").is_ok_and(|s| !s.trim().is_empty()) {
+        eprintln!("SETTLEMENT_DATABASE_URL is set, but this build has no PostgreSQL support (feature `postgres`)");
+        std::process::exit(1);
+    }
+    None
+}
+
+/// Where remote copies of the state go: the database at STATE_DATABASE_URL or the bucket at STATE_S3_BUCKET, if
+/// either is set; exits on a bad config, or when both are set.
+async fn remote_store() -> Option<std::sync::Arc<dyn ObjectStore>> {
+    match (database_state_store().await, s3_state_store()) {
+        (Some(_), Some(_)) => {
+            eprintln!("STATE_DATABASE_URL and STATE_S3_BUCKET are both set; choose one remote state store");
+            std::process::exit(1);
+        }
+        (database, bucket) => database.or(bucket),
+    }
+}
+
+/// The PostgreSQL database at STATE_DATABASE_URL for remote copies of the state, if set; exits if it cannot
+/// connect.
+#[cfg(feature = "postgres")]
+async fn database_state_store() -> Option<std::sync::Arc<dyn ObjectStore>> {
+    let url = std::env::var("STATE_DATABASE_URL").ok().filter(|s| !s.trim().is_empty())?;
+    let store = dire_matching_engine::postgres::PostgresStore::connect(url.trim()).await.unwrap_or_else(|e| {
+        eprintln!("STATE_DATABASE_URL: {}", e);
+        std::process::exit(1);
+    });
+    eprintln!("Remote state store: PostgreSQL");
+    Some(std::sync::Arc::new(store))
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn database_state_store() -> Option<std::sync::Arc<dyn ObjectStore>> {
+    if std::env::var("STATE_DATABASE_URL").is_ok_and(|s| !s.trim().is_empty()) {
+        eprintln!("STATE_DATABASE_URL is set, but this build has no PostgreSQL support (feature `postgres`)");
+        std::process::exit(1);
+    }
+    None
+}
+
+/// The S3 bucket at STATE_S3_BUCKET for remote copies of the state, if set; exits on a bad config.
+#[cfg(feature = "s3")]
+fn s3_state_store() -> Option<std::sync::Arc<dyn ObjectStore>> {
+    use dire_matching_engine::s3::{S3Config, S3Store};
+    let store = S3Config::from_env()?.and_then(S3Store::new).unwrap_or_else(|e| {
+        eprintln!("STATE_S3_BUCKET: {}", e);
+        std::process::exit(1);
+    });
+    eprintln!("Remote state store: {:?}", store);
+    Some(std::sync::Arc::new(store))
+}
+
+#[cfg(not(feature = "s3"))]
+fn s3_state_store() -> Option<std::sync::Arc<dyn ObjectStore>> {
+    if std::env::var("STATE_S3_BUCKET").is_ok_and(|s| !s.trim().is_empty()) {
+        eprintln!("STATE_S3_BUCKET is set, but this build has no S3 support (feature `s3`)");
+        std::process::exit(1);
+    }
+    None
+}
+
+/// Restore the state file and command log at `path` from `store` when there is no state file; exits if the
+/// store cannot be read.
+async fn restore_from_remote(store: &std::sync::Arc<dyn ObjectStore>, path: &str) {
+    let (store, persistence) = (store.clone(), FilePersistence::new(path));
+    let restored = tokio::task::spawn_blocking(move || remote::restore(store.as_ref(), &persistence))
+        .await
+        .expect("restore");
+    match restored {
+        Ok(Some(restored)) => eprintln!("Restored state from remote storage: {:?}", restored),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Remote restore: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Serve standbys on REPLICATION_PORT, if set; exits if it cannot be bound.
+fn start_replication_primary(state: &api::AppState) {
+    let Some(port) = std::env::var("REPLICATION_PORT").ok().filter(|s| !s.trim().is_empty()) else {
+        return;
+    };
+    let addr = format!("0.0.0.0:{}", port.trim());
+    let listener = std::net::TcpListener::bind(&addr).unwrap_or_else(|e| {
+        eprintln!("REPLICATION_PORT: {}: {}", addr, e);
+        std::process::exit(1);
+    });
+    let state = state.clone();
+    std::thread::spawn(move || replication::run_replication_primary(listener, state));
+    eprintln!("Replication to standbys on tcp://{}", addr);
+}
+
 fn parse_instruments() -> Vec<(InstrumentId, Option<String>)> {
     if let Ok(s) = std::env::var("INSTRUMENT_IDS") {
         let mut out = Vec::new();
@@ -57,7 +301,15 @@ async fn main() {
         .and_then(|s| s.parse().ok())
         .unwrap_or(9876);
 
-    let state = if let Ok(path) = std::env::var("PERSISTENCE_PATH") {
+    let remote_store = remote_store().await;
+    if let Some(store) = &remote_store {
+        let Ok(path) = std::env::var("PERSISTENCE_PATH") else {
+            eprintln!("Remote state (STATE_S3_BUCKET or STATE_DATABASE_URL) needs PERSISTENCE_PATH for the local copy");
+            std::process::exit(1);
+        };
+        restore_from_remote(store, &path).await;
+    }
+    let mut state = if let Ok(path) = std::env::var("PERSISTENCE_PATH") {
         eprintln!("Persistence enabled: {}", path);
         api::create_app_state_with_persistence(instruments, path)
     } else if instruments.len() == 1 && instruments[0].1.is_none() {
@@ -65,21 +317,131 @@ async fn main() {
     } else {
         api::create_app_state_with_instruments(instruments)
     };
-    let app = api::create_router_with_state(state.clone());
+    let limits = BookLimits::from_env();
+    if limits != BookLimits::default() {
+        eprintln!("Book limits: {:?}", limits);
+    }
+    state.engine.lock().expect("lock").set_book_limits(limits);
+    let venue_config = state.venue_config.lock().expect("lock").clone();
+    if venue_config.version == 0 {
+        // Never changed through /admin/config: start from the environment.
+        let venue_config = VenueConfig::from_env();
+        if venue_config.risk != RiskLimits::default() {
+            eprintln!("Risk limits: {:?}", venue_config.risk);
+        }
+        state.set_venue_config(venue_config);
+    } else {
+        eprintln!("Venue config version {} (persisted): {:?}", venue_config.version, venue_config);
+    }
+    let snapshot_levels = std::env::var("SNAPSHOT_LEVELS").ok().and_then(|s| s.trim().parse().ok());
+    state.engine.lock().expect("lock").set_snapshot_levels(snapshot_levels);
+    state.slow_consumer = api::SlowConsumerPolicy::from_env();
+    if state.slow_consumer != api::SlowConsumerPolicy::default() {
+        eprintln!("WebSocket slow-consumer policy: {:?}", state.slow_consumer);
+    }
+    state.heartbeat = api::HeartbeatPolicy::from_env();
+    if state.heartbeat != api::HeartbeatPolicy::default() {
+        eprintln!("WebSocket heartbeat policy: {:?}", state.heartbeat);
+    }
+
+    let standby = std::env::var("REPLICATION_PRIMARY")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(|primary| {
+            eprintln!("Standby of {}: order entry opens on POST /admin/failover", primary.trim());
+            replication::start_standby(&mut state, primary.trim())
+        });
+    start_replication_primary(&state);
+
+    let settlement = start_settlement(&state).await;
+
+    let grace = std::env::var("SHUTDOWN_GRACE_MS")
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .map_or(Duration::from_millis(DEFAULT_SHUTDOWN_GRACE_MS), Duration::from_millis);
+
+    let http_tls = load_tls("TLS_CERT_PATH", "TLS_KEY_PATH", None);
+    let fix_tls = load_tls("FIX_TLS_CERT_PATH", "FIX_TLS_KEY_PATH", http_tls.as_ref().map(|(paths, _)| paths));
 
     let fix_addr = format!("0.0.0.0:{}", fix_port);
-    let fix_listener = std::net::TcpListener::bind(&fix_addr).expect("FIX bind");
+    let bind_fix = |addr: &str| std::net::TcpListener::bind(addr).expect("FIX bind");
+    // A standby binds once promoted, so initiators see the port closed until then.
+    let fix_listener = standby.is_none().then(|| bind_fix(&fix_addr));
+    let fix_standby_addr = fix_addr.clone();
     let engine = state.engine.clone();
     let market_state = state.market_state.clone();
-    std::thread::spawn(move || {
-        fix::run_fix_acceptor(fix_listener, engine, market_state);
+    let fix_tls_config = fix_tls.as_ref().map(|(_, config)| config.clone());
+    let fix_sessions = match std::env::var("PERSISTENCE_PATH") {
+        Ok(path) => FixSessionStore::open(FilePersistence::new(path).fix_session_path()).unwrap_or_else(|e| {
+            eprintln!("FIX sessions: {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => FixSessionStore::in_memory(),
+    };
+    let fix_sessions = std::sync::Arc::new(fix_sessions);
+    let audit = state.audit_sink();
+    let fix_acceptor = std::thread::spawn(move || {
+        let fix_listener = fix_listener.unwrap_or_else(|| {
+            standby.expect("standby").wait_for_promotion();
+            eprintln!("Promoted: FIX acceptor opens");
+            bind_fix(&fix_standby_addr)
+        });
+        let auth = AuthConfig::from_env();
+        fix::run_fix_acceptor_with_sessions(fix_listener, engine, market_state, fix_tls_config, auth, fix_sessions, audit)
     });
-    eprintln!("FIX acceptor on {}", fix_addr);
+    state.fix_acceptor = Some(std::sync::Arc::new(fix_acceptor));
+    match &fix_tls {
+        Some((paths, _)) => eprintln!("FIX acceptor on {} (TLS, {})", fix_addr, paths.cert_path.display()),
+        None => eprintln!("FIX acceptor on {}", fix_addr),
+    }
+    start_binary_feed(&state);
+    start_order_feed(&state);
+    let snapshot_policy = SnapshotPolicy::from_env();
+    if api::start_snapshotter(&state, snapshot_policy).is_some() {
+        eprintln!("State snapshots: {:?}", snapshot_policy);
+    }
+    let upload_interval = remote::upload_interval_from_env();
+    let uploader = remote_store.and_then(|store| api::start_remote_uploader(&state, store, upload_interval));
+    if uploader.is_some() {
+        eprintln!("Remote state uploads every {:?}", upload_interval);
+    }
+    let app = api::create_router_with_state(state.clone());
 
     let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(&addr).await.expect("bind");
-    eprintln!("listening on http://{}", addr);
-    axum::serve(listener, app.into_make_service())
-        .await
-        .expect("serve");
+    let shutdown_state = state.clone();
+    let shutdown = async move {
+        tokio::select! {
+            _ = shutdown_signal() => {
+                eprintln!("Shutdown signal received");
+                shutdown_state.begin_shutdown("signal", &AuditContext::internal());
+            }
+            _ = shutdown_state.shutdown.requested() => {}
+        }
+    };
+    match http_tls {
+        Some((paths, config)) => {
+            let listener = std::net::TcpListener::bind(&addr).expect("bind");
+            eprintln!("listening on https://{} ({})", addr, paths.cert_path.display());
+            tls::serve_tls(listener, app, config, shutdown).await.expect("serve");
+        }
+        None => {
+            let listener = TcpListener::bind(&addr).await.expect("bind");
+            eprintln!("listening on http://{}", addr);
+            axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+                .with_graceful_shutdown(shutdown)
+                .await
+                .expect("serve");
+        }
+    }
+    if !state.shutdown.drained(grace).await {
+        eprintln!("{} WebSocket(s) still open after {:?}; closing them", state.shutdown.open_sockets(), grace);
+    }
+    state.flush();
+    if let Some(uploader) = uploader {
+        let _ = tokio::task::spawn_blocking(move || uploader.finish()).await;
+    }
+    if settlement.is_some_and(|sink| !sink.flush(grace)) {
+        eprintln!("Settlement records still unstored after {:?}", grace);
+    }
+    eprintln!("Shutdown complete");
 }
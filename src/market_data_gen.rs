@@ -2,13 +2,64 @@
 //!
 //! Deterministic, configurable order stream for replay tests, demos, and load tests.
 //! Same seed ⇒ same sequence of orders.
+//!
+//! With [`GeneratorConfig::arrival_rate`] set, orders arrive as a Poisson process: gaps between them are
+//! exponentially distributed, and each order's `timestamp` is the simulated clock in nanoseconds, which
+//! [`replay_into_engine_at_arrival_times`] plays back in real time.
+//!
+//! Limit prices are uniform in a fixed band by default. A [`PriceProcess`] instead moves a mid price from order
+//! to order (a random walk, or mean-reverting around a drifting level) and prices each order at an offset from
+//! it, so passive orders build a book around the mid and aggressive ones cross it.
 
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 
 use crate::types::{InstrumentId, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
 
+/// How limit prices are drawn. The mid-price processes step once per generated order, by a normal draw.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PriceProcess {
+    /// Uniform in `price_min..=price_max`.
+    #[default]
+    Uniform,
+    /// The mid starts at `start` and moves by a normal step with standard deviation `volatility`.
+    RandomWalk { start: f64, volatility: f64 },
+    /// Ornstein–Uhlenbeck: the mid starts at `start` and moves `reversion` (0.0..=1.0) of the way back to an
+    /// anchor level, plus a normal step with standard deviation `volatility`; the anchor starts at `start` too and
+    /// moves by `drift` per order.
+    MeanReverting { start: f64, reversion: f64, volatility: f64, drift: f64 },
+}
+
+/// One step of a generated command stream (see [`Generator::next_event`]). Cancels and modifies refer to an
+/// order submitted earlier in the stream.
+#[derive(Clone, Debug)]
+pub enum GenEvent {
+    /// A new order.
+    Submit(Order),
+    /// Cancel a live order.
+    Cancel { order_id: OrderId, timestamp: u64 },
+    /// Replace a live order: same id, side, and trader, with a new price and quantity.
+    Modify { order_id: OrderId, replacement: Order },
+}
+
+impl GenEvent {
+    /// When the event happens, on the same clock as [`Order::timestamp`].
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            GenEvent::Submit(order) | GenEvent::Modify { replacement: order, .. } => order.timestamp,
+            GenEvent::Cancel { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+impl From<Order> for GenEvent {
+    fn from(order: Order) -> Self {
+        GenEvent::Submit(order)
+    }
+}
+
 /// Configuration for the synthetic order generator.
 /// All ranges are inclusive. Same config + seed produces the same stream.
 #[derive(Clone, Debug)]
@@ -17,15 +68,23 @@ pub struct GeneratorConfig {
     pub seed: u64,
     /// Instrument for all generated orders.
     pub instrument_id: InstrumentId,
-    /// Number of orders to generate (used by [`Generator::take`] or when collecting).
+    /// Number of orders to generate (used by [`Generator::all_orders`]), or events (by [`Generator::all_events`]).
     pub num_orders: usize,
     /// Probability of Buy (0.0..=1.0). Sell otherwise.
     pub buy_ratio: f64,
     /// Probability of Limit order (0.0..=1.0). Market otherwise.
     pub limit_ratio: f64,
-    /// Price range (inclusive) for limit orders. Ignored for market.
+    /// Price range (inclusive) for limit orders with [`PriceProcess::Uniform`]. Ignored for market.
     pub price_min: i64,
     pub price_max: i64,
+    /// Where limit prices come from.
+    pub price_process: PriceProcess,
+    /// With a mid-price process, limit orders are priced `offset` away from the mid on their own side (buys below,
+    /// sells above), `offset` uniform in this range (inclusive); a negative offset crosses the mid.
+    pub price_offset_min: f64,
+    pub price_offset_max: f64,
+    /// With a mid-price process, limit prices are rounded to a multiple of this, and are at least one tick.
+    pub price_tick: Decimal,
     /// Quantity range (inclusive), whole units.
     pub quantity_min: u64,
     pub quantity_max: u64,
@@ -34,8 +93,21 @@ pub struct GeneratorConfig {
     pub tif_ioc_ratio: f64,
     /// Number of distinct trader IDs (1..=num_traders).
     pub num_traders: u64,
+    /// Probability that an event from [`Generator::next_event`] cancels a live order, then that it modifies one
+    /// (0.0..=1.0 together); a new order otherwise, and always while there is no live order. Live orders are the
+    /// GTC limit orders generated and not yet canceled; some will have filled by the time they are canceled or
+    /// modified, and replay skips those.
+    pub cancel_ratio: f64,
+    pub modify_ratio: f64,
+    /// Mean orders per second of simulated time. When set, gaps between orders are exponential with this rate
+    /// and timestamps are the simulated clock in nanoseconds (see [`ARRIVAL_CLOCK_TICKS_PER_SEC`]); when `None`
+    /// (or not positive and finite), timestamps are 1, 2, 3, …
+    pub arrival_rate: Option<f64>,
 }
 
+/// Timestamp units per second of the simulated clock used with [`GeneratorConfig::arrival_rate`].
+pub const ARRIVAL_CLOCK_TICKS_PER_SEC: u64 = 1_000_000_000;
+
 impl Default for GeneratorConfig {
     fn default() -> Self {
         Self {
@@ -46,11 +118,18 @@ impl Default for GeneratorConfig {
             limit_ratio: 0.9,
             price_min: 95,
             price_max: 105,
+            price_process: PriceProcess::Uniform,
+            price_offset_min: -1.0,
+            price_offset_max: 5.0,
+            price_tick: Decimal::ONE,
             quantity_min: 1,
             quantity_max: 100,
             tif_gtc_ratio: 0.8,
             tif_ioc_ratio: 0.1,
             num_traders: 5,
+            cancel_ratio: 0.0,
+            modify_ratio: 0.0,
+            arrival_rate: None,
         }
     }
 }
@@ -61,22 +140,132 @@ pub struct Generator {
     config: GeneratorConfig,
     next_order_id: u64,
     next_timestamp: u64,
+    /// Simulated seconds since the stream started, when orders arrive at [`GeneratorConfig::arrival_rate`].
+    clock: f64,
+    /// Mid price and the level it reverts to, with a mid-price [`PriceProcess`].
+    mid: f64,
+    anchor: f64,
+    /// GTC limit orders generated and not canceled since, as last replaced, for cancels and modifies to pick from.
+    live: Vec<Order>,
 }
 
 impl Generator {
     /// Builds a generator with the given config. Same config (including seed) ⇒ same stream.
     pub fn new(config: GeneratorConfig) -> Self {
         let rng = StdRng::seed_from_u64(config.seed);
+        let start = match config.price_process {
+            PriceProcess::Uniform => 0.0,
+            PriceProcess::RandomWalk { start, .. } | PriceProcess::MeanReverting { start, .. } => start,
+        };
         Self {
             rng,
-            config: config,
+            config,
             next_order_id: 1,
             next_timestamp: 1,
+            clock: 0.0,
+            mid: start,
+            anchor: start,
+            live: Vec::new(),
+        }
+    }
+
+    /// The current mid price, `None` with [`PriceProcess::Uniform`].
+    pub fn mid(&self) -> Option<f64> {
+        (self.config.price_process != PriceProcess::Uniform).then_some(self.mid)
+    }
+
+    /// A standard normal draw (Box–Muller).
+    fn standard_normal(&mut self) -> f64 {
+        let u1: f64 = 1.0 - self.rng.gen::<f64>();
+        let u2: f64 = self.rng.gen();
+        (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+    }
+
+    /// Moves the mid one step along the price process.
+    fn step_mid(&mut self) {
+        match self.config.price_process {
+            PriceProcess::Uniform => {}
+            PriceProcess::RandomWalk { volatility, .. } => self.mid += volatility * self.standard_normal(),
+            PriceProcess::MeanReverting { reversion, volatility, drift, .. } => {
+                self.mid += reversion * (self.anchor - self.mid) + volatility * self.standard_normal();
+                self.anchor += drift;
+            }
+        }
+    }
+
+    /// A limit price for `side`: uniform in the band, or offset from the mid and rounded to the tick.
+    fn limit_price(&mut self, side: Side) -> Decimal {
+        if self.config.price_process == PriceProcess::Uniform {
+            return Decimal::from(self.rng.gen_range(self.config.price_min..=self.config.price_max));
         }
+        let (low, high) = (self.config.price_offset_min, self.config.price_offset_max);
+        let offset = if low < high { self.rng.gen_range(low..=high) } else { low };
+        let price = match side {
+            Side::Buy => self.mid - offset,
+            Side::Sell => self.mid + offset,
+        };
+        let tick = Some(self.config.price_tick).filter(|tick| *tick > Decimal::ZERO).unwrap_or(Decimal::ONE);
+        let ticks = ((price / tick.to_f64().unwrap_or(1.0)).round() as i64).max(1);
+        Decimal::from(ticks) * tick
+    }
+
+    /// Mean arrival rate, if orders arrive as a Poisson process.
+    fn arrival_rate(&self) -> Option<f64> {
+        self.config.arrival_rate.filter(|rate| rate.is_finite() && *rate > 0.0)
+    }
+
+    /// Advances the simulated clock by an exponential gap and returns it as a timestamp. Inverse transform of a
+    /// uniform draw in (0, 1], so the gap is finite.
+    fn next_arrival(&mut self, rate: f64) -> u64 {
+        let u: f64 = 1.0 - self.rng.gen::<f64>();
+        self.clock += -u.ln() / rate;
+        (self.clock * ARRIVAL_CLOCK_TICKS_PER_SEC as f64) as u64
+    }
+
+    /// Advances the clock and the mid price by one event, and returns the event's timestamp.
+    fn next_step(&mut self) -> u64 {
+        let timestamp = match self.arrival_rate() {
+            Some(rate) => self.next_arrival(rate),
+            None => self.next_timestamp,
+        };
+        self.next_timestamp += 1;
+        self.step_mid();
+        timestamp
     }
 
     /// Generates the next order. Advances internal state (order id, timestamp, RNG).
     pub fn next_order(&mut self) -> Order {
+        let timestamp = self.next_step();
+        self.new_order(timestamp)
+    }
+
+    /// Generates the next event: a cancel or modify of a live order at [`GeneratorConfig::cancel_ratio`] and
+    /// [`GeneratorConfig::modify_ratio`], otherwise a new order as from [`Generator::next_order`]. With both
+    /// ratios zero, the same stream as [`Generator::next_order`].
+    pub fn next_event(&mut self) -> GenEvent {
+        let timestamp = self.next_step();
+        let (cancel, modify) = (self.config.cancel_ratio, self.config.modify_ratio);
+        if !self.live.is_empty() && cancel + modify > 0.0 {
+            let r = self.rng.gen::<f64>();
+            if r < cancel + modify {
+                let i = self.rng.gen_range(0..self.live.len());
+                if r < cancel {
+                    let order_id = self.live.swap_remove(i).order_id;
+                    return GenEvent::Cancel { order_id, timestamp };
+                }
+                let side = self.live[i].side;
+                let price = self.limit_price(side);
+                let quantity = Decimal::from(self.rng.gen_range(self.config.quantity_min..=self.config.quantity_max));
+                let replacement = Order { price: Some(price), quantity, timestamp, ..self.live[i].clone() };
+                self.live[i] = replacement.clone();
+                return GenEvent::Modify { order_id: replacement.order_id, replacement };
+            }
+        }
+        GenEvent::Submit(self.new_order(timestamp))
+    }
+
+    /// A new order at `timestamp`, remembered as live if it is a GTC limit.
+    fn new_order(&mut self, timestamp: u64) -> Order {
         let order_id = OrderId(self.next_order_id);
         self.next_order_id += 1;
         let client_order_id = format!("gen-{}", order_id.0);
@@ -94,14 +283,7 @@ impl Generator {
         let quantity = Decimal::from(
             self.rng.gen_range(self.config.quantity_min..=self.config.quantity_max),
         );
-        let price = if is_limit {
-            let p = self
-                .rng
-                .gen_range(self.config.price_min..=self.config.price_max);
-            Some(Decimal::from(p))
-        } else {
-            None
-        };
+        let price = if is_limit { Some(self.limit_price(side)) } else { None };
         let r = self.rng.gen::<f64>();
         let time_in_force = if r < self.config.tif_gtc_ratio {
             TimeInForce::GTC
@@ -110,12 +292,10 @@ impl Generator {
         } else {
             TimeInForce::FOK
         };
-        let timestamp = self.next_timestamp;
-        self.next_timestamp += 1;
         let trader_id = TraderId(
             self.rng.gen_range(1..=self.config.num_traders.max(1)),
         );
-        Order {
+        let order = Order {
             order_id,
             client_order_id,
             instrument_id: self.config.instrument_id,
@@ -126,7 +306,11 @@ impl Generator {
             time_in_force,
             timestamp,
             trader_id,
+        };
+        if is_limit && time_in_force == TimeInForce::GTC && self.config.cancel_ratio + self.config.modify_ratio > 0.0 {
+            self.live.push(order.clone());
         }
+        order
     }
 
     /// Returns a vector of exactly `n` orders (or all remaining if generator is finite).
@@ -139,45 +323,113 @@ impl Generator {
     pub fn all_orders(&mut self) -> Vec<Order> {
         self.take_orders(self.config.num_orders)
     }
+
+    /// Returns the next `n` events. Advances the generator state.
+    pub fn take_events(&mut self, n: usize) -> Vec<GenEvent> {
+        (0..n).map(|_| self.next_event()).collect()
+    }
+
+    /// Returns `config.num_orders` events.
+    pub fn all_events(&mut self) -> Vec<GenEvent> {
+        self.take_events(self.config.num_orders)
+    }
 }
 
-/// Replays a sequence of orders into the engine. Returns total trades and reports count (or first error).
-/// For rate-limited feed, use [`replay_into_engine_with_delay`] or loop with your own delay.
-pub fn replay_into_engine<E>(engine: &mut E, orders: impl IntoIterator<Item = Order>) -> Result<(usize, usize), String>
+/// Applies one event to the engine and returns its trade and report counts. A cancel or modify of an order that is
+/// no longer resting (it filled, or was canceled) does nothing, as a venue rejects a late cancel.
+fn apply_event<E>(engine: &mut E, event: GenEvent) -> Result<(usize, usize), String>
 where
     E: crate::MatchingEngine,
+{
+    let (trades, reports) = match event {
+        GenEvent::Submit(order) => engine.submit_order(order)?,
+        GenEvent::Cancel { order_id, .. } => {
+            engine.cancel_order(order_id);
+            return Ok((0, 0));
+        }
+        GenEvent::Modify { order_id, replacement } => {
+            if engine.get_order(order_id).is_none() {
+                return Ok((0, 0));
+            }
+            engine.modify_order(order_id, &replacement)?
+        }
+    };
+    Ok((trades.len(), reports.len()))
+}
+
+/// Replays a sequence of orders, or of [`GenEvent`]s, into the engine. Returns total trades and reports count (or
+/// first error). For rate-limited feed, use [`replay_into_engine_with_delay`], [`replay_into_engine_at_arrival_times`],
+/// or loop with your own delay.
+pub fn replay_into_engine<E, T>(engine: &mut E, events: impl IntoIterator<Item = T>) -> Result<(usize, usize), String>
+where
+    E: crate::MatchingEngine,
+    T: Into<GenEvent>,
 {
     let mut total_trades = 0usize;
     let mut total_reports = 0usize;
-    for order in orders {
-        let (trades, reports) = engine.submit_order(order)?;
-        total_trades += trades.len();
-        total_reports += reports.len();
+    for event in events {
+        let (trades, reports) = apply_event(engine, event.into())?;
+        total_trades += trades;
+        total_reports += reports;
     }
     Ok((total_trades, total_reports))
 }
 
-/// Replays orders into the engine with a delay between each order (e.g. for demos or rate-limited load).
-/// `delay_per_order` is applied after each submission. Returns total trades and reports count (or first error).
-pub fn replay_into_engine_with_delay<E>(
+/// Replays orders or events into the engine with a delay between each (e.g. for demos or rate-limited load).
+/// `delay_per_order` is applied after each one. Returns total trades and reports count (or first error).
+/// For the bursts of a generated arrival process, use [`replay_into_engine_at_arrival_times`].
+pub fn replay_into_engine_with_delay<E, T>(
     engine: &mut E,
-    orders: impl IntoIterator<Item = Order>,
+    events: impl IntoIterator<Item = T>,
     delay_per_order: std::time::Duration,
 ) -> Result<(usize, usize), String>
 where
     E: crate::MatchingEngine,
+    T: Into<GenEvent>,
 {
     let mut total_trades = 0usize;
     let mut total_reports = 0usize;
-    for order in orders {
-        let (trades, reports) = engine.submit_order(order)?;
-        total_trades += trades.len();
-        total_reports += reports.len();
+    for event in events {
+        let (trades, reports) = apply_event(engine, event.into())?;
+        total_trades += trades;
+        total_reports += reports;
         std::thread::sleep(delay_per_order);
     }
     Ok((total_trades, total_reports))
 }
 
+/// Replays orders or events into the engine at the pace of their timestamps, read as
+/// [`ARRIVAL_CLOCK_TICKS_PER_SEC`] per second (as generated with [`GeneratorConfig::arrival_rate`]): each is
+/// applied once its timestamp's distance from the first one's has passed, divided by `speed` (2.0 replays twice as
+/// fast; not positive, without waiting). Events already due are applied without sleeping, so bursts stay bursts
+/// and a slow engine catches up rather than drifting. Returns total trades and reports count (or first error).
+pub fn replay_into_engine_at_arrival_times<E, T>(
+    engine: &mut E,
+    events: impl IntoIterator<Item = T>,
+    speed: f64,
+) -> Result<(usize, usize), String>
+where
+    E: crate::MatchingEngine,
+    T: Into<GenEvent>,
+{
+    let start = std::time::Instant::now();
+    let mut first_timestamp = None;
+    let mut total_trades = 0usize;
+    let mut total_reports = 0usize;
+    for event in events {
+        let event = event.into();
+        let first = *first_timestamp.get_or_insert(event.timestamp());
+        let offset = event.timestamp().saturating_sub(first) as f64 / ARRIVAL_CLOCK_TICKS_PER_SEC as f64 / speed;
+        if let Some(wait) = std::time::Duration::try_from_secs_f64(offset).ok().and_then(|due| due.checked_sub(start.elapsed())) {
+            std::thread::sleep(wait);
+        }
+        let (trades, reports) = apply_event(engine, event)?;
+        total_trades += trades;
+        total_reports += reports;
+    }
+    Ok((total_trades, total_reports))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -237,4 +489,169 @@ mod tests {
         assert!(total_reports >= 20);
         assert!(total_trades <= 20 * 20); // at most N^2 possible matches
     }
+
+    #[test]
+    fn arrival_rate_gives_exponential_gaps_on_a_nanosecond_clock() {
+        let config = GeneratorConfig {
+            seed: 7,
+            num_orders: 20_000,
+            arrival_rate: Some(1_000.0),
+            ..Default::default()
+        };
+        let orders = Generator::new(config.clone()).all_orders();
+        let timestamps: Vec<u64> = orders.iter().map(|o| o.timestamp).collect();
+        assert_eq!(timestamps, Generator::new(config).all_orders().iter().map(|o| o.timestamp).collect::<Vec<_>>());
+        assert!(timestamps.windows(2).all(|w| w[0] <= w[1]), "the clock never goes back");
+        let gaps: Vec<f64> = timestamps.windows(2).map(|w| (w[1] - w[0]) as f64 / 1e9).collect();
+        let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+        let sd = (gaps.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / gaps.len() as f64).sqrt();
+        assert!((mean - 0.001).abs() < 0.00005, "mean gap {} is 1 / rate", mean);
+        assert!((sd / mean - 1.0).abs() < 0.05, "exponential gaps vary as much as their mean ({})", sd / mean);
+        let short = gaps.iter().filter(|&&g| g < mean / 10.0).count() as f64 / gaps.len() as f64;
+        assert!((short - (1.0 - (-0.1f64).exp())).abs() < 0.01, "bursts: {} of gaps under a tenth of the mean", short);
+
+        // Without a rate the timestamps count up as before.
+        let sequential = Generator::new(GeneratorConfig { arrival_rate: Some(0.0), num_orders: 3, ..Default::default() }).all_orders();
+        assert_eq!(sequential.iter().map(|o| o.timestamp).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn mean_reverting_mid_stays_near_its_drifting_anchor_and_a_random_walk_wanders() {
+        let run = |price_process| {
+            let mut generator = Generator::new(GeneratorConfig {
+                seed: 11,
+                limit_ratio: 1.0,
+                price_process,
+                price_tick: Decimal::new(1, 2),
+                ..Default::default()
+            });
+            (0..5_000)
+                .map(|i| {
+                    let order = generator.next_order();
+                    (i, generator.mid().unwrap(), order)
+                })
+                .collect::<Vec<_>>()
+        };
+        let reverting = run(PriceProcess::MeanReverting { start: 100.0, reversion: 0.1, volatility: 0.5, drift: 0.01 });
+        let furthest = reverting.iter().map(|(i, mid, _)| (mid - (100.0 + 0.01 * *i as f64)).abs()).fold(0.0, f64::max);
+        assert!(furthest < 6.0, "mid strayed {} from the anchor", furthest);
+        assert!(reverting.last().unwrap().1 > 140.0, "the anchor drifted the mid up");
+        for (_, mid, order) in &reverting {
+            let price = order.price.unwrap();
+            assert_eq!(price, price.round_dp(2), "on the tick");
+            let offset = match order.side {
+                Side::Buy => mid - price.to_f64().unwrap(),
+                Side::Sell => price.to_f64().unwrap() - mid,
+            };
+            assert!((-1.01..=5.01).contains(&offset), "{} from the mid", offset);
+        }
+
+        let walk = run(PriceProcess::RandomWalk { start: 100.0, volatility: 0.5 });
+        let furthest = walk.iter().map(|(_, mid, _)| (mid - 100.0).abs()).fold(0.0, f64::max);
+        assert!(furthest > 10.0, "a random walk wanders ({})", furthest);
+        assert_eq!(Generator::new(GeneratorConfig::default()).mid(), None);
+    }
+
+    #[test]
+    fn mid_price_streams_trade_and_build_a_two_sided_book() {
+        use crate::Engine;
+        let orders = Generator::new(GeneratorConfig {
+            seed: 5,
+            num_orders: 2_000,
+            tif_gtc_ratio: 1.0,
+            tif_ioc_ratio: 0.0,
+            price_process: PriceProcess::MeanReverting { start: 100.0, reversion: 0.05, volatility: 0.3, drift: 0.0 },
+            ..Default::default()
+        })
+        .all_orders();
+        let mut engine = Engine::new(InstrumentId(1));
+        let (trades, _) = replay_into_engine(&mut engine, orders).unwrap();
+        assert!(trades > 200, "aggressive orders trade ({})", trades);
+        let (bid, ask) = (engine.best_bid().unwrap(), engine.best_ask().unwrap());
+        assert!(bid < ask && bid > Decimal::from(90) && ask < Decimal::from(110), "book {} / {} around the mid", bid, ask);
+    }
+
+    #[test]
+    fn event_streams_cancel_and_modify_live_orders_and_replay_them() {
+        use crate::{Engine, MatchingEngine};
+        use std::collections::HashMap;
+        let config = GeneratorConfig {
+            seed: 21,
+            num_orders: 3_000,
+            cancel_ratio: 0.2,
+            modify_ratio: 0.15,
+            ..Default::default()
+        };
+        let events = Generator::new(config.clone()).all_events();
+        let mut live: HashMap<OrderId, Order> = HashMap::new();
+        let (mut cancels, mut modifies) = (0, 0);
+        for event in &events {
+            match event {
+                GenEvent::Submit(order) => {
+                    if order.order_type == OrderType::Limit && order.time_in_force == TimeInForce::GTC {
+                        live.insert(order.order_id, order.clone());
+                    }
+                }
+                GenEvent::Cancel { order_id, .. } => {
+                    assert!(live.remove(order_id).is_some(), "cancel of {:?}, which is not live", order_id);
+                    cancels += 1;
+                }
+                GenEvent::Modify { order_id, replacement } => {
+                    let original = live.get(order_id).expect("modify of a live order");
+                    assert_eq!((replacement.side, replacement.trader_id), (original.side, original.trader_id));
+                    live.insert(*order_id, replacement.clone());
+                    modifies += 1;
+                }
+            }
+        }
+        // Fewer than the ratios alone when no order is live; between themselves, in proportion.
+        let share = cancels as f64 / (cancels + modifies) as f64;
+        assert!(cancels + modifies > 900 && (share - 0.2 / 0.35).abs() < 0.05, "{} cancels, {} modifies", cancels, modifies);
+        let timestamps: Vec<u64> = events.iter().map(GenEvent::timestamp).collect();
+        assert_eq!(timestamps, (1..=3_000).collect::<Vec<_>>());
+
+        // Replay applies every kind; what filled in the meantime is skipped. What rests is what the stream left live.
+        let mut engine = Engine::new(InstrumentId(1));
+        let (_, reports) = replay_into_engine(&mut engine, events).unwrap();
+        assert!(reports > 2_000);
+        let resting: Vec<OrderId> = live.keys().copied().filter(|id| engine.get_order(*id).is_some()).collect();
+        assert!(!resting.is_empty());
+        let book = engine.book_levels_for(InstrumentId(1)).unwrap();
+        let resting_quantity: Decimal = book.bids.iter().chain(book.asks.iter()).map(|(_, quantity)| *quantity).sum();
+        let live_quantity: Decimal = resting.iter().map(|id| engine.get_order(*id).unwrap().remaining_quantity).sum();
+        assert_eq!(resting_quantity, live_quantity, "nothing canceled is still resting");
+
+        // Without cancels or modifies, events are the order stream.
+        let plain = GeneratorConfig { seed: 21, num_orders: 50, ..Default::default() };
+        let orders = Generator::new(plain.clone()).all_orders();
+        let submitted: Vec<Order> = Generator::new(plain)
+            .all_events()
+            .into_iter()
+            .map(|event| match event {
+                GenEvent::Submit(order) => order,
+                other => panic!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(serde_json::to_string(&submitted).unwrap(), serde_json::to_string(&orders).unwrap());
+    }
+
+    #[test]
+    fn replay_at_arrival_times_follows_the_simulated_clock() {
+        use crate::Engine;
+        let orders = Generator::new(GeneratorConfig {
+            seed: 3,
+            num_orders: 50,
+            arrival_rate: Some(1_000.0),
+            ..Default::default()
+        })
+        .all_orders();
+        let span = std::time::Duration::from_nanos(orders[49].timestamp - orders[0].timestamp);
+        let start = std::time::Instant::now();
+        let (_, reports) = replay_into_engine_at_arrival_times(&mut Engine::new(InstrumentId(1)), orders.clone(), 2.0).unwrap();
+        assert!(reports >= 50);
+        assert!(start.elapsed() >= span / 2, "{:?} at double speed of {:?}", start.elapsed(), span);
+        let start = std::time::Instant::now();
+        replay_into_engine_at_arrival_times(&mut Engine::new(InstrumentId(1)), orders, 0.0).unwrap();
+        assert!(start.elapsed() < span / 2);
+    }
 }
@@ -5,20 +5,63 @@
 
 use crate::execution::{ExecutionReport, Trade};
 use crate::order_book::{Fill, OrderBook};
-use crate::types::{ExecType, ExecutionId, Order, OrderStatus, Side, TimeInForce, TradeId};
+use crate::types::{ExecType, ExecutionId, Order, OrderId, OrderStatus, Side, TimeInForce, TradeId};
 use rust_decimal::Decimal;
 
 /// Run matching for one order against the book. Price-time priority, partial fills, TIF (GTC/IOC/FOK), self-trade prevention.
 /// Returns (trades, execution_reports). Reports include one per fill for resting orders and the aggressor's New/PartialFill/Fill or Canceled.
+///
+/// Allocates fresh vectors per call; on a hot path use [`match_order_into`] with a reused [`MatchOutput`].
 pub fn match_order(
     book: &mut OrderBook,
     order: &Order,
     next_trade_id: u64,
     next_exec_id: u64,
 ) -> (Vec<Trade>, Vec<ExecutionReport>) {
+    let mut out = MatchOutput::new();
+    match_order_into(book, order, next_trade_id, next_exec_id, &mut out);
+    out.take()
+}
+
+/// Reusable buffers for [`match_order_into`]. Keep one per engine (or thread) so that, once the buffers have
+/// grown to the usual order size, matching allocates nothing for its trades, reports, and fills.
+#[derive(Debug, Default)]
+pub struct MatchOutput {
+    pub trades: Vec<Trade>,
+    pub reports: Vec<ExecutionReport>,
+    /// Scratch fills from the book, kept only for their capacity.
+    fills: Vec<Fill>,
+}
+
+impl MatchOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Empty the buffers, keeping their capacity.
+    pub fn clear(&mut self) {
+        self.trades.clear();
+        self.reports.clear();
+        self.fills.clear();
+    }
+
+    /// Move the trades and reports out (the buffers start over empty; the fills scratch keeps its capacity).
+    pub fn take(&mut self) -> (Vec<Trade>, Vec<ExecutionReport>) {
+        (std::mem::take(&mut self.trades), std::mem::take(&mut self.reports))
+    }
+}
+
+/// Like [`match_order`], writing the trades and reports into `out` (cleared first) instead of new vectors.
+pub fn match_order_into(
+    book: &mut OrderBook,
+    order: &Order,
+    next_trade_id: u64,
+    next_exec_id: u64,
+    out: &mut MatchOutput,
+) {
     let instrument_id = book.instrument_id();
-    let mut trades = Vec::new();
-    let mut reports = Vec::new();
+    out.clear();
+    let MatchOutput { trades, reports, fills } = out;
     let mut exec_id = next_exec_id;
     let mut trade_id = next_trade_id;
 
@@ -47,18 +90,17 @@ pub fn match_order(
             last_qty: None,
             last_px: None,
             timestamp: order.timestamp,
+            orig_order_id: None,
+            seq: 0,
         });
-        return (trades, reports);
+        return;
     }
 
-    let fills: Vec<Fill> = match order.side {
-        Side::Buy => book.take_from_asks(price_limit, order.quantity, order.trader_id),
-        Side::Sell => book.take_from_bids(price_limit, order.quantity, order.trader_id),
-    };
+    book.take_into(order.side, price_limit, order.quantity, order.trader_id, fills);
 
     let mut filled_qty = Decimal::ZERO;
     let mut avg_px_sum = Decimal::ZERO;
-    for f in &fills {
+    for f in fills.iter() {
         filled_qty += f.quantity;
         avg_px_sum += f.price * f.quantity;
     }
@@ -70,7 +112,7 @@ pub fn match_order(
     let remaining = order.quantity - filled_qty;
 
     // Emit trades and execution reports for resting orders
-    for f in &fills {
+    for f in fills.iter() {
         let (buy_oid, sell_oid) = match order.side {
             Side::Buy => (order.order_id, f.resting_order_id),
             Side::Sell => (f.resting_order_id, order.order_id),
@@ -84,6 +126,7 @@ pub fn match_order(
             quantity: f.quantity,
             timestamp: order.timestamp,
             aggressor_side: order.side,
+            seq: 0,
         });
         trade_id += 1;
         // Resting order report (PartialFill or Fill)
@@ -100,12 +143,14 @@ pub fn match_order(
             } else {
                 OrderStatus::PartiallyFilled
             },
-            filled_quantity: f.quantity,
-            remaining_quantity: Decimal::ZERO, // per-fill report; full state would require lookup
+            filled_quantity: f.quantity, // per-fill report; cumulative fills would require lookup
+            remaining_quantity: f.resting_remaining,
             avg_price: Some(f.price),
             last_qty: Some(f.quantity),
             last_px: Some(f.price),
             timestamp: order.timestamp,
+            orig_order_id: None,
+            seq: 0,
         });
         exec_id += 1;
     }
@@ -124,8 +169,10 @@ pub fn match_order(
             last_qty: None,
             last_px: None,
             timestamp: order.timestamp,
+            orig_order_id: None,
+            seq: 0,
         });
-        return (trades, reports);
+        return;
     }
 
     let aggressor_status = if remaining <= Decimal::ZERO {
@@ -154,21 +201,124 @@ pub fn match_order(
         last_qty: fills.last().map(|f| f.quantity),
         last_px: fills.last().map(|f| f.price),
         timestamp: order.timestamp,
+        orig_order_id: None,
+        seq: 0,
     });
 
     // GTC: add remainder to book. IOC/FOK: don't add (FOK reject already returned above).
-    if remaining > Decimal::ZERO && matches!(order.time_in_force, TimeInForce::GTC) {
-        if let Some(limit_price) = order.price {
-            let mut rest_order = order.clone();
-            rest_order.quantity = remaining;
-            rest_order.price = Some(limit_price);
-            let _ = book.add_order(&rest_order);
-        }
+    if remaining > Decimal::ZERO && matches!(order.time_in_force, TimeInForce::GTC) && order.price.is_some() {
+        let _ = book.add_order_with_quantity(order, remaining);
     }
+}
 
+/// Uncross the book by re-matching every bid priced at or above the best ask as an aggressor against the
+/// asks, best bid first. Trades print at the resting ask prices; remainders go back on the book in their
+/// original time order. Overlaps between one trader's own orders are left alone (self-trade prevention),
+/// so the book may still be crossed afterwards.
+/// Returns trades and reports for the fills; exec ids run contiguously from `next_exec_id`.
+pub fn uncross_book(
+    book: &mut OrderBook,
+    next_trade_id: u64,
+    next_exec_id: u64,
+) -> (Vec<Trade>, Vec<ExecutionReport>) {
+    let mut trades = Vec::new();
+    let mut reports = Vec::new();
+    let Some(best_ask) = book.best_ask() else {
+        return (trades, reports);
+    };
+    let instrument_id = book.instrument_id();
+    let crossing: Vec<Order> = book
+        .bids_iter()
+        .take_while(|bid| bid.price >= best_ask)
+        .map(|bid| bid.to_resting_order(instrument_id).to_order())
+        .collect();
+    for bid in crossing {
+        book.cancel_order(bid.order_id);
+        let (matched_trades, matched) = match_order(book, &bid, next_trade_id + trades.len() as u64, 0);
+        trades.extend(matched_trades);
+        // An unfilled bid simply rests again; it is not a new order.
+        reports.extend(
+            matched
+                .into_iter()
+                .filter(|r| !(r.order_id == bid.order_id && r.exec_type == ExecType::New)),
+        );
+    }
+    for (i, report) in reports.iter_mut().enumerate() {
+        report.exec_id = ExecutionId(next_exec_id + i as u64);
+    }
     (trades, reports)
 }
 
+/// Cancel/replace `order_id` with `replacement` as one atomic amendment.
+///
+/// The first report is always [`ExecType::Replaced`] for the replacement id, carrying the original id in
+/// `orig_order_id`. Quantity-down amendments keep their queue position (see [`amend_in_place`]); anything
+/// else cancels the original and matches the replacement, whose fills and final status follow the
+/// Replaced report (a plain resting `New` is folded into it).
+/// Returns `Err` if `order_id` is not on the book, the replacement is for another instrument, or the
+/// replacement would rest past a [`crate::order_book::BookLimits`] cap (the original is left untouched).
+pub fn replace_order(
+    book: &mut OrderBook,
+    order_id: OrderId,
+    replacement: &Order,
+    next_trade_id: u64,
+    next_exec_id: u64,
+) -> Result<(Vec<Trade>, Vec<ExecutionReport>), String> {
+    if replacement.instrument_id != book.instrument_id() {
+        return Err("Replacement order must be for the same instrument".into());
+    }
+    if let Some(reports) = amend_in_place(book, order_id, replacement, next_exec_id) {
+        return Ok((Vec::new(), reports));
+    }
+    if !book.contains_order(order_id) {
+        return Err(format!("Order {} not found", order_id.0));
+    }
+    book.validate_order(replacement, Some(order_id))?;
+    book.cancel_order(order_id);
+    let (trades, matched) = match_order(book, replacement, next_trade_id, next_exec_id + 1);
+    let mut reports = Vec::with_capacity(matched.len() + 1);
+    reports.push(replaced_report(order_id, replacement, replacement.quantity, next_exec_id));
+    reports.extend(
+        matched
+            .into_iter()
+            .filter(|r| !(r.order_id == replacement.order_id && r.exec_type == ExecType::New)),
+    );
+    Ok((trades, reports))
+}
+
+/// Amend a resting order in place when the replacement only lowers its quantity (see
+/// [`OrderBook::is_quantity_reduction`]). The order keeps its queue position; no matching runs.
+/// Returns `None` if the amendment does not qualify, in which case the caller cancels and re-matches.
+pub fn amend_in_place(
+    book: &mut OrderBook,
+    order_id: OrderId,
+    replacement: &Order,
+    next_exec_id: u64,
+) -> Option<Vec<ExecutionReport>> {
+    if replacement.instrument_id != book.instrument_id() || !book.is_quantity_reduction(order_id, replacement) {
+        return None;
+    }
+    book.amend_in_place(order_id, replacement).ok()?;
+    Some(vec![replaced_report(order_id, replacement, replacement.quantity, next_exec_id)])
+}
+
+fn replaced_report(orig_order_id: OrderId, replacement: &Order, remaining: Decimal, exec_id: u64) -> ExecutionReport {
+    ExecutionReport {
+        order_id: replacement.order_id,
+        exec_id: ExecutionId(exec_id),
+        exec_type: ExecType::Replaced,
+        order_status: OrderStatus::New,
+        filled_quantity: Decimal::ZERO,
+        remaining_quantity: remaining,
+        avg_price: None,
+        last_qty: None,
+        last_px: None,
+        timestamp: replacement.timestamp,
+        orig_order_id: Some(orig_order_id),
+        seq: 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -409,14 +559,13 @@ mod tests {
 
     /// Invariant: book is never crossed (best_bid < best_ask when both sides exist).
     fn assert_no_crossed_book(book: &OrderBook) {
-        match (book.best_bid(), book.best_ask()) {
-            (Some(bid), Some(ask)) => assert!(
+        if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask()) {
+            assert!(
                 bid < ask,
                 "invariant violated: best_bid {:?} >= best_ask {:?}",
                 bid,
                 ask
-            ),
-            _ => {}
+            );
         }
     }
 
@@ -533,4 +682,116 @@ mod tests {
         assert_eq!(canceled.order_id, OrderId(2));
         assert_eq!(book.best_bid(), Some(Decimal::from(100)));
     }
+
+    #[test]
+    fn replace_order_emits_single_replaced_report_when_resting() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 10, Some(100), TimeInForce::GTC, 1))
+            .unwrap();
+        let replacement = order(2, Side::Buy, 10, Some(101), TimeInForce::GTC, 1);
+        let (trades, reports) = replace_order(&mut book, OrderId(1), &replacement, 1, 7).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].exec_type, ExecType::Replaced);
+        assert_eq!(reports[0].order_id, OrderId(2));
+        assert_eq!(reports[0].orig_order_id, Some(OrderId(1)));
+        assert_eq!(reports[0].exec_id, ExecutionId(7));
+        assert_eq!(book.best_bid(), Some(Decimal::from(101)));
+    }
+
+    #[test]
+    fn replace_order_that_crosses_reports_replaced_then_fills() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Sell, 5, Some(101), TimeInForce::GTC, 1))
+            .unwrap();
+        book.add_order(&order(2, Side::Buy, 5, Some(100), TimeInForce::GTC, 2))
+            .unwrap();
+        let replacement = order(3, Side::Buy, 5, Some(101), TimeInForce::GTC, 2);
+        let (trades, reports) = replace_order(&mut book, OrderId(2), &replacement, 1, 1).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(reports[0].exec_type, ExecType::Replaced);
+        assert_eq!(reports[0].orig_order_id, Some(OrderId(2)));
+        let aggressor = reports.last().unwrap();
+        assert_eq!(aggressor.order_id, OrderId(3));
+        assert_eq!(aggressor.exec_type, ExecType::Fill);
+        let ids: Vec<u64> = reports.iter().map(|r| r.exec_id.0).collect();
+        assert_eq!(ids, vec![1, 2, 3], "exec ids stay dense");
+    }
+
+    #[test]
+    fn replace_order_unknown_order_returns_err() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        let replacement = order(2, Side::Buy, 10, Some(100), TimeInForce::GTC, 1);
+        let err = replace_order(&mut book, OrderId(1), &replacement, 1, 1).unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[test]
+    fn uncross_book_matches_overlapping_levels_and_skips_self_cross() {
+        let resting = |id: u64, side: Side, qty: i64, price: i64, trader: u64| crate::types::RestingOrder {
+            order_id: OrderId(id),
+            instrument_id: InstrumentId(1),
+            side,
+            price: Decimal::from(price),
+            quantity: Decimal::from(qty),
+            trader_id: TraderId(trader),
+            client_order_id: format!("c{}", id),
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+        };
+        let mut book = OrderBook::new(InstrumentId(1));
+        // Restored crossed state: two traders' bids above two asks.
+        book.load_resting_orders(&[
+            resting(1, Side::Buy, 5, 102, 1),
+            resting(2, Side::Buy, 5, 101, 2),
+            resting(3, Side::Sell, 4, 100, 3),
+            resting(4, Side::Sell, 3, 101, 3),
+            resting(5, Side::Sell, 2, 101, 2),
+        ])
+        .unwrap();
+        assert!(book.is_crossed());
+
+        let (trades, reports) = uncross_book(&mut book, 10, 20);
+        let fills: Vec<_> = trades
+            .iter()
+            .map(|t| (t.buy_order_id.0, t.sell_order_id.0, t.price, t.quantity))
+            .collect();
+        assert_eq!(
+            fills,
+            vec![
+                (1, 3, Decimal::from(100), Decimal::from(4)),
+                (1, 4, Decimal::from(101), Decimal::from(1)),
+                (2, 4, Decimal::from(101), Decimal::from(2)),
+            ]
+        );
+        assert_eq!(trades.iter().map(|t| t.trade_id.0).collect::<Vec<_>>(), vec![10, 11, 12]);
+        let exec_ids: Vec<u64> = reports.iter().map(|r| r.exec_id.0).collect();
+        assert_eq!(exec_ids, (20..20 + reports.len() as u64).collect::<Vec<_>>());
+        // Bid 2 (trader 2) still overlaps its own ask 5 at 101.
+        assert!(book.is_crossed());
+        assert_eq!(book.get_order(OrderId(2)).unwrap().remaining_quantity, Decimal::from(3));
+        assert!(!book.contains_order(OrderId(1)));
+    }
+
+    #[test]
+    fn match_order_into_reuses_buffers_and_matches_like_match_order() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Sell, 5, Some(100), TimeInForce::GTC, 1)).unwrap();
+        book.add_order(&order(2, Side::Sell, 5, Some(101), TimeInForce::GTC, 1)).unwrap();
+        let mut out = MatchOutput::new();
+        match_order_into(&mut book, &order(3, Side::Buy, 7, Some(101), TimeInForce::GTC, 2), 1, 1, &mut out);
+        assert_eq!(out.trades.len(), 2);
+        assert_eq!(out.reports.len(), 3);
+        let capacity = out.trades.capacity();
+
+        // The next call clears the previous results and keeps the allocation.
+        match_order_into(&mut book, &order(4, Side::Buy, 10, Some(99), TimeInForce::GTC, 2), 3, 4, &mut out);
+        assert!(out.trades.is_empty());
+        assert_eq!(out.reports.len(), 1);
+        assert_eq!(out.reports[0].exec_type, ExecType::New);
+        assert_eq!(out.trades.capacity(), capacity);
+        assert_eq!(book.best_bid(), Some(Decimal::from(99)));
+        assert_eq!(book.best_ask(), Some(Decimal::from(101)));
+    }
 }
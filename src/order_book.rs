@@ -2,15 +2,61 @@
 //!
 //! Supports add, cancel, modify, and taking liquidity (used by [`crate::matching`]).
 //! Each price level is FIFO; best bid is highest price, best ask is lowest.
+//!
+//! Resting orders live in a slab; each price level is an intrusive doubly linked list over slab
+//! slots, and the order index maps `OrderId` to its slot. Cancel, fill, and amend touch a single
+//! slot without scanning the level. Each level and each trader's orders per level keep a running
+//! quantity total, so depth and FOK availability are sums over levels rather than over orders.
+//!
+//! Prices are held internally as integer ticks of the book's tick size (see
+//! [`OrderBook::with_tick_size`]), so level lookups and price comparisons on the matching path are
+//! integer compares. The public API takes and returns `Decimal` prices and converts at the boundary.
 
-use crate::types::{Order, OrderId, OrderType, RestingOrder, Side, TimeInForce, TraderId};
+use crate::types::{
+    BookOrder, Order, OrderId, OrderType, QueuePosition, RestingOrder, RestingOrderView, Side, TimeInForce,
+    TraderId,
+};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use std::collections::{BTreeMap, HashMap};
 
-/// One order at a price level: (OrderId, remaining_qty, TraderId) for price-time and self-trade.
-type BookEntry = (OrderId, Decimal, TraderId);
-/// Price level -> FIFO queue of orders.
-type PriceLevel = BTreeMap<Decimal, Vec<BookEntry>>;
+/// One resting order in the slab, linked to its neighbours in the same price level.
+#[derive(Clone, Debug)]
+struct OrderNode {
+    order_id: OrderId,
+    side: Side,
+    price: Ticks,
+    remaining: Decimal,
+    trader_id: TraderId,
+    client_order_id: String,
+    order_type: OrderType,
+    time_in_force: TimeInForce,
+    timestamp: u64,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// FIFO queue of orders at one price: head (next to fill) and tail slab slots, the number of
+/// orders, and the level's total remaining quantity.
+#[derive(Clone, Copy, Debug)]
+struct LevelQueue {
+    head: usize,
+    tail: usize,
+    len: usize,
+    quantity: Decimal,
+}
+
+/// Price as a whole number of ticks of the book's tick size.
+type Ticks = i64;
+
+/// Price level (in ticks) -> FIFO queue of orders.
+type PriceLevel = BTreeMap<Ticks, LevelQueue>;
+
+/// Tick size of books created without one: 1e-8, fine enough for any price with up to 8 decimal places.
+pub const DEFAULT_TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 8);
+
+/// Levels per side covered by [`OrderBook::checksum`].
+pub const CHECKSUM_LEVELS: usize = 25;
 
 /// Result of taking liquidity from the book (one per resting order filled).
 #[derive(Clone, Debug)]
@@ -21,6 +67,202 @@ pub struct Fill {
     pub quantity: Decimal,
     /// True if the resting order was fully filled (removed from book).
     pub resting_fully_filled: bool,
+    /// Quantity the resting order has left after this fill.
+    pub resting_remaining: Decimal,
+}
+
+/// Resting-order caps enforced when an order would rest on the book. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BookLimits {
+    /// Max resting orders per trader on this book.
+    pub max_orders_per_trader: Option<usize>,
+    /// Max resting orders at one price level.
+    pub max_orders_per_level: Option<usize>,
+    /// Max resting orders on the book in total.
+    pub max_book_orders: Option<usize>,
+}
+
+impl BookLimits {
+    /// Read limits from `MAX_ORDERS_PER_TRADER`, `MAX_ORDERS_PER_LEVEL`, and `MAX_BOOK_ORDERS`.
+    /// Unset or unparsable variables leave that limit off.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse().ok());
+        Self {
+            max_orders_per_trader: var("MAX_ORDERS_PER_TRADER"),
+            max_orders_per_level: var("MAX_ORDERS_PER_LEVEL"),
+            max_book_orders: var("MAX_BOOK_ORDERS"),
+        }
+    }
+}
+
+/// Borrowed view of one resting order, yielded by [`OrderBook::bids_iter`] and [`OrderBook::asks_iter`]
+/// without copying the order's metadata.
+#[derive(Clone, Copy, Debug)]
+pub struct RestingOrderRef<'a> {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub price: Decimal,
+    /// 0-based position within the price level (0 = next to fill).
+    pub queue_position: usize,
+    pub remaining_quantity: Decimal,
+    pub trader_id: TraderId,
+    pub client_order_id: &'a str,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    pub timestamp: u64,
+}
+
+impl RestingOrderRef<'_> {
+    /// Owned copy for persistence.
+    pub fn to_resting_order(&self, instrument_id: crate::types::InstrumentId) -> RestingOrder {
+        RestingOrder {
+            order_id: self.order_id,
+            instrument_id,
+            side: self.side,
+            price: self.price,
+            quantity: self.remaining_quantity,
+            trader_id: self.trader_id,
+            client_order_id: self.client_order_id.to_string(),
+            order_type: self.order_type,
+            time_in_force: self.time_in_force,
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Book analytics over the best `levels` price levels per side (see [`OrderBook::stats`]).
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BookStats {
+    /// Number of levels per side the volumes and imbalance cover.
+    pub levels: usize,
+    /// Total bid quantity within `levels`.
+    pub bid_volume: Decimal,
+    /// Total ask quantity within `levels`.
+    pub ask_volume: Decimal,
+    /// `(bid_volume - ask_volume) / (bid_volume + ask_volume)`, in [-1, 1]. `None` if both are zero.
+    pub imbalance: Option<Decimal>,
+    /// Best ask minus best bid. `None` unless both sides are present.
+    pub spread: Option<Decimal>,
+    /// Top-of-book size-weighted mid: `(bid_px * ask_qty + ask_px * bid_qty) / (bid_qty + ask_qty)`.
+    /// `None` unless both sides are present.
+    pub microprice: Option<Decimal>,
+}
+
+/// Aggregated (price, total quantity) levels for both sides, best price first (see [`OrderBook::levels`]).
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BookLevels {
+    pub bids: Vec<(Decimal, Decimal)>,
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+/// How a price level differs between two [`BookLevels`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LevelAction {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// One changed price level in a [`BookDelta`]. `quantity` is the level's new total (zero when removed).
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LevelChange {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub action: LevelAction,
+}
+
+/// Incremental L2 update: the levels added, changed, or removed per side between two [`BookLevels`].
+/// Applying it to the earlier levels yields the later ones.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BookDelta {
+    /// Bid changes, best (highest) price first.
+    pub bids: Vec<LevelChange>,
+    /// Ask changes, best (lowest) price first.
+    pub asks: Vec<LevelChange>,
+}
+
+impl BookDelta {
+    /// Level changes from `before` to `after`.
+    pub fn between(before: &BookLevels, after: &BookLevels) -> Self {
+        let mut bids = Self::side_changes(&before.bids, &after.bids);
+        bids.reverse();
+        Self {
+            bids,
+            asks: Self::side_changes(&before.asks, &after.asks),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// Fold `later` (the next delta of the same book) into this one, so applying the result to this delta's
+    /// base levels yields the levels after `later`.
+    pub fn merge(&mut self, later: BookDelta) {
+        let mut bids = Self::merge_side(std::mem::take(&mut self.bids), later.bids);
+        bids.reverse();
+        self.bids = bids;
+        self.asks = Self::merge_side(std::mem::take(&mut self.asks), later.asks);
+    }
+
+    /// Net changes of one side over two deltas, in ascending price order.
+    fn merge_side(earlier: Vec<LevelChange>, later: Vec<LevelChange>) -> Vec<LevelChange> {
+        // Per price: whether the level existed before the first change, and its latest change.
+        let mut levels: BTreeMap<Decimal, (bool, LevelChange)> = BTreeMap::new();
+        for change in earlier.into_iter().chain(later) {
+            let existed = levels
+                .get(&change.price)
+                .map_or(change.action != LevelAction::Added, |&(existed, _)| existed);
+            levels.insert(change.price, (existed, change));
+        }
+        levels
+            .into_values()
+            .filter_map(|(existed, change)| {
+                let action = match (existed, change.action != LevelAction::Removed) {
+                    (false, false) => return None,
+                    (false, true) => LevelAction::Added,
+                    (true, false) => LevelAction::Removed,
+                    (true, true) => LevelAction::Changed,
+                };
+                Some(LevelChange { action, ..change })
+            })
+            .collect()
+    }
+
+    /// Changes for one side, in ascending price order.
+    fn side_changes(before: &[(Decimal, Decimal)], after: &[(Decimal, Decimal)]) -> Vec<LevelChange> {
+        let mut levels: BTreeMap<Decimal, (Option<Decimal>, Option<Decimal>)> = BTreeMap::new();
+        for &(price, qty) in before {
+            levels.entry(price).or_default().0 = Some(qty);
+        }
+        for &(price, qty) in after {
+            levels.entry(price).or_default().1 = Some(qty);
+        }
+        levels
+            .into_iter()
+            .filter_map(|(price, level)| {
+                let (quantity, action) = match level {
+                    (None, Some(qty)) => (qty, LevelAction::Added),
+                    (Some(_), None) => (Decimal::ZERO, LevelAction::Removed),
+                    (Some(old), Some(qty)) if old != qty => (qty, LevelAction::Changed),
+                    _ => return None,
+                };
+                Some(LevelChange { price, quantity, action })
+            })
+            .collect()
+    }
+}
+
+/// Serializable state of one [`OrderBook`]: configuration plus every resting order with its metadata,
+/// in priority order (see [`OrderBook::snapshot`] and [`OrderBook::restore`]).
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OrderBookSnapshot {
+    pub instrument_id: crate::types::InstrumentId,
+    pub tick_size: Decimal,
+    #[serde(default)]
+    pub limits: BookLimits,
+    /// Bids then asks, best price first; within a level, in time priority.
+    pub orders: Vec<RestingOrder>,
 }
 
 /// Single-instrument order book.
@@ -29,8 +271,18 @@ pub struct OrderBook {
     instrument_id: crate::types::InstrumentId,
     bids: PriceLevel,
     asks: PriceLevel,
-    /// Orders by id for cancel/modify: (side, price, remaining_qty).
-    orders: HashMap<OrderId, (Side, Decimal, Decimal)>,
+    /// Slab of resting orders; `None` slots are free and listed in `free_slots`.
+    slots: Vec<Option<OrderNode>>,
+    free_slots: Vec<usize>,
+    /// Orders by id for cancel/modify: slab slot of the order.
+    orders: HashMap<OrderId, usize>,
+    /// Per-trader remaining quantity by side and price, for excluding own orders from FOK checks.
+    trader_levels: HashMap<(TraderId, Side), BTreeMap<Ticks, Decimal>>,
+    /// Resting order count per trader, for [`BookLimits::max_orders_per_trader`].
+    trader_order_counts: HashMap<TraderId, usize>,
+    limits: BookLimits,
+    /// Minimum price increment; every resting price is a whole number of ticks.
+    tick_size: Decimal,
 }
 
 impl OrderBook {
@@ -39,52 +291,223 @@ impl OrderBook {
             instrument_id,
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
             orders: std::collections::HashMap::new(),
+            trader_levels: HashMap::new(),
+            trader_order_counts: HashMap::new(),
+            limits: BookLimits::default(),
+            tick_size: DEFAULT_TICK_SIZE,
+        }
+    }
+
+    /// Book whose prices must be multiples of `tick_size`. Returns `Err` if `tick_size` is not positive.
+    pub fn with_tick_size(instrument_id: crate::types::InstrumentId, tick_size: Decimal) -> Result<Self, String> {
+        if tick_size <= Decimal::ZERO {
+            return Err(format!("Tick size {} must be positive", tick_size));
+        }
+        let mut book = Self::new(instrument_id);
+        book.tick_size = tick_size;
+        Ok(book)
+    }
+
+    pub fn tick_size(&self) -> Decimal {
+        self.tick_size
+    }
+
+    /// Change the tick size, keeping every resting order and its queue priority. Returns `Err`, leaving the
+    /// book unchanged, if `tick_size` is not positive or a resting price is not a multiple of it.
+    pub fn set_tick_size(&mut self, tick_size: Decimal) -> Result<(), String> {
+        let mut snapshot = self.snapshot();
+        snapshot.tick_size = tick_size;
+        *self = Self::restore(&snapshot)?;
+        Ok(())
+    }
+
+    /// `price` as a whole number of ticks. Returns `Err` if it is not a multiple of the tick size
+    /// or does not fit the tick range.
+    fn to_ticks(&self, price: Decimal) -> Result<Ticks, String> {
+        match price.checked_div(self.tick_size) {
+            Some(ticks) if !ticks.fract().is_zero() => Err(format!(
+                "Price {} is not a multiple of tick size {}",
+                price, self.tick_size
+            )),
+            ticks => ticks
+                .and_then(|t| t.to_i64())
+                .ok_or_else(|| format!("Price {} is out of range", price)),
         }
     }
 
+    /// Tick bound for taking from `side` up to `price_limit`: rounded down on the ask side and up on
+    /// the bid side, so an off-tick limit never reaches past itself. Saturates for unbounded limits
+    /// (e.g. `Decimal::MAX` for market buys).
+    fn limit_ticks(&self, side: Side, price_limit: Decimal) -> Ticks {
+        let saturated = if price_limit.is_sign_negative() { Ticks::MIN } else { Ticks::MAX };
+        let Some(ticks) = price_limit.checked_div(self.tick_size) else {
+            return saturated;
+        };
+        let rounded = match side {
+            Side::Sell => ticks.floor(),
+            Side::Buy => ticks.ceil(),
+        };
+        rounded.to_i64().unwrap_or(saturated)
+    }
+
+    /// Decimal price of a tick count, normalized (no trailing zeros).
+    fn price(&self, ticks: Ticks) -> Decimal {
+        (Decimal::from(ticks) * self.tick_size).normalize()
+    }
+
+    /// Book with resting-order caps (see [`BookLimits`]).
+    pub fn with_limits(instrument_id: crate::types::InstrumentId, limits: BookLimits) -> Self {
+        let mut book = Self::new(instrument_id);
+        book.limits = limits;
+        book
+    }
+
+    pub fn limits(&self) -> BookLimits {
+        self.limits
+    }
+
+    /// Replace the resting-order caps. Orders already resting are kept even if they exceed new caps.
+    pub fn set_limits(&mut self, limits: BookLimits) {
+        self.limits = limits;
+    }
+
     /// Add a limit order to the book. Does not run matching; caller uses matching module.
+    /// Returns `Err` if the order has no price, its price is not on a tick, its id is already resting,
+    /// or it would breach a [`BookLimits`] cap.
     pub fn add_order(&mut self, order: &Order) -> Result<(), String> {
-        let price = order.price.ok_or("Limit order must have price")?;
-        let side = order.side;
-        let order_id = order.order_id;
-        let qty = order.quantity;
-        let trader_id = order.trader_id;
+        self.add_order_with_quantity(order, order.quantity)
+    }
 
-        let level = match side {
-            Side::Buy => &mut self.bids,
-            Side::Sell => &mut self.asks,
+    /// Like [`OrderBook::add_order`], resting `quantity` instead of `order.quantity` (e.g. the unfilled
+    /// remainder after matching), without cloning the order.
+    pub fn add_order_with_quantity(&mut self, order: &Order, quantity: Decimal) -> Result<(), String> {
+        let price = self.to_ticks(order.price.ok_or("Limit order must have price")?)?;
+        self.check_limits(order.side, price, order.trader_id, None)?;
+        self.insert_order(order, quantity)
+    }
+
+    /// Add without checking [`BookLimits`] (restores may exceed caps lowered since the snapshot).
+    fn insert_order(&mut self, order: &Order, quantity: Decimal) -> Result<(), String> {
+        let price = self.to_ticks(order.price.ok_or("Limit order must have price")?)?;
+        if self.orders.contains_key(&order.order_id) {
+            return Err(format!("Order {} already exists", order.order_id.0));
+        }
+        let node = OrderNode {
+            order_id: order.order_id,
+            side: order.side,
+            price,
+            remaining: quantity,
+            trader_id: order.trader_id,
+            client_order_id: order.client_order_id.clone(),
+            order_type: order.order_type,
+            time_in_force: order.time_in_force,
+            timestamp: order.timestamp,
+            prev: None,
+            next: None,
         };
-        level
-            .entry(price)
-            .or_default()
-            .push((order_id, qty, trader_id));
-        self.orders.insert(order_id, (side, price, qty));
+        let slot = match self.free_slots.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(node);
+                slot
+            }
+            None => {
+                self.slots.push(Some(node));
+                self.slots.len() - 1
+            }
+        };
+        self.link_back(slot);
+        self.adjust_level_quantity(order.side, price, order.trader_id, quantity);
+        *self.trader_order_counts.entry(order.trader_id).or_insert(0) += 1;
+        self.orders.insert(order.order_id, slot);
+        Ok(())
+    }
+
+    /// Err naming the [`BookLimits`] cap a new resting order at (`side`, `price`) for `trader_id`
+    /// would breach. `replacing` is an order that leaves the book first (cancel/replace) and is not counted.
+    fn check_limits(
+        &self,
+        side: Side,
+        price: Ticks,
+        trader_id: TraderId,
+        replacing: Option<OrderId>,
+    ) -> Result<(), String> {
+        let replaced = replacing.and_then(|id| self.order_node(id));
+        if let Some(max) = self.limits.max_book_orders {
+            if self.orders.len() - usize::from(replaced.is_some()) >= max {
+                return Err(format!("Book order limit reached ({} resting orders)", max));
+            }
+        }
+        if let Some(max) = self.limits.max_orders_per_level {
+            let levels = match side {
+                Side::Buy => &self.bids,
+                Side::Sell => &self.asks,
+            };
+            let len = levels.get(&price).map_or(0, |queue| queue.len);
+            let same_level = replaced.is_some_and(|n| n.side == side && n.price == price);
+            if len - usize::from(same_level) >= max {
+                return Err(format!("Price level {} order limit reached ({} orders)", self.price(price), max));
+            }
+        }
+        if let Some(max) = self.limits.max_orders_per_trader {
+            let count = self.trader_order_counts.get(&trader_id).copied().unwrap_or(0);
+            let same_trader = replaced.is_some_and(|n| n.trader_id == trader_id);
+            if count - usize::from(same_trader) >= max {
+                return Err(format!("Trader {} resting order limit reached ({} orders)", trader_id.0, max));
+            }
+        }
         Ok(())
     }
 
+    /// Check [`BookLimits`] for an incoming order before matching. Only a GTC limit whose quantity is not
+    /// fully covered by crossing liquidity can rest, so anything else passes. `replacing` is the order a
+    /// cancel/replace removes first. Returns `Err` with the breached cap.
+    pub fn check_resting_limits(&self, order: &Order, replacing: Option<OrderId>) -> Result<(), String> {
+        let Some(price) = order.price else {
+            return Ok(());
+        };
+        if !order.is_limit() || !matches!(order.time_in_force, TimeInForce::GTC) {
+            return Ok(());
+        }
+        let available = match order.side {
+            Side::Buy => self.available_ask_qty_at_or_below(price, order.trader_id),
+            Side::Sell => self.available_bid_qty_at_or_above(price, order.trader_id),
+        };
+        if available >= order.quantity {
+            return Ok(());
+        }
+        self.check_limits(order.side, self.to_ticks(price)?, order.trader_id, replacing)
+    }
+
+    /// Pre-match checks for an incoming order: a limit price must be a multiple of the tick size, and
+    /// the order must not breach a [`BookLimits`] cap if it rests (see [`OrderBook::check_resting_limits`]).
+    pub fn validate_order(&self, order: &Order, replacing: Option<OrderId>) -> Result<(), String> {
+        if let (true, Some(price)) = (order.is_limit(), order.price) {
+            self.to_ticks(price)?;
+        }
+        self.check_resting_limits(order, replacing)
+    }
+
     /// Remove order by id. Returns true if found and removed.
     pub fn cancel_order(&mut self, order_id: OrderId) -> bool {
-        let Some((side, price, _)) = self.orders.remove(&order_id) else {
+        let Some(slot) = self.orders.remove(&order_id) else {
             return false;
         };
-        let level = match side {
-            Side::Buy => &mut self.bids,
-            Side::Sell => &mut self.asks,
-        };
-        if let Some(queue) = level.get_mut(&price) {
-            queue.retain(|(id, _, _)| *id != order_id);
-            if queue.is_empty() {
-                level.remove(&price);
-            }
-        }
+        self.unlink(slot);
         true
     }
 
     /// Modify an order: cancel by `order_id`, then add the replacement order.
     /// Replacement may use the same `order_id` (in-place replace) or a new one.
+    /// When only the quantity goes down (same side, price, and trader), the order keeps its
+    /// queue position (see [`OrderBook::reduce_order_quantity`]).
     /// Returns `Err` if the order to modify is not found, or if the replacement is invalid (e.g. limit with no price).
     pub fn modify_order(&mut self, order_id: OrderId, replacement: &Order) -> Result<(), String> {
+        if replacement.instrument_id == self.instrument_id && self.is_quantity_reduction(order_id, replacement) {
+            return self.amend_in_place(order_id, replacement);
+        }
         if !self.cancel_order(order_id) {
             return Err(format!("Order {} not found", order_id.0));
         }
@@ -94,24 +517,195 @@ impl OrderBook {
         self.add_order(replacement)
     }
 
+    /// True if `replacement` only lowers the quantity of resting order `order_id`: same side, price,
+    /// and trader, a GTC limit, and 0 < new quantity <= remaining. Such amendments keep time priority.
+    pub fn is_quantity_reduction(&self, order_id: OrderId, replacement: &Order) -> bool {
+        let Some(node) = self.order_node(order_id) else {
+            return false;
+        };
+        if replacement.side != node.side
+            || replacement.price != Some(self.price(node.price))
+            || !replacement.is_limit()
+            || !matches!(replacement.time_in_force, TimeInForce::GTC)
+            || replacement.quantity <= Decimal::ZERO
+            || replacement.quantity > node.remaining
+        {
+            return false;
+        }
+        if replacement.order_id != order_id && self.orders.contains_key(&replacement.order_id) {
+            return false;
+        }
+        node.trader_id == replacement.trader_id
+    }
+
+    /// Reduce a resting order's remaining quantity in place, keeping its position in the price level.
+    /// The entry is re-keyed to `new_order_id` when it differs (cancel/replace with a new id).
+    /// Returns `Err` if the order is not found or `new_quantity` is not in `(0, remaining]`.
+    pub fn reduce_order_quantity(
+        &mut self,
+        order_id: OrderId,
+        new_order_id: OrderId,
+        new_quantity: Decimal,
+    ) -> Result<(), String> {
+        let slot = *self
+            .orders
+            .get(&order_id)
+            .ok_or_else(|| format!("Order {} not found", order_id.0))?;
+        let remaining = self.node(slot).remaining;
+        if new_quantity <= Decimal::ZERO || new_quantity > remaining {
+            return Err(format!(
+                "Quantity {} is not a reduction of remaining {}",
+                new_quantity, remaining
+            ));
+        }
+        if new_order_id != order_id && self.orders.contains_key(&new_order_id) {
+            return Err(format!("Order {} already exists", new_order_id.0));
+        }
+        let node = self.node_mut(slot);
+        node.order_id = new_order_id;
+        node.remaining = new_quantity;
+        let (side, price, trader_id) = (node.side, node.price, node.trader_id);
+        self.adjust_level_quantity(side, price, trader_id, new_quantity - remaining);
+        self.orders.remove(&order_id);
+        self.orders.insert(new_order_id, slot);
+        Ok(())
+    }
+
+    /// Apply a quantity-down amendment in place (see [`OrderBook::reduce_order_quantity`]); the order also
+    /// takes the replacement's client order id. Time priority and the original timestamp are kept.
+    pub fn amend_in_place(&mut self, order_id: OrderId, replacement: &Order) -> Result<(), String> {
+        self.reduce_order_quantity(order_id, replacement.order_id, replacement.quantity)?;
+        let slot = self.orders[&replacement.order_id];
+        self.node_mut(slot).client_order_id = replacement.client_order_id.clone();
+        Ok(())
+    }
+
+    fn order_node(&self, order_id: OrderId) -> Option<&OrderNode> {
+        self.orders.get(&order_id).map(|&slot| self.node(slot))
+    }
+
+    fn node(&self, slot: usize) -> &OrderNode {
+        self.slots[slot].as_ref().expect("live order slot")
+    }
+
+    fn node_mut(&mut self, slot: usize) -> &mut OrderNode {
+        self.slots[slot].as_mut().expect("live order slot")
+    }
+
+    fn levels_mut(&mut self, side: Side) -> &mut PriceLevel {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// Append `slot` to the tail of its price level, creating the level if needed.
+    fn link_back(&mut self, slot: usize) {
+        let (side, price) = {
+            let node = self.node(slot);
+            (node.side, node.price)
+        };
+        let old_tail = match self.levels_mut(side).get_mut(&price) {
+            Some(queue) => {
+                let old_tail = queue.tail;
+                queue.tail = slot;
+                queue.len += 1;
+                Some(old_tail)
+            }
+            None => {
+                self.levels_mut(side).insert(
+                    price,
+                    LevelQueue {
+                        head: slot,
+                        tail: slot,
+                        len: 1,
+                        quantity: Decimal::ZERO,
+                    },
+                );
+                None
+            }
+        };
+        if let Some(old_tail) = old_tail {
+            self.node_mut(old_tail).next = Some(slot);
+            self.node_mut(slot).prev = Some(old_tail);
+        }
+    }
+
+    /// Detach `slot` from its price level (dropping the level if it empties) and free the slot.
+    /// The caller removes the `orders` index entry.
+    fn unlink(&mut self, slot: usize) -> OrderNode {
+        let node = self.slots[slot].take().expect("live order slot");
+        self.free_slots.push(slot);
+        self.adjust_level_quantity(node.side, node.price, node.trader_id, -node.remaining);
+        if let Some(count) = self.trader_order_counts.get_mut(&node.trader_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.trader_order_counts.remove(&node.trader_id);
+            }
+        }
+        if let Some(prev) = node.prev {
+            self.node_mut(prev).next = node.next;
+        }
+        if let Some(next) = node.next {
+            self.node_mut(next).prev = node.prev;
+        }
+        let level = self.levels_mut(node.side);
+        if node.prev.is_none() && node.next.is_none() {
+            level.remove(&node.price);
+        } else if let Some(queue) = level.get_mut(&node.price) {
+            queue.len -= 1;
+            if node.prev.is_none() {
+                queue.head = node.next.expect("non-empty level");
+            }
+            if node.next.is_none() {
+                queue.tail = node.prev.expect("non-empty level");
+            }
+        }
+        node
+    }
+
+    /// Add `delta` to the level total and the trader's total at (`side`, `price`).
+    /// The level must exist; trader entries are dropped when they reach zero.
+    fn adjust_level_quantity(&mut self, side: Side, price: Ticks, trader_id: TraderId, delta: Decimal) {
+        if delta.is_zero() {
+            return;
+        }
+        if let Some(queue) = self.levels_mut(side).get_mut(&price) {
+            queue.quantity += delta;
+        }
+        let by_price = self.trader_levels.entry((trader_id, side)).or_default();
+        let total = by_price.entry(price).or_insert(Decimal::ZERO);
+        *total += delta;
+        if total.is_zero() {
+            by_price.remove(&price);
+            if by_price.is_empty() {
+                self.trader_levels.remove(&(trader_id, side));
+            }
+        }
+    }
+
+    /// Remaining quantity `trader_id` has resting on `side` at prices within `range`.
+    fn trader_quantity_in<R: std::ops::RangeBounds<Ticks>>(&self, trader_id: TraderId, side: Side, range: R) -> Decimal {
+        self.trader_levels
+            .get(&(trader_id, side))
+            .map(|by_price| by_price.range(range).map(|(_, qty)| *qty).sum())
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Orders at one level in time priority.
+    fn level_nodes(&self, queue: LevelQueue) -> impl Iterator<Item = &OrderNode> + '_ {
+        std::iter::successors(Some(self.node(queue.head)), move |node| node.next.map(|slot| self.node(slot)))
+    }
+
     /// Total ask quantity at or below given price (excluding exclude_trader). For FOK check.
     pub fn available_ask_qty_at_or_below(
         &self,
         price_limit: Decimal,
         exclude_trader: TraderId,
     ) -> Decimal {
-        let mut total = Decimal::ZERO;
-        for (&price, queue) in self.asks.iter() {
-            if price > price_limit {
-                break;
-            }
-            for (_, qty, trader_id) in queue {
-                if *trader_id != exclude_trader {
-                    total += qty;
-                }
-            }
-        }
-        total
+        let limit = self.limit_ticks(Side::Sell, price_limit);
+        let total: Decimal = self.asks.range(..=limit).map(|(_, queue)| queue.quantity).sum();
+        total - self.trader_quantity_in(exclude_trader, Side::Sell, ..=limit)
     }
 
     /// Total bid quantity at or above given price (excluding exclude_trader). For FOK check.
@@ -120,15 +714,9 @@ impl OrderBook {
         price_limit: Decimal,
         exclude_trader: TraderId,
     ) -> Decimal {
-        let mut total = Decimal::ZERO;
-        for (_, queue) in self.bids.range(price_limit..) {
-            for (_, qty, trader_id) in queue {
-                if *trader_id != exclude_trader {
-                    total += qty;
-                }
-            }
-        }
-        total
+        let limit = self.limit_ticks(Side::Buy, price_limit);
+        let total: Decimal = self.bids.range(limit..).map(|(_, queue)| queue.quantity).sum();
+        total - self.trader_quantity_in(exclude_trader, Side::Buy, limit..)
     }
 
     /// Take liquidity from the ask side (for an incoming buy). Price-time priority, skip exclude_trader.
@@ -136,58 +724,11 @@ impl OrderBook {
     pub fn take_from_asks(
         &mut self,
         price_limit: Decimal,
-        mut quantity: Decimal,
+        quantity: Decimal,
         exclude_trader: TraderId,
     ) -> Vec<Fill> {
         let mut fills = Vec::new();
-        let mut empty_prices = Vec::new();
-        let mut orders_remove = Vec::new();
-        let mut orders_update: Vec<(OrderId, Decimal)> = Vec::new();
-        for (price, queue) in self.asks.iter_mut() {
-            if *price > price_limit || quantity <= Decimal::ZERO {
-                break;
-            }
-            let mut i = 0;
-            while i < queue.len() && quantity > Decimal::ZERO {
-                let (order_id, rest_qty, trader_id) = queue[i];
-                if trader_id == exclude_trader {
-                    i += 1;
-                    continue;
-                }
-                let fill_qty = quantity.min(rest_qty);
-                quantity -= fill_qty;
-                fills.push(Fill {
-                    resting_order_id: order_id,
-                    resting_trader_id: trader_id,
-                    price: *price,
-                    quantity: fill_qty,
-                    resting_fully_filled: fill_qty >= rest_qty,
-                });
-                if fill_qty >= rest_qty {
-                    orders_remove.push(order_id);
-                    queue.remove(i);
-                } else {
-                    let new_rest = rest_qty - fill_qty;
-                    orders_update.push((order_id, new_rest));
-                    queue[i] = (order_id, new_rest, trader_id);
-                    i += 1;
-                }
-            }
-            if queue.is_empty() {
-                empty_prices.push(*price);
-            }
-        }
-        for oid in orders_remove {
-            self.orders.remove(&oid);
-        }
-        for (oid, new_qty) in orders_update {
-            if let Some((_, ref mut stored_qty, _)) = self.orders.get_mut(&oid) {
-                *stored_qty = new_qty;
-            }
-        }
-        for p in empty_prices {
-            self.asks.remove(&p);
-        }
+        self.take_liquidity(Side::Sell, price_limit, quantity, exclude_trader, &mut fills);
         fills
     }
 
@@ -195,145 +736,332 @@ impl OrderBook {
     pub fn take_from_bids(
         &mut self,
         price_limit: Decimal,
-        mut quantity: Decimal,
+        quantity: Decimal,
         exclude_trader: TraderId,
     ) -> Vec<Fill> {
         let mut fills = Vec::new();
-        let mut empty_prices = Vec::new();
-        let mut orders_remove = Vec::new();
-        let mut orders_update: Vec<(OrderId, Decimal)> = Vec::new();
-        // BTreeMap: iterate bids in descending price (best bid first).
-        let bid_prices: Vec<Decimal> = self.bids.keys().copied().rev().collect();
-        for price in bid_prices {
-            if price < price_limit || quantity <= Decimal::ZERO {
-                break;
+        self.take_liquidity(Side::Buy, price_limit, quantity, exclude_trader, &mut fills);
+        fills
+    }
+
+    /// Take liquidity for an incoming `aggressor` order (from the asks for a buy, the bids for a sell), appending
+    /// the fills to `fills` so a caller can reuse one buffer across orders.
+    pub fn take_into(
+        &mut self,
+        aggressor: Side,
+        price_limit: Decimal,
+        quantity: Decimal,
+        exclude_trader: TraderId,
+        fills: &mut Vec<Fill>,
+    ) {
+        let resting = match aggressor {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        self.take_liquidity(resting, price_limit, quantity, exclude_trader, fills);
+    }
+
+    /// Next price level on `side` after `after`, best price first; `None` once past `price_limit`.
+    fn next_level(&self, side: Side, after: Option<Ticks>, price_limit: Ticks) -> Option<(Ticks, LevelQueue)> {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+        match side {
+            Side::Sell => {
+                let lower = after.map_or(Unbounded, Excluded);
+                self.asks.range((lower, Included(price_limit))).next()
+            }
+            Side::Buy => {
+                let upper = after.map_or(Unbounded, Excluded);
+                self.bids.range((Included(price_limit), upper)).next_back()
             }
-            let queue = match self.bids.get_mut(&price) {
-                Some(q) => q,
-                None => continue,
+        }
+        .map(|(price, queue)| (*price, *queue))
+    }
+
+    /// Walk resting `side` levels from the best price up to `price_limit`, filling in time priority.
+    fn take_liquidity(
+        &mut self,
+        side: Side,
+        price_limit: Decimal,
+        mut quantity: Decimal,
+        exclude_trader: TraderId,
+        fills: &mut Vec<Fill>,
+    ) {
+        let price_limit = self.limit_ticks(side, price_limit);
+        let mut after = None;
+        while quantity > Decimal::ZERO {
+            let Some((price, queue)) = self.next_level(side, after, price_limit) else {
+                break;
             };
-            let mut i = 0;
-            while i < queue.len() && quantity > Decimal::ZERO {
-                let (order_id, rest_qty, trader_id) = queue[i];
-                if trader_id == exclude_trader {
-                    i += 1;
+            after = Some(price);
+            let fill_price = self.price(price);
+            let mut cursor = Some(queue.head);
+            while let Some(slot) = cursor {
+                if quantity <= Decimal::ZERO {
+                    break;
+                }
+                let node = self.node_mut(slot);
+                cursor = node.next;
+                if node.trader_id == exclude_trader {
                     continue;
                 }
-                let fill_qty = quantity.min(rest_qty);
+                let fill_qty = quantity.min(node.remaining);
                 quantity -= fill_qty;
+                let fully_filled = fill_qty >= node.remaining;
+                node.remaining -= fill_qty;
+                let (order_id, trader_id, remaining) = (node.order_id, node.trader_id, node.remaining);
+                self.adjust_level_quantity(side, price, trader_id, -fill_qty);
                 fills.push(Fill {
                     resting_order_id: order_id,
                     resting_trader_id: trader_id,
-                    price,
+                    price: fill_price,
                     quantity: fill_qty,
-                    resting_fully_filled: fill_qty >= rest_qty,
+                    resting_fully_filled: fully_filled,
+                    resting_remaining: remaining,
                 });
-                if fill_qty >= rest_qty {
-                    orders_remove.push(order_id);
-                    queue.remove(i);
-                } else {
-                    let new_rest = rest_qty - fill_qty;
-                    orders_update.push((order_id, new_rest));
-                    queue[i] = (order_id, new_rest, trader_id);
-                    i += 1;
+                if fully_filled {
+                    let node = self.unlink(slot);
+                    self.orders.remove(&node.order_id);
                 }
             }
-            if queue.is_empty() {
-                empty_prices.push(price);
-            }
-        }
-        for oid in orders_remove {
-            self.orders.remove(&oid);
-        }
-        for (oid, new_qty) in orders_update {
-            if let Some((_, ref mut stored_qty, _)) = self.orders.get_mut(&oid) {
-                *stored_qty = new_qty;
-            }
-        }
-        for p in empty_prices {
-            self.bids.remove(&p);
         }
-        fills
     }
 
     pub fn instrument_id(&self) -> crate::types::InstrumentId {
         self.instrument_id
     }
 
+    /// Returns true if `order_id` is resting on this book.
+    pub fn contains_order(&self, order_id: OrderId) -> bool {
+        self.orders.contains_key(&order_id)
+    }
+
+    /// Look up a resting order: side, price, remaining quantity, and queue position within its level.
+    /// `None` if the order is not resting (filled, canceled, or unknown).
+    pub fn get_order(&self, order_id: OrderId) -> Option<RestingOrderView> {
+        let node = self.order_node(order_id)?;
+        let queue = self.queue_position(order_id)?;
+        Some(RestingOrderView {
+            order_id: node.order_id,
+            instrument_id: self.instrument_id,
+            side: node.side,
+            price: self.price(node.price),
+            remaining_quantity: node.remaining,
+            queue_position: queue.position,
+            level: queue.level,
+            quantity_ahead: queue.quantity_ahead,
+            trader_id: node.trader_id,
+        })
+    }
+
+    /// Price level index (0 = best on the order's side), position within the level, and the
+    /// quantity resting ahead of the order at that level. `None` if the order is not resting.
+    pub fn queue_position(&self, order_id: OrderId) -> Option<QueuePosition> {
+        use std::ops::Bound::{Excluded, Unbounded};
+        let node = self.order_node(order_id)?;
+        let level = match node.side {
+            Side::Buy => self.bids.range((Excluded(node.price), Unbounded)).count(),
+            Side::Sell => self.asks.range(..node.price).count(),
+        };
+        let (position, quantity_ahead) = std::iter::successors(node.prev, |&slot| self.node(slot).prev)
+            .fold((0, Decimal::ZERO), |(n, qty), slot| (n + 1, qty + self.node(slot).remaining));
+        Some(QueuePosition {
+            level,
+            position,
+            quantity_ahead,
+        })
+    }
+
+    /// True if the best bid is at or above the best ask. Matching never crosses two traders' orders, but a
+    /// restored snapshot can, and self-trade prevention leaves a trader's own bid and ask overlapping.
+    pub fn is_crossed(&self) -> bool {
+        match (self.bids.keys().next_back(), self.asks.keys().next()) {
+            (Some(bid), Some(ask)) => bid >= ask,
+            _ => false,
+        }
+    }
+
     /// Returns true if the book has at least one resting order (for admin delete-instrument checks).
     pub fn has_resting_orders(&self) -> bool {
         !self.orders.is_empty()
     }
 
-    /// Export resting orders for persistence. Caller must set instrument_id on each (use `instrument_id()`).
-    pub fn resting_orders_snapshot(&self) -> Vec<RestingOrder> {
-        let mut out = Vec::new();
-        for (price, queue) in &self.bids {
-            for (order_id, qty, trader_id) in queue {
-                out.push(RestingOrder {
-                    order_id: *order_id,
-                    instrument_id: self.instrument_id,
-                    side: Side::Buy,
-                    price: *price,
-                    quantity: *qty,
-                    trader_id: *trader_id,
-                });
-            }
+    /// Number of resting orders on the book.
+    pub fn resting_count(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Number of resting orders `trader_id` has on the book.
+    pub fn resting_count_for_trader(&self, trader_id: TraderId) -> usize {
+        self.trader_order_counts.get(&trader_id).copied().unwrap_or(0)
+    }
+
+    /// Total remaining quantity `trader_id` has resting on `side`.
+    pub fn resting_qty_by_trader(&self, trader_id: TraderId, side: Side) -> Decimal {
+        self.trader_quantity_in(trader_id, side, ..)
+    }
+
+    /// Resting orders of `trader_id` (e.g. for cancel-on-disconnect): bids then asks, best price first,
+    /// in time priority within a level.
+    pub fn resting_orders_for_trader(&self, trader_id: TraderId) -> impl Iterator<Item = RestingOrderRef<'_>> + '_ {
+        self.bids_iter().chain(self.asks_iter()).filter(move |o| o.trader_id == trader_id)
+    }
+
+    /// Aggregated depth for one side: up to `levels` (price, total quantity) pairs, best price first.
+    pub fn depth(&self, side: Side, levels: usize) -> Vec<(Decimal, Decimal)> {
+        let level_total = |(price, queue): (&Ticks, &LevelQueue)| (self.price(*price), queue.quantity);
+        match side {
+            Side::Buy => self.bids.iter().rev().take(levels).map(level_total).collect(),
+            Side::Sell => self.asks.iter().take(levels).map(level_total).collect(),
         }
-        for (price, queue) in &self.asks {
-            for (order_id, qty, trader_id) in queue {
-                out.push(RestingOrder {
-                    order_id: *order_id,
-                    instrument_id: self.instrument_id,
-                    side: Side::Sell,
-                    price: *price,
-                    quantity: *qty,
-                    trader_id: *trader_id,
-                });
+    }
+
+    /// Every aggregated level on both sides, best price first. Diff two of these with [`BookDelta::between`].
+    pub fn levels(&self) -> BookLevels {
+        BookLevels {
+            bids: self.depth(Side::Buy, usize::MAX),
+            asks: self.depth(Side::Sell, usize::MAX),
+        }
+    }
+
+    /// Imbalance, spread, and microprice over the best `levels` levels per side.
+    pub fn stats(&self, levels: usize) -> BookStats {
+        let bids = self.depth(Side::Buy, levels);
+        let asks = self.depth(Side::Sell, levels);
+        let bid_volume: Decimal = bids.iter().map(|(_, qty)| *qty).sum();
+        let ask_volume: Decimal = asks.iter().map(|(_, qty)| *qty).sum();
+        let total = bid_volume + ask_volume;
+        let imbalance = (!total.is_zero()).then(|| (bid_volume - ask_volume) / total);
+        let (spread, microprice) = match (self.depth(Side::Buy, 1).first(), self.depth(Side::Sell, 1).first()) {
+            (Some(&(bid_px, bid_qty)), Some(&(ask_px, ask_qty))) => (
+                Some(ask_px - bid_px),
+                Some((bid_px * ask_qty + ask_px * bid_qty) / (bid_qty + ask_qty)),
+            ),
+            _ => (None, None),
+        };
+        BookStats {
+            levels,
+            bid_volume,
+            ask_volume,
+            imbalance,
+            spread,
+            microprice,
+        }
+    }
+
+    /// Deterministic CRC32 of the aggregated book, for clients to detect divergence of a local copy.
+    ///
+    /// Covers the best [`CHECKSUM_LEVELS`] levels per side. The input string interleaves levels by
+    /// depth, `bid_px:bid_qty:ask_px:ask_qty:...`, skipping a side once it runs out; prices and
+    /// quantities use their normalized decimal form (no trailing zeros). An empty book hashes to 0.
+    pub fn checksum(&self) -> u32 {
+        let bids = self.depth(Side::Buy, CHECKSUM_LEVELS);
+        let asks = self.depth(Side::Sell, CHECKSUM_LEVELS);
+        let mut parts = Vec::with_capacity((bids.len() + asks.len()) * 2);
+        for i in 0..bids.len().max(asks.len()) {
+            for level in [bids.get(i), asks.get(i)].into_iter().flatten() {
+                parts.push(level.0.normalize().to_string());
+                parts.push(level.1.normalize().to_string());
             }
         }
-        out
+        crc32fast::hash(parts.join(":").as_bytes())
     }
 
-    /// Restore resting orders (e.g. after load from persistence). Clears the book first. Each order must be for this book's instrument.
-    pub fn load_resting_orders(
-        &mut self,
-        orders: &[RestingOrder],
-        order_type: OrderType,
-        time_in_force: TimeInForce,
-    ) -> Result<(), String> {
+    /// Borrowing iterator over resting bids, best (highest) price first; within a level, in time priority.
+    pub fn bids_iter(&self) -> impl Iterator<Item = RestingOrderRef<'_>> + '_ {
+        self.bids.values().rev().flat_map(move |queue| self.level_refs(*queue))
+    }
+
+    /// Borrowing iterator over resting asks, best (lowest) price first; within a level, in time priority.
+    pub fn asks_iter(&self) -> impl Iterator<Item = RestingOrderRef<'_>> + '_ {
+        self.asks.values().flat_map(move |queue| self.level_refs(*queue))
+    }
+
+    fn level_refs(&self, queue: LevelQueue) -> impl Iterator<Item = RestingOrderRef<'_>> + '_ {
+        self.level_nodes(queue)
+            .enumerate()
+            .map(|(queue_position, node)| RestingOrderRef {
+                order_id: node.order_id,
+                side: node.side,
+                price: self.price(node.price),
+                queue_position,
+                remaining_quantity: node.remaining,
+                trader_id: node.trader_id,
+                client_order_id: &node.client_order_id,
+                order_type: node.order_type,
+                time_in_force: node.time_in_force,
+                timestamp: node.timestamp,
+            })
+    }
+
+    /// Iterate the full book order-by-order: bids best (highest) price first, then asks best
+    /// (lowest) price first; within a level, in time priority.
+    pub fn orders_iter(&self) -> impl Iterator<Item = BookOrder> + '_ {
+        self.bids_iter().chain(self.asks_iter()).map(|o| BookOrder {
+            side: o.side,
+            price: o.price,
+            queue_position: o.queue_position,
+            order_id: o.order_id,
+            remaining_quantity: o.remaining_quantity,
+            trader_id: o.trader_id,
+        })
+    }
+
+    /// Export resting orders for persistence (bids then asks, best price first). To walk the book
+    /// without copying, use [`OrderBook::bids_iter`] / [`OrderBook::asks_iter`].
+    pub fn resting_orders_snapshot(&self) -> Vec<RestingOrder> {
+        self.bids_iter()
+            .chain(self.asks_iter())
+            .map(|o| o.to_resting_order(self.instrument_id))
+            .collect()
+    }
+
+    /// Snapshot of this book alone, for persisting or moving one instrument without the engine.
+    pub fn snapshot(&self) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            instrument_id: self.instrument_id,
+            tick_size: self.tick_size,
+            limits: self.limits,
+            orders: self.resting_orders_snapshot(),
+        }
+    }
+
+    /// Rebuild a book from [`OrderBook::snapshot`], keeping queue priority. Returns `Err` if the tick size
+    /// is not positive or an order is off-tick, for another instrument, or a duplicate.
+    pub fn restore(snapshot: &OrderBookSnapshot) -> Result<Self, String> {
+        let mut book = Self::with_tick_size(snapshot.instrument_id, snapshot.tick_size)?;
+        book.limits = snapshot.limits;
+        book.load_resting_orders(&snapshot.orders)?;
+        Ok(book)
+    }
+
+    /// Restore resting orders (e.g. after load from persistence) with their stored metadata. Clears the
+    /// book first. Each order must be for this book's instrument.
+    pub fn load_resting_orders(&mut self, orders: &[RestingOrder]) -> Result<(), String> {
         self.bids.clear();
         self.asks.clear();
+        self.slots.clear();
+        self.free_slots.clear();
         self.orders.clear();
+        self.trader_levels.clear();
+        self.trader_order_counts.clear();
         for r in orders {
             if r.instrument_id != self.instrument_id {
                 return Err(format!("Resting order instrument {} does not match book {}", r.instrument_id.0, self.instrument_id.0));
             }
-            let order = Order {
-                order_id: r.order_id,
-                client_order_id: format!("restore-{}", r.order_id.0),
-                instrument_id: r.instrument_id,
-                side: r.side,
-                order_type,
-                quantity: r.quantity,
-                price: Some(r.price),
-                time_in_force,
-                timestamp: 0,
-                trader_id: r.trader_id,
-            };
-            self.add_order(&order)?;
+            let order = r.to_order();
+            self.insert_order(&order, order.quantity)?;
         }
         Ok(())
     }
 
     /// Best bid price (None if empty).
     pub fn best_bid(&self) -> Option<Decimal> {
-        self.bids.keys().next_back().copied()
+        self.bids.keys().next_back().map(|&ticks| self.price(ticks))
     }
 
     /// Best ask price (None if empty).
     pub fn best_ask(&self) -> Option<Decimal> {
-        self.asks.keys().next().copied()
+        self.asks.keys().next().map(|&ticks| self.price(ticks))
     }
 
     /// Whether the book has a bid.
@@ -347,6 +1075,112 @@ impl OrderBook {
     }
 }
 
+/// Builds a populated [`OrderBook`] from price/quantity pairs, for tests, benchmarks, and docs.
+///
+/// Orders get ids 1, 2, ... and timestamps in the order they are added, so each level fills in that
+/// order. Each order is its own trader (trader id = order id) unless [`OrderBookBuilder::trader`] is set.
+///
+/// ```
+/// use dire_matching_engine::order_book::OrderBookBuilder;
+/// use rust_decimal::Decimal;
+///
+/// let book = OrderBookBuilder::new().bid(100, 10).ask(101, 5).build();
+/// assert_eq!(book.best_bid(), Some(Decimal::from(100)));
+/// assert_eq!(book.best_ask(), Some(Decimal::from(101)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct OrderBookBuilder {
+    instrument_id: crate::types::InstrumentId,
+    tick_size: Decimal,
+    limits: BookLimits,
+    trader_id: Option<TraderId>,
+    orders: Vec<Order>,
+}
+
+impl Default for OrderBookBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBookBuilder {
+    /// Empty builder for instrument 1 with [`DEFAULT_TICK_SIZE`] and no limits.
+    pub fn new() -> Self {
+        Self {
+            instrument_id: crate::types::InstrumentId(1),
+            tick_size: DEFAULT_TICK_SIZE,
+            limits: BookLimits::default(),
+            trader_id: None,
+            orders: Vec::new(),
+        }
+    }
+
+    pub fn instrument(mut self, instrument_id: crate::types::InstrumentId) -> Self {
+        self.instrument_id = instrument_id;
+        for order in &mut self.orders {
+            order.instrument_id = instrument_id;
+        }
+        self
+    }
+
+    pub fn tick_size(mut self, tick_size: Decimal) -> Self {
+        self.tick_size = tick_size;
+        self
+    }
+
+    pub fn limits(mut self, limits: BookLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Trader for the orders added after this call.
+    pub fn trader(mut self, trader_id: TraderId) -> Self {
+        self.trader_id = Some(trader_id);
+        self
+    }
+
+    /// Add a GTC limit buy.
+    pub fn bid(self, price: impl Into<Decimal>, quantity: impl Into<Decimal>) -> Self {
+        self.order(Side::Buy, price.into(), quantity.into())
+    }
+
+    /// Add a GTC limit sell.
+    pub fn ask(self, price: impl Into<Decimal>, quantity: impl Into<Decimal>) -> Self {
+        self.order(Side::Sell, price.into(), quantity.into())
+    }
+
+    fn order(mut self, side: Side, price: Decimal, quantity: Decimal) -> Self {
+        let id = self.orders.len() as u64 + 1;
+        self.orders.push(Order {
+            order_id: OrderId(id),
+            client_order_id: format!("b{}", id),
+            instrument_id: self.instrument_id,
+            side,
+            order_type: OrderType::Limit,
+            quantity,
+            price: Some(price),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: self.trader_id.unwrap_or(TraderId(id)),
+        });
+        self
+    }
+
+    /// Build the book. Orders are added as given, without matching, so the result may be crossed.
+    ///
+    /// # Panics
+    ///
+    /// If the tick size is not positive or an order is rejected (off-tick price or over a limit).
+    pub fn build(self) -> OrderBook {
+        let mut book = OrderBook::with_tick_size(self.instrument_id, self.tick_size).expect("valid tick size");
+        book.set_limits(self.limits);
+        for order in &self.orders {
+            book.add_order(order).expect("builder order accepted");
+        }
+        book
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -378,6 +1212,249 @@ mod tests {
         assert!(book.best_bid().is_none());
     }
 
+    #[test]
+    fn cancel_from_middle_head_and_tail_keeps_fifo_and_reuses_slots() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        for id in 1..=4 {
+            book.add_order(&order(id, Side::Sell, 1, 100, id)).unwrap();
+        }
+        assert!(book.cancel_order(OrderId(2)));
+        assert!(book.cancel_order(OrderId(1)));
+        assert!(book.cancel_order(OrderId(4)));
+        book.add_order(&order(5, Side::Sell, 1, 100, 5)).unwrap();
+        let ids: Vec<u64> = book.orders_iter().map(|o| o.order_id.0).collect();
+        assert_eq!(ids, vec![3, 5]);
+        assert_eq!(book.slots.len(), 4, "freed slot reused");
+        let fills = book.take_from_asks(Decimal::from(100), Decimal::from(2), TraderId(99));
+        assert_eq!(fills.iter().map(|f| f.resting_order_id.0).collect::<Vec<_>>(), vec![3, 5]);
+        assert!(!book.has_ask());
+        assert!(!book.has_resting_orders());
+    }
+
+    #[test]
+    fn level_quantities_track_adds_fills_cancels_and_amends() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Sell, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Sell, 20, 100, 2)).unwrap();
+        book.add_order(&order(3, Side::Sell, 5, 101, 1)).unwrap();
+        book.add_order(&order(4, Side::Buy, 7, 99, 3)).unwrap();
+        assert_eq!(
+            book.depth(Side::Sell, 5),
+            vec![(Decimal::from(100), Decimal::from(30)), (Decimal::from(101), Decimal::from(5))]
+        );
+        assert_eq!(book.depth(Side::Buy, 5), vec![(Decimal::from(99), Decimal::from(7))]);
+
+        book.take_from_asks(Decimal::from(100), Decimal::from(4), TraderId(9));
+        book.reduce_order_quantity(OrderId(2), OrderId(2), Decimal::from(15)).unwrap();
+        assert!(book.cancel_order(OrderId(3)));
+        assert_eq!(book.depth(Side::Sell, 5), vec![(Decimal::from(100), Decimal::from(21))]);
+        assert_eq!(
+            book.available_ask_qty_at_or_below(Decimal::from(101), TraderId(1)),
+            Decimal::from(15)
+        );
+        assert_eq!(
+            book.available_ask_qty_at_or_below(Decimal::from(101), TraderId(2)),
+            Decimal::from(6)
+        );
+        assert_eq!(book.depth(Side::Sell, 0), vec![]);
+
+        // A remainder rests with what is left of the order, not its original quantity.
+        book.add_order_with_quantity(&order(5, Side::Buy, 10, 98, 3), Decimal::from(4)).unwrap();
+        assert_eq!(book.depth(Side::Buy, 5), vec![(Decimal::from(99), Decimal::from(7)), (Decimal::from(98), Decimal::from(4))]);
+        assert!(book.cancel_order(OrderId(5)));
+        assert_eq!(book.depth(Side::Buy, 5), vec![(Decimal::from(99), Decimal::from(7))]);
+    }
+
+    #[test]
+    fn get_order_reports_remaining_and_queue_position() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 20, 100, 2)).unwrap();
+        book.add_order(&order(3, Side::Buy, 30, 100, 3)).unwrap();
+        let view = book.get_order(OrderId(3)).unwrap();
+        assert_eq!(view.queue_position, 2);
+        assert_eq!(view.side, Side::Buy);
+        assert_eq!(view.price, Decimal::from(100));
+        assert_eq!(view.remaining_quantity, Decimal::from(30));
+
+        book.take_from_bids(Decimal::from(100), Decimal::from(15), TraderId(9));
+        assert!(book.get_order(OrderId(1)).is_none(), "fully filled order is gone");
+        let view = book.get_order(OrderId(2)).unwrap();
+        assert_eq!(view.queue_position, 0);
+        assert_eq!(view.remaining_quantity, Decimal::from(15));
+        assert_eq!(book.get_order(OrderId(3)).unwrap().queue_position, 1);
+        assert!(book.get_order(OrderId(42)).is_none());
+    }
+
+    #[test]
+    fn checksum_covers_interleaved_levels_and_changes_with_book() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        assert_eq!(book.checksum(), 0);
+        book.add_order(&order(1, Side::Buy, 10, 99, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 5, 98, 1)).unwrap();
+        book.add_order(&order(3, Side::Sell, 7, 101, 2)).unwrap();
+        assert_eq!(book.checksum(), crc32fast::hash(b"99:10:101:7:98:5"));
+
+        let mut same = OrderBook::new(InstrumentId(1));
+        same.add_order(&order(10, Side::Sell, 3, 101, 3)).unwrap();
+        same.add_order(&order(11, Side::Sell, 4, 101, 4)).unwrap();
+        same.add_order(&order(12, Side::Buy, 5, 98, 5)).unwrap();
+        same.add_order(&order(13, Side::Buy, 10, 99, 6)).unwrap();
+        assert_eq!(same.checksum(), book.checksum(), "aggregated levels only, not order ids");
+
+        book.cancel_order(OrderId(2));
+        assert_ne!(book.checksum(), same.checksum());
+    }
+
+    #[test]
+    fn stats_compute_imbalance_spread_and_microprice() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        let empty = book.stats(5);
+        assert_eq!(empty.imbalance, None);
+        assert_eq!(empty.spread, None);
+        assert_eq!(empty.microprice, None);
+
+        book.add_order(&order(1, Side::Buy, 30, 99, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 50, 98, 1)).unwrap();
+        book.add_order(&order(3, Side::Sell, 10, 101, 2)).unwrap();
+        book.add_order(&order(4, Side::Sell, 10, 102, 2)).unwrap();
+        let top = book.stats(1);
+        assert_eq!(top.bid_volume, Decimal::from(30));
+        assert_eq!(top.ask_volume, Decimal::from(10));
+        assert_eq!(top.imbalance, Some(Decimal::new(5, 1)));
+        assert_eq!(top.spread, Some(Decimal::from(2)));
+        // (99 * 10 + 101 * 30) / 40 = 100.5: heavier bid pulls the microprice toward the ask.
+        assert_eq!(top.microprice, Some(Decimal::new(1005, 1)));
+
+        let deep = book.stats(5);
+        assert_eq!(deep.bid_volume, Decimal::from(80));
+        assert_eq!(deep.ask_volume, Decimal::from(20));
+        assert_eq!(deep.imbalance, Some(Decimal::new(6, 1)));
+        assert_eq!(deep.microprice, top.microprice);
+    }
+
+    #[test]
+    fn resting_orders_snapshot_round_trips_metadata() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        let mut o = order(1, Side::Sell, 5, 101, 7);
+        o.client_order_id = "client-abc".into();
+        o.timestamp = 1234;
+        book.add_order(&o).unwrap();
+        book.add_order(&order(2, Side::Buy, 3, 99, 8)).unwrap();
+        let snap = book.resting_orders_snapshot();
+
+        let mut restored = OrderBook::new(InstrumentId(1));
+        restored.load_resting_orders(&snap).unwrap();
+        let again = restored.resting_orders_snapshot();
+        let sell = again.iter().find(|r| r.order_id == OrderId(1)).unwrap();
+        assert_eq!(sell.client_order_id, "client-abc");
+        assert_eq!(sell.timestamp, 1234);
+        assert_eq!(sell.order_type, OrderType::Limit);
+        assert_eq!(sell.time_in_force, TimeInForce::GTC);
+        assert_eq!(sell.quantity, Decimal::from(5));
+        assert_eq!(again.len(), 2);
+    }
+
+    #[test]
+    fn resting_order_without_metadata_loads_with_defaults() {
+        let json = r#"{"order_id":9,"instrument_id":1,"side":"Buy","price":"100","quantity":"2","trader_id":3}"#;
+        let r: RestingOrder = serde_json::from_str(json).unwrap();
+        let order = r.to_order();
+        assert_eq!(order.client_order_id, "restore-9");
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.time_in_force, TimeInForce::GTC);
+        assert_eq!(order.timestamp, 0);
+    }
+
+    #[test]
+    fn queue_position_reports_level_and_quantity_ahead() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Sell, 4, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Sell, 6, 101, 2)).unwrap();
+        book.add_order(&order(3, Side::Sell, 9, 101, 3)).unwrap();
+        book.add_order(&order(4, Side::Sell, 1, 101, 4)).unwrap();
+        book.add_order(&order(5, Side::Buy, 2, 99, 5)).unwrap();
+        book.add_order(&order(6, Side::Buy, 2, 98, 6)).unwrap();
+
+        let q = book.queue_position(OrderId(4)).unwrap();
+        assert_eq!(q.level, 1);
+        assert_eq!(q.position, 2);
+        assert_eq!(q.quantity_ahead, Decimal::from(15));
+        let q = book.queue_position(OrderId(1)).unwrap();
+        assert_eq!((q.level, q.position, q.quantity_ahead), (0, 0, Decimal::ZERO));
+        assert_eq!(book.queue_position(OrderId(6)).unwrap().level, 1);
+        assert!(book.queue_position(OrderId(99)).is_none());
+
+        let view = book.get_order(OrderId(3)).unwrap();
+        assert_eq!((view.level, view.queue_position), (1, 1));
+        assert_eq!(view.quantity_ahead, Decimal::from(6));
+    }
+
+    #[test]
+    fn bids_and_asks_iter_borrow_in_priority_order() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 1, 98, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 2, 99, 1)).unwrap();
+        book.add_order(&order(3, Side::Buy, 3, 99, 1)).unwrap();
+        book.add_order(&order(4, Side::Sell, 4, 102, 1)).unwrap();
+        book.add_order(&order(5, Side::Sell, 5, 101, 1)).unwrap();
+        let bids: Vec<(u64, usize)> = book.bids_iter().map(|o| (o.order_id.0, o.queue_position)).collect();
+        assert_eq!(bids, vec![(2, 0), (3, 1), (1, 0)]);
+        let asks: Vec<u64> = book.asks_iter().map(|o| o.order_id.0).collect();
+        assert_eq!(asks, vec![5, 4]);
+        let first = book.asks_iter().next().unwrap();
+        assert_eq!(first.client_order_id, "c5");
+        assert_eq!(first.remaining_quantity, Decimal::from(5));
+    }
+
+    #[test]
+    fn add_order_enforces_limits_with_distinct_reasons() {
+        let limits = BookLimits {
+            max_orders_per_trader: Some(2),
+            max_orders_per_level: Some(2),
+            max_book_orders: Some(3),
+        };
+        let mut book = OrderBook::with_limits(InstrumentId(1), limits);
+        book.add_order(&order(1, Side::Buy, 1, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 1, 99, 1)).unwrap();
+        let err = book.add_order(&order(3, Side::Buy, 1, 98, 1)).unwrap_err();
+        assert!(err.contains("Trader 1 resting order limit"), "{}", err);
+
+        book.add_order(&order(4, Side::Buy, 1, 100, 2)).unwrap();
+        let err = book.add_order(&order(5, Side::Sell, 1, 101, 3)).unwrap_err();
+        assert!(err.contains("Book order limit"), "{}", err);
+
+        book.cancel_order(OrderId(2));
+        let err = book.add_order(&order(6, Side::Buy, 1, 100, 3)).unwrap_err();
+        assert!(err.contains("Price level 100 order limit"), "{}", err);
+        book.add_order(&order(7, Side::Buy, 1, 99, 3)).unwrap();
+    }
+
+    #[test]
+    fn check_resting_limits_ignores_orders_that_cannot_rest_and_the_replaced_order() {
+        let limits = BookLimits {
+            max_orders_per_trader: Some(1),
+            ..Default::default()
+        };
+        let mut book = OrderBook::with_limits(InstrumentId(1), limits);
+        book.add_order(&order(1, Side::Buy, 5, 99, 1)).unwrap();
+        book.add_order(&order(2, Side::Sell, 5, 101, 2)).unwrap();
+        assert!(book.check_resting_limits(&order(3, Side::Buy, 1, 98, 1), None).is_err());
+        assert!(book.check_resting_limits(&order(3, Side::Buy, 5, 101, 1), None).is_ok(), "fully crosses");
+        let mut ioc = order(3, Side::Buy, 1, 98, 1);
+        ioc.time_in_force = TimeInForce::IOC;
+        assert!(book.check_resting_limits(&ioc, None).is_ok());
+        assert!(book.check_resting_limits(&order(3, Side::Buy, 1, 98, 1), Some(OrderId(1))).is_ok());
+    }
+
+    #[test]
+    fn add_order_existing_id_returns_err() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 1, 100, 1)).unwrap();
+        assert!(book.add_order(&order(1, Side::Buy, 1, 101, 1)).is_err());
+        assert_eq!(book.best_bid(), Some(Decimal::from(100)));
+    }
+
     #[test]
     fn modify_order_same_id_replaces_price_and_quantity() {
         let mut book = OrderBook::new(InstrumentId(1));
@@ -401,6 +1478,71 @@ mod tests {
         assert!(book.best_ask().is_none());
     }
 
+    #[test]
+    fn modify_order_quantity_down_keeps_time_priority() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Sell, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Sell, 10, 100, 2)).unwrap();
+        let replacement = order(1, Side::Sell, 4, 100, 1);
+        assert!(book.is_quantity_reduction(OrderId(1), &replacement));
+        book.modify_order(OrderId(1), &replacement).unwrap();
+        let fills = book.take_from_asks(Decimal::from(100), Decimal::from(4), TraderId(3));
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].resting_order_id, OrderId(1), "reduced order stays at the front");
+        assert!(fills[0].resting_fully_filled);
+    }
+
+    #[test]
+    fn modify_order_quantity_down_new_id_rekeys_in_place() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 10, 100, 2)).unwrap();
+        book.modify_order(OrderId(1), &order(3, Side::Buy, 5, 100, 1)).unwrap();
+        assert!(!book.cancel_order(OrderId(1)), "old id no longer on book");
+        let fills = book.take_from_bids(Decimal::from(100), Decimal::from(5), TraderId(4));
+        assert_eq!(fills[0].resting_order_id, OrderId(3));
+    }
+
+    #[test]
+    fn modify_order_quantity_up_or_price_change_loses_priority() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Sell, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Sell, 10, 100, 2)).unwrap();
+        let bigger = order(1, Side::Sell, 15, 100, 1);
+        assert!(!book.is_quantity_reduction(OrderId(1), &bigger));
+        book.modify_order(OrderId(1), &bigger).unwrap();
+        let fills = book.take_from_asks(Decimal::from(100), Decimal::from(1), TraderId(3));
+        assert_eq!(fills[0].resting_order_id, OrderId(2), "quantity-up goes to the back");
+        assert!(!book.is_quantity_reduction(OrderId(2), &order(2, Side::Sell, 5, 101, 2)));
+    }
+
+    #[test]
+    fn orders_iter_lists_bids_then_asks_in_priority_order() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 10, 99, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 5, 100, 2)).unwrap();
+        book.add_order(&order(3, Side::Buy, 7, 100, 3)).unwrap();
+        book.add_order(&order(4, Side::Sell, 4, 102, 4)).unwrap();
+        book.add_order(&order(5, Side::Sell, 6, 101, 5)).unwrap();
+        let view: Vec<(Side, i64, usize, u64)> = book
+            .orders_iter()
+            .map(|o| (o.side, o.price.try_into().unwrap(), o.queue_position, o.order_id.0))
+            .collect();
+        assert_eq!(
+            view,
+            vec![
+                (Side::Buy, 100, 0, 2),
+                (Side::Buy, 100, 1, 3),
+                (Side::Buy, 99, 0, 1),
+                (Side::Sell, 101, 0, 5),
+                (Side::Sell, 102, 0, 4),
+            ]
+        );
+        let first = book.orders_iter().next().unwrap();
+        assert_eq!(first.remaining_quantity, Decimal::from(5));
+        assert_eq!(first.trader_id, TraderId(2));
+    }
+
     #[test]
     fn modify_order_not_found_returns_err() {
         let mut book = OrderBook::new(InstrumentId(1));
@@ -428,6 +1570,145 @@ mod tests {
         assert!(err.to_lowercase().contains("price"));
     }
 
+    #[test]
+    fn tick_size_rejects_off_tick_prices_and_rounds_taking_limits_inward() {
+        assert!(OrderBook::with_tick_size(InstrumentId(1), Decimal::ZERO).is_err());
+        let tick = Decimal::new(5, 2);
+        let mut book = OrderBook::with_tick_size(InstrumentId(1), tick).unwrap();
+        let mut off_tick = order(1, Side::Sell, 10, 100, 1);
+        off_tick.price = Some(Decimal::new(10003, 2));
+        let err = book.add_order(&off_tick).unwrap_err();
+        assert!(err.contains("tick size"), "{}", err);
+        assert!(book.validate_order(&off_tick, None).is_err());
+
+        let mut ask = order(2, Side::Sell, 10, 100, 1);
+        ask.price = Some(Decimal::new(10005, 2));
+        book.add_order(&ask).unwrap();
+        assert_eq!(book.best_ask(), Some(Decimal::new(10005, 2)));
+        // A buy limit of 100.04 rounds down to 100.00 and must not reach the 100.05 ask.
+        let limit = Decimal::new(10004, 2);
+        assert_eq!(book.available_ask_qty_at_or_below(limit, TraderId(2)), Decimal::ZERO);
+        assert!(book.take_from_asks(limit, Decimal::from(1), TraderId(2)).is_empty());
+        let fills = book.take_from_asks(Decimal::MAX, Decimal::from(4), TraderId(2));
+        assert_eq!(fills[0].price, Decimal::new(10005, 2));
+        assert_eq!(book.depth(Side::Sell, 1), vec![(Decimal::new(10005, 2), Decimal::from(6))]);
+    }
+
+    #[test]
+    fn book_delta_reports_added_changed_and_removed_levels() {
+        let mut book = OrderBookBuilder::new().bid(99, 10).bid(98, 5).ask(101, 7).build();
+        let before = book.levels();
+        assert!(BookDelta::between(&before, &before).is_empty());
+
+        book.cancel_order(OrderId(2));
+        book.add_order(&order(4, Side::Buy, 3, 100, 1)).unwrap();
+        book.take_from_asks(Decimal::from(101), Decimal::from(2), TraderId(9));
+        let delta = BookDelta::between(&before, &book.levels());
+        let change = |price: i64, qty: i64, action| LevelChange {
+            price: Decimal::from(price),
+            quantity: Decimal::from(qty),
+            action,
+        };
+        assert_eq!(
+            delta.bids,
+            vec![change(100, 3, LevelAction::Added), change(98, 0, LevelAction::Removed)]
+        );
+        assert_eq!(delta.asks, vec![change(101, 5, LevelAction::Changed)]);
+    }
+
+    #[test]
+    fn merged_book_deltas_take_the_first_base_to_the_last_levels() {
+        let mut book = OrderBookBuilder::new().bid(99, 10).bid(98, 5).ask(101, 7).build();
+        let first = book.levels();
+        book.add_order(&order(4, Side::Buy, 3, 100, 1)).unwrap();
+        book.cancel_order(OrderId(2));
+        let second = book.levels();
+        book.cancel_order(OrderId(4));
+        book.add_order(&order(5, Side::Buy, 6, 98, 1)).unwrap();
+        book.take_from_asks(Decimal::from(101), Decimal::from(2), TraderId(9));
+        let third = book.levels();
+
+        let mut merged = BookDelta::between(&first, &second);
+        merged.merge(BookDelta::between(&second, &third));
+        // 100 was added then removed (no net change); 98 was removed then re-added.
+        assert_eq!(merged, BookDelta::between(&first, &third));
+        let change = |price: i64, qty: i64, action| LevelChange {
+            price: Decimal::from(price),
+            quantity: Decimal::from(qty),
+            action,
+        };
+        assert_eq!(merged.bids, vec![change(98, 6, LevelAction::Changed)]);
+        assert_eq!(merged.asks, vec![change(101, 5, LevelAction::Changed)]);
+    }
+
+    #[test]
+    fn snapshot_restore_round_trips_config_orders_and_priority() {
+        let limits = BookLimits {
+            max_orders_per_trader: Some(5),
+            ..BookLimits::default()
+        };
+        let mut book = OrderBook::with_tick_size(InstrumentId(1), Decimal::new(5, 1)).unwrap();
+        book.set_limits(limits);
+        book.add_order(&order(1, Side::Buy, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 4, 100, 2)).unwrap();
+        book.add_order(&order(3, Side::Sell, 7, 102, 3)).unwrap();
+        book.take_from_bids(Decimal::from(100), Decimal::from(3), TraderId(9));
+
+        let json = serde_json::to_string(&book.snapshot()).unwrap();
+        let restored = OrderBook::restore(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(restored.snapshot(), book.snapshot());
+        assert_eq!(restored.tick_size(), Decimal::new(5, 1));
+        assert_eq!(restored.limits(), limits);
+        assert_eq!(restored.queue_position(OrderId(2)).unwrap().quantity_ahead, Decimal::from(7));
+        assert_eq!(restored.get_order(OrderId(1)).unwrap().remaining_quantity, Decimal::from(7));
+
+        let mut bad = book.snapshot();
+        bad.tick_size = Decimal::new(3, 0);
+        assert!(OrderBook::restore(&bad).is_err());
+    }
+
+    #[test]
+    fn per_trader_resting_helpers() {
+        let mut book = OrderBook::new(InstrumentId(1));
+        book.add_order(&order(1, Side::Buy, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 4, 99, 1)).unwrap();
+        book.add_order(&order(3, Side::Sell, 7, 102, 1)).unwrap();
+        book.add_order(&order(4, Side::Buy, 5, 100, 2)).unwrap();
+        assert_eq!(book.resting_count(), 4);
+        assert_eq!(book.resting_count_for_trader(TraderId(1)), 3);
+        assert_eq!(book.resting_qty_by_trader(TraderId(1), Side::Buy), Decimal::from(14));
+        assert_eq!(book.resting_qty_by_trader(TraderId(1), Side::Sell), Decimal::from(7));
+        assert_eq!(book.resting_qty_by_trader(TraderId(3), Side::Buy), Decimal::ZERO);
+        let ids: Vec<u64> = book.resting_orders_for_trader(TraderId(1)).map(|o| o.order_id.0).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        let ids: Vec<u64> = book.resting_orders_for_trader(TraderId(2)).map(|o| o.order_id.0).collect();
+        assert_eq!(ids, vec![4]);
+        assert_eq!(book.resting_orders_for_trader(TraderId(3)).count(), 0);
+    }
+
+    #[test]
+    fn is_crossed_when_best_bid_reaches_best_ask() {
+        assert!(!OrderBookBuilder::new().bid(100, 10).build().is_crossed());
+        assert!(!OrderBookBuilder::new().bid(100, 10).ask(101, 10).build().is_crossed());
+        assert!(OrderBookBuilder::new().bid(100, 10).ask(101, 10).ask(100, 10).build().is_crossed());
+    }
+
+    #[test]
+    fn builder_assigns_ids_priority_and_traders() {
+        let book = OrderBookBuilder::new()
+            .tick_size(Decimal::new(5, 1))
+            .bid(Decimal::new(995, 1), 3)
+            .bid(Decimal::new(995, 1), 4)
+            .trader(TraderId(7))
+            .ask(101, 5)
+            .build();
+        assert_eq!(book.tick_size(), Decimal::new(5, 1));
+        assert_eq!(book.depth(Side::Buy, 1), vec![(Decimal::new(995, 1), Decimal::from(7))]);
+        let second = book.get_order(OrderId(2)).unwrap();
+        assert_eq!((second.queue_position, second.trader_id), (1, TraderId(2)));
+        assert_eq!(book.get_order(OrderId(3)).unwrap().trader_id, TraderId(7));
+    }
+
     #[test]
     fn instrument_id_returns_book_instrument() {
         let book = OrderBook::new(InstrumentId(42));
@@ -467,4 +1748,26 @@ mod tests {
             Decimal::from(10)
         );
     }
+
+    #[test]
+    fn set_tick_size_keeps_priority_and_refuses_off_grid_orders() {
+        let mut book = OrderBook::with_tick_size(InstrumentId(1), Decimal::new(5, 1)).unwrap();
+        book.add_order(&order(1, Side::Buy, 10, 100, 1)).unwrap();
+        book.add_order(&order(2, Side::Buy, 4, 100, 2)).unwrap();
+        book.add_order(&order(3, Side::Sell, 7, 102, 3)).unwrap();
+
+        assert!(book.set_tick_size(Decimal::new(5, 0)).is_err(), "102 is off a 5 grid");
+        assert_eq!(book.tick_size(), Decimal::new(5, 1));
+        assert!(book.set_tick_size(Decimal::ZERO).is_err());
+
+        book.set_tick_size(Decimal::from(2)).unwrap();
+        assert_eq!(book.tick_size(), Decimal::from(2));
+        assert_eq!(book.best_bid(), Some(Decimal::from(100)));
+        assert_eq!(book.best_ask(), Some(Decimal::from(102)));
+        assert_eq!(book.queue_position(OrderId(2)).unwrap().quantity_ahead, Decimal::from(10));
+        let mut off_tick = order(4, Side::Buy, 1, 101, 4);
+        assert!(book.validate_order(&off_tick, None).is_err());
+        off_tick.price = Some(Decimal::from(98));
+        assert!(book.validate_order(&off_tick, None).is_ok());
+    }
 }
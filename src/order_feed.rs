@@ -0,0 +1,732 @@
+//! Order-by-order (L3) public feed in the style of ITCH: every resting order's Add, Execute, Cancel, Delete, and
+//! Replace, so a subscriber can rebuild each book exactly, queue order included (see [`OrderBookReplica`]).
+//! Traders are not disclosed.
+//!
+//! Messages are big-endian: a `u16` length of what follows, a type byte, then `seq` (`u64`, 1, 2, 3, ... across
+//! all instruments), `engine_seq` (`u64`, the engine sequence number of the trade or book change), and
+//! `instrument_id` (`u64`), then the type's fields. Prices and quantities are `i64` mantissas with exponent -8
+//! (as in [`crate::binary_feed`]); sides are `B` and `S`.
+//!
+//! - **`A` Add:** `order_id`, `side`, `quantity`, `price`. The order joins the back of its price level.
+//! - **`E` Execute:** `order_id`, `quantity`, `price`, `trade_id`. A resting order traded; it leaves the book
+//!   when nothing remains.
+//! - **`X` Cancel:** `order_id`, `quantity`. Part of the order was canceled (an amendment down in place).
+//! - **`D` Delete:** `order_id`. The order left the book (canceled, expired, or removed).
+//! - **`U` Replace:** `orig_order_id`, `order_id`, `quantity`, `price`, `keeps_priority` (`u8`). The original
+//!   leaves the book; the new order takes its place in the queue when `keeps_priority` is 1, else joins the
+//!   back of its level.
+//! - **`R` Reset:** the instrument's book is empty; Adds of its orders follow. Sent in the snapshot on connect,
+//!   and whenever the feed cannot account for a change any other way (e.g. a state restore).
+//! - **`H` Heartbeat:** `seq` of the last message, instrument 0.
+//!
+//! [`OrderFeed`] derives the messages from the engine event stream and checks every book against what a
+//! subscriber would have rebuilt; [`run_order_feed_tcp`] serves it.
+
+use crate::binary_feed::{mantissa, price};
+use crate::engine::{MatchingEngine, MultiEngine};
+use crate::events::{BookObserver, EngineEvent, EngineEventSink};
+use crate::types::{BookOrder, ExecType, InstrumentId, OrderId, Side, TradeId};
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A subscriber this many messages behind is disconnected.
+const SUBSCRIBER_CAPACITY: usize = 65_536;
+/// How often an idle subscriber gets a Heartbeat.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// Length prefix, type, `seq`, `engine_seq`, `instrument_id`.
+const HEADER_LEN: usize = 2 + 1 + 24;
+
+/// What happened to the book, per message type (see the module docs).
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderFeedEvent {
+    Add {
+        order_id: OrderId,
+        side: Side,
+        quantity: Decimal,
+        price: Decimal,
+    },
+    Execute {
+        order_id: OrderId,
+        quantity: Decimal,
+        price: Decimal,
+        trade_id: TradeId,
+    },
+    Cancel {
+        order_id: OrderId,
+        quantity: Decimal,
+    },
+    Delete {
+        order_id: OrderId,
+    },
+    Replace {
+        orig_order_id: OrderId,
+        order_id: OrderId,
+        quantity: Decimal,
+        price: Decimal,
+        keeps_priority: bool,
+    },
+    Reset,
+    Heartbeat,
+}
+
+/// One feed message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderFeedMessage {
+    pub seq: u64,
+    pub engine_seq: u64,
+    pub instrument_id: InstrumentId,
+    pub event: OrderFeedEvent,
+}
+
+impl OrderFeedMessage {
+    /// The framed message. `Err` when a price or quantity does not fit the feed's encoding.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut body = Vec::with_capacity(64);
+        let type_byte = match &self.event {
+            OrderFeedEvent::Add { .. } => b'A',
+            OrderFeedEvent::Execute { .. } => b'E',
+            OrderFeedEvent::Cancel { .. } => b'X',
+            OrderFeedEvent::Delete { .. } => b'D',
+            OrderFeedEvent::Replace { .. } => b'U',
+            OrderFeedEvent::Reset => b'R',
+            OrderFeedEvent::Heartbeat => b'H',
+        };
+        body.push(type_byte);
+        for value in [self.seq, self.engine_seq, self.instrument_id.0] {
+            body.extend(value.to_be_bytes());
+        }
+        let decimal = |body: &mut Vec<u8>, value: Decimal| -> Result<(), String> {
+            body.extend(mantissa(value)?.to_be_bytes());
+            Ok(())
+        };
+        match &self.event {
+            OrderFeedEvent::Add { order_id, side, quantity, price } => {
+                body.extend(order_id.0.to_be_bytes());
+                body.push(side_code(*side));
+                decimal(&mut body, *quantity)?;
+                decimal(&mut body, *price)?;
+            }
+            OrderFeedEvent::Execute { order_id, quantity, price, trade_id } => {
+                body.extend(order_id.0.to_be_bytes());
+                decimal(&mut body, *quantity)?;
+                decimal(&mut body, *price)?;
+                body.extend(trade_id.0.to_be_bytes());
+            }
+            OrderFeedEvent::Cancel { order_id, quantity } => {
+                body.extend(order_id.0.to_be_bytes());
+                decimal(&mut body, *quantity)?;
+            }
+            OrderFeedEvent::Delete { order_id } => body.extend(order_id.0.to_be_bytes()),
+            OrderFeedEvent::Replace { orig_order_id, order_id, quantity, price, keeps_priority } => {
+                body.extend(orig_order_id.0.to_be_bytes());
+                body.extend(order_id.0.to_be_bytes());
+                decimal(&mut body, *quantity)?;
+                decimal(&mut body, *price)?;
+                body.push(u8::from(*keeps_priority));
+            }
+            OrderFeedEvent::Reset | OrderFeedEvent::Heartbeat => {}
+        }
+        let mut out = Vec::with_capacity(2 + body.len());
+        out.extend((body.len() as u16).to_be_bytes());
+        out.extend(body);
+        Ok(out)
+    }
+
+    /// The message at the start of `buf` and the bytes it takes; `Ok(None)` while it is incomplete.
+    pub fn decode(buf: &[u8]) -> Result<Option<(OrderFeedMessage, usize)>, String> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let len = 2 + u16::from_be_bytes([buf[0], buf[1]]) as usize;
+        if len < HEADER_LEN {
+            return Err(format!("message length {} is shorter than its header", len));
+        }
+        if buf.len() < len {
+            return Ok(None);
+        }
+        let mut r = Reader { buf: &buf[3..len] };
+        let (seq, engine_seq, instrument_id) = (r.u64()?, r.u64()?, InstrumentId(r.u64()?));
+        let event = match buf[2] {
+            b'A' => OrderFeedEvent::Add {
+                order_id: OrderId(r.u64()?),
+                side: side_of(r.u8()?)?,
+                quantity: price(r.i64()?),
+                price: price(r.i64()?),
+            },
+            b'E' => OrderFeedEvent::Execute {
+                order_id: OrderId(r.u64()?),
+                quantity: price(r.i64()?),
+                price: price(r.i64()?),
+                trade_id: TradeId(r.u64()?),
+            },
+            b'X' => OrderFeedEvent::Cancel {
+                order_id: OrderId(r.u64()?),
+                quantity: price(r.i64()?),
+            },
+            b'D' => OrderFeedEvent::Delete { order_id: OrderId(r.u64()?) },
+            b'U' => OrderFeedEvent::Replace {
+                orig_order_id: OrderId(r.u64()?),
+                order_id: OrderId(r.u64()?),
+                quantity: price(r.i64()?),
+                price: price(r.i64()?),
+                keeps_priority: r.u8()? != 0,
+            },
+            b'R' => OrderFeedEvent::Reset,
+            b'H' => OrderFeedEvent::Heartbeat,
+            other => return Err(format!("unknown message type {:?}", other as char)),
+        };
+        Ok(Some((OrderFeedMessage { seq, engine_seq, instrument_id, event }, len)))
+    }
+}
+
+fn side_code(side: Side) -> u8 {
+    match side {
+        Side::Buy => b'B',
+        Side::Sell => b'S',
+    }
+}
+
+fn side_of(code: u8) -> Result<Side, String> {
+    match code {
+        b'B' => Ok(Side::Buy),
+        b'S' => Ok(Side::Sell),
+        other => Err(format!("invalid side {:?}", other as char)),
+    }
+}
+
+/// Big-endian reads off the front of a message.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        if self.buf.len() < N {
+            return Err("message shorter than its type".into());
+        }
+        let (head, rest) = self.buf.split_at(N);
+        self.buf = rest;
+        Ok(head.try_into().expect("N bytes"))
+    }
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.take()?))
+    }
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_be_bytes(self.take()?))
+    }
+}
+
+/// A resting order as the feed shows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedOrder {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+/// One instrument's book as rebuilt from the feed: price levels per side, orders in queue order.
+#[derive(Clone, Debug, Default)]
+struct ReplicaBook {
+    bids: BTreeMap<Decimal, VecDeque<(OrderId, Decimal)>>,
+    asks: BTreeMap<Decimal, VecDeque<(OrderId, Decimal)>>,
+    /// Side and price of each order, to find its level.
+    index: HashMap<OrderId, (Side, Decimal)>,
+}
+
+impl ReplicaBook {
+    fn levels(&mut self, side: Side) -> &mut BTreeMap<Decimal, VecDeque<(OrderId, Decimal)>> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    fn quantity(&self, order_id: OrderId) -> Option<Decimal> {
+        let (side, price) = self.index.get(&order_id)?;
+        let levels = match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        };
+        levels[price].iter().find(|(id, _)| *id == order_id).map(|(_, qty)| *qty)
+    }
+
+    fn add(&mut self, order_id: OrderId, side: Side, price: Decimal, quantity: Decimal) {
+        self.levels(side).entry(price).or_default().push_back((order_id, quantity));
+        self.index.insert(order_id, (side, price));
+    }
+
+    /// Remove `order_id`, returning its side, price, quantity, and position in its level.
+    fn remove(&mut self, order_id: OrderId) -> Option<(Side, Decimal, Decimal, usize)> {
+        let (side, price) = self.index.remove(&order_id)?;
+        let levels = self.levels(side);
+        let level = levels.get_mut(&price)?;
+        let position = level.iter().position(|(id, _)| *id == order_id)?;
+        let (_, quantity) = level.remove(position)?;
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+        Some((side, price, quantity, position))
+    }
+
+    /// Take `quantity` off `order_id`, removing it when nothing is left.
+    fn reduce(&mut self, order_id: OrderId, quantity: Decimal) -> Result<(), String> {
+        let (side, price) = *self.index.get(&order_id).ok_or_else(|| format!("unknown order {}", order_id.0))?;
+        let level = self.levels(side).get_mut(&price).expect("indexed level");
+        let entry = level.iter_mut().find(|(id, _)| *id == order_id).expect("indexed order");
+        if quantity > entry.1 {
+            return Err(format!("order {} has {} left, not {}", order_id.0, entry.1, quantity));
+        }
+        entry.1 -= quantity;
+        if entry.1.is_zero() {
+            self.remove(order_id);
+        }
+        Ok(())
+    }
+
+    /// Orders in book order: bids best (highest) first, then asks best (lowest) first, each level in time
+    /// priority, as [`MultiEngine::book_orders`] lists them.
+    fn orders(&self) -> Vec<FeedOrder> {
+        let side = |side: Side, level: (&Decimal, &VecDeque<(OrderId, Decimal)>)| {
+            let (&price, queue) = level;
+            queue
+                .iter()
+                .map(move |&(order_id, quantity)| FeedOrder { order_id, side, price, quantity })
+                .collect::<Vec<_>>()
+        };
+        let mut out: Vec<FeedOrder> = self.bids.iter().rev().flat_map(|l| side(Side::Buy, l)).collect();
+        out.extend(self.asks.iter().flat_map(|l| side(Side::Sell, l)));
+        out
+    }
+}
+
+/// Every book as a subscriber rebuilds it from [`OrderFeedMessage`]s.
+#[derive(Clone, Debug, Default)]
+pub struct OrderBookReplica {
+    books: HashMap<InstrumentId, ReplicaBook>,
+}
+
+impl OrderBookReplica {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply one message. `Err` when it names an order the replica does not have.
+    pub fn apply(&mut self, message: &OrderFeedMessage) -> Result<(), String> {
+        if message.event == OrderFeedEvent::Heartbeat {
+            return Ok(());
+        }
+        let book = self.books.entry(message.instrument_id).or_default();
+        let unknown = |order_id: OrderId| format!("unknown order {}", order_id.0);
+        match &message.event {
+            OrderFeedEvent::Add { order_id, side, quantity, price } => book.add(*order_id, *side, *price, *quantity),
+            OrderFeedEvent::Execute { order_id, quantity, .. } | OrderFeedEvent::Cancel { order_id, quantity } => {
+                book.reduce(*order_id, *quantity)?
+            }
+            OrderFeedEvent::Delete { order_id } => {
+                book.remove(*order_id).ok_or_else(|| unknown(*order_id))?;
+            }
+            OrderFeedEvent::Replace { orig_order_id, order_id, quantity, price, keeps_priority } => {
+                let (side, _, _, position) = book.remove(*orig_order_id).ok_or_else(|| unknown(*orig_order_id))?;
+                let level = book.levels(side).entry(*price).or_default();
+                let position = if *keeps_priority { position.min(level.len()) } else { level.len() };
+                level.insert(position, (*order_id, *quantity));
+                book.index.insert(*order_id, (side, *price));
+            }
+            OrderFeedEvent::Reset => *book = ReplicaBook::default(),
+            OrderFeedEvent::Heartbeat => {}
+        }
+        Ok(())
+    }
+
+    /// Resting orders of `instrument_id` in book order (see [`MultiEngine::book_orders`]).
+    pub fn orders(&self, instrument_id: InstrumentId) -> Vec<FeedOrder> {
+        self.books.get(&instrument_id).map(ReplicaBook::orders).unwrap_or_default()
+    }
+}
+
+fn feed_orders(orders: &[BookOrder]) -> Vec<FeedOrder> {
+    orders
+        .iter()
+        .map(|o| FeedOrder {
+            order_id: o.order_id,
+            side: o.side,
+            price: o.price,
+            quantity: o.remaining_quantity,
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct FeedState {
+    /// `seq` of the last message published.
+    last_seq: u64,
+    /// The books as subscribers have them.
+    replica: OrderBookReplica,
+    /// Engine events since the last book change, turned into messages at the next one.
+    pending: Vec<EngineEvent>,
+    /// Books resent with a Reset since the feed started.
+    resets: u64,
+    subscribers: Vec<SyncSender<Arc<[u8]>>>,
+}
+
+impl FeedState {
+    /// Number `event`, apply it to the replica, and send it.
+    fn publish(&mut self, engine_seq: u64, instrument_id: InstrumentId, event: OrderFeedEvent) {
+        let message = OrderFeedMessage {
+            seq: self.last_seq + 1,
+            engine_seq,
+            instrument_id,
+            event,
+        };
+        let bytes: Arc<[u8]> = match message.encode() {
+            Ok(bytes) => bytes.into(),
+            Err(e) => {
+                warn!("order feed message not published: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self.replica.apply(&message) {
+            warn!("order feed message not published: {}", e);
+            return;
+        }
+        self.last_seq += 1;
+        self.subscribers.retain(|tx| match tx.try_send(bytes.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!("order feed subscriber dropped: {} messages behind", SUBSCRIBER_CAPACITY);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+
+    /// Messages for the events since the last change of `instrument_id`'s book, which now holds `orders`.
+    fn book_changed(&mut self, instrument_id: InstrumentId, engine_seq: u64, orders: &[FeedOrder]) {
+        let events = std::mem::take(&mut self.pending);
+        let book = self.replica.books.entry(instrument_id).or_default();
+        let now: HashMap<OrderId, &FeedOrder> = orders.iter().map(|o| (o.order_id, o)).collect();
+        let traded = events.iter().any(|event| matches!(event, EngineEvent::Trade(trade) if trade.instrument_id == instrument_id));
+        let mut replaced: Vec<(OrderId, OrderId)> = events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::Report(report) if report.exec_type == ExecType::Replaced => report.orig_order_id.map(|orig| (orig, report.order_id)),
+                _ => None,
+            })
+            .filter(|(orig, _)| book.index.contains_key(orig))
+            .collect();
+        let replaced_originals: HashSet<OrderId> = replaced.iter().map(|(orig, _)| *orig).collect();
+        // The original of a replacement that traded, or does not rest, left the book before the replacement
+        // matched; the replacement then shows up as an Add.
+        replaced.retain(|&(orig, order_id)| {
+            if !traded && now.contains_key(&order_id) {
+                return true;
+            }
+            self.publish(engine_seq, instrument_id, OrderFeedEvent::Delete { order_id: orig });
+            false
+        });
+        for event in &events {
+            match event {
+                EngineEvent::Trade(trade) if trade.instrument_id == instrument_id => {
+                    for order_id in [trade.buy_order_id, trade.sell_order_id] {
+                        let resting = self.replica.books[&instrument_id].index.contains_key(&order_id);
+                        if resting && !replaced_originals.contains(&order_id) {
+                            let event = OrderFeedEvent::Execute {
+                                order_id,
+                                quantity: trade.quantity,
+                                price: trade.price,
+                                trade_id: trade.trade_id,
+                            };
+                            self.publish(trade.seq, instrument_id, event);
+                        }
+                    }
+                }
+                EngineEvent::Canceled { order_id, instrument_id: id } | EngineEvent::Expired { order_id, instrument_id: id, .. }
+                    if *id == instrument_id
+                        && !replaced_originals.contains(order_id)
+                        && self.replica.books[&instrument_id].index.contains_key(order_id) =>
+                {
+                    self.publish(engine_seq, instrument_id, OrderFeedEvent::Delete { order_id: *order_id });
+                }
+                _ => {}
+            }
+        }
+        for (orig, order_id) in replaced {
+            let book = &self.replica.books[&instrument_id];
+            let (Some(&(side, old_price)), Some(order)) = (book.index.get(&orig), now.get(&order_id)) else { continue };
+            let old_quantity = book.quantity(orig).unwrap_or_default();
+            let event = OrderFeedEvent::Replace {
+                orig_order_id: orig,
+                order_id,
+                quantity: order.quantity,
+                price: order.price,
+                keeps_priority: order.side == side && order.price == old_price && order.quantity <= old_quantity,
+            };
+            self.publish(engine_seq, instrument_id, event);
+        }
+        // What the events don't explain: departures, partial cancels, and new resting orders.
+        let before = self.replica.orders(instrument_id);
+        for order in &before {
+            match now.get(&order.order_id) {
+                None => self.publish(engine_seq, instrument_id, OrderFeedEvent::Delete { order_id: order.order_id }),
+                Some(after) if after.quantity < order.quantity && after.price == order.price => {
+                    let event = OrderFeedEvent::Cancel {
+                        order_id: order.order_id,
+                        quantity: order.quantity - after.quantity,
+                    };
+                    self.publish(engine_seq, instrument_id, event);
+                }
+                Some(_) => {}
+            }
+        }
+        let known: HashSet<OrderId> = before.iter().map(|o| o.order_id).collect();
+        for order in orders.iter().filter(|o| !known.contains(&o.order_id)) {
+            let event = OrderFeedEvent::Add {
+                order_id: order.order_id,
+                side: order.side,
+                quantity: order.quantity,
+                price: order.price,
+            };
+            self.publish(engine_seq, instrument_id, event);
+        }
+        if self.replica.orders(instrument_id) != orders {
+            warn!("order feed resets instrument {}: the book changed in a way the events do not explain", instrument_id.0);
+            self.reset(instrument_id, engine_seq, orders);
+        }
+    }
+
+    /// Reset `instrument_id` and add `orders` again.
+    fn reset(&mut self, instrument_id: InstrumentId, engine_seq: u64, orders: &[FeedOrder]) {
+        self.resets += 1;
+        self.publish(engine_seq, instrument_id, OrderFeedEvent::Reset);
+        for order in orders {
+            let event = OrderFeedEvent::Add {
+                order_id: order.order_id,
+                side: order.side,
+                quantity: order.quantity,
+                price: order.price,
+            };
+            self.publish(engine_seq, instrument_id, event);
+        }
+    }
+}
+
+/// Publishes the order-by-order feed of an engine. Register it with [`OrderFeed::attach`]; it runs under the
+/// engine lock, so messages are numbered in engine order.
+#[derive(Default)]
+pub struct OrderFeed {
+    state: Mutex<FeedState>,
+}
+
+impl OrderFeed {
+    /// A feed registered as an event sink and book observer of `engine`, starting from its books as they are now.
+    pub fn attach(engine: &Mutex<MultiEngine>) -> Arc<Self> {
+        let mut engine = engine.lock().expect("lock");
+        let mut replica = OrderBookReplica::new();
+        for id in engine.instruments() {
+            let book = replica.books.entry(id).or_default();
+            for order in feed_orders(&engine.book_orders(id).unwrap_or_default()) {
+                book.add(order.order_id, order.side, order.price, order.quantity);
+            }
+        }
+        let feed = Arc::new(Self {
+            state: Mutex::new(FeedState { replica, ..FeedState::default() }),
+        });
+        engine.add_event_sink(feed.clone());
+        engine.add_book_observer(feed.clone());
+        feed
+    }
+
+    /// A snapshot (Reset and Adds per instrument, numbered with the last `seq`), then a channel of the messages
+    /// that follow it. Holds the engine lock so no change falls between the two.
+    pub fn subscribe(&self, engine: &Mutex<MultiEngine>) -> (Vec<OrderFeedMessage>, Receiver<Arc<[u8]>>) {
+        let engine = engine.lock().expect("lock");
+        let mut state = self.state.lock().expect("lock");
+        let mut instruments: Vec<_> = state.replica.books.keys().copied().collect();
+        instruments.sort_by_key(|id| id.0);
+        let mut snapshot = Vec::new();
+        for instrument_id in instruments {
+            let message = |event| OrderFeedMessage {
+                seq: state.last_seq,
+                engine_seq: engine.book_snapshot_for(instrument_id).map_or(0, |snapshot| snapshot.seq),
+                instrument_id,
+                event,
+            };
+            snapshot.push(message(OrderFeedEvent::Reset));
+            for order in state.replica.orders(instrument_id) {
+                snapshot.push(message(OrderFeedEvent::Add {
+                    order_id: order.order_id,
+                    side: order.side,
+                    quantity: order.quantity,
+                    price: order.price,
+                }));
+            }
+        }
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_CAPACITY);
+        state.subscribers.push(tx);
+        (snapshot, rx)
+    }
+
+    /// `seq` of the last message published.
+    pub fn last_seq(&self) -> u64 {
+        self.state.lock().expect("lock").last_seq
+    }
+
+    /// How many times a book was resent with a Reset because the events did not explain a change.
+    pub fn resets(&self) -> u64 {
+        self.state.lock().expect("lock").resets
+    }
+}
+
+impl EngineEventSink for OrderFeed {
+    fn on_event(&self, event: &EngineEvent) {
+        if matches!(
+            event,
+            EngineEvent::Trade(_) | EngineEvent::Report(_) | EngineEvent::Canceled { .. } | EngineEvent::Expired { .. }
+        ) {
+            self.state.lock().expect("lock").pending.push(event.clone());
+        }
+    }
+}
+
+impl BookObserver for OrderFeed {
+    fn on_book_changed(&self, engine: &MultiEngine, instrument_id: InstrumentId) {
+        let engine_seq = engine.book_snapshot_for(instrument_id).map_or(0, |snapshot| snapshot.seq);
+        let mut state = self.state.lock().expect("lock");
+        match engine.book_orders(instrument_id) {
+            Some(orders) => state.book_changed(instrument_id, engine_seq, &feed_orders(&orders)),
+            None => {
+                state.pending.clear();
+                if state.replica.books.remove(&instrument_id).is_some() {
+                    state.publish(engine_seq, instrument_id, OrderFeedEvent::Reset);
+                    state.replica.books.remove(&instrument_id);
+                }
+            }
+        }
+    }
+}
+
+/// Serve `feed` over TCP on `listener`: each connection gets the snapshot, then every message as it is
+/// published, and a Heartbeat when idle. One that falls too far behind is disconnected.
+pub fn run_order_feed_tcp(listener: TcpListener, engine: Arc<Mutex<MultiEngine>>, feed: Arc<OrderFeed>) {
+    for stream in listener.incoming().flatten() {
+        let (snapshot, rx) = feed.subscribe(&engine);
+        let last_seq = snapshot.first().map_or_else(|| feed.last_seq(), |m| m.seq);
+        std::thread::spawn(move || {
+            let mut stream = stream;
+            let _ = stream.set_nodelay(true);
+            let mut send = |bytes: &[u8]| stream.write_all(bytes).map_err(|e| e.to_string());
+            let result = snapshot
+                .iter()
+                .try_for_each(|m| send(&m.encode()?))
+                .and_then(|()| forward(&rx, last_seq, send));
+            if let Err(e) = result {
+                warn!("order feed subscriber disconnected: {}", e);
+            }
+        });
+    }
+}
+
+/// Send what `rx` delivers, and a Heartbeat with the last `seq` whenever nothing comes for a while.
+fn forward(rx: &Receiver<Arc<[u8]>>, mut last_seq: u64, mut send: impl FnMut(&[u8]) -> Result<(), String>) -> Result<(), String> {
+    loop {
+        match rx.recv_timeout(HEARTBEAT_INTERVAL) {
+            Ok(bytes) => {
+                // `seq` follows the length and type.
+                last_seq = u64::from_be_bytes(bytes[3..11].try_into().expect("8 bytes"));
+                send(&bytes)?;
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let heartbeat = OrderFeedMessage {
+                    seq: last_seq,
+                    engine_seq: 0,
+                    instrument_id: InstrumentId(0),
+                    event: OrderFeedEvent::Heartbeat,
+                };
+                send(&heartbeat.encode()?)?;
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err("feed dropped the subscriber".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(seq: u64, event: OrderFeedEvent) -> OrderFeedMessage {
+        OrderFeedMessage {
+            seq,
+            engine_seq: seq * 10,
+            instrument_id: InstrumentId(1),
+            event,
+        }
+    }
+
+    fn add(order_id: u64, side: Side, quantity: i64, price: i64) -> OrderFeedEvent {
+        OrderFeedEvent::Add {
+            order_id: OrderId(order_id),
+            side,
+            quantity: Decimal::from(quantity),
+            price: Decimal::from(price),
+        }
+    }
+
+    #[test]
+    fn messages_round_trip_and_rebuild_the_queue() {
+        let messages = [
+            message(1, add(1, Side::Buy, 5, 100)),
+            message(2, add(2, Side::Buy, 3, 100)),
+            message(3, add(3, Side::Sell, 4, 101)),
+            message(4, OrderFeedEvent::Execute {
+                order_id: OrderId(1),
+                quantity: Decimal::new(25, 1),
+                price: Decimal::from(100),
+                trade_id: TradeId(7),
+            }),
+            message(5, OrderFeedEvent::Cancel { order_id: OrderId(3), quantity: Decimal::ONE }),
+            // In place: 1 keeps its place ahead of 2 under its new id.
+            message(6, OrderFeedEvent::Replace {
+                orig_order_id: OrderId(1),
+                order_id: OrderId(4),
+                quantity: Decimal::from(2),
+                price: Decimal::from(100),
+                keeps_priority: true,
+            }),
+            message(7, OrderFeedEvent::Replace {
+                orig_order_id: OrderId(2),
+                order_id: OrderId(5),
+                quantity: Decimal::from(3),
+                price: Decimal::from(99),
+                keeps_priority: false,
+            }),
+            message(8, OrderFeedEvent::Delete { order_id: OrderId(3) }),
+            message(9, OrderFeedEvent::Heartbeat),
+        ];
+        let stream: Vec<u8> = messages.iter().flat_map(|m| m.encode().unwrap()).collect();
+        let mut replica = OrderBookReplica::new();
+        let mut offset = 0;
+        for expected in &messages {
+            assert_eq!(OrderFeedMessage::decode(&stream[offset..offset + 2]).unwrap(), None);
+            let (decoded, len) = OrderFeedMessage::decode(&stream[offset..]).unwrap().unwrap();
+            assert_eq!(&decoded, expected);
+            replica.apply(&decoded).unwrap();
+            offset += len;
+        }
+        let book: Vec<_> = replica.orders(InstrumentId(1)).iter().map(|o| (o.order_id.0, o.price, o.quantity)).collect();
+        assert_eq!(book, [(4, Decimal::from(100), Decimal::from(2)), (5, Decimal::from(99), Decimal::from(3))]);
+
+        assert!(replica.apply(&message(10, OrderFeedEvent::Delete { order_id: OrderId(3) })).is_err());
+        replica.apply(&message(11, OrderFeedEvent::Reset)).unwrap();
+        assert!(replica.orders(InstrumentId(1)).is_empty());
+    }
+}
@@ -1,43 +1,1033 @@
 //! Persistence: save and load engine state (+ market state) to a file.
 //! Enables recovery after restart: instruments, resting orders, and next IDs are restored.
+//! Trade and execution ids are also reserved in a side file ([`FilePersistence::id_store`]) so they stay unique
+//! when the engine crashes after trading but before the next save.
+//! Every engine command (order entry, instrument changes, uncrosses, timers) is written ahead to a
+//! [`CommandLog`] ([`FilePersistence::command_log`]) before the engine applies it, and replayed on top of the
+//! state file at startup, so a crash between a match and the next save loses nothing.
+//! Every trade and execution report is also appended to a [`HistoryLog`] ([`FilePersistence::history_log`]),
+//! which is never compacted, so the day's activity outlives restarts and not just the live book.
+//! Saves replace the state file atomically. Admin changes are saved as they are made; for order entry, a
+//! [`PersistenceNotifier`] on the engine event stream marks the state dirty whatever it came from (REST, FIX,
+//! engine timers), and the background thread of a [`SnapshotPolicy`] (see [`crate::api::start_snapshotter`])
+//! saves it once changes settle, as well as on a timer or event count, keeping numbered copies of earlier
+//! snapshots.
+//! State files are zstd-compressed MessagePack behind a format header by default ([`SnapshotFormat::Binary`]);
+//! [`SnapshotFormat::Json`] writes readable JSON for debugging. Loading accepts either.
+//! Files record their schema version ([`PersistedState::version`]); older files are rewritten step by step by
+//! [`STATE_MIGRATIONS`] as they load.
+//! Every log record carries a CRC32 and binary state files a zstd checksum, so a torn or corrupted file is
+//! detected on load: a state file that fails falls back to an older copy, and log records that fail are dropped
+//! and counted in the [`RecoveryReport`] rather than stopping startup.
+//! [`crate::remote`] copies the state file and command log to an object store for nodes without a lasting disk.
 
 use crate::engine::EngineSnapshot;
-use std::path::Path;
+use crate::events::{EngineEvent, EngineEventSink};
+use crate::history::HistoryRecord;
+use crate::ids::FileIdStore;
+use crate::journal::JournalEntry;
+use crate::venue::VenueConfig;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
-/// Full persisted state: engine snapshot and market state (Open/Halted/Closed).
+/// Current state file schema version ([`PersistedState::version`]).
+pub const STATE_VERSION: u32 = 3;
+
+/// One step of [`STATE_MIGRATIONS`]: rewrites a decoded state file of version `from` into version `from + 1`.
+pub struct StateMigration {
+    pub from: u32,
+    pub description: &'static str,
+    pub migrate: fn(&mut Value) -> Result<(), String>,
+}
+
+/// Schema history of the state file, oldest first. A change to [`PersistedState`] or [`EngineSnapshot`] that
+/// `#[serde(default)]` cannot absorb (a rename, a new required field, a change of meaning) bumps
+/// [`STATE_VERSION`] and adds a step here, with a fixture of the old version in `tests/fixtures/state/`.
+pub const STATE_MIGRATIONS: &[StateMigration] = &[
+    StateMigration {
+        from: 1,
+        description: "resting order metadata and a versioned engine snapshot",
+        migrate: migrate_v1_resting_order_metadata,
+    },
+    StateMigration {
+        from: 2,
+        description: "state file version",
+        migrate: |_| Ok(()),
+    },
+];
+
+/// Version 1 resting orders held only id, instrument, side, price, quantity, and trader: they restore as GTC
+/// limits with a `restore-<id>` client id and no timestamp. The engine snapshot had no `version` (and no fill
+/// state, so partially filled orders restore with their remaining quantity as their size).
+fn migrate_v1_resting_order_metadata(state: &mut Value) -> Result<(), String> {
+    let engine = state.get_mut("engine").and_then(Value::as_object_mut).ok_or("missing engine")?;
+    engine.insert("version".into(), 2.into());
+    let books = engine.get_mut("books").and_then(Value::as_array_mut).ok_or("missing engine.books")?;
+    for book in books {
+        let orders = book.get_mut(1).and_then(Value::as_array_mut).ok_or("malformed engine.books")?;
+        for order in orders {
+            let order = order.as_object_mut().ok_or("malformed resting order")?;
+            let id = order.get("order_id").and_then(Value::as_u64).ok_or("resting order without order_id")?;
+            order.entry("client_order_id").or_insert_with(|| format!("restore-{}", id).into());
+            order.entry("order_type").or_insert_with(|| "Limit".into());
+            order.entry("time_in_force").or_insert_with(|| "GTC".into());
+            order.entry("timestamp").or_insert_with(|| 0.into());
+        }
+    }
+    Ok(())
+}
+
+/// Schema version of a decoded state file: its `version`, or for files written before it existed, 2 when the
+/// engine snapshot has a `version` and 1 when it does not.
+fn state_version(state: &Value) -> Result<u32, String> {
+    let version = |value: &Value| value.get("version").and_then(Value::as_u64);
+    if let Some(v) = version(state) {
+        return u32::try_from(v).map_err(|_| format!("Unsupported state file version {}", v));
+    }
+    let engine = state.get("engine").ok_or("Not a state file: no engine snapshot")?;
+    Ok(if version(engine).is_some() { 2 } else { 1 })
+}
+
+/// Bring a decoded state file of any earlier version up to [`STATE_VERSION`] and parse it. `Err` for files of
+/// a newer version, or ones a migration step cannot read.
+pub fn migrate_state(mut state: Value) -> Result<PersistedState, String> {
+    let version = state_version(&state)?;
+    if version > STATE_VERSION {
+        return Err(format!(
+            "Unsupported state file version {} (this engine reads up to {})",
+            version, STATE_VERSION
+        ));
+    }
+    for step in STATE_MIGRATIONS.iter().filter(|step| step.from >= version) {
+        (step.migrate)(&mut state).map_err(|e| format!("State file migration from version {}: {}", step.from, e))?;
+        state["version"] = (step.from + 1).into();
+        log::info!("Migrated state file from version {} to {} ({})", step.from, step.from + 1, step.description);
+    }
+    serde_json::from_value(state).map_err(|e| e.to_string())
+}
+
+/// Leading bytes of a [`SnapshotFormat::Binary`] state file, followed by a [`SNAPSHOT_FORMAT_VERSION`] byte.
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"DIRESNAP";
+
+/// Encoding after [`SNAPSHOT_MAGIC`]: 1 = zstd-compressed MessagePack of [`PersistedState`], fields by name. Files
+/// are written with a zstd content checksum; earlier files without one still load.
+pub const SNAPSHOT_FORMAT_VERSION: u8 = 1;
+
+/// zstd level for binary state files: most of the size reduction of higher levels at a fraction of the time.
+const SNAPSHOT_ZSTD_LEVEL: i32 = 3;
+
+/// How [`FilePersistence::save`] encodes the state file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// [`SNAPSHOT_MAGIC`], [`SNAPSHOT_FORMAT_VERSION`], then zstd-compressed MessagePack. MessagePack rather than
+    /// bincode because it describes itself: decimals and tagged enums decode, and fields added later with
+    /// `#[serde(default)]` load from older files as they do from JSON.
+    #[default]
+    Binary,
+    /// Pretty-printed JSON, for reading and editing by hand.
+    Json,
+}
+
+impl SnapshotFormat {
+    /// Read the format from `STATE_SNAPSHOT_FORMAT` (`binary` or `json`). Unset or unknown values give binary.
+    pub fn from_env() -> Self {
+        match std::env::var("STATE_SNAPSHOT_FORMAT") {
+            Ok(v) if v.trim().eq_ignore_ascii_case("json") => SnapshotFormat::Json,
+            _ => SnapshotFormat::Binary,
+        }
+    }
+
+    /// Encode `state` in this format.
+    pub fn encode(self, state: &PersistedState) -> Result<Vec<u8>, String> {
+        match self {
+            SnapshotFormat::Json => serde_json::to_vec_pretty(state).map_err(|e| e.to_string()),
+            SnapshotFormat::Binary => {
+                let packed = rmp_serde::to_vec_named(state).map_err(|e| e.to_string())?;
+                let mut data = Vec::with_capacity(SNAPSHOT_MAGIC.len() + 1 + packed.len() / 4);
+                data.extend_from_slice(SNAPSHOT_MAGIC);
+                data.push(SNAPSHOT_FORMAT_VERSION);
+                // The frame checksum makes a corrupted file fail to decode rather than load wrong.
+                let mut encoder = zstd::stream::Encoder::new(data, SNAPSHOT_ZSTD_LEVEL).map_err(|e| e.to_string())?;
+                encoder.include_checksum(true).map_err(|e| e.to_string())?;
+                encoder.write_all(&packed).map_err(|e| e.to_string())?;
+                encoder.finish().map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Decode a state file in either format: binary when it starts with [`SNAPSHOT_MAGIC`], JSON otherwise.
+    /// Files of the current schema version parse straight into [`PersistedState`]; others are parsed again as a
+    /// [`Value`] and go through [`migrate_state`].
+    pub fn decode(data: &[u8]) -> Result<PersistedState, String> {
+        let Some(rest) = data.strip_prefix(SNAPSHOT_MAGIC) else {
+            return match serde_json::from_slice::<PersistedState>(data) {
+                Ok(state) if state.version == STATE_VERSION => Ok(state),
+                _ => migrate_state(serde_json::from_slice(data).map_err(|e| e.to_string())?),
+            };
+        };
+        match rest.split_first() {
+            Some((&SNAPSHOT_FORMAT_VERSION, compressed)) => {
+                let packed = zstd::stream::decode_all(compressed).map_err(|e| format!("decompress: {}", e))?;
+                match rmp_serde::from_slice::<PersistedState>(&packed) {
+                    Ok(state) if state.version == STATE_VERSION => Ok(state),
+                    _ => migrate_state(rmp_serde::from_slice(&packed).map_err(|e| e.to_string())?),
+                }
+            }
+            Some((version, _)) => Err(format!("Unsupported snapshot format version {}", version)),
+            None => Err("Snapshot format version missing".to_string()),
+        }
+    }
+}
+
+/// Full persisted state: engine snapshot, market state (Open/Halted/Closed), and venue config.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct PersistedState {
+    /// Schema version ([`STATE_VERSION`] when written). Files written before it existed parse with 0 and are
+    /// migrated by [`migrate_state`].
+    #[serde(default)]
+    pub version: u32,
     pub engine: EngineSnapshot,
     pub market_state: String,
+    /// `None` in files saved before the venue config existed.
+    #[serde(default)]
+    pub venue_config: Option<VenueConfig>,
 }
 
-/// File-based persistence: one JSON file. Save after state changes; load on startup.
+/// File-based persistence: one file, in a [`SnapshotFormat`]. Save after state changes; load on startup.
 #[derive(Clone, Debug)]
 pub struct FilePersistence {
     path: std::path::PathBuf,
+    /// Whether the command log is synced to disk after every entry, and the state file on every save.
+    fsync: bool,
+    format: SnapshotFormat,
+    /// Held while a save or archive writes, so concurrent saves do not share the temp file.
+    save_lock: Arc<Mutex<()>>,
 }
 
 impl FilePersistence {
     pub fn new(path: impl AsRef<Path>) -> Self {
         Self {
             path: path.as_ref().to_path_buf(),
+            fsync: false,
+            format: SnapshotFormat::default(),
+            save_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Sync the command log to disk after every entry, and the state file before it replaces the old one, so
+    /// accepted orders survive a power loss and not just a crash of the process. Costs a disk flush per order.
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Encode saves in `format` (files in either format load).
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save state to file. It is encoded in the [`SnapshotFormat`] and written to `<path>.tmp` and renamed over the existing file, so a crash
+    /// mid-save leaves the previous state whole. Returns the CRC32 of what was written.
+    pub fn save(&self, state: &PersistedState) -> Result<u32, String> {
+        let data = self.format.encode(state)?;
+        let _guard = self.save_lock.lock().expect("lock");
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let mut file = File::create(&tmp).map_err(|e| e.to_string())?;
+        file.write_all(&data).map_err(|e| e.to_string())?;
+        if self.fsync {
+            file.sync_all().map_err(|e| e.to_string())?;
+        }
+        drop(file);
+        std::fs::rename(&tmp, &self.path).map_err(|e| e.to_string())?;
+        Ok(crc32fast::hash(&data))
+    }
+
+    /// Copy the state file to `<path>.snapshot-<seq>`, `seq` being the last engine sequence number it covers,
+    /// then delete all but the newest `retain` copies. Returns the new copy's path.
+    pub fn archive(&self, seq: u64, retain: usize) -> Result<PathBuf, String> {
+        let _guard = self.save_lock.lock().expect("lock");
+        let mut copy = self.path.clone().into_os_string();
+        copy.push(format!(".snapshot-{}", seq));
+        let mut tmp = copy.clone();
+        tmp.push(".tmp");
+        std::fs::copy(&self.path, &tmp).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &copy).map_err(|e| e.to_string())?;
+        let snapshots = self.snapshots()?;
+        for (_, path) in &snapshots[..snapshots.len().saturating_sub(retain)] {
+            std::fs::remove_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
         }
+        Ok(copy.into())
+    }
+
+    /// Copies kept by [`FilePersistence::archive`], with the sequence number each covers, oldest first.
+    pub fn snapshots(&self) -> Result<Vec<(u64, PathBuf)>, String> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let Some(name) = self.path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(Vec::new());
+        };
+        let prefix = format!("{}.snapshot-", name);
+        let mut snapshots = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let file_name = entry.file_name();
+            let seq = file_name.to_str().and_then(|s| s.strip_prefix(&prefix)).and_then(|s| s.parse().ok());
+            if let Some(seq) = seq {
+                snapshots.push((seq, entry.path()));
+            }
+        }
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    /// Id reservation file next to the state file (`<path>.ids`).
+    pub fn id_store(&self) -> FileIdStore {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".ids");
+        FileIdStore::new(path)
+    }
+
+    /// Command log next to the state file (`<path>.wal`), see [`FileCommandLog::open`].
+    pub fn command_log(&self) -> Result<FileCommandLog, String> {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".wal");
+        FileCommandLog::open(path, self.fsync)
     }
 
-    /// Save state to file. Overwrites existing file.
-    pub fn save(&self, state: &PersistedState) -> Result<(), String> {
-        let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
-        std::fs::write(&self.path, json).map_err(|e| e.to_string())
+    /// Trade and execution history next to the state file (`<path>.history`), see [`FileHistoryLog::open`].
+    pub fn history_log(&self) -> Result<FileHistoryLog, String> {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".history");
+        FileHistoryLog::open(path, self.fsync)
     }
 
-    /// Load state from file. Returns None if file does not exist or is invalid.
+    /// FIX session file next to the state file (`<path>.fix-sessions`), see [`crate::fix::FixSessionStore::open`].
+    pub fn fix_session_path(&self) -> std::path::PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".fix-sessions");
+        path.into()
+    }
+
+    /// Check that the state file's directory accepts writes, by writing and removing `<path>.probe`.
+    pub fn check_writable(&self) -> Result<(), String> {
+        let mut probe = self.path.clone().into_os_string();
+        probe.push(".probe");
+        std::fs::write(&probe, b"ok").map_err(|e| e.to_string())?;
+        std::fs::remove_file(&probe).map_err(|e| e.to_string())
+    }
+
+    /// Load state from file. Returns None if the file does not exist, `Err` if it is unreadable or invalid.
     pub fn load(&self) -> Result<Option<PersistedState>, String> {
-        let data = match std::fs::read_to_string(&self.path) {
+        Ok(self.load_with_checksum()?.map(|(state, _)| state))
+    }
+
+    /// Like [`FilePersistence::load`], with the CRC32 of the file (as returned by [`FilePersistence::save`]).
+    pub fn load_with_checksum(&self) -> Result<Option<(PersistedState, u32)>, String> {
+        let data = match std::fs::read(&self.path) {
             Ok(d) => d,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
             Err(e) => return Err(e.to_string()),
         };
-        let state: PersistedState = serde_json::from_str(&data).map_err(|e| e.to_string())?;
-        Ok(Some(state))
+        let state = SnapshotFormat::decode(&data)?;
+        Ok(Some((state, crc32fast::hash(&data))))
+    }
+}
+
+/// How the state was recovered at startup (`GET /admin/recovery`): the snapshot loaded, then the command-log
+/// entries replayed on top of it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct RecoveryReport {
+    /// The file the snapshot came from: the state file, or the newest retained copy
+    /// ([`FilePersistence::archive`]) that loads when the state file does not. `None` when the engine started
+    /// fresh.
+    pub snapshot_path: Option<String>,
+    /// Last engine sequence number and command-log entry the snapshot covered.
+    pub snapshot_seq: u64,
+    pub snapshot_command_seq: u64,
+    /// Command-log entries applied on top of the snapshot.
+    pub replayed: usize,
+    /// Entries that failed again on replay (as they did when first applied).
+    pub skipped: usize,
+    /// Entries thrown away because they did not follow on from the snapshot, or no snapshot loaded.
+    pub discarded: usize,
+    /// State files that did not load, newest first, before the one that did (all of them when none did).
+    pub rejected_snapshots: Vec<RejectedSnapshot>,
+    /// Command-log records cut off when the log was opened (see [`FileCommandLog::dropped`]).
+    pub command_log_dropped: usize,
+    /// Trades and execution reports reloaded from the history log ([`FilePersistence::history_log`]).
+    pub history_records: usize,
+    /// History-log records skipped or cut off when the log was opened (see [`FileHistoryLog::dropped`]).
+    pub history_dropped: usize,
+    /// Last engine sequence number and command-log entry once recovered.
+    pub seq: u64,
+    pub command_seq: u64,
+    /// Time spent loading the snapshot and replaying the log.
+    pub load_ms: u64,
+    pub replay_ms: u64,
+}
+
+/// A state file recovery could not load, and why (e.g. a failed checksum, or JSON cut short).
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct RejectedSnapshot {
+    pub path: String,
+    pub error: String,
+}
+
+/// When the background snapshotter saves state (see [`crate::api::start_snapshotter`]). The default never runs
+/// it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotPolicy {
+    /// Save this long after the first change the state file does not have ([`PersistenceNotifier`]), so a
+    /// burst of changes is saved once.
+    pub debounce: Option<Duration>,
+    /// Save this often, if the engine sequence number moved since the last save.
+    pub interval: Option<Duration>,
+    /// Save once the engine sequence number moved this far (trades, reports, and book changes each count one).
+    pub every_events: Option<u64>,
+    /// Earlier snapshots to keep as `<path>.snapshot-<seq>` (0 keeps none).
+    pub retain: usize,
+}
+
+impl SnapshotPolicy {
+    /// Read the policy from `STATE_SAVE_DEBOUNCE_MS` (default [`DEFAULT_SAVE_DEBOUNCE`]),
+    /// `STATE_SNAPSHOT_INTERVAL_SECS`, `STATE_SNAPSHOT_EVERY_EVENTS`, and `STATE_SNAPSHOT_RETAIN`. Unset or
+    /// unparsable variables leave the other triggers off and keep no copies.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse().ok());
+        Self {
+            debounce: Some(var("STATE_SAVE_DEBOUNCE_MS").map_or(DEFAULT_SAVE_DEBOUNCE, Duration::from_millis)),
+            interval: var("STATE_SNAPSHOT_INTERVAL_SECS").filter(|&secs| secs > 0).map(Duration::from_secs),
+            every_events: var("STATE_SNAPSHOT_EVERY_EVENTS").filter(|&n| n > 0),
+            retain: std::env::var("STATE_SNAPSHOT_RETAIN").ok().and_then(|s| s.trim().parse().ok()).unwrap_or(0),
+        }
+    }
+
+    /// Whether any trigger is set.
+    pub fn is_enabled(&self) -> bool {
+        self.debounce.is_some() || self.interval.is_some() || self.every_events.is_some()
+    }
+}
+
+/// How long the snapshotter waits after a change before saving, unless `STATE_SAVE_DEBOUNCE_MS` says otherwise.
+pub const DEFAULT_SAVE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Whether the state has changed since it was last saved. Attached to the engine as an event sink, it is marked
+/// by every [`EngineEvent`], so changes from FIX sessions and engine timers count as well as REST ones. Admin
+/// changes are saved by their handlers instead, which clears it.
+#[derive(Debug, Default)]
+pub struct PersistenceNotifier {
+    /// When the first change since the last save was made; `None` when the state file is up to date.
+    dirty_since: Mutex<Option<Instant>>,
+    changed: Condvar,
+}
+
+impl PersistenceNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a change the state file does not have yet.
+    pub fn mark_dirty(&self) {
+        let mut dirty_since = self.dirty_since.lock().expect("lock");
+        if dirty_since.is_none() {
+            *dirty_since = Some(Instant::now());
+            self.changed.notify_all();
+        }
+    }
+
+    /// When the first unsaved change was made, if there is one.
+    pub fn dirty_since(&self) -> Option<Instant> {
+        *self.dirty_since.lock().expect("lock")
+    }
+
+    /// Like [`PersistenceNotifier::dirty_since`], waiting up to `timeout` for a change when there is none.
+    pub fn wait(&self, timeout: Duration) -> Option<Instant> {
+        let dirty_since = self.dirty_since.lock().expect("lock");
+        *self.changed.wait_timeout_while(dirty_since, timeout, |since| since.is_none()).expect("lock").0
+    }
+
+    /// Mark the state saved. Call before taking what is saved, so changes made meanwhile mark it dirty again.
+    pub fn clear(&self) {
+        *self.dirty_since.lock().expect("lock") = None;
+    }
+}
+
+impl EngineEventSink for PersistenceNotifier {
+    fn on_event(&self, _event: &EngineEvent) {
+        self.mark_dirty();
+    }
+}
+
+/// Write-ahead log of the engine's commands (see [`crate::MultiEngine::set_command_log`]). Entries
+/// are numbered from 1 by the engine; a snapshot records the last one it includes
+/// ([`EngineSnapshot::command_seq`]), and [`crate::MultiEngine::replay_command_log`] applies the rest.
+pub trait CommandLog: Send + Sync + std::fmt::Debug {
+    /// Append `entry`. Must be durable when it returns `Ok`; the engine refuses the command otherwise.
+    fn append(&self, entry: &JournalEntry) -> Result<(), String>;
+    /// Entries numbered above `seq`, oldest first.
+    fn entries_after(&self, seq: u64) -> Result<Vec<JournalEntry>, String>;
+    /// Drop the log once a saved state covers it: a no-op unless every entry is numbered `seq` or lower.
+    fn compact(&self, seq: u64) -> Result<(), String>;
+    /// Drop every entry, e.g. when the engine state is replaced by a restore.
+    fn clear(&self) -> Result<(), String>;
+}
+
+/// Keeps entries in memory (for tests).
+#[derive(Debug, Default)]
+pub struct InMemoryCommandLog {
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl InMemoryCommandLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.lock().expect("lock").clone()
+    }
+}
+
+impl CommandLog for InMemoryCommandLog {
+    fn append(&self, entry: &JournalEntry) -> Result<(), String> {
+        self.entries.lock().expect("lock").push(entry.clone());
+        Ok(())
+    }
+
+    fn entries_after(&self, seq: u64) -> Result<Vec<JournalEntry>, String> {
+        Ok(self.entries().into_iter().filter(|entry| entry.seq > seq).collect())
+    }
+
+    fn compact(&self, seq: u64) -> Result<(), String> {
+        let mut entries = self.entries.lock().expect("lock");
+        if entries.last().is_none_or(|last| last.seq <= seq) {
+            entries.clear();
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        self.entries.lock().expect("lock").clear();
+        Ok(())
+    }
+}
+
+/// Append `record` to `out` as a line of a command or history log: the CRC32 of its JSON as 8 hex digits, a
+/// space, and the JSON.
+pub(crate) fn write_record(out: &mut Vec<u8>, record: &impl serde::Serialize) -> Result<(), String> {
+    let json = serde_json::to_vec(record).map_err(|e| e.to_string())?;
+    out.extend_from_slice(format!("{:08x} ", crc32fast::hash(&json)).as_bytes());
+    out.extend_from_slice(&json);
+    out.push(b'\n');
+    Ok(())
+}
+
+/// Parse a line written by [`write_record`], without its newline. A line of bare JSON (logs written before
+/// records had checksums) is parsed unchecked.
+fn read_record<T: serde::de::DeserializeOwned>(line: &[u8]) -> Result<T, String> {
+    let json = if line.starts_with(b"{") {
+        line
+    } else {
+        let (checksum, json) = line.split_at_checked(9).ok_or("record too short")?;
+        let checksum = std::str::from_utf8(&checksum[..8]).ok().and_then(|c| u32::from_str_radix(c, 16).ok());
+        if checksum != Some(crc32fast::hash(json)) {
+            return Err("checksum mismatch".to_string());
+        }
+        json
+    };
+    serde_json::from_slice(json).map_err(|e| e.to_string())
+}
+
+/// What [`scan_log`] found in a log file.
+struct LogScan {
+    /// Length of the part of the file to keep.
+    end: u64,
+    /// Sequence number of the last record kept (0 when none).
+    last_seq: u64,
+    /// Complete records that fail their checksum or do not parse, and those cut off after them.
+    corrupt: usize,
+    /// Whether the file ends in a record cut short (no newline), e.g. by a crash mid-append.
+    torn: bool,
+}
+
+/// Read a log file from the start. With `skip_corrupt`, a corrupt record is kept in place and passed over;
+/// otherwise the file is only kept up to the first one.
+fn scan_log<T: serde::de::DeserializeOwned>(file: &File, seq: impl Fn(&T) -> u64, skip_corrupt: bool) -> Result<LogScan, String> {
+    let mut scan = LogScan { end: 0, last_seq: 0, corrupt: 0, torn: false };
+    let mut reader = std::io::BufReader::new(file);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let n = reader.read_until(b'\n', &mut line).map_err(|e| e.to_string())?;
+        let Some(record) = line.strip_suffix(b"\n") else {
+            scan.torn = n > 0;
+            break;
+        };
+        match read_record::<T>(record) {
+            Ok(record) if scan.corrupt == 0 || skip_corrupt => {
+                scan.end += n as u64;
+                scan.last_seq = seq(&record);
+            }
+            _ => {
+                scan.corrupt += 1;
+                if skip_corrupt {
+                    scan.end += n as u64;
+                }
+            }
+        }
+    }
+    Ok(scan)
+}
+
+/// Move what follows the first `end` bytes of the log at `path` to `<path>.corrupt` (appended, for inspection)
+/// and cut the file there.
+fn cut_log(file: &mut File, path: &Path, end: u64) -> Result<(), String> {
+    let mut rest = Vec::new();
+    file.seek(SeekFrom::Start(end)).map_err(|e| e.to_string())?;
+    file.read_to_end(&mut rest).map_err(|e| e.to_string())?;
+    let mut corrupt = path.to_path_buf().into_os_string();
+    corrupt.push(".corrupt");
+    let mut kept = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&corrupt)
+        .map_err(|e| format!("{}: {}", Path::new(&corrupt).display(), e))?;
+    kept.write_all(&rest).map_err(|e| e.to_string())?;
+    file.set_len(end).map_err(|e| e.to_string())
+}
+
+/// Append-only file of [`JournalEntry`] records, one per line with its checksum.
+#[derive(Debug)]
+pub struct FileCommandLog {
+    path: PathBuf,
+    fsync: bool,
+    /// The file, and the number of its last entry (0 when empty).
+    file: Mutex<(File, u64)>,
+    dropped: usize,
+}
+
+impl FileCommandLog {
+    /// Open (or create) the log at `path`. A last entry cut short by a crash is cut off: its command was never
+    /// applied, as the engine only goes on once the append returns. From an entry that fails its checksum on,
+    /// nothing is replayed (later commands may depend on it): the log is cut there and the rest moved to
+    /// `<path>.corrupt`. With `fsync`, every append is synced to disk before it returns.
+    pub fn open(path: impl AsRef<Path>, fsync: bool) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let scan = scan_log::<JournalEntry>(&file, |entry| entry.seq, false)?;
+        if scan.corrupt > 0 {
+            log::warn!(
+                "Command log {} has a corrupt entry after entry {}; moving it and the {} lines after it to {}.corrupt",
+                path.display(),
+                scan.last_seq,
+                scan.corrupt - 1 + scan.torn as usize,
+                path.display()
+            );
+            cut_log(&mut file, &path, scan.end)?;
+        } else if scan.torn {
+            log::warn!("Command log {} ends in a partial entry; dropping it", path.display());
+            file.set_len(scan.end).map_err(|e| e.to_string())?;
+        }
+        file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path,
+            fsync,
+            file: Mutex::new((file, scan.last_seq)),
+            dropped: scan.corrupt + scan.torn as usize,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entries cut off when the log was opened: a partial last entry, or a corrupt entry and every one after it.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Number of the last entry, 0 when the log is empty.
+    pub fn last_seq(&self) -> u64 {
+        self.file.lock().expect("lock").1
+    }
+}
+
+impl CommandLog for FileCommandLog {
+    fn append(&self, entry: &JournalEntry) -> Result<(), String> {
+        let mut line = Vec::new();
+        write_record(&mut line, entry)?;
+        let mut guard = self.file.lock().expect("lock");
+        let (file, last_seq) = &mut *guard;
+        file.write_all(&line).map_err(|e| e.to_string())?;
+        if self.fsync {
+            file.sync_data().map_err(|e| e.to_string())?;
+        }
+        *last_seq = entry.seq;
+        Ok(())
+    }
+
+    fn entries_after(&self, seq: u64) -> Result<Vec<JournalEntry>, String> {
+        let _guard = self.file.lock().expect("lock");
+        let file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).split(b'\n') {
+            let line = line.map_err(|e| e.to_string())?;
+            let entry: JournalEntry = read_record(&line).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+            if entry.seq > seq {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    fn compact(&self, seq: u64) -> Result<(), String> {
+        let guard = self.file.lock().expect("lock");
+        if guard.1 > seq {
+            return Ok(());
+        }
+        guard.0.set_len(0).map_err(|e| e.to_string())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        let mut guard = self.file.lock().expect("lock");
+        guard.0.set_len(0).map_err(|e| e.to_string())?;
+        guard.1 = 0;
+        Ok(())
+    }
+}
+
+/// Append-only record of every trade and execution report (see [`crate::MultiEngine::set_history_log`]). It is
+/// never compacted: it holds the activity since it was created, across restarts and restores, while the engine
+/// keeps only the most recent in memory.
+pub trait HistoryLog: Send + Sync + std::fmt::Debug {
+    /// Append `records`, in order.
+    fn append(&self, records: &[HistoryRecord]) -> Result<(), String>;
+    /// Every record, oldest first.
+    fn records(&self) -> Result<Vec<HistoryRecord>, String>;
+    /// Sequence number of the last record appended (0 when empty).
+    fn last_seq(&self) -> u64;
+}
+
+/// Keeps records in memory (for tests).
+#[derive(Debug, Default)]
+pub struct InMemoryHistoryLog {
+    records: Mutex<Vec<HistoryRecord>>,
+}
+
+impl InMemoryHistoryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl HistoryLog for InMemoryHistoryLog {
+    fn append(&self, records: &[HistoryRecord]) -> Result<(), String> {
+        self.records.lock().expect("lock").extend_from_slice(records);
+        Ok(())
+    }
+
+    fn records(&self) -> Result<Vec<HistoryRecord>, String> {
+        Ok(self.records.lock().expect("lock").clone())
+    }
+
+    fn last_seq(&self) -> u64 {
+        self.records.lock().expect("lock").last().map_or(0, HistoryRecord::seq)
+    }
+}
+
+/// Append-only file of [`HistoryRecord`]s, one per line with its checksum.
+#[derive(Debug)]
+pub struct FileHistoryLog {
+    path: PathBuf,
+    fsync: bool,
+    /// The file, and the sequence number of its last record (0 when empty).
+    file: Mutex<(File, u64)>,
+    dropped: usize,
+}
+
+impl FileHistoryLog {
+    /// Open (or create) the log at `path`. A last record cut short by a crash is cut off; replaying the command
+    /// log records it again. Records that fail their checksum are left in place and skipped, as the records
+    /// around them stand on their own. With `fsync`, every append is synced to disk before it returns.
+    pub fn open(path: impl AsRef<Path>, fsync: bool) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let scan = scan_log::<HistoryRecord>(&file, HistoryRecord::seq, true)?;
+        if scan.corrupt > 0 {
+            log::warn!("History log {} has {} corrupt records; skipping them", path.display(), scan.corrupt);
+        }
+        if scan.torn {
+            log::warn!("History log {} ends in a partial record; dropping it", path.display());
+            file.set_len(scan.end).map_err(|e| e.to_string())?;
+        }
+        file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path,
+            fsync,
+            file: Mutex::new((file, scan.last_seq)),
+            dropped: scan.corrupt + scan.torn as usize,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records passed over when the log was opened: corrupt ones, and a partial last one.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl HistoryLog for FileHistoryLog {
+    fn append(&self, records: &[HistoryRecord]) -> Result<(), String> {
+        let Some(last) = records.last() else { return Ok(()) };
+        let mut lines = Vec::new();
+        for record in records {
+            write_record(&mut lines, record)?;
+        }
+        let mut guard = self.file.lock().expect("lock");
+        let (file, last_seq) = &mut *guard;
+        file.write_all(&lines).map_err(|e| e.to_string())?;
+        if self.fsync {
+            file.sync_data().map_err(|e| e.to_string())?;
+        }
+        *last_seq = last.seq();
+        Ok(())
+    }
+
+    fn records(&self) -> Result<Vec<HistoryRecord>, String> {
+        let _guard = self.file.lock().expect("lock");
+        let file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut records = Vec::new();
+        for line in std::io::BufReader::new(file).split(b'\n') {
+            // Corrupt records were counted when the log was opened.
+            if let Ok(record) = read_record(&line.map_err(|e| e.to_string())?) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    fn last_seq(&self) -> u64 {
+        self.file.lock().expect("lock").1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::Command;
+    use crate::types::OrderId;
+
+    fn cancel(seq: u64) -> JournalEntry {
+        JournalEntry::new(seq, Command::Cancel { order_id: OrderId(seq) })
+    }
+
+    #[test]
+    fn file_command_log_reads_back_entries_drops_a_torn_tail_and_compacts() {
+        let path = std::env::temp_dir().join(format!("dire_wal_{}.wal", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = FileCommandLog::open(&path, true).unwrap();
+        (1..=3).for_each(|seq| log.append(&cancel(seq)).unwrap());
+        drop(log);
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":4,"timest"#).unwrap();
+        drop(file);
+
+        let log = FileCommandLog::open(&path, false).unwrap();
+        assert_eq!(log.last_seq(), 3);
+        log.append(&cancel(4)).unwrap();
+        let seqs = |entries: Vec<JournalEntry>| entries.iter().map(|entry| entry.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(log.entries_after(1).unwrap()), [2, 3, 4]);
+
+        // Entries past the saved state are kept until a later save covers them.
+        log.compact(3).unwrap();
+        assert_eq!(seqs(log.entries_after(0).unwrap()), [1, 2, 3, 4]);
+        log.compact(4).unwrap();
+        assert!(log.entries_after(0).unwrap().is_empty());
+        log.append(&cancel(5)).unwrap();
+        assert_eq!(seqs(FileCommandLog::open(&path, false).unwrap().entries_after(0).unwrap()), [5]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn log_records_that_fail_their_checksum_cut_the_command_log_and_are_skipped_in_the_history() {
+        use crate::execution::Trade;
+        use crate::types::{InstrumentId, Side, TradeId};
+        let dir = std::env::temp_dir().join(format!("dire_log_checksums_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Change a digit of record `n`'s JSON, leaving it parseable but not matching its checksum.
+        let corrupt = |path: &Path, n: usize| {
+            let data = std::fs::read_to_string(path).unwrap();
+            let mut lines: Vec<String> = data.lines().map(String::from).collect();
+            lines[n] = lines[n].replacen("\"seq\":", "\"seq\":9", 1);
+            std::fs::write(path, lines.join("\n") + "\n").unwrap();
+        };
+
+        // Entries before the corrupt one replay; it and those after are moved aside. Logs without checksums load.
+        let wal = dir.join("state.wal");
+        let log = FileCommandLog::open(&wal, false).unwrap();
+        (1..=4).for_each(|seq| log.append(&cancel(seq)).unwrap());
+        drop(log);
+        corrupt(&wal, 2);
+        let mut legacy = serde_json::to_vec(&cancel(0)).unwrap();
+        legacy.push(b'\n');
+        let mut data = std::fs::read(&wal).unwrap();
+        data.splice(0..0, legacy);
+        std::fs::write(&wal, data).unwrap();
+        let log = FileCommandLog::open(&wal, false).unwrap();
+        assert_eq!((log.last_seq(), log.dropped()), (2, 2));
+        let seqs: Vec<_> = log.entries_after(0).unwrap().iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, [1, 2]);
+        let moved = std::fs::read_to_string(dir.join("state.wal.corrupt")).unwrap();
+        assert_eq!(moved.lines().count(), 2);
+        assert!(moved.lines().nth(1).unwrap().contains("\"seq\":4"));
+        assert_eq!(FileCommandLog::open(&wal, false).unwrap().dropped(), 0);
+
+        let trade = |seq: u64| HistoryRecord::Trade {
+            trade: Trade {
+                trade_id: TradeId(seq),
+                instrument_id: InstrumentId(1),
+                buy_order_id: OrderId(1),
+                sell_order_id: OrderId(2),
+                price: rust_decimal::Decimal::from(100),
+                quantity: rust_decimal::Decimal::from(1),
+                timestamp: seq,
+                aggressor_side: Side::Buy,
+                seq,
+            },
+            buyer: None,
+            seller: None,
+        };
+        let path = dir.join("state.history");
+        let history = FileHistoryLog::open(&path, false).unwrap();
+        history.append(&(1..=3).map(trade).collect::<Vec<_>>()).unwrap();
+        drop(history);
+        corrupt(&path, 1);
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"0badf00d {\"ty").unwrap();
+        let history = FileHistoryLog::open(&path, false).unwrap();
+        assert_eq!((history.last_seq(), history.dropped()), (3, 2));
+        let seqs: Vec<_> = history.records().unwrap().iter().map(HistoryRecord::seq).collect();
+        assert_eq!(seqs, [1, 3]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saves_replace_the_file_whole_and_archive_keeps_the_newest_copies() {
+        let dir = std::env::temp_dir().join(format!("dire_archive_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let persistence = FilePersistence::new(dir.join("state.json")).with_fsync(true);
+        let mut state = PersistedState {
+            version: STATE_VERSION,
+            engine: crate::MultiEngine::new_with_instruments(vec![]).snapshot(),
+            market_state: "Open".into(),
+            venue_config: None,
+        };
+        for (seq, market_state) in [(3, "Open"), (10, "Halted"), (12, "Open")] {
+            state.market_state = market_state.into();
+            persistence.save(&state).unwrap();
+            persistence.archive(seq, 2).unwrap();
+        }
+        assert_eq!(persistence.load().unwrap().unwrap().market_state, "Open");
+        let snapshots = persistence.snapshots().unwrap();
+        assert_eq!(snapshots.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(), [10, 12]);
+        let copy = FilePersistence::new(&snapshots[0].1).load().unwrap().unwrap();
+        assert_eq!(copy.market_state, "Halted");
+
+        // No temp files are left behind.
+        let mut names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["state.json", "state.json.snapshot-10", "state.json.snapshot-12"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn binary_snapshots_round_trip_and_json_files_still_load() {
+        use crate::engine::MatchingEngine;
+        use crate::scheduler::TimedAction;
+        use crate::types::{InstrumentId, Order, OrderType, Side, TimeInForce, TraderId};
+        use rust_decimal::Decimal;
+        let order = |id: u64, side: Side, quantity: i64| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(quantity),
+            price: Some(Decimal::new(10025, 2)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let mut engine = crate::MultiEngine::new_with_instruments(vec![(InstrumentId(1), Some("AAPL".into()))]);
+        engine.submit_order(order(1, Side::Sell, 5)).unwrap();
+        engine.submit_order(order(2, Side::Buy, 2)).unwrap();
+        engine.schedule(50, TimedAction::Uncross { instrument_id: InstrumentId(1) }).unwrap();
+        let state = PersistedState {
+            version: STATE_VERSION,
+            engine: engine.snapshot(),
+            market_state: "Halted".into(),
+            venue_config: None,
+        };
+        let path = std::env::temp_dir().join(format!("dire_binary_state_{}", std::process::id()));
+        let as_json = |state: &PersistedState| serde_json::to_value(state).unwrap();
+
+        let persistence = FilePersistence::new(&path);
+        let checksum = persistence.save(&state).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(&data[..9], b"DIRESNAP\x01");
+        let (loaded, loaded_checksum) = persistence.load_with_checksum().unwrap().unwrap();
+        assert_eq!(as_json(&loaded), as_json(&state));
+        assert_eq!(loaded_checksum, checksum);
+
+        let json = FilePersistence::new(&path).with_format(SnapshotFormat::Json);
+        json.save(&state).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[0], b'{');
+        assert_eq!(as_json(&persistence.load().unwrap().unwrap()), as_json(&state));
+
+        // A flipped bit or a cut-short file fails to load rather than loading something else.
+        let mut flipped = data.clone();
+        let middle = flipped.len() / 2;
+        flipped[middle] ^= 0x10;
+        std::fs::write(&path, &flipped).unwrap();
+        assert!(persistence.load().is_err());
+        std::fs::write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(persistence.load().unwrap_err().starts_with("decompress"));
+
+        let mut future = data;
+        future[8] = 9;
+        std::fs::write(&path, &future).unwrap();
+        assert_eq!(persistence.load().unwrap_err(), "Unsupported snapshot format version 9");
+        let _ = std::fs::remove_file(&path);
     }
 }
@@ -0,0 +1,164 @@
+//! Per-trader, per-instrument positions netted from fills (foundation for risk limits).
+//!
+//! Each fill moves the trader's net quantity (long positive, short negative). Fills that add to a position
+//! update its volume-weighted average entry price; fills that reduce it realize P&L against that price.
+
+use crate::execution::Trade;
+use crate::types::{InstrumentId, Side, TraderId};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// A trader's position in one instrument, plus the quantity they still have working on the book.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Position {
+    pub trader_id: TraderId,
+    pub instrument_id: InstrumentId,
+    /// Net filled quantity: positive = long, negative = short.
+    pub net_quantity: Decimal,
+    /// Average price of the open position; `None` when flat.
+    pub avg_entry_price: Option<Decimal>,
+    /// P&L realized by fills that reduced or flipped the position.
+    pub realized_pnl: Decimal,
+    /// Resting buy quantity (exposure that could still add to the position).
+    pub open_buy_quantity: Decimal,
+    /// Resting sell quantity.
+    pub open_sell_quantity: Decimal,
+}
+
+impl Position {
+    /// Flat position with nothing working.
+    pub fn flat(trader_id: TraderId, instrument_id: InstrumentId) -> Self {
+        Self {
+            trader_id,
+            instrument_id,
+            net_quantity: Decimal::ZERO,
+            avg_entry_price: None,
+            realized_pnl: Decimal::ZERO,
+            open_buy_quantity: Decimal::ZERO,
+            open_sell_quantity: Decimal::ZERO,
+        }
+    }
+
+    /// Apply one fill of `quantity` at `price` on `side`.
+    fn apply_fill(&mut self, side: Side, price: Decimal, quantity: Decimal) {
+        let signed = match side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        };
+        let net = self.net_quantity;
+        let avg = self.avg_entry_price.unwrap_or(price);
+        if net.is_zero() || net.is_sign_positive() == signed.is_sign_positive() {
+            let size = net.abs() + quantity;
+            self.avg_entry_price = Some((net.abs() * avg + quantity * price) / size);
+        } else {
+            let closed = quantity.min(net.abs());
+            let direction = if net.is_sign_positive() { Decimal::ONE } else { -Decimal::ONE };
+            self.realized_pnl += closed * (price - avg) * direction;
+            if quantity > net.abs() {
+                self.avg_entry_price = Some(price);
+            } else if quantity == net.abs() {
+                self.avg_entry_price = None;
+            }
+        }
+        self.net_quantity = net + signed;
+    }
+}
+
+/// Positions for every (trader, instrument) that has traded.
+#[derive(Debug, Default)]
+pub struct PositionBook {
+    positions: HashMap<(TraderId, InstrumentId), Position>,
+}
+
+impl PositionBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Net one trade into the buyer's and the seller's positions.
+    pub fn apply_trade(&mut self, buyer: TraderId, seller: TraderId, trade: &Trade) {
+        for (trader_id, side) in [(buyer, Side::Buy), (seller, Side::Sell)] {
+            self.positions
+                .entry((trader_id, trade.instrument_id))
+                .or_insert_with(|| Position::flat(trader_id, trade.instrument_id))
+                .apply_fill(side, trade.price, trade.quantity);
+        }
+    }
+
+    /// Position of `trader_id` in `instrument_id` (flat if they have never traded it). Open quantities are zero;
+    /// the engine fills them in from the book.
+    pub fn get(&self, trader_id: TraderId, instrument_id: InstrumentId) -> Position {
+        self.positions
+            .get(&(trader_id, instrument_id))
+            .cloned()
+            .unwrap_or_else(|| Position::flat(trader_id, instrument_id))
+    }
+
+    /// Instruments `trader_id` has traded, in instrument id order.
+    pub fn instruments_for(&self, trader_id: TraderId) -> Vec<InstrumentId> {
+        let mut ids: Vec<InstrumentId> = self
+            .positions
+            .keys()
+            .filter(|(trader, _)| *trader == trader_id)
+            .map(|(_, instrument)| *instrument)
+            .collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderId, TradeId};
+
+    fn trade(price: i64, quantity: i64) -> Trade {
+        Trade {
+            trade_id: TradeId(1),
+            instrument_id: InstrumentId(1),
+            buy_order_id: OrderId(1),
+            sell_order_id: OrderId(2),
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            timestamp: 0,
+            aggressor_side: Side::Buy,
+            seq: 0,
+        }
+    }
+
+    #[test]
+    fn fills_net_into_average_price_and_realized_pnl() {
+        let (a, b) = (TraderId(1), TraderId(2));
+        let mut book = PositionBook::new();
+        book.apply_trade(a, b, &trade(100, 10));
+        book.apply_trade(a, b, &trade(110, 10));
+        let long = book.get(a, InstrumentId(1));
+        assert_eq!(long.net_quantity, Decimal::from(20));
+        assert_eq!(long.avg_entry_price, Some(Decimal::from(105)));
+        let short = book.get(b, InstrumentId(1));
+        assert_eq!(short.net_quantity, Decimal::from(-20));
+        assert_eq!(short.avg_entry_price, Some(Decimal::from(105)));
+
+        // A sells 5 at 120: realizes 5 * (120 - 105); average unchanged.
+        book.apply_trade(b, a, &trade(120, 5));
+        let a_pos = book.get(a, InstrumentId(1));
+        assert_eq!(a_pos.net_quantity, Decimal::from(15));
+        assert_eq!(a_pos.avg_entry_price, Some(Decimal::from(105)));
+        assert_eq!(a_pos.realized_pnl, Decimal::from(75));
+        assert_eq!(book.get(b, InstrumentId(1)).realized_pnl, Decimal::from(-75));
+
+        // A sells 20 at 100: closes 15 (realizing -75) and flips short 5 at 100.
+        book.apply_trade(b, a, &trade(100, 20));
+        let a_pos = book.get(a, InstrumentId(1));
+        assert_eq!(a_pos.net_quantity, Decimal::from(-5));
+        assert_eq!(a_pos.avg_entry_price, Some(Decimal::from(100)));
+        assert_eq!(a_pos.realized_pnl, Decimal::ZERO);
+
+        book.apply_trade(a, b, &trade(100, 5));
+        let a_pos = book.get(a, InstrumentId(1));
+        assert!(a_pos.net_quantity.is_zero());
+        assert_eq!(a_pos.avg_entry_price, None);
+        assert_eq!(book.get(TraderId(3), InstrumentId(1)), Position::flat(TraderId(3), InstrumentId(1)));
+        assert_eq!(book.instruments_for(a), vec![InstrumentId(1)]);
+    }
+}
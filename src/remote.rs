@@ -0,0 +1,264 @@
+//! Remote copies of the state file and command log in an object store, so a node whose disk is gone (a
+//! stateless container, a replaced host) recovers from remote storage rather than starting empty.
+//!
+//! A [`RemoteUploader`] (run by [`crate::api::start_remote_uploader`]) puts the state file under
+//! [`SNAPSHOT_KEY`] whenever it changes, and the command-log entries since under
+//! `journal/<first>-<last>` (see [`segment_key`]), deleting segments once an uploaded snapshot covers them.
+//! [`restore`] does the reverse on startup when there is no local state file: it writes the snapshot and the
+//! segments that follow it on from it next to the state file, where the usual recovery loads and replays them.
+//! What the remote copy lacks is at most one upload interval of order entry. One node writes to a store (or a
+//! prefix of one); the history log, id reservations, and FIX sessions are not copied.
+//!
+//! The store is an [`ObjectStore`]: [`InMemoryObjectStore`] for tests, or an S3-compatible bucket
+//! (`crate::s3::S3Store`, feature `s3`).
+
+use crate::journal::JournalEntry;
+use crate::persistence::{write_record, CommandLog, FilePersistence, SnapshotFormat};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Key of the latest state file.
+pub const SNAPSHOT_KEY: &str = "snapshot";
+
+/// Prefix of the command-log segment keys.
+pub const SEGMENT_PREFIX: &str = "journal/";
+
+/// How often the uploader runs unless `STATE_UPLOAD_INTERVAL_SECS` says otherwise.
+pub const DEFAULT_UPLOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A flat store of byte objects by key, such as an S3 bucket. Calls block until the store has answered.
+pub trait ObjectStore: Send + Sync + std::fmt::Debug {
+    /// Store `data` under `key`, replacing what was there.
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), String>;
+    /// The object under `key`, `None` when there is none.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+    /// Keys starting with `prefix`, sorted.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, String>;
+    /// Remove the object under `key`; removing a missing key succeeds.
+    fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+/// Keeps objects in memory (for tests).
+#[derive(Debug, Default)]
+pub struct InMemoryObjectStore {
+    objects: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+impl InMemoryObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ObjectStore for InMemoryObjectStore {
+    fn put(&self, key: &str, data: &[u8]) -> Result<(), String> {
+        self.objects.lock().expect("lock").insert(key.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.objects.lock().expect("lock").get(key).cloned())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>, String> {
+        let objects = self.objects.lock().expect("lock");
+        Ok(objects.keys().filter(|key| key.starts_with(prefix)).cloned().collect())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        self.objects.lock().expect("lock").remove(key);
+        Ok(())
+    }
+}
+
+/// Read the upload interval from `STATE_UPLOAD_INTERVAL_SECS`. Unset, zero, or unparsable values give
+/// [`DEFAULT_UPLOAD_INTERVAL`].
+pub fn upload_interval_from_env() -> Duration {
+    std::env::var("STATE_UPLOAD_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .filter(|&secs| secs > 0)
+        .map_or(DEFAULT_UPLOAD_INTERVAL, Duration::from_secs)
+}
+
+/// Key of the segment holding command-log entries `first` to `last`. Numbers are zero-padded so keys sort in
+/// log order.
+pub fn segment_key(first: u64, last: u64) -> String {
+    format!("{}{:020}-{:020}", SEGMENT_PREFIX, first, last)
+}
+
+/// The first and last entry of the segment under `key`, `None` if it is not a segment key.
+fn parse_segment_key(key: &str) -> Option<(u64, u64)> {
+    let (first, last) = key.strip_prefix(SEGMENT_PREFIX)?.split_once('-')?;
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
+
+/// Segments in `store`, with their first and last entry, in log order.
+fn segments(store: &dyn ObjectStore) -> Result<Vec<(u64, u64, String)>, String> {
+    let mut segments: Vec<_> = store
+        .list(SEGMENT_PREFIX)?
+        .into_iter()
+        .filter_map(|key| parse_segment_key(&key).map(|(first, last)| (first, last, key)))
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+/// What one [`RemoteUploader::upload`] put in the store.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Upload {
+    /// Whether the state file had changed and was uploaded.
+    pub snapshot: bool,
+    /// Command-log entries uploaded as a new segment.
+    pub entries: usize,
+}
+
+/// Copies a node's state file and command log to an [`ObjectStore`], uploading only what changed since its last
+/// upload.
+#[derive(Debug)]
+pub struct RemoteUploader {
+    store: std::sync::Arc<dyn ObjectStore>,
+    /// CRC32 of the state file last uploaded.
+    snapshot_checksum: Option<u32>,
+    /// Last command-log entry in that state file.
+    snapshot_command_seq: u64,
+    /// Last command-log entry in the store, in the snapshot or a segment.
+    uploaded_through: u64,
+    /// Segments this uploader put that no uploaded snapshot covers yet (first and last entry). Other segments in
+    /// the store are left from an earlier run, and are deleted with the first snapshot.
+    segments: Vec<(u64, u64)>,
+}
+
+impl RemoteUploader {
+    pub fn new(store: std::sync::Arc<dyn ObjectStore>) -> Self {
+        Self {
+            store,
+            snapshot_checksum: None,
+            snapshot_command_seq: 0,
+            uploaded_through: 0,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Upload the state file of `persistence` if it changed, then the entries of `command_log` the store does
+    /// not have. Segments the snapshot covers are deleted after it is uploaded; segments it does not follow on
+    /// from (an earlier run's, or entries abandoned by a restore) before, so the store never pairs a snapshot
+    /// with entries from another history.
+    pub fn upload(&mut self, persistence: &FilePersistence, command_log: Option<&dyn CommandLog>) -> Result<Upload, String> {
+        // Entries are read before the state file: a save between the two then only makes the snapshot newer,
+        // where the other way round compaction could drop entries that neither has.
+        let entries = match command_log {
+            Some(log) => log.entries_after(self.uploaded_through)?,
+            None => Vec::new(),
+        };
+        let mut upload = Upload::default();
+        let data = match std::fs::read(persistence.path()) {
+            Ok(data) => Some(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("{}: {}", persistence.path().display(), e)),
+        };
+        let mut rewound = false;
+        if let Some(data) = data.filter(|data| Some(crc32fast::hash(data)) != self.snapshot_checksum) {
+            let command_seq = SnapshotFormat::decode(&data)?.engine.command_seq;
+            // A snapshot behind the last one uploaded means the engine was restored: start the log over.
+            rewound = self.snapshot_checksum.is_some() && command_seq < self.snapshot_command_seq;
+            if rewound {
+                self.segments.clear();
+                self.uploaded_through = command_seq;
+            }
+            let existing = segments(self.store.as_ref())?;
+            for (first, last, key) in &existing {
+                if *last > command_seq && !self.segments.contains(&(*first, *last)) {
+                    self.store.delete(key)?;
+                }
+            }
+            self.store.put(SNAPSHOT_KEY, &data)?;
+            for (_, last, key) in existing.iter().filter(|(_, last, _)| *last <= command_seq) {
+                self.store.delete(key)?;
+                self.segments.retain(|&(_, kept)| kept != *last);
+            }
+            self.snapshot_checksum = Some(crc32fast::hash(&data));
+            self.snapshot_command_seq = command_seq;
+            self.uploaded_through = self.uploaded_through.max(command_seq);
+            upload.snapshot = true;
+        }
+        // After a restore the entries were read from too late an entry; the next upload reads them again.
+        let entries: Vec<JournalEntry> = if rewound {
+            Vec::new()
+        } else {
+            entries.into_iter().filter(|entry| entry.seq > self.uploaded_through).collect()
+        };
+        let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+            return Ok(upload);
+        };
+        if first.seq != self.uploaded_through + 1 {
+            // Compacted before the store had them; the next snapshot covers them.
+            return Ok(upload);
+        }
+        let (first, last) = (first.seq, last.seq);
+        let mut data = Vec::new();
+        for entry in &entries {
+            write_record(&mut data, entry)?;
+        }
+        self.store.put(&segment_key(first, last), &data)?;
+        self.segments.push((first, last));
+        self.uploaded_through = last;
+        upload.entries = entries.len();
+        Ok(upload)
+    }
+}
+
+/// What [`restore`] wrote.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct RemoteRestore {
+    /// Last command-log entry in the restored snapshot.
+    pub snapshot_command_seq: u64,
+    /// Segments written to the command log, and the entries in them.
+    pub segments: usize,
+    pub entries: usize,
+    /// Segments left out because one before them is missing.
+    pub skipped_segments: usize,
+}
+
+/// Copy the snapshot in `store` to the state file of `persistence`, and the segments that follow on from it to
+/// its command log, when there is no state file yet: a local state file is at least as new as the store. Returns
+/// `None` when nothing was restored (a state file exists, or the store has no snapshot), `Err` if the store
+/// cannot be read or its snapshot does not decode. Call it before the state is loaded.
+pub fn restore(store: &dyn ObjectStore, persistence: &FilePersistence) -> Result<Option<RemoteRestore>, String> {
+    if persistence.path().exists() {
+        return Ok(None);
+    }
+    let Some(snapshot) = store.get(SNAPSHOT_KEY)? else {
+        return Ok(None);
+    };
+    let command_seq = SnapshotFormat::decode(&snapshot).map_err(|e| format!("remote snapshot: {}", e))?.engine.command_seq;
+    let mut restored = RemoteRestore { snapshot_command_seq: command_seq, ..RemoteRestore::default() };
+    let mut log = Vec::new();
+    let mut next = command_seq + 1;
+    let following: Vec<_> = segments(store)?.into_iter().filter(|(_, last, _)| *last >= next).collect();
+    for (i, (first, last, key)) in following.iter().enumerate() {
+        let data = match store.get(key)? {
+            Some(data) if *first <= next => data,
+            _ => {
+                log::warn!("Remote command log has no segment from entry {}; leaving out {} from there on", next, key);
+                restored.skipped_segments = following.len() - i;
+                break;
+            }
+        };
+        restored.entries += data.iter().filter(|&&b| b == b'\n').count();
+        restored.segments += 1;
+        log.extend_from_slice(&data);
+        next = last + 1;
+    }
+    // The command log first: until the state file is in place, a restart restores again.
+    let mut wal = persistence.path().to_path_buf().into_os_string();
+    wal.push(".wal");
+    std::fs::write(&wal, &log).map_err(|e| format!("{}: {}", std::path::Path::new(&wal).display(), e))?;
+    let mut tmp = persistence.path().to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, &snapshot).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, persistence.path()).map_err(|e| e.to_string())?;
+    log::info!("Restored from remote storage: {:?}", restored);
+    Ok(Some(restored))
+}
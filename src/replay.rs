@@ -0,0 +1,747 @@
+//! Async replay of generated orders and events (feature `replay`) into a running server, over its REST API
+//! ([`replay_via_http`]) or a FIX session ([`replay_via_fix`]), for demos and soak tests.
+//!
+//! Unlike [`crate::replay_into_engine_with_delay`], which sleeps a thread between calls into an in-process
+//! engine, sends are scheduled against the clock: each event goes out when its [`Pace`] makes it due, measured
+//! from the start, so time spent waiting on the server does not add up to drift. Up to
+//! [`ReplayConfig::max_in_flight`] requests are outstanding at once, so the pace does not depend on the server's
+//! latency; a cancel or modify still waits until the server has answered the order's earlier request, so it
+//! cannot overtake it. The [`ReplayReport`] counts what the server accepted and refused, how far the replay fell
+//! behind its pace, and how long answers took.
+
+use crate::fix::initiator::{framed, logon_fields, session_message};
+use crate::fix::{
+    execution_report_from_fix, new_order_single_to_fix, order_cancel_replace_request_to_fix, order_cancel_request_to_fix,
+    FixDecoder, FixInitiatorConfig, FixMessage,
+};
+use crate::market_data_gen::{GenEvent, ARRIVAL_CLOCK_TICKS_PER_SEC};
+use crate::types::{ExecType, OrderId};
+use log::warn;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// How long one HTTP request may take before it counts as failed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// When a replay sends each event.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Pace {
+    /// Back to back, as fast as the in-flight limit allows.
+    #[default]
+    Unlimited,
+    /// This many events per second, evenly spaced. Not positive, unlimited.
+    Rate(f64),
+    /// At the events' timestamps, read as [`ARRIVAL_CLOCK_TICKS_PER_SEC`] per second from the first event's (as
+    /// generated with [`crate::GeneratorConfig::arrival_rate`]), `speed` times faster than real time. Not
+    /// positive, unlimited.
+    ArrivalTimes { speed: f64 },
+}
+
+/// How a replay sends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayConfig {
+    pub pace: Pace,
+    /// Requests sent and not yet answered, at most (at least 1). With 1 the server sees the events in stream
+    /// order, and the book it ends with is the one [`crate::replay_into_engine`] builds; with more, new orders
+    /// can overtake each other.
+    pub max_in_flight: usize,
+    /// Added to every order id, so a stream replayed again, or into a server that already has orders, does not
+    /// reuse ids.
+    pub order_id_offset: u64,
+}
+
+impl Default for ReplayConfig {
+    /// Unlimited pace, 64 in flight, ids as generated.
+    fn default() -> Self {
+        Self { pace: Pace::Unlimited, max_in_flight: 64, order_id_offset: 0 }
+    }
+}
+
+/// What a replay did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// Events sent.
+    pub sent: usize,
+    /// Requests the server accepted.
+    pub accepted: usize,
+    /// Requests the server refused: rejected orders, and cancels or modifies of orders no longer open (the
+    /// generator does not know which of its orders have filled).
+    pub rejected: usize,
+    /// Requests without an answer: connection errors and timeouts.
+    pub failed: usize,
+    /// The first rejection reason or failure.
+    pub first_error: Option<String>,
+    /// From the start to the last answer.
+    pub elapsed: Duration,
+    /// The most any send was late against the pace. Much above a millisecond, the server or the in-flight
+    /// limit could not keep up with it.
+    pub max_lag: Duration,
+    /// Time from sending a request to its answer: median, 99th percentile, and maximum.
+    pub latency_p50: Duration,
+    pub latency_p99: Duration,
+    pub latency_max: Duration,
+}
+
+impl ReplayReport {
+    /// Events sent per second.
+    pub fn rate(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            0.0
+        } else {
+            self.sent as f64 / self.elapsed.as_secs_f64()
+        }
+    }
+}
+
+/// Replay `events` into the server at `base_url` (e.g. `http://127.0.0.1:8080`) through `POST /orders`,
+/// `/orders/cancel`, and `/orders/modify`, with `api_key` as `X-API-Key` when set. The key must be allowed to
+/// enter orders for the events' traders. `Err` only when no HTTP client can be built.
+pub async fn replay_via_http<T>(
+    base_url: &str,
+    api_key: Option<&str>,
+    events: impl IntoIterator<Item = T>,
+    config: &ReplayConfig,
+) -> Result<ReplayReport, String>
+where
+    T: Into<GenEvent>,
+{
+    let client = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().map_err(|e| e.to_string())?;
+    let tally = Arc::new(Mutex::new(Tally::default()));
+    let mut target = HttpTarget {
+        client,
+        base_url: base_url.trim_end_matches('/').to_string(),
+        api_key: api_key.map(str::to_string),
+        tally: tally.clone(),
+    };
+    let in_flight = Arc::new(Semaphore::new(in_flight_limit(config)));
+    let run = replay(&mut target, events, config, &in_flight, &tally, REQUEST_TIMEOUT).await;
+    let report = tally.lock().expect("lock").report(&run);
+    Ok(report)
+}
+
+/// Replay `events` over a FIX session to the acceptor at `addr`, logged on as `session` says, for the
+/// instrument `symbol`: NewOrderSingle (D), OrderCancelRequest (F), and OrderCancelReplaceRequest (G). The
+/// acceptor takes a NewOrderSingle's numeric ClOrdID as the order id, so orders go in under their ids; a
+/// replaced order goes on under the ClOrdID of its replacement. Requests still unanswered
+/// [`FixInitiatorConfig::read_timeout`] after the last send count as failed; the session then logs out. `Err`
+/// when the connection or the Logon fails.
+pub async fn replay_via_fix<T>(
+    addr: &str,
+    session: FixInitiatorConfig,
+    symbol: &str,
+    events: impl IntoIterator<Item = T>,
+    config: &ReplayConfig,
+) -> Result<ReplayReport, String>
+where
+    T: Into<GenEvent>,
+{
+    let read_timeout = session.read_timeout;
+    let mut stream = TcpStream::connect(addr).await.map_err(|e| e.to_string())?;
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    let logon = framed(&session, session_message(&session, "A", 1, &logon_fields(&session)));
+    stream.write_all(&logon).await.map_err(|e| e.to_string())?;
+    let mut decoder = FixDecoder::new();
+    let reply = tokio::time::timeout(read_timeout, next_message(&mut stream, &mut decoder))
+        .await
+        .map_err(|_| format!("no Logon reply for {}s", read_timeout.as_secs()))??;
+    match reply.get(&35).map(String::as_str) {
+        Some("A") => {}
+        Some("5") => return Err(format!("logon refused: {}", reply.get(&58).map_or("", String::as_str))),
+        other => return Err(format!("expected Logon (A), got MsgType {}", other.unwrap_or("?"))),
+    }
+
+    let (read_half, write_half) = stream.into_split();
+    let heartbeat_interval = session.heartbeat_interval;
+    let out = Arc::new(tokio::sync::Mutex::new(FixOut {
+        half: write_half,
+        config: session,
+        next_seq: 2,
+        last_sent: Instant::now(),
+        logging_out: false,
+    }));
+    let tally = Arc::new(Mutex::new(Tally::default()));
+    let pending = Arc::new(Mutex::new(Pending::default()));
+    let in_flight = Arc::new(Semaphore::new(in_flight_limit(config)));
+    let reader = FixReader {
+        half: read_half,
+        decoder,
+        out: out.clone(),
+        pending: pending.clone(),
+        tally: tally.clone(),
+        heartbeat_interval,
+    };
+    let reading = tokio::spawn({
+        let in_flight = in_flight.clone();
+        let tally = tally.clone();
+        async move {
+            if let Err(e) = reader.run().await {
+                tally.lock().expect("lock").first_error.get_or_insert(e);
+            }
+            // Nothing more will be answered: stop sending, and stop waiting for answers.
+            in_flight.close();
+        }
+    });
+    let mut target = FixTarget { out: out.clone(), symbol: symbol.to_string(), pending: pending.clone() };
+    let run = replay(&mut target, events, config, &in_flight, &tally, read_timeout).await;
+
+    let unanswered: Vec<_> = pending.lock().expect("lock").by_cl_ord_id.drain().collect();
+    for _ in &unanswered {
+        tally.lock().expect("lock").fail(format!("no answer within {}s", read_timeout.as_secs()));
+    }
+    drop(unanswered);
+    {
+        let mut out = out.lock().await;
+        out.logging_out = true;
+        let _ = out.send_session("5", &[]).await;
+    }
+    if tokio::time::timeout(read_timeout, reading).await.is_err() {
+        warn!("FIX replay: no Logout reply within {}s", read_timeout.as_secs());
+    }
+    let report = tally.lock().expect("lock").report(&run);
+    Ok(report)
+}
+
+/// The in-flight limit of `config`, as permits.
+fn in_flight_limit(config: &ReplayConfig) -> usize {
+    config.max_in_flight.clamp(1, u32::MAX as usize)
+}
+
+/// Where [`replay`] sends events.
+trait Target {
+    /// Send `event`. `hold` is to be dropped once the server has answered (or the request has failed), and
+    /// after its outcome is in the tally. `Err` when nothing more can be sent.
+    async fn send(&mut self, event: GenEvent, hold: Hold) -> Result<(), String>;
+}
+
+/// Kept while a request is in flight: its order's lock, and one in-flight permit.
+struct Hold {
+    sent: Instant,
+    _order: OwnedMutexGuard<()>,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// What [`replay`] measured itself.
+struct Run {
+    sent: usize,
+    max_lag: Duration,
+    start: Instant,
+}
+
+/// Send `events` to `target` at the pace of `config`, then wait up to `drain_timeout` for the outstanding
+/// answers. Every request holds one of `in_flight`'s permits until it is answered; closing it stops the replay.
+async fn replay<T: Into<GenEvent>>(
+    target: &mut impl Target,
+    events: impl IntoIterator<Item = T>,
+    config: &ReplayConfig,
+    in_flight: &Arc<Semaphore>,
+    tally: &Mutex<Tally>,
+    drain_timeout: Duration,
+) -> Run {
+    let mut pacer = Pacer::new(config.pace);
+    let mut locks = OrderLocks::default();
+    let mut run = Run { sent: 0, max_lag: Duration::ZERO, start: pacer.start };
+    for event in events {
+        let event = with_id_offset(event.into(), config.order_id_offset);
+        let due = pacer.wait(&event).await;
+        let order = locks.acquire(order_id(&event)).await;
+        let Ok(permit) = in_flight.clone().acquire_owned().await else {
+            break;
+        };
+        if let Some(due) = due {
+            run.max_lag = run.max_lag.max(Instant::now().saturating_duration_since(due));
+        }
+        run.sent += 1;
+        let hold = Hold { sent: Instant::now(), _order: order, _permit: permit };
+        if let Err(e) = target.send(event, hold).await {
+            tally.lock().expect("lock").fail(e);
+            break;
+        }
+    }
+    let all = in_flight_limit(config) as u32;
+    let _ = tokio::time::timeout(drain_timeout, in_flight.acquire_many(all)).await;
+    run
+}
+
+/// When each event is due, from the start of the replay.
+struct Pacer {
+    pace: Pace,
+    start: Instant,
+    count: u64,
+    first_timestamp: Option<u64>,
+}
+
+impl Pacer {
+    fn new(pace: Pace) -> Self {
+        Self { pace, start: Instant::now(), count: 0, first_timestamp: None }
+    }
+
+    /// How long after the start the next event, `event`, is due; `None` when it is not held back.
+    fn offset(&mut self, event: &GenEvent) -> Option<Duration> {
+        let n = self.count;
+        self.count += 1;
+        let secs = match self.pace {
+            Pace::Rate(rate) if rate > 0.0 => n as f64 / rate,
+            Pace::ArrivalTimes { speed } if speed > 0.0 => {
+                let first = *self.first_timestamp.get_or_insert(event.timestamp());
+                event.timestamp().saturating_sub(first) as f64 / ARRIVAL_CLOCK_TICKS_PER_SEC as f64 / speed
+            }
+            _ => return None,
+        };
+        Duration::try_from_secs_f64(secs).ok()
+    }
+
+    /// Wait until `event` is due, and return when that was.
+    async fn wait(&mut self, event: &GenEvent) -> Option<Instant> {
+        let due = self.start + self.offset(event)?;
+        tokio::time::sleep_until(due).await;
+        Some(due)
+    }
+}
+
+/// A lock per order, held while a request for it is in flight.
+#[derive(Default)]
+struct OrderLocks {
+    locks: HashMap<OrderId, Arc<tokio::sync::Mutex<()>>>,
+    prune_at: usize,
+}
+
+impl OrderLocks {
+    /// Wait until `order_id` has no request in flight, and lock it.
+    async fn acquire(&mut self, order_id: OrderId) -> OwnedMutexGuard<()> {
+        if self.locks.len() >= self.prune_at {
+            // A lock nobody holds is as good as a new one.
+            self.locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            self.prune_at = (2 * self.locks.len()).max(1024);
+        }
+        self.locks.entry(order_id).or_default().clone().lock_owned().await
+    }
+}
+
+fn order_id(event: &GenEvent) -> OrderId {
+    match event {
+        GenEvent::Submit(order) => order.order_id,
+        GenEvent::Cancel { order_id, .. } | GenEvent::Modify { order_id, .. } => *order_id,
+    }
+}
+
+fn with_id_offset(event: GenEvent, offset: u64) -> GenEvent {
+    let shift = |id: OrderId| OrderId(id.0 + offset);
+    match event {
+        GenEvent::Submit(mut order) => {
+            order.order_id = shift(order.order_id);
+            GenEvent::Submit(order)
+        }
+        GenEvent::Cancel { order_id, timestamp } => GenEvent::Cancel { order_id: shift(order_id), timestamp },
+        GenEvent::Modify { order_id, mut replacement } => {
+            replacement.order_id = shift(replacement.order_id);
+            GenEvent::Modify { order_id: shift(order_id), replacement }
+        }
+    }
+}
+
+/// Outcomes so far.
+#[derive(Default)]
+struct Tally {
+    accepted: usize,
+    rejected: usize,
+    failed: usize,
+    first_error: Option<String>,
+    latencies: Vec<Duration>,
+}
+
+impl Tally {
+    /// The server answered the request sent at `sent`, accepting it or refusing it with a reason.
+    fn answer(&mut self, sent: Instant, outcome: Result<(), String>) {
+        self.latencies.push(sent.elapsed());
+        match outcome {
+            Ok(()) => self.accepted += 1,
+            Err(reason) => {
+                self.rejected += 1;
+                self.first_error.get_or_insert(reason);
+            }
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        self.failed += 1;
+        self.first_error.get_or_insert(error);
+    }
+
+    fn report(&mut self, run: &Run) -> ReplayReport {
+        self.latencies.sort();
+        let percentile = |p: f64| match self.latencies.len() {
+            0 => Duration::ZERO,
+            n => self.latencies[((n - 1) as f64 * p).round() as usize],
+        };
+        ReplayReport {
+            sent: run.sent,
+            accepted: self.accepted,
+            rejected: self.rejected,
+            failed: self.failed,
+            first_error: self.first_error.clone(),
+            elapsed: run.start.elapsed(),
+            max_lag: run.max_lag,
+            latency_p50: percentile(0.5),
+            latency_p99: percentile(0.99),
+            latency_max: percentile(1.0),
+        }
+    }
+}
+
+struct HttpTarget {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    tally: Arc<Mutex<Tally>>,
+}
+
+impl Target for HttpTarget {
+    /// Start the request; a task waits for the answer.
+    async fn send(&mut self, event: GenEvent, hold: Hold) -> Result<(), String> {
+        let is_cancel = matches!(event, GenEvent::Cancel { .. });
+        let (path, body) = match &event {
+            GenEvent::Submit(order) => ("orders", serde_json::to_vec(order)),
+            GenEvent::Cancel { order_id, .. } => ("orders/cancel", serde_json::to_vec(&serde_json::json!({ "order_id": order_id.0 }))),
+            GenEvent::Modify { order_id, replacement } => (
+                "orders/modify",
+                serde_json::to_vec(&serde_json::json!({ "order_id": order_id.0, "replacement": replacement })),
+            ),
+        };
+        let mut request = self
+            .client
+            .post(format!("{}/{}", self.base_url, path))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.map_err(|e| e.to_string())?);
+        if let Some(key) = &self.api_key {
+            request = request.header("X-API-Key", key);
+        }
+        let tally = self.tally.clone();
+        tokio::spawn(async move {
+            let answer = http_answer(request, is_cancel).await;
+            let mut tally = tally.lock().expect("lock");
+            match answer {
+                Ok(outcome) => tally.answer(hold.sent, outcome),
+                Err(e) => tally.fail(e),
+            }
+            drop(tally);
+            drop(hold);
+        });
+        Ok(())
+    }
+}
+
+/// The server's answer to `request`: `Ok(Err(reason))` when it refused it, `Err` when there was none. A cancel
+/// answered `canceled: false` was refused: the order was not open.
+async fn http_answer(request: reqwest::RequestBuilder, is_cancel: bool) -> Result<Result<(), String>, String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body = response.bytes().await.map_err(|e| e.to_string())?;
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+    if !status.is_success() {
+        let message = body["message"].as_str().unwrap_or_else(|| status.canonical_reason().unwrap_or_default());
+        return Ok(Err(format!("{} {}", status.as_u16(), message)));
+    }
+    if is_cancel && body["canceled"] == false {
+        return Ok(Err("order not open".into()));
+    }
+    Ok(Ok(()))
+}
+
+/// The sending side of a FIX replay session.
+struct FixOut {
+    half: OwnedWriteHalf,
+    config: FixInitiatorConfig,
+    next_seq: u32,
+    last_sent: Instant,
+    /// We sent Logout, so the acceptor's is the reply.
+    logging_out: bool,
+}
+
+impl FixOut {
+    /// Write the message `build` makes for the next MsgSeqNum.
+    async fn send(&mut self, build: impl FnOnce(u32, &FixInitiatorConfig) -> Vec<u8>) -> Result<(), String> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.write(build(seq, &self.config)).await
+    }
+
+    async fn send_session(&mut self, msg_type: &str, fields: &[(u32, String)]) -> Result<(), String> {
+        self.send(|seq, config| session_message(config, msg_type, seq, fields)).await
+    }
+
+    /// Write `out`, framed for the session's version.
+    async fn write(&mut self, out: Vec<u8>) -> Result<(), String> {
+        let out = framed(&self.config, out);
+        self.half.write_all(&out).await.map_err(|e| e.to_string())?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+/// What a FIX request asked for, which decides the reports that answer it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RequestKind {
+    New,
+    Cancel,
+    Replace,
+}
+
+struct Request {
+    kind: RequestKind,
+    seq: u32,
+    order_id: OrderId,
+    hold: Hold,
+}
+
+/// FIX requests in flight, by the ClOrdID their answer carries and by MsgSeqNum (the RefSeqNum of a reject).
+#[derive(Default)]
+struct Pending {
+    by_cl_ord_id: HashMap<String, Request>,
+    by_seq: HashMap<u32, String>,
+    /// ClOrdIDs of orders replaced under a new one; other orders go by their order id.
+    replaced: HashMap<OrderId, String>,
+}
+
+impl Pending {
+    /// The ClOrdID `order_id` is known by at the acceptor.
+    fn cl_ord_id(&self, order_id: OrderId) -> String {
+        self.replaced.get(&order_id).cloned().unwrap_or_else(|| order_id.0.to_string())
+    }
+
+    fn insert(&mut self, cl_ord_id: String, request: Request) {
+        self.by_seq.insert(request.seq, cl_ord_id.clone());
+        self.by_cl_ord_id.insert(cl_ord_id, request);
+    }
+
+    /// The request `cl_ord_id` answers, if it is of a kind `answers` accepts.
+    fn take(&mut self, cl_ord_id: &str, answers: impl Fn(RequestKind) -> bool) -> Option<(String, Request)> {
+        if !self.by_cl_ord_id.get(cl_ord_id).is_some_and(|request| answers(request.kind)) {
+            return None;
+        }
+        let request = self.by_cl_ord_id.remove(cl_ord_id)?;
+        self.by_seq.remove(&request.seq);
+        Some((cl_ord_id.to_string(), request))
+    }
+
+    fn take_seq(&mut self, seq: u32) -> Option<(String, Request)> {
+        let cl_ord_id = self.by_seq.get(&seq)?.clone();
+        self.take(&cl_ord_id, |_| true)
+    }
+}
+
+struct FixTarget {
+    out: Arc<tokio::sync::Mutex<FixOut>>,
+    symbol: String,
+    pending: Arc<Mutex<Pending>>,
+}
+
+impl Target for FixTarget {
+    /// Write the request; the session's reader matches the answer to it.
+    async fn send(&mut self, event: GenEvent, hold: Hold) -> Result<(), String> {
+        let mut out = self.out.lock().await;
+        let seq = out.next_seq;
+        let order_id = order_id(&event);
+        let (orig_cl_ord_id, cl_ord_id) = {
+            let mut pending = self.pending.lock().expect("lock");
+            let orig_cl_ord_id = pending.cl_ord_id(order_id);
+            // A cancel is answered under the order's ClOrdID, new orders and replacements under their own.
+            let (kind, cl_ord_id) = match &event {
+                GenEvent::Submit(_) => (RequestKind::New, order_id.0.to_string()),
+                GenEvent::Cancel { .. } => {
+                    pending.replaced.remove(&order_id);
+                    (RequestKind::Cancel, orig_cl_ord_id.clone())
+                }
+                GenEvent::Modify { .. } => (RequestKind::Replace, format!("{}-{}", order_id.0, seq)),
+            };
+            pending.insert(cl_ord_id.clone(), Request { kind, seq, order_id, hold });
+            (orig_cl_ord_id, cl_ord_id)
+        };
+        let symbol = self.symbol.as_str();
+        let sent = out
+            .send(|seq, config| {
+                let (sender, target) = (config.sender_comp_id.as_str(), config.target_comp_id.as_str());
+                match &event {
+                    GenEvent::Submit(order) => {
+                        let order = crate::types::Order { client_order_id: cl_ord_id.clone(), ..order.clone() };
+                        new_order_single_to_fix(&order, symbol, seq, sender, target)
+                    }
+                    GenEvent::Cancel { order_id, .. } => {
+                        let request_id = format!("{}-{}", order_id.0, seq);
+                        order_cancel_request_to_fix(&orig_cl_ord_id, &request_id, symbol, seq, sender, target)
+                    }
+                    GenEvent::Modify { replacement, .. } => {
+                        order_cancel_replace_request_to_fix(replacement, &orig_cl_ord_id, &cl_ord_id, symbol, seq, sender, target)
+                    }
+                }
+            })
+            .await;
+        if sent.is_err() {
+            self.pending.lock().expect("lock").take_seq(seq);
+        }
+        sent
+    }
+}
+
+/// The receiving side of a FIX replay session: answers session-level messages and matches ExecutionReports and
+/// rejects to the requests in flight.
+struct FixReader {
+    half: OwnedReadHalf,
+    decoder: FixDecoder,
+    out: Arc<tokio::sync::Mutex<FixOut>>,
+    pending: Arc<Mutex<Pending>>,
+    tally: Arc<Mutex<Tally>>,
+    heartbeat_interval: Duration,
+}
+
+impl FixReader {
+    /// Read until the session ends: `Ok` on Logout, `Err` when the connection fails.
+    async fn run(mut self) -> Result<(), String> {
+        let mut chunk = [0u8; 4096];
+        let mut heartbeat = tokio::time::interval(self.heartbeat_interval.max(Duration::from_secs(1)));
+        loop {
+            while let Some(msg) = self.decoder.next_message() {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        warn!("FIX replay dropped garbled input: {}", e);
+                        continue;
+                    }
+                };
+                if !self.handle(&msg).await? {
+                    return Ok(());
+                }
+            }
+            tokio::select! {
+                read = self.half.read(&mut chunk) => match read {
+                    Ok(0) => return Err("connection closed".into()),
+                    Ok(n) => self.decoder.extend(&chunk[..n]),
+                    Err(e) => return Err(e.to_string()),
+                },
+                _ = heartbeat.tick() => {
+                    let mut out = self.out.lock().await;
+                    if out.last_sent.elapsed() >= self.heartbeat_interval {
+                        out.send_session("0", &[]).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle one message; `false` once the session has logged out.
+    async fn handle(&mut self, msg: &FixMessage) -> Result<bool, String> {
+        let text = || msg.get(&58).cloned().unwrap_or_default();
+        match msg.get(&35).map(String::as_str).unwrap_or_default() {
+            "1" => {
+                let test_req_id = msg.get(&112).cloned().unwrap_or_default();
+                self.out.lock().await.send_session("0", &[(112, test_req_id)]).await?;
+            }
+            "2" => {
+                // Nothing is kept to resend: fill the gap.
+                let mut out = self.out.lock().await;
+                let begin = msg.get(&7).and_then(|s| s.parse::<u32>().ok()).unwrap_or(1);
+                let fields = [(43, "Y".to_string()), (123, "Y".to_string()), (36, out.next_seq.to_string())];
+                let gap_fill = session_message(&out.config, "4", begin, &fields);
+                out.write(gap_fill).await?;
+            }
+            "5" => {
+                let mut out = self.out.lock().await;
+                if !out.logging_out {
+                    out.logging_out = true;
+                    out.send_session("5", &[]).await?;
+                    return Err(format!("logged out: {}", text()));
+                }
+                return Ok(false);
+            }
+            "8" => {
+                let Ok(report) = execution_report_from_fix(msg) else {
+                    return Ok(true);
+                };
+                // A cancel's ClOrdID is the order's, which fills are reported under too.
+                let canceled = report.exec_type == ExecType::Canceled;
+                let answered = self.pending.lock().expect("lock").take(&report.cl_ord_id, |kind| kind != RequestKind::Cancel || canceled);
+                if let Some((cl_ord_id, request)) = answered {
+                    let outcome = if report.exec_type == ExecType::Rejected {
+                        Err(report.text.unwrap_or_else(|| "rejected".into()))
+                    } else {
+                        if request.kind == RequestKind::Replace {
+                            self.pending.lock().expect("lock").replaced.insert(request.order_id, cl_ord_id);
+                        }
+                        Ok(())
+                    };
+                    self.tally.lock().expect("lock").answer(request.hold.sent, outcome);
+                }
+            }
+            "9" => {
+                let answered = {
+                    let mut pending = self.pending.lock().expect("lock");
+                    [11, 41].iter().filter_map(|tag| msg.get(tag)).find_map(|id| pending.take(id, |kind| kind != RequestKind::New))
+                };
+                if let Some((_, request)) = answered {
+                    self.tally.lock().expect("lock").answer(request.hold.sent, Err(text()));
+                }
+            }
+            msg_type @ ("3" | "j") => {
+                let seq = msg.get(&45).and_then(|s| s.parse::<u32>().ok());
+                let answered = seq.and_then(|seq| self.pending.lock().expect("lock").take_seq(seq));
+                if let Some((_, request)) = answered {
+                    let name = if msg_type == "3" { "Reject (3)" } else { "BusinessMessageReject (j)" };
+                    self.tally.lock().expect("lock").answer(request.hold.sent, Err(format!("{}: {}", name, text())));
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+}
+
+/// The next whole message off `stream`.
+async fn next_message(stream: &mut TcpStream, decoder: &mut FixDecoder) -> Result<FixMessage, String> {
+    let mut chunk = [0u8; 4096];
+    loop {
+        match decoder.next_message() {
+            Some(Ok(msg)) => return Ok(msg),
+            Some(Err(e)) => warn!("FIX replay dropped garbled input: {}", e),
+            None => match stream.read(&mut chunk).await {
+                Ok(0) => return Err("connection closed".into()),
+                Ok(n) => decoder.extend(&chunk[..n]),
+                Err(e) => return Err(e.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Generator, GeneratorConfig};
+
+    fn offsets(pace: Pace, timestamps: &[u64]) -> Vec<Option<Duration>> {
+        let mut order = Generator::new(GeneratorConfig::default()).next_order();
+        let mut pacer = Pacer::new(pace);
+        timestamps
+            .iter()
+            .map(|timestamp| {
+                order.timestamp = *timestamp;
+                pacer.offset(&GenEvent::Submit(order.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sends_are_due_at_the_rate_or_the_arrival_times_from_the_start() {
+        let ms = |ms: u64| Some(Duration::from_millis(ms));
+        assert_eq!(offsets(Pace::Rate(4.0), &[9, 9, 9]), [ms(0), ms(250), ms(500)]);
+        let second = ARRIVAL_CLOCK_TICKS_PER_SEC;
+        assert_eq!(offsets(Pace::ArrivalTimes { speed: 2.0 }, &[second, second, 2 * second, 4 * second]), [ms(0), ms(0), ms(500), ms(1500)]);
+        assert_eq!(offsets(Pace::Unlimited, &[1, 2]), [None, None]);
+        for pace in [Pace::Rate(0.0), Pace::Rate(-1.0), Pace::ArrivalTimes { speed: 0.0 }] {
+            assert_eq!(offsets(pace, &[1, 2 * second]), [None, None], "{:?}", pace);
+        }
+    }
+}
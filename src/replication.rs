@@ -0,0 +1,396 @@
+//! Primary/backup replication: a primary streams every command its engine accepts to warm standbys, which apply
+//! them to their own engine, so a standby can take over with the same state.
+//!
+//! The primary serves standbys on a TCP port ([`run_replication_primary`]). Each connection gets the primary's
+//! state (engine snapshot, market state, and venue config), then every command the engine accepts from there on
+//! (the input journal, see [`crate::journal`]), numbered from the snapshot, and market state and venue config
+//! changes; one JSON [`ReplicationMessage`] per line. A standby ([`start_standby`]) applies them with
+//! [`MultiEngine::apply_input`]. When a command does not follow on from the last one or does not apply, the
+//! connection drops, or the primary's engine is restored, the standby reconnects and starts over from a new
+//! snapshot. So does a standby more than [`REPLICATION_QUEUE_CAPACITY`] commands behind, which the primary drops.
+//!
+//! A standby refuses REST writes and keeps its FIX acceptor closed until it is promoted with
+//! `POST /admin/failover` ([`Standby::promote`]), after which it no longer follows the primary. Replication is
+//! asynchronous: commands the primary accepted but had not sent when it failed are lost. Nothing fences the old
+//! primary; stop it before promoting.
+
+use crate::api::{self, AppState};
+use crate::ids::IdStore;
+use crate::journal::{Command, InputSink, JournalEntry};
+use crate::persistence::{PersistedState, STATE_VERSION};
+use crate::types::MarketState;
+use crate::venue::VenueConfig;
+use crate::MultiEngine;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Commands queued for one standby before the primary drops it.
+pub const REPLICATION_QUEUE_CAPACITY: usize = 65_536;
+
+/// How long the primary stays silent before it sends a [`ReplicationMessage::Heartbeat`].
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a standby waits for a message before it takes the primary for gone and reconnects.
+pub const PRIMARY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a standby waits between connection attempts.
+pub const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a primary's connection checks for market state and venue config changes.
+const CHANGE_POLL: Duration = Duration::from_millis(100);
+
+/// How long a primary waits for a standby to take a write before it drops the connection.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One line of the replication stream.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplicationMessage {
+    /// The primary's state, first on every connection. `seq` is the number of the last command it includes.
+    Snapshot { seq: u64, state: Box<PersistedState> },
+    /// A command the primary accepted; its `seq` is one more than the last command's.
+    Command(JournalEntry),
+    /// The venue market state changed (as saved: during a shutdown, the one to resume).
+    MarketState { market_state: MarketState },
+    VenueConfig { config: VenueConfig },
+    /// Sent after [`HEARTBEAT_INTERVAL`] without another message.
+    Heartbeat,
+}
+
+/// The primary's [`InputSink`]: numbers accepted commands and queues them for each connected standby.
+#[derive(Debug, Default)]
+struct ReplicationFeed {
+    inner: Mutex<Feed>,
+}
+
+#[derive(Debug, Default)]
+struct Feed {
+    /// Number of the last command accepted.
+    seq: u64,
+    followers: Vec<SyncSender<JournalEntry>>,
+}
+
+impl ReplicationFeed {
+    /// Queue the commands accepted from now on for a new standby. Call with the engine lock held, around the
+    /// snapshot it starts from; returns the number of the last command in that snapshot.
+    fn follow(&self) -> (u64, Receiver<JournalEntry>) {
+        let (tx, rx) = mpsc::sync_channel(REPLICATION_QUEUE_CAPACITY);
+        let mut feed = self.inner.lock().expect("lock");
+        feed.followers.push(tx);
+        (feed.seq, rx)
+    }
+}
+
+impl InputSink for ReplicationFeed {
+    fn on_command(&self, command: &Command) {
+        let mut feed = self.inner.lock().expect("lock");
+        feed.seq += 1;
+        if feed.followers.is_empty() {
+            return;
+        }
+        let entry = JournalEntry::new(feed.seq, command.clone());
+        // A full queue is a standby too far behind; closing it makes the standby resync.
+        feed.followers.retain(|tx| tx.try_send(entry.clone()).is_ok());
+    }
+
+    fn on_reset(&self) {
+        // The state the standbys follow is gone: they reconnect for the new one.
+        self.inner.lock().expect("lock").followers.clear();
+    }
+}
+
+/// Serve standbys on `listener` with the state and accepted commands of `state`'s engine, one thread per
+/// connection. Blocks while the listener accepts.
+pub fn run_replication_primary(listener: TcpListener, state: AppState) {
+    let feed = Arc::new(ReplicationFeed::default());
+    state.engine.lock().expect("lock").add_input_sink(feed.clone());
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Replication accept failed: {}", e);
+                continue;
+            }
+        };
+        let (state, feed) = (state.clone(), feed.clone());
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+            log::info!("Standby {} connected", peer);
+            match serve_standby(stream, &state, &feed) {
+                Ok(()) => log::info!("Standby {} resyncs", peer),
+                Err(e) => log::info!("Standby {} disconnected: {}", peer, e),
+            }
+        });
+    }
+}
+
+/// Stream to one standby until it disconnects (`Err`) or has to resync (`Ok`).
+fn serve_standby(stream: TcpStream, state: &AppState, feed: &ReplicationFeed) -> Result<(), String> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    let mut out = BufWriter::new(stream);
+    let (seq, engine, commands) = {
+        let engine = state.engine.lock().expect("lock");
+        let (seq, commands) = feed.follow();
+        (seq, engine.snapshot(), commands)
+    };
+    let mut market_state = state.resume_market_state();
+    let mut venue_config = state.venue_config.lock().expect("lock").clone();
+    let snapshot = PersistedState {
+        version: STATE_VERSION,
+        engine,
+        market_state: market_state.as_str().to_string(),
+        venue_config: Some(venue_config.clone()),
+    };
+    send(&mut out, &ReplicationMessage::Snapshot { seq, state: Box::new(snapshot) })?;
+    out.flush().map_err(|e| e.to_string())?;
+    let mut sent_at = Instant::now();
+    loop {
+        match commands.recv_timeout(CHANGE_POLL) {
+            Ok(entry) => {
+                send(&mut out, &ReplicationMessage::Command(entry))?;
+                // Queued commands go out in one write.
+                while let Ok(entry) = commands.try_recv() {
+                    send(&mut out, &ReplicationMessage::Command(entry))?;
+                }
+                sent_at = Instant::now();
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        let current = state.resume_market_state();
+        if current != market_state {
+            market_state = current;
+            send(&mut out, &ReplicationMessage::MarketState { market_state })?;
+            sent_at = Instant::now();
+        }
+        let current = state.venue_config.lock().expect("lock").clone();
+        if current != venue_config {
+            send(&mut out, &ReplicationMessage::VenueConfig { config: current.clone() })?;
+            venue_config = current;
+            sent_at = Instant::now();
+        }
+        if sent_at.elapsed() >= HEARTBEAT_INTERVAL {
+            send(&mut out, &ReplicationMessage::Heartbeat)?;
+            sent_at = Instant::now();
+        }
+        out.flush().map_err(|e| e.to_string())?;
+    }
+}
+
+fn send(out: &mut impl Write, message: &ReplicationMessage) -> Result<(), String> {
+    serde_json::to_writer(&mut *out, message).map_err(|e| e.to_string())?;
+    out.write_all(b"\n").map_err(|e| e.to_string())
+}
+
+/// Where a standby is (`checks.replication` of `/health/ready`, and what `POST /admin/failover` returns).
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct StandbyStatus {
+    /// `host:port` of the primary's replication port.
+    pub primary: String,
+    pub connected: bool,
+    pub promoted: bool,
+    /// Number of the last command applied, in the current connection's numbering.
+    pub seq: u64,
+    /// Snapshots loaded and commands applied since the standby started.
+    pub snapshots: u64,
+    pub applied: u64,
+    /// Why the last connection ended, if one did.
+    pub last_error: Option<String>,
+}
+
+/// A node following a primary (see [`start_standby`]).
+pub struct Standby {
+    status: Mutex<StandbyStatus>,
+    /// Notified, with `status` locked, when `stop` is set and when the promotion is done.
+    changed: Condvar,
+    /// Set when the promotion starts: the replication thread applies nothing more.
+    stop: AtomicBool,
+    /// The connection to the primary, shut down on promotion.
+    connection: Mutex<Option<TcpStream>>,
+    thread: Mutex<Option<std::thread::JoinHandle<()>>>,
+    engine: Arc<Mutex<MultiEngine>>,
+    /// The engine's id store, detached while ids come from the primary's snapshots and given back on promotion.
+    id_store: Mutex<Option<Arc<dyn IdStore>>>,
+}
+
+impl Standby {
+    /// `host:port` of the primary.
+    pub fn primary(&self) -> String {
+        self.status.lock().expect("lock").primary.clone()
+    }
+
+    pub fn status(&self) -> StandbyStatus {
+        self.status.lock().expect("lock").clone()
+    }
+
+    pub fn is_promoted(&self) -> bool {
+        self.status.lock().expect("lock").promoted
+    }
+
+    /// Block until the standby is promoted.
+    pub fn wait_for_promotion(&self) {
+        let status = self.status.lock().expect("lock");
+        drop(self.changed.wait_while(status, |status| !status.promoted).expect("lock"));
+    }
+
+    fn stopping(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    /// Stop following the primary and take over with the state applied so far: once this returns, nothing more
+    /// is applied and [`Standby::is_promoted`] is true. The id store is back in use, so trade and execution ids
+    /// continue past this node's own reservations too (see [`crate::IdAllocator::set_store`]). Blocks until the replication
+    /// thread has stopped. Returns the status as of the promotion, `Err` if already promoted.
+    pub fn promote(&self) -> Result<StandbyStatus, String> {
+        if self.stop.swap(true, Ordering::SeqCst) {
+            return Err("this node was already promoted".to_string());
+        }
+        {
+            let _status = self.status.lock().expect("lock");
+            self.changed.notify_all();
+        }
+        if let Some(connection) = self.connection.lock().expect("lock").take() {
+            let _ = connection.shutdown(std::net::Shutdown::Both);
+        }
+        if let Some(thread) = self.thread.lock().expect("lock").take() {
+            let _ = thread.join();
+        }
+        if let Some(store) = self.id_store.lock().expect("lock").take() {
+            if let Err(e) = self.engine.lock().expect("lock").set_id_store(store) {
+                log::warn!("Failed to reopen the id reservation file: {}; ids are only persisted with snapshots", e);
+            }
+        }
+        let mut status = self.status.lock().expect("lock");
+        (status.connected, status.promoted) = (false, true);
+        self.changed.notify_all();
+        log::info!("Promoted from standby of {} at command {}", status.primary, status.seq);
+        Ok(status.clone())
+    }
+}
+
+/// Make `state` a standby of the primary at `primary` (`host:port` of its replication port): a thread connects,
+/// loads the primary's state, and applies its commands until [`Standby::promote`], reconnecting every
+/// [`RECONNECT_INTERVAL`] when the connection fails. Sets [`AppState::standby`], so the router built from
+/// `state` afterwards refuses writes. With persistence, every snapshot loaded is saved at once.
+pub fn start_standby(state: &mut AppState, primary: impl Into<String>) -> Arc<Standby> {
+    let id_store = state.engine.lock().expect("lock").take_id_store();
+    let standby = Arc::new(Standby {
+        status: Mutex::new(StandbyStatus {
+            primary: primary.into(),
+            ..StandbyStatus::default()
+        }),
+        changed: Condvar::new(),
+        stop: AtomicBool::new(false),
+        connection: Mutex::new(None),
+        thread: Mutex::new(None),
+        engine: state.engine.clone(),
+        id_store: Mutex::new(id_store),
+    });
+    state.standby = Some(standby.clone());
+    let (follower, following) = (standby.clone(), state.clone());
+    let thread = std::thread::spawn(move || follow(&follower, &following));
+    *standby.thread.lock().expect("lock") = Some(thread);
+    standby
+}
+
+/// Follow the primary until promoted.
+fn follow(standby: &Standby, state: &AppState) {
+    while !standby.stopping() {
+        let result = follow_connection(standby, state);
+        let mut status = standby.status.lock().expect("lock");
+        status.connected = false;
+        if standby.stopping() {
+            break;
+        }
+        let error = result.err().unwrap_or_else(|| "primary closed the connection".to_string());
+        log::warn!("Replication from {}: {}; reconnecting", status.primary, error);
+        status.last_error = Some(error);
+        let _ = standby.changed.wait_timeout_while(status, RECONNECT_INTERVAL, |_| !standby.stopping());
+    }
+}
+
+/// One connection to the primary: load its snapshot, then apply what follows until the connection ends, a
+/// message does not apply, or the standby is promoted.
+fn follow_connection(standby: &Standby, state: &AppState) -> Result<(), String> {
+    let primary = standby.primary();
+    let addr = primary
+        .to_socket_addrs()
+        .map_err(|e| format!("{}: {}", primary, e))?
+        .next()
+        .ok_or_else(|| format!("{}: no address", primary))?;
+    let stream = TcpStream::connect_timeout(&addr, PRIMARY_TIMEOUT).map_err(|e| format!("{}: {}", primary, e))?;
+    stream.set_read_timeout(Some(PRIMARY_TIMEOUT)).map_err(|e| e.to_string())?;
+    *standby.connection.lock().expect("lock") = Some(stream.try_clone().map_err(|e| e.to_string())?);
+    // Promoted while connecting: the connection was not there to shut down.
+    if standby.stopping() {
+        return Ok(());
+    }
+    let mut next_seq = None;
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if standby.stopping() {
+            return Ok(());
+        }
+        let message: ReplicationMessage = serde_json::from_str(&line).map_err(|e| format!("bad message: {}", e))?;
+        match message {
+            ReplicationMessage::Snapshot { seq, state: snapshot } => {
+                load_snapshot(state, *snapshot)?;
+                next_seq = Some(seq + 1);
+                let mut status = standby.status.lock().expect("lock");
+                (status.connected, status.seq) = (true, seq);
+                status.snapshots += 1;
+                log::info!("Replicating from {} at command {}", primary, seq);
+            }
+            ReplicationMessage::Command(entry) => {
+                let expected = next_seq.ok_or("command before the snapshot")?;
+                if entry.seq != expected {
+                    return Err(format!("command {} after {}", entry.seq, expected - 1));
+                }
+                state
+                    .engine
+                    .lock()
+                    .expect("lock")
+                    .apply_input(&entry.command)
+                    .map_err(|e| format!("command {} does not apply: {}", entry.seq, e))?;
+                next_seq = Some(entry.seq + 1);
+                let mut status = standby.status.lock().expect("lock");
+                status.seq = entry.seq;
+                status.applied += 1;
+            }
+            ReplicationMessage::MarketState { market_state } => {
+                *state.market_state.lock().expect("lock") = market_state;
+                api::publish_market_state(state, market_state);
+            }
+            ReplicationMessage::VenueConfig { config } => {
+                state.set_venue_config(config);
+                state.notifier.mark_dirty();
+            }
+            ReplicationMessage::Heartbeat => {}
+        }
+    }
+    Ok(())
+}
+
+/// Replace the standby's engine state, market state, and venue config with the primary's, and save them.
+fn load_snapshot(state: &AppState, snapshot: PersistedState) -> Result<(), String> {
+    let market_state = MarketState::from_str(snapshot.market_state.trim()).unwrap_or(MarketState::Open);
+    state
+        .engine
+        .lock()
+        .expect("lock")
+        .load_from_snapshot(snapshot.engine)
+        .map_err(|e| format!("snapshot does not load: {}", e))?;
+    *state.market_state.lock().expect("lock") = market_state;
+    api::publish_market_state(state, market_state);
+    if let Some(config) = snapshot.venue_config {
+        state.set_venue_config(config);
+    }
+    // Loading cleared the command log, so the state file has to catch up before it is replayed on.
+    api::persist_state(state);
+    Ok(())
+}
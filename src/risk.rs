@@ -0,0 +1,60 @@
+//! Pre-trade risk checks run by the engine before matching (see [`RiskLimits`]).
+
+use crate::positions::Position;
+use crate::types::{Order, Side};
+use rust_decimal::Decimal;
+
+/// Per-order and per-position caps checked on submit and modify. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskLimits {
+    /// Max quantity of a single order.
+    pub max_order_quantity: Option<Decimal>,
+    /// Max price × quantity of a single limit order (market orders have no price and are not checked).
+    pub max_order_notional: Option<Decimal>,
+    /// Max absolute position per trader per instrument, counting the trader's resting orders on the same
+    /// side as if they filled.
+    pub max_position: Option<Decimal>,
+}
+
+impl RiskLimits {
+    /// Read limits from `MAX_ORDER_QUANTITY`, `MAX_ORDER_NOTIONAL`, and `MAX_POSITION`.
+    /// Unset or unparsable variables leave that limit off.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.trim().parse().ok());
+        Self {
+            max_order_quantity: var("MAX_ORDER_QUANTITY"),
+            max_order_notional: var("MAX_ORDER_NOTIONAL"),
+            max_position: var("MAX_POSITION"),
+        }
+    }
+
+    /// Check `order` against the limits, given the trader's current `position` in the instrument
+    /// (with open quantities excluding any order being replaced). Returns the rejection reason on failure.
+    pub fn check(&self, order: &Order, position: &Position) -> Result<(), String> {
+        if let Some(max) = self.max_order_quantity {
+            if order.quantity > max {
+                return Err(format!("Order quantity {} exceeds max order quantity {}", order.quantity, max));
+            }
+        }
+        if let (Some(max), Some(price)) = (self.max_order_notional, order.price) {
+            let notional = price * order.quantity;
+            if notional > max {
+                return Err(format!("Order notional {} exceeds max order notional {}", notional, max));
+            }
+        }
+        if let Some(max) = self.max_position {
+            let worst = match order.side {
+                Side::Buy => position.net_quantity + position.open_buy_quantity + order.quantity,
+                Side::Sell => position.net_quantity - position.open_sell_quantity - order.quantity,
+            };
+            if worst.abs() > max {
+                return Err(format!(
+                    "Position {} for trader {} would exceed max position {}",
+                    worst, order.trader_id.0, max
+                ));
+            }
+        }
+        Ok(())
+    }
+}
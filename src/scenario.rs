@@ -0,0 +1,436 @@
+//! Scripted market situations for QA: a [`Scenario`] is a named list of steps (seed book levels, submit a
+//! burst, halt, expire orders, check the outcome), read from JSON and run by a [`ScenarioRunner`] against a
+//! [`MultiEngine`], so cases like "flash crash into the price band, then halt" become reusable fixtures.
+//!
+//! Steps run in order. The runner numbers orders from 1 and timestamps them with the engine time
+//! ([`MultiEngine::now`], moved by [`Step::AdvanceTime`]). An order the engine rejects does not stop the
+//! scenario: rejections are counted, with their reasons, in the [`ScenarioReport`] and can be checked with
+//! [`Step::Expect`]. An expectation that does not hold, or a step the engine refuses (an unknown instrument),
+//! stops it with `Err` naming the step.
+
+use crate::engine::MultiEngine;
+use crate::scheduler::TimedAction;
+use crate::types::{InstrumentId, MarketState, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+use crate::venue::BandConfig;
+use crate::MatchingEngine;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// A scripted market situation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Instruments added before the steps run, unless the engine already has them.
+    #[serde(default)]
+    pub instruments: Vec<ScenarioInstrument>,
+    pub steps: Vec<NamedStep>,
+}
+
+/// An instrument a [`Scenario`] trades.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioInstrument {
+    pub instrument_id: InstrumentId,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Minimum price increment; the engine's default when unset.
+    #[serde(default)]
+    pub tick_size: Option<Decimal>,
+}
+
+/// A step and, optionally, what to call it in errors and the report (its kind otherwise).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NamedStep {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub step: Step,
+}
+
+/// One price level of [`Step::SeedBook`]: `orders` orders of `quantity` each.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SeedLevel {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    #[serde(default = "one")]
+    pub orders: usize,
+}
+
+/// What one step does. Orders go through matching like any other, so a seeded level that crosses trades.
+/// Seeded orders default to trader 1 and other orders to trader 2, so they trade with each other
+/// (self-trade prevention keeps a trader's orders from matching its own).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Rest GTC limit orders at each level on one side; with `expire_at`, each expires at that engine time.
+    SeedBook {
+        instrument_id: InstrumentId,
+        side: Side,
+        levels: Vec<SeedLevel>,
+        #[serde(default = "seed_trader")]
+        trader_id: TraderId,
+        #[serde(default)]
+        expire_at: Option<u64>,
+    },
+    /// One order: a limit at `price`, or a market order without one.
+    Order {
+        instrument_id: InstrumentId,
+        side: Side,
+        quantity: Decimal,
+        #[serde(default)]
+        price: Option<Decimal>,
+        #[serde(default = "gtc")]
+        time_in_force: TimeInForce,
+        #[serde(default = "order_trader")]
+        trader_id: TraderId,
+        #[serde(default)]
+        expire_at: Option<u64>,
+    },
+    /// `count` orders of `quantity` each, back to back: limits starting at `price` and moving by `price_step`
+    /// per order (negative walks down), or market orders without a price.
+    Burst {
+        instrument_id: InstrumentId,
+        side: Side,
+        count: usize,
+        quantity: Decimal,
+        #[serde(default)]
+        price: Option<Decimal>,
+        #[serde(default)]
+        price_step: Decimal,
+        #[serde(default = "gtc")]
+        time_in_force: TimeInForce,
+        #[serde(default = "order_trader")]
+        trader_id: TraderId,
+    },
+    /// Cancel every resting order of the instrument, or of one side of it (liquidity pulled).
+    CancelAll {
+        instrument_id: InstrumentId,
+        #[serde(default)]
+        side: Option<Side>,
+    },
+    /// Halt the instrument: new orders are rejected, cancels still accepted.
+    Halt { instrument_id: InstrumentId },
+    /// Open the instrument again.
+    Resume { instrument_id: InstrumentId },
+    /// Set the dynamic price band: limit prices more than this many percent from the last trade are rejected.
+    /// `null` removes it.
+    PriceBand { max_deviation_pct: Option<Decimal> },
+    /// Move engine time to `to`, running the expiries (and other timers) due by then.
+    AdvanceTime { to: u64 },
+    /// Check the state of the instrument and the running totals; every field set must match.
+    Expect(Expectation),
+}
+
+/// The checks of a [`Step::Expect`]. Unset fields are not checked.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    pub instrument_id: InstrumentId,
+    #[serde(default)]
+    pub best_bid: Option<Decimal>,
+    #[serde(default)]
+    pub best_ask: Option<Decimal>,
+    /// Price levels on each side (0 for an empty side).
+    #[serde(default)]
+    pub bid_levels: Option<usize>,
+    #[serde(default)]
+    pub ask_levels: Option<usize>,
+    #[serde(default)]
+    pub resting_orders: Option<usize>,
+    /// Price of the instrument's last trade.
+    #[serde(default)]
+    pub last_price: Option<Decimal>,
+    #[serde(default)]
+    pub market_state: Option<MarketState>,
+    /// Trades, rejected orders, and expired orders since the scenario started, over all instruments.
+    #[serde(default)]
+    pub trades: Option<usize>,
+    #[serde(default)]
+    pub rejected: Option<usize>,
+    #[serde(default)]
+    pub expired: Option<usize>,
+}
+
+fn one() -> usize {
+    1
+}
+
+fn gtc() -> TimeInForce {
+    TimeInForce::GTC
+}
+
+fn seed_trader() -> TraderId {
+    TraderId(1)
+}
+
+fn order_trader() -> TraderId {
+    TraderId(2)
+}
+
+impl Step {
+    /// The step's `step` tag, e.g. `seed_book`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Step::SeedBook { .. } => "seed_book",
+            Step::Order { .. } => "order",
+            Step::Burst { .. } => "burst",
+            Step::CancelAll { .. } => "cancel_all",
+            Step::Halt { .. } => "halt",
+            Step::Resume { .. } => "resume",
+            Step::PriceBand { .. } => "price_band",
+            Step::AdvanceTime { .. } => "advance_time",
+            Step::Expect(_) => "expect",
+        }
+    }
+}
+
+impl Scenario {
+    /// Parse a scenario from JSON. Errors name the path of the bad field, e.g. `steps[2].levels[0]: missing
+    /// field `price``.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let de = &mut serde_json::Deserializer::from_str(json);
+        serde_path_to_error::deserialize(de).map_err(|e| format!("{}: {}", e.path(), e.inner()))
+    }
+
+    /// Read and parse a scenario file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// What one step did.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct StepReport {
+    /// The step's name, or its kind.
+    pub step: String,
+    pub trades: usize,
+    /// Why each rejected order was rejected, in order.
+    pub rejected: Vec<String>,
+    pub expired: usize,
+}
+
+/// What a scenario run did, step by step.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub steps: Vec<StepReport>,
+    pub trades: usize,
+    pub rejected: usize,
+    pub expired: usize,
+}
+
+/// Runs [`Scenario`]s against an engine. Scenarios run one after another continue from the state the last one
+/// left, order ids included.
+#[derive(Debug)]
+pub struct ScenarioRunner {
+    engine: MultiEngine,
+    next_order_id: u64,
+}
+
+impl ScenarioRunner {
+    /// A runner on `engine`, numbering its orders from 1: the engine should not already hold orders with those ids.
+    pub fn new(engine: MultiEngine) -> Self {
+        Self { engine, next_order_id: 1 }
+    }
+
+    pub fn engine(&self) -> &MultiEngine {
+        &self.engine
+    }
+
+    pub fn into_engine(self) -> MultiEngine {
+        self.engine
+    }
+
+    /// Add the scenario's instruments, then run its steps in order. `Err` names the first step that failed.
+    pub fn run(&mut self, scenario: &Scenario) -> Result<ScenarioReport, String> {
+        for instrument in &scenario.instruments {
+            if self.engine.instruments().contains(&instrument.instrument_id) {
+                continue;
+            }
+            let tick_size = instrument.tick_size.unwrap_or(crate::DEFAULT_TICK_SIZE);
+            self.engine
+                .add_instrument_with_tick_size(instrument.instrument_id, instrument.symbol.clone(), tick_size)
+                .map_err(|e| format!("{}: instrument {}: {}", scenario.name, instrument.instrument_id.0, e))?;
+        }
+        let mut report = ScenarioReport { name: scenario.name.clone(), ..ScenarioReport::default() };
+        for (i, named) in scenario.steps.iter().enumerate() {
+            let label = named.name.clone().unwrap_or_else(|| named.step.kind().to_string());
+            let mut step = StepReport { step: label.clone(), ..StepReport::default() };
+            self.run_step(&named.step, &report, &mut step)
+                .map_err(|e| format!("{}: step {} ({}): {}", scenario.name, i + 1, label, e))?;
+            report.trades += step.trades;
+            report.rejected += step.rejected.len();
+            report.expired += step.expired;
+            report.steps.push(step);
+        }
+        Ok(report)
+    }
+
+    fn run_step(&mut self, step: &Step, totals: &ScenarioReport, report: &mut StepReport) -> Result<(), String> {
+        match step {
+            Step::SeedBook { instrument_id, side, levels, trader_id, expire_at } => {
+                for level in levels {
+                    for _ in 0..level.orders {
+                        let order = self.order(*instrument_id, *side, level.quantity, Some(level.price), TimeInForce::GTC, *trader_id);
+                        self.submit(order, *expire_at, report);
+                    }
+                }
+            }
+            Step::Order { instrument_id, side, quantity, price, time_in_force, trader_id, expire_at } => {
+                let order = self.order(*instrument_id, *side, *quantity, *price, *time_in_force, *trader_id);
+                self.submit(order, *expire_at, report);
+            }
+            Step::Burst { instrument_id, side, count, quantity, price, price_step, time_in_force, trader_id } => {
+                for i in 0..*count {
+                    let price = price.map(|price| price + *price_step * Decimal::from(i));
+                    let order = self.order(*instrument_id, *side, *quantity, price, *time_in_force, *trader_id);
+                    self.submit(order, None, report);
+                }
+            }
+            Step::CancelAll { instrument_id, side } => {
+                let orders = self
+                    .engine
+                    .book_orders(*instrument_id)
+                    .ok_or_else(|| format!("Instrument {} not found", instrument_id.0))?;
+                for order in orders.iter().filter(|order| side.is_none_or(|side| order.side == side)) {
+                    MatchingEngine::cancel_order(&mut self.engine, order.order_id);
+                }
+            }
+            Step::Halt { instrument_id } => self.engine.set_instrument_market_state(*instrument_id, MarketState::Halted)?,
+            Step::Resume { instrument_id } => self.engine.set_instrument_market_state(*instrument_id, MarketState::Open)?,
+            Step::PriceBand { max_deviation_pct } => {
+                self.engine.set_band_config(BandConfig { max_deviation_pct: *max_deviation_pct });
+            }
+            Step::AdvanceTime { to } => {
+                for fired in self.engine.advance_time(*to) {
+                    match (&fired.timer.action, fired.result) {
+                        (TimedAction::ExpireOrder { .. }, Ok(_)) => report.expired += 1,
+                        (_, Ok((trades, _))) => report.trades += trades.len(),
+                        (_, Err(_)) => {}
+                    }
+                }
+            }
+            Step::Expect(expected) => self.check(expected, totals)?,
+        }
+        Ok(())
+    }
+
+    fn order(
+        &mut self,
+        instrument_id: InstrumentId,
+        side: Side,
+        quantity: Decimal,
+        price: Option<Decimal>,
+        time_in_force: TimeInForce,
+        trader_id: TraderId,
+    ) -> Order {
+        let order_id = OrderId(self.next_order_id);
+        self.next_order_id += 1;
+        Order {
+            order_id,
+            client_order_id: format!("scenario-{}", order_id.0),
+            instrument_id,
+            side,
+            order_type: if price.is_some() { OrderType::Limit } else { OrderType::Market },
+            quantity,
+            price,
+            time_in_force,
+            timestamp: self.engine.now(),
+            trader_id,
+        }
+    }
+
+    /// Submit `order`, counting its trades or its rejection, and expire it at `expire_at` if it rests.
+    fn submit(&mut self, order: Order, expire_at: Option<u64>, report: &mut StepReport) {
+        let order_id = order.order_id;
+        match self.engine.submit_order(order) {
+            Ok((trades, _)) => report.trades += trades.len(),
+            Err(reason) => report.rejected.push(reason),
+        }
+        if let Some(due) = expire_at {
+            let _ = self.engine.expire_order_at(order_id, due);
+        }
+    }
+
+    fn check(&self, expected: &Expectation, totals: &ScenarioReport) -> Result<(), String> {
+        let id = expected.instrument_id;
+        let snapshot = self.engine.book_snapshot_for(id).ok_or_else(|| format!("Instrument {} not found", id.0))?;
+        let levels = self.engine.book_levels_for(id).unwrap_or_default();
+        let resting = self.engine.book_orders(id).map_or(0, |orders| orders.len());
+        let last = self.engine.stats_for(id).and_then(|stats| stats.last);
+        let mut failures = Vec::new();
+        let mut compare = |what: &str, expected: Option<String>, actual: String| {
+            if let Some(expected) = expected.filter(|expected| *expected != actual) {
+                failures.push(format!("{} is {}, expected {}", what, actual, expected));
+            }
+        };
+        let price = |price: Option<Decimal>| price.map_or("none".to_string(), |price| price.normalize().to_string());
+        compare("best_bid", expected.best_bid.map(|p| price(Some(p))), price(snapshot.best_bid));
+        compare("best_ask", expected.best_ask.map(|p| price(Some(p))), price(snapshot.best_ask));
+        compare("bid_levels", expected.bid_levels.map(|n| n.to_string()), levels.bids.len().to_string());
+        compare("ask_levels", expected.ask_levels.map(|n| n.to_string()), levels.asks.len().to_string());
+        compare("resting_orders", expected.resting_orders.map(|n| n.to_string()), resting.to_string());
+        compare("last_price", expected.last_price.map(|p| price(Some(p))), price(last));
+        let state = self.engine.instrument_market_state(id).unwrap_or_default();
+        compare("market_state", expected.market_state.map(|s| s.as_str().to_string()), state.as_str().to_string());
+        compare("trades", expected.trades.map(|n| n.to_string()), totals.trades.to_string());
+        compare("rejected", expected.rejected.map(|n| n.to_string()), totals.rejected.to_string());
+        compare("expired", expected.expired.map(|n| n.to_string()), totals.expired.to_string());
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = r#"{
+        "name": "book",
+        "instruments": [{ "instrument_id": 1 }],
+        "steps": [
+            { "step": "seed_book", "instrument_id": 1, "side": "Buy", "levels": [{ "price": "99", "quantity": "5", "orders": 2 }] },
+            { "name": "lift", "step": "order", "instrument_id": 1, "side": "Sell", "quantity": "7", "price": "99" },
+            { "name": "after lift", "step": "expect", "instrument_id": 1, "best_bid": "99", "resting_orders": 1, "trades": 2 }
+        ]
+    }"#;
+
+    #[test]
+    fn named_steps_run_and_report_under_their_names() {
+        let scenario = Scenario::from_json(BOOK).unwrap();
+        assert_eq!(scenario.steps[0].name, None);
+        assert_eq!(scenario.steps[1].name.as_deref(), Some("lift"));
+        let report = ScenarioRunner::new(MultiEngine::new_with_instruments(vec![])).run(&scenario).unwrap();
+        let names: Vec<_> = report.steps.iter().map(|step| step.step.as_str()).collect();
+        assert_eq!(names, ["seed_book", "lift", "after lift"]);
+        assert_eq!((report.steps[1].trades, report.trades, report.rejected), (2, 2, 0));
+    }
+
+    #[test]
+    fn a_failed_expectation_names_the_step_and_every_mismatch() {
+        let json = BOOK.replace(r#""best_bid": "99", "resting_orders": 1"#, r#""best_bid": "98", "resting_orders": 2, "ask_levels": 0"#);
+        let scenario = Scenario::from_json(&json).unwrap();
+        let err = ScenarioRunner::new(MultiEngine::new_with_instruments(vec![])).run(&scenario).unwrap_err();
+        assert_eq!(err, "book: step 3 (after lift): best_bid is 99, expected 98; resting_orders is 1, expected 2");
+    }
+
+    #[test]
+    fn parse_errors_name_the_bad_field() {
+        let missing = BOOK.replace(r#"{ "price": "99", "quantity": "5", "orders": 2 }"#, r#"{ "quantity": "5" }"#);
+        let err = Scenario::from_json(&missing).unwrap_err();
+        assert!(err.starts_with("steps[0]") && err.contains("missing field `price`"), "{}", err);
+        let unknown = BOOK.replace(r#""best_bid": "99""#, r#""best_bidd": "99""#);
+        assert!(Scenario::from_json(&unknown).unwrap_err().contains("best_bidd"));
+        let kind = BOOK.replace(r#""step": "order""#, r#""step": "sweep""#);
+        assert!(Scenario::from_json(&kind).unwrap_err().contains("unknown variant `sweep`"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Time-based scheduler for [`crate::MultiEngine`]: order expiry (good-till-date), auction uncrosses, and other
+//! delayed actions, all run from [`crate::MultiEngine::advance_time`].
+//!
+//! The engine never reads a clock. Time is whatever the caller passes to `advance_time` (e.g. milliseconds
+//! since the Unix epoch), so a run is reproducible from its inputs and tests can step time explicitly.
+
+use crate::execution::{ExecutionReport, Trade};
+use crate::types::{InstrumentId, OrderId};
+use std::collections::BTreeMap;
+
+/// Timer identifier, unique per engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize)]
+pub struct TimerId(pub u64);
+
+/// Action run once engine time reaches its due time.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type")]
+pub enum TimedAction {
+    /// Remove a resting order like a cancel, reported as `Expired` (good-till-date).
+    ExpireOrder { order_id: OrderId },
+    /// Uncross an instrument's book (e.g. at the end of a call auction).
+    Uncross { instrument_id: InstrumentId },
+}
+
+/// A pending timer.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Timer {
+    pub timer_id: TimerId,
+    /// Engine time at which the action runs.
+    pub due: u64,
+    pub action: TimedAction,
+}
+
+/// A timer run by [`crate::MultiEngine::advance_time`] and what its action returned: the trades and reports of
+/// an uncross (none for an expiry), or why it could not run (e.g. the order had already filled).
+#[derive(Clone, Debug)]
+pub struct FiredTimer {
+    pub timer: Timer,
+    pub result: Result<(Vec<Trade>, Vec<ExecutionReport>), String>,
+}
+
+/// Scheduler state carried in [`crate::EngineSnapshot`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SchedulerSnapshot {
+    /// Engine time of the last `advance_time`.
+    pub now: u64,
+    pub next_timer_id: u64,
+    /// Pending timers in firing order.
+    pub timers: Vec<Timer>,
+}
+
+/// Pending timers keyed by (due time, timer id): timers due at the same time fire in the order they were set.
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    now: u64,
+    next_timer_id: u64,
+    timers: BTreeMap<(u64, TimerId), TimedAction>,
+}
+
+impl Scheduler {
+    pub(crate) fn new() -> Self {
+        Self::restore(SchedulerSnapshot::default())
+    }
+
+    pub(crate) fn restore(snap: SchedulerSnapshot) -> Self {
+        Self {
+            now: snap.now,
+            next_timer_id: snap.next_timer_id.max(1),
+            timers: snap.timers.into_iter().map(|t| ((t.due, t.timer_id), t.action)).collect(),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> SchedulerSnapshot {
+        SchedulerSnapshot {
+            now: self.now,
+            next_timer_id: self.next_timer_id,
+            timers: self.pending(),
+        }
+    }
+
+    pub(crate) fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub(crate) fn schedule(&mut self, due: u64, action: TimedAction) -> TimerId {
+        let timer_id = TimerId(self.next_timer_id);
+        self.next_timer_id += 1;
+        self.timers.insert((due, timer_id), action);
+        timer_id
+    }
+
+    /// Remove a pending timer. Returns `false` if it already fired or never existed.
+    pub(crate) fn cancel(&mut self, timer_id: TimerId) -> bool {
+        let key = self.timers.keys().find(|(_, id)| *id == timer_id).copied();
+        key.is_some_and(|key| self.timers.remove(&key).is_some())
+    }
+
+    /// Whether a timer is pending as `timer_id`.
+    pub(crate) fn is_pending(&self, timer_id: TimerId) -> bool {
+        self.timers.keys().any(|(_, id)| *id == timer_id)
+    }
+
+    /// Whether [`Scheduler::advance`] to `now` would take any timers.
+    pub(crate) fn has_due(&self, now: u64) -> bool {
+        self.timers.keys().next().is_some_and(|(due, _)| *due <= self.now.max(now))
+    }
+
+    /// Move time forward to `now` (never backwards) and take the timers now due, in firing order.
+    pub(crate) fn advance(&mut self, now: u64) -> Vec<Timer> {
+        self.now = self.now.max(now);
+        let later = match self.now.checked_add(1) {
+            Some(next) => self.timers.split_off(&(next, TimerId(0))),
+            None => BTreeMap::new(),
+        };
+        std::mem::replace(&mut self.timers, later)
+            .into_iter()
+            .map(|((due, timer_id), action)| Timer { timer_id, due, action })
+            .collect()
+    }
+
+    pub(crate) fn pending(&self) -> Vec<Timer> {
+        self.timers
+            .iter()
+            .map(|(&(due, timer_id), action)| Timer {
+                timer_id,
+                due,
+                action: action.clone(),
+            })
+            .collect()
+    }
+}
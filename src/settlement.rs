@@ -0,0 +1,306 @@
+//! Settlement store: a durable record of accepted orders, trades, and execution reports for downstream
+//! settlement and reconciliation, kept outside the engine so it survives the loss of the host.
+//!
+//! A [`SettlementSink`] is an engine event sink; it queues records for a background thread that writes them to a
+//! [`SettlementStore`] in batches, in engine order. A batch that fails is retried until it is stored, so a short
+//! store outage delays records but loses none while the process runs; a longer one fills the queue, after which
+//! new records are dropped, counted, and logged rather than held without bound.
+//! [`crate::postgres::PostgresStore`] (feature `postgres`) keeps them in PostgreSQL; [`InMemorySettlementStore`]
+//! is for tests.
+
+use crate::events::{EngineEvent, EngineEventSink};
+use crate::execution::{ExecutionReport, Trade};
+use crate::types::Order;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Most records written to the store in one batch.
+pub const SETTLEMENT_BATCH: usize = 1000;
+
+/// Records a [`SettlementSink`] holds for the store by default before it drops new ones.
+pub const SETTLEMENT_QUEUE_CAPACITY: usize = 1_000_000;
+
+/// How long the writer waits before retrying a batch the store refused.
+const SETTLEMENT_RETRY: Duration = Duration::from_secs(1);
+
+/// One record for the settlement store.
+#[derive(Clone, Debug)]
+pub enum SettlementRecord {
+    /// An order (or replacement) as accepted: the trader, instrument, and side behind its trades and reports.
+    Order(Order),
+    Trade(Trade),
+    Report(ExecutionReport),
+}
+
+/// Where a [`SettlementSink`] writes records.
+pub trait SettlementStore: Send + Sync {
+    /// Store `records`, in order, all or none. A batch that failed is passed again, so storing a record twice
+    /// must leave one copy (orders are keyed by order id, the latest kept; trades by trade id; reports by exec id).
+    fn store(&self, records: &[SettlementRecord]) -> Result<(), String>;
+}
+
+/// Keeps records in memory (for tests).
+#[derive(Default)]
+pub struct InMemorySettlementStore {
+    records: Mutex<Vec<SettlementRecord>>,
+}
+
+impl InMemorySettlementStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every record stored, oldest first.
+    pub fn records(&self) -> Vec<SettlementRecord> {
+        self.records.lock().expect("lock").clone()
+    }
+}
+
+impl SettlementStore for InMemorySettlementStore {
+    fn store(&self, records: &[SettlementRecord]) -> Result<(), String> {
+        self.records.lock().expect("lock").extend_from_slice(records);
+        Ok(())
+    }
+}
+
+enum SettlementMessage {
+    Record(SettlementRecord),
+    /// Acknowledged once every earlier record is stored.
+    Flush(mpsc::Sender<()>),
+}
+
+/// Engine event sink that hands [`EngineEvent::OrderAccepted`], [`EngineEvent::Trade`], and
+/// [`EngineEvent::Report`] to a background writer for a [`SettlementStore`]. The engine never waits on the
+/// store: while it is down records queue up to the sink's capacity, and past that new ones are dropped, with a
+/// warning at the first drop and every doubling after. [`SettlementSink::queued`] and
+/// [`SettlementSink::dropped`] tell how far behind the store is.
+pub struct SettlementSink {
+    tx: mpsc::Sender<SettlementMessage>,
+    capacity: usize,
+    /// Records handed to the writer and not yet stored.
+    queued: Arc<AtomicUsize>,
+    dropped: AtomicU64,
+}
+
+impl SettlementSink {
+    /// Start the writer thread with a queue of [`SETTLEMENT_QUEUE_CAPACITY`] records; it stops once the sink is
+    /// dropped and the queue stored.
+    pub fn new(store: Arc<dyn SettlementStore>) -> Self {
+        Self::with_capacity(store, SETTLEMENT_QUEUE_CAPACITY)
+    }
+
+    /// Like [`SettlementSink::new`], holding at most `capacity` records for the store.
+    pub fn with_capacity(store: Arc<dyn SettlementStore>, capacity: usize) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let stored = queued.clone();
+        std::thread::Builder::new()
+            .name("settlement-writer".to_string())
+            .spawn(move || {
+                let mut batch = Vec::new();
+                let mut acks = Vec::new();
+                while let Ok(message) = rx.recv() {
+                    let mut next = Some(message);
+                    while let Some(message) = next.take() {
+                        match message {
+                            SettlementMessage::Record(record) => batch.push(record),
+                            SettlementMessage::Flush(ack) => acks.push(ack),
+                        }
+                        if batch.len() < SETTLEMENT_BATCH {
+                            next = rx.try_recv().ok();
+                        }
+                    }
+                    while !batch.is_empty() {
+                        match store.store(&batch) {
+                            Ok(()) => {
+                                stored.fetch_sub(batch.len(), Ordering::Relaxed);
+                                batch.clear();
+                            }
+                            Err(e) => {
+                                log::warn!("settlement store failed: {}; retrying {} records", e, batch.len());
+                                std::thread::sleep(SETTLEMENT_RETRY);
+                            }
+                        }
+                    }
+                    for ack in acks.drain(..) {
+                        let _ = ack.send(());
+                    }
+                }
+            })
+            .expect("spawn settlement writer");
+        Self {
+            tx,
+            capacity,
+            queued,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Wait up to `timeout` for every record queued so far to be stored. Returns false if they were not.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let (ack, done) = mpsc::channel();
+        self.tx.send(SettlementMessage::Flush(ack)).is_ok() && done.recv_timeout(timeout).is_ok()
+    }
+
+    /// Records waiting for the store.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Records dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl EngineEventSink for SettlementSink {
+    fn on_event(&self, event: &EngineEvent) {
+        let record = match event {
+            EngineEvent::OrderAccepted { order } => SettlementRecord::Order(order.clone()),
+            EngineEvent::Trade(trade) => SettlementRecord::Trade(trade.clone()),
+            EngineEvent::Report(report) => SettlementRecord::Report(report.clone()),
+            _ => return,
+        };
+        if self.queued.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < self.capacity).then_some(n + 1)).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                log::warn!("settlement queue full ({} records); {} records dropped", self.capacity, dropped);
+            }
+            return;
+        }
+        if self.tx.send(SettlementMessage::Record(record)).is_err() {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{MatchingEngine, MultiEngine};
+    use crate::types::{InstrumentId, OrderId, OrderType, Side, TimeInForce, TraderId};
+    use rust_decimal::Decimal;
+    use std::sync::atomic::AtomicBool;
+
+    /// Refuses the first `failures` batches.
+    struct FlakyStore {
+        failures: AtomicUsize,
+        inner: InMemorySettlementStore,
+    }
+
+    impl SettlementStore for FlakyStore {
+        fn store(&self, records: &[SettlementRecord]) -> Result<(), String> {
+            if self.failures.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok() {
+                return Err("connection refused".to_string());
+            }
+            self.inner.store(records)
+        }
+    }
+
+    #[test]
+    fn settlement_sink_stores_orders_trades_and_reports_in_order_after_a_store_outage() {
+        let order = |id: u64, side: Side| Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(2),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        };
+        let store = Arc::new(FlakyStore {
+            failures: AtomicUsize::new(1),
+            inner: InMemorySettlementStore::new(),
+        });
+        let sink = Arc::new(SettlementSink::new(store.clone()));
+        let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+        engine.add_event_sink(sink.clone());
+        engine.submit_order(order(1, Side::Sell)).unwrap();
+        let (trades, reports) = engine.submit_order(order(2, Side::Buy)).unwrap();
+
+        assert!(sink.flush(Duration::from_secs(5)));
+        let records = store.inner.records();
+        let orders: Vec<_> = records
+            .iter()
+            .filter_map(|r| match r {
+                SettlementRecord::Order(order) => Some(order.order_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(orders, [OrderId(1), OrderId(2)]);
+        let stored_trades: Vec<_> = records
+            .iter()
+            .filter_map(|r| match r {
+                SettlementRecord::Trade(trade) => Some(trade.trade_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stored_trades, trades.iter().map(|t| t.trade_id).collect::<Vec<_>>());
+        let seqs: Vec<_> = records
+            .iter()
+            .filter_map(|r| match r {
+                SettlementRecord::Trade(trade) => Some(trade.seq),
+                SettlementRecord::Report(report) => Some(report.seq),
+                SettlementRecord::Order(_) => None,
+            })
+            .collect();
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]), "engine order: {:?}", seqs);
+        assert!(reports.iter().all(|r| seqs.contains(&r.seq)));
+    }
+    /// Refuses every batch until it is `up`.
+    #[derive(Default)]
+    struct DownStore {
+        up: AtomicBool,
+        inner: InMemorySettlementStore,
+    }
+
+    impl SettlementStore for DownStore {
+        fn store(&self, records: &[SettlementRecord]) -> Result<(), String> {
+            if !self.up.load(Ordering::Relaxed) {
+                return Err("connection refused".to_string());
+            }
+            self.inner.store(records)
+        }
+    }
+
+    #[test]
+    fn settlement_sink_drops_and_counts_records_past_its_capacity_while_the_store_is_down() {
+        let store = Arc::new(DownStore::default());
+        let sink = SettlementSink::with_capacity(store.clone(), 2);
+        for id in 1..=3 {
+            let order = Order {
+                order_id: OrderId(id),
+                client_order_id: format!("c{}", id),
+                instrument_id: InstrumentId(1),
+                side: Side::Buy,
+                order_type: OrderType::Market,
+                quantity: Decimal::ONE,
+                price: None,
+                time_in_force: TimeInForce::IOC,
+                timestamp: id,
+                trader_id: TraderId(1),
+            };
+            sink.on_event(&EngineEvent::OrderAccepted { order });
+        }
+        assert_eq!((sink.queued(), sink.dropped()), (2, 1));
+
+        store.up.store(true, Ordering::Relaxed);
+        assert!(sink.flush(Duration::from_secs(5)));
+        assert_eq!((sink.queued(), sink.dropped()), (0, 1));
+        let stored: Vec<_> = store
+            .inner
+            .records()
+            .iter()
+            .filter_map(|r| match r {
+                SettlementRecord::Order(order) => Some(order.order_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stored, [OrderId(1), OrderId(2)]);
+    }
+}
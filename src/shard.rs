@@ -0,0 +1,154 @@
+//! Sharded engine: instruments split across single-threaded [`MultiEngine`] workers, one OS thread per shard,
+//! so orders for instruments on different shards match in parallel instead of serializing on one lock.
+//!
+//! Each shard is an [`EngineHandle`]: it owns its engine outright and is fed through a channel, and callers get
+//! futures for the results (see [`ShardedEngine`]). Each shard has its own sequence numbers, trade and execution
+//! ids, duplicate-id window, positions, and risk limits, so ids are unique per instrument rather than per process.
+
+use crate::engine::{BookSnapshot, EngineSnapshot, MatchingEngine, MultiEngine};
+use crate::execution::{ExecutionReport, Trade};
+use crate::handle::EngineHandle;
+use crate::types::{InstrumentId, Order, OrderId};
+use std::collections::HashMap;
+
+type Fills = Result<(Vec<Trade>, Vec<ExecutionReport>), String>;
+
+/// Instruments spread over `shard_count` engine threads. Methods route by instrument and return futures that
+/// resolve once the owning shard has processed the request; requests to one shard run in the order sent.
+///
+/// Dropping the `ShardedEngine` stops the threads once they finish the requests already queued.
+#[derive(Debug)]
+pub struct ShardedEngine {
+    /// One [`EngineHandle`] per shard.
+    shards: Vec<EngineHandle>,
+    shard_of: HashMap<InstrumentId, usize>,
+}
+
+impl ShardedEngine {
+    /// Start `shard_count` engine threads (at least one) and assign instruments round-robin in the order given.
+    /// Use `shard_count == instruments.len()` for one thread per instrument.
+    pub fn new(instruments: Vec<(InstrumentId, Option<String>)>, shard_count: usize) -> Self {
+        let shard_count = shard_count.max(1);
+        let mut per_shard: Vec<Vec<(InstrumentId, Option<String>)>> = vec![Vec::new(); shard_count];
+        let mut shard_of = HashMap::new();
+        for (i, (instrument_id, symbol)) in instruments.into_iter().enumerate() {
+            shard_of.insert(instrument_id, i % shard_count);
+            per_shard[i % shard_count].push((instrument_id, symbol));
+        }
+        let shards = per_shard
+            .into_iter()
+            .enumerate()
+            .map(|(i, instruments)| {
+                EngineHandle::spawn_named(MultiEngine::new_with_instruments(instruments), format!("engine-shard-{}", i))
+            })
+            .collect();
+        Self { shards, shard_of }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Index of the shard that owns `instrument_id`, or `None` if the instrument is unknown.
+    pub fn shard_for(&self, instrument_id: InstrumentId) -> Option<usize> {
+        self.shard_of.get(&instrument_id).copied()
+    }
+
+    /// Submit an order to its instrument's shard.
+    pub async fn submit_order(&self, order: Order) -> Fills {
+        let shard = self.route(order.instrument_id)?;
+        self.shards[shard].submit_order(order).await
+    }
+
+    /// Cancel a resting order. The caller need not know its instrument: every shard is asked and the one holding
+    /// the order answers. Returns its instrument, or `None` if no shard had it.
+    pub async fn cancel_order(&self, order_id: OrderId) -> Option<InstrumentId> {
+        let mut canceled = None;
+        for shard in &self.shards {
+            if let Some(instrument_id) = shard.cancel_order(order_id).await {
+                canceled = Some(instrument_id);
+            }
+        }
+        canceled
+    }
+
+    /// Modify a resting order on the replacement's instrument (orders cannot move between instruments).
+    pub async fn modify_order(&self, order_id: OrderId, replacement: Order) -> Fills {
+        let shard = self.route(replacement.instrument_id)?;
+        self.shards[shard].modify_order(order_id, replacement).await
+    }
+
+    /// Top of book for one instrument, or `None` if the instrument is unknown.
+    pub async fn book_snapshot_for(&self, instrument_id: InstrumentId) -> Option<BookSnapshot> {
+        let shard = self.route(instrument_id).ok()?;
+        self.shards[shard]
+            .query(move |engine| engine.book_snapshot_for(instrument_id))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// One [`EngineSnapshot`] per shard, in shard order (each can be loaded into its own [`MultiEngine`]).
+    pub async fn snapshots(&self) -> Result<Vec<EngineSnapshot>, String> {
+        let mut snapshots = Vec::with_capacity(self.shards.len());
+        for shard in &self.shards {
+            snapshots.push(shard.query(|engine| engine.snapshot()).await?);
+        }
+        Ok(snapshots)
+    }
+
+    fn route(&self, instrument_id: InstrumentId) -> Result<usize, String> {
+        self.shard_for(instrument_id)
+            .ok_or_else(|| format!("Unknown instrument {}", instrument_id.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderType, Side, TimeInForce, TraderId};
+    use rust_decimal::Decimal;
+
+    fn order(id: u64, instrument: u64, side: Side) -> Order {
+        Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(instrument),
+            side,
+            order_type: OrderType::Limit,
+            quantity: Decimal::from(1),
+            price: Some(Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(id),
+        }
+    }
+
+    #[tokio::test]
+    async fn shards_match_instruments_independently_and_route_cancels() {
+        let engine = ShardedEngine::new(vec![(InstrumentId(1), None), (InstrumentId(2), None)], 2);
+        assert_eq!(engine.shard_count(), 2);
+        assert_ne!(engine.shard_for(InstrumentId(1)), engine.shard_for(InstrumentId(2)));
+
+        let (a, b) = tokio::join!(
+            engine.submit_order(order(1, 1, Side::Buy)),
+            engine.submit_order(order(2, 2, Side::Buy))
+        );
+        assert!(a.unwrap().0.is_empty() && b.unwrap().0.is_empty());
+        let (trades, _) = engine.submit_order(order(3, 1, Side::Sell)).await.unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].instrument_id, InstrumentId(1));
+
+        // Cancel finds the order on its shard; instrument 2's bid is the only one left.
+        assert_eq!(engine.cancel_order(OrderId(2)).await, Some(InstrumentId(2)));
+        assert_eq!(engine.cancel_order(OrderId(2)).await, None);
+        assert_eq!(engine.book_snapshot_for(InstrumentId(2)).await.unwrap().best_bid, None);
+        assert!(engine.modify_order(OrderId(9), order(10, 2, Side::Buy)).await.is_err());
+
+        assert_eq!(
+            engine.submit_order(order(4, 7, Side::Buy)).await.unwrap_err(),
+            "Unknown instrument 7"
+        );
+        assert_eq!(engine.snapshots().await.unwrap().len(), 2);
+    }
+}
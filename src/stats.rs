@@ -0,0 +1,115 @@
+//! Per-instrument session statistics (open, high, low, last, volume, VWAP) built from the engine's trades.
+//!
+//! Statistics cover every trade since the engine started, the snapshot it was restored from was taken, or the
+//! last [`crate::MultiEngine::reset_session_stats`]; reset them at the start of each trading day.
+
+use crate::execution::Trade;
+use crate::types::InstrumentId;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Trading statistics of one instrument for the current session. Prices are `None` until it trades.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InstrumentStats {
+    pub instrument_id: InstrumentId,
+    /// Price of the first trade of the session.
+    pub open: Option<Decimal>,
+    pub high: Option<Decimal>,
+    pub low: Option<Decimal>,
+    /// Price of the most recent trade.
+    pub last: Option<Decimal>,
+    /// Quantity of the most recent trade.
+    pub last_quantity: Option<Decimal>,
+    /// Total quantity traded.
+    pub volume: Decimal,
+    /// Sum of price × quantity over all trades.
+    pub notional: Decimal,
+    /// Volume-weighted average price (`notional / volume`).
+    pub vwap: Option<Decimal>,
+    pub trade_count: u64,
+    /// Engine sequence number of the most recent trade (0 if none).
+    pub seq: u64,
+}
+
+impl InstrumentStats {
+    /// Statistics of an instrument that has not traded this session.
+    pub fn empty(instrument_id: InstrumentId) -> Self {
+        Self {
+            instrument_id,
+            open: None,
+            high: None,
+            low: None,
+            last: None,
+            last_quantity: None,
+            volume: Decimal::ZERO,
+            notional: Decimal::ZERO,
+            vwap: None,
+            trade_count: 0,
+            seq: 0,
+        }
+    }
+
+    fn apply_trade(&mut self, trade: &Trade) {
+        let price = trade.price;
+        self.open.get_or_insert(price);
+        self.high = Some(self.high.map_or(price, |high| high.max(price)));
+        self.low = Some(self.low.map_or(price, |low| low.min(price)));
+        self.last = Some(price);
+        self.last_quantity = Some(trade.quantity);
+        self.volume += trade.quantity;
+        self.notional += price * trade.quantity;
+        self.vwap = (!self.volume.is_zero()).then(|| (self.notional / self.volume).normalize());
+        self.trade_count += 1;
+        self.seq = trade.seq;
+    }
+}
+
+/// Session statistics for every instrument that has traded.
+#[derive(Debug, Default)]
+pub struct StatsBook {
+    stats: HashMap<InstrumentId, InstrumentStats>,
+}
+
+impl StatsBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold `trades` (already sequenced) into their instruments' statistics.
+    pub fn record(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            self.stats
+                .entry(trade.instrument_id)
+                .or_insert_with(|| InstrumentStats::empty(trade.instrument_id))
+                .apply_trade(trade);
+        }
+    }
+
+    /// Statistics for `instrument_id`; empty if it has not traded.
+    pub fn get(&self, instrument_id: InstrumentId) -> InstrumentStats {
+        self.stats
+            .get(&instrument_id)
+            .cloned()
+            .unwrap_or_else(|| InstrumentStats::empty(instrument_id))
+    }
+
+    /// Forget one instrument's statistics.
+    pub fn remove(&mut self, instrument_id: InstrumentId) {
+        self.stats.remove(&instrument_id);
+    }
+
+    /// Start a new session for every instrument.
+    pub fn clear(&mut self) {
+        self.stats.clear();
+    }
+
+    /// Statistics of every instrument that has traded (for snapshots).
+    pub fn all(&self) -> Vec<InstrumentStats> {
+        self.stats.values().cloned().collect()
+    }
+
+    /// Replace all statistics (from a snapshot).
+    pub fn restore(&mut self, stats: Vec<InstrumentStats>) {
+        self.stats = stats.into_iter().map(|s| (s.instrument_id, s)).collect();
+    }
+}
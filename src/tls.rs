@@ -0,0 +1,108 @@
+//! Optional TLS termination (rustls) for the HTTP and FIX listeners.
+//!
+//! Each listener takes a PEM certificate chain and private key (see [`TlsPaths::from_env`]); without them it
+//! serves plaintext. HTTP is served with [`serve_tls`]; FIX with [`crate::fix::run_fix_acceptor_tls`].
+
+use axum::Router;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Certificate chain and private key files of one listener.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsPaths {
+    /// PEM certificate chain, leaf first.
+    pub cert_path: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1, or SEC1).
+    pub key_path: PathBuf,
+}
+
+impl TlsPaths {
+    /// Read the paths from `cert_var` and `key_var` (e.g. `TLS_CERT_PATH`, `TLS_KEY_PATH`). `None` when neither
+    /// is set; `Err` when only one is.
+    pub fn from_env(cert_var: &str, key_var: &str) -> Result<Option<Self>, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|s| !s.trim().is_empty());
+        match (var(cert_var), var(key_var)) {
+            (Some(cert), Some(key)) => Ok(Some(Self {
+                cert_path: cert.trim().into(),
+                key_path: key.trim().into(),
+            })),
+            (None, None) => Ok(None),
+            (Some(_), None) => Err(format!("{} is set but {} is not", cert_var, key_var)),
+            (None, Some(_)) => Err(format!("{} is set but {} is not", key_var, cert_var)),
+        }
+    }
+
+    /// Load the files into a server config.
+    pub fn load(&self) -> Result<Arc<ServerConfig>, String> {
+        let read = |path: &PathBuf| std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e));
+        server_config(&read(&self.cert_path)?, &read(&self.key_path)?)
+    }
+}
+
+/// Server config for a PEM certificate chain and private key: TLS 1.2 and 1.3, no client certificates.
+pub fn server_config(cert_pem: &[u8], key_pem: &[u8]) -> Result<Arc<ServerConfig>, String> {
+    let certs = CertificateDer::pem_slice_iter(cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid certificate PEM: {}", e))?;
+    if certs.is_empty() {
+        return Err("no certificate in certificate PEM".to_string());
+    }
+    let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(|e| format!("invalid private key PEM: {}", e))?;
+    let config = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| e.to_string())?;
+    Ok(Arc::new(config))
+}
+
+/// Serve `app` over TLS on `listener` until `shutdown` completes, then stop accepting connections and wait for
+/// requests in progress (as [`axum::serve`] with graceful shutdown does for plaintext).
+pub async fn serve_tls(
+    listener: std::net::TcpListener,
+    app: Router,
+    config: Arc<ServerConfig>,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let handle = axum_server::Handle::new();
+    let stopper = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        stopper.graceful_shutdown(None);
+    });
+    axum_server::from_tcp_rustls(listener, axum_server::tls_rustls::RustlsConfig::from_config(config))
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_pem_files_and_rejects_bad_ones() {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let paths = TlsPaths {
+            cert_path: fixtures.join("test_cert.pem"),
+            key_path: fixtures.join("test_key.pem"),
+        };
+        assert!(paths.load().is_ok());
+
+        let swapped = TlsPaths {
+            cert_path: paths.key_path.clone(),
+            key_path: paths.cert_path.clone(),
+        };
+        assert!(swapped.load().unwrap_err().contains("certificate"));
+        let missing = TlsPaths {
+            cert_path: fixtures.join("missing.pem"),
+            ..paths
+        };
+        assert!(missing.load().unwrap_err().contains("missing.pem"));
+    }
+}
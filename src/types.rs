@@ -26,7 +26,7 @@ pub struct InstrumentId(pub u64);
 pub struct TraderId(pub u64);
 
 /// Order side.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Side {
     Buy,
     Sell,
@@ -50,6 +50,35 @@ pub enum TimeInForce {
     FOK,
 }
 
+/// Market state (US-011, US-012), venue-wide or per instrument. When not Open, order submission is rejected
+/// (503 / FIX reject).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MarketState {
+    #[default]
+    Open,
+    Halted,
+    Closed,
+}
+
+impl MarketState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketState::Open => "Open",
+            MarketState::Halted => "Halted",
+            MarketState::Closed => "Closed",
+        }
+    }
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "Open" => Some(MarketState::Open),
+            "Halted" => Some(MarketState::Halted),
+            "Closed" => Some(MarketState::Closed),
+            _ => None,
+        }
+    }
+}
+
 /// Order lifecycle status in execution reports.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum OrderStatus {
@@ -67,6 +96,8 @@ pub enum ExecType {
     PartialFill,
     Fill,
     Canceled,
+    /// Cancel/replace accepted: the report carries both the original and the replacement order id.
+    Replaced,
     Rejected,
 }
 
@@ -97,8 +128,112 @@ impl Order {
     }
 }
 
-/// Minimal representation of a resting order for persistence/snapshot.
+/// A market maker's two-sided quote (see [`crate::MultiEngine::submit_quote`]). Each side rests as a GTC limit
+/// order under its own order id; a side with zero quantity is not quoted.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Quote {
+    /// Client quote id, used as the client order id of both sides.
+    pub quote_id: String,
+    pub trader_id: TraderId,
+    pub instrument_id: InstrumentId,
+    pub bid_order_id: OrderId,
+    pub bid_price: Decimal,
+    pub bid_quantity: Decimal,
+    pub ask_order_id: OrderId,
+    pub ask_price: Decimal,
+    pub ask_quantity: Decimal,
+    pub timestamp: u64,
+}
+
+impl Quote {
+    /// The quoted sides as orders, bid first; sides with zero quantity are left out.
+    pub fn orders(&self) -> Vec<Order> {
+        [
+            (Side::Buy, self.bid_order_id, self.bid_price, self.bid_quantity),
+            (Side::Sell, self.ask_order_id, self.ask_price, self.ask_quantity),
+        ]
+        .into_iter()
+        .filter(|(_, _, _, quantity)| !quantity.is_zero())
+        .map(|(side, order_id, price, quantity)| Order {
+            order_id,
+            client_order_id: self.quote_id.clone(),
+            instrument_id: self.instrument_id,
+            side,
+            order_type: OrderType::Limit,
+            quantity,
+            price: Some(price),
+            time_in_force: TimeInForce::GTC,
+            timestamp: self.timestamp,
+            trader_id: self.trader_id,
+        })
+        .collect()
+    }
+}
+
+/// One resting order in the full (L3) book view, for surveillance and debugging.
+/// `queue_position` is 0-based within the price level (0 = next to fill).
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BookOrder {
+    pub side: Side,
+    pub price: Decimal,
+    pub queue_position: usize,
+    pub order_id: OrderId,
+    pub remaining_quantity: Decimal,
+    pub trader_id: TraderId,
+}
+
+/// Where a resting order sits in the book: its price level (0 = best price on its side), its
+/// 0-based position within that level, and the resting quantity ahead of it at that level.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct QueuePosition {
+    pub level: usize,
+    pub position: usize,
+    pub quantity_ahead: Decimal,
+}
+
+/// Live state of one resting order, for order-status queries.
+/// `queue_position` is 0-based within its price level (0 = next to fill).
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RestingOrderView {
+    pub order_id: OrderId,
+    pub instrument_id: InstrumentId,
+    pub side: Side,
+    pub price: Decimal,
+    pub remaining_quantity: Decimal,
+    pub queue_position: usize,
+    /// Price level index on the order's side (0 = best).
+    pub level: usize,
+    /// Resting quantity ahead of this order at its price level.
+    pub quantity_ahead: Decimal,
+    pub trader_id: TraderId,
+}
+
+/// Engine-side status of an accepted order, whether still resting, filled, or canceled.
+/// The queue fields are present only while the order rests on the book.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OrderStatusView {
+    pub order_id: OrderId,
+    pub instrument_id: InstrumentId,
+    pub side: Side,
+    pub price: Option<Decimal>,
+    pub status: OrderStatus,
+    pub quantity: Decimal,
+    pub filled_quantity: Decimal,
+    /// Quantity still working on the book (zero once filled or canceled).
+    pub remaining_quantity: Decimal,
+    pub avg_price: Option<Decimal>,
+    pub trader_id: TraderId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity_ahead: Option<Decimal>,
+}
+
+/// A resting order for persistence/snapshot: remaining quantity plus the original order metadata.
+/// Snapshots written before the metadata fields existed load as GTC limits with a `restore-<id>` client id.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct RestingOrder {
     pub order_id: OrderId,
     pub instrument_id: InstrumentId,
@@ -106,4 +241,43 @@ pub struct RestingOrder {
     pub price: Decimal,
     pub quantity: Decimal,
     pub trader_id: TraderId,
+    #[serde(default)]
+    pub client_order_id: String,
+    #[serde(default = "default_resting_order_type")]
+    pub order_type: OrderType,
+    #[serde(default = "default_resting_time_in_force")]
+    pub time_in_force: TimeInForce,
+    #[serde(default)]
+    pub timestamp: u64,
+}
+
+fn default_resting_order_type() -> OrderType {
+    OrderType::Limit
+}
+
+fn default_resting_time_in_force() -> TimeInForce {
+    TimeInForce::GTC
+}
+
+impl RestingOrder {
+    /// Rebuild the order to re-add to a book, with the remaining quantity as its quantity.
+    pub fn to_order(&self) -> Order {
+        let client_order_id = if self.client_order_id.is_empty() {
+            format!("restore-{}", self.order_id.0)
+        } else {
+            self.client_order_id.clone()
+        };
+        Order {
+            order_id: self.order_id,
+            client_order_id,
+            instrument_id: self.instrument_id,
+            side: self.side,
+            order_type: self.order_type,
+            quantity: self.quantity,
+            price: Some(self.price),
+            time_in_force: self.time_in_force,
+            timestamp: self.timestamp,
+            trader_id: self.trader_id,
+        }
+    }
 }
@@ -0,0 +1,329 @@
+//! Venue-wide configuration (see [`VenueConfig`]): risk limits, order-entry rate limits, the dynamic price band,
+//! and the trading session, read and changed at runtime through `/admin/config`.
+//!
+//! Changes are JSON merge patches ([`VenueConfig::patched`]) validated as a whole before anything is applied:
+//! the engine takes the risk limits, band, and session, and the REST order-entry middleware the rate limits.
+
+use crate::risk::RiskLimits;
+use rust_decimal::Decimal;
+
+/// Typed venue configuration. `version` counts accepted changes (0 until the first one).
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VenueConfig {
+    #[serde(default)]
+    pub version: u64,
+    #[serde(default)]
+    pub risk: RiskLimits,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+    #[serde(default)]
+    pub bands: BandConfig,
+    #[serde(default)]
+    pub session: TradingSession,
+}
+
+/// Order-entry rate limits. `None` means unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimits {
+    /// Max order submits, cancels, and modifies per second per API key (all anonymous clients share one budget).
+    pub orders_per_second: Option<u32>,
+}
+
+/// Dynamic price band around each instrument's last trade, on top of any static band of the instrument.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BandConfig {
+    /// Reject limit prices more than this many percent away from the last trade price. Instruments that have
+    /// not traded this session are not checked.
+    pub max_deviation_pct: Option<Decimal>,
+}
+
+impl BandConfig {
+    /// The allowed `(low, high)` limit prices around `last`, if a band is set.
+    pub fn range(&self, last: Decimal) -> Option<(Decimal, Decimal)> {
+        let pct = self.max_deviation_pct?;
+        let width = last.abs() * pct / Decimal::ONE_HUNDRED;
+        Some(((last - width).normalize(), (last + width).normalize()))
+    }
+}
+
+/// Daily trading hours in UTC. New orders and replaces outside them are rejected as if the market were closed;
+/// cancels are still accepted. Unset means always open. When `close` is before `open`, the session spans
+/// midnight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TradingSession {
+    pub open: Option<SessionTime>,
+    pub close: Option<SessionTime>,
+}
+
+impl TradingSession {
+    /// Whether `time` falls in the session (`open` inclusive, `close` exclusive).
+    pub fn is_open_at(&self, time: SessionTime) -> bool {
+        match (self.open, self.close) {
+            (Some(open), Some(close)) if open <= close => open <= time && time < close,
+            (Some(open), Some(close)) => time >= open || time < close,
+            _ => true,
+        }
+    }
+
+    /// Whether the session is open at the current wall-clock time.
+    pub fn is_open_now(&self) -> bool {
+        self.is_open_at(SessionTime::now())
+    }
+
+    /// `Err` with the rejection reason when the session is closed at the current time.
+    pub fn check_open(&self) -> Result<(), String> {
+        match (self.open, self.close) {
+            (Some(open), Some(close)) if !self.is_open_now() => {
+                Err(format!("market not open: outside trading session {}-{} UTC", open, close))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Time of day in UTC, to the minute. Written as `"HH:MM"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SessionTime(u16);
+
+impl SessionTime {
+    /// `hour:minute`, or `None` past 23:59.
+    pub fn new(hour: u16, minute: u16) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self(hour * 60 + minute))
+    }
+
+    pub fn now() -> Self {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self(((secs / 60) % (24 * 60)) as u16)
+    }
+
+    /// Minutes since midnight.
+    pub fn minutes(&self) -> u16 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SessionTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl std::str::FromStr for SessionTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid time {:?}, expected HH:MM", s);
+        let (hour, minute) = s.trim().split_once(':').ok_or_else(invalid)?;
+        if hour.len() != 2 || minute.len() != 2 {
+            return Err(invalid());
+        }
+        let (hour, minute) = (hour.parse().map_err(|_| invalid())?, minute.parse().map_err(|_| invalid())?);
+        Self::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+impl serde::Serialize for SessionTime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SessionTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl VenueConfig {
+    /// Initial configuration: risk limits from the environment (see [`RiskLimits::from_env`]), nothing else set.
+    pub fn from_env() -> Self {
+        Self {
+            risk: RiskLimits::from_env(),
+            ..Self::default()
+        }
+    }
+
+    /// This configuration with the JSON merge patch (RFC 7396) `patch` applied: objects merge key by key and
+    /// `null` unsets a value. The result is validated and its version bumped; `version` in the patch, when
+    /// present, must equal the current version. Returns `Err((field, message))` naming the dotted field at
+    /// fault, and changes nothing.
+    pub fn patched(&self, patch: &serde_json::Value) -> Result<Self, (String, String)> {
+        let Some(fields) = patch.as_object() else {
+            return Err((String::new(), "config must be a JSON object".into()));
+        };
+        if let Some(expected) = fields.get("version") {
+            if expected.as_u64() != Some(self.version) {
+                return Err(("version".into(), format!("config version is {}, not {}", self.version, expected)));
+            }
+        }
+        let mut merged = serde_json::to_value(self).map_err(|e| (String::new(), e.to_string()))?;
+        merge_patch(&mut merged, patch);
+        merged["version"] = serde_json::Value::from(self.version + 1);
+        let config: Self = serde_path_to_error::deserialize(&merged).map_err(|e| (e.path().to_string(), e.inner().to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// `Err((field, message))` for values a well-formed config may still not have.
+    pub fn validate(&self) -> Result<(), (String, String)> {
+        let risk = [
+            ("risk.max_order_quantity", self.risk.max_order_quantity),
+            ("risk.max_order_notional", self.risk.max_order_notional),
+            ("risk.max_position", self.risk.max_position),
+            ("bands.max_deviation_pct", self.bands.max_deviation_pct),
+        ];
+        for (field, value) in risk {
+            if value.is_some_and(|v| v.is_sign_negative()) {
+                return Err((field.into(), format!("{} must not be negative", field)));
+            }
+        }
+        if self.rate_limits.orders_per_second == Some(0) {
+            return Err(("rate_limits.orders_per_second".into(), "rate_limits.orders_per_second must be positive".into()));
+        }
+        match (self.session.open, self.session.close) {
+            (Some(open), Some(close)) if open == close => {
+                Err(("session.close".into(), "session.close must differ from session.open".into()))
+            }
+            (Some(_), None) => Err(("session.close".into(), "session.close is required with session.open".into())),
+            (None, Some(_)) => Err(("session.open".into(), "session.open is required with session.close".into())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Fixed one-second windows of order-entry requests per client, for [`RateLimits::orders_per_second`].
+#[derive(Debug)]
+pub struct OrderRateLimiter {
+    start: std::time::Instant,
+    /// Client → (second since `start`, requests in that second).
+    windows: std::sync::Mutex<std::collections::HashMap<String, (u64, u32)>>,
+}
+
+impl Default for OrderRateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+            windows: std::sync::Mutex::new(std::collections::HashMap::new()),
+        }
+    }
+
+    /// Count a request from `client` at `now` and whether it is within `limit` per second (`None` = unlimited,
+    /// not counted).
+    pub fn allow(&self, client: &str, limit: Option<u32>, now: std::time::Instant) -> bool {
+        let Some(limit) = limit else { return true };
+        let second = now.saturating_duration_since(self.start).as_secs();
+        let mut windows = self.windows.lock().expect("lock");
+        let window = windows.entry(client.to_string()).or_insert((second, 0));
+        if window.0 != second {
+            *window = (second, 0);
+        }
+        window.1 += 1;
+        window.1 <= limit
+    }
+}
+
+/// Apply `patch` to `target` as a JSON merge patch.
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let Some(patch) = patch.as_object() else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let target = target.as_object_mut().expect("object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn patches_merge_validate_and_bump_the_version() {
+        let config = VenueConfig::default();
+        let patched = config
+            .patched(&json!({
+                "risk": { "max_order_quantity": 500 },
+                "rate_limits": { "orders_per_second": 20 },
+                "session": { "open": "09:30", "close": "16:00" }
+            }))
+            .unwrap();
+        assert_eq!(patched.version, 1);
+        assert_eq!(patched.risk.max_order_quantity, Some(Decimal::from(500)));
+        assert_eq!(patched.rate_limits.orders_per_second, Some(20));
+        assert_eq!(patched.session.open, SessionTime::new(9, 30));
+
+        // null unsets one value and leaves its siblings.
+        let cleared = patched
+            .patched(&json!({ "version": 1, "risk": { "max_order_quantity": null, "max_position": "5" } }))
+            .unwrap();
+        assert_eq!(cleared.version, 2);
+        assert_eq!(cleared.risk.max_order_quantity, None);
+        assert_eq!(cleared.risk.max_position, Some(Decimal::from(5)));
+        assert_eq!(cleared.rate_limits, patched.rate_limits);
+
+        let field = |patch: serde_json::Value| patched.patched(&patch).unwrap_err().0;
+        assert_eq!(field(json!({ "version": 0 })), "version");
+        assert_eq!(field(json!({ "risk": { "max_order_quantity": "lots" } })), "risk.max_order_quantity");
+        assert_eq!(field(json!({ "risk": { "max_position": -1 } })), "risk.max_position");
+        assert_eq!(field(json!({ "risk": { "max_qty": 1 } })), "risk.max_qty");
+        assert_eq!(field(json!({ "tick": 1 })), "tick");
+        assert_eq!(field(json!({ "rate_limits": { "orders_per_second": 0 } })), "rate_limits.orders_per_second");
+        assert_eq!(field(json!({ "session": { "open": "9:30" } })), "session.open");
+        assert_eq!(field(json!({ "session": { "close": null } })), "session.close");
+        assert_eq!(field(json!({ "session": { "close": "09:30" } })), "session.close");
+        assert!(patched.patched(&json!([1])).is_err());
+    }
+
+    #[test]
+    fn sessions_span_midnight_and_bands_scale_with_the_last_price() {
+        let at = |s: &str| s.parse::<SessionTime>().unwrap();
+        let day = TradingSession { open: Some(at("09:30")), close: Some(at("16:00")) };
+        assert!(day.is_open_at(at("09:30")) && day.is_open_at(at("15:59")));
+        assert!(!day.is_open_at(at("16:00")) && !day.is_open_at(at("09:29")));
+        let night = TradingSession { open: Some(at("22:00")), close: Some(at("02:00")) };
+        assert!(night.is_open_at(at("23:00")) && night.is_open_at(at("01:59")));
+        assert!(!night.is_open_at(at("12:00")));
+        assert!(TradingSession::default().check_open().is_ok());
+        assert_eq!(at("07:05").to_string(), "07:05");
+        assert!("24:00".parse::<SessionTime>().is_err());
+
+        let band = BandConfig { max_deviation_pct: Some(Decimal::from(10)) };
+        assert_eq!(band.range(Decimal::from(100)), Some((Decimal::from(90), Decimal::from(110))));
+        assert_eq!(BandConfig::default().range(Decimal::from(100)), None);
+    }
+
+    #[test]
+    fn rate_limiter_counts_per_client_per_second() {
+        let limiter = OrderRateLimiter::new();
+        let now = std::time::Instant::now();
+        assert!(limiter.allow("a", Some(2), now));
+        assert!(limiter.allow("a", Some(2), now));
+        assert!(!limiter.allow("a", Some(2), now));
+        assert!(limiter.allow("b", Some(2), now));
+        assert!(limiter.allow("a", None, now));
+        assert!(limiter.allow("a", Some(2), now + std::time::Duration::from_secs(1)));
+    }
+}
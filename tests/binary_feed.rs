@@ -0,0 +1,133 @@
+//! Binary market data feed integration tests. Subscribe over TCP and UDP and decode the SBE-style messages.
+
+use dire_matching_engine::api;
+use dire_matching_engine::binary_feed::{run_binary_feed_tcp, run_binary_feed_udp, BinaryFeed, FeedMessage};
+use dire_matching_engine::order_book::LevelAction;
+use dire_matching_engine::{
+    InstrumentId, MatchingEngine, MultiEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId,
+};
+use rust_decimal::Decimal;
+use std::io::Read;
+use std::net::{TcpStream, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn limit(id: u64, side: Side, quantity: i64, price: &str, trader: u64) -> Order {
+    Order {
+        order_id: OrderId(id),
+        client_order_id: id.to_string(),
+        instrument_id: InstrumentId(1),
+        side,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(quantity),
+        price: Some(price.parse().unwrap()),
+        time_in_force: TimeInForce::GTC,
+        timestamp: 1,
+        trader_id: TraderId(trader),
+    }
+}
+
+fn submit(engine: &Mutex<MultiEngine>, order: Order) {
+    engine.lock().unwrap().submit_order(order).unwrap();
+}
+
+/// Reads whole feed messages off a TCP stream.
+struct TcpSubscriber {
+    stream: TcpStream,
+    buf: Vec<u8>,
+}
+
+impl TcpSubscriber {
+    fn next(&mut self) -> FeedMessage {
+        loop {
+            if let Some((message, len)) = FeedMessage::decode(&self.buf).unwrap() {
+                self.buf.drain(..len);
+                return message;
+            }
+            let mut chunk = [0u8; 4096];
+            let n = self.stream.read(&mut chunk).expect("feed message");
+            assert!(n > 0, "feed closed");
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+#[test]
+fn binary_feed_tcp_sends_snapshot_then_sequenced_book_updates_and_trades() {
+    let state = api::create_app_state(InstrumentId(1));
+    let engine = state.engine.clone();
+    let feed = BinaryFeed::attach(&engine);
+    submit(&engine, limit(1, Side::Buy, 3, "99.5", 1));
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tcp_engine, tcp_feed) = (engine.clone(), feed.clone());
+    std::thread::spawn(move || run_binary_feed_tcp(listener, tcp_engine, tcp_feed));
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(3))).unwrap();
+    let mut subscriber = TcpSubscriber { stream, buf: Vec::new() };
+
+    let FeedMessage::BookSnapshot(snapshot) = subscriber.next() else {
+        panic!("expected a snapshot first");
+    };
+    assert_eq!((snapshot.feed_seq, snapshot.instrument_id), (1, InstrumentId(1)));
+    assert_eq!(snapshot.levels.len(), 1);
+    assert_eq!((snapshot.levels[0].side, snapshot.levels[0].price), (Side::Buy, "99.5".parse().unwrap()));
+
+    submit(&engine, limit(2, Side::Sell, 5, "101.25", 2));
+    let FeedMessage::BookUpdate(update) = subscriber.next() else {
+        panic!("expected a book update");
+    };
+    assert_eq!(update.feed_seq, 2);
+    assert!(update.engine_seq > snapshot.engine_seq);
+    assert_eq!((update.levels[0].side, update.levels[0].action), (Side::Sell, LevelAction::Added));
+    assert_eq!(update.levels[0].quantity, Decimal::from(5));
+
+    submit(&engine, limit(3, Side::Buy, 2, "101.25", 1));
+    let mut messages = [subscriber.next(), subscriber.next()];
+    messages.sort_by_key(FeedMessage::feed_seq);
+    assert_eq!(messages.iter().map(FeedMessage::feed_seq).collect::<Vec<_>>(), [3, 4]);
+    let trade = messages
+        .iter()
+        .find_map(|m| match m {
+            FeedMessage::Trade(trade) => Some(trade),
+            _ => None,
+        })
+        .expect("a trade");
+    assert_eq!((trade.price, trade.quantity, trade.aggressor_side), ("101.25".parse().unwrap(), Decimal::from(2), Side::Buy));
+    let book = messages
+        .iter()
+        .find_map(|m| match m {
+            FeedMessage::BookUpdate(book) => Some(book),
+            _ => None,
+        })
+        .expect("a book update");
+    assert_eq!((book.levels[0].action, book.levels[0].quantity), (LevelAction::Changed, Decimal::from(3)));
+
+    // Idle: a heartbeat carrying the last feed_seq.
+    assert_eq!(subscriber.next(), FeedMessage::Heartbeat { feed_seq: 4 });
+}
+
+#[test]
+fn binary_feed_udp_sends_one_message_per_datagram() {
+    let state = api::create_app_state(InstrumentId(1));
+    let engine: Arc<Mutex<MultiEngine>> = state.engine.clone();
+    let feed = BinaryFeed::attach(&engine);
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver.set_read_timeout(Some(Duration::from_secs(3))).unwrap();
+    let destination = receiver.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let udp_engine = engine.clone();
+    std::thread::spawn(move || run_binary_feed_udp(sender, destination, udp_engine, feed));
+    std::thread::sleep(Duration::from_millis(50));
+
+    submit(&engine, limit(1, Side::Sell, 4, "100", 2));
+    let mut datagram = [0u8; 1500];
+    let n = receiver.recv(&mut datagram).unwrap();
+    let (message, len) = FeedMessage::decode(&datagram[..n]).unwrap().unwrap();
+    assert_eq!(len, n);
+    let FeedMessage::BookUpdate(update) = message else {
+        panic!("expected a book update, got {:?}", message);
+    };
+    assert_eq!((update.feed_seq, update.levels.len(), update.levels[0].side), (1, 1, Side::Sell));
+}
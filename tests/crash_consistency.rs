@@ -0,0 +1,184 @@
+//! Crash consistency of persistence: a restart after the command log was cut at any byte, or after state and
+//! log files were corrupted, recovers the last good state and reports what it dropped; instrument changes and
+//! timers made since the last save are replayed from the command log along with the orders.
+
+use dire_matching_engine::api::{self, AppState};
+use dire_matching_engine::audit::InMemoryAuditSink;
+use dire_matching_engine::persistence::FilePersistence;
+use dire_matching_engine::{InstrumentId, MatchingEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+use rust_decimal::Decimal;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+fn start(persistence: &Arc<FilePersistence>) -> AppState {
+    api::create_app_state_with_sink_and_instruments(
+        vec![(InstrumentId(1), None)],
+        Arc::new(InMemoryAuditSink::new()),
+        Some(persistence.clone()),
+    )
+}
+
+/// Order `id` straight to the engine, which (unlike the REST handlers) does not save: it is only in the
+/// command log. Bids at different prices, so nothing trades.
+fn submit(state: &AppState, id: u64) {
+    let order = Order {
+        order_id: OrderId(id),
+        client_order_id: format!("c{}", id),
+        instrument_id: InstrumentId(1),
+        side: Side::Buy,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(1),
+        price: Some(Decimal::from(100 - id as i64)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: id,
+        trader_id: TraderId(1),
+    };
+    state.engine.lock().unwrap().submit_order(order).unwrap();
+}
+
+/// Ids of the orders resting after recovery.
+fn resting(state: &AppState) -> Vec<u64> {
+    let mut ids: Vec<_> = state.engine.lock().unwrap().book_orders(InstrumentId(1)).unwrap().iter().map(|o| o.order_id.0).collect();
+    ids.sort();
+    ids
+}
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dire_crash_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.to_path_buf().into_os_string();
+    path.push(suffix);
+    path.into()
+}
+
+#[test]
+fn a_command_log_cut_at_any_byte_recovers_every_entry_written_whole() {
+    let dir = fresh_dir("cut");
+    let path = dir.join("state.json");
+    let persistence = Arc::new(FilePersistence::new(&path));
+    let state = start(&persistence);
+    state.flush();
+    (1..=3).for_each(|id| submit(&state, id));
+    drop(state);
+    let (snapshot, wal) = (std::fs::read(&path).unwrap(), std::fs::read(with_suffix(&path, ".wal")).unwrap());
+
+    for cut in 0..=wal.len() {
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            std::fs::remove_file(entry.unwrap().path()).unwrap();
+        }
+        std::fs::write(&path, &snapshot).unwrap();
+        std::fs::write(with_suffix(&path, ".wal"), &wal[..cut]).unwrap();
+        let whole = wal[..cut].iter().filter(|&&b| b == b'\n').count();
+        let state = start(&persistence);
+        assert_eq!(resting(&state), (1..=whole as u64).collect::<Vec<_>>(), "cut at {}", cut);
+        let recovery = state.recovery().unwrap();
+        assert_eq!(recovery.replayed, whole, "cut at {}", cut);
+        assert_eq!(recovery.command_log_dropped, usize::from(cut > 0 && wal[cut - 1] != b'\n'), "cut at {}", cut);
+        assert!(recovery.rejected_snapshots.is_empty());
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn instrument_changes_and_timers_since_the_last_save_are_replayed_with_the_orders() {
+    use dire_matching_engine::InstrumentState;
+    let dir = fresh_dir("admin");
+    let path = dir.join("state.json");
+    let persistence = Arc::new(FilePersistence::new(&path));
+    let state = start(&persistence);
+    state.flush();
+    let order = Order {
+        order_id: OrderId(7),
+        client_order_id: "c7".to_string(),
+        instrument_id: InstrumentId(2),
+        side: Side::Sell,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(3),
+        price: Some(Decimal::new(105, 1)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: 7,
+        trader_id: TraderId(1),
+    };
+    // Per-instrument parts of the engine's snapshot, by instrument id.
+    let parts = |engine: &dire_matching_engine::MultiEngine| {
+        let snapshot = serde_json::to_value(engine.snapshot()).unwrap();
+        ["instruments", "books", "tick_sizes", "instrument_states", "scheduler"].map(|field| {
+            let mut part = snapshot[field].clone();
+            if let Some(by_instrument) = part.as_array_mut() {
+                by_instrument.sort_by_key(|entry| entry[0].as_u64());
+            }
+            part
+        })
+    };
+    let expected = {
+        let mut engine = state.engine.lock().unwrap();
+        engine.add_instrument_with_tick_size(InstrumentId(2), Some("NEW".to_string()), Decimal::new(5, 1)).unwrap();
+        engine.submit_order(order).unwrap();
+        engine.set_instrument_state(InstrumentId(1), InstrumentState::Suspended).unwrap();
+        engine.expire_order_at(OrderId(7), 1_000).unwrap();
+        parts(&engine)
+    };
+    // Crash: nothing was saved after the flush, so all of it is only in the command log.
+    drop(state);
+
+    let state = start(&persistence);
+    assert_eq!(state.recovery().unwrap().replayed, 4);
+    let mut engine = state.engine.lock().unwrap();
+    assert_eq!(parts(&engine), expected);
+    // The replayed timer still runs.
+    assert_eq!(engine.advance_time(1_000).len(), 1);
+    assert!(engine.book_orders(InstrumentId(2)).unwrap().is_empty());
+    drop(engine);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn corrupt_state_files_and_log_records_fall_back_to_the_last_good_state_and_are_reported() {
+    let dir = fresh_dir("corrupt");
+    let path = dir.join("state.json");
+    let persistence = Arc::new(FilePersistence::new(&path));
+    let wal = with_suffix(&path, ".wal");
+
+    // Order 1 is in a retained copy, 1 and 2 in the state file, and 3 and 4 only in the command log.
+    let state = start(&persistence);
+    submit(&state, 1);
+    state.flush();
+    let copy_seq = state.engine.lock().unwrap().last_seq();
+    persistence.archive(copy_seq, 1).unwrap();
+    submit(&state, 2);
+    state.flush();
+    (3..=4).for_each(|id| submit(&state, id));
+    drop(state);
+
+    // Order 4's entry no longer matches its checksum: order 3 is replayed, and 4 is moved aside.
+    let log = std::fs::read_to_string(&wal).unwrap();
+    let (first, second) = log.trim_end().split_once('\n').unwrap();
+    std::fs::write(&wal, format!("{}\n{}\n", first, second.replacen("\"order_id\":4", "\"order_id\":5", 1))).unwrap();
+    let state = start(&persistence);
+    assert_eq!(resting(&state), [1, 2, 3]);
+    let recovery = state.recovery().unwrap();
+    assert_eq!((recovery.replayed, recovery.command_log_dropped), (1, 1));
+    assert_eq!(recovery.snapshot_path.as_deref(), Some(path.to_str().unwrap()));
+    let moved = std::fs::read_to_string(with_suffix(&wal, ".corrupt")).unwrap();
+    assert!(moved.contains("\"order_id\":5") && moved.lines().count() == 1);
+    drop(state);
+
+    // A flipped bit in the state file: the retained copy loads instead, and the state file is reported.
+    let mut data = std::fs::read(&path).unwrap();
+    let middle = data.len() / 2;
+    data[middle] ^= 0x10;
+    std::fs::write(&path, &data).unwrap();
+    let state = start(&persistence);
+    assert_eq!(resting(&state), [1]);
+    let recovery = state.recovery().unwrap();
+    assert_eq!(recovery.rejected_snapshots.len(), 1);
+    assert_eq!(recovery.rejected_snapshots[0].path, path.display().to_string());
+    assert!(!recovery.rejected_snapshots[0].error.is_empty());
+    assert_eq!(recovery.snapshot_path, Some(format!("{}.snapshot-{}", path.display(), copy_seq)));
+    let _ = std::fs::remove_dir_all(&dir);
+}
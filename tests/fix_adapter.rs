@@ -3,9 +3,10 @@
 
 use dire_matching_engine::api;
 use dire_matching_engine::api::MarketState;
-use dire_matching_engine::fix::message::{parse_fix_message, FixWriter};
-use dire_matching_engine::fix::run_fix_acceptor;
-use dire_matching_engine::InstrumentId;
+use dire_matching_engine::fix::message::{parse_fix_message, FixVersion, FixWriter};
+use dire_matching_engine::fix::{run_fix_acceptor, FixInitiator, FixInitiatorConfig};
+use dire_matching_engine::{ExecType, InstrumentId, Order, OrderId, OrderStatus, OrderType, Side, TimeInForce, TraderId};
+use rust_decimal::Decimal;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::time::Duration;
@@ -79,6 +80,7 @@ fn fix_new_order_single_returns_execution_report() {
 
     let new_order = build_fix_message(&[
         (35, "D"),
+        (34, "2"),
         (11, "100"),
         (55, "1"),
         (54, "1"),
@@ -95,6 +97,7 @@ fn fix_new_order_single_returns_execution_report() {
     assert_eq!(msg.get(&35).map(|s| s.as_str()), Some("8"));
     assert_eq!(msg.get(&39).map(|s| s.as_str()), Some("0")); // OrdStatus New
     assert_eq!(msg.get(&150).map(|s| s.as_str()), Some("0")); // ExecType New
+    assert_eq!(msg.get(&5001).map(|s| s.as_str()), Some("1")); // engine sequence number
 }
 
 /// When market state is Halted, NewOrderSingle receives a FIX reject (39=8) with text "market not open".
@@ -120,6 +123,7 @@ fn fix_new_order_single_rejected_when_market_halted() {
 
     let new_order = build_fix_message(&[
         (35, "D"),
+        (34, "2"),
         (11, "101"),
         (55, "1"),
         (54, "1"),
@@ -138,3 +142,1248 @@ fn fix_new_order_single_rejected_when_market_halted() {
     assert_eq!(msg.get(&150).map(|s| s.as_str()), Some("8")); // ExecType Rejected
     assert!(msg.get(&58).map(|s| s.contains("market not open")).unwrap_or(false));
 }
+
+/// OrderCancelReplaceRequest answers with one ExecType=5 (Replaced) report carrying OrigClOrdID (41).
+#[test]
+fn fix_cancel_replace_returns_replaced_report_with_orig_cl_ord_id() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+    let logon = build_fix_message(&[
+        (35, "A"),
+        (34, "1"),
+        (49, "CLIENT"),
+        (52, "20250101-12:00:00"),
+        (56, "DIRED"),
+    ]);
+    stream.write_all(&logon).unwrap();
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf).unwrap();
+
+    let new_order = build_fix_message(&[
+        (35, "D"),
+        (34, "2"),
+        (11, "200"),
+        (55, "1"),
+        (54, "2"),
+        (38, "10"),
+        (40, "2"),
+        (44, "101"),
+        (59, "0"),
+    ]);
+    stream.write_all(&new_order).unwrap();
+    let _ = stream.read(&mut buf).unwrap();
+
+    let replace = build_fix_message(&[
+        (35, "G"),
+        (34, "3"),
+        (11, "201"),
+        (41, "200"),
+        (55, "1"),
+        (54, "2"),
+        (38, "5"),
+        (40, "2"),
+        (44, "102"),
+        (59, "0"),
+    ]);
+    stream.write_all(&replace).unwrap();
+
+    let n = stream.read(&mut buf).unwrap();
+    let (msg, _) = parse_fix_message(&buf[..n]).expect("parse ExecutionReport");
+    assert_eq!(msg.get(&35).map(|s| s.as_str()), Some("8"));
+    assert_eq!(msg.get(&150).map(|s| s.as_str()), Some("5")); // ExecType Replaced
+    assert_eq!(msg.get(&11).map(|s| s.as_str()), Some("201"));
+    assert_eq!(msg.get(&41).map(|s| s.as_str()), Some("200"));
+}
+
+#[test]
+fn fix_cancel_replace_ids_come_from_the_engine_and_differ_across_sessions() {
+    use dire_matching_engine::{MatchingEngine, VENUE_ORDER_ID_BASE};
+    let state = api::create_app_state(InstrumentId(1));
+    let engine = state.engine.clone();
+    let (port, _handle) = spawn_fix_acceptor_with_state(state);
+    let logon = |sender: &str, pending: &mut Vec<u8>| {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        stream.write_all(&build_fix_message(&[(35, "A"), (34, "1"), (49, sender), (56, "DIRED")])).unwrap();
+        assert_eq!(tag(&read_message(&mut stream, pending), 35), Some("A"));
+        stream
+    };
+    let order = |seq: &str, cl_ord_id: &str| {
+        build_fix_message(&[(35, "D"), (34, seq), (11, cl_ord_id), (55, "1"), (54, "1"), (38, "2"), (40, "2"), (44, "90")])
+    };
+    let replace = |seq: &str, cl_ord_id: &str, orig: &str| {
+        build_fix_message(&[(35, "G"), (34, seq), (11, cl_ord_id), (41, orig), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "91")])
+    };
+    let (mut pending_a, mut pending_b) = (Vec::new(), Vec::new());
+    let mut a = logon("CLIENT_A", &mut pending_a);
+    let mut b = logon("CLIENT_B", &mut pending_b);
+
+    a.write_all(&order("2", "300")).unwrap();
+    read_message(&mut a, &mut pending_a);
+    // A client order already has the first venue id: the allocator skips it.
+    let taken = VENUE_ORDER_ID_BASE.to_string();
+    a.write_all(&order("3", &taken)).unwrap();
+    assert_eq!(tag(&read_message(&mut a, &mut pending_a), 39), Some("0"));
+    b.write_all(&order("2", "400")).unwrap();
+    read_message(&mut b, &mut pending_b);
+
+    a.write_all(&replace("4", "301", "300")).unwrap();
+    let replaced_a = read_message(&mut a, &mut pending_a);
+    b.write_all(&replace("3", "401", "400")).unwrap();
+    let replaced_b = read_message(&mut b, &mut pending_b);
+    assert_eq!((tag(&replaced_a, 150), tag(&replaced_b, 150)), (Some("5"), Some("5")), "{:?}", tag(&replaced_b, 58));
+    let id_a = (VENUE_ORDER_ID_BASE + 1).to_string();
+    let id_b = (VENUE_ORDER_ID_BASE + 2).to_string();
+    assert_eq!((tag(&replaced_a, 37), tag(&replaced_b, 37)), (Some(id_a.as_str()), Some(id_b.as_str())));
+    let engine = engine.lock().unwrap();
+    for id in [VENUE_ORDER_ID_BASE, VENUE_ORDER_ID_BASE + 1, VENUE_ORDER_ID_BASE + 2] {
+        assert!(engine.get_order(OrderId(id)).is_some(), "order {} rests", id);
+    }
+}
+
+#[test]
+fn fix_new_order_single_rejects_duplicate_cl_ord_id() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+    let logon = build_fix_message(&[
+        (35, "A"),
+        (34, "1"),
+        (49, "CLIENT"),
+        (52, "20250101-12:00:00"),
+        (56, "DIRED"),
+    ]);
+    stream.write_all(&logon).unwrap();
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf).unwrap();
+
+    let new_order = |seq: &str| {
+        build_fix_message(&[
+            (35, "D"),
+            (34, seq),
+            (11, "300"),
+            (55, "1"),
+            (54, "1"),
+            (38, "10"),
+            (40, "2"),
+            (44, "99"),
+            (59, "0"),
+        ])
+    };
+    stream.write_all(&new_order("2")).unwrap();
+    let _ = stream.read(&mut buf).unwrap();
+
+    stream.write_all(&new_order("3")).unwrap();
+    let n = stream.read(&mut buf).unwrap();
+    let (msg, _) = parse_fix_message(&buf[..n]).expect("parse ExecutionReport");
+    assert_eq!(msg.get(&150).map(|s| s.as_str()), Some("8")); // ExecType Rejected
+    assert_eq!(msg.get(&11).map(|s| s.as_str()), Some("300"));
+    assert_eq!(msg.get(&58).map(|s| s.as_str()), Some("duplicate ClOrdID"));
+}
+
+/// Read messages until a MassQuoteAcknowledgement (35=b) arrives.
+fn read_mass_quote_ack(stream: &mut TcpStream) -> dire_matching_engine::fix::FixMessage {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        while let Some((msg, consumed)) = parse_fix_message(&buf) {
+            buf.drain(..consumed);
+            if msg.get(&35).map(|s| s.as_str()) == Some("b") {
+                return msg;
+            }
+        }
+        let n = stream.read(&mut chunk).unwrap();
+        assert!(n > 0, "connection closed before MassQuoteAcknowledgement");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+#[test]
+fn fix_mass_quote_replaces_quote_and_rejects_self_cross() {
+    let state = api::create_app_state(InstrumentId(1));
+    let engine = state.engine.clone();
+    let (port, _handle) = spawn_fix_acceptor_with_state(state);
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+
+    let logon = build_fix_message(&[
+        (35, "A"),
+        (34, "1"),
+        (49, "CLIENT"),
+        (52, "20250101-12:00:00"),
+        (56, "DIRED"),
+    ]);
+    stream.write_all(&logon).unwrap();
+    let mut buf = [0u8; 1024];
+    let _ = stream.read(&mut buf).unwrap();
+
+    let quote = |seq: &str, quote_id: &str, bid: &str, ask: &str| {
+        build_fix_message(&[
+            (35, "i"),
+            (34, seq),
+            (117, quote_id),
+            (296, "1"),
+            (295, "1"),
+            (55, "1"),
+            (132, bid),
+            (134, "5"),
+            (133, ask),
+            (135, "5"),
+            (1, "7"),
+        ])
+    };
+    stream.write_all(&quote("2", "Q1", "99", "101")).unwrap();
+    let ack = read_mass_quote_ack(&mut stream);
+    assert_eq!(ack.get(&117).map(|s| s.as_str()), Some("Q1"));
+    assert_eq!(ack.get(&297).map(|s| s.as_str()), Some("0")); // Accepted
+
+    stream.write_all(&quote("3", "Q2", "98", "102")).unwrap();
+    let ack = read_mass_quote_ack(&mut stream);
+    assert_eq!(ack.get(&297).map(|s| s.as_str()), Some("0"));
+    {
+        use dire_matching_engine::MatchingEngine;
+        let guard = engine.lock().unwrap();
+        assert_eq!(guard.book_orders(InstrumentId(1)).unwrap().len(), 2);
+        let snap = guard.book_snapshot_for(InstrumentId(1)).unwrap();
+        assert_eq!(snap.best_bid.map(|p| p.to_string()), Some("98".to_string()));
+    }
+
+    stream.write_all(&quote("4", "Q3", "102", "101")).unwrap();
+    let ack = read_mass_quote_ack(&mut stream);
+    assert_eq!(ack.get(&297).map(|s| s.as_str()), Some("5")); // Rejected
+    assert_eq!(ack.get(&58).map(|s| s.as_str()), Some("Quote bid 102 must be below ask 101"));
+}
+
+#[test]
+fn fix_over_tls_logs_on_and_refuses_plaintext() {
+    use rustls::pki_types::pem::PemObject;
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tls = dire_matching_engine::tls::TlsPaths {
+        cert_path: fixtures.join("test_cert.pem"),
+        key_path: fixtures.join("test_key.pem"),
+    }
+    .load()
+    .unwrap();
+    let state = api::create_app_state(InstrumentId(1));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (engine, market_state) = (state.engine.clone(), state.market_state.clone());
+    std::thread::spawn(move || dire_matching_engine::fix::run_fix_acceptor_tls(listener, engine, market_state, tls));
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots
+        .add(rustls::pki_types::CertificateDer::from_pem_file(fixtures.join("test_ca.pem")).unwrap())
+        .unwrap();
+    let client = rustls::ClientConfig::builder_with_provider(std::sync::Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let conn = rustls::ClientConnection::new(std::sync::Arc::new(client), "localhost".try_into().unwrap()).unwrap();
+    let tcp = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    tcp.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let mut stream = rustls::StreamOwned::new(conn, tcp);
+    let logon = build_fix_message(&[(35, "A"), (34, "1"), (49, "CLIENT"), (52, "20250101-12:00:00"), (56, "DIRED")]);
+    stream.write_all(&logon).unwrap();
+    stream.flush().unwrap();
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).unwrap();
+    let (msg, _) = parse_fix_message(&buf[..n]).expect("parse response");
+    assert_eq!(msg.get(&35).map(|s| s.as_str()), Some("A"));
+
+    // A plaintext logon is not a TLS handshake: the acceptor drops the connection without a FIX reply.
+    let mut plain = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    plain.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    plain.write_all(&logon).unwrap();
+    let mut reply = Vec::new();
+    let _ = plain.read_to_end(&mut reply);
+    assert!(parse_fix_message(&reply).is_none());
+}
+
+/// Read the next message, keeping any bytes after it in `pending`.
+fn read_message(stream: &mut TcpStream, pending: &mut Vec<u8>) -> dire_matching_engine::fix::FixMessage {
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some((msg, consumed)) = parse_fix_message(pending) {
+            pending.drain(..consumed);
+            return msg;
+        }
+        let n = stream.read(&mut chunk).unwrap();
+        assert!(n > 0, "connection closed");
+        pending.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// Connect and log on with ResetSeqNumFlag (141=Y): MsgSeqNums start at 1 even when the session existed before.
+fn logged_on_fix_stream(port: u16, pending: &mut Vec<u8>) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let logon = [(35, "A"), (34, "1"), (49, "CLIENT"), (52, "20250101-12:00:00"), (56, "DIRED"), (141, "Y")];
+    stream.write_all(&build_fix_message(&logon)).unwrap();
+    assert_eq!(read_message(&mut stream, pending).get(&35).map(|s| s.as_str()), Some("A"));
+    stream
+}
+
+fn tag(msg: &dire_matching_engine::fix::FixMessage, tag: u32) -> Option<&str> {
+    msg.get(&tag).map(|s| s.as_str())
+}
+
+#[test]
+fn fix_sequence_gap_sends_resend_request_and_processes_in_order() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let order = |seq: &str, cl_ord_id: &str, poss_dup: bool| {
+        let mut fields = vec![(35, "D"), (34, seq), (11, cl_ord_id), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "90")];
+        if poss_dup {
+            fields.push((43, "Y"));
+        }
+        build_fix_message(&fields)
+    };
+
+    // 34=3 skips 2: the acceptor asks for 2 onwards and holds 3.
+    stream.write_all(&order("3", "401", false)).unwrap();
+    let resend = read_message(&mut stream, &mut pending);
+    assert_eq!(tag(&resend, 35), Some("2"));
+    assert_eq!(tag(&resend, 7), Some("2"));
+    assert_eq!(tag(&resend, 16), Some("0"));
+
+    // The resent 2 fills the gap; 2 and then the held 3 are handled.
+    stream.write_all(&order("2", "400", true)).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 11), Some("400"));
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 11), Some("401"));
+
+    // A possible duplicate of 3 is ignored; 4 is next.
+    stream.write_all(&order("3", "401", true)).unwrap();
+    stream.write_all(&build_fix_message(&[(35, "1"), (34, "4"), (112, "ping")])).unwrap();
+    let heartbeat = read_message(&mut stream, &mut pending);
+    assert_eq!(tag(&heartbeat, 35), Some("0"));
+    assert_eq!(tag(&heartbeat, 112), Some("ping"));
+}
+
+#[test]
+fn fix_sequence_reset_moves_expected_msg_seq_num_and_too_low_logs_out() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+
+    // Reset mode ignores MsgSeqNum; gap fill follows it.
+    stream.write_all(&build_fix_message(&[(35, "4"), (34, "1"), (36, "10")])).unwrap();
+    stream.write_all(&build_fix_message(&[(35, "0"), (34, "10")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("0"));
+    stream.write_all(&build_fix_message(&[(35, "4"), (34, "11"), (123, "Y"), (36, "20")])).unwrap();
+    stream.write_all(&build_fix_message(&[(35, "0"), (34, "20")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("0"));
+
+    // Lowering the expected number is refused.
+    stream.write_all(&build_fix_message(&[(35, "4"), (34, "21"), (36, "5")])).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(tag(&reject, 35), Some("3"));
+    assert_eq!(tag(&reject, 45), Some("21"));
+    assert_eq!(tag(&reject, 373), Some("5"));
+
+    stream.write_all(&build_fix_message(&[(35, "0"), (34, "5")])).unwrap();
+    let logout = read_message(&mut stream, &mut pending);
+    assert_eq!(tag(&logout, 35), Some("5"));
+    assert_eq!(tag(&logout, 58), Some("MsgSeqNum too low, expecting 21 but received 5"));
+    let mut rest = Vec::new();
+    assert_eq!(stream.read_to_end(&mut rest).unwrap(), 0);
+
+    // A message without MsgSeqNum is not accepted either.
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    stream.write_all(&build_fix_message(&[(35, "0")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 58), Some("MsgSeqNum (34) missing"));
+}
+
+#[test]
+fn fix_resend_request_replays_application_messages_as_possible_duplicates() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let order = |seq: &str, cl_ord_id: &str| {
+        build_fix_message(&[(35, "D"), (34, seq), (11, cl_ord_id), (55, "1"), (54, "2"), (38, "1"), (40, "2"), (44, "110")])
+    };
+    stream.write_all(&order("2", "500")).unwrap();
+    let first = read_message(&mut stream, &mut pending);
+    stream.write_all(&build_fix_message(&[(35, "0"), (34, "3")])).unwrap();
+    read_message(&mut stream, &mut pending);
+    stream.write_all(&order("4", "501")).unwrap();
+    read_message(&mut stream, &mut pending);
+
+    stream.write_all(&build_fix_message(&[(35, "2"), (34, "5"), (7, "1"), (16, "0")])).unwrap();
+    // Logon (1) and Heartbeat (3) are gap filled; the execution reports (2, 4) are sent again.
+    let gap = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&gap, 35), tag(&gap, 34), tag(&gap, 123), tag(&gap, 36)), (Some("4"), Some("1"), Some("Y"), Some("2")));
+    let resent = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&resent, 35), tag(&resent, 34), tag(&resent, 11)), (Some("8"), Some("2"), Some("500")));
+    assert_eq!(tag(&resent, 43), Some("Y"));
+    assert_eq!(tag(&resent, 122), tag(&first, 52));
+    assert_eq!(tag(&resent, 17), tag(&first, 17));
+    let gap = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&gap, 35), tag(&gap, 34), tag(&gap, 36)), (Some("4"), Some("3"), Some("4")));
+    let resent = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&resent, 34), tag(&resent, 11), tag(&resent, 43)), (Some("4"), Some("501"), Some("Y")));
+
+    // The resend used no new numbers: the next reply is 5.
+    stream.write_all(&build_fix_message(&[(35, "0"), (34, "6")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 34), Some("5"));
+}
+
+#[test]
+fn fix_replies_are_addressed_with_the_logon_comp_ids_swapped() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let addressed = |msg: &dire_matching_engine::fix::FixMessage| (tag(msg, 49).map(str::to_string), tag(msg, 56).map(str::to_string));
+    let acme = (Some("VENUE".to_string()), Some("ACME".to_string()));
+
+    stream.write_all(&build_fix_message(&[(35, "A"), (34, "1"), (49, "ACME"), (56, "VENUE"), (141, "Y")])).unwrap();
+    let logon = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&logon, 35), addressed(&logon)), (Some("A"), acme.clone()));
+    let order = [(35, "D"), (34, "2"), (11, "600"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "90")];
+    stream.write_all(&build_fix_message(&order)).unwrap();
+    let report = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&report, 35), addressed(&report)), (Some("8"), acme.clone()));
+    stream.write_all(&build_fix_message(&[(35, "1"), (34, "3"), (112, "ping")])).unwrap();
+    let heartbeat = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&heartbeat, 35), addressed(&heartbeat)), (Some("0"), acme.clone()));
+    stream.write_all(&build_fix_message(&[(35, "ZZ"), (34, "4")])).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&reject, 35), addressed(&reject)), (Some("j"), acme.clone()));
+
+    // Resent messages and gap fills too.
+    stream.write_all(&build_fix_message(&[(35, "2"), (34, "5"), (7, "1"), (16, "0")])).unwrap();
+    for expected in ["4", "8", "4", "j"] {
+        let resent = read_message(&mut stream, &mut pending);
+        assert_eq!((tag(&resent, 35), addressed(&resent)), (Some(expected), acme.clone()));
+    }
+    stream.write_all(&build_fix_message(&[(35, "5"), (34, "6")])).unwrap();
+    let logout = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&logout, 35), addressed(&logout)), (Some("5"), acme));
+
+    // Before any Logon, the reply goes back to whoever sent the message.
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    stream.write_all(&build_fix_message(&[(35, "0"), (34, "1"), (49, "OTHER"), (56, "DESK")])).unwrap();
+    let logout = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&logout, 35), addressed(&logout)), (Some("5"), (Some("DESK".to_string()), Some("OTHER".to_string()))));
+}
+
+#[test]
+fn fix_logon_requires_credentials_or_allowed_sender_comp_id_and_binds_the_trader() {
+    use dire_matching_engine::auth::AuthConfig;
+    use dire_matching_engine::{AuthThrottlePolicy, MatchingEngine, OrderId};
+    let state = api::create_app_state(InstrumentId(1));
+    let engine = state.engine.clone();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let market_state = state.market_state.clone();
+    let auth = AuthConfig::from_keys("k7:trader:7,adm:admin,md:trader:8:view_market_data,k6:trader:6::2,far:trader:5")
+        .with_fix_sender_comp_ids("DESK9:9")
+        .with_key_allowed_cidrs("far=10.0.0.0/8")
+        .with_fix_allowed_cidrs("DESK9=127.0.0.1,REMOTE=10.0.0.0/8")
+        // Failed logons count per address, and every one here comes from 127.0.0.1.
+        .with_throttle_policy(AuthThrottlePolicy { max_failures: 20, ..Default::default() });
+    let acceptor_engine = engine.clone();
+    std::thread::spawn(move || {
+        dire_matching_engine::fix::run_fix_acceptor_with_auth(listener, acceptor_engine, market_state, None, auth)
+    });
+    let connect = || {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        stream
+    };
+    let logon = |sender: &str, credentials: &[(u32, &str)]| {
+        let mut fields = vec![(35, "A"), (34, "1"), (49, sender), (52, "20250101-12:00:00"), (56, "DIRED")];
+        fields.extend_from_slice(credentials);
+        build_fix_message(&fields)
+    };
+    let order = |seq: &str, cl_ord_id: &str, account: Option<&str>| {
+        let mut fields = vec![(35, "D"), (34, seq), (11, cl_ord_id), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "95")];
+        if let Some(account) = account {
+            fields.push((1, account));
+        }
+        build_fix_message(&fields)
+    };
+    let mut pending = Vec::new();
+
+    // Rejected logons and messages before Logon get a Logout with the reason, then the connection closes.
+    for (first, reason) in [
+        (logon("CLIENT", &[]), "SenderCompID CLIENT is not allowed without Username (553) and Password (554)"),
+        (logon("DESK9", &[(553, "u"), (554, "wrong")]), "invalid Password (554) for Username u"),
+        (logon("CLIENT", &[(553, "u"), (554, "adm")]), "API key is not bound to a trader"),
+        (logon("CLIENT", &[(553, "u"), (554, "far")]), "API key is not allowed from 127.0.0.1"),
+        (logon("REMOTE", &[(553, "u"), (554, "k7")]), "SenderCompID REMOTE is not allowed from 127.0.0.1"),
+        (order("1", "1", None), "first message must be Logon (A)"),
+    ] {
+        let mut stream = connect();
+        stream.write_all(&first).unwrap();
+        let logout = read_message(&mut stream, &mut pending);
+        assert_eq!((tag(&logout, 35), tag(&logout, 58)), (Some("5"), Some(reason)));
+        let mut rest = Vec::new();
+        assert_eq!(stream.read_to_end(&mut rest).unwrap(), 0);
+    }
+
+    // Credentials bind the session to the key's trader; Account (1) can't name another.
+    let mut stream = connect();
+    stream.write_all(&logon("CLIENT", &[(553, "u"), (554, "k7")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("A"));
+    stream.write_all(&order("2", "700", Some("8"))).unwrap();
+    let report = read_message(&mut stream, &mut pending);
+    assert_eq!(tag(&report, 39), Some("8"));
+    assert_eq!(tag(&report, 58), Some("Account (1) 8 does not match the session's trader 7"));
+    stream.write_all(&order("3", "701", None)).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("0"));
+
+    // An allowed SenderCompID logs on without credentials.
+    let mut stream = connect();
+    stream.write_all(&logon("DESK9", &[])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("A"));
+    stream.write_all(&order("2", "900", Some("9"))).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("0"));
+
+    // A key without submit_orders logs on (e.g. for market data) but its orders are refused.
+    let mut stream = connect();
+    stream.write_all(&logon("MDCLIENT", &[(553, "u"), (554, "md")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("A"));
+    stream.write_all(&order("2", "800", None)).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&reject, 35), tag(&reject, 380)), (Some("j"), Some("6")));
+    assert_eq!(tag(&reject, 58), Some("permission submit_orders required"));
+
+    // A key entitled to instrument 2 only: orders and market data for instrument 1 are refused.
+    let mut stream = connect();
+    stream.write_all(&logon("ENTCLIENT", &[(553, "u"), (554, "k6")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("A"));
+    stream.write_all(&order("2", "600", None)).unwrap();
+    let report = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&report, 39), tag(&report, 58)), (Some("8"), Some("not entitled to Symbol (55) 1")));
+    stream
+        .write_all(&build_fix_message(&[(35, "V"), (34, "3"), (262, "md1"), (263, "0"), (146, "1"), (55, "1")]))
+        .unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&reject, 35), tag(&reject, 281)), (Some("Y"), Some("3")));
+
+    let guard = engine.lock().unwrap();
+    assert_eq!(guard.order_status(OrderId(701)).map(|o| o.trader_id.0), Some(7));
+    assert_eq!(guard.order_status(OrderId(900)).map(|o| o.trader_id.0), Some(9));
+    assert!(guard.order_status(OrderId(600)).is_none());
+}
+
+#[test]
+fn fix_cancel_and_replace_failures_return_order_cancel_reject() {
+    let state = api::create_app_state(InstrumentId(1));
+    let market_state = state.market_state.clone();
+    let (port, _handle) = spawn_fix_acceptor_with_state(state);
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let order = |seq: &str, cl_ord_id: &str| {
+        build_fix_message(&[(35, "D"), (34, seq), (11, cl_ord_id), (55, "1"), (54, "1"), (38, "2"), (40, "2"), (44, "90")])
+    };
+    let cancel = |seq: &str, cl_ord_id: &str, orig: &str| {
+        build_fix_message(&[(35, "F"), (34, seq), (11, cl_ord_id), (41, orig), (55, "1"), (54, "1")])
+    };
+    let replace = |seq: &str, cl_ord_id: &str, orig: &str| {
+        build_fix_message(&[(35, "G"), (34, seq), (11, cl_ord_id), (41, orig), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "91")])
+    };
+    let reject_fields = |msg: &dire_matching_engine::fix::FixMessage| {
+        [35, 37, 11, 41, 39, 434, 102].map(|t| tag(msg, t).unwrap_or("-").to_string()).join(" ")
+    };
+
+    stream.write_all(&order("2", "600")).unwrap();
+    read_message(&mut stream, &mut pending);
+
+    stream.write_all(&cancel("3", "c1", "nope")).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(reject_fields(&reject), "9 NONE c1 nope 8 1 1");
+    assert_eq!(tag(&reject, 58), Some("unknown OrigClOrdID"));
+
+    stream.write_all(&replace("4", "600", "600")).unwrap();
+    assert_eq!(reject_fields(&read_message(&mut stream, &mut pending)), "9 600 600 600 0 2 6");
+
+    stream.write_all(&cancel("5", "c2", "600")).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("4"));
+    stream.write_all(&cancel("6", "c3", "600")).unwrap();
+    assert_eq!(reject_fields(&read_message(&mut stream, &mut pending)), "9 600 c3 600 4 1 0");
+    stream.write_all(&replace("7", "601", "600")).unwrap();
+    assert_eq!(reject_fields(&read_message(&mut stream, &mut pending)), "9 600 601 600 4 2 0");
+
+    stream.write_all(&order("8", "610")).unwrap();
+    read_message(&mut stream, &mut pending);
+    *market_state.lock().unwrap() = MarketState::Halted;
+    stream.write_all(&replace("9", "611", "610")).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(reject_fields(&reject), "9 610 611 610 0 2 2");
+    assert_eq!(tag(&reject, 58), Some("market not open"));
+}
+
+#[test]
+fn fix_logons_orders_and_expiries_are_audited_with_the_sender_comp_id_as_actor() {
+    use dire_matching_engine::audit::{AuditSource, InMemoryAuditSink};
+    use dire_matching_engine::auth::AuthConfig;
+    use dire_matching_engine::fix::{run_fix_acceptor_with_sessions, FixSessionStore};
+    use std::sync::Arc;
+    let audit = Arc::new(InMemoryAuditSink::new());
+    let state = api::create_app_state_with_sink(InstrumentId(1), audit.clone());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (engine, market_state, sink) = (state.engine.clone(), state.market_state.clone(), state.audit_sink());
+    std::thread::spawn(move || {
+        let (auth, sessions) = (AuthConfig::from_keys("k7:trader:7"), Arc::new(FixSessionStore::in_memory()));
+        run_fix_acceptor_with_sessions(listener, engine, market_state, None, auth, sessions, sink)
+    });
+    let logon = |password: &str| {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let logon = [(35, "A"), (34, "1"), (49, "DESK7"), (56, "DIRED"), (553, "u"), (554, password)];
+        stream.write_all(&build_fix_message(&logon)).unwrap();
+        stream
+    };
+    let mut pending = Vec::new();
+
+    let mut stream = logon("wrong");
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("5"));
+    assert_eq!(stream.read_to_end(&mut Vec::new()).unwrap(), 0);
+
+    let mut stream = logon("k7");
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("A"));
+    for message in [
+        build_fix_message(&[(35, "D"), (34, "2"), (11, "700"), (55, "1"), (54, "1"), (38, "2"), (40, "2"), (44, "90")]),
+        build_fix_message(&[(35, "G"), (34, "3"), (11, "701"), (41, "700"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "91")]),
+        build_fix_message(&[(35, "F"), (34, "4"), (11, "c1"), (41, "701"), (55, "1"), (54, "1")]),
+        build_fix_message(&[(35, "D"), (34, "5"), (11, "702"), (55, "1"), (54, "2"), (38, "1"), (40, "2"), (44, "95"), (59, "3")]),
+    ] {
+        stream.write_all(&message).unwrap();
+        read_message(&mut stream, &mut pending);
+    }
+    stream.write_all(&build_fix_message(&[(35, "5"), (34, "6")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("5"));
+    assert_eq!(stream.read_to_end(&mut Vec::new()).unwrap(), 0);
+
+    let events = audit.events();
+    let trail: Vec<_> = events.iter().map(|e| (e.actor.as_str(), e.action.as_str(), e.outcome.as_str())).collect();
+    assert_eq!(
+        trail,
+        [
+            ("DESK7", "fix_logon", "rejected"),
+            ("DESK7", "fix_logon", "success"),
+            ("DESK7", "order_submit", "success"),
+            ("DESK7", "order_modify", "success"),
+            ("DESK7", "order_cancel", "success"),
+            // The IOC's unfilled quantity expires inside the engine, before the submit is audited.
+            ("engine", "order_expired", "success"),
+            ("DESK7", "order_submit", "success"),
+            ("DESK7", "fix_logout", "success"),
+        ]
+    );
+    assert_eq!(events[0].resource.as_ref().unwrap()["reason"], "invalid Password (554) for Username u");
+    assert_eq!(events[1].resource.as_ref().unwrap()["trader_id"], 7);
+    assert_eq!(events[2].resource.as_ref().unwrap()["cl_ord_id"], "700");
+    // Session events are correlated by session and MsgSeqNum; the engine's own carry no request.
+    let context = |i: usize| (events[i].source, events[i].request_id.as_deref(), events[i].client_ip.map(|ip| ip.to_string()));
+    assert_eq!(context(2), (AuditSource::Fix, Some("DESK7:DIRED:2"), Some("127.0.0.1".to_string())));
+    assert_eq!(context(4).1, Some("DESK7:DIRED:4"));
+    assert_eq!(context(5), (AuditSource::Internal, None, None));
+    let modified = events[3].resource.as_ref().unwrap();
+    assert_eq!((&modified["before"]["price"], &modified["before"]["quantity"]), (&serde_json::json!("90"), &serde_json::json!("2")));
+    assert_eq!((&modified["after"]["price"], &modified["after"]["quantity"]), (&serde_json::json!("91"), &serde_json::json!("1")));
+    assert_eq!(events[5].resource.as_ref().unwrap()["quantity"], "1");
+}
+
+/// Read messages until one of `msg_type` arrives; its fields in order (repeating groups included).
+fn read_fields_of(stream: &mut TcpStream, pending: &mut Vec<u8>, msg_type: &str) -> Vec<(u32, String)> {
+    loop {
+        let mut chunk = [0u8; 1024];
+        while parse_fix_message(pending).is_none() {
+            let n = stream.read(&mut chunk).unwrap();
+            assert!(n > 0, "connection closed");
+            pending.extend_from_slice(&chunk[..n]);
+        }
+        let (msg, consumed) = parse_fix_message(pending).unwrap();
+        let raw: Vec<u8> = pending.drain(..consumed).collect();
+        if tag(&msg, 35) == Some(msg_type) {
+            return String::from_utf8(raw)
+                .unwrap()
+                .split('\x01')
+                .filter_map(|field| field.split_once('='))
+                .map(|(t, v)| (t.parse().unwrap(), v.to_string()))
+                .filter(|(t, _)| [262, 268, 279, 269, 55, 270, 271, 281, 41, 530, 531, 532, 533, 534, 535].contains(t))
+                .collect();
+        }
+    }
+}
+
+fn fields(pairs: &[(u32, &str)]) -> Vec<(u32, String)> {
+    pairs.iter().map(|(t, v)| (*t, v.to_string())).collect()
+}
+
+#[test]
+fn fix_market_data_request_sends_snapshot_and_incremental_refreshes() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    // Buys and sells come from different accounts: self-trades do not match.
+    let order = |seq: &str, cl_ord_id: &str, side: &str, qty: &str, price: &str| {
+        build_fix_message(&[(35, "D"), (34, seq), (11, cl_ord_id), (1, side), (55, "1"), (54, side), (38, qty), (40, "2"), (44, price)])
+    };
+    let md_request = |seq: &str, req_id: &str, kind: &str, symbol: &str| {
+        build_fix_message(&[(35, "V"), (34, seq), (262, req_id), (263, kind), (264, "0"), (267, "2"), (269, "0"), (269, "1"), (146, "1"), (55, symbol)])
+    };
+
+    stream.write_all(&order("2", "800", "1", "2", "99")).unwrap();
+    read_message(&mut stream, &mut pending);
+    stream.write_all(&md_request("3", "md1", "1", "1")).unwrap();
+    assert_eq!(
+        read_fields_of(&mut stream, &mut pending, "W"),
+        fields(&[(262, "md1"), (55, "1"), (268, "1"), (269, "0"), (270, "99"), (271, "2")])
+    );
+
+    // A trade changes the bid level: one incremental with the level change and the trade.
+    stream.write_all(&order("4", "801", "2", "1", "99")).unwrap();
+    assert_eq!(
+        read_fields_of(&mut stream, &mut pending, "X"),
+        fields(&[
+            (262, "md1"),
+            (268, "2"),
+            (279, "1"),
+            (269, "0"),
+            (55, "1"),
+            (270, "99"),
+            (271, "1"),
+            (279, "0"),
+            (269, "2"),
+            (55, "1"),
+            (270, "99"),
+            (271, "1"),
+        ])
+    );
+
+    stream.write_all(&md_request("5", "md1", "1", "1")).unwrap();
+    assert_eq!(read_fields_of(&mut stream, &mut pending, "Y"), fields(&[(262, "md1"), (281, "1")]));
+    stream.write_all(&md_request("6", "md2", "1", "42")).unwrap();
+    assert_eq!(read_fields_of(&mut stream, &mut pending, "Y"), fields(&[(262, "md2"), (281, "0")]));
+
+    // After unsubscribing, book changes send nothing more.
+    stream.write_all(&md_request("7", "md1", "2", "1")).unwrap();
+    stream.write_all(&order("8", "802", "1", "1", "98")).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    stream.write_all(&build_fix_message(&[(35, "1"), (34, "9"), (112, "done")])).unwrap();
+    loop {
+        let msg = read_message(&mut stream, &mut pending);
+        assert_ne!(tag(&msg, 35), Some("X"));
+        if tag(&msg, 35) == Some("0") {
+            break;
+        }
+    }
+}
+
+#[test]
+fn fix_routes_orders_by_registered_symbol_and_rejects_unknown_symbols() {
+    use dire_matching_engine::MatchingEngine;
+    let state = api::create_app_state_with_instruments(vec![
+        (InstrumentId(1), Some("BTC-USD".into())),
+        (InstrumentId(2), Some("ETH-USD".into())),
+    ]);
+    let engine = state.engine.clone();
+    let (port, _handle) = spawn_fix_acceptor_with_state(state);
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let order = |seq: &str, cl_ord_id: &str, symbol: &str| {
+        build_fix_message(&[(35, "D"), (34, seq), (11, cl_ord_id), (55, symbol), (54, "1"), (38, "1"), (40, "2"), (44, "50")])
+    };
+
+    stream.write_all(&order("2", "900", "ETH-USD")).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("0"));
+    let bid = |id: u64| engine.lock().unwrap().book_snapshot_for(InstrumentId(id)).unwrap().best_bid;
+    assert_eq!(bid(2).map(|p| p.to_string()), Some("50".to_string()));
+    assert_eq!(bid(1), None);
+
+    // An instrument id still addresses the instrument.
+    stream.write_all(&order("3", "901", "1")).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("0"));
+    assert_eq!(bid(1).map(|p| p.to_string()), Some("50".to_string()));
+
+    stream.write_all(&order("4", "902", "XRP-USD")).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(tag(&reject, 39), Some("8"));
+    assert_eq!(tag(&reject, 58), Some("unknown Symbol (55) XRP-USD"));
+
+    let request = |seq: &str, req_id: &str, symbol: &str| {
+        build_fix_message(&[(35, "V"), (34, seq), (262, req_id), (263, "0"), (146, "1"), (55, symbol)])
+    };
+    stream.write_all(&request("5", "md1", "ETH-USD")).unwrap();
+    assert_eq!(
+        read_fields_of(&mut stream, &mut pending, "W"),
+        fields(&[(262, "md1"), (55, "ETH-USD"), (268, "1"), (269, "0"), (270, "50"), (271, "1")])
+    );
+    stream.write_all(&request("6", "md2", "XRP-USD")).unwrap();
+    assert_eq!(read_fields_of(&mut stream, &mut pending, "Y"), fields(&[(262, "md2"), (281, "0")]));
+}
+
+#[test]
+fn fix_order_mass_cancel_request_cancels_by_symbol_side_or_all() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let order = |seq: &str, cl_ord_id: &str, side: &str, price: &str| {
+        build_fix_message(&[(35, "D"), (34, seq), (11, cl_ord_id), (55, "1"), (54, side), (38, "1"), (40, "2"), (44, price)])
+    };
+    for (seq, cl_ord_id, side, price) in [("2", "700", "1", "99"), ("3", "701", "2", "101"), ("4", "702", "1", "98")] {
+        stream.write_all(&order(seq, cl_ord_id, side, price)).unwrap();
+        read_message(&mut stream, &mut pending);
+    }
+    let mass_cancel = |seq: &str, fields: &[(u32, &str)]| {
+        let mut all = vec![(35, "q"), (34, seq), (11, "mc")];
+        all.extend_from_slice(fields);
+        build_fix_message(&all)
+    };
+
+    stream.write_all(&mass_cancel("5", &[(530, "1"), (55, "1"), (54, "1")])).unwrap();
+    assert_eq!(
+        read_fields_of(&mut stream, &mut pending, "r"),
+        fields(&[(530, "1"), (531, "1"), (533, "2"), (534, "2"), (41, "700"), (535, "700"), (41, "702"), (535, "702"), (55, "1")])
+    );
+    stream.write_all(&mass_cancel("6", &[(530, "7")])).unwrap();
+    assert_eq!(
+        read_fields_of(&mut stream, &mut pending, "r"),
+        fields(&[(530, "7"), (531, "7"), (533, "1"), (534, "1"), (41, "701"), (535, "701")])
+    );
+    stream.write_all(&mass_cancel("7", &[(530, "7")])).unwrap();
+    assert_eq!(read_fields_of(&mut stream, &mut pending, "r"), fields(&[(530, "7"), (531, "7"), (533, "0")]));
+
+    stream.write_all(&mass_cancel("8", &[(530, "3")])).unwrap();
+    assert_eq!(read_fields_of(&mut stream, &mut pending, "r"), fields(&[(530, "3"), (531, "0"), (532, "0"), (533, "0")]));
+    stream.write_all(&mass_cancel("9", &[(530, "1"), (55, "XRP-USD")])).unwrap();
+    assert_eq!(
+        read_fields_of(&mut stream, &mut pending, "r"),
+        fields(&[(530, "1"), (531, "0"), (532, "1"), (533, "0"), (55, "XRP-USD")])
+    );
+}
+
+#[test]
+fn fix_session_resumes_sequence_numbers_and_orders_after_reconnect_and_restart() {
+    use dire_matching_engine::auth::AuthConfig;
+    use dire_matching_engine::fix::{run_fix_acceptor_with_sessions, FixSessionStore};
+    use std::sync::Arc;
+    let path = std::env::temp_dir().join(format!("fix_session_resume_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let state = api::create_app_state(InstrumentId(1));
+    let start_acceptor = |sessions: FixSessionStore| {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (engine, market_state, audit) = (state.engine.clone(), state.market_state.clone(), state.audit_sink());
+        std::thread::spawn(move || {
+            let auth = AuthConfig::disabled();
+            run_fix_acceptor_with_sessions(listener, engine, market_state, None, auth, Arc::new(sessions), audit)
+        });
+        port
+    };
+    let logon = |port: u16, seq: &str, pending: &mut Vec<u8>| {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        stream
+            .write_all(&build_fix_message(&[(35, "A"), (34, seq), (49, "CLIENT"), (56, "DIRED")]))
+            .unwrap();
+        let reply = read_message(&mut stream, pending);
+        (stream, reply)
+    };
+    let log_out = |stream: &mut TcpStream, seq: &str, pending: &mut Vec<u8>| {
+        stream.write_all(&build_fix_message(&[(35, "5"), (34, seq)])).unwrap();
+        assert_eq!(tag(&read_message(stream, pending), 35), Some("5"));
+        assert_eq!(stream.read_to_end(&mut Vec::new()).unwrap(), 0);
+    };
+    let mut pending = Vec::new();
+
+    let port = start_acceptor(FixSessionStore::open(&path).unwrap());
+    let (mut stream, reply) = logon(port, "1", &mut pending);
+    assert_eq!(tag(&reply, 34), Some("1"));
+    let order = build_fix_message(&[(35, "D"), (34, "2"), (11, "600"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "90")]);
+    stream.write_all(&order).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 34), Some("2"));
+    log_out(&mut stream, "3", &mut pending);
+
+    // Reconnecting continues the numbers: starting again at 1 is too low.
+    let (mut stream, reply) = logon(port, "1", &mut pending);
+    assert_eq!(tag(&reply, 58), Some("MsgSeqNum too low, expecting 4 but received 1"));
+    assert_eq!(stream.read_to_end(&mut Vec::new()).unwrap(), 0);
+
+    let (mut stream, reply) = logon(port, "4", &mut pending);
+    assert_eq!((tag(&reply, 35), tag(&reply, 34)), (Some("A"), Some("4")));
+    let (_, duplicate) = logon(port, "5", &mut Vec::new());
+    assert_eq!(tag(&duplicate, 58), Some("session CLIENT:DIRED is already logged on"));
+    // Messages sent before the disconnect can still be resent.
+    stream.write_all(&build_fix_message(&[(35, "2"), (34, "5"), (7, "2"), (16, "2")])).unwrap();
+    let resent = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&resent, 34), tag(&resent, 43), tag(&resent, 11)), (Some("2"), Some("Y"), Some("600")));
+    // And the order entered on the earlier connection can be canceled by its ClOrdID.
+    stream.write_all(&build_fix_message(&[(35, "F"), (34, "6"), (11, "601"), (41, "600")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("4"));
+    log_out(&mut stream, "7", &mut pending);
+
+    // A restarted acceptor picks the session up from the file.
+    let port = start_acceptor(FixSessionStore::open(&path).unwrap());
+    let (mut stream, reply) = logon(port, "8", &mut pending);
+    assert_eq!((tag(&reply, 35), tag(&reply, 34)), (Some("A"), Some("7")));
+    let reused = build_fix_message(&[(35, "D"), (34, "9"), (11, "600"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "90")]);
+    stream.write_all(&reused).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 58), Some("duplicate ClOrdID"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fix_session_state_stays_with_its_trader_and_orders_with_their_owner() {
+    use dire_matching_engine::auth::AuthConfig;
+    use dire_matching_engine::fix::{run_fix_acceptor_with_sessions, FixSessionState, FixSessionStore};
+    use dire_matching_engine::{MatchingEngine, OrderId, TraderId};
+    use std::sync::Arc;
+    let state = api::create_app_state(InstrumentId(1));
+    let sessions = Arc::new(FixSessionStore::in_memory());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (engine, market_state, audit) = (state.engine.clone(), state.market_state.clone(), state.audit_sink());
+    let store = sessions.clone();
+    std::thread::spawn(move || {
+        let auth = AuthConfig::from_keys("k7:trader:7,k8:trader:8,ops:trader:9:submit_orders+cancel_any");
+        run_fix_acceptor_with_sessions(listener, engine, market_state, None, auth, store, audit)
+    });
+    let logon = |sender: &str, key: &str, pending: &mut Vec<u8>| {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let fields = [(35, "A"), (34, "1"), (49, sender), (56, "DIRED"), (553, "u"), (554, key), (141, "Y")];
+        stream.write_all(&build_fix_message(&fields)).unwrap();
+        let reply = read_message(&mut stream, pending);
+        (stream, reply)
+    };
+    let cancel = |seq: &str| build_fix_message(&[(35, "F"), (34, seq), (11, "c1"), (41, "700")]);
+    let replace = |seq: &str| {
+        build_fix_message(&[(35, "G"), (34, seq), (11, "r1"), (41, "700"), (55, "1"), (54, "1"), (38, "2"), (40, "2"), (44, "90")])
+    };
+    let mut pending = Vec::new();
+
+    let (mut stream, reply) = logon("DESK7", "k7", &mut pending);
+    assert_eq!(tag(&reply, 35), Some("A"));
+    let order = build_fix_message(&[(35, "D"), (34, "2"), (11, "700"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "90")]);
+    stream.write_all(&order).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("0"));
+    stream.write_all(&build_fix_message(&[(35, "5"), (34, "3")])).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 35), Some("5"));
+    assert_eq!(stream.read_to_end(&mut Vec::new()).unwrap(), 0);
+
+    // Another key giving trader 7's CompIDs does not get the session, or the ClOrdIDs that name its orders.
+    let (mut stream, reply) = logon("DESK7", "k8", &mut pending);
+    assert_eq!((tag(&reply, 35), tag(&reply, 58)), (Some("5"), Some("session DESK7:DIRED is bound to another trader")));
+    assert_eq!(stream.read_to_end(&mut Vec::new()).unwrap(), 0);
+
+    // Even a stored session that does name another trader's order can't cancel or replace it.
+    let mut foreign = FixSessionState { trader_id: Some(TraderId(8)), ..FixSessionState::default() };
+    foreign.cl_ord_to_order_id.insert("700".into(), OrderId(700));
+    sessions.put("DESK8:DIRED", foreign.clone()).unwrap();
+    let (mut stream, reply) = logon("DESK8", "k8", &mut pending);
+    assert_eq!(tag(&reply, 35), Some("A"));
+    for (request, seq) in [(cancel("2"), "2"), (replace("3"), "3")] {
+        stream.write_all(&request).unwrap();
+        let reject = read_message(&mut stream, &mut pending);
+        assert_eq!((tag(&reject, 35), tag(&reject, 58)), (Some("9"), Some("order belongs to another trader")), "MsgSeqNum {}", seq);
+    }
+    assert!(state.engine.lock().unwrap().get_order(OrderId(700)).is_some());
+
+    // A key with cancel_any may, as over REST.
+    sessions.put("OPS:DIRED", FixSessionState { trader_id: Some(TraderId(9)), ..foreign }).unwrap();
+    let (mut stream, _) = logon("OPS", "ops", &mut pending);
+    stream.write_all(&cancel("2")).unwrap();
+    assert_eq!(tag(&read_message(&mut stream, &mut pending), 39), Some("4"));
+    assert!(state.engine.lock().unwrap().get_order(OrderId(700)).is_none());
+}
+
+#[test]
+fn fix_malformed_and_unsupported_messages_get_reject_or_business_message_reject() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+
+    stream.write_all(&build_fix_message(&[(35, "R"), (34, "2"), (131, "qr1")])).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        [35, 45, 372, 380, 58].map(|t| tag(&reject, t)),
+        [Some("j"), Some("2"), Some("R"), Some("3"), Some("unsupported MsgType R")]
+    );
+
+    let order = |seq: &str, side: &str, qty: Option<&str>| {
+        let mut fields = vec![(35, "D"), (34, seq), (11, "500"), (55, "1"), (54, side), (40, "2"), (44, "100")];
+        fields.extend(qty.map(|q| (38, q)));
+        build_fix_message(&fields)
+    };
+    stream.write_all(&order("3", "1", None)).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        [35, 45, 371, 372, 373, 58].map(|t| tag(&reject, t)),
+        [Some("3"), Some("3"), Some("38"), Some("D"), Some("1"), Some("missing OrderQty (38)")]
+    );
+    stream.write_all(&order("4", "9", Some("1"))).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!([45, 371, 373].map(|t| tag(&reject, t)), [Some("4"), Some("54"), Some("5")]);
+
+    stream.write_all(&build_fix_message(&[(34, "5"), (49, "CLIENT")])).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        [35, 45, 371, 373, 58].map(|t| tag(&reject, t)),
+        [Some("3"), Some("5"), Some("35"), Some("1"), Some("MsgType (35) missing")]
+    );
+    // Rejected messages still count: the session goes on in sequence.
+    stream.write_all(&build_fix_message(&[(35, "1"), (34, "6"), (112, "still-here")])).unwrap();
+    let heartbeat = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&heartbeat, 35), tag(&heartbeat, 112)), (Some("0"), Some("still-here")));
+}
+
+#[test]
+fn fix_execution_reports_echo_the_order_attributes() {
+    let state = api::create_app_state_with_instruments(vec![(InstrumentId(1), Some("BTC-USD".into()))]);
+    let (port, _handle) = spawn_fix_acceptor_with_state(state);
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let echoed = |msg: &dire_matching_engine::fix::FixMessage| [11, 1, 55, 54, 40, 44, 59, 39].map(|t| tag(msg, t).map(str::to_string));
+    let expect = |values: [Option<&str>; 8]| values.map(|v| v.map(str::to_string));
+    let transact_time = |msg: &dire_matching_engine::fix::FixMessage| {
+        let time = tag(msg, 60).expect("TransactTime (60)").as_bytes().to_vec();
+        time.len() == 21 && time[8] == b'-' && time[17] == b'.' && time[18..].iter().all(u8::is_ascii_digit)
+    };
+
+    let sell = [(35, "D"), (34, "2"), (11, "700"), (1, "7"), (55, "BTC-USD"), (54, "2"), (38, "2"), (40, "2"), (44, "100"), (59, "1")];
+    stream.write_all(&build_fix_message(&sell)).unwrap();
+    let report = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        echoed(&report),
+        expect([Some("700"), Some("7"), Some("BTC-USD"), Some("2"), Some("2"), Some("100"), Some("1"), Some("0")])
+    );
+    assert!(transact_time(&report), "TransactTime with milliseconds: {:?}", tag(&report, 60));
+
+    // A market IOC buy from another account: the resting sell's fill is reported under its own ClOrdID.
+    let buy = [(35, "D"), (34, "3"), (11, "701"), (1, "8"), (55, "BTC-USD"), (54, "1"), (38, "1"), (40, "1"), (59, "3")];
+    stream.write_all(&build_fix_message(&buy)).unwrap();
+    let resting = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        echoed(&resting),
+        expect([Some("700"), Some("7"), Some("BTC-USD"), Some("2"), Some("2"), Some("100"), Some("1"), Some("1")])
+    );
+    let aggressor = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        echoed(&aggressor),
+        expect([Some("701"), Some("8"), Some("BTC-USD"), Some("1"), Some("1"), None, Some("3"), Some("2")])
+    );
+    assert!(transact_time(&aggressor));
+
+    stream.write_all(&build_fix_message(&[(35, "F"), (34, "4"), (11, "702"), (41, "700"), (55, "BTC-USD"), (54, "2")])).unwrap();
+    let canceled = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        echoed(&canceled),
+        expect([Some("700"), Some("7"), Some("BTC-USD"), Some("2"), Some("2"), Some("100"), Some("1"), Some("4")])
+    );
+    assert!(transact_time(&canceled));
+
+    // A rejected order echoes what the request carried.
+    let unknown = [(35, "D"), (34, "5"), (11, "703"), (55, "XRP-USD"), (54, "1"), (38, "1"), (40, "2"), (44, "5")];
+    stream.write_all(&build_fix_message(&unknown)).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        echoed(&reject),
+        expect([Some("703"), None, Some("XRP-USD"), Some("1"), Some("2"), Some("5"), None, Some("8")])
+    );
+}
+
+#[test]
+fn fix_fills_are_reported_as_trades_with_partially_filled_or_filled_status() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let order = |seq: &str, cl_ord_id: &str, account: &str, side: &str, qty: &str, tif: &str| {
+        build_fix_message(&[
+            (35, "D"), (34, seq), (11, cl_ord_id), (1, account), (55, "1"), (54, side), (38, qty), (40, "2"), (44, "100"), (59, tif),
+        ])
+    };
+    stream.write_all(&order("2", "800", "7", "2", "3", "1")).unwrap();
+    stream.write_all(&order("3", "801", "8", "1", "1", "3")).unwrap();
+    stream.write_all(&order("4", "802", "8", "1", "5", "3")).unwrap();
+    let mut next = || {
+        let msg = read_message(&mut stream, &mut pending);
+        [11, 150, 39, 14, 151].map(|t| tag(&msg, t).unwrap().to_string())
+    };
+    let expect = |values: [&str; 5]| values.map(str::to_string);
+
+    assert_eq!(next(), expect(["800", "0", "0", "0", "3"]));
+    // A fill that leaves quantity open: ExecType Trade (F), OrdStatus PartiallyFilled (1), LeavesQty 2.
+    assert_eq!(next(), expect(["800", "F", "1", "1", "2"]));
+    assert_eq!(next(), expect(["801", "F", "2", "1", "0"]));
+    // The rest of the sell fills it (2); the larger buy is left partially filled (1).
+    assert_eq!(next(), expect(["800", "F", "2", "2", "0"]));
+    assert_eq!(next(), expect(["802", "F", "1", "2", "3"]));
+}
+
+#[test]
+fn fix_split_and_pipelined_messages_are_framed_and_garbage_is_skipped() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    let test_request = |seq: &str, id: &str| build_fix_message(&[(35, "1"), (34, seq), (112, id)]);
+    let mut corrupt = test_request("2", "corrupt");
+    let n = corrupt.len();
+    corrupt[n - 2] = if corrupt[n - 2] == b'0' { b'1' } else { b'0' };
+
+    // Garbage, a message with a bad CheckSum, and the first half of a good one.
+    let good = test_request("2", "split");
+    let (head, tail) = good.split_at(good.len() / 2);
+    stream.write_all(&[b"\x01garbage".as_slice(), &corrupt, head].concat()).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    // The rest of it, pipelined with the next message.
+    stream.write_all(&[tail, &test_request("3", "pipelined")].concat()).unwrap();
+
+    for id in ["split", "pipelined"] {
+        let heartbeat = read_message(&mut stream, &mut pending);
+        assert_eq!((tag(&heartbeat, 35), tag(&heartbeat, 112)), (Some("0"), Some(id)));
+    }
+}
+
+#[test]
+fn fix_message_with_valid_checksum_but_unparsable_field_gets_reject() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut pending = Vec::new();
+    let mut stream = logged_on_fix_stream(port, &mut pending);
+    // BodyLength and CheckSum are right, so the message is intact but its third field has no tag number.
+    let body = "35=1\x0134=2\x01X=1\x01112=bad\x01";
+    let head = format!("8=FIX.4.4\x019={}\x01{}", body.len(), body);
+    let checksum = head.bytes().map(u32::from).sum::<u32>() % 256;
+    stream.write_all(format!("{}10={:03}\x01", head, checksum).as_bytes()).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        [35, 45, 372, 373, 58].map(|t| tag(&reject, t)),
+        [Some("3"), Some("2"), Some("1"), Some("0"), Some("invalid tag number X")]
+    );
+
+    stream.write_all(&build_fix_message(&[(35, "1"), (34, "3"), (112, "next")])).unwrap();
+    let heartbeat = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&heartbeat, 35), tag(&heartbeat, 112)), (Some("0"), Some("next")));
+}
+
+fn build_fixt_message(fields: &[(u32, &str)]) -> Vec<u8> {
+    let mut w = FixWriter::with_version(FixVersion::Fix50Sp2);
+    for (tag, value) in fields {
+        w.set(*tag, *value);
+    }
+    let mut out = Vec::new();
+    w.write(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn fix_fixt_logon_with_default_appl_ver_id_negotiates_fix50sp2() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let connect = || {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        stream
+    };
+    let logon = |appl_ver_id: Option<&str>| {
+        let mut fields = vec![(35, "A"), (34, "1"), (49, "FIXT-CLIENT"), (56, "DIRED"), (141, "Y")];
+        fields.extend(appl_ver_id.map(|id| (1137, id)));
+        build_fixt_message(&fields)
+    };
+
+    let mut pending = Vec::new();
+    let mut stream = connect();
+    stream.write_all(&logon(None)).unwrap();
+    let logout = read_message(&mut stream, &mut pending);
+    assert_eq!((tag(&logout, 35), tag(&logout, 58)), (Some("5"), Some("missing DefaultApplVerID (1137)")));
+
+    let mut pending = Vec::new();
+    let mut stream = connect();
+    stream.write_all(&logon(Some("9"))).unwrap();
+    let reply = read_message(&mut stream, &mut pending);
+    assert_eq!([8, 35, 1137].map(|t| tag(&reply, t)), [Some("FIXT.1.1"), Some("A"), Some("9")]);
+
+    let order = [(35, "D"), (34, "2"), (11, "600"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "100")];
+    stream.write_all(&build_fixt_message(&order)).unwrap();
+    let report = read_message(&mut stream, &mut pending);
+    assert_eq!([8, 35, 11, 39].map(|t| tag(&report, t)), [Some("FIXT.1.1"), Some("8"), Some("600"), Some("0")]);
+
+    let fix44_order = [(35, "D"), (34, "3"), (1128, "7"), (11, "601"), (55, "1"), (54, "1"), (38, "1"), (40, "2"), (44, "99")];
+    stream.write_all(&build_fixt_message(&fix44_order)).unwrap();
+    let reject = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        [8, 35, 45, 371, 373].map(|t| tag(&reject, t)),
+        [Some("FIXT.1.1"), Some("3"), Some("3"), Some("1128"), Some("18")]
+    );
+
+    // A FIX.4.4 message on the FIXT.1.1 session ends it.
+    stream.write_all(&build_fix_message(&[(35, "1"), (34, "4"), (112, "t")])).unwrap();
+    let logout = read_message(&mut stream, &mut pending);
+    assert_eq!(
+        (tag(&logout, 35), tag(&logout, 58)),
+        (Some("5"), Some("BeginString (8) FIX.4.4 does not match the session's FIXT.1.1"))
+    );
+}
+
+fn limit_order(cl_ord_id: &str, side: Side, quantity: i64, price: i64, trader: u64) -> Order {
+    Order {
+        order_id: OrderId(0),
+        client_order_id: cl_ord_id.to_string(),
+        instrument_id: InstrumentId(1),
+        side,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(quantity),
+        price: Some(Decimal::from(price)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: 0,
+        trader_id: TraderId(trader),
+    }
+}
+
+#[test]
+fn fix_initiator_logs_on_sends_orders_and_reads_execution_reports() {
+    let (port, _handle) = spawn_fix_acceptor();
+    let mut seller = FixInitiator::connect(("127.0.0.1", port), FixInitiatorConfig::new("SELLER", "DIRED")).unwrap();
+    seller.logon().unwrap();
+    assert!(seller.logged_on());
+    let mut config = FixInitiatorConfig::new("BUYER", "DIRED");
+    config.version = FixVersion::Fix50Sp2;
+    let mut buyer = FixInitiator::connect(("127.0.0.1", port), config).unwrap();
+    buyer.logon().unwrap();
+
+    // A second connection for a logged-on session is refused.
+    let mut again = FixInitiator::connect(("127.0.0.1", port), FixInitiatorConfig::new("SELLER", "DIRED")).unwrap();
+    assert_eq!(again.logon().unwrap_err(), "logon refused: session SELLER:DIRED is already logged on");
+
+    seller.send_order(&limit_order("700", Side::Sell, 5, 100, 2), "1").unwrap();
+    let new = seller.next_execution_report().unwrap();
+    assert_eq!((new.cl_ord_id.as_str(), new.exec_type, new.ord_status), ("700", ExecType::New, OrderStatus::New));
+    assert_eq!((new.side, new.leaves_qty), (Some(Side::Sell), Decimal::from(5)));
+
+    // A buy for another account on the same session: both sides' fills come back, each under its ClOrdID.
+    seller.send_order(&limit_order("701", Side::Buy, 2, 100, 1), "1").unwrap();
+    let mut fills = [seller.next_execution_report().unwrap(), seller.next_execution_report().unwrap()];
+    fills.sort_by(|a, b| a.cl_ord_id.cmp(&b.cl_ord_id));
+    let [passive, aggressor] = fills;
+    assert_eq!((passive.cl_ord_id.as_str(), passive.exec_type), ("700", ExecType::PartialFill));
+    assert_eq!((passive.cum_qty, passive.leaves_qty), (Decimal::from(2), Decimal::from(3)));
+    assert_eq!((aggressor.cl_ord_id.as_str(), aggressor.exec_type, aggressor.ord_status), ("701", ExecType::Fill, OrderStatus::Filled));
+    assert_eq!((aggressor.last_qty, aggressor.last_px), (Some(Decimal::from(2)), Some(Decimal::from(100))));
+
+    // Over FIXT.1.1 alike.
+    buyer.send_order(&limit_order("800", Side::Buy, 3, 100, 1), "1").unwrap();
+    let fill = buyer.next_execution_report().unwrap();
+    assert_eq!((fill.cl_ord_id.as_str(), fill.exec_type, fill.leaves_qty), ("800", ExecType::Fill, Decimal::ZERO));
+
+    // Rejected orders come back as a rejected report, or as an error for a session Reject.
+    buyer.send_order(&limit_order("801", Side::Buy, 1, 99, 1), "XRP-USD").unwrap();
+    let reject = buyer.next_execution_report().unwrap();
+    assert_eq!((reject.exec_type, reject.text.as_deref()), (ExecType::Rejected, Some("unknown Symbol (55) XRP-USD")));
+    buyer.send_order(&limit_order("not-numeric", Side::Buy, 1, 99, 1), "1").unwrap();
+    assert_eq!(buyer.next_execution_report().unwrap_err(), "Reject (3): invalid ClOrdID (11): must be numeric");
+
+    seller.logout().unwrap();
+    assert!(!seller.logged_on());
+    assert_eq!(seller.send_order(&limit_order("702", Side::Sell, 1, 100, 2), "1").unwrap_err(), "not logged on");
+    buyer.logout().unwrap();
+}
@@ -0,0 +1,157 @@
+//! Order-by-order feed integration tests. Rebuild the book from the feed over TCP and compare it with the engine's.
+
+use dire_matching_engine::api;
+use dire_matching_engine::order_feed::{run_order_feed_tcp, OrderBookReplica, OrderFeed, OrderFeedEvent, OrderFeedMessage};
+use dire_matching_engine::{
+    InstrumentId, MatchingEngine, MultiEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId,
+};
+use rust_decimal::Decimal;
+use std::io::Read;
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn limit(id: u64, side: Side, quantity: i64, price: &str, trader: u64) -> Order {
+    Order {
+        order_id: OrderId(id),
+        client_order_id: id.to_string(),
+        instrument_id: InstrumentId(1),
+        side,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(quantity),
+        price: Some(price.parse().unwrap()),
+        time_in_force: TimeInForce::GTC,
+        timestamp: 1,
+        trader_id: TraderId(trader),
+    }
+}
+
+fn submit(engine: &Mutex<MultiEngine>, order: Order) {
+    engine.lock().unwrap().submit_order(order).unwrap();
+}
+
+fn modify(engine: &Mutex<MultiEngine>, order_id: u64, replacement: Order) {
+    engine.lock().unwrap().modify_order(OrderId(order_id), &replacement).unwrap();
+}
+
+/// Reads whole feed messages off a TCP stream into a replica.
+struct Subscriber {
+    stream: TcpStream,
+    buf: Vec<u8>,
+    replica: OrderBookReplica,
+    last_seq: u64,
+}
+
+impl Subscriber {
+    fn next(&mut self) -> OrderFeedMessage {
+        loop {
+            if let Some((message, len)) = OrderFeedMessage::decode(&self.buf).unwrap() {
+                self.buf.drain(..len);
+                self.replica.apply(&message).unwrap();
+                return message;
+            }
+            let mut chunk = [0u8; 4096];
+            let n = self.stream.read(&mut chunk).expect("feed message");
+            assert!(n > 0, "feed closed");
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Read through `seq`, checking the numbering is gap-free; returns the messages.
+    fn read_to(&mut self, seq: u64) -> Vec<OrderFeedMessage> {
+        let mut out = Vec::new();
+        while self.last_seq < seq {
+            let message = self.next();
+            if message.event == OrderFeedEvent::Heartbeat {
+                continue;
+            }
+            assert_eq!(message.seq, self.last_seq + 1, "{:?}", message);
+            self.last_seq = message.seq;
+            out.push(message);
+        }
+        out
+    }
+
+    fn assert_matches(&self, engine: &Mutex<MultiEngine>) {
+        let book: Vec<_> = engine.lock().unwrap().book_orders(InstrumentId(1)).unwrap();
+        let book: Vec<_> = book.iter().map(|o| (o.order_id, o.side, o.price, o.remaining_quantity)).collect();
+        let replica: Vec<_> = self.replica.orders(InstrumentId(1)).iter().map(|o| (o.order_id, o.side, o.price, o.quantity)).collect();
+        assert_eq!(replica, book);
+    }
+}
+
+#[test]
+fn order_feed_rebuilds_the_book_order_by_order() {
+    let state = api::create_app_state(InstrumentId(1));
+    let engine = state.engine.clone();
+    let feed = OrderFeed::attach(&engine);
+    submit(&engine, limit(1, Side::Buy, 3, "99.5", 1));
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tcp_engine, tcp_feed) = (engine.clone(), feed.clone());
+    std::thread::spawn(move || run_order_feed_tcp(listener, tcp_engine, tcp_feed));
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(3))).unwrap();
+    let mut subscriber = Subscriber {
+        stream,
+        buf: Vec::new(),
+        replica: OrderBookReplica::new(),
+        last_seq: 0,
+    };
+
+    // Snapshot: Reset, then the resting order, numbered with the last seq.
+    let reset = subscriber.next();
+    assert_eq!((reset.seq, reset.event), (1, OrderFeedEvent::Reset));
+    assert!(matches!(subscriber.next().event, OrderFeedEvent::Add { order_id: OrderId(1), .. }));
+    subscriber.last_seq = 1;
+    subscriber.assert_matches(&engine);
+
+    submit(&engine, limit(2, Side::Buy, 2, "99.5", 2));
+    submit(&engine, limit(3, Side::Sell, 5, "101", 3));
+    let added = subscriber.read_to(3);
+    assert!(added.windows(2).all(|w| w[0].engine_seq < w[1].engine_seq));
+    subscriber.assert_matches(&engine);
+
+    // A sell through the bids: 1 fills, 2 partly; nothing of the aggressor rests.
+    submit(&engine, limit(4, Side::Sell, 4, "99.5", 3));
+    let executed = subscriber.read_to(5);
+    let fills: Vec<_> = executed
+        .iter()
+        .map(|m| match &m.event {
+            OrderFeedEvent::Execute { order_id, quantity, .. } => (order_id.0, *quantity),
+            other => panic!("expected an Execute, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(fills, [(1, Decimal::from(3)), (2, Decimal::from(1))]);
+    subscriber.assert_matches(&engine);
+
+    // Down in place keeps priority; a new price goes to the back.
+    submit(&engine, limit(5, Side::Sell, 1, "101", 5));
+    subscriber.read_to(6);
+    modify(&engine, 3, limit(6, Side::Sell, 4, "101", 3));
+    let replaced = subscriber.read_to(7);
+    assert!(matches!(replaced[0].event, OrderFeedEvent::Replace { orig_order_id: OrderId(3), order_id: OrderId(6), keeps_priority: true, .. }));
+    modify(&engine, 2, limit(7, Side::Buy, 2, "99", 2));
+    let replaced = subscriber.read_to(8);
+    assert!(matches!(replaced[0].event, OrderFeedEvent::Replace { orig_order_id: OrderId(2), keeps_priority: false, .. }));
+    subscriber.assert_matches(&engine);
+
+    // A replacement that crosses trades as a new order: the original is deleted, the resting side executes.
+    modify(&engine, 7, limit(8, Side::Buy, 6, "101", 2));
+    let crossed = subscriber.read_to(11);
+    assert_eq!(crossed[0].event, OrderFeedEvent::Delete { order_id: OrderId(7) });
+    assert!(matches!(crossed[1].event, OrderFeedEvent::Execute { order_id: OrderId(6), .. }));
+    assert!(matches!(crossed[2].event, OrderFeedEvent::Execute { order_id: OrderId(5), .. }));
+    subscriber.read_to(12);
+    subscriber.assert_matches(&engine);
+
+    assert!(engine.lock().unwrap().cancel_order(OrderId(8)).is_some());
+    assert_eq!(subscriber.read_to(13)[0].event, OrderFeedEvent::Delete { order_id: OrderId(8) });
+    subscriber.assert_matches(&engine);
+    assert_eq!(feed.resets(), 0);
+
+    // Idle: a heartbeat carrying the last seq.
+    let heartbeat = subscriber.next();
+    assert_eq!((heartbeat.seq, heartbeat.event), (13, OrderFeedEvent::Heartbeat));
+}
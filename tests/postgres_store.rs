@@ -0,0 +1,163 @@
+//! PostgreSQL settlement store integration tests (feature `postgres`). They need a server: set
+//! `TEST_DATABASE_URL` (e.g. `postgres://postgres@127.0.0.1:5432/postgres`) to a role that can create databases;
+//! each test creates its own and drops it. Without it they pass without running.
+#![cfg(feature = "postgres")]
+
+use dire_matching_engine::persistence::CommandLog;
+use dire_matching_engine::postgres::PostgresStore;
+use dire_matching_engine::remote::ObjectStore;
+use dire_matching_engine::settlement::{SettlementRecord, SettlementSink};
+use dire_matching_engine::{
+    InstrumentId, MatchingEngine, MultiEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId,
+};
+use rust_decimal::Decimal;
+use sqlx::Connection;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn limit(id: u64, side: Side, quantity: i64, price: i64, trader: u64) -> Order {
+    Order {
+        order_id: OrderId(id),
+        client_order_id: format!("c{}", id),
+        instrument_id: InstrumentId(1),
+        side,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(quantity),
+        price: Some(Decimal::from(price)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: id,
+        trader_id: TraderId(trader),
+    }
+}
+
+/// A fresh database on the `TEST_DATABASE_URL` server: its URL, and the server's for dropping it.
+async fn fresh_database(name: &str) -> Option<(String, String)> {
+    let Ok(server) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set; skipping");
+        return None;
+    };
+    let database = format!("{}_{}", name, std::process::id());
+    let mut admin = sqlx::PgConnection::connect(&server).await.unwrap();
+    sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {}", database)).execute(&mut admin).await.unwrap();
+    sqlx::raw_sql(&format!("CREATE DATABASE {}", database)).execute(&mut admin).await.unwrap();
+    let (base, _) = server.rsplit_once('/').expect("URL ends in a database name");
+    Some((format!("{}/{}", base, database), server))
+}
+
+async fn drop_database(server: &str, url: &str) {
+    let database = url.rsplit_once('/').unwrap().1;
+    let mut admin = sqlx::PgConnection::connect(server).await.unwrap();
+    sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", database)).execute(&mut admin).await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn postgres_store_migrates_and_keeps_trades_and_executions_for_settlement() {
+    let Some((url, server)) = fresh_database("dire_settlement").await else { return };
+    let store = PostgresStore::connect(&url).await.unwrap();
+    assert!(store.migrate().await.unwrap().is_empty(), "already applied on connect");
+    let versions: Vec<i64> = sqlx::query_scalar("SELECT version FROM dire_schema_migrations ORDER BY version")
+        .fetch_all(&mut sqlx::PgConnection::connect(&url).await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(versions, [1, 2]);
+
+    // Orders through an engine: 1 rests, 2 replaces it, 3 trades against 2 in part.
+    let sink = Arc::new(SettlementSink::new(Arc::new(store.clone())));
+    let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+    engine.add_event_sink(sink.clone());
+    engine.submit_order(limit(1, Side::Sell, 5, 101, 7)).unwrap();
+    engine.modify_order(OrderId(1), &limit(2, Side::Sell, 4, 100, 7)).unwrap();
+    let (trades, reports) = engine.submit_order(limit(3, Side::Buy, 3, 100, 8)).unwrap();
+    let flushed = tokio::task::spawn_blocking(move || sink.flush(Duration::from_secs(10))).await.unwrap();
+    assert!(flushed);
+
+    let settled = store.trades(None, 0, 10).await.unwrap();
+    assert_eq!(settled.len(), 1);
+    let trade = &settled[0];
+    assert_eq!((trade.trade.trade_id, trade.trade.seq), (trades[0].trade_id, trades[0].seq));
+    assert_eq!((trade.trade.price, trade.trade.quantity), (Decimal::from(100), Decimal::from(3)));
+    assert_eq!((trade.trade.buy_order_id, trade.trade.sell_order_id), (OrderId(3), OrderId(2)));
+    assert_eq!(trade.trade.aggressor_side, Side::Buy);
+    assert_eq!((trade.buy_trader_id, trade.sell_trader_id), (Some(TraderId(8)), Some(TraderId(7))));
+    assert!(store.trades(Some(InstrumentId(2)), 0, 10).await.unwrap().is_empty());
+    assert!(store.trades(None, trade.trade.seq, 10).await.unwrap().is_empty());
+
+    // A batch stored again (a retry after a failure) leaves one copy of each record.
+    let retry: Vec<_> = trades
+        .iter()
+        .cloned()
+        .map(SettlementRecord::Trade)
+        .chain(reports.iter().cloned().map(SettlementRecord::Report))
+        .collect();
+    store.insert(&retry).await.unwrap();
+    let mut conn = sqlx::PgConnection::connect(&url).await.unwrap();
+    let count = |table: &str| format!("SELECT count(*) FROM {}", table);
+    let trades_stored: i64 = sqlx::query_scalar(&count("trades")).fetch_one(&mut conn).await.unwrap();
+    let orders_stored: i64 = sqlx::query_scalar(&count("orders")).fetch_one(&mut conn).await.unwrap();
+    let executions: Vec<(String, String)> =
+        sqlx::query_as("SELECT exec_type, order_status FROM executions WHERE order_id = 2 ORDER BY seq")
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+    assert_eq!((trades_stored, orders_stored), (1, 3));
+    assert_eq!(
+        executions,
+        [("Replaced".to_string(), "New".to_string()), ("PartialFill".to_string(), "PartiallyFilled".to_string())]
+    );
+    drop(conn);
+    drop(store);
+    drop_database(&server, &url).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn postgres_store_keeps_the_command_log_and_state_objects_for_recovery() {
+    let Some((url, server)) = fresh_database("dire_engine_state").await else { return };
+    let store = PostgresStore::connect(&url).await.unwrap();
+
+    // The engine writes its commands ahead to the database; a fresh engine replays them to the same book.
+    let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+    engine.set_command_log(Arc::new(store.clone()));
+    engine.submit_order(limit(1, Side::Sell, 5, 101, 7)).unwrap();
+    engine.submit_order(limit(2, Side::Buy, 3, 99, 8)).unwrap();
+    engine.submit_order(limit(3, Side::Buy, 2, 98, 8)).unwrap();
+    engine.cancel_order(OrderId(2)).unwrap();
+    let last = engine.command_seq();
+    let mut recovered = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+    assert_eq!(recovered.replay_command_log(&store).unwrap().applied, 4);
+    let book = |engine: &MultiEngine| {
+        let mut ids: Vec<_> = engine.book_orders(InstrumentId(1)).unwrap().iter().map(|o| o.order_id.0).collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(book(&recovered), [1, 3]);
+    assert_eq!(book(&recovered), book(&engine));
+
+    // Compacted only once a snapshot covers every entry; cleared outright.
+    store.compact(last - 1).unwrap();
+    assert_eq!(store.entries_after(0).unwrap().len(), 4);
+    assert_eq!(store.entries_after(last - 1).unwrap().iter().map(|e| e.seq).collect::<Vec<_>>(), [last]);
+    store.compact(last).unwrap();
+    assert!(store.entries_after(0).unwrap().is_empty());
+    engine.submit_order(limit(4, Side::Buy, 1, 97, 8)).unwrap();
+    store.clear().unwrap();
+    assert!(store.entries_after(0).unwrap().is_empty());
+
+    // State objects: `%` and `_` in a prefix are not wildcards.
+    for key in ["journal/a_b", "journal/a%c", "journal/axb", "snapshot"] {
+        store.put(key, key.as_bytes()).unwrap();
+    }
+    store.put("snapshot", b"newer").unwrap();
+    assert_eq!(store.get("snapshot").unwrap(), Some(b"newer".to_vec()));
+    assert_eq!(store.get("missing").unwrap(), None);
+    assert_eq!(store.list("journal/a_").unwrap(), ["journal/a_b"]);
+    assert_eq!(store.list("journal/").unwrap(), ["journal/a%c", "journal/a_b", "journal/axb"]);
+    store.delete("journal/a%c").unwrap();
+    store.delete("journal/a%c").unwrap();
+    assert_eq!(store.list("").unwrap(), ["journal/a_b", "journal/axb", "snapshot"]);
+
+    // Ids beyond BIGINT are refused rather than stored negative.
+    let err = store.insert(&[SettlementRecord::Order(limit(u64::MAX, Side::Buy, 1, 100, 8))]).await.unwrap_err();
+    assert!(err.contains("does not fit"), "{}", err);
+    drop(store);
+    drop_database(&server, &url).await;
+}
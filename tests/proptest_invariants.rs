@@ -3,7 +3,10 @@
 //! Uses proptest to generate (seed, num_orders); replays synthetic orders into the engine
 //! and asserts: no crossed book, no negative quantities, quantity conservation.
 //! Deterministic replay: same config ⇒ same outcome.
+//! FIX framing fuzz: mutated and garbage-laden byte streams never yield a corrupt message.
 
+use dire_matching_engine::fix::message::{fix_frame_len, parse_fix_message, FixMessage, FixWriter};
+use dire_matching_engine::fix::FixDecoder;
 use dire_matching_engine::market_data_gen::{Generator, GeneratorConfig};
 use dire_matching_engine::{Engine, InstrumentId};
 use proptest::prelude::*;
@@ -35,9 +38,8 @@ fn replay_collect(
 fn assert_no_crossed_book(engine: &Engine) {
     let bid = engine.best_bid();
     let ask = engine.best_ask();
-    match (bid, ask) {
-        (Some(b), Some(a)) => assert!(b < a, "invariant: best_bid {:?} < best_ask {:?}", b, a),
-        _ => {}
+    if let (Some(b), Some(a)) = (bid, ask) {
+        assert!(b < a, "invariant: best_bid {:?} < best_ask {:?}", b, a);
     }
 }
 
@@ -114,3 +116,93 @@ fn deterministic_replay_same_seed_same_outcome() {
     let total2: Decimal = trades2.iter().map(|t| t.quantity).sum();
     assert_eq!(total1, total2, "same total traded quantity");
 }
+
+/// Valid FIX NewOrderSingles numbered 1..=count, one after the other as on the wire.
+fn fix_messages(count: usize) -> Vec<Vec<u8>> {
+    (1..=count)
+        .map(|seq| {
+            let mut w = FixWriter::new();
+            for (tag, value) in [(35, "D"), (55, "BTC-USD"), (54, "1"), (38, "5"), (40, "2"), (44, "100.5")] {
+                w.set(tag, value);
+            }
+            w.set(34, seq.to_string());
+            w.set(11, format!("{}", 1000 + seq));
+            let mut out = Vec::new();
+            w.write(&mut out).unwrap();
+            out
+        })
+        .collect()
+}
+
+/// Feed `stream` to a decoder `chunk` bytes at a time; the messages it decodes.
+fn decode_in_chunks(stream: &[u8], chunk: usize) -> Vec<FixMessage> {
+    let mut decoder = FixDecoder::new();
+    let mut decoded = Vec::new();
+    for bytes in stream.chunks(chunk) {
+        decoder.extend(bytes);
+        while let Some(next) = decoder.next_message() {
+            decoded.extend(next.ok());
+        }
+    }
+    decoded
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(200))]
+
+    /// Any single changed byte makes the message fail BodyLength or CheckSum validation.
+    #[test]
+    fn prop_fix_single_byte_change_is_rejected(index in any::<prop::sample::Index>(), byte in any::<u8>()) {
+        let mut message = fix_messages(1).remove(0);
+        let i = index.index(message.len());
+        prop_assume!(message[i] != byte);
+        message[i] = byte;
+        prop_assert!(parse_fix_message(&message).is_none());
+        prop_assert!(fix_frame_len(&message).map_or(true, |len| len.is_none()));
+    }
+
+    /// A stream with one changed byte, read in any chunk size, loses at most the message with that byte: the
+    /// others decode intact and in order.
+    #[test]
+    fn prop_fix_decoder_resyncs_after_a_corrupt_byte(
+        count in 1usize..6,
+        index in any::<prop::sample::Index>(),
+        byte in any::<u8>(),
+        chunk in 1usize..64,
+    ) {
+        let messages = fix_messages(count);
+        let mut stream = messages.concat();
+        let i = index.index(stream.len());
+        stream[i] = byte;
+        let mut start = 0;
+        let intact: Vec<FixMessage> = messages
+            .iter()
+            .filter(|m| {
+                let range = start..start + m.len();
+                start = range.end;
+                !range.contains(&i) || m[i - range.start] == byte
+            })
+            .map(|m| parse_fix_message(m).unwrap().0)
+            .collect();
+        let decoded = decode_in_chunks(&stream, chunk);
+        prop_assert_eq!(decoded, intact);
+    }
+
+    /// Arbitrary bytes around and between valid messages never stop the decoder from finding them.
+    #[test]
+    fn prop_fix_decoder_skips_random_garbage(
+        garbage in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..40), 4),
+        chunk in 1usize..64,
+    ) {
+        let messages = fix_messages(3);
+        let mut stream = Vec::new();
+        for (junk, message) in garbage.iter().zip(&messages) {
+            stream.extend_from_slice(junk);
+            stream.extend_from_slice(message);
+        }
+        stream.extend_from_slice(&garbage[3]);
+        let decoded = decode_in_chunks(&stream, chunk);
+        let expected: Vec<FixMessage> = messages.iter().map(|m| parse_fix_message(m).unwrap().0).collect();
+        prop_assert_eq!(decoded, expected);
+    }
+}
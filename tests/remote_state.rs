@@ -0,0 +1,279 @@
+//! Remote state: a node uploads its state file and command log to an object store, and a node started on an
+//! empty disk restores from it and recovers the same book. With the `s3` feature, the same against a fake
+//! S3-compatible server.
+
+use dire_matching_engine::api::{self, AppState};
+use dire_matching_engine::audit::InMemoryAuditSink;
+use dire_matching_engine::persistence::FilePersistence;
+use dire_matching_engine::remote::{self, InMemoryObjectStore, ObjectStore, SEGMENT_PREFIX, SNAPSHOT_KEY};
+use dire_matching_engine::{InstrumentId, MatchingEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+use rust_decimal::Decimal;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Long enough that the uploader only runs when it starts and when it finishes.
+const NEVER: Duration = Duration::from_secs(3600);
+
+fn start(persistence: &Arc<FilePersistence>) -> AppState {
+    api::create_app_state_with_sink_and_instruments(
+        vec![(InstrumentId(1), None)],
+        Arc::new(InMemoryAuditSink::new()),
+        Some(persistence.clone()),
+    )
+}
+
+/// Order `id` straight to the engine, so it is in the command log but not the state file. Bids at different
+/// prices, so nothing trades.
+fn submit(state: &AppState, id: u64) {
+    let order = Order {
+        order_id: OrderId(id),
+        client_order_id: format!("c{}", id),
+        instrument_id: InstrumentId(1),
+        side: Side::Buy,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(1),
+        price: Some(Decimal::from(100 - id as i64)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: id,
+        trader_id: TraderId(1),
+    };
+    state.engine.lock().unwrap().submit_order(order).unwrap();
+}
+
+fn resting(state: &AppState) -> Vec<u64> {
+    let mut ids: Vec<_> = state.engine.lock().unwrap().book_orders(InstrumentId(1)).unwrap().iter().map(|o| o.order_id.0).collect();
+    ids.sort();
+    ids
+}
+
+/// A state file path in an empty directory, as a new container sees it.
+fn empty_disk(name: &str) -> (PathBuf, Arc<FilePersistence>) {
+    let dir = std::env::temp_dir().join(format!("dire_remote_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let persistence = Arc::new(FilePersistence::new(dir.join("state")));
+    (dir, persistence)
+}
+
+/// Run a node on `persistence` uploading to `store`: `work` runs while the uploader does, which then uploads
+/// once more and stops.
+fn run_node(persistence: &Arc<FilePersistence>, store: &Arc<dyn ObjectStore>, work: impl FnOnce(&AppState)) -> Vec<u64> {
+    let state = start(persistence);
+    let uploader = api::start_remote_uploader(&state, store.clone(), NEVER).unwrap();
+    work(&state);
+    uploader.finish();
+    resting(&state)
+}
+
+/// Two nodes in turn, each on an empty disk: the first uploads a snapshot and the orders in its command log,
+/// the second restores them, trades on, and uploads again for a third.
+fn stateless_restarts_recover_from(store: Arc<dyn ObjectStore>) {
+    let (first_dir, first) = empty_disk("first");
+    let booked = run_node(&first, &store, |state| {
+        state.flush();
+        (1..=3).for_each(|id| submit(state, id));
+    });
+    assert_eq!(booked, [1, 2, 3]);
+    assert!(store.get(SNAPSHOT_KEY).unwrap().is_some());
+    assert!(!store.list(SEGMENT_PREFIX).unwrap().is_empty(), "orders after the snapshot are in segments");
+    let _ = std::fs::remove_dir_all(&first_dir);
+
+    let (second_dir, second) = empty_disk("second");
+    let restored = remote::restore(store.as_ref(), &second).unwrap().unwrap();
+    assert_eq!((restored.snapshot_command_seq, restored.entries, restored.skipped_segments), (0, 3, 0));
+    assert_eq!(remote::restore(store.as_ref(), &second).unwrap(), None, "a local state file wins");
+    let state = start(&second);
+    assert_eq!(resting(&state), [1, 2, 3]);
+    assert_eq!(state.recovery().unwrap().replayed, 3);
+    drop(state);
+    // Recovery saved the replayed orders into the state file: once that is uploaded, the segments go.
+    let booked = run_node(&second, &store, |state| submit(state, 4));
+    assert_eq!(booked, [1, 2, 3, 4]);
+    let segments = store.list(SEGMENT_PREFIX).unwrap();
+    assert_eq!(segments, [remote::segment_key(4, 4)]);
+    let _ = std::fs::remove_dir_all(&second_dir);
+
+    // A segment that does not follow on from the others is left out.
+    store.put(&remote::segment_key(9, 9), b"").unwrap();
+    let (third_dir, third) = empty_disk("third");
+    let restored = remote::restore(store.as_ref(), &third).unwrap().unwrap();
+    assert_eq!((restored.snapshot_command_seq, restored.segments, restored.skipped_segments), (3, 1, 1));
+    assert_eq!(resting(&start(&third)), [1, 2, 3, 4]);
+    let _ = std::fs::remove_dir_all(&third_dir);
+}
+
+#[test]
+fn a_node_on_an_empty_disk_recovers_from_the_object_store() {
+    stateless_restarts_recover_from(Arc::new(InMemoryObjectStore::new()));
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use super::*;
+    use axum::body::Bytes;
+    use axum::extract::{Path, Query, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use dire_matching_engine::s3::{S3Config, S3Store};
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Mutex;
+
+    /// Keys listed per page, so listing has to follow continuation tokens.
+    const PAGE: usize = 2;
+
+    /// Objects by `<bucket>/<key>`, and every request's method and `Authorization` header.
+    #[derive(Clone, Default)]
+    struct FakeS3 {
+        objects: Arc<Mutex<BTreeMap<String, Vec<u8>>>>,
+        requests: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl FakeS3 {
+        /// Record the request, and check its payload hash as S3 does.
+        fn record(&self, method: &str, headers: &HeaderMap, body: &[u8]) -> Result<(), StatusCode> {
+            let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+            self.requests.lock().unwrap().push((method.to_string(), header("authorization")));
+            let hash: String = ring::digest::digest(&ring::digest::SHA256, body).as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+            if header("x-amz-content-sha256") != hash {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            Ok(())
+        }
+    }
+
+    async fn put_object(State(s3): State<FakeS3>, Path((bucket, key)): Path<(String, String)>, headers: HeaderMap, body: Bytes) -> StatusCode {
+        if let Err(status) = s3.record("PUT", &headers, &body) {
+            return status;
+        }
+        s3.objects.lock().unwrap().insert(format!("{}/{}", bucket, key), body.to_vec());
+        StatusCode::OK
+    }
+
+    async fn get_object(State(s3): State<FakeS3>, Path((bucket, key)): Path<(String, String)>, headers: HeaderMap) -> Result<Vec<u8>, StatusCode> {
+        s3.record("GET", &headers, b"")?;
+        s3.objects.lock().unwrap().get(&format!("{}/{}", bucket, key)).cloned().ok_or(StatusCode::NOT_FOUND)
+    }
+
+    async fn delete_object(State(s3): State<FakeS3>, Path((bucket, key)): Path<(String, String)>, headers: HeaderMap) -> StatusCode {
+        if let Err(status) = s3.record("DELETE", &headers, b"") {
+            return status;
+        }
+        s3.objects.lock().unwrap().remove(&format!("{}/{}", bucket, key));
+        StatusCode::NO_CONTENT
+    }
+
+    /// ListObjectsV2, `PAGE` keys at a time; the continuation token is the last key listed.
+    async fn list_objects(
+        State(s3): State<FakeS3>,
+        Path(bucket): Path<String>,
+        Query(query): Query<HashMap<String, String>>,
+        headers: HeaderMap,
+    ) -> Result<String, StatusCode> {
+        s3.record("LIST", &headers, b"")?;
+        if query.get("list-type").map(String::as_str) != Some("2") {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let (prefix, after) = (query.get("prefix").cloned().unwrap_or_default(), query.get("continuation-token"));
+        let objects = s3.objects.lock().unwrap();
+        let keys: Vec<_> = objects
+            .keys()
+            .filter_map(|path| path.strip_prefix(&format!("{}/", bucket)))
+            .filter(|key| key.starts_with(&prefix) && after.is_none_or(|after| *key > after.as_str()))
+            .map(str::to_string)
+            .collect();
+        let page = &keys[..keys.len().min(PAGE)];
+        let mut xml = format!("<ListBucketResult><Name>{}</Name><Prefix>{}</Prefix>", bucket, prefix);
+        for key in page {
+            xml.push_str(&format!("<Contents><Key>{}</Key><Size>0</Size></Contents>", key));
+        }
+        match page.last().filter(|_| keys.len() > PAGE) {
+            Some(last) => xml.push_str(&format!(
+                "<IsTruncated>true</IsTruncated><NextContinuationToken>{}</NextContinuationToken>",
+                last
+            )),
+            None => xml.push_str("<IsTruncated>false</IsTruncated>"),
+        }
+        xml.push_str("</ListBucketResult>");
+        Ok(xml)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn s3_store_signs_requests_pages_listings_and_backs_stateless_restarts() {
+        let fake = FakeS3::default();
+        let app = Router::new()
+            .route("/:bucket", get(list_objects))
+            .route("/:bucket/*key", get(get_object).put(put_object).delete(delete_object))
+            .with_state(fake.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let store = S3Store::new(S3Config {
+            endpoint: format!("http://{}", addr),
+            bucket: "engine-state".into(),
+            prefix: "node-1/".into(),
+            region: "eu-west-1".into(),
+            access_key_id: "AKIDEXAMPLE".into(),
+            secret_access_key: "secret".into(),
+            session_token: None,
+        })
+        .unwrap();
+        assert!(!format!("{:?}", store).contains("secret"), "Debug leaves out the secret key");
+
+        // Objects land under the prefix; listing strips it and pages through every key.
+        store.put_object("journal/a b", b"1".to_vec()).await.unwrap();
+        for key in ["journal/b", "journal/c", "snapshot"] {
+            store.put_object(key, key.as_bytes().to_vec()).await.unwrap();
+        }
+        assert!(fake.objects.lock().unwrap().contains_key("engine-state/node-1/journal/a b"));
+        assert_eq!(store.get_object("journal/a b").await.unwrap(), Some(b"1".to_vec()));
+        assert_eq!(store.get_object("missing").await.unwrap(), None);
+        assert_eq!(store.list_objects("journal/").await.unwrap(), ["journal/a b", "journal/b", "journal/c"]);
+        store.delete_object("journal/b").await.unwrap();
+        store.delete_object("journal/b").await.unwrap();
+        assert_eq!(store.list_objects("").await.unwrap(), ["journal/a b", "journal/c", "snapshot"]);
+        for (method, authorization) in fake.requests.lock().unwrap().iter() {
+            assert!(
+                authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/")
+                    && authorization.contains("/eu-west-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature="),
+                "{} {}",
+                method,
+                authorization
+            );
+        }
+        for key in store.list_objects("").await.unwrap() {
+            store.delete_object(&key).await.unwrap();
+        }
+
+        // As an ObjectStore it blocks, so the nodes run off the runtime's threads.
+        tokio::task::spawn_blocking(move || stateless_restarts_recover_from(Arc::new(store))).await.unwrap();
+    }
+}
+
+/// The same against PostgreSQL, when `TEST_DATABASE_URL` names a server (see `tests/postgres_store.rs`).
+#[cfg(feature = "postgres")]
+mod postgres {
+    use super::*;
+    use dire_matching_engine::postgres::PostgresStore;
+    use sqlx::Connection;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn postgres_store_backs_stateless_restarts() {
+        let Ok(server) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("TEST_DATABASE_URL not set; skipping");
+            return;
+        };
+        let database = format!("dire_remote_state_{}", std::process::id());
+        let mut admin = sqlx::PgConnection::connect(&server).await.unwrap();
+        sqlx::raw_sql(&format!("DROP DATABASE IF EXISTS {}", database)).execute(&mut admin).await.unwrap();
+        sqlx::raw_sql(&format!("CREATE DATABASE {}", database)).execute(&mut admin).await.unwrap();
+        let url = format!("{}/{}", server.rsplit_once('/').expect("URL ends in a database name").0, database);
+        let store = PostgresStore::connect(&url).await.unwrap();
+
+        tokio::task::spawn_blocking(move || stateless_restarts_recover_from(Arc::new(store))).await.unwrap();
+        let drop = format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", database);
+        sqlx::raw_sql(&drop).execute(&mut admin).await.unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Async replay into a running server (feature `replay`): generated orders, cancels, and modifies go in over
+//! REST and over FIX at a set pace, and the server ends with the book an in-process replay builds.
+#![cfg(feature = "replay")]
+
+use dire_matching_engine::api::{self, AppState};
+use dire_matching_engine::auth::AuthConfig;
+use dire_matching_engine::fix::{run_fix_acceptor, FixInitiatorConfig};
+use dire_matching_engine::replay::{replay_via_fix, replay_via_http, Pace, ReplayConfig, ReplayReport};
+use dire_matching_engine::{
+    GenEvent, Generator, GeneratorConfig, InstrumentId, MatchingEngine, MultiEngine, OrderId, PriceProcess, Side,
+};
+use dire_matching_engine::market_data_gen::ARRIVAL_CLOCK_TICKS_PER_SEC;
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+/// 300 events about 150 ms apart in all, around a mean-reverting mid, so orders trade, rest, and are canceled
+/// or modified both while resting and after they filled.
+fn events() -> Vec<GenEvent> {
+    Generator::new(GeneratorConfig {
+        seed: 11,
+        num_orders: 300,
+        cancel_ratio: 0.2,
+        modify_ratio: 0.15,
+        price_process: PriceProcess::MeanReverting { start: 100.0, reversion: 0.1, volatility: 0.5, drift: 0.0 },
+        arrival_rate: Some(2000.0),
+        ..Default::default()
+    })
+    .all_events()
+}
+
+/// Resting orders of instrument 1 as (side, price, quantity), sorted: order ids aside, the shape of the book.
+fn book(engine: &MultiEngine) -> Vec<(Side, Decimal, Decimal)> {
+    let mut orders: Vec<_> = engine
+        .book_orders(InstrumentId(1))
+        .unwrap()
+        .into_iter()
+        .map(|order| (order.side, order.price, order.remaining_quantity))
+        .collect();
+    orders.sort_by_key(|&(side, price, quantity)| (side == Side::Buy, price, quantity));
+    orders
+}
+
+/// `events` applied one at a time to an in-process engine: the book it ends with, and how many of them the
+/// engine refused (late cancels and modifies included).
+fn expected(events: &[GenEvent]) -> (Vec<(Side, Decimal, Decimal)>, usize) {
+    let mut engine = MultiEngine::new_with_instruments(vec![(InstrumentId(1), None)]);
+    let mut refused = 0;
+    for event in events.iter().cloned() {
+        let accepted = match event {
+            GenEvent::Submit(order) => engine.submit_order(order).is_ok(),
+            GenEvent::Cancel { order_id, .. } => MatchingEngine::cancel_order(&mut engine, order_id).is_some(),
+            GenEvent::Modify { order_id, replacement } => engine.modify_order(order_id, &replacement).is_ok(),
+        };
+        refused += usize::from(!accepted);
+    }
+    (book(&engine), refused)
+}
+
+fn assert_all_answered(report: &ReplayReport, sent: usize) {
+    assert_eq!((report.sent, report.failed), (sent, 0), "{:?}", report);
+    assert_eq!(report.accepted + report.rejected, sent, "{:?}", report);
+    assert!(report.latency_p50 <= report.latency_p99 && report.latency_p99 <= report.latency_max);
+}
+
+async fn serve(state: AppState) -> String {
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("adm:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app.into_make_service()).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn http_replay_keeps_its_rate_and_builds_the_in_process_book() {
+    let events = events();
+    let (expected_book, expected_refused) = expected(&events);
+    assert!(expected_refused > 0 && !expected_book.is_empty());
+    let state = api::create_app_state(InstrumentId(1));
+    let base_url = serve(state.clone()).await;
+
+    // One at a time, the server sees the stream in order: the same book, the same refusals.
+    let paced = ReplayConfig { pace: Pace::Rate(1000.0), max_in_flight: 1, order_id_offset: 0 };
+    let report = replay_via_http(&base_url, Some("adm"), events.clone(), &paced).await.unwrap();
+    assert_all_answered(&report, events.len());
+    assert_eq!(report.rejected, expected_refused, "{:?}", report.first_error);
+    assert!(report.elapsed >= Duration::from_millis(299), "event 300 is due at 299 ms: {:?}", report.elapsed);
+    assert!(report.rate() <= 1000.0);
+    assert_eq!(book(&state.engine.lock().unwrap()), expected_book);
+
+    // Many in flight and unpaced, the same stream again under new ids; a wrong key is refused, not failed.
+    let burst = ReplayConfig { order_id_offset: 10_000, ..ReplayConfig::default() };
+    let report = replay_via_http(&base_url, Some("adm"), events.clone(), &burst).await.unwrap();
+    assert_all_answered(&report, events.len());
+    assert!(state.engine.lock().unwrap().order_status(OrderId(10_001)).is_some());
+    let report = replay_via_http(&base_url, Some("wrong"), events[..3].to_vec(), &burst).await.unwrap();
+    assert_eq!((report.rejected, report.first_error.as_deref()), (3, Some("401 invalid API key or session token")));
+    let report = replay_via_http("http://127.0.0.1:1", None, events[..2].to_vec(), &burst).await.unwrap();
+    assert_eq!((report.sent, report.failed), (2, 2));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fix_replay_follows_arrival_times_and_builds_the_in_process_book() {
+    let events = events();
+    let (expected_book, expected_refused) = expected(&events);
+    let state = api::create_app_state(InstrumentId(1));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let (engine, market_state) = (state.engine.clone(), state.market_state.clone());
+    std::thread::spawn(move || run_fix_acceptor(listener, engine, market_state));
+
+    // The acceptor numbers replacement orders itself, far above the generated ids.
+    let config = ReplayConfig { pace: Pace::ArrivalTimes { speed: 1.0 }, max_in_flight: 1, order_id_offset: 0 };
+    let session = FixInitiatorConfig::new("REPLAY", "DIRED");
+    let report = replay_via_fix(&addr, session.clone(), "1", events.clone(), &config).await.unwrap();
+    assert_all_answered(&report, events.len());
+    assert_eq!(report.rejected, expected_refused, "{:?}", report.first_error);
+    let span = (events.last().unwrap().timestamp() - events[0].timestamp()) as f64 / ARRIVAL_CLOCK_TICKS_PER_SEC as f64;
+    assert!(report.elapsed.as_secs_f64() >= span, "{:?} for {} s of arrivals", report.elapsed, span);
+    assert_eq!(book(&state.engine.lock().unwrap()), expected_book);
+
+    let err = replay_via_fix(&addr, session, "1", Vec::<GenEvent>::new(), &config).await;
+    assert!(err.is_ok(), "a second session logs on: {:?}", err);
+    let unknown = replay_via_fix(&addr, FixInitiatorConfig::new("REPLAY2", "DIRED"), "NOPE", events[..1].to_vec(), &config).await.unwrap();
+    assert_eq!((unknown.rejected, unknown.first_error.as_deref()), (1, Some("unknown Symbol (55) NOPE")));
+}
@@ -0,0 +1,186 @@
+//! Primary/backup replication: a standby follows a primary's engine over the replication stream, resyncs when the
+//! primary's state is replaced, and takes over with the same state once promoted.
+
+use dire_matching_engine::api::{self, AppState, MarketState};
+use dire_matching_engine::audit::{AuditAction, InMemoryAuditSink};
+use dire_matching_engine::auth::AuthConfig;
+use dire_matching_engine::persistence::FilePersistence;
+use dire_matching_engine::replication;
+use dire_matching_engine::{
+    BookLimits, InstrumentId, InstrumentState, MatchingEngine, Order, OrderId, OrderStatus, OrderType, Quote, RiskLimits, Side,
+    TimeInForce, TimedAction, TraderId, VenueConfig,
+};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn order(id: u64, side: Side, price: i64, quantity: i64) -> Order {
+    Order {
+        order_id: OrderId(id),
+        client_order_id: format!("c{}", id),
+        instrument_id: InstrumentId(1),
+        side,
+        order_type: OrderType::Limit,
+        quantity: Decimal::from(quantity),
+        price: Some(Decimal::from(price)),
+        time_in_force: TimeInForce::GTC,
+        timestamp: id,
+        trader_id: TraderId(id),
+    }
+}
+
+/// The engine snapshot without the node's own command-log numbering, and with lists taken from hash maps sorted.
+fn snapshot(state: &AppState) -> serde_json::Value {
+    normalized(serde_json::to_value(state.engine.lock().unwrap().snapshot()).unwrap())
+}
+
+fn normalized(mut snapshot: serde_json::Value) -> serde_json::Value {
+    let fields = snapshot.as_object_mut().unwrap();
+    fields.remove("command_seq");
+    for value in fields.values_mut() {
+        if let Some(list) = value.as_array_mut() {
+            list.sort_by_key(|item| item.to_string());
+        }
+    }
+    snapshot
+}
+
+/// Same engine state, market state, and venue config.
+fn in_sync(primary: &AppState, standby: &AppState) -> bool {
+    snapshot(primary) == snapshot(standby)
+        && *primary.market_state.lock().unwrap() == *standby.market_state.lock().unwrap()
+        && *primary.venue_config.lock().unwrap() == *standby.venue_config.lock().unwrap()
+}
+
+/// Wait up to ten seconds for `done`.
+async fn eventually(what: &str, done: impl Fn() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out waiting for {}", what);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_standby_follows_the_primary_and_takes_over_with_the_same_state() {
+    let primary = api::create_app_state_with_sink_and_instruments(vec![(InstrumentId(1), None)], Arc::new(InMemoryAuditSink::new()), None);
+    primary.engine.lock().unwrap().submit_order(order(1, Side::Buy, 100, 5)).unwrap();
+    let base = primary.engine.lock().unwrap().snapshot();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let primary_addr = listener.local_addr().unwrap();
+    let serving = primary.clone();
+    std::thread::spawn(move || replication::run_replication_primary(listener, serving));
+
+    // The standby keeps its own state file, and its book limits do not stop what the primary accepted.
+    let dir = std::env::temp_dir().join(format!("dire_replication_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let persistence = Arc::new(FilePersistence::new(dir.join("state")));
+    let audit = Arc::new(InMemoryAuditSink::new());
+    let mut state = api::create_app_state_with_sink_and_instruments(vec![(InstrumentId(9), None)], audit.clone(), Some(persistence.clone()));
+    state.engine.lock().unwrap().set_book_limits(BookLimits { max_orders_per_level: Some(1), ..BookLimits::default() });
+    let standby = replication::start_standby(&mut state, primary_addr.to_string());
+    eventually("the snapshot", || standby.status().snapshots == 1).await;
+    assert!(in_sync(&primary, &state));
+
+    // Order entry with a trade, a cancel, a replace, and a quote; admin changes; a timer.
+    {
+        let mut engine = primary.engine.lock().unwrap();
+        engine.submit_order(order(2, Side::Buy, 100, 5)).unwrap();
+        let (trades, _) = engine.submit_order(order(3, Side::Sell, 100, 7)).unwrap();
+        assert_eq!(trades.len(), 2);
+        engine.cancel_order(OrderId(2)).unwrap();
+        engine.submit_order(order(4, Side::Buy, 99, 1)).unwrap();
+        engine.modify_order(OrderId(4), &order(4, Side::Buy, 98, 2)).unwrap();
+        let quote = Quote {
+            quote_id: "q1".into(),
+            trader_id: TraderId(7),
+            instrument_id: InstrumentId(1),
+            bid_order_id: OrderId(5),
+            bid_price: Decimal::from(97),
+            bid_quantity: Decimal::from(3),
+            ask_order_id: OrderId(6),
+            ask_price: Decimal::from(103),
+            ask_quantity: Decimal::from(3),
+            timestamp: 5,
+        };
+        engine.submit_quote(&quote).unwrap();
+        engine.add_instrument(InstrumentId(2), Some("B".into())).unwrap();
+        engine.set_instrument_state(InstrumentId(2), InstrumentState::Suspended).unwrap();
+        engine.schedule(10, TimedAction::ExpireOrder { order_id: OrderId(4) }).unwrap();
+        engine.advance_time(10);
+    }
+    primary.set_venue_config(VenueConfig {
+        version: 1,
+        risk: RiskLimits { max_order_quantity: Some(Decimal::from(1000)), ..RiskLimits::default() },
+        ..VenueConfig::default()
+    });
+    *primary.market_state.lock().unwrap() = MarketState::Halted;
+    eventually("the commands", || in_sync(&primary, &state)).await;
+    assert_eq!(standby.status().applied, 10);
+    let expired = state.engine.lock().unwrap().order_status(OrderId(4)).unwrap();
+    assert_eq!(expired.status, OrderStatus::Canceled, "the timer ran on the standby too");
+
+    // Restoring the primary replaces the state the standby followed: it resyncs from a new snapshot.
+    primary.engine.lock().unwrap().load_from_snapshot(base).unwrap();
+    *primary.market_state.lock().unwrap() = MarketState::Open;
+    eventually("the resync", || standby.status().snapshots == 2 && in_sync(&primary, &state)).await;
+
+    let app = api::create_router_with_state_and_auth(state.clone(), Some(AuthConfig::from_keys("adm:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app.into_make_service()).await.unwrap() });
+    let client = reqwest::Client::new();
+    let new_order = serde_json::json!({
+        "order_id": 20,
+        "client_order_id": "c20",
+        "instrument_id": 1,
+        "side": "Sell",
+        "order_type": "Limit",
+        "quantity": "1",
+        "price": "105",
+        "time_in_force": "GTC",
+        "timestamp": 20,
+        "trader_id": 1
+    });
+
+    // Unpromoted, the standby serves reads, refuses writes, and is not ready.
+    assert_eq!(client.get(format!("http://{}/book/1", addr)).header("X-API-Key", "adm").send().await.unwrap().status(), 200);
+    let response = client.post(format!("http://{}/orders", addr)).json(&new_order).header("X-API-Key", "adm").send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.json::<serde_json::Value>().await.unwrap()["code"], "standby");
+    let response = client.get(format!("http://{}/health/ready", addr)).header("X-API-Key", "adm").send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    let ready: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(ready["checks"]["replication"]["status"], "standby");
+    assert_eq!(ready["checks"]["replication"]["connected"], true);
+
+    // Promoted, it takes over with the primary's state, saved, and no longer follows. Its id reservation file
+    // is back in use, so trade and execution ids may skip ahead but never go back.
+    assert!(in_sync(&primary, &state));
+    let without_ids = |mut snapshot: serde_json::Value| {
+        let ids = ["next_trade_id", "next_exec_id", "next_order_id"].map(|field| snapshot.as_object_mut().unwrap().remove(field).unwrap().as_u64().unwrap());
+        (snapshot, ids)
+    };
+    let (replicated, replicated_ids) = without_ids(snapshot(&primary));
+    let response = client.post(format!("http://{}/admin/failover", addr)).header("X-API-Key", "adm").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!((status["promoted"].as_bool(), status["primary"].as_str()), (Some(true), Some(primary_addr.to_string().as_str())));
+    let (promoted, promoted_ids) = without_ids(snapshot(&state));
+    assert_eq!(promoted, replicated);
+    assert!(promoted_ids.iter().zip(replicated_ids).all(|(promoted, replicated)| *promoted >= replicated));
+    let saved = persistence.load().unwrap().unwrap();
+    assert_eq!(normalized(serde_json::to_value(&saved.engine).unwrap()), snapshot(&state));
+    assert!(audit.events().iter().any(|event| event.action == AuditAction::Failover));
+    primary.engine.lock().unwrap().submit_order(order(21, Side::Buy, 90, 1)).unwrap();
+    let response = client.post(format!("http://{}/orders", addr)).json(&new_order).header("X-API-Key", "adm").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(state.engine.lock().unwrap().order_status(OrderId(21)).is_none());
+    assert!(primary.engine.lock().unwrap().order_status(OrderId(20)).is_none());
+    assert_eq!(client.get(format!("http://{}/health/ready", addr)).header("X-API-Key", "adm").send().await.unwrap().status(), 200);
+    let response = client.post(format!("http://{}/admin/failover", addr)).header("X-API-Key", "adm").send().await.unwrap();
+    assert_eq!(response.status(), 409);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -1,9 +1,9 @@
 //! REST API integration tests (Phase 2). Spawn the server and call endpoints with reqwest.
 
 use dire_matching_engine::api;
-use dire_matching_engine::audit::InMemoryAuditSink;
+use dire_matching_engine::audit::{AuditAction, AuditContext, AuditSource, InMemoryAuditSink};
 use dire_matching_engine::auth::AuthConfig;
-use dire_matching_engine::InstrumentId;
+use dire_matching_engine::{InstrumentId, KeyId};
 use std::net::SocketAddr;
 use std::sync::Arc;
 
@@ -54,7 +54,63 @@ async fn health_returns_ok() {
     let client = reqwest::Client::new();
     let response = client.get(&url).send().await.unwrap();
     assert_eq!(response.status(), 200);
+    // Requests without a usable X-Request-Id get one of their own.
+    assert!(response.headers()["x-request-id"].to_str().unwrap().bytes().all(|b| b.is_ascii_hexdigit()));
     assert_eq!(response.text().await.unwrap(), "ok");
+    let long = "r".repeat(129);
+    let response = client.get(&url).header("X-Request-Id", &long).send().await.unwrap();
+    assert_ne!(response.headers()["x-request-id"], long.as_str());
+}
+
+#[tokio::test]
+async fn health_live_and_ready_report_checks_and_instruments() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    let response = client.get(format!("http://{}/health/live", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(body["engine"]["status"], "ok");
+
+    let response = client.get(format!("http://{}/health/ready", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["market_state"], "Open");
+    assert_eq!(body["checks"]["engine"]["status"], "ok");
+    assert_eq!(body["checks"]["persistence"]["status"], "disabled");
+    assert_eq!(body["checks"]["fix_acceptor"]["status"], "disabled");
+    assert_eq!(body["instruments"][0]["instrument_id"], 1);
+    assert_eq!(body["instruments"][0]["state"], "Active");
+    assert_eq!(body["instruments"][0]["market_state"], "Open");
+}
+
+#[tokio::test]
+async fn health_ready_fails_on_unwritable_persistence_or_stopped_fix_acceptor() {
+    let mut state = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], "/nonexistent-dir/state.json");
+    let stopped = std::thread::spawn(|| {});
+    while !stopped.is_finished() {
+        std::thread::yield_now();
+    }
+    state.fix_acceptor = Some(Arc::new(stopped));
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::disabled()));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let response = client.get(format!("http://{}/health/ready", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["persistence"]["status"], "error");
+    assert_eq!(body["checks"]["fix_acceptor"]["status"], "error");
+    // Still live: only the engine decides that.
+    let response = client.get(format!("http://{}/health/live", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
 }
 
 #[tokio::test]
@@ -201,6 +257,9 @@ async fn modify_order_returns_trades_and_reports() {
     let json: serde_json::Value = response.json().await.unwrap();
     assert!(json.get("reports").is_some());
     assert!(json.get("trades").is_some());
+    let first = &json["reports"][0];
+    assert_eq!(first["exec_type"], "Replaced");
+    assert_eq!(first["orig_order_id"], 1);
 }
 
 #[tokio::test]
@@ -223,7 +282,9 @@ async fn submit_order_invalid_limit_no_price_returns_400() {
     let response = client.post(&url).json(&order).send().await.unwrap();
     assert_eq!(response.status(), 400);
     let json: serde_json::Value = response.json().await.unwrap();
-    assert!(json.get("error").is_some());
+    assert_eq!(json["code"], "invalid_price");
+    assert_eq!(json["field"], "price");
+    assert_eq!(json["message"], "Limit order must have price");
 }
 
 // --- Phase 3: API key auth ---
@@ -349,8 +410,617 @@ async fn rbac_operator_to_admin_returns_200() {
     assert_eq!(response.status(), 200);
 }
 
+#[tokio::test]
+async fn trader_bound_keys_enter_and_manage_only_their_own_orders() {
+    let (addr, _handle, sink) = spawn_app_with_audit_sink(Some("t7:trader:7,t8:trader:8,a:admin")).await;
+    let client = reqwest::Client::new();
+    let order = |order_id: u64, trader_id: u64| {
+        serde_json::json!({
+            "order_id": order_id,
+            "client_order_id": format!("c{}", order_id),
+            "instrument_id": 1,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": "99",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": trader_id
+        })
+    };
+    let post = |path: &str, key: &str, body: serde_json::Value| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&body)
+            .send()
+    };
+
+    let response = post("/orders", "t7", order(1, 8)).await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!((body["code"].as_str(), body["field"].as_str()), (Some("forbidden"), Some("trader_id")));
+    assert_eq!(post("/orders", "t7", order(1, 7)).await.unwrap().status(), 200);
+
+    // Another trader's key can neither cancel nor replace trader 7's order.
+    let response = post("/orders/cancel", "t8", serde_json::json!({ "order_id": 1 })).await.unwrap();
+    assert_eq!(response.status(), 403);
+    let modify = |trader_id| serde_json::json!({ "order_id": 1, "replacement": order(2, trader_id) });
+    let response = post("/orders/modify", "t8", modify(8)).await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["field"], "order_id");
+    let response = post("/orders/modify", "t7", modify(8)).await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["field"], "replacement.trader_id");
+
+    assert_eq!(post("/orders/modify", "t7", modify(7)).await.unwrap().status(), 200);
+    // Unbound keys (here an admin's) act for any trader.
+    let response = post("/orders/cancel", "a", serde_json::json!({ "order_id": 2 })).await.unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["canceled"], true);
+
+    let outcomes: Vec<_> = sink.events().iter().map(|e| format!("{} {}", e.action, e.outcome)).collect();
+    assert_eq!(
+        outcomes,
+        [
+            "order_submit forbidden",
+            "order_submit success",
+            "order_cancel forbidden",
+            "order_modify forbidden",
+            "order_modify forbidden",
+            "order_modify success",
+            "order_cancel success",
+        ]
+    );
+    // The replace is recorded with the order as it stood and as requested.
+    let events = sink.events();
+    let modified = events.iter().find(|e| e.action == AuditAction::OrderModify && e.outcome == "success").unwrap();
+    let resource = modified.resource.as_ref().unwrap();
+    assert_eq!((&resource["order_id"], &resource["replacement_order_id"]), (&serde_json::json!(1), &serde_json::json!(2)));
+    assert_eq!(resource["before"]["remaining_quantity"], "1");
+    assert_eq!(resource["before"]["status"], "New");
+    assert_eq!(resource["after"]["client_order_id"], "c2");
+    assert_eq!((&resource["after"]["price"], &resource["after"]["trader_id"]), (&serde_json::json!("99"), &serde_json::json!(7)));
+}
+
+#[tokio::test]
+async fn permissions_gate_routes_and_keys_are_managed_at_runtime() {
+    let keys = "adm:admin,ops:operator::halt_market,md:trader::view_market_data,desk:trader:9:submit_orders+cancel_any";
+    let (addr, _handle, sink) = spawn_app_with_audit_sink(Some(keys)).await;
+    let client = reqwest::Client::new();
+    let get = |path: &str, key: &str| {
+        client
+            .get(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .send()
+    };
+    let order = |order_id: u64, trader_id: u64| {
+        serde_json::json!({
+            "order_id": order_id,
+            "client_order_id": format!("c{}", order_id),
+            "instrument_id": 1,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": "99",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": trader_id
+        })
+    };
+
+    // Each route needs its permission, whatever the role.
+    assert_eq!(get("/admin/status", "ops").await.unwrap().status(), 200);
+    let response = get("/admin/instruments", "ops").await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "permission manage_instruments required");
+    assert_eq!(get("/book/1", "md").await.unwrap().status(), 200);
+    assert_eq!(get("/events", "md").await.unwrap().status(), 403);
+    assert_eq!(get("/events", "adm").await.unwrap().status(), 200);
+    let post = |path: &str, key: &str, body: serde_json::Value| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&body)
+            .send()
+    };
+    assert_eq!(post("/orders", "md", order(1, 1)).await.unwrap().status(), 403);
+    assert_eq!(post("/orders", "adm", order(1, 1)).await.unwrap().status(), 200);
+    assert_eq!(get("/book/1", "desk").await.unwrap().status(), 403);
+    // cancel_any: a key bound to trader 9 cancels trader 1's order.
+    let response = post("/orders/cancel", "desk", serde_json::json!({ "order_id": 1 })).await.unwrap();
+    assert_eq!(response.json::<serde_json::Value>().await.unwrap()["canceled"], true);
+
+    // Key management: admins only; changes apply to the next request.
+    assert_eq!(get("/admin/keys", "ops").await.unwrap().status(), 403);
+    // Keys are listed by id only.
+    let keys: serde_json::Value = get("/admin/keys", "adm").await.unwrap().json().await.unwrap();
+    assert_eq!(keys.as_array().unwrap().len(), 4);
+    let desk = keys.as_array().unwrap().iter().find(|k| k["key_id"] == KeyId::of("desk").to_string()).unwrap();
+    assert_eq!(desk["trader_id"], 9);
+    assert_eq!(desk["permissions"], serde_json::json!(["submit_orders", "cancel_any"]));
+    assert!(keys.as_array().unwrap().iter().all(|k| k.get("key").is_none()));
+    let put = |key: &str, body: serde_json::Value| {
+        client
+            .put(format!("http://{}/admin/keys/{}", addr, key))
+            .header("Authorization", "Bearer adm")
+            .json(&body)
+            .send()
+    };
+    let response = put("new", serde_json::json!({ "role": "operator", "permissions": ["manage_instruments"] })).await.unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(get("/admin/instruments", "new").await.unwrap().status(), 200);
+    assert_eq!(get("/admin/status", "new").await.unwrap().status(), 403);
+    // Without permissions, the role's defaults.
+    let response = put("md", serde_json::json!({ "role": "trader", "trader_id": 4 })).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["permissions"], serde_json::json!(["submit_orders", "view_market_data"]));
+    assert_eq!(post("/orders", "md", order(2, 4)).await.unwrap().status(), 200);
+    assert_eq!(put("bad", serde_json::json!({ "role": "king" })).await.unwrap().status(), 422);
+    assert_eq!(put("bad", serde_json::json!({ "role": "trader", "permissions": ["fly"] })).await.unwrap().status(), 422);
+
+    let delete = |key: &str| {
+        client
+            .delete(format!("http://{}/admin/keys/{}", addr, key))
+            .header("Authorization", "Bearer adm")
+            .send()
+    };
+    assert_eq!(delete("new").await.unwrap().status(), 200);
+    assert_eq!(delete("new").await.unwrap().status(), 404);
+    assert_eq!(get("/admin/instruments", "new").await.unwrap().status(), 401);
+    // A key can also be deleted by its listed id.
+    assert_eq!(delete(&KeyId::of("md").to_string()).await.unwrap().status(), 200);
+    assert_eq!(get("/book/1", "md").await.unwrap().status(), 401);
+
+    let events: Vec<_> = sink.events().into_iter().filter(|e| e.action.as_str().starts_with("key_")).collect();
+    let actions: Vec<_> = events.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, ["key_set", "key_set", "key_delete", "key_delete"]);
+    assert!(events.iter().all(|e| e.actor == KeyId::of("adm").to_string()));
+    assert_eq!(events[0].resource.as_ref().unwrap()["key_id"], KeyId::of("new").to_string());
+    let audited = serde_json::to_string(&sink.events()).unwrap();
+    assert!(!audited.contains("\"new\"") && !audited.contains("\"adm\""), "{}", audited);
+}
+
+#[tokio::test]
+async fn instrument_entitlements_limit_orders_and_market_data() {
+    let (addr, _handle) = spawn_app_with_auth(Some("adm:admin,one:trader:7::1,desk:trader:9:submit_orders+cancel_any:2")).await;
+    let client = reqwest::Client::new();
+    let get = |path: &str, key: &str| {
+        client
+            .get(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .send()
+    };
+    let post = |path: &str, key: &str, body: serde_json::Value| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&body)
+            .send()
+    };
+    let order = |order_id: u64, instrument_id: u64, trader_id: u64| {
+        serde_json::json!({
+            "order_id": order_id,
+            "client_order_id": format!("c{}", order_id),
+            "instrument_id": instrument_id,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": "99",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": trader_id
+        })
+    };
+    let add = post("/admin/instruments", "adm", serde_json::json!({ "instrument_id": 2, "symbol": "BAR" })).await.unwrap();
+    assert_eq!(add.status(), 201);
+
+    // Orders: only for entitled instruments.
+    assert_eq!(post("/orders", "one", order(1, 1, 7)).await.unwrap().status(), 200);
+    let response = post("/orders", "one", order(2, 2, 7)).await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "API key is not entitled to instrument 2");
+    assert_eq!(body["field"], "instrument_id");
+    let modify = serde_json::json!({ "order_id": 1, "replacement": order(3, 2, 7) });
+    assert_eq!(post("/orders/modify", "one", modify).await.unwrap().status(), 403);
+    // cancel_any covers other traders, not other instruments.
+    let response = post("/orders/cancel", "desk", serde_json::json!({ "order_id": 1 })).await.unwrap();
+    assert_eq!(response.status(), 403);
+    let response = post("/orders/cancel", "one", serde_json::json!({ "order_id": 1 })).await.unwrap();
+    assert_eq!(response.json::<serde_json::Value>().await.unwrap()["canceled"], true);
+
+    // Market data: only entitled instruments are served or listed.
+    assert_eq!(get("/book/1", "one").await.unwrap().status(), 200);
+    assert_eq!(get("/book/2", "one").await.unwrap().status(), 403);
+    assert_eq!(get("/ticker/2", "one").await.unwrap().status(), 403);
+    let tickers: serde_json::Value = get("/ticker", "one").await.unwrap().json().await.unwrap();
+    assert_eq!(tickers["tickers"].as_array().unwrap().len(), 1);
+    assert_eq!(tickers["tickers"][0]["instrument_id"], 1);
+    assert_eq!(get("/trades?instrument_id=1", "one").await.unwrap().status(), 200);
+    assert_eq!(get("/trades", "one").await.unwrap().status(), 403);
+    assert_eq!(get("/trades", "adm").await.unwrap().status(), 200);
+
+    // Entitlements are managed with the key.
+    let response = client
+        .put(format!("http://{}/admin/keys/one", addr))
+        .header("Authorization", "Bearer adm")
+        .json(&serde_json::json!({ "role": "trader", "trader_id": 7, "instruments": [2] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.json::<serde_json::Value>().await.unwrap()["instruments"], serde_json::json!([2]));
+    assert_eq!(get("/book/1", "one").await.unwrap().status(), 403);
+    assert_eq!(post("/orders", "one", order(4, 2, 7)).await.unwrap().status(), 200);
+}
+
+#[tokio::test]
+async fn failed_authentications_are_audited_and_lock_the_client_out() {
+    use dire_matching_engine::AuthThrottlePolicy;
+    use std::time::Duration;
+
+    let audit_sink = Arc::new(InMemoryAuditSink::new());
+    let state = api::create_app_state_with_sink(InstrumentId(1), audit_sink.clone());
+    let policy = AuthThrottlePolicy {
+        max_failures: 3,
+        lockout: Duration::from_millis(500),
+        max_lockout: Duration::from_secs(60),
+    };
+    let auth_config = AuthConfig::from_keys("good:trader").with_throttle_policy(policy);
+    let app = api::create_router_with_state_and_auth(state, Some(auth_config));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let get = |key: &str| {
+        client
+            .get(format!("http://{}/ticker", addr))
+            .header("Authorization", format!("Bearer {}", key))
+            .send()
+    };
+
+    // A success clears earlier failures: clients behind one address are not locked out by those before them.
+    assert_eq!(get("wrong1").await.unwrap().status(), 401);
+    assert_eq!(get("good").await.unwrap().status(), 200);
+    assert_eq!(get("wrong2").await.unwrap().status(), 401);
+    assert_eq!(get("wrong3").await.unwrap().status(), 401);
+    let response = client.get(format!("http://{}/ticker", addr)).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    // Locked out: even the right key gets 429 until the lockout ends.
+    let response = get("good").await.unwrap();
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()["retry-after"], "1");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "rate_limited");
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(get("good").await.unwrap().status(), 200);
+
+    let failures: Vec<_> = audit_sink.events().into_iter().filter(|e| e.action == AuditAction::AuthFailure).collect();
+    assert_eq!(failures.len(), 4);
+    let outcomes: Vec<_> = failures.iter().map(|e| e.outcome.as_str()).collect();
+    assert_eq!(outcomes, ["unauthorized", "unauthorized", "unauthorized", "locked_out"]);
+    let last = failures[3].resource.as_ref().unwrap();
+    assert_eq!((last["source"].as_str(), last["reason"].as_str()), (Some("127.0.0.1"), Some("missing")));
+    assert_eq!((last["failures"].as_u64(), last["lockout_ms"].as_u64()), (Some(3), Some(500)));
+    assert_eq!(failures[1].resource.as_ref().unwrap()["key_id"], KeyId::of("wrong2").to_string());
+}
+
+#[tokio::test]
+async fn keys_with_allowed_cidrs_work_only_from_those_addresses() {
+    let audit_sink = Arc::new(InMemoryAuditSink::new());
+    let state = api::create_app_state_with_sink(InstrumentId(1), audit_sink.clone());
+    let auth_config = AuthConfig::from_keys("adm:admin,local:trader,remote:trader")
+        .with_key_allowed_cidrs("local=127.0.0.0/8+::1,remote=10.0.0.0/8");
+    let app = api::create_router_with_state_and_auth(state, Some(auth_config));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let get = |key: &str| {
+        client
+            .get(format!("http://{}/ticker", addr))
+            .header("Authorization", format!("Bearer {}", key))
+            .send()
+    };
+    assert_eq!(get("local").await.unwrap().status(), 200);
+    let response = get("remote").await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "API key is not allowed from 127.0.0.1");
+    let failure = audit_sink.events().into_iter().find(|e| e.action == AuditAction::AuthFailure).expect("auth_failure");
+    assert_eq!((failure.source, failure.client_ip), (AuditSource::Rest, Some("127.0.0.1".parse().unwrap())));
+    assert_eq!((failure.outcome.as_str(), &failure.resource.unwrap()["reason"]), ("forbidden", &serde_json::json!("address_not_allowed")));
+
+    // Allowlists are managed with the key.
+    let response = client
+        .put(format!("http://{}/admin/keys/remote", addr))
+        .header("Authorization", "Bearer adm")
+        .json(&serde_json::json!({ "role": "trader", "allowed_cidrs": ["10.0.0.0/8", "127.0.0.1"] }))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["allowed_cidrs"], serde_json::json!(["10.0.0.0/8", "127.0.0.1/32"]));
+    assert_eq!(get("remote").await.unwrap().status(), 200);
+    let response = client
+        .put(format!("http://{}/admin/keys/remote", addr))
+        .header("Authorization", "Bearer adm")
+        .json(&serde_json::json!({ "role": "trader", "allowed_cidrs": ["10.0.0.0/40"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 422);
+}
+
+#[tokio::test]
+async fn browser_sessions_exchange_a_key_for_expiring_refreshable_tokens() {
+    use dire_matching_engine::SessionPolicy;
+    use std::time::Duration;
+
+    let audit_sink = Arc::new(InMemoryAuditSink::new());
+    let state = api::create_app_state_with_sink(InstrumentId(1), audit_sink.clone());
+    let policy = SessionPolicy {
+        ttl: Duration::from_millis(300),
+        refresh_ttl: Duration::from_secs(60),
+    };
+    let auth_config = AuthConfig::from_keys("adm:admin,web:trader:7").with_session_policy(policy);
+    let app = api::create_router_with_state_and_auth(state, Some(auth_config));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let post = |path: &str, credential: &str| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", credential))
+            .send()
+    };
+    let get_ticker = |credential: &str| {
+        client
+            .get(format!("http://{}/ticker", addr))
+            .header("Authorization", format!("Bearer {}", credential))
+            .send()
+    };
+
+    let response = post("/auth/login", "web").await.unwrap();
+    assert_eq!(response.status(), 200);
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    assert_eq!((tokens["expires_in"].as_u64(), tokens["refresh_expires_in"].as_u64()), (Some(0), Some(60)));
+    let token = tokens["token"].as_str().unwrap().to_string();
+    let refresh_token = tokens["refresh_token"].as_str().unwrap().to_string();
+
+    // The token acts as its key: bound to trader 7, not an admin.
+    assert_eq!(get_ticker(&token).await.unwrap().status(), 200);
+    let order = serde_json::json!({
+        "order_id": 1, "client_order_id": "c1", "instrument_id": 1, "side": "Buy", "order_type": "Limit",
+        "quantity": "1", "price": "100", "time_in_force": "GTC", "timestamp": 1, "trader_id": 8
+    });
+    let response = client
+        .post(format!("http://{}/orders", addr))
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&order)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    let response = client
+        .get(format!("http://{}/admin/keys", addr))
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    // Only a key logs in; a refresh token only refreshes.
+    assert_eq!(post("/auth/login", &token).await.unwrap().status(), 403);
+    assert_eq!(get_ticker(&refresh_token).await.unwrap().status(), 401);
+    assert_eq!(post("/auth/refresh", "web").await.unwrap().status(), 401);
+
+    // Expired: refresh for a new pair; the old refresh token is spent.
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let response = get_ticker(&token).await.unwrap();
+    assert_eq!(response.status(), 401);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "invalid API key or session token");
+    let response = post("/auth/refresh", &refresh_token).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let renewed: serde_json::Value = response.json().await.unwrap();
+    let token = renewed["token"].as_str().unwrap().to_string();
+    assert_eq!(get_ticker(&token).await.unwrap().status(), 200);
+    assert_eq!(post("/auth/refresh", &refresh_token).await.unwrap().status(), 401);
+
+    // Logout ends the session.
+    let response = post("/auth/logout", &token).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(get_ticker(&token).await.unwrap().status(), 401);
+    let refresh_token = renewed["refresh_token"].as_str().unwrap();
+    assert_eq!(post("/auth/refresh", refresh_token).await.unwrap().status(), 401);
+
+    let actions: Vec<_> = audit_sink
+        .events()
+        .into_iter()
+        .filter(|e| e.action == AuditAction::Login || e.action == AuditAction::Logout)
+        .map(|e| (e.actor, e.action))
+        .collect();
+    let web = KeyId::of("web").to_string();
+    assert_eq!(actions, [(web.clone(), AuditAction::Login), (web, AuditAction::Logout)]);
+}
+
 // --- Phase 3 §3: Audit trail ---
 
+#[tokio::test]
+async fn admin_audit_queries_the_stored_trail_by_actor_action_and_time() {
+    use dire_matching_engine::audit::{AuditBufferPolicy, BufferedAuditSink, FileAuditStore};
+    let path = std::env::temp_dir().join(format!("dire_audit_{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = Arc::new(FileAuditStore::open(&path).unwrap());
+    let sink = Arc::new(BufferedAuditSink::new(store.clone(), AuditBufferPolicy::default()));
+    let state = api::create_app_state_with_sink(InstrumentId(1), sink);
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin,o:operator,t:trader")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let set_state = |key: &'static str, state: &'static str| {
+        client
+            .post(format!("http://{}/admin/market-state", addr))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&serde_json::json!({ "state": state }))
+            .send()
+    };
+    let audit = |key: &'static str, query: String| {
+        client
+            .get(format!("http://{}/admin/audit{}", addr, query))
+            .header("Authorization", format!("Bearer {}", key))
+            .send()
+    };
+    let (admin, operator) = (KeyId::of("a").to_string(), KeyId::of("o").to_string());
+
+    assert_eq!(set_state("o", "Halted").await.unwrap().status(), 200);
+    assert_eq!(set_state("a", "Open").await.unwrap().status(), 200);
+    assert_eq!(audit("t", String::new()).await.unwrap().status(), 403);
+    let (open, _open_handle) = spawn_app().await;
+    let resp = client.get(format!("http://{}/admin/audit", open)).send().await.unwrap();
+    assert_eq!(resp.status(), 403, "auth disabled: the audit trail is not anonymous");
+
+    // Who halted the market: newest first, straight after the change (queued events are written first).
+    let resp = audit("a", "?action=market_state_change".to_string()).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let events = resp.json::<serde_json::Value>().await.unwrap()["events"].clone();
+    let trail: Vec<_> = events.as_array().unwrap().iter().map(|e| (e["actor"].clone(), e["resource"]["state"].clone())).collect();
+    assert_eq!(trail, [(serde_json::json!(admin), serde_json::json!("Open")), (serde_json::json!(operator), serde_json::json!("Halted"))]);
+    let at = events[1]["timestamp_secs"].as_u64().unwrap();
+
+    let count = |body: serde_json::Value| body["events"].as_array().unwrap().len();
+    let query = format!("?actor={}&from={}&to={}", operator, at, at + 1);
+    assert_eq!(count(audit("o", query).await.unwrap().json().await.unwrap()), 1);
+    assert_eq!(count(audit("o", format!("?to={}", at - 1)).await.unwrap().json().await.unwrap()), 0);
+    assert_eq!(count(audit("o", "?limit=1".to_string()).await.unwrap().json().await.unwrap()), 1);
+    assert_eq!(audit("o", "?from=soon".to_string()).await.unwrap().status(), 400);
+    assert_eq!(store.len(), 2);
+
+    // Without a store there is nothing to query.
+    let (plain, _handle) = spawn_app_with_auth(Some("a:admin")).await;
+    let resp = client
+        .get(format!("http://{}/admin/audit", plain))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 409);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn admin_audit_has_the_exact_before_and_after_of_order_modifies_and_config_changes() {
+    use dire_matching_engine::audit::{AuditBufferPolicy, BufferedAuditSink, FileAuditStore};
+    let path = std::env::temp_dir().join(format!("dire_audit_diff_{}.ndjson", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = Arc::new(FileAuditStore::open(&path).unwrap());
+    let sink = Arc::new(BufferedAuditSink::new(store, AuditBufferPolicy::default()));
+    let state = api::create_app_state_with_sink(InstrumentId(1), sink);
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin,t7:trader:7,t8:trader:8")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let send = |method: reqwest::Method, path: &str, key: &str, body: Option<serde_json::Value>| {
+        let request = client
+            .request(method, format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key));
+        match body {
+            Some(body) => request.json(&body).send(),
+            None => request.send(),
+        }
+    };
+    let order = |order_id: u64, client_order_id: &str, side: &str, quantity: &str, price: &str, trader_id: u64| {
+        serde_json::json!({
+            "order_id": order_id,
+            "client_order_id": client_order_id,
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": quantity,
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": order_id,
+            "trader_id": trader_id
+        })
+    };
+    let audited = |action: &'static str| {
+        let request = send(reqwest::Method::GET, &format!("/admin/audit?action={}", action), "a", None);
+        async move {
+            let body: serde_json::Value = request.await.unwrap().json().await.unwrap();
+            let events = body["events"].as_array().unwrap().clone();
+            assert_eq!(events.len(), 1, "{} events: {:?}", action, events);
+            events[0]["resource"].clone()
+        }
+    };
+
+    // A partly filled order is replaced at a new price and size.
+    assert_eq!(send(reqwest::Method::POST, "/orders", "t7", Some(order(1, "c1", "Sell", "10", "100", 7))).await.unwrap().status(), 200);
+    assert_eq!(send(reqwest::Method::POST, "/orders", "t8", Some(order(2, "c2", "Buy", "4", "100", 8))).await.unwrap().status(), 200);
+    let modify = serde_json::json!({ "order_id": 1, "replacement": order(3, "c1b", "Sell", "5", "101", 7) });
+    assert_eq!(send(reqwest::Method::POST, "/orders/modify", "t7", Some(modify)).await.unwrap().status(), 200);
+    assert_eq!(
+        audited("order_modify").await,
+        serde_json::json!({
+            "order_id": 1,
+            "replacement_order_id": 3,
+            "before": {
+                "instrument_id": 1,
+                "side": "Sell",
+                "price": "100",
+                "quantity": "10",
+                "filled_quantity": "4",
+                "remaining_quantity": "6",
+                "trader_id": 7,
+                "status": "PartiallyFilled"
+            },
+            "after": {
+                "client_order_id": "c1b",
+                "instrument_id": 1,
+                "side": "Sell",
+                "order_type": "Limit",
+                "price": "101",
+                "quantity": "5",
+                "time_in_force": "GTC",
+                "trader_id": 7
+            }
+        })
+    );
+
+    // A config patch records the patch and the whole config on either side of it.
+    let before: serde_json::Value = send(reqwest::Method::GET, "/admin/config", "a", None).await.unwrap().json().await.unwrap();
+    let patch = serde_json::json!({ "version": 0, "risk": { "max_order_quantity": 500 } });
+    let response = send(reqwest::Method::PATCH, "/admin/config", "a", Some(patch.clone())).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let after: serde_json::Value = response.json().await.unwrap();
+    let mut expected_after = before.clone();
+    expected_after["version"] = serde_json::json!(1);
+    expected_after["risk"]["max_order_quantity"] = serde_json::json!("500");
+    assert_eq!(after, expected_after);
+    assert_eq!(
+        audited("config_change").await,
+        serde_json::json!({ "version": 1, "patch": patch, "before": before, "after": after })
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
 #[tokio::test]
 async fn audit_order_submit_emits_event() {
     let (addr, _handle, sink) = spawn_app_with_audit_sink(None).await;
@@ -358,6 +1028,7 @@ async fn audit_order_submit_emits_event() {
     let client = reqwest::Client::new();
     let response = client
         .post(&url)
+        .header("X-Request-Id", "req-42")
         .json(&serde_json::json!({
             "order_id": 42,
             "client_order_id": "c42",
@@ -374,10 +1045,13 @@ async fn audit_order_submit_emits_event() {
         .await
         .unwrap();
     assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-request-id"], "req-42");
     let events = sink.events();
     assert_eq!(events.len(), 1);
-    assert_eq!(events[0].action, "order_submit");
+    assert_eq!(events[0].action, AuditAction::OrderSubmit);
     assert_eq!(events[0].outcome, "success");
+    assert_eq!((events[0].schema_version, events[0].source), (2, AuditSource::Rest));
+    assert_eq!(events[0].request_id.as_deref(), Some("req-42"));
     assert_eq!(
         events[0].resource.as_ref().and_then(|r| r.get("order_id").and_then(|v| v.as_u64())),
         Some(42)
@@ -567,52 +1241,1659 @@ async fn admin_instruments_add_list_delete() {
 }
 
 #[tokio::test]
-async fn admin_config_get_and_patch() {
-    let (addr, _handle) = spawn_app_with_auth(Some("a:admin")).await;
+async fn admin_instrument_tick_size_rejects_off_tick_prices() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin,t:trader")).await;
     let client = reqwest::Client::new();
-    let auth = "Bearer a";
 
-    let get0 = client
-        .get(format!("http://{}/admin/config", addr))
-        .header("Authorization", auth)
+    let add = client
+        .post(format!("http://{}/admin/instruments", addr))
+        .header("Authorization", "Bearer a")
+        .json(&serde_json::json!({ "instrument_id": 2, "tick_size": "0.05" }))
         .send()
         .await
         .unwrap();
-    assert_eq!(get0.status(), 200);
-    let empty: serde_json::Value = get0.json().await.unwrap();
-    assert!(empty.as_object().map(|o| o.is_empty()).unwrap_or(false));
+    assert_eq!(add.status(), 201);
 
-    let patch = client
-        .patch(format!("http://{}/admin/config", addr))
-        .header("Authorization", auth)
-        .json(&serde_json::json!({ "max_order_quantity": 500 }))
+    let list: Vec<serde_json::Value> = client
+        .get(format!("http://{}/admin/instruments", addr))
+        .header("Authorization", "Bearer a")
         .send()
         .await
+        .unwrap()
+        .json()
+        .await
         .unwrap();
-    assert_eq!(patch.status(), 200);
+    let inst2 = list.iter().find(|o| o["instrument_id"] == 2).unwrap();
+    assert_eq!(inst2["tick_size"], "0.05");
 
-    let get1 = client
-        .get(format!("http://{}/admin/config", addr))
-        .header("Authorization", auth)
+    let order = |id: u64, price: &str| {
+        serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 2,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": id,
+            "trader_id": 1
+        })
+    };
+    let off_tick = client
+        .post(format!("http://{}/orders", addr))
+        .header("Authorization", "Bearer t")
+        .json(&order(1, "100.03"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(off_tick.status(), 400);
+    let body: serde_json::Value = off_tick.json().await.unwrap();
+    assert!(body["message"].as_str().unwrap().contains("tick size"), "{:?}", body);
+    assert_eq!(body["code"], "invalid_price");
+
+    let on_tick = client
+        .post(format!("http://{}/orders", addr))
+        .header("Authorization", "Bearer t")
+        .json(&order(2, "100.05"))
         .send()
         .await
         .unwrap();
-    assert_eq!(get1.status(), 200);
-    let config: serde_json::Value = get1.json().await.unwrap();
-    assert_eq!(config.get("max_order_quantity").and_then(|v| v.as_u64()), Some(500));
+    assert_eq!(on_tick.status(), 200);
 }
 
-/// Trader cannot change market state (RBAC: admin/operator only).
 #[tokio::test]
-async fn integration_trader_cannot_set_market_state() {
-    let (addr, _handle) = spawn_app_with_auth(Some("t:trader,a:admin")).await;
+async fn admin_book_uncross_matches_restored_crossed_book() {
+    let state = api::create_app_state(InstrumentId(1));
+    let resting = |id: u64, side: dire_matching_engine::Side, price: i64, trader: u64| dire_matching_engine::RestingOrder {
+        order_id: dire_matching_engine::OrderId(id),
+        instrument_id: InstrumentId(1),
+        side,
+        price: rust_decimal::Decimal::from(price),
+        quantity: rust_decimal::Decimal::from(5),
+        trader_id: dire_matching_engine::TraderId(trader),
+        client_order_id: format!("c{}", id),
+        order_type: dire_matching_engine::OrderType::Limit,
+        time_in_force: dire_matching_engine::TimeInForce::GTC,
+        timestamp: id,
+    };
+    state
+        .engine
+        .lock()
+        .unwrap()
+        .load_from_snapshot(dire_matching_engine::EngineSnapshot {
+            version: dire_matching_engine::ENGINE_SNAPSHOT_VERSION,
+            instruments: vec![(InstrumentId(1), None)],
+            books: vec![(
+                InstrumentId(1),
+                vec![resting(1, dire_matching_engine::Side::Buy, 101, 1), resting(2, dire_matching_engine::Side::Sell, 100, 2)],
+            )],
+            order_to_instrument: vec![],
+            next_trade_id: 1,
+            next_exec_id: 1,
+            next_seq: 1,
+            tick_sizes: vec![],
+            scheduler: Default::default(),
+            order_fills: vec![],
+            instrument_states: vec![],
+            instrument_market_states: vec![],
+            quotes: vec![],
+            session_stats: vec![],
+            lot_sizes: vec![],
+            price_bands: vec![],
+            command_seq: 0,
+            next_order_id: 0,
+        })
+        .unwrap();
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
     let client = reqwest::Client::new();
-    let resp = client
-        .post(format!("http://{}/admin/market-state", addr))
-        .header("Authorization", "Bearer t")
-        .json(&serde_json::json!({ "state": "Halted" }))
+
+    let res = client
+        .post(format!("http://{}/admin/book/1/uncross", addr))
+        .header("Authorization", "Bearer a")
         .send()
         .await
         .unwrap();
-    assert_eq!(resp.status(), 403);
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["crossed"], false);
+    let trades = body["trades"].as_array().unwrap();
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0]["price"], "100");
+    assert_eq!(trades[0]["quantity"], "5");
+
+    let unknown = client
+        .post(format!("http://{}/admin/book/9/uncross", addr))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown.status(), 404);
+}
+
+#[tokio::test]
+async fn get_order_returns_resting_state_then_canceled_status() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    let order = serde_json::json!({
+        "order_id": 5,
+        "client_order_id": "c5",
+        "instrument_id": 1,
+        "side": "Sell",
+        "order_type": "Limit",
+        "quantity": "4",
+        "price": "101",
+        "time_in_force": "GTC",
+        "timestamp": 1,
+        "trader_id": 1
+    });
+    let resp = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = client.get(format!("http://{}/orders/5", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json.get("order_id").and_then(|v| v.as_u64()), Some(5));
+    assert_eq!(json.get("instrument_id").and_then(|v| v.as_u64()), Some(1));
+    assert_eq!(json.get("side").and_then(|v| v.as_str()), Some("Sell"));
+    assert_eq!(json.get("price").and_then(|v| v.as_str()), Some("101"));
+    assert_eq!(json.get("remaining_quantity").and_then(|v| v.as_str()), Some("4"));
+    assert_eq!(json.get("queue_position").and_then(|v| v.as_u64()), Some(0));
+    assert_eq!(json.get("level").and_then(|v| v.as_u64()), Some(0));
+    assert_eq!(json.get("quantity_ahead").and_then(|v| v.as_str()), Some("0"));
+    assert_eq!(json.get("status").and_then(|v| v.as_str()), Some("New"));
+
+    let resp = client
+        .post(format!("http://{}/orders/cancel", addr))
+        .json(&serde_json::json!({ "order_id": 5 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let resp = client.get(format!("http://{}/orders/5", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json.get("status").and_then(|v| v.as_str()), Some("Canceled"));
+    assert_eq!(json.get("remaining_quantity").and_then(|v| v.as_str()), Some("0"));
+    assert!(json.get("queue_position").is_none());
+
+    let resp = client.get(format!("http://{}/orders/999", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn list_open_orders_filters_by_trader() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    for (id, trader, price) in [(1, 7, "99"), (2, 8, "98"), (3, 7, "97")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": trader
+        });
+        let resp = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+    let _ = client
+        .post(format!("http://{}/orders/cancel", addr))
+        .json(&serde_json::json!({ "order_id": 3 }))
+        .send()
+        .await
+        .unwrap();
+
+    let resp = client.get(format!("http://{}/orders?trader_id=7", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json.get("trader_id").and_then(|v| v.as_u64()), Some(7));
+    let orders = json.get("orders").and_then(|v| v.as_array()).unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].get("order_id").and_then(|v| v.as_u64()), Some(1));
+    assert_eq!(orders[0].get("status").and_then(|v| v.as_str()), Some("New"));
+
+    let resp = client.get(format!("http://{}/orders", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn list_trades_returns_recent_trades() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    for (id, side) in [(1, "Sell"), (2, "Buy"), (3, "Sell"), (4, "Buy")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": "2",
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": id
+        });
+        let resp = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+    let trade_ids = |json: serde_json::Value| -> Vec<u64> {
+        json["trades"].as_array().unwrap().iter().map(|t| t["trade_id"].as_u64().unwrap()).collect()
+    };
+
+    let json: serde_json::Value = client.get(format!("http://{}/trades", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(json["trades"][0]["buy_order_id"].as_u64(), Some(2));
+    assert_eq!(json["trades"][0]["quantity"].as_str(), Some("2"));
+    assert_eq!(trade_ids(json), vec![1, 2]);
+
+    let json = client.get(format!("http://{}/trades?since=1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(trade_ids(json), vec![2]);
+
+    let json = client.get(format!("http://{}/trades?instrument_id=1&limit=1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(trade_ids(json), vec![2]);
+
+    let json = client.get(format!("http://{}/trades?instrument_id=2", addr)).send().await.unwrap().json().await.unwrap();
+    assert!(trade_ids(json).is_empty());
+
+    // Paging back through the tape: the most recent trades below `before`.
+    let json = client.get(format!("http://{}/trades?before=3&limit=1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(trade_ids(json), vec![2]);
+    let json = client.get(format!("http://{}/trades?before=2&instrument_id=1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(trade_ids(json), vec![1]);
+}
+
+#[tokio::test]
+async fn positions_endpoint_reports_net_fills() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    for (id, side, qty, trader) in [(1, "Sell", "5", 7), (2, "Buy", "3", 8)] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": qty,
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": trader
+        });
+        let resp = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    let json: serde_json::Value = client.get(format!("http://{}/positions?trader_id=7", addr)).send().await.unwrap().json().await.unwrap();
+    let positions = json["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0]["net_quantity"].as_str(), Some("-3"));
+    assert_eq!(positions[0]["avg_entry_price"].as_str(), Some("100"));
+    assert_eq!(positions[0]["open_sell_quantity"].as_str(), Some("2"));
+
+    let json: serde_json::Value = client
+        .get(format!("http://{}/positions?trader_id=8&instrument_id=1", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(json["positions"][0]["net_quantity"].as_str(), Some("3"));
+
+    let resp = client.get(format!("http://{}/positions", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn admin_book_returns_orders_in_queue_order() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin")).await;
+    let client = reqwest::Client::new();
+    let auth = "Bearer a";
+
+    for (id, trader) in [(1, 7), (2, 8)] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "3",
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": id,
+            "trader_id": trader
+        });
+        let resp = client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", auth)
+            .json(&order)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    let resp = client
+        .get(format!("http://{}/admin/book/1", addr))
+        .header("Authorization", auth)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    let orders = json.get("orders").and_then(|o| o.as_array()).unwrap();
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].get("order_id").and_then(|v| v.as_u64()), Some(1));
+    assert_eq!(orders[0].get("queue_position").and_then(|v| v.as_u64()), Some(0));
+    assert_eq!(orders[1].get("order_id").and_then(|v| v.as_u64()), Some(2));
+    assert_eq!(orders[1].get("queue_position").and_then(|v| v.as_u64()), Some(1));
+    assert_eq!(orders[1].get("trader_id").and_then(|v| v.as_u64()), Some(8));
+    assert_eq!(orders[1].get("remaining_quantity").and_then(|v| v.as_str()), Some("3"));
+
+    let missing = client
+        .get(format!("http://{}/admin/book/99", addr))
+        .header("Authorization", auth)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
+
+#[tokio::test]
+async fn admin_config_get_and_patch() {
+    let (addr, _handle, sink) = spawn_app_with_audit_sink(Some("a:admin")).await;
+    let client = reqwest::Client::new();
+    let auth = "Bearer a";
+    let patch = |body: serde_json::Value| {
+        client
+            .patch(format!("http://{}/admin/config", addr))
+            .header("Authorization", auth)
+            .json(&body)
+            .send()
+    };
+
+    let get0 = client
+        .get(format!("http://{}/admin/config", addr))
+        .header("Authorization", auth)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(get0.status(), 200);
+    let config: serde_json::Value = get0.json().await.unwrap();
+    assert_eq!(config["version"], 0);
+    assert!(config["risk"]["max_order_quantity"].is_null());
+    assert!(config["session"]["open"].is_null());
+
+    let resp = patch(serde_json::json!({
+        "version": 0,
+        "risk": { "max_order_quantity": 500 },
+        "session": { "open": "00:00", "close": "23:59" }
+    }))
+    .await
+    .unwrap();
+    assert_eq!(resp.status(), 200);
+    let config: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(config["version"], 1);
+    assert_eq!(config["risk"]["max_order_quantity"], "500");
+    assert_eq!(config["session"]["close"], "23:59");
+
+    let get1 = client
+        .get(format!("http://{}/admin/config", addr))
+        .header("Authorization", auth)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(get1.json::<serde_json::Value>().await.unwrap(), config);
+    let events = sink.events();
+    let change = events.iter().find(|e| e.action == AuditAction::ConfigChange).expect("config_change audited");
+    let resource = change.resource.as_ref().unwrap();
+    assert_eq!(resource["version"], 1);
+    assert_eq!((&resource["before"]["version"], &resource["after"]["version"]), (&serde_json::json!(0), &serde_json::json!(1)));
+    assert!(resource["before"]["risk"]["max_order_quantity"].is_null());
+    assert_eq!(resource["after"], config);
+
+    // A stale version conflicts; bad values name their field; nothing is applied.
+    let resp = patch(serde_json::json!({ "version": 0, "risk": { "max_position": 1 } })).await.unwrap();
+    assert_eq!(resp.status(), 409);
+    assert_eq!(resp.json::<serde_json::Value>().await.unwrap()["field"], "version");
+    for (body, field) in [
+        (serde_json::json!({ "risk": { "max_order_quantity": "lots" } }), "risk.max_order_quantity"),
+        (serde_json::json!({ "bands": { "max_deviation_pct": -5 } }), "bands.max_deviation_pct"),
+        (serde_json::json!({ "session": { "open": "25:00" } }), "session.open"),
+        (serde_json::json!({ "max_order_quantity": 1 }), "max_order_quantity"),
+    ] {
+        let resp = patch(body).await.unwrap();
+        assert_eq!(resp.status(), 422);
+        let json: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!((json["code"].as_str(), json["field"].as_str()), (Some("invalid_field"), Some(field)));
+    }
+    let get2 = client
+        .get(format!("http://{}/admin/config", addr))
+        .header("Authorization", auth)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(get2.json::<serde_json::Value>().await.unwrap()["version"], 1);
+}
+
+#[tokio::test]
+async fn admin_config_max_order_quantity_is_enforced() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin,t:trader")).await;
+    let client = reqwest::Client::new();
+    let patch = |body: serde_json::Value| {
+        client
+            .patch(format!("http://{}/admin/config", addr))
+            .header("Authorization", "Bearer a")
+            .json(&body)
+            .send()
+    };
+    let submit = |id: u64, qty: &str| {
+        client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", "Bearer t")
+            .json(&serde_json::json!({
+                "order_id": id,
+                "client_order_id": format!("c{}", id),
+                "instrument_id": 1,
+                "side": "Buy",
+                "order_type": "Limit",
+                "quantity": qty,
+                "price": "100",
+                "time_in_force": "GTC",
+                "timestamp": 1,
+                "trader_id": 1
+            }))
+            .send()
+    };
+
+    assert_eq!(patch(serde_json::json!({ "risk": { "max_order_quantity": 500 } })).await.unwrap().status(), 200);
+    let resp = submit(1, "600").await.unwrap();
+    assert_eq!(resp.status(), 400);
+    let json: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(json["message"].as_str(), Some("Order quantity 600 exceeds max order quantity 500"));
+    assert_eq!(json["code"], "risk_limit");
+    assert_eq!(submit(2, "500").await.unwrap().status(), 200);
+
+    assert_eq!(patch(serde_json::json!({ "risk": { "max_order_quantity": "lots" } })).await.unwrap().status(), 422);
+    assert_eq!(patch(serde_json::json!({ "risk": { "max_order_quantity": null } })).await.unwrap().status(), 200);
+    assert_eq!(submit(3, "600").await.unwrap().status(), 200);
+}
+
+#[tokio::test]
+async fn admin_config_rate_limits_order_entry_per_key() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin,t:trader,u:trader")).await;
+    let client = reqwest::Client::new();
+    let resp = client
+        .patch(format!("http://{}/admin/config", addr))
+        .header("Authorization", "Bearer a")
+        .json(&serde_json::json!({ "rate_limits": { "orders_per_second": 2 } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let cancel = |key: &'static str, id: u64| {
+        client
+            .post(format!("http://{}/orders/cancel", addr))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&serde_json::json!({ "order_id": id }))
+            .send()
+    };
+
+    // Unknown orders still count: the limit is on requests, not accepted orders. Five quick requests span at
+    // most two one-second windows, so at most four get through.
+    let mut limited = None;
+    let mut allowed = 0;
+    for id in 1..=5 {
+        let resp = cancel("t", id).await.unwrap();
+        match resp.status().as_u16() {
+            429 => limited = limited.or(Some(resp)),
+            _ => allowed += 1,
+        }
+    }
+    assert!(allowed <= 4, "{} requests allowed", allowed);
+    let limited = limited.expect("rate limit applied");
+    assert_eq!(limited.headers()["retry-after"], "1");
+    let json: serde_json::Value = limited.json().await.unwrap();
+    assert_eq!(json["code"], "rate_limited");
+    assert_ne!(cancel("u", 1).await.unwrap().status(), 429, "other keys have their own budget");
+    let list = client
+        .get(format!("http://{}/orders?trader_id=1", addr))
+        .header("Authorization", "Bearer t")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(list.status(), 200, "reads are not rate limited");
+}
+
+#[tokio::test]
+async fn admin_config_is_persisted_and_reapplied_on_restart() {
+    let path = std::env::temp_dir().join(format!("dire_config_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let state = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let resp = reqwest::Client::new()
+        .patch(format!("http://{}/admin/config", addr))
+        .header("Authorization", "Bearer a")
+        .json(&serde_json::json!({ "risk": { "max_order_quantity": 5 }, "bands": { "max_deviation_pct": "2.5" } }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    // Saved before the response, without a snapshotter: admin changes are not debounced.
+    let saved = dire_matching_engine::persistence::FilePersistence::new(&path).load().unwrap().unwrap();
+    assert_eq!(saved.venue_config.map(|config| config.version), Some(1));
+
+    let restarted = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let config = restarted.venue_config.lock().unwrap().clone();
+    assert_eq!(config.version, 1);
+    assert_eq!(config.bands.max_deviation_pct, Some(rust_decimal::Decimal::new(25, 1)));
+    assert_eq!(restarted.engine.lock().unwrap().risk_limits().max_order_quantity, Some(rust_decimal::Decimal::from(5)));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("json.ids"));
+}
+
+#[tokio::test]
+async fn engine_events_reach_app_state_subscribers() {
+    let state = api::create_app_state(InstrumentId(1));
+    let mut events = state.subscribe_events();
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin,t:trader")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let order = serde_json::json!({
+        "order_id": 1,
+        "client_order_id": "c1",
+        "instrument_id": 1,
+        "side": "Buy",
+        "order_type": "Limit",
+        "quantity": "1",
+        "price": "100",
+        "time_in_force": "GTC",
+        "timestamp": 1,
+        "trader_id": 1
+    });
+    let resp = client
+        .post(format!("http://{}/orders", addr))
+        .header("Authorization", "Bearer t")
+        .json(&order)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let resp = client
+        .post(format!("http://{}/admin/emergency-halt", addr))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let mut types = Vec::new();
+    for _ in 0..4 {
+        let event = serde_json::to_value(events.recv().await.unwrap()).unwrap();
+        types.push(event["type"].as_str().unwrap().to_string());
+        if event["type"] == "StateChange" {
+            assert_eq!(event["state"].as_str(), Some("Halted"));
+        }
+    }
+    assert_eq!(types, vec!["OrderAccepted", "Report", "BookChanged", "StateChange"]);
+}
+
+#[tokio::test]
+async fn admin_shutdown_closes_market_and_saves_the_state_to_resume() {
+    let path = std::env::temp_dir().join(format!("dire_shutdown_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let state = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let app = api::create_router_with_state_and_auth(state.clone(), Some(AuthConfig::from_keys("a:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let post = |path: &'static str, body: serde_json::Value| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", "Bearer a")
+            .json(&body)
+            .send()
+    };
+    let resp = post("/admin/market-state", serde_json::json!({ "state": "Halted" })).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let resp = post("/admin/shutdown", serde_json::json!({})).await.unwrap();
+    assert_eq!(resp.status(), 202);
+    assert_eq!(*state.market_state.lock().unwrap(), dire_matching_engine::MarketState::Closed);
+    assert!(!state.begin_shutdown("again", &AuditContext::internal()), "already shutting down");
+
+    state.flush();
+    let restarted = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    assert_eq!(*restarted.market_state.lock().unwrap(), dire_matching_engine::MarketState::Halted);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("json.ids"));
+}
+
+#[tokio::test]
+async fn orders_accepted_since_the_last_save_are_replayed_from_the_command_log_on_restart() {
+    use dire_matching_engine::MatchingEngine;
+    let path = std::env::temp_dir().join(format!("dire_wal_restart_{}.json", std::process::id()));
+    let wal = path.with_extension("json.wal");
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&wal);
+    let state = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let app = api::create_router_with_state_and_auth(state.clone(), None);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let order = |id: u64, side: &str, quantity: &str| {
+        serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": quantity,
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": id,
+            "trader_id": id
+        })
+    };
+    let resp = reqwest::Client::new()
+        .post(format!("http://{}/orders", addr))
+        .json(&order(1, "Sell", "5"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    // Saved, so the log is emptied.
+    state.flush();
+    assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+
+    // A crash between matching and the save: the order is in the log but not the state file.
+    let crossing = serde_json::from_value(order(2, "Buy", "2")).unwrap();
+    state.engine.lock().unwrap().submit_order(crossing).unwrap();
+    assert_eq!(std::fs::read_to_string(&wal).unwrap().lines().count(), 1);
+
+    // The restart replays it on top of the state file, trading once, then saves and empties the log.
+    let restarted = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let book = |state: &api::AppState| {
+        let engine = state.engine.lock().unwrap();
+        let orders = engine.book_orders(InstrumentId(1)).unwrap();
+        (orders.len(), orders[0].order_id.0, orders[0].remaining_quantity, engine.command_seq())
+    };
+    assert_eq!(book(&restarted), (1, 1, rust_decimal::Decimal::from(3), 2));
+    assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+
+    // Nothing is replayed twice.
+    drop(restarted);
+    let again = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    assert_eq!(book(&again), (1, 1, rust_decimal::Decimal::from(3), 2));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&wal);
+    let _ = std::fs::remove_file(path.with_extension("json.ids"));
+}
+
+#[test]
+fn snapshotter_saves_state_in_the_background_and_keeps_the_newest_copies() {
+    use dire_matching_engine::persistence::{FilePersistence, SnapshotPolicy};
+    use dire_matching_engine::{MatchingEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+    let dir = std::env::temp_dir().join(format!("dire_snapshotter_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("state.json");
+    let state = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let policy = SnapshotPolicy {
+        debounce: None,
+        interval: None,
+        every_events: Some(2),
+        retain: 1,
+    };
+    assert!(api::start_snapshotter(&state, SnapshotPolicy::default()).is_none());
+    let snapshotter = api::start_snapshotter(&state, policy).unwrap();
+    let persistence = FilePersistence::new(&path);
+    let submit = |id: u64| {
+        let order = Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: rust_decimal::Decimal::from(1),
+            price: Some(rust_decimal::Decimal::from(100 - id as i64)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(1),
+        };
+        // Straight to the engine, which (unlike the REST handlers) does not save.
+        state.engine.lock().unwrap().submit_order(order).unwrap();
+        state.engine.lock().unwrap().last_seq()
+    };
+    // Copies are pruned just after the new one is written: wait for both.
+    let wait_for_copy = |seq: u64| {
+        for _ in 0..50 {
+            let copies = persistence.snapshots().unwrap();
+            if copies.iter().any(|(copy, _)| *copy == seq) && copies.len() <= policy.retain {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("no snapshot at seq {}", seq);
+    };
+
+    let seq = submit(1);
+    wait_for_copy(seq);
+    let saved = persistence.load().unwrap().unwrap();
+    assert_eq!(saved.engine.next_seq, seq + 1);
+    assert_eq!(std::fs::metadata(path.with_extension("json.wal")).unwrap().len(), 0);
+
+    let seq = submit(2);
+    wait_for_copy(seq);
+    assert_eq!(persistence.snapshots().unwrap().len(), 1, "only the newest copy is kept");
+    let copy = FilePersistence::new(&persistence.snapshots().unwrap()[0].1).load().unwrap().unwrap();
+    assert_eq!(copy.engine.next_seq, seq + 1);
+
+    // A shutdown stops it.
+    assert!(state.begin_shutdown("test", &AuditContext::internal()));
+    snapshotter.join().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+
+#[test]
+fn snapshotter_saves_changes_made_outside_rest_once_they_settle() {
+    use dire_matching_engine::persistence::{FilePersistence, SnapshotPolicy};
+    use dire_matching_engine::scheduler::TimedAction;
+    use dire_matching_engine::{MatchingEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+    let dir = std::env::temp_dir().join(format!("dire_save_notifier_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("state.json");
+    let state = api::create_app_state_with_persistence(vec![(InstrumentId(1), None)], &path);
+    let policy = SnapshotPolicy {
+        debounce: Some(std::time::Duration::from_millis(20)),
+        ..SnapshotPolicy::default()
+    };
+    let snapshotter = api::start_snapshotter(&state, policy).unwrap();
+    let persistence = FilePersistence::new(&path);
+    let saved_orders = || {
+        let saved = persistence.load().ok().flatten()?;
+        Some(saved.engine.books.iter().map(|(_, orders)| orders.len()).sum::<usize>())
+    };
+    let wait_for_saved_orders = |expected: usize| {
+        for _ in 0..100 {
+            if saved_orders() == Some(expected) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("saved state never had {} resting orders (has {:?})", expected, saved_orders());
+    };
+
+    // Order entry straight to the engine, as from a FIX session: saved, and the command log emptied.
+    for id in 1..=20 {
+        let order = Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: rust_decimal::Decimal::from(1),
+            price: Some(rust_decimal::Decimal::from(100 - id as i64)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(1),
+        };
+        state.engine.lock().unwrap().submit_order(order).unwrap();
+    }
+    wait_for_saved_orders(20);
+    assert_eq!(std::fs::metadata(path.with_extension("json.wal")).unwrap().len(), 0);
+
+    // An engine timer expiring an order.
+    {
+        let mut engine = state.engine.lock().unwrap();
+        engine.schedule(5, TimedAction::ExpireOrder { order_id: OrderId(1) }).unwrap();
+        engine.advance_time(10);
+    }
+    wait_for_saved_orders(19);
+
+    assert!(state.begin_shutdown("test", &AuditContext::internal()));
+    snapshotter.join().unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+#[tokio::test]
+async fn recovery_falls_back_to_the_newest_retained_snapshot_and_reports_what_it_replayed() {
+    use dire_matching_engine::persistence::FilePersistence;
+    use dire_matching_engine::{MatchingEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+    let dir = std::env::temp_dir().join(format!("dire_recovery_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("state.json");
+    let persistence = Arc::new(FilePersistence::new(&path));
+    let start = |audit_sink: Arc<InMemoryAuditSink>| {
+        api::create_app_state_with_sink_and_instruments(vec![(InstrumentId(1), None)], audit_sink, Some(persistence.clone()))
+    };
+    let recovery = |state: api::AppState| async move {
+        let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin,t:trader")));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service()).await.unwrap();
+        });
+        let get = |key: &'static str| {
+            reqwest::Client::new()
+                .get(format!("http://{}/admin/recovery", addr))
+                .header("Authorization", format!("Bearer {}", key))
+                .send()
+        };
+        assert_eq!(get("t").await.unwrap().status(), 403);
+        let resp = get("a").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        resp.json::<serde_json::Value>().await.unwrap()
+    };
+    // Straight to the engine, which (unlike the REST handlers) does not save.
+    let submit = |state: &api::AppState, id: u64| {
+        let order = Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            quantity: rust_decimal::Decimal::from(1),
+            price: Some(rust_decimal::Decimal::from(100 - id as i64)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(1),
+        };
+        state.engine.lock().unwrap().submit_order(order).unwrap();
+    };
+
+    // Order 1 is saved and the save kept as a copy; order 2 is only in the command log.
+    let state = start(Arc::new(InMemoryAuditSink::new()));
+    submit(&state, 1);
+    state.flush();
+    let copy_seq = state.engine.lock().unwrap().last_seq();
+    persistence.archive(copy_seq, 1).unwrap();
+    submit(&state, 2);
+    let restarted = start(Arc::new(InMemoryAuditSink::new()));
+    let report = recovery(restarted.clone()).await;
+    assert_eq!(report["snapshot_path"], path.display().to_string());
+    assert_eq!((report["snapshot_seq"].as_u64(), report["snapshot_command_seq"].as_u64()), (Some(copy_seq), Some(1)));
+    assert_eq!((report["replayed"].as_u64(), report["skipped"].as_u64(), report["discarded"].as_u64()), (Some(1), Some(0), Some(0)));
+    assert_eq!(report["command_seq"], 2);
+    assert!(report["load_ms"].is_u64() && report["replay_ms"].is_u64());
+
+    // Order 3 is only in the log when the state file is lost: the copy loads, and the log, which no longer
+    // follows on from it, is thrown away.
+    submit(&restarted, 3);
+    std::fs::write(&path, b"{ not json").unwrap();
+    let audit_sink = Arc::new(InMemoryAuditSink::new());
+    let fallback = start(audit_sink.clone());
+    {
+        let engine = fallback.engine.lock().unwrap();
+        assert!(engine.get_order(OrderId(1)).is_some());
+        assert!(engine.get_order(OrderId(2)).is_none() && engine.get_order(OrderId(3)).is_none());
+    }
+    let report = recovery(fallback).await;
+    let copy = format!("{}.snapshot-{}", path.display(), copy_seq);
+    assert_eq!(report["snapshot_path"], copy);
+    assert_eq!((report["replayed"].as_u64(), report["discarded"].as_u64(), report["command_seq"].as_u64()), (Some(0), Some(1), Some(1)));
+    let loads: Vec<_> = audit_sink
+        .events()
+        .into_iter()
+        .filter(|e| e.action == AuditAction::StateLoad)
+        .map(|e| (e.outcome, e.resource.unwrap()))
+        .collect();
+    assert_eq!(loads.len(), 3);
+    assert_eq!((loads[0].0.as_str(), &loads[0].1["path"]), ("error", &serde_json::json!(path.display().to_string())));
+    assert_eq!((loads[1].0.as_str(), &loads[1].1["path"]), ("success", &serde_json::json!(copy)));
+    assert_eq!((loads[2].0.as_str(), &loads[2].1["discarded"]), ("error", &serde_json::json!(1)));
+
+    // Without persistence there is no recovery to report.
+    let (plain, _handle) = spawn_app_with_auth(Some("a:admin")).await;
+    let resp = reqwest::Client::new()
+        .get(format!("http://{}/admin/recovery", plain))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 409);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+
+#[tokio::test]
+async fn trades_and_executions_survive_restarts_through_the_history_log() {
+    use dire_matching_engine::persistence::FilePersistence;
+    use dire_matching_engine::{HistoryQuery, MatchingEngine, Order, OrderId, OrderType, Side, TimeInForce, TraderId};
+    use std::io::Write;
+    let dir = std::env::temp_dir().join(format!("dire_history_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("state.json");
+    let history = dir.join("state.json.history");
+    let persistence = Arc::new(FilePersistence::new(&path));
+    let start = || {
+        api::create_app_state_with_sink_and_instruments(
+            vec![(InstrumentId(1), None)],
+            Arc::new(InMemoryAuditSink::new()),
+            Some(persistence.clone()),
+        )
+    };
+    let history_lines = || std::fs::read_to_string(&history).unwrap().lines().count();
+    // Straight to the engine, which (unlike the REST handlers) does not save.
+    let submit = |state: &api::AppState, id: u64, side: Side, trader: u64| {
+        let order = Order {
+            order_id: OrderId(id),
+            client_order_id: format!("c{}", id),
+            instrument_id: InstrumentId(1),
+            side,
+            order_type: OrderType::Limit,
+            quantity: rust_decimal::Decimal::from(1),
+            price: Some(rust_decimal::Decimal::from(100)),
+            time_in_force: TimeInForce::GTC,
+            timestamp: id,
+            trader_id: TraderId(trader),
+        };
+        state.engine.lock().unwrap().submit_order(order).unwrap();
+    };
+
+    let state = start();
+    submit(&state, 1, Side::Sell, 7);
+    submit(&state, 2, Side::Buy, 8);
+    let reports = state.engine.lock().unwrap().execution_history(&HistoryQuery::latest(100)).items.len();
+    assert_eq!(history_lines(), 1 + reports);
+
+    // A restart without a save replays the command log on top of the history, which already holds the trade,
+    // after a crash left half a record at its end.
+    std::fs::OpenOptions::new().append(true).open(&history).unwrap().write_all(b"{\"type\":\"Tra").unwrap();
+    let restarted = start();
+    assert_eq!(history_lines(), 1 + reports);
+    let app = api::create_router_with_state_and_auth(restarted.clone(), Some(AuthConfig::from_keys("a:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let get = |path: &str| {
+        let url = format!("http://{}{}", addr, path);
+        async move {
+            let resp = reqwest::Client::new().get(url).header("Authorization", "Bearer a").send().await.unwrap();
+            resp.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+    assert_eq!(get("/admin/recovery").await["history_records"].as_u64(), Some(1 + reports as u64));
+    let trades = get("/trades?trader_id=8").await;
+    assert_eq!(trades["trades"].as_array().unwrap().len(), 1);
+    assert_eq!(trades["trades"][0]["trade_id"], 1);
+    assert_eq!(get("/executions?limit=100").await["executions"].as_array().unwrap().len(), reports);
+
+    // Once a save covers the trade (and the command log is emptied), the history still has it.
+    restarted.flush();
+    let saved = start();
+    let engine = saved.engine.lock().unwrap();
+    assert_eq!(engine.trade_history(&HistoryQuery::latest(10)).items.len(), 1);
+    assert_eq!(engine.execution_history(&HistoryQuery::latest(100)).items.len(), reports);
+    drop(engine);
+    assert_eq!(history_lines(), 1 + reports);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn admin_snapshot_and_restore_round_trip_through_the_state_file() {
+    use dire_matching_engine::MatchingEngine;
+    let path = std::env::temp_dir().join(format!("dire_snapshot_{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let audit_sink = Arc::new(InMemoryAuditSink::new());
+    let persistence = Arc::new(dire_matching_engine::persistence::FilePersistence::new(&path));
+    let state = api::create_app_state_with_sink_and_instruments(vec![(InstrumentId(1), None)], audit_sink.clone(), Some(persistence));
+    let app = api::create_router_with_state_and_auth(state.clone(), Some(AuthConfig::from_keys("a:admin,t:trader")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    let post = |path: &'static str, key: &'static str, body: serde_json::Value| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&body)
+            .send()
+    };
+    let order = |id: u64| {
+        serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": 1
+        })
+    };
+
+    assert_eq!(post("/admin/restore", "a", serde_json::json!({})).await.unwrap().status(), 404);
+    assert_eq!(post("/orders", "t", order(1)).await.unwrap().status(), 200);
+    assert_eq!(post("/admin/snapshot", "t", serde_json::json!({})).await.unwrap().status(), 403);
+    let resp = post("/admin/snapshot", "a", serde_json::json!({})).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let saved: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(saved["instruments"], 1);
+    assert_eq!(saved["resting_orders"], 1);
+    assert_eq!(saved["market_state"], "Open");
+    assert_eq!(saved["checksum"], crc32fast::hash(&std::fs::read(&path).unwrap()));
+
+    // Every change rewrites the file; put the snapshot back (as from a backup) and restore it.
+    let backup = std::fs::read(&path).unwrap();
+    assert_eq!(post("/orders", "t", order(2)).await.unwrap().status(), 200);
+    assert_eq!(post("/orders/cancel", "t", serde_json::json!({ "order_id": 1 })).await.unwrap().status(), 200);
+    std::fs::write(&path, &backup).unwrap();
+    let resp = post("/admin/restore", "a", serde_json::json!({})).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.json::<serde_json::Value>().await.unwrap(), saved);
+    {
+        let engine = state.engine.lock().unwrap();
+        assert!(engine.get_order(dire_matching_engine::OrderId(1)).is_some());
+        assert!(engine.get_order(dire_matching_engine::OrderId(2)).is_none());
+    }
+    let actions: Vec<_> = audit_sink.events().iter().map(|e| e.action).collect();
+    assert!(actions.contains(&AuditAction::Snapshot) && actions.contains(&AuditAction::Restore));
+
+    // Loading the file at startup is audited by `persistence`.
+    let load_audit = |path: &std::path::Path| {
+        let audit_sink = Arc::new(InMemoryAuditSink::new());
+        let persistence = Arc::new(dire_matching_engine::persistence::FilePersistence::new(path));
+        api::create_app_state_with_sink_and_instruments(vec![(InstrumentId(1), None)], audit_sink.clone(), Some(persistence));
+        let event = audit_sink.events().into_iter().find(|e| e.action == AuditAction::StateLoad).expect("state_load audited");
+        assert_eq!(event.actor, "persistence");
+        (event.outcome, event.resource.unwrap())
+    };
+    let (outcome, resource) = load_audit(&path);
+    assert_eq!((outcome.as_str(), &resource["resting_orders"]), ("success", &serde_json::json!(1)));
+
+    // A corrupt file is refused and the engine keeps running as it was.
+    std::fs::write(&path, b"{ not json").unwrap();
+    let resp = post("/admin/restore", "a", serde_json::json!({})).await.unwrap();
+    assert_eq!(resp.status(), 500);
+    assert_eq!(resp.json::<serde_json::Value>().await.unwrap()["code"], "internal");
+    assert!(state.engine.lock().unwrap().get_order(dire_matching_engine::OrderId(1)).is_some());
+    assert_eq!(load_audit(&path).0, "error");
+
+    // Without persistence there is nothing to snapshot to.
+    let (plain, _handle) = spawn_app_with_auth(Some("a:admin")).await;
+    let resp = client
+        .post(format!("http://{}/admin/snapshot", plain))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 409);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(path.with_extension("json.ids"));
+}
+
+/// Trader cannot change market state (RBAC: admin/operator only).
+#[tokio::test]
+async fn integration_trader_cannot_set_market_state() {
+    let (addr, _handle) = spawn_app_with_auth(Some("t:trader,a:admin")).await;
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("http://{}/admin/market-state", addr))
+        .header("Authorization", "Bearer t")
+        .json(&serde_json::json!({ "state": "Halted" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 403);
+}
+
+#[tokio::test]
+async fn events_endpoint_replays_journal_and_reports_gaps() {
+    // The journal names traders and order contents: anonymous callers do not get it, even with auth disabled.
+    let (open, _open_handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    assert_eq!(client.get(format!("http://{}/events", open)).send().await.unwrap().status(), 403);
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin")).await;
+    for (id, side) in [(1, "Sell"), (2, "Buy")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": "2",
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": id
+        });
+        let resp = client.post(format!("http://{}/orders", addr)).header("Authorization", "Bearer a").json(&order).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+    let summary = |json: serde_json::Value| -> Vec<String> {
+        json["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| format!("{}:{}", e["type"].as_str().unwrap(), e["seq"]))
+            .collect()
+    };
+
+    let json = client.get(format!("http://{}/events", addr)).header("Authorization", "Bearer a").send().await.unwrap().json().await.unwrap();
+    assert_eq!(
+        summary(json),
+        vec!["Report:1", "BookChanged:2", "Trade:3", "Report:4", "Report:5", "BookChanged:6"]
+    );
+    let json = client.get(format!("http://{}/events?since=3&limit=2", addr)).header("Authorization", "Bearer a").send().await.unwrap().json().await.unwrap();
+    assert_eq!(summary(json), vec!["Report:4", "Report:5"]);
+
+    // After a restore the journal starts empty at the snapshot's sequence number; older gaps cannot be filled.
+    let state = api::create_app_state(InstrumentId(1));
+    state
+        .engine
+        .lock()
+        .unwrap()
+        .load_from_snapshot(dire_matching_engine::EngineSnapshot {
+            version: dire_matching_engine::ENGINE_SNAPSHOT_VERSION,
+            instruments: vec![(InstrumentId(1), None)],
+            books: vec![],
+            order_to_instrument: vec![],
+            next_trade_id: 1,
+            next_exec_id: 1,
+            next_seq: 10,
+            tick_sizes: vec![],
+            scheduler: Default::default(),
+            order_fills: vec![],
+            instrument_states: vec![],
+            instrument_market_states: vec![],
+            quotes: vec![],
+            session_stats: vec![],
+            lot_sizes: vec![],
+            price_bands: vec![],
+            command_seq: 0,
+            next_order_id: 0,
+        })
+        .unwrap();
+    let app = api::create_router_with_state_and_auth(state, Some(AuthConfig::from_keys("a:admin")));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let restored = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let res = client.get(format!("http://{}/events?since=5", restored)).header("Authorization", "Bearer a").send().await.unwrap();
+    assert_eq!(res.status(), 410);
+    let json: serde_json::Value = res.json().await.unwrap();
+    assert!(json["message"].as_str().unwrap().contains("no longer retained"));
+    assert_eq!(json["code"], "gone");
+    let res = client.get(format!("http://{}/events?since=9", restored)).header("Authorization", "Bearer a").send().await.unwrap();
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn admin_instrument_state_suspends_one_instrument() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin,t:trader")).await;
+    let client = reqwest::Client::new();
+    let add = client
+        .post(format!("http://{}/admin/instruments", addr))
+        .header("Authorization", "Bearer a")
+        .json(&serde_json::json!({ "instrument_id": 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(add.status(), 201);
+    let set_state = |id: u64, state: &str| {
+        client
+            .post(format!("http://{}/admin/instruments/{}/state", addr, id))
+            .header("Authorization", "Bearer a")
+            .json(&serde_json::json!({ "state": state }))
+            .send()
+    };
+    let submit = |order_id: u64, instrument_id: u64| {
+        client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", "Bearer t")
+            .json(&serde_json::json!({
+                "order_id": order_id,
+                "client_order_id": format!("c{}", order_id),
+                "instrument_id": instrument_id,
+                "side": "Buy",
+                "order_type": "Limit",
+                "quantity": "1",
+                "price": "100",
+                "time_in_force": "GTC",
+                "timestamp": order_id,
+                "trader_id": 1
+            }))
+            .send()
+    };
+
+    let res = set_state(2, "Suspended").await.unwrap();
+    assert_eq!(res.status(), 200);
+    let res = submit(1, 2).await.unwrap();
+    assert_eq!(res.status(), 400);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["message"], "Instrument 2 is suspended");
+    assert_eq!(body["code"], "instrument_unavailable");
+    assert_eq!(submit(2, 1).await.unwrap().status(), 200);
+
+    let list: Vec<serde_json::Value> = client
+        .get(format!("http://{}/admin/instruments", addr))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let inst2 = list.iter().find(|o| o["instrument_id"] == 2).unwrap();
+    assert_eq!(inst2["state"], "Suspended");
+
+    assert_eq!(set_state(2, "Active").await.unwrap().status(), 200);
+    assert_eq!(submit(3, 2).await.unwrap().status(), 200);
+    assert_eq!(set_state(2, "Delisted").await.unwrap().status(), 200);
+    assert_eq!(set_state(2, "Active").await.unwrap().status(), 409);
+    assert_eq!(set_state(9, "Suspended").await.unwrap().status(), 404);
+    assert_eq!(set_state(1, "Paused").await.unwrap().status(), 422);
+
+    // Resting orders on a delisted instrument can still be canceled.
+    let cancel = client
+        .post(format!("http://{}/orders/cancel", addr))
+        .header("Authorization", "Bearer t")
+        .json(&serde_json::json!({ "order_id": 3 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(cancel.status(), 200);
+}
+
+#[tokio::test]
+async fn admin_instrument_patch_updates_metadata_and_rules() {
+    let (addr, _handle, sink) = spawn_app_with_audit_sink(Some("a:admin,t:trader")).await;
+    let client = reqwest::Client::new();
+    let patch = |id: u64, body: serde_json::Value| {
+        client
+            .patch(format!("http://{}/admin/instruments/{}", addr, id))
+            .header("Authorization", "Bearer a")
+            .json(&body)
+            .send()
+    };
+    let submit = |order_id: u64, quantity: &str, price: &str| {
+        client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", "Bearer t")
+            .json(&serde_json::json!({
+                "order_id": order_id,
+                "client_order_id": format!("c{}", order_id),
+                "instrument_id": 1,
+                "side": "Buy",
+                "order_type": "Limit",
+                "quantity": quantity,
+                "price": price,
+                "time_in_force": "GTC",
+                "timestamp": order_id,
+                "trader_id": 1
+            }))
+            .send()
+    };
+    assert_eq!(submit(1, "3", "100.5").await.unwrap().status(), 200);
+
+    let res = patch(1, serde_json::json!({
+        "symbol": "ABC",
+        "tick_size": "0.5",
+        "lot_size": "1",
+        "price_band": { "low": "90", "high": "110" }
+    }))
+    .await
+    .unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["symbol"], "ABC");
+    assert_eq!(body["tick_size"], "0.5");
+    assert_eq!(body["lot_size"], "1");
+    assert_eq!(body["price_band"], serde_json::json!({ "low": "90", "high": "110" }));
+    assert_eq!(body["state"], "Active");
+
+    let res = submit(2, "1.5", "100").await.unwrap();
+    assert_eq!(res.status(), 400);
+    let err: serde_json::Value = res.json().await.unwrap();
+    assert_eq!((err["code"].as_str(), err["field"].as_str()), (Some("invalid_quantity"), Some("quantity")));
+    let err: serde_json::Value = submit(3, "1", "111").await.unwrap().json().await.unwrap();
+    assert_eq!((err["code"].as_str(), err["field"].as_str()), (Some("invalid_price"), Some("price")));
+    assert_eq!(submit(4, "2", "99.5").await.unwrap().status(), 200);
+
+    // Invalid or conflicting changes are refused as a whole.
+    let res = patch(1, serde_json::json!({ "tick_size": "1", "symbol": "XYZ" })).await.unwrap();
+    assert_eq!(res.status(), 409);
+    let err: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(err["field"], "tick_size");
+    let res = patch(1, serde_json::json!({ "price_band": { "low": "110", "high": "90" } })).await.unwrap();
+    assert_eq!(res.status(), 422);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["field"], "price_band");
+    let res = patch(1, serde_json::json!({ "lot_size": "0" })).await.unwrap();
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["field"], "lot_size");
+    let res = patch(1, serde_json::json!({ "state": "Paused" })).await.unwrap();
+    assert_eq!(res.status(), 422);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["field"], "state");
+    assert_eq!(patch(1, serde_json::json!({})).await.unwrap().status(), 422);
+    assert_eq!(patch(9, serde_json::json!({ "symbol": "N" })).await.unwrap().status(), 404);
+
+    // null clears; trading state changes with the rest.
+    let res = patch(1, serde_json::json!({ "price_band": null, "symbol": null, "state": "Suspended" })).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert!(body["price_band"].is_null() && body.get("symbol").is_none());
+    assert_eq!(body["lot_size"], "1");
+    assert_eq!(body["state"], "Suspended");
+
+    let res = client
+        .patch(format!("http://{}/admin/instruments/1", addr))
+        .header("Authorization", "Bearer t")
+        .json(&serde_json::json!({ "symbol": "T" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 403);
+
+    let updates: Vec<_> = sink.events().into_iter().filter(|e| e.action == AuditAction::InstrumentUpdate).collect();
+    assert_eq!(updates.len(), 2);
+    let resource = updates[0].resource.as_ref().unwrap();
+    assert_eq!(resource["before"]["tick_size"], "0.00000001");
+    assert_eq!(resource["after"]["tick_size"], "0.5");
+}
+
+#[tokio::test]
+async fn admin_instrument_market_state_halts_one_instrument() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin,t:trader")).await;
+    let client = reqwest::Client::new();
+    let add = client
+        .post(format!("http://{}/admin/instruments", addr))
+        .header("Authorization", "Bearer a")
+        .json(&serde_json::json!({ "instrument_id": 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(add.status(), 201);
+    let set_market_state = |id: u64, market_state: &str| {
+        client
+            .post(format!("http://{}/admin/instruments/{}/state", addr, id))
+            .header("Authorization", "Bearer a")
+            .json(&serde_json::json!({ "market_state": market_state }))
+            .send()
+    };
+    let submit = |order_id: u64, instrument_id: u64| {
+        client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", "Bearer t")
+            .json(&serde_json::json!({
+                "order_id": order_id,
+                "client_order_id": format!("c{}", order_id),
+                "instrument_id": instrument_id,
+                "side": "Buy",
+                "order_type": "Limit",
+                "quantity": "1",
+                "price": "100",
+                "time_in_force": "GTC",
+                "timestamp": order_id,
+                "trader_id": 1
+            }))
+            .send()
+    };
+
+    let res = set_market_state(2, "Halted").await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["state"], "Active");
+    assert_eq!(body["market_state"], "Halted");
+    let res = submit(1, 2).await.unwrap();
+    assert_eq!(res.status(), 503);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["message"], "market not open for instrument 2");
+    assert_eq!(body["code"], "market_closed");
+    assert_eq!(submit(2, 1).await.unwrap().status(), 200);
+
+    let res = client
+        .get(format!("http://{}/admin/instruments/2/state", addr))
+        .header("Authorization", "Bearer a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["market_state"], "Halted");
+
+    assert_eq!(set_market_state(2, "Open").await.unwrap().status(), 200);
+    assert_eq!(submit(3, 2).await.unwrap().status(), 200);
+    assert_eq!(set_market_state(9, "Halted").await.unwrap().status(), 404);
+    assert_eq!(set_market_state(1, "Paused").await.unwrap().status(), 422);
+    let empty = client
+        .post(format!("http://{}/admin/instruments/1/state", addr))
+        .header("Authorization", "Bearer a")
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(empty.status(), 422);
+}
+
+#[tokio::test]
+async fn book_depth_returns_levels_spread_and_last_trade() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    for (id, side, price, qty) in [(1, "Buy", "99", "3"), (2, "Buy", "98", "1"), (3, "Sell", "101", "2"), (4, "Sell", "102", "5"), (5, "Buy", "101", "1")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": qty,
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": 10 + id,
+            "trader_id": id
+        });
+        let resp = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    let body: serde_json::Value = client.get(format!("http://{}/book/1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body["bids"], serde_json::json!([["99", "3"], ["98", "1"]]));
+    assert_eq!(body["asks"], serde_json::json!([["101", "1"], ["102", "5"]]));
+    assert_eq!((body["best_bid"].as_str(), body["best_ask"].as_str()), (Some("99"), Some("101")));
+    assert_eq!(body["spread"], "2");
+    assert_eq!(body["last_trade"]["price"], "101");
+    assert_eq!(body["last_trade"]["aggressor_side"], "Buy");
+    assert_eq!(body["last_trade"]["timestamp"], 15);
+
+    let body: serde_json::Value = client.get(format!("http://{}/book/1?levels=1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body["bids"], serde_json::json!([["99", "3"]]));
+    assert_eq!(body["asks"], serde_json::json!([["101", "1"]]));
+    assert_eq!(client.get(format!("http://{}/book/7", addr)).send().await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn validation_errors_use_structured_envelope() {
+    let (addr, _handle) = spawn_app_with_auth(Some("a:admin,t:trader")).await;
+    let client = reqwest::Client::new();
+    let order = |side: &str, quantity: &str| {
+        serde_json::json!({
+            "order_id": 1,
+            "client_order_id": "c1",
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": quantity,
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": 1
+        })
+    };
+    let post = |path: &'static str, key: &'static str, body: serde_json::Value| {
+        client
+            .post(format!("http://{}{}", addr, path))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&body)
+            .send()
+    };
+
+    let res = post("/orders", "t", order("Up", "1")).await.unwrap();
+    assert_eq!(res.status(), 422);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!((body["code"].as_str(), body["field"].as_str()), (Some("invalid_field"), Some("side")));
+    assert!(body["message"].as_str().unwrap().contains("unknown variant"), "{:?}", body);
+
+    let res = post("/orders", "t", order("Buy", "-5")).await.unwrap();
+    assert_eq!(res.status(), 422);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!((body["code"].as_str(), body["field"].as_str()), (Some("invalid_field"), Some("quantity")));
+
+    let modify = serde_json::json!({ "order_id": 1, "replacement": order("Buy", "0") });
+    let body: serde_json::Value = post("/orders/modify", "t", modify).await.unwrap().json().await.unwrap();
+    assert_eq!(body["field"], "replacement.quantity");
+    let body: serde_json::Value = post("/orders/cancel", "t", serde_json::json!({})).await.unwrap().json().await.unwrap();
+    assert_eq!((body["code"].as_str(), body["field"].as_str()), (Some("invalid_field"), Some("order_id")));
+
+    let res = client
+        .post(format!("http://{}/orders", addr))
+        .header("Authorization", "Bearer t")
+        .header("Content-Type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["code"], "invalid_json");
+
+    assert_eq!(post("/orders", "t", order("Buy", "1")).await.unwrap().status(), 200);
+    let body: serde_json::Value = post("/orders", "t", order("Buy", "1")).await.unwrap().json().await.unwrap();
+    assert_eq!((body["code"].as_str(), body["field"].as_str()), (Some("duplicate_order_id"), Some("order_id")));
+
+    let res = post("/admin/market-state", "t", serde_json::json!({ "state": "Open" })).await.unwrap();
+    assert_eq!(res.status(), 403);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["code"], "forbidden");
+    let res = post("/admin/market-state", "a", serde_json::json!({ "state": "Paused" })).await.unwrap();
+    assert_eq!(res.status(), 422);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["field"], "state");
+    let res = client.get(format!("http://{}/orders/1", addr)).send().await.unwrap();
+    assert_eq!(res.status(), 401);
+    assert_eq!(res.json::<serde_json::Value>().await.unwrap()["code"], "unauthorized");
+}
+
+#[tokio::test]
+async fn trade_and_execution_history_page_with_filters() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    for (id, side, trader) in [(1, "Sell", 1), (2, "Buy", 2), (3, "Sell", 1), (4, "Buy", 3)] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": "2",
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": id * 10,
+            "trader_id": trader
+        });
+        let resp = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+    let get = |path: String| {
+        let client = client.clone();
+        async move { client.get(format!("http://{}{}", addr, path)).send().await.unwrap() }
+    };
+    let ids = |json: &serde_json::Value, key: &str, id: &str| -> Vec<u64> {
+        json[key].as_array().unwrap().iter().map(|t| t[id].as_u64().unwrap()).collect()
+    };
+
+    // Forward paging: pass next_cursor back as `since` until it is null.
+    let json: serde_json::Value = get("/trades?since=0&limit=1".into()).await.json().await.unwrap();
+    assert_eq!((ids(&json, "trades", "trade_id"), json["next_cursor"].as_u64()), (vec![1], Some(1)));
+    let json: serde_json::Value = get("/trades?since=1&limit=1".into()).await.json().await.unwrap();
+    assert_eq!(ids(&json, "trades", "trade_id"), vec![2]);
+    assert!(json["next_cursor"].is_null());
+
+    let json: serde_json::Value = get("/trades?trader_id=3".into()).await.json().await.unwrap();
+    assert_eq!(ids(&json, "trades", "trade_id"), vec![2]);
+    let json: serde_json::Value = get("/trades?trader_id=1".into()).await.json().await.unwrap();
+    assert_eq!(ids(&json, "trades", "trade_id"), vec![1, 2]);
+    let json: serde_json::Value = get("/trades?from=15&to=25".into()).await.json().await.unwrap();
+    assert_eq!(ids(&json, "trades", "trade_id"), vec![1]);
+
+    // Executions: backward from the newest by default, filtered by the trader of the order.
+    let json: serde_json::Value = get("/executions?limit=2".into()).await.json().await.unwrap();
+    let newest = ids(&json, "executions", "exec_id");
+    assert_eq!(newest.len(), 2);
+    assert_eq!(json["next_cursor"].as_u64(), Some(newest[0]));
+    let older: serde_json::Value = get(format!("/executions?before={}", newest[0])).await.json().await.unwrap();
+    assert!(ids(&older, "executions", "exec_id").iter().all(|&id| id < newest[0]));
+    let json: serde_json::Value = get("/executions?trader_id=3".into()).await.json().await.unwrap();
+    let orders = ids(&json, "executions", "order_id");
+    assert!(!orders.is_empty() && orders.iter().all(|&id| id == 4), "{:?}", json);
+    let json: serde_json::Value = get("/executions?instrument_id=2".into()).await.json().await.unwrap();
+    assert!(json["executions"].as_array().unwrap().is_empty());
+
+    let resp = get("/trades?since=1&before=2".into()).await;
+    assert_eq!(resp.status(), 422);
+    assert_eq!(resp.json::<serde_json::Value>().await.unwrap()["field"], "before");
+    let resp = get("/executions?from=5&to=1".into()).await;
+    assert_eq!(resp.status(), 422);
+}
+
+#[tokio::test]
+async fn https_serves_the_api_and_stops_on_shutdown() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tls = dire_matching_engine::tls::TlsPaths {
+        cert_path: fixtures.join("test_cert.pem"),
+        key_path: fixtures.join("test_key.pem"),
+    }
+    .load()
+    .unwrap();
+    let app = api::create_router_with_state_and_auth(api::create_app_state(InstrumentId(1)), Some(AuthConfig::disabled()));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(dire_matching_engine::tls::serve_tls(listener, app, tls, async move {
+        let _ = stopped.await;
+    }));
+
+    let ca = reqwest::Certificate::from_pem(&std::fs::read(fixtures.join("test_ca.pem")).unwrap()).unwrap();
+    let client = reqwest::Client::builder().add_root_certificate(ca).build().unwrap();
+    let resp = client.get(format!("https://localhost:{}/health", port)).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(
+        reqwest::get(format!("http://localhost:{}/health", port)).await.map_or(true, |r| !r.status().is_success()),
+        "plaintext is not served on the TLS port"
+    );
+
+    stop.send(()).unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(5), server)
+        .await
+        .expect("server stops")
+        .unwrap()
+        .unwrap();
 }
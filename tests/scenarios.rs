@@ -0,0 +1,21 @@
+//! Scenario fixtures: every scenario in `tests/fixtures/scenarios` runs on a fresh engine and its expectations hold.
+
+use dire_matching_engine::{MultiEngine, Scenario, ScenarioRunner};
+use std::path::Path;
+
+#[test]
+fn every_scenario_fixture_runs_and_meets_its_expectations() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/scenarios");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    assert!(paths.len() >= 2, "fixtures in {}", dir.display());
+    for path in paths {
+        let scenario = Scenario::load(&path).unwrap_or_else(|e| panic!("{}", e));
+        let report = ScenarioRunner::new(MultiEngine::new_with_instruments(vec![])).run(&scenario).unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(report.steps.len(), scenario.steps.len(), "{}", scenario.name);
+    }
+}
@@ -0,0 +1,79 @@
+//! State file schema versions: a fixture of every version in `tests/fixtures/state/` (`v<N>.json`, plus the
+//! current version as binary) loads through the migrations and restores the same book.
+
+use dire_matching_engine::persistence::{FilePersistence, STATE_MIGRATIONS, STATE_VERSION};
+use dire_matching_engine::{InstrumentId, MatchingEngine, MultiEngine, OrderId, OrderStatus, Side};
+use rust_decimal::Decimal;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/state").join(name)
+}
+
+#[test]
+fn state_files_of_every_version_load_and_restore_the_same_book() {
+    assert!(
+        STATE_MIGRATIONS.iter().map(|step| step.from).eq(1..STATE_VERSION),
+        "one migration step per earlier version"
+    );
+    let mut fixtures: Vec<_> = (1..=STATE_VERSION).map(|v| (v, format!("v{}.json", v))).collect();
+    fixtures.push((STATE_VERSION, format!("v{}.bin", STATE_VERSION)));
+    for (version, name) in fixtures {
+        let state = FilePersistence::new(fixture(&name))
+            .load()
+            .unwrap_or_else(|e| panic!("{}: {}", name, e))
+            .unwrap_or_else(|| panic!("{}: missing; add a fixture for every version", name));
+        assert_eq!(state.version, STATE_VERSION, "{}", name);
+        assert_eq!(state.market_state, "Halted", "{}", name);
+        assert_eq!(state.venue_config.is_some(), version > 1, "{}", name);
+
+        let mut engine = MultiEngine::new_with_instruments(vec![]);
+        engine.load_from_snapshot(state.engine).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let book: Vec<_> = engine
+            .book_orders(InstrumentId(1))
+            .unwrap()
+            .iter()
+            .map(|o| (o.order_id, o.side, o.price, o.remaining_quantity))
+            .collect();
+        assert_eq!(
+            book,
+            [
+                (OrderId(3), Side::Buy, Decimal::from(100), Decimal::from(7)),
+                (OrderId(1), Side::Sell, Decimal::new(1005, 1), Decimal::from(6)),
+            ],
+            "{}",
+            name
+        );
+        assert_eq!(engine.book_orders(InstrumentId(2)).unwrap().len(), 0, "{}", name);
+
+        // Version 1 had no order metadata or fill state: order 1 restores as a fresh order of its remaining size.
+        let snapshot = engine.snapshot();
+        let (_, resting) = snapshot.books.iter().find(|(id, _)| *id == InstrumentId(1)).unwrap();
+        let client_ids: Vec<_> = resting.iter().map(|o| o.client_order_id.as_str()).collect();
+        let order_1 = engine.order_status(OrderId(1)).unwrap();
+        if version == 1 {
+            assert_eq!(client_ids, ["restore-3", "restore-1"]);
+            assert_eq!((order_1.status, order_1.quantity), (OrderStatus::New, Decimal::from(6)));
+        } else {
+            assert_eq!(client_ids, ["c3", "c1"], "{}", name);
+            assert_eq!((order_1.status, order_1.quantity), (OrderStatus::PartiallyFilled, Decimal::from(10)), "{}", name);
+            assert_eq!(order_1.filled_quantity, Decimal::from(4), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn state_files_of_a_newer_version_are_refused() {
+    let dir = std::env::temp_dir().join(format!("dire_state_versions_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("state.json");
+    let mut newer: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(fixture(&format!("v{}.json", STATE_VERSION))).unwrap()).unwrap();
+    newer["version"] = (STATE_VERSION + 1).into();
+    std::fs::write(&path, serde_json::to_vec(&newer).unwrap()).unwrap();
+    assert_eq!(
+        FilePersistence::new(&path).load().unwrap_err(),
+        format!("Unsupported state file version {} (this engine reads up to {})", STATE_VERSION + 1, STATE_VERSION)
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -16,6 +16,13 @@ async fn spawn_app() -> (SocketAddr, tokio::task::JoinHandle<()>) {
     (addr, handle)
 }
 
+type WsStream = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn next_json(ws: &mut WsStream) -> serde_json::Value {
+    let raw = ws.next().await.expect("message").expect("ws recv");
+    serde_json::from_str(&raw.into_text().expect("text frame")).expect("json")
+}
+
 #[derive(serde::Deserialize)]
 struct MarketDataSnapshot {
     #[serde(rename = "type")]
@@ -23,6 +30,7 @@ struct MarketDataSnapshot {
     instrument_id: u64,
     best_bid: Option<rust_decimal::Decimal>,
     best_ask: Option<rust_decimal::Decimal>,
+    checksum: u32,
 }
 
 #[tokio::test]
@@ -40,6 +48,7 @@ async fn ws_market_data_sends_snapshot_on_connect() {
     // Empty book at start
     assert!(snapshot.best_bid.is_none());
     assert!(snapshot.best_ask.is_none());
+    assert_eq!(snapshot.checksum, 0);
 }
 
 #[tokio::test]
@@ -74,6 +83,7 @@ async fn ws_market_data_snapshot_reflects_book_after_order() {
     assert!(snapshot.best_bid.is_some());
     let expected_bid: rust_decimal::Decimal = "99.5".parse().unwrap();
     assert_eq!(snapshot.best_bid.unwrap(), expected_bid);
+    assert_eq!(snapshot.checksum, crc32fast::hash(b"99.5:5"));
 }
 
 #[tokio::test]
@@ -115,3 +125,733 @@ async fn ws_market_data_broadcasts_update_after_order() {
     let expected_bid: rust_decimal::Decimal = "101".parse().unwrap();
     assert_eq!(second.best_bid.unwrap(), expected_bid);
 }
+
+#[tokio::test]
+async fn ws_market_data_includes_stats_when_requested() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    for (id, side, price) in [(30, "Buy", "99"), (31, "Sell", "101")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": "10",
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": id
+        });
+        let _ = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+    }
+
+    let url = format!("ws://{}/ws/market-data?stats_levels=5", addr);
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url)
+        .await
+        .expect("connect");
+    let raw = ws.next().await.expect("one message").expect("ws recv");
+    let msg: serde_json::Value = serde_json::from_str(&raw.into_text().expect("text frame")).expect("json");
+    let stats = msg.get("stats").expect("stats present");
+    assert_eq!(stats.get("levels").and_then(|v| v.as_u64()), Some(5));
+    assert_eq!(stats.get("spread").and_then(|v| v.as_str()), Some("2"));
+    assert_eq!(stats.get("microprice").and_then(|v| v.as_str()), Some("100"));
+    assert_eq!(stats.get("imbalance").and_then(|v| v.as_str()), Some("0"));
+
+    let url = format!("ws://{}/ws/market-data", addr);
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url)
+        .await
+        .expect("connect");
+    let raw = ws.next().await.expect("one message").expect("ws recv");
+    let msg: serde_json::Value = serde_json::from_str(&raw.into_text().expect("text frame")).expect("json");
+    assert!(msg.get("stats").is_none(), "stats only when requested");
+}
+
+#[tokio::test]
+async fn ws_market_data_delta_mode_sends_levels_then_deltas() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    let submit = |id: u64, side: &str, price: &str, qty: &str| {
+        serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": qty,
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": id
+        })
+    };
+    let _ = client.post(format!("http://{}/orders", addr)).json(&submit(40, "Sell", "101", "10")).send().await.unwrap();
+
+    let url = format!("ws://{}/ws/market-data?deltas=true", addr);
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url)
+        .await
+        .expect("connect");
+    let raw = ws.next().await.expect("snapshot").expect("ws recv");
+    let msg: serde_json::Value = serde_json::from_str(&raw.into_text().expect("text frame")).expect("json");
+    assert_eq!(msg["type"], "snapshot");
+    assert_eq!(msg["bids"], serde_json::json!([]));
+    assert_eq!(msg["asks"], serde_json::json!([["101", "10"]]));
+
+    let _ = client.post(format!("http://{}/orders", addr)).json(&submit(41, "Buy", "101", "4")).send().await.unwrap();
+    let raw = ws.next().await.expect("delta").expect("ws recv");
+    let msg: serde_json::Value = serde_json::from_str(&raw.into_text().expect("text frame")).expect("json");
+    assert_eq!(msg["type"], "delta");
+    assert_eq!(msg["bids"], serde_json::json!([]));
+    assert_eq!(
+        msg["asks"],
+        serde_json::json!([{ "price": "101", "quantity": "6", "action": "Changed" }])
+    );
+    assert_eq!(msg["checksum"].as_u64(), Some(crc32fast::hash(b"101:6") as u64));
+    // Sell New report (1), sell book change (2), then the trade, two reports, and this book change.
+    assert_eq!(msg["seq"].as_u64(), Some(6));
+    // Chained to the snapshot's seq, so a client can tell it missed nothing.
+    assert_eq!(msg["prev_seq"].as_u64(), Some(2));
+
+    let _ = client.post(format!("http://{}/orders/cancel", addr)).json(&serde_json::json!({ "order_id": 40 })).send().await.unwrap();
+    let raw = ws.next().await.expect("delta").expect("ws recv");
+    let msg: serde_json::Value = serde_json::from_str(&raw.into_text().expect("text frame")).expect("json");
+    assert_eq!(
+        msg["asks"],
+        serde_json::json!([{ "price": "101", "quantity": "0", "action": "Removed" }])
+    );
+    assert!(msg.get("best_bid").is_none());
+    assert_eq!(msg["prev_seq"].as_u64(), Some(6));
+    let last_seq = msg["seq"].clone();
+
+    // On a detected gap the client asks for a fresh snapshot; deltas then chain from it.
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+    ws.send(Message::Text(r#"{"type":"snapshot","instrument_id":1}"#.into())).await.unwrap();
+    let msg = next_json(&mut ws).await;
+    assert_eq!(msg["type"], "snapshot");
+    assert_eq!((msg["asks"].clone(), msg["seq"].clone()), (serde_json::json!([]), last_seq.clone()));
+    let _ = client.post(format!("http://{}/orders", addr)).json(&submit(42, "Buy", "99", "1")).send().await.unwrap();
+    let msg = next_json(&mut ws).await;
+    assert_eq!((msg["type"].as_str(), msg["prev_seq"].clone()), (Some("delta"), last_seq));
+
+    ws.send(Message::Text(r#"{"type":"snapshot","instrument_id":9}"#.into())).await.unwrap();
+    let msg = next_json(&mut ws).await;
+    assert_eq!((msg["type"].as_str(), msg["code"].as_str()), (Some("error"), Some("not_found")));
+    ws.send(Message::Text("resend please".into())).await.unwrap();
+    assert_eq!(next_json(&mut ws).await["code"], "invalid_json");
+}
+
+#[tokio::test]
+async fn ws_market_data_snapshot_carries_configured_top_levels() {
+    let state = api::create_app_state(InstrumentId(1));
+    state.engine.lock().unwrap().set_snapshot_levels(Some(2));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = api::create_router_with_state(state);
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let client = reqwest::Client::new();
+    for (id, price) in [(50, "99"), (51, "98"), (52, "97")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": "Buy",
+            "order_type": "Limit",
+            "quantity": "1",
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": 1
+        });
+        let _ = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+    }
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/market-data", addr))
+        .await
+        .expect("connect");
+    let raw = ws.next().await.expect("snapshot").expect("ws recv");
+    let msg: serde_json::Value = serde_json::from_str(&raw.into_text().expect("text frame")).expect("json");
+    assert_eq!(msg["type"], "snapshot");
+    assert_eq!(msg["bids"], serde_json::json!([["99", "1"], ["98", "1"]]));
+    assert_eq!(msg["asks"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn ws_market_data_ticker_mode_sends_session_stats_after_trades() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    let submit = |id: u64, side: &str, price: &str, qty: &str| {
+        serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": qty,
+            "price": price,
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": id
+        })
+    };
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/market-data?ticker=true", addr))
+        .await
+        .expect("connect");
+    assert_eq!(next_json(&mut ws).await["type"], "snapshot");
+    let msg = next_json(&mut ws).await;
+    assert_eq!(msg["type"], "ticker");
+    assert_eq!(msg["volume"], "0");
+    assert!(msg["last"].is_null());
+
+    for (id, side, price, qty) in [(60, "Sell", "101", "4"), (61, "Buy", "101", "4"), (62, "Sell", "102", "5"), (63, "Buy", "102", "1")] {
+        let _ = client.post(format!("http://{}/orders", addr)).json(&submit(id, side, price, qty)).send().await.unwrap();
+    }
+    // Resting orders only move the book; each trade is followed by a ticker.
+    let mut messages = Vec::new();
+    for _ in 0..6 {
+        messages.push(next_json(&mut ws).await);
+    }
+    let tickers: Vec<&serde_json::Value> = messages.iter().filter(|m| m["type"] == "ticker").collect();
+    assert_eq!(tickers.len(), 2);
+    let last = tickers[1];
+    assert_eq!((last["open"].as_str(), last["high"].as_str(), last["low"].as_str()), (Some("101"), Some("102"), Some("101")));
+    assert_eq!((last["last"].as_str(), last["volume"].as_str()), (Some("102"), Some("5")));
+    assert_eq!(last["vwap"], "101.2");
+    assert_eq!(last["trade_count"], 2);
+
+    let rest: serde_json::Value = client.get(format!("http://{}/ticker/1", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(rest["vwap"], "101.2");
+    assert_eq!(rest["seq"], last["seq"]);
+    let all: serde_json::Value = client.get(format!("http://{}/ticker", addr)).send().await.unwrap().json().await.unwrap();
+    assert_eq!(all["tickers"].as_array().unwrap().len(), 1);
+    assert_eq!(client.get(format!("http://{}/ticker/9", addr)).send().await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn ws_market_data_trades_mode_sends_trade_tape() {
+    let (addr, _handle) = spawn_app().await;
+    let client = reqwest::Client::new();
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/market-data?trades=true", addr))
+        .await
+        .expect("connect");
+    assert_eq!(next_json(&mut ws).await["type"], "snapshot");
+
+    for (id, side, qty) in [(70, "Sell", "3"), (71, "Buy", "2")] {
+        let order = serde_json::json!({
+            "order_id": id,
+            "client_order_id": format!("c{}", id),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": qty,
+            "price": "100",
+            "time_in_force": "GTC",
+            "timestamp": 5,
+            "trader_id": id
+        });
+        let _ = client.post(format!("http://{}/orders", addr)).json(&order).send().await.unwrap();
+    }
+    let mut messages = Vec::new();
+    for _ in 0..3 {
+        messages.push(next_json(&mut ws).await);
+    }
+    let trade = messages.iter().find(|m| m["type"] == "trade").expect("trade message");
+    assert_eq!(trade["price"], "100");
+    assert_eq!(trade["quantity"], "2");
+    assert_eq!(trade["aggressor_side"], "Buy");
+    assert_eq!(trade["timestamp"], 5);
+    assert_eq!(trade["trade_id"], 1);
+    assert!(trade.get("buy_order_id").is_none(), "order ids stay off the public tape");
+    assert_eq!(messages.iter().filter(|m| m["type"] == "snapshot").count(), 2);
+}
+
+#[tokio::test]
+async fn ws_executions_streams_only_the_key_traders_reports_and_fills() {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let state = api::create_app_state(InstrumentId(1));
+    let auth = dire_matching_engine::AuthConfig::from_keys("t7:trader:7,t8:trader:8,unbound:trader");
+    let app = api::create_router_with_state_and_auth(state, Some(auth));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let connect = |key: &'static str| async move {
+        let mut request = format!("ws://{}/ws/executions", addr).into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Authorization", format!("Bearer {}", key).parse().unwrap());
+        tokio_tungstenite::connect_async(request).await
+    };
+    match connect("unbound").await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => assert_eq!(resp.status(), 403),
+        other => panic!("expected 403, got {:?}", other.map(|_| ())),
+    }
+    let (mut ws, _) = connect("t7").await.expect("connect");
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    let client = reqwest::Client::new();
+    let submit = |key: &'static str, id: u64, side: &str, price: &str, trader: u64| {
+        client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", format!("Bearer {}", key))
+            .json(&serde_json::json!({
+                "order_id": id,
+                "client_order_id": format!("c{}", id),
+                "instrument_id": 1,
+                "side": side,
+                "order_type": "Limit",
+                "quantity": "1",
+                "price": price,
+                "time_in_force": "GTC",
+                "timestamp": id,
+                "trader_id": trader
+            }))
+            .send()
+    };
+    assert_eq!(submit("t8", 1, "Sell", "100", 8).await.unwrap().status(), 200);
+    assert_eq!(submit("t7", 2, "Buy", "100", 7).await.unwrap().status(), 200);
+    assert_eq!(submit("t7", 3, "Buy", "99", 7).await.unwrap().status(), 200);
+    let resp = client
+        .post(format!("http://{}/orders/cancel", addr))
+        .header("Authorization", "Bearer t7")
+        .json(&serde_json::json!({ "order_id": 3 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let mut messages = Vec::new();
+    loop {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(2), next_json(&mut ws))
+            .await
+            .expect("message before timeout");
+        let done = msg["type"] == "canceled";
+        messages.push(msg);
+        if done {
+            break;
+        }
+    }
+    let fill = messages.iter().find(|m| m["type"] == "fill").expect("fill");
+    assert_eq!(fill["order_id"], 2);
+    assert_eq!(fill["side"], "Buy");
+    assert_eq!(fill["aggressor"], true);
+    assert_eq!(fill["quantity"], "1");
+    let report_orders: Vec<u64> = messages
+        .iter()
+        .filter(|m| m["type"] == "execution_report")
+        .map(|m| m["order_id"].as_u64().unwrap())
+        .collect();
+    assert!(report_orders.contains(&2) && report_orders.contains(&3), "{:?}", messages);
+    assert!(report_orders.iter().all(|&id| id != 1), "{:?}", messages);
+    assert_eq!(messages.last().unwrap()["order_id"], 3);
+    let seqs: Vec<u64> = messages.iter().filter_map(|m| m["seq"].as_u64()).collect();
+    assert!(seqs.windows(2).all(|w| w[0] < w[1]), "{:?}", seqs);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn ws_market_data_conflated_deltas_rebuild_the_book_under_bursts() {
+    use rust_decimal::Decimal;
+    use std::collections::BTreeMap;
+
+    let mut state = api::create_app_state(InstrumentId(1));
+    state.slow_consumer = api::SlowConsumerPolicy {
+        max_conflated: None,
+        send_timeout: Some(std::time::Duration::from_secs(5)),
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = api::create_router_with_state(state);
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+
+    let url = format!("ws://{}/ws/market-data?deltas=true", addr);
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.expect("connect");
+    let snapshot = next_json(&mut ws).await;
+    assert_eq!(snapshot["type"], "snapshot");
+    let mut last_seq = snapshot["seq"].as_u64().unwrap();
+
+    // Fire a burst of orders at once; updates queued while the socket is busy are conflated per instrument.
+    let client = reqwest::Client::new();
+    let submits = (0..60u64).map(|i| {
+        let side = if i % 2 == 0 { "Buy" } else { "Sell" };
+        let price = if i % 2 == 0 { 95 + i % 7 } else { 99 + i % 5 };
+        let order = serde_json::json!({
+            "order_id": 100 + i,
+            "client_order_id": format!("b{}", i),
+            "instrument_id": 1,
+            "side": side,
+            "order_type": "Limit",
+            "quantity": (1 + i % 3).to_string(),
+            "price": price.to_string(),
+            "time_in_force": "GTC",
+            "timestamp": 1,
+            "trader_id": i % 4 + 1
+        });
+        client.post(format!("http://{}/orders", addr)).json(&order).send()
+    });
+    for res in futures_util::future::join_all(submits).await {
+        assert!(res.unwrap().status().is_success());
+    }
+    let book: serde_json::Value = client
+        .get(format!("http://{}/book/1?levels=100", addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    // Every delta chains to the previous message and applying them all yields the final book. A client that
+    // fell behind the broadcast buffer gets a snapshot to rebuild from instead.
+    let mut levels: [BTreeMap<Decimal, Decimal>; 2] = Default::default();
+    let decimal = |v: &serde_json::Value| v.as_str().unwrap().parse::<Decimal>().unwrap();
+    while last_seq < book["seq"].as_u64().unwrap() {
+        let msg = next_json(&mut ws).await;
+        let prev_seq = std::mem::replace(&mut last_seq, msg["seq"].as_u64().unwrap());
+        if msg["type"] == "snapshot" {
+            for (side, key) in levels.iter_mut().zip(["bids", "asks"]) {
+                *side = msg[key]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|level| (decimal(&level[0]), decimal(&level[1])))
+                    .collect();
+            }
+            continue;
+        }
+        assert_eq!(msg["type"], "delta");
+        assert_eq!(msg["prev_seq"].as_u64(), Some(prev_seq));
+        for (side, key) in levels.iter_mut().zip(["bids", "asks"]) {
+            for change in msg[key].as_array().unwrap() {
+                let price = decimal(&change["price"]);
+                match change["action"].as_str().unwrap() {
+                    "Removed" => assert!(side.remove(&price).is_some()),
+                    "Added" => assert!(side.insert(price, decimal(&change["quantity"])).is_none()),
+                    _ => assert!(side.insert(price, decimal(&change["quantity"])).is_some()),
+                }
+            }
+        }
+        if last_seq == book["seq"].as_u64().unwrap() {
+            assert_eq!(msg["checksum"], book["checksum"]);
+        }
+    }
+    for (side, key) in levels.iter().zip(["bids", "asks"]) {
+        let expected: BTreeMap<Decimal, Decimal> = book[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|level| (decimal(&level[0]), decimal(&level[1])))
+            .collect();
+        assert_eq!(side, &expected, "{}", key);
+    }
+}
+
+async fn spawn_app_with_heartbeat(heartbeat: api::HeartbeatPolicy) -> SocketAddr {
+    let mut state = api::create_app_state(InstrumentId(1));
+    state.heartbeat = heartbeat;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = api::create_router_with_state(state);
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn ws_heartbeats_ping_clients_and_close_idle_ones() {
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message;
+
+    // A client that keeps reading answers the pings, so it outlives the idle timeout.
+    let addr = spawn_app_with_heartbeat(api::HeartbeatPolicy {
+        interval: Some(Duration::from_millis(50)),
+        idle_timeout: Some(Duration::from_millis(200)),
+    })
+    .await;
+    let (mut live, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/market-data", addr))
+        .await
+        .expect("connect");
+    assert_eq!(next_json(&mut live).await["type"], "snapshot");
+    let mut heartbeats = 0;
+    while heartbeats < 6 {
+        match live.next().await.expect("message").expect("ws recv") {
+            Message::Ping(_) => {}
+            Message::Text(text) => {
+                let msg: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(msg["type"], "heartbeat");
+                assert!(msg["timestamp"].as_u64().unwrap() > 0);
+                heartbeats += 1;
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    // One that sends nothing is closed once the timeout passes.
+    let addr = spawn_app_with_heartbeat(api::HeartbeatPolicy {
+        interval: None,
+        idle_timeout: Some(Duration::from_millis(100)),
+    })
+    .await;
+    let (mut idle, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/executions?trader_id=1", addr))
+        .await
+        .expect("connect");
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    match idle.next().await.expect("message").expect("ws recv") {
+        Message::Close(Some(frame)) => {
+            assert_eq!((frame.code, frame.reason.as_ref()), (CloseCode::Away, "idle timeout"))
+        }
+        other => panic!("expected close, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_market_data_broadcasts_fix_and_admin_book_changes() {
+    use dire_matching_engine::fix::message::FixWriter;
+    use std::io::{Read, Write};
+
+    let state = api::create_app_state(InstrumentId(1));
+    let fix_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let fix_port = fix_listener.local_addr().unwrap().port();
+    let (engine, market_state) = (state.engine.clone(), state.market_state.clone());
+    std::thread::spawn(move || dire_matching_engine::fix::run_fix_acceptor(fix_listener, engine, market_state));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = api::create_router_with_state(state.clone());
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/market-data?deltas=true", addr))
+        .await
+        .expect("connect");
+    assert_eq!(next_json(&mut ws).await["type"], "snapshot");
+
+    // A resting FIX order shows up as a delta, like one sent over REST.
+    tokio::task::spawn_blocking(move || {
+        let fix = |fields: &[(u32, &str)]| {
+            let mut w = FixWriter::new();
+            for (tag, value) in fields {
+                w.set(*tag, *value);
+            }
+            let mut out = Vec::new();
+            w.write(&mut out).unwrap();
+            out
+        };
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", fix_port)).unwrap();
+        stream.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let mut buf = [0u8; 1024];
+        stream
+            .write_all(&fix(&[(35, "A"), (34, "1"), (49, "CLIENT"), (52, "20250101-12:00:00"), (56, "DIRED")]))
+            .unwrap();
+        let _ = stream.read(&mut buf).unwrap();
+        let order = [(35, "D"), (34, "2"), (11, "100"), (55, "1"), (54, "1"), (38, "5"), (40, "2"), (44, "99"), (59, "0")];
+        stream.write_all(&fix(&order)).unwrap();
+        let _ = stream.read(&mut buf).unwrap();
+    })
+    .await
+    .unwrap();
+    let msg = next_json(&mut ws).await;
+    assert_eq!(msg["type"], "delta");
+    assert_eq!(msg["bids"], serde_json::json!([{ "price": "99", "quantity": "5", "action": "Added" }]));
+
+    // So do changes made directly on the engine (e.g. an admin action or another adapter).
+    use dire_matching_engine::MatchingEngine;
+    {
+        let mut engine = state.engine.lock().unwrap();
+        let order_id = engine.book_orders(InstrumentId(1)).unwrap()[0].order_id;
+        engine.cancel_order(order_id);
+    }
+    let msg = next_json(&mut ws).await;
+    assert_eq!(msg["bids"], serde_json::json!([{ "price": "99", "quantity": "0", "action": "Removed" }]));
+}
+
+#[tokio::test]
+async fn ws_sockets_drain_and_close_on_graceful_shutdown() {
+    use std::time::Duration;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let state = api::create_app_state(InstrumentId(1));
+    let auth = dire_matching_engine::AuthConfig::from_keys("a:admin,t7:trader:7");
+    let app = api::create_router_with_state_and_auth(state.clone(), Some(auth));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let connect = |path: &'static str| async move {
+        let mut request = format!("ws://{}{}", addr, path).into_client_request().unwrap();
+        request.headers_mut().insert("Authorization", "Bearer t7".parse().unwrap());
+        tokio_tungstenite::connect_async(request).await.expect("connect").0
+    };
+    let mut market_data = connect("/ws/market-data").await;
+    let mut executions = connect("/ws/executions").await;
+    assert_eq!(next_json(&mut market_data).await["type"], "snapshot");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(state.shutdown.open_sockets(), 2);
+
+    let client = reqwest::Client::new();
+    let shutdown = |key: &'static str| {
+        client
+            .post(format!("http://{}/admin/shutdown", addr))
+            .header("Authorization", format!("Bearer {}", key))
+            .send()
+    };
+    let submit = |id: u64| {
+        client
+            .post(format!("http://{}/orders", addr))
+            .header("Authorization", "Bearer t7")
+            .json(&serde_json::json!({
+                "order_id": id,
+                "client_order_id": format!("c{}", id),
+                "instrument_id": 1,
+                "side": "Buy",
+                "order_type": "Limit",
+                "quantity": "1",
+                "price": "99",
+                "time_in_force": "GTC",
+                "timestamp": id,
+                "trader_id": 7
+            }))
+            .send()
+    };
+    assert_eq!(shutdown("t7").await.unwrap().status(), 403);
+    assert!(!state.shutdown.is_requested());
+    assert_eq!(submit(1).await.unwrap().status(), 200);
+    let resp = shutdown("a").await.unwrap();
+    assert_eq!(resp.status(), 202);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["state"], "Closed");
+
+    // Each socket gets what was queued before the shutdown, then a 1001 close.
+    for (ws, wanted) in [(&mut market_data, "snapshot"), (&mut executions, "execution_report")] {
+        let mut types = Vec::new();
+        let close = loop {
+            let msg = tokio::time::timeout(Duration::from_secs(2), ws.next())
+                .await
+                .expect("message before timeout")
+                .expect("open")
+                .expect("ws recv");
+            match msg {
+                Message::Text(text) => {
+                    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    types.push(json["type"].as_str().unwrap_or_default().to_string());
+                }
+                Message::Close(frame) => break frame.expect("close frame"),
+                _ => {}
+            }
+        };
+        assert_eq!(close.code, CloseCode::Away);
+        assert_eq!(close.reason, "server shutting down");
+        assert!(types.iter().any(|t| t == wanted), "{:?}", types);
+    }
+    assert!(state.shutdown.drained(Duration::from_secs(2)).await);
+
+    let resp = submit(2).await.unwrap();
+    assert_eq!(resp.status(), 503);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], "market_closed");
+    assert_eq!(shutdown("a").await.unwrap().status(), 202);
+}
+
+#[tokio::test]
+async fn ws_market_data_carries_only_entitled_instruments() {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::Message;
+    use futures_util::SinkExt;
+
+    let state = api::create_app_state(InstrumentId(1));
+    state.engine.lock().unwrap().add_instrument(InstrumentId(2), Some("BAR".into())).unwrap();
+    let auth = dire_matching_engine::AuthConfig::from_keys("all:trader,two:trader:7::2");
+    let app = api::create_router_with_state_and_auth(state, Some(auth));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let connect = |key: &'static str, query: &'static str| async move {
+        let mut request = format!("ws://{}/ws/market-data{}", addr, query).into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert("Authorization", format!("Bearer {}", key).parse().unwrap());
+        tokio_tungstenite::connect_async(request).await
+    };
+    match connect("two", "?instrument_ids=1,2").await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => assert_eq!(resp.status(), 403),
+        other => panic!("expected 403, got {:?}", other.map(|_| ())),
+    }
+
+    // Without instrument_ids: every instrument the key is entitled to.
+    let (mut ws, _) = connect("two", "").await.expect("connect");
+    assert_eq!(next_json(&mut ws).await["instrument_id"], 2);
+    ws.send(Message::Text(r#"{"type":"snapshot","instrument_id":1}"#.into())).await.unwrap();
+    let error = next_json(&mut ws).await;
+    assert_eq!((error["type"].as_str(), error["code"].as_str()), (Some("error"), Some("forbidden")));
+
+    // A subscription narrows an unrestricted key too.
+    let (mut ws, _) = connect("all", "?instrument_ids=2").await.expect("connect");
+    assert_eq!(next_json(&mut ws).await["instrument_id"], 2);
+    ws.send(Message::Text(r#"{"type":"snapshot"}"#.into())).await.unwrap();
+    let snapshot = next_json(&mut ws).await;
+    assert_eq!((snapshot["type"].as_str(), snapshot["instrument_id"].as_u64()), (Some("snapshot"), Some(2)));
+}
+
+#[tokio::test]
+async fn ws_sockets_authenticate_by_query_parameter_and_close_when_the_key_is_revoked() {
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let state = api::create_app_state(InstrumentId(1));
+    let auth = dire_matching_engine::AuthConfig::from_keys("adm:admin,t7:trader:7");
+    let app = api::create_router_with_state_and_auth(state, Some(auth));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service()).await.unwrap();
+    });
+    let connect = |path: &'static str| tokio_tungstenite::connect_async(format!("ws://{}{}", addr, path));
+    for path in ["/ws/market-data", "/ws/market-data?api_key=wrong"] {
+        match connect(path).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => assert_eq!(resp.status(), 401),
+            other => panic!("expected 401, got {:?}", other.map(|_| ())),
+        }
+    }
+    let (mut market_data, _) = connect("/ws/market-data?api_key=t7&ticker=true").await.expect("connect");
+    assert_eq!(next_json(&mut market_data).await["type"], "snapshot");
+    assert_eq!(next_json(&mut market_data).await["type"], "ticker");
+    let (mut executions, _) = connect("/ws/executions?api_key=t7").await.expect("connect");
+
+    // The key only works as a query parameter on upgrades.
+    let client = reqwest::Client::new();
+    let resp = client.get(format!("http://{}/ticker?api_key=t7", addr)).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = client
+        .delete(format!("http://{}/admin/keys/t7", addr))
+        .header("Authorization", "Bearer adm")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    for ws in [&mut market_data, &mut executions] {
+        let close = loop {
+            let msg = tokio::time::timeout(std::time::Duration::from_secs(3), ws.next())
+                .await
+                .expect("close before timeout")
+                .expect("message")
+                .expect("ws recv");
+            if let Message::Close(frame) = msg {
+                break frame.expect("close frame");
+            }
+        };
+        assert_eq!((close.code, close.reason.as_ref()), (CloseCode::Policy, "API key revoked"));
+    }
+}